@@ -1,15 +1,35 @@
 use crate::types::*;
 
+mod adapter;
+mod direct;
+mod naming;
+mod object_api;
+mod prelude;
+mod round_trip;
+#[cfg(feature = "serde")]
+mod serde;
+#[cfg(feature = "tonic")]
+mod tonic;
+
 #[cfg(test)]
 use crate::{field, table};
 
 use butte::VOffsetT;
 use heck::{ShoutySnakeCase, SnakeCase};
-use itertools::Itertools;
-use proc_macro2::TokenStream;
+use proc_macro2::{Literal, Span, TokenStream};
 use quote::{format_ident, quote, ToTokens};
-use std::{convert::TryInto, fmt::Display};
-use syn::spanned::Spanned;
+use std::{
+    collections::HashMap,
+    convert::TryInto,
+    fmt::Display,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use syn::{ext::IdentExt, spanned::Spanned};
+
+pub use naming::Naming;
+use naming::{field_ident, TypeNames};
+pub use object_api::EmptyCollections;
 
 #[cfg(test)]
 fn to_code(value: impl ToTokens) -> String {
@@ -53,9 +73,33 @@ mod constant_tests {
     }
 }
 
+/// Rust's keywords, including those reserved for future use, which schema
+/// names like `type` or `match` have to be escaped from.
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// The Rust identifier for the name `name` from a schema.
+///
+/// Keywords are written as raw identifiers, e.g. `r#type`, except for those
+/// that can't be raw identifiers, like `self`, which get a trailing
+/// underscore instead.
+fn ident(name: impl AsRef<str>) -> proc_macro2::Ident {
+    let name = name.as_ref();
+    match name {
+        "crate" | "self" | "Self" | "super" | "_" => format_ident!("{}_", name),
+        name if KEYWORDS.contains(&name) => proc_macro2::Ident::new_raw(name, Span::call_site()),
+        name => format_ident!("{}", name),
+    }
+}
+
 impl ToTokens for Ident<'_> {
     fn to_tokens(&self, tokens: &mut TokenStream) {
-        format_ident!("{}", self.raw).to_tokens(tokens)
+        ident(self.raw).to_tokens(tokens)
     }
 }
 
@@ -69,598 +113,4939 @@ mod ident_tests {
         let expected = "foo";
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn test_keywords() {
+        assert_eq!(to_code(Ident::from("type")), "r#type");
+        assert_eq!(to_code(Ident::from("match")), "r#match");
+        assert_eq!(to_code(Ident::from("self")), "self_");
+        assert_eq!(to_code(Ident::from("Self")), "Self_");
+        assert_eq!(to_code(Ident::from("types")), "types");
+    }
+
+    #[test]
+    fn test_keyword_names() {
+        let schema = crate::parser::parse_schema(
+            "namespace ref.mod;
+             enum Kind : ubyte { struct, loop }
+             struct Pair { type: int; self: int; }
+             table Node { type: Kind; match: string; ref: Pair; }",
+        )
+        .unwrap();
+        let result = generate(&schema, &Options::default()).to_string();
+        assert!(result.contains("pub mod r#ref { # [allow"));
+        assert!(result.contains("pub mod r#mod {"));
+        assert!(result.contains("pub enum Kind { r#struct = 0_u8 , r#loop = 1_u8 }"));
+        assert!(result.contains("pub fn r#type (& self) -> i32"));
+        assert!(result.contains("pub fn self_ (& self) -> i32"));
+        assert!(result.contains("pub fn r#type (& self) -> butte :: Result < Kind >"));
+        assert!(
+            result.contains("pub fn r#match (& self) -> butte :: Result < Option < & 'a str >>")
+        );
+        assert!(result.contains("pub r#ref : Option < & 'a Pair >"));
+        assert!(result.contains("ds . field (\"ref\" , & value)"));
+        assert!(result.contains("Kind :: r#struct => \"struct\""));
+    }
 }
 
 impl ToTokens for Scalar {
     fn to_tokens(&self, tokens: &mut TokenStream) {
         match self {
-            Scalar::Integer(i) => i.to_tokens(tokens),
-            Scalar::Float(f) => f.to_tokens(tokens),
+            // Unsuffixed, as an `i128` suffix would fit no field's type.
+            Scalar::Integer(i) => Literal::i128_unsuffixed(*i).to_tokens(tokens),
+            Scalar::Float(f) if f.is_finite() => f.to_tokens(tokens),
+            Scalar::Float(f) => float_literal(*f, &Type::Double).to_tokens(tokens),
             Scalar::Boolean(b) => b.to_tokens(tokens),
         }
     }
 }
 
-/// Convert a `types::Type` to a type with the supplied wrapper for reference types
-fn to_type(ty: &Type, lifetime: impl ToTokens, wrap_refs_types: impl ToTokens) -> impl ToTokens {
-    match ty {
-        Type::String => {
-            let wrap_tokens = wrap_refs_types.into_token_stream();
-            if wrap_tokens.is_empty() {
-                quote!(&#lifetime str)
-            } else {
-                quote!(#wrap_tokens::<&#lifetime str>)
-            }
-        }
-        // TODO other reference types?
-        _ => quote!(#ty),
-    }
+/// A user-defined type declared somewhere in a schema.
+#[derive(Debug, Clone)]
+pub(crate) enum Declaration<'a> {
+    Table,
+    Struct(Struct<'a>),
+    Enum(Enum<'a>),
+    Union(Union<'a>),
 }
 
-fn offset_id(field: &Field) -> impl ToTokens {
-    format_ident!("VT_{}", field.id.as_ref().to_shouty_snake_case())
+/// Maps the fully qualified name of every user-defined type in a schema to
+/// its declaration.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SymbolTable<'a> {
+    declarations: HashMap<Vec<&'a str>, Declaration<'a>>,
+    /// The file identifier of the schema, which applies to all of its
+    /// namespaces.
+    file_identifier: Option<String>,
+    /// The attributes declared by the schema with `attribute "name";`.
+    attributes: Vec<&'a str>,
 }
 
-impl ToTokens for Table<'_> {
-    fn to_tokens(&self, tokens: &mut TokenStream) {
-        let Self {
-            id: struct_id,
-            fields,
-            doc,
-            ..
-        } = self;
+impl<'a> SymbolTable<'a> {
+    pub(crate) fn new(elements: &[Element<'a>]) -> Self {
+        let mut namespace = vec![];
+        let mut declarations = HashMap::new();
+        let mut file_identifier = None;
+        let mut attributes = vec![];
+        for element in elements {
+            let (id, declaration) = match element {
+                Element::Namespace(Namespace { ident, .. }) => {
+                    namespace = ident.parts.iter().map(|part| part.raw).collect();
+                    continue;
+                }
+                Element::FileIdentifier(FileIdentifier { id, .. }) => {
+                    file_identifier = Some(id.iter().collect());
+                    continue;
+                }
+                Element::Attribute(Attribute { attr, .. }) => {
+                    attributes.push(attr.raw);
+                    continue;
+                }
+                Element::Table(t) => (t.id, Declaration::Table),
+                Element::Struct(s) => (s.id, Declaration::Struct(s.clone())),
+                Element::Enum(e) => (e.id, Declaration::Enum(e.clone())),
+                Element::Union(u) => (u.id, Declaration::Union(u.clone())),
+                _ => continue,
+            };
+            let mut name = namespace.clone();
+            name.push(id.raw);
+            declarations.insert(name, declaration);
+        }
+        Self {
+            declarations,
+            file_identifier,
+            attributes,
+        }
+    }
 
-        let raw_struct_name = struct_id.raw;
+    /// The attributes declared by the schema, in the order they are declared.
+    pub(crate) fn attributes(&self) -> &[&'a str] {
+        &self.attributes
+    }
 
-        let builder_add_calls = fields.iter().map(|Field { id: field_id, .. }| {
-            let raw_field_name = field_id.raw;
-            let add_field_method = format_ident!("add_{}", raw_field_name);
-            quote!(builder.#add_field_method(args.#field_id);)
-        });
+    /// Resolve a type reference made from within `namespace`.
+    ///
+    /// Like flatc, the reference is first looked up relative to `namespace`
+    /// and then relative to each of its enclosing namespaces in turn.
+    pub(crate) fn resolve(
+        &self,
+        namespace: &[&'a str],
+        id: &DottedIdent<'a>,
+    ) -> Option<(&[&'a str], &Declaration<'a>)> {
+        (0..=namespace.len()).rev().find_map(|depth| {
+            let mut name = namespace[..depth].to_vec();
+            name.extend(id.parts.iter().map(|part| part.raw));
+            self.declarations
+                .get_key_value(&name)
+                .map(|(name, declaration)| (name.as_slice(), declaration))
+        })
+    }
 
-        let args = format_ident!("{}Args", raw_struct_name);
-        let args_fields = fields.iter().map(
-            |Field {
-                 id: field_id,
-                 ty,
-                 scalar,
-                 ..
-             }| {
-                let arg_ty = to_type(ty, quote!('a), quote!(butte::WIPOffset));
-                // Scalar fields can have a default value
-                let default = if let Some(default_value) = scalar {
-                    quote!(#[default = #default_value])
-                } else {
-                    quote!()
-                };
-                quote! {
-                    #default
-                    pub #field_id: #arg_ty
+    /// The size and alignment in bytes of a type stored inline in a struct.
+    fn inline_size_and_align(&self, namespace: &[&'a str], ty: &Type<'a>) -> (usize, usize) {
+        if let Some(size) = ty.scalar_size() {
+            return (size, size);
+        }
+        match ty {
+            Type::Ident(id) => match self.resolve(namespace, id) {
+                Some((_, Declaration::Enum(e))) => {
+                    self.inline_size_and_align(namespace, &e.base_type)
                 }
-            },
-        );
-
-        let builder_type = format_ident!("{}Builder", struct_id.raw);
-
-        // TODO: field accessors
-        // TODO: check the impl of offset generation
-        // TODO: unions
-        // TODO: testing this is going to be fun
-        let field_nested_flatbuffers = fields.iter().filter_map(|Field { id: field_id, metadata, .. }| {
-            let method_name = format_ident!("{}_nested_flatbuffer", field_id.raw);
-            if let Some(metadata) = metadata {
-                if metadata.values.contains_key(&Ident::from("nested_flatbuffer")) {
-                    Some(quote! {
-                        pub fn #method_name(&self) -> Option<Self> {
-                            self.#field_id.map(|data| <butte::ForwardsUOffset<Self>>::follow(data, 0))
-                        }
-                    })
-                } else {
-                    None
+                Some((name, Declaration::Struct(s))) => {
+                    let layout = self.struct_layout(&name[..name.len() - 1], s);
+                    (layout.size, layout.align)
                 }
-            } else {
-                None
+                _ => panic!("struct fields must be scalars, enums or structs: {:?}", id),
+            },
+            Type::FixedArray(element, len) => {
+                let (size, align) = self.inline_size_and_align(namespace, element);
+                (size * *len as usize, align)
             }
-        });
+            _ => panic!("struct fields must be scalars, enums or structs: {:?}", ty),
+        }
+    }
 
-        let builder_field_methods = fields.iter().map(|field| {
-            let Field {
-                id: field_id,
-                ty,
-                scalar,
-                ..
-            } = field;
-            let add_method_name = format_ident!("add_{}", field_id.raw);
-            let offset = offset_id(&field);
-            let field_offset = quote!(#struct_id::#offset);
-            let arg_ty = to_type(ty, quote!('_), quote!(butte::WIPOffset));
-            let body = if ty.is_scalar() {
-                if let Some(default_value) = scalar {
-                    quote!(self.fbb.push_slot<#arg_ty>(#field_offset, #field_id, #default_value))
-                } else {
-                    quote!(self.fbb.push_slot_always::<#arg_ty>(#field_offset, #field_id))
-                }
-            } else {
-                quote!(self.fbb.push_slot_always::<#arg_ty>(#field_offset, #field_id))
+    /// Compute the layout of a struct declared in `namespace`.
+    ///
+    /// FlatBuffers structs follow C layout rules: every field is aligned to its
+    /// own alignment and the struct is padded to a multiple of its largest
+    /// field alignment, or of its `force_align` attribute if that is larger.
+    pub(crate) fn struct_layout(&self, namespace: &[&'a str], s: &Struct<'a>) -> StructLayout {
+        let mut offsets = Vec::with_capacity(s.fields.len());
+        let mut sizes = Vec::with_capacity(s.fields.len());
+        let mut size = 0;
+        let mut align = 1;
+        for field in &s.fields {
+            let (field_size, field_align) = self.inline_size_and_align(namespace, &field.ty);
+            size += padding_bytes(size, field_align);
+            offsets.push(size);
+            sizes.push(field_size);
+            size += field_size;
+            align = align.max(field_align);
+        }
+        align = align.max(s.force_align().unwrap_or(1));
+        size += padding_bytes(size, align);
+        StructLayout {
+            offsets,
+            sizes,
+            size,
+            align,
+        }
+    }
+
+    /// The offsets of the bytes holding bools in a struct declared in
+    /// `namespace`, including those of the structs and arrays it contains.
+    pub(crate) fn bool_offsets(&self, namespace: &[&'a str], s: &Struct<'a>) -> Vec<usize> {
+        let layout = self.struct_layout(namespace, s);
+        let mut offsets = vec![];
+        for (field, offset) in s.fields.iter().zip(&layout.offsets) {
+            let (element, len) = match &field.ty {
+                Type::FixedArray(element, len) => (element.as_ref(), *len as usize),
+                ty => (ty, 1),
             };
-            let arg_ty = to_type(ty, quote!('b), quote!(butte::WIPOffset));
-            quote! {
-                #[inline]
-                fn #add_method_name(&mut self, #field_id: #arg_ty) {
-                    #body;
-                }
+            let inner = match element {
+                Type::Bool => vec![0],
+                Type::Ident(id) => match self.resolve(namespace, id) {
+                    Some((name, Declaration::Struct(inner))) => {
+                        self.bool_offsets(&name[..name.len() - 1], inner)
+                    }
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            let (size, _) = self.inline_size_and_align(namespace, element);
+            for index in 0..len {
+                offsets.extend(inner.iter().map(|inner| offset + size * index + inner));
             }
-        });
+        }
+        offsets
+    }
+}
 
-        let field_offset_constants = fields.iter().enumerate().map(|(index, field)| {
-            let offset_name = offset_id(&field);
-            let offset_value = butte::field_index_to_field_offset(index as VOffsetT);
-            quote! {
-                pub const #offset_name: butte::VOffsetT = #offset_value;
-            }
-        });
+/// The layout of a struct: the byte offset and size of every field, plus the
+/// size and alignment of the struct as a whole.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct StructLayout {
+    pub(crate) offsets: Vec<usize>,
+    pub(crate) sizes: Vec<usize>,
+    pub(crate) size: usize,
+    pub(crate) align: usize,
+}
 
-        let field_accessors = fields.iter().map(|field| {
-            let snake_name = format_ident!("{}", field.id.as_ref().to_snake_case());
-            let offset_name = offset_id(&field);
-            let ty = &field.ty;
-            let ty_simple_lifetime = to_type(ty, quote!('a), quote!());
-            let ty_wrapped = to_type(ty, quote!(), quote!(butte::ForwardsUOffset));
+/// The number of bytes needed to pad `offset` to a multiple of `align`.
+fn padding_bytes(offset: usize, align: usize) -> usize {
+    (align - offset % align) % align
+}
 
-            quote! {
-                #[inline]
-                pub fn #snake_name(&self) -> Option<#ty_simple_lifetime> {
-                    self.table
-                        .get::<#ty_wrapped>(#struct_id::#offset_name, None)
-                }
-            }
-        });
+#[cfg(test)]
+mod symbol_table_tests {
+    use super::*;
+    use crate::namespace;
 
-        let struct_offset_enum_name = format_ident!("{}Offset", struct_id.raw);
+    #[test]
+    fn test_resolve_searches_enclosing_namespaces() {
+        let elements = vec![
+            Element::from(namespace!(a)),
+            Element::from(table!(Outer, [field!(x, Int)])),
+            Element::from(namespace!(a::b)),
+            Element::from(table!(Inner, [field!(x, Int)])),
+        ];
+        let symbols = SymbolTable::new(&elements);
 
-        let required_fields = fields.iter().map(|field| {
-            let snake_name = field.id.as_ref().to_snake_case();
-            let offset_name = offset_id(field);
-            quote! {
-                self.fbb.required(o, #struct_id::#offset_name, #snake_name);
-            }
-        });
+        let (name, _) = symbols
+            .resolve(&["a", "b"], &vec!["Outer".into()].into())
+            .unwrap();
+        assert_eq!(name, &["a", "Outer"]);
 
-        (quote! {
-            pub enum #struct_offset_enum_name {}
+        let (name, _) = symbols
+            .resolve(&["a"], &vec!["b".into(), "Inner".into()].into())
+            .unwrap();
+        assert_eq!(name, &["a", "b", "Inner"]);
 
-            #[derive(Copy, Clone, Debug, PartialEq)]
-            #doc
-            pub struct #struct_id<'a> {
-                table: butte::Table<'a>,
-            }
+        assert!(symbols.resolve(&[], &vec!["Inner".into()].into()).is_none());
+    }
 
-            impl<'a> From<butte::Table<'a>> for #struct_id<'a> {
-                fn from(table: butte::Table<'a>) -> Self {
-                    Self { table }
-                }
+    #[test]
+    fn test_struct_layout() {
+        let s = Struct::builder()
+            .id(Ident::from("Padded"))
+            .fields(vec![field!(a, UByte), field!(b, Double), field!(c, Short)])
+            .build();
+        let layout = SymbolTable::default().struct_layout(&[], &s);
+        assert_eq!(
+            layout,
+            StructLayout {
+                offsets: vec![0, 8, 16],
+                sizes: vec![1, 8, 2],
+                size: 24,
+                align: 8,
             }
+        );
+    }
 
-            impl<'a> #struct_id<'a> {
-                pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
-                    fbb: &'mut_bldr mut butte::FlatBufferBuilder<'bldr>,
-                    args: &'args #args<'args>
-                ) -> butte::WIPOffset<#struct_id<'bldr>> {
-                    let mut builder = #builder_type::new(fbb);
-                    #(#builder_add_calls)*
-                    builder.finish()
-                }
+    #[test]
+    fn test_struct_layout_force_align() {
+        let elements = crate::parser::parse_schema(
+            "struct Vec3 (force_align: 16) { x: float; y: float; z: float; }
+             struct Ray { flag: bool; origin: Vec3; }",
+        )
+        .unwrap()
+        .elements;
+        let symbols = SymbolTable::new(&elements);
+        let layouts = elements
+            .iter()
+            .map(|element| match element {
+                Element::Struct(s) => symbols.struct_layout(&[], s),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!((layouts[0].size, layouts[0].align), (16, 16));
+        assert_eq!(layouts[1].offsets, vec![0, 16]);
+        assert_eq!((layouts[1].size, layouts[1].align), (32, 16));
+    }
 
-                // field offset constants
-                #(#field_offset_constants)*
+    #[test]
+    fn test_bool_offsets() {
+        let elements = crate::parser::parse_schema(
+            "struct Inner { a: byte; b: bool; }
+             struct Outer { x: int; flags: [bool:2]; inners: [Inner:2]; }",
+        )
+        .unwrap()
+        .elements;
+        let symbols = SymbolTable::new(&elements);
+        let offsets = elements
+            .iter()
+            .map(|element| match element {
+                Element::Struct(s) => symbols.bool_offsets(&[], s),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(offsets, vec![vec![1], vec![4, 5, 7, 9]]);
+    }
+}
 
-                // fields access
-                #(#field_accessors)*
+/// Settings that affect the generated code.
+#[derive(Clone, Default)]
+pub struct Options {
+    /// Extra traits to derive for generated structs and enums, in addition to
+    /// the ones that are always derived. `Hash` is implemented by hand for
+    /// structs, so that it agrees with their `PartialEq`.
+    pub derives: Vec<syn::Path>,
 
-                // nested flatbuffers if applicable
-                #(#field_nested_flatbuffers)*
-            }
+    /// Generate an owned object type for every table and union, along with
+    /// methods to convert between readers and objects, see `object_api`.
+    pub object_api: bool,
 
-            impl<'a> butte::Follow<'a> for #struct_id<'a> {
-                type Inner = Self;
+    /// Extra traits to derive for the object types of tables and unions, in
+    /// addition to `Debug`, `Clone` and `PartialEq`.
+    pub object_derives: Vec<syn::Path>,
 
-                #[inline]
-                fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
-                    let table = butte::Table { buf, loc };
-                    Self { table }
-                }
-            }
+    /// Generate a `#[cfg(test)]` round-trip test for every table, which
+    /// builds it from values generated by `proptest` and reads them back.
+    pub round_trip_tests: bool,
 
-            // Builder Args
-            // TODO: Can't use this because we can mix fields that are
-            // default-able with those that are not
-            pub struct #args<'a> {
-                #(#args_fields),*
-            }
+    /// Hooks that map the attributes of RPC methods to generated code, in
+    /// the order they are applied.
+    pub rpc_method_hooks: Vec<Arc<dyn RpcMethodHook>>,
 
-            //// builder
-            pub struct #builder_type<'a, 'b> {
-                fbb: &'b mut butte::FlatBufferBuilder<'a>,
-                start: butte::WIPOffset<butte::TableUnfinishedWIPOffset>,
-            }
+    /// Plugins that append code of their own to the code of schema items,
+    /// in the order they are applied.
+    pub plugins: Vec<Arc<dyn CodegenPlugin>>,
 
-            impl<'a: 'b, 'b> #builder_type<'a, 'b> {
-                #(#builder_field_methods)*
+    /// How types and fields are named, see [`Naming`].
+    pub naming: Naming,
 
-                #[inline]
-                pub fn new(fbb: &'b mut butte::FlatBufferBuilder<'a>) -> Self {
-                    let start = fbb.start_table();
-                    #builder_type {
-                        fbb, start
-                    }
-                }
+    /// How the object API and `create_direct` write empty strings and
+    /// vectors, see [`EmptyCollections`].
+    pub empty_collections: EmptyCollections,
 
-                #[inline]
-                pub fn finish(self) -> butte::WIPOffset<#struct_id<'a>> {
-                    let o = self.fbb.end_table(self.start);
-                    #(#required_fields)*
-                    butte::WIPOffset::new(o.value())
-                }
-            }
-        })
-        .to_tokens(tokens)
-    }
-}
+    /// The root type of the schema, a name like `my.ns.Monster`, overriding
+    /// the root types declared by its files, see
+    /// [`SchemaFiles::parse_with_root`](crate::include::SchemaFiles::parse_with_root).
+    pub root_type: Option<String>,
 
-#[cfg(test)]
-mod product_type_tests {
-    use super::*;
+    /// The visibility of generated items, see [`Visibility`].
+    pub visibility: Visibility,
 
-    #[test]
-    fn test_visit_product_type_table() {
-        let table = table!(
-            MyMessage,
-            [field!(message, String), field!(foo, Float64 = 2.0)]
-        );
-        let result = to_code(table);
-        assert!(!result.is_empty());
-    }
+    /// Generate a `prelude` module re-exporting the readers of the types
+    /// reachable from the root type, or of all types if there is none.
+    pub prelude: bool,
+
+    /// The module to put all of the generated code in, which otherwise
+    /// starts with the items of the root namespace.
+    pub module: Option<proc_macro2::Ident>,
 }
 
-impl ToTokens for Type<'_> {
+/// The visibility of the items generated at the level of a namespace's module,
+/// and of the modules themselves. The fields and methods of generated types
+/// are always `pub`, so they are as visible as their types.
+#[derive(Clone, Default)]
+pub enum Visibility {
+    /// `pub`, for schemas that are part of a crate's API.
+    #[default]
+    Public,
+    /// `pub(crate)`, for schemas that are an implementation detail of a crate.
+    Crate,
+    /// `pub(in path)`, where `path` names a module of the crate, starting
+    /// with `crate`.
+    Restricted(syn::Path),
+}
+
+impl ToTokens for Visibility {
     fn to_tokens(&self, tokens: &mut TokenStream) {
         match self {
-            Type::Bool => quote!(bool),
-            Type::Byte => quote!(i8),
-            Type::UByte => quote!(u8),
-            Type::Short => quote!(i16),
-            Type::UShort => quote!(u16),
-            Type::Int => quote!(i32),
-            Type::UInt => quote!(u32),
-            Type::Float => quote!(f32),
-            Type::Long => quote!(i64),
-            Type::ULong => quote!(u64),
-            Type::Double => quote!(f64),
-            Type::Int8 => quote!(i8),
-            Type::UInt8 => quote!(u8),
-            Type::Int16 => quote!(i16),
-            Type::UInt16 => quote!(u16),
-            Type::Int32 => quote!(i32),
-            Type::UInt32 => quote!(u32),
-            Type::Int64 => quote!(i64),
-            Type::UInt64 => quote!(u64),
-            Type::Float32 => quote!(f32),
-            Type::Float64 => quote!(f64),
-            Type::String => quote!(String),
-            Type::Array(ty) => quote!(Vec<#ty>),
-            Type::Ident(id) => quote!(#id),
+            Visibility::Public => quote!(pub),
+            Visibility::Crate => quote!(pub(crate)),
+            Visibility::Restricted(path) => quote!(pub(in #path)),
         }
         .to_tokens(tokens)
     }
 }
 
-#[cfg(test)]
-mod type_tests {
-    use super::*;
+/// An extension of the code generator, which appends code of its own to the
+/// code generated for each item of a schema, e.g. conversions from tables to
+/// the domain types of a crate.
+///
+/// The code of a plugin is put right after the code of the item, in the
+/// module of its namespace, so it can refer to the item's generated types by
+/// name. The types the item refers to are resolved with
+/// [`ItemContext::type_path`].
+///
+/// # Examples
+///
+/// ```
+/// use butte_build::{codegen::{CodegenPlugin, ItemContext}, types::Table};
+/// use proc_macro2::TokenStream;
+/// use quote::{format_ident, quote};
+///
+/// /// Give every table a `FIELDS` constant listing the names of its fields.
+/// struct FieldNames;
+///
+/// impl CodegenPlugin for FieldNames {
+///     fn table<'a>(&self, table: &Table<'a>, _context: &ItemContext<'a, '_>) -> TokenStream {
+///         let id = format_ident!("{}", table.id.raw);
+///         let names = table.fields.iter().map(|field| field.id.raw);
+///         quote! {
+///             impl #id<'_> {
+///                 pub const FIELDS: &'static [&'static str] = &[#(#names),*];
+///             }
+///         }
+///     }
+/// }
+/// ```
+pub trait CodegenPlugin {
+    /// Code to append to that of `table`.
+    fn table<'a>(&self, table: &Table<'a>, context: &ItemContext<'a, '_>) -> TokenStream {
+        let _ = (table, context);
+        quote!()
+    }
 
-    #[test]
-    fn test_visit_type() {
-        let result = to_code(Type::Bool);
-        let expected = "bool";
-        assert_eq!(result, expected);
+    /// Code to append to that of `struct_`.
+    fn struct_<'a>(&self, struct_: &Struct<'a>, context: &ItemContext<'a, '_>) -> TokenStream {
+        let _ = (struct_, context);
+        quote!()
+    }
 
-        let result = to_code(Type::Array(Box::new(Type::String)));
-        let expected = "Vec < String >";
-        assert_eq!(result, expected);
+    /// Code to append to that of `enum_`.
+    fn enum_<'a>(&self, enum_: &Enum<'a>, context: &ItemContext<'a, '_>) -> TokenStream {
+        let _ = (enum_, context);
+        quote!()
+    }
 
-        let result = to_code(Type::Ident(DottedIdent::from(vec!["MyType".into()])));
-        let expected = "MyType";
-        assert_eq!(result, expected);
+    /// Code to append to that of `union`.
+    fn union<'a>(&self, union: &Union<'a>, context: &ItemContext<'a, '_>) -> TokenStream {
+        let _ = (union, context);
+        quote!()
     }
-}
 
-// TODO: Properly implement this.
-// We only generate a trait method right now.
-// TODO: Figure out how this will integrate into tonic.
-impl ToTokens for RpcMethod<'_> {
-    fn to_tokens(&self, tokens: &mut TokenStream) {
-        let Self {
-            id,
-            request_type,
-            response_type,
-            doc,
-            ..
-        } = self;
-        let snake_name = format_ident!("{}", id.raw.to_snake_case());
-        (quote! {
-            #doc
-            fn #snake_name(request: #request_type) -> #response_type;
-        })
-        .to_tokens(tokens)
+    /// Code to append to that of `rpc`.
+    fn rpc<'a>(&self, rpc: &Rpc<'a>, context: &ItemContext<'a, '_>) -> TokenStream {
+        let _ = (rpc, context);
+        quote!()
     }
 }
 
-impl ToTokens for Comment<'_> {
-    fn to_tokens(&self, tokens: &mut TokenStream) {
-        let doc = self.lines.iter().rev().fold(quote!(), |docs, line| {
-            quote! {
-                #[doc = #line]
-                #docs
-            }
-        });
-        doc.to_tokens(tokens)
-    }
+/// Where the item passed to a [`CodegenPlugin`] is generated.
+#[derive(Clone, Copy)]
+pub struct ItemContext<'a, 'b> {
+    scope: Scope<'a, 'b>,
 }
 
-impl ToTokens for DottedIdent<'_> {
-    fn to_tokens(&self, tokens: &mut TokenStream) {
-        let parts = &self.parts;
-        debug_assert!(!self.parts.is_empty());
-        let code = parts.iter().map(|e| e.raw).join("::");
-        let num_parts = parts.len();
-        let path_string = if num_parts > 1 {
-            format!(
-                "{}::{}",
-                std::iter::repeat("super").take(num_parts - 1).join("::"),
-                code
-            )
-        } else {
-            code
-        };
-        syn::parse_str::<syn::Path>(&path_string)
-            .expect("Cannot parse path")
-            .to_tokens(tokens)
+impl<'a> ItemContext<'a, '_> {
+    /// The namespace of the item, e.g. `["my_game", "sample"]`.
+    pub fn namespace(&self) -> &[&'a str] {
+        self.scope.namespace
     }
-}
 
-// TODO: This is woefully incomplete
-impl ToTokens for Rpc<'_> {
+    /// The path to the Rust type generated for `ty`, relative to the module
+    /// of the item, e.g. `super::weapons::Sword` for `weapons.Sword`. For a
+    /// table, the path is that of its reader, and for a union, that of the
+    /// enum of its readers.
+    pub fn type_path(&self, ty: &Type<'a>) -> TokenStream {
+        self.scope.type_path(ty)
+    }
+
+    /// The attributes declared by the schema, e.g. `priority` for
+    /// `attribute "priority";`, in the order they are declared. Their uses
+    /// are found with [`Metadata::custom_attributes`].
+    pub fn declared_attributes(&self) -> &[&'a str] {
+        self.scope.symbols.attributes()
+    }
+}
+
+/// A hook that maps the attributes of RPC methods, including custom ones,
+/// to generated code, e.g. to trace the calls of some methods.
+///
+/// # Examples
+///
+/// ```
+/// use butte_build::{codegen::RpcMethodHook, types::{Rpc, RpcMethod}};
+/// use proc_macro2::TokenStream;
+/// use quote::quote;
+///
+/// /// Handle the requests to methods with a `traced` attribute in a span.
+/// struct Traced;
+///
+/// impl RpcMethodHook for Traced {
+///     fn server_call(&self, rpc: &Rpc, method: &RpcMethod, call: TokenStream) -> TokenStream {
+///         if !method.has_attribute("traced") {
+///             return call;
+///         }
+///         let name = format!("{}.{}", rpc.id.raw, method.id.raw);
+///         quote!(tracing::Instrument::instrument(#call, tracing::info_span!(#name)))
+///     }
+/// }
+/// ```
+pub trait RpcMethodHook {
+    /// Attributes to put on the methods generated for `method` of `rpc`:
+    /// the method of the service trait and, with the `tonic` feature, those
+    /// of the gRPC service trait and client.
+    fn attributes(&self, rpc: &Rpc, method: &RpcMethod) -> TokenStream {
+        let _ = (rpc, method);
+        quote!()
+    }
+
+    /// Wrap `call`, the future that handles a request to `method` of `rpc`
+    /// in the gRPC server of the `tonic` feature.
+    fn server_call(&self, rpc: &Rpc, method: &RpcMethod, call: TokenStream) -> TokenStream {
+        let _ = (rpc, method);
+        call
+    }
+}
+
+/// The derive attribute of a generated type: the traits that are always
+/// derived for it, followed by the `extra` ones that aren't among them.
+fn derives(builtin: &[&str], extra: &[syn::Path]) -> TokenStream {
+    let builtin_paths = builtin
+        .iter()
+        .map(|name| syn::Path::from(format_ident!("{}", name)));
+    let extra = extra.iter().filter(|path| {
+        !builtin
+            .iter()
+            .any(|name| path.get_ident().is_some_and(|ident| ident == name))
+    });
+    let paths = builtin_paths.chain(extra.cloned());
+    quote! {
+        #[derive(#(#paths),*)]
+    }
+}
+
+/// The attributes of the module of a namespace, which keep lints that
+/// downstream crates enable, like clippy's pedantic ones, from firing on
+/// generated code.
+fn module_attrs() -> TokenStream {
+    quote! {
+        #[allow(clippy::all, clippy::pedantic, clippy::nursery)]
+    }
+}
+
+/// Whether `path` names the trait `name`, e.g. `Hash` or `core::hash::Hash`.
+fn is_trait(path: &syn::Path, name: &str) -> bool {
+    path.segments
+        .last()
+        .is_some_and(|segment| segment.ident == name)
+}
+
+/// The namespace an item is generated in along with the symbol table used to
+/// resolve the types it references.
+#[derive(Clone, Copy)]
+struct Scope<'a, 'b> {
+    symbols: &'b SymbolTable<'a>,
+    namespace: &'b [&'a str],
+    options: &'b Options,
+}
+
+impl<'a> Scope<'a, '_> {
+    fn resolve(&self, id: &DottedIdent<'a>) -> Option<&Declaration<'a>> {
+        self.symbols
+            .resolve(self.namespace, id)
+            .map(|(_, declaration)| declaration)
+    }
+
+    /// Check whether `ty` refers to a struct.
+    fn is_struct(&self, ty: &Type<'a>) -> bool {
+        match ty {
+            Type::Ident(id) => matches!(self.resolve(id), Some(Declaration::Struct(_))),
+            _ => false,
+        }
+    }
+
+    /// The size in bytes of the slot of a table field.
+    ///
+    /// Scalars, enums and structs are stored inline, everything else is
+    /// referred to by an offset.
+    fn slot_size(&self, field: &Field<'a>) -> usize {
+        if field.is_offset64() {
+            return butte::SIZE_UOFFSET64;
+        }
+        match &field.ty {
+            Type::String | Type::Array(_) => butte::SIZE_UOFFSET,
+            Type::Ident(id) => match self.resolve(id) {
+                Some(Declaration::Enum(_)) | Some(Declaration::Struct(_)) => {
+                    self.symbols
+                        .inline_size_and_align(self.namespace, &field.ty)
+                        .0
+                }
+                _ => butte::SIZE_UOFFSET,
+            },
+            ty => ty.scalar_size().expect("scalar type"),
+        }
+    }
+
+    /// Check whether `ty` is a scalar or refers to an enum.
+    fn is_scalar(&self, ty: &Type<'a>) -> bool {
+        match ty {
+            Type::Ident(id) => matches!(self.resolve(id), Some(Declaration::Enum(_))),
+            ty => ty.is_scalar(),
+        }
+    }
+
+    /// Check whether `field` is optional in the `Args` of its table.
+    ///
+    /// Scalars are always written, all other fields can be left out unless
+    /// they are required.
+    fn is_optional_arg(&self, field: &Field<'a>) -> bool {
+        match field.default {
+            Some(DefaultValue::Null) => true,
+            _ => !self.is_scalar(&field.ty) && !field.is_required(),
+        }
+    }
+
+    /// The value a scalar or enum field has when it is missing from a table,
+    /// unless it's optional. Like in flatc, a scalar without a default value
+    /// defaults to zero.
+    fn default_value(&self, field: &Field<'a>) -> Option<TokenStream> {
+        let ty = &field.ty;
+        let default = match &field.default {
+            Some(default) => default,
+            None if self.is_scalar(ty) => return Some(self.zero_value(ty)),
+            None => return None,
+        };
+        let e = match ty {
+            Type::Ident(id) => match self.resolve(id) {
+                Some(Declaration::Enum(e)) => Some(e),
+                _ => None,
+            },
+            _ => None,
+        };
+        let variant = match (default, e) {
+            (DefaultValue::Null, _) => return None,
+            (DefaultValue::Scalar(value), None) if ty.is_scalar() => {
+                return Some(default_literal(value, ty))
+            }
+            (DefaultValue::EnumVal(variant), Some(e)) => e
+                .values
+                .iter()
+                .map(|value| value.id)
+                .find(|id| id == variant),
+            (DefaultValue::Scalar(Scalar::Integer(value)), Some(e)) if e.is_bit_flags() => {
+                // Any combination of flags is a valid default.
+                let path = self.type_path(ty);
+                let bits = Literal::i128_unsuffixed(*value);
+                return Some(quote!(#path::from_bits_truncate(#bits)));
+            }
+            (DefaultValue::Scalar(Scalar::Integer(value)), Some(e)) => {
+                enum_discriminants(&e.values)
+                    .into_iter()
+                    .find(|(_, discriminant)| discriminant == value)
+                    .map(|(id, _)| id)
+            }
+            _ => None,
+        };
+        let variant = variant.unwrap_or_else(|| {
+            panic!(
+                "invalid default value {:?} for field {} of type {:?}",
+                default, field.id.raw, ty
+            )
+        });
+        let path = self.type_path(ty);
+        Some(quote!(#path::#variant))
+    }
+
+    /// The value zero stands for in the scalar or enum type `ty`: the
+    /// variant of an enum whose discriminant is zero, or else its first one.
+    fn zero_value(&self, ty: &Type<'a>) -> TokenStream {
+        match ty {
+            Type::Ident(id) => match self.resolve(id) {
+                Some(Declaration::Enum(e)) if e.is_bit_flags() => {
+                    let path = self.type_path(ty);
+                    quote!(#path::from_bits_truncate(0))
+                }
+                Some(Declaration::Enum(e)) => {
+                    let discriminants = enum_discriminants(&e.values);
+                    let variant = discriminants
+                        .iter()
+                        .find(|(_, discriminant)| *discriminant == 0)
+                        .or_else(|| discriminants.first())
+                        .map(|(id, _)| id);
+                    let path = self.type_path(ty);
+                    quote!(#path::#variant)
+                }
+                _ => quote!(core::default::Default::default()),
+            },
+            ty => default_literal(&Scalar::Integer(0), ty),
+        }
+    }
+
+    /// The value of a scalar or enum field in the `Default` of the `Args` of
+    /// its table: its default value, as when the field is missing from a
+    /// table.
+    fn default_arg(&self, field: &Field<'a>) -> TokenStream {
+        self.default_value(field)
+            .unwrap_or_else(|| quote!(core::default::Default::default()))
+    }
+
+    /// Check whether `ty` refers to a table.
+    fn is_table(&self, ty: &Type<'a>) -> bool {
+        match ty {
+            Type::Ident(id) => matches!(self.resolve(id), Some(Declaration::Table)),
+            _ => false,
+        }
+    }
+
+    /// The path to the item `id` in the namespace `namespace`, relative to
+    /// the module of this scope.
+    fn relative_path(&self, namespace: &[&'a str], id: impl Display) -> TokenStream {
+        let common = self
+            .namespace
+            .iter()
+            .zip(namespace)
+            .take_while(|(a, b)| a == b)
+            .count();
+        let supers = std::iter::repeat_n(quote!(super), self.namespace.len() - common);
+        let parts = namespace[common..]
+            .iter()
+            .map(ident)
+            .chain(std::iter::once(ident(id.to_string())));
+        quote!(#(#supers::)* #(#parts)::*)
+    }
+
+    /// The path to the Rust type generated for the scalar or user-defined
+    /// type `ty`.
+    fn type_path(&self, ty: &Type<'a>) -> TokenStream {
+        match ty {
+            Type::Ident(id) => match self.symbols.resolve(self.namespace, id) {
+                Some((name, _)) => {
+                    let (last, namespace) = name.split_last().expect("empty name");
+                    self.relative_path(namespace, last)
+                }
+                None => quote!(#id),
+            },
+            Type::FixedArray(element, len) => {
+                let element = self.type_path(element);
+                let len = Literal::i128_unsuffixed(*len);
+                quote!([#element; #len])
+            }
+            ty => quote!(#ty),
+        }
+    }
+
+    /// The path to the discriminant enum of the union that `ty` refers to.
+    fn union_type_path(&self, ty: &Type<'a>) -> TokenStream {
+        match ty {
+            Type::Ident(id) => match self.symbols.resolve(self.namespace, id) {
+                Some((name, Declaration::Union(_))) => {
+                    let (last, namespace) = name.split_last().expect("empty name");
+                    self.relative_path(namespace, union_type_id(&Ident::from(*last)))
+                }
+                _ => panic!("not a union type: {:?}", ty),
+            },
+            _ => panic!("not a union type: {:?}", ty),
+        }
+    }
+
+    /// The path to the object type generated for the table or union `ty`,
+    /// see `object_api`.
+    fn object_path(&self, ty: &Type<'a>) -> TokenStream {
+        match ty {
+            Type::Ident(id) => match self.symbols.resolve(self.namespace, id) {
+                Some((name, _)) => {
+                    let (last, namespace) = name.split_last().expect("empty name");
+                    self.relative_path(namespace, object_id(last))
+                }
+                None => panic!("unknown type: {:?}", ty),
+            },
+            _ => panic!("not a table or union: {:?}", ty),
+        }
+    }
+
+    /// The path to the table `variant` of the union that `ty` refers to.
+    ///
+    /// Variants are resolved relative to the namespace of the union.
+    fn union_variant_path(&self, ty: &Type<'a>, variant: &EnumVal<'a>) -> TokenStream {
+        self.union_variant_path_with(ty, variant, |id| id.to_string())
+    }
+
+    /// The path to the object type of the table `variant` of the union that
+    /// `ty` refers to.
+    fn union_variant_object_path(&self, ty: &Type<'a>, variant: &EnumVal<'a>) -> TokenStream {
+        self.union_variant_path_with(ty, variant, object_id)
+    }
+
+    /// The path to the item named `name(variant)` in the namespace of the
+    /// table `variant` of the union that `ty` refers to.
+    fn union_variant_path_with(
+        &self,
+        ty: &Type<'a>,
+        variant: &EnumVal<'a>,
+        name: impl Fn(&str) -> String,
+    ) -> TokenStream {
+        let union_namespace = match ty {
+            Type::Ident(id) => match self.symbols.resolve(self.namespace, id) {
+                Some((name, _)) => &name[..name.len() - 1],
+                None => panic!("not a union type: {:?}", ty),
+            },
+            _ => panic!("not a union type: {:?}", ty),
+        };
+        let variant = variant.union_type();
+        match self.symbols.resolve(union_namespace, &variant) {
+            Some((name_parts, _)) => {
+                let (last, namespace) = name_parts.split_last().expect("empty name");
+                self.relative_path(namespace, name(last))
+            }
+            None => {
+                let last = variant.parts.last().expect("empty name");
+                let id = ident(name(last.raw));
+                quote!(#id)
+            }
+        }
+    }
+
+    /// The enum `ty` refers to, if it is a C-style enum: one without the
+    /// `bit_flags` attribute, whose values must be checked when they are
+    /// read.
+    fn c_style_enum(&self, ty: &Type<'a>) -> Option<&Enum<'a>> {
+        match ty {
+            Type::Ident(id) => match self.resolve(id) {
+                Some(Declaration::Enum(e)) if !e.is_bit_flags() => Some(e),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Return the union declaration `ty` refers to, if any.
+    fn union(&self, ty: &Type<'a>) -> Option<&Union<'a>> {
+        match ty {
+            Type::Ident(id) => match self.resolve(id) {
+                Some(Declaration::Union(u)) => Some(u),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Return the union declaration `ty` is a vector of, if any.
+    fn union_vector(&self, ty: &Type<'a>) -> Option<&Union<'a>> {
+        match ty {
+            Type::Array(element) => self.union(element),
+            _ => None,
+        }
+    }
+
+    /// Check whether a field of type `ty` has a `_type` field holding the
+    /// discriminants of its values, as unions and vectors of unions do.
+    fn has_type_field(&self, ty: &Type<'a>) -> bool {
+        self.union(ty).is_some() || self.union_vector(ty).is_some()
+    }
+}
+
+#[cfg(test)]
+mod scope_tests {
+    use super::*;
+    use crate::namespace;
+
+    #[test]
+    fn test_type_path_is_relative() {
+        let elements = vec![
+            Element::from(namespace!(a)),
+            Element::from(table!(Outer, [field!(x, Int)])),
+            Element::from(namespace!(a::b)),
+            Element::from(table!(Inner, [field!(x, Int)])),
+            Element::from(namespace!(c)),
+            Element::from(table!(Other, [field!(x, Int)])),
+        ];
+        let symbols = SymbolTable::new(&elements);
+        let path = |namespace: &[&str], id: Vec<&str>| {
+            let scope = Scope {
+                symbols: &symbols,
+                namespace,
+                options: &Options::default(),
+            };
+            let ty = Type::Ident(id.into_iter().map(Ident::from).collect::<Vec<_>>().into());
+            scope.type_path(&ty).to_string()
+        };
+
+        assert_eq!(path(&["a"], vec!["Outer"]), "Outer");
+        assert_eq!(path(&["a", "b"], vec!["Outer"]), "super :: Outer");
+        assert_eq!(path(&["a"], vec!["b", "Inner"]), "b :: Inner");
+        assert_eq!(
+            path(&["a", "b"], vec!["c", "Other"]),
+            "super :: super :: c :: Other"
+        );
+        assert_eq!(path(&[], vec!["a", "b", "Inner"]), "a :: b :: Inner");
+    }
+
+    #[test]
+    fn test_type_path_of_sibling_namespace() {
+        let elements = vec![
+            Element::from(namespace!(b)),
+            Element::from(table!(Shadowed, [field!(x, Int)])),
+            Element::from(namespace!(a::b)),
+            Element::from(table!(Shadowed, [field!(x, Int)])),
+            Element::from(table!(Inner, [field!(x, Int)])),
+            Element::from(namespace!(a::c)),
+            Element::from(table!(Other, [field!(x, Int)])),
+        ];
+        let symbols = SymbolTable::new(&elements);
+        let scope = Scope {
+            symbols: &symbols,
+            namespace: &["a", "c"],
+            options: &Options::default(),
+        };
+        let path = |id: Vec<&str>| {
+            let ty = Type::Ident(id.into_iter().map(Ident::from).collect::<Vec<_>>().into());
+            scope.type_path(&ty).to_string()
+        };
+
+        // `b` is looked up in `a.c`, then in `a` and only then at the root
+        assert_eq!(path(vec!["b", "Inner"]), "super :: b :: Inner");
+        assert_eq!(path(vec!["b", "Shadowed"]), "super :: b :: Shadowed");
+        assert_eq!(path(vec!["a", "c", "Other"]), "Other");
+    }
+}
+
+/// An item paired with the `Scope` it is generated in.
+struct Scoped<'a, 'b, T> {
+    item: &'b T,
+    scope: Scope<'a, 'b>,
+}
+
+/// The places in generated code where a field's type is spelled out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TypeUsage {
+    /// The type returned by a reader's field accessor.
+    Read,
+    /// The type passed to `butte::Table::get` to read a field.
+    Follow,
+    /// The type passed to a builder to write a field.
+    Write,
+}
+
+/// Convert a `types::Type` to the Rust type used for `usage`.
+fn to_type<'a>(
+    ty: &Type<'a>,
+    lifetime: impl ToTokens,
+    usage: TypeUsage,
+    scope: Scope<'a, '_>,
+) -> TokenStream {
+    let lifetime = lifetime.to_token_stream();
+    let path = scope.type_path(ty);
+    let read_type = match ty {
+        Type::String => quote!(&#lifetime str),
+        // vectors of unions are read along with the vector of their types
+        Type::Array(element) if scope.union(element).is_some() => {
+            let reader = scope.type_path(element);
+            return match usage {
+                TypeUsage::Read => quote!(butte::UnionVector<#lifetime, #reader<#lifetime>>),
+                TypeUsage::Follow => quote! {
+                    butte::ForwardsUOffset<butte::Vector<#lifetime, butte::ForwardsUOffset<butte::Table<#lifetime>>>>
+                },
+                TypeUsage::Write => quote! {
+                    butte::WIPOffset<butte::Vector<#lifetime, butte::ForwardsUOffset<butte::UnionWIPOffset>>>
+                },
+            };
+        }
+        Type::Array(element) => {
+            let element = to_type(element, lifetime.clone(), TypeUsage::Follow, scope);
+            quote!(butte::Vector<#lifetime, #element>)
+        }
+        ty if scope.is_table(ty) => quote!(#path<#lifetime>),
+        // structs are stored inline but read by reference
+        ty if scope.is_struct(ty) => {
+            return match usage {
+                TypeUsage::Follow => quote!(#path),
+                TypeUsage::Read | TypeUsage::Write => quote!(&#lifetime #path),
+            };
+        }
+        // scalars and enums are stored inline
+        _ => return path,
+    };
+    // everything else is stored behind an offset
+    match usage {
+        TypeUsage::Read => read_type,
+        TypeUsage::Follow => quote!(butte::ForwardsUOffset<#read_type>),
+        TypeUsage::Write => quote!(butte::WIPOffset<#read_type>),
+    }
+}
+
+#[cfg(test)]
+mod to_type_tests {
+    use super::*;
+
+    fn to_types(ty: Type, elements: &[Element]) -> [String; 3] {
+        let symbols = SymbolTable::new(elements);
+        let scope = Scope {
+            symbols: &symbols,
+            namespace: &[],
+            options: &Options::default(),
+        };
+        [TypeUsage::Read, TypeUsage::Follow, TypeUsage::Write]
+            .map(|usage| to_type(&ty, quote!('a), usage, scope).to_string())
+    }
+
+    #[test]
+    fn test_vector_of_scalars() {
+        let result = to_types(Type::Array(Box::new(Type::UByte)), &[]);
+        assert_eq!(
+            result,
+            [
+                "butte :: Vector < 'a , u8 >",
+                "butte :: ForwardsUOffset < butte :: Vector < 'a , u8 > >",
+                "butte :: WIPOffset < butte :: Vector < 'a , u8 > >",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_vector_of_strings() {
+        let [read, _, _] = to_types(Type::Array(Box::new(Type::String)), &[]);
+        assert_eq!(
+            read,
+            "butte :: Vector < 'a , butte :: ForwardsUOffset < & 'a str > >"
+        );
+    }
+
+    #[test]
+    fn test_vector_of_tables() {
+        let elements = [Element::from(table!(Item, [field!(x, Int)]))];
+        let ty = Type::Array(Box::new(Type::Ident(vec!["Item".into()].into())));
+        let [read, _, _] = to_types(ty, &elements);
+        assert_eq!(
+            read,
+            "butte :: Vector < 'a , butte :: ForwardsUOffset < Item < 'a > > >"
+        );
+    }
+}
+
+/// Convert the type of a table field to the Rust type used for `usage`,
+/// taking the `offset64` and `vector64` attributes into account.
+fn field_type<'a>(
+    field: &Field<'a>,
+    lifetime: impl ToTokens,
+    usage: TypeUsage,
+    scope: Scope<'a, '_>,
+) -> TokenStream {
+    if !field.is_offset64() {
+        return to_type(&field.ty, lifetime, usage, scope);
+    }
+    let lifetime = lifetime.to_token_stream();
+    let read_type = match &field.ty {
+        Type::Array(element) if field.is_vector64() => {
+            let element = to_type(element, lifetime.clone(), TypeUsage::Follow, scope);
+            quote!(butte::Vector64<#lifetime, #element>)
+        }
+        ty => to_type(ty, lifetime, TypeUsage::Read, scope),
+    };
+    match usage {
+        TypeUsage::Read => read_type,
+        TypeUsage::Follow => quote!(butte::ForwardsUOffset64<#read_type>),
+        TypeUsage::Write => quote!(butte::WIPOffset64<#read_type>),
+    }
+}
+
+/// The Rust type used for `usage` of the vector holding the discriminants of
+/// the vector of unions `ty`.
+fn union_types_vector<'a>(
+    ty: &Type<'a>,
+    lifetime: impl ToTokens,
+    usage: TypeUsage,
+    scope: Scope<'a, '_>,
+) -> TokenStream {
+    let union_type = match ty {
+        Type::Array(element) => scope.union_type_path(element),
+        _ => panic!("not a vector of unions: {:?}", ty),
+    };
+    let read_type = quote!(butte::Vector<#lifetime, #union_type>);
+    match usage {
+        TypeUsage::Read => read_type,
+        TypeUsage::Follow => quote!(butte::ForwardsUOffset<#read_type>),
+        TypeUsage::Write => quote!(butte::WIPOffset<#read_type>),
+    }
+}
+
+/// A scalar default value as a literal of the field's type.
+fn default_literal(value: &Scalar, ty: &Type) -> TokenStream {
+    match (value, ty) {
+        (Scalar::Integer(i), Type::Float | Type::Float32) => {
+            Literal::f32_unsuffixed(*i as f32).into_token_stream()
+        }
+        (Scalar::Integer(i), Type::Double | Type::Float64) => {
+            Literal::f64_unsuffixed(*i as f64).into_token_stream()
+        }
+        (Scalar::Float(f), Type::Float | Type::Float32 | Type::Double | Type::Float64) => {
+            float_literal(*f, ty)
+        }
+        (Scalar::Integer(i), Type::Bool) => (*i != 0).into_token_stream(),
+        (Scalar::Integer(i), _) => Literal::i128_unsuffixed(*i).into_token_stream(),
+        (Scalar::Boolean(b), _) => b.into_token_stream(),
+        (Scalar::Float(f), _) => panic!("invalid default value {} for type {:?}", f, ty),
+    }
+}
+
+/// A float literal of the float type `ty`, or its constant for `nan`, `inf`
+/// and `-inf`, which have no literal.
+fn float_literal(value: f64, ty: &Type) -> TokenStream {
+    let single = matches!(ty, Type::Float | Type::Float32);
+    let path = if single { quote!(f32) } else { quote!(f64) };
+    if value.is_nan() {
+        quote!(#path::NAN)
+    } else if value == f64::INFINITY {
+        quote!(#path::INFINITY)
+    } else if value == f64::NEG_INFINITY {
+        quote!(#path::NEG_INFINITY)
+    } else if single {
+        Literal::f32_unsuffixed(value as f32).into_token_stream()
+    } else {
+        Literal::f64_unsuffixed(value).into_token_stream()
+    }
+}
+
+#[cfg(test)]
+mod default_literal_tests {
+    use super::*;
+
+    #[test]
+    fn test_default_literal() {
+        let result = default_literal(&Scalar::Integer(2), &Type::Float64);
+        assert_eq!(result.to_string(), "2.0");
+
+        let result = default_literal(&Scalar::Integer(-3), &Type::Short);
+        assert_eq!(result.to_string(), "- 3");
+
+        let result = default_literal(&Scalar::Integer(1), &Type::Bool);
+        assert_eq!(result.to_string(), "true");
+
+        let result = default_literal(&Scalar::Float(0.5), &Type::Float);
+        assert_eq!(result.to_string(), "0.5");
+
+        let result = default_literal(&Scalar::Float(2.5e3), &Type::Float);
+        assert_eq!(result.to_string(), "2500.0");
+
+        let result = default_literal(&Scalar::Float(f64::NAN), &Type::Float);
+        assert_eq!(result.to_string(), "f32 :: NAN");
+
+        let result = default_literal(&Scalar::Float(f64::INFINITY), &Type::Double);
+        assert_eq!(result.to_string(), "f64 :: INFINITY");
+
+        let result = default_literal(&Scalar::Float(f64::NEG_INFINITY), &Type::Float32);
+        assert_eq!(result.to_string(), "f32 :: NEG_INFINITY");
+    }
+}
+
+fn offset_id(field: &Field) -> impl ToTokens {
+    format_ident!("VT_{}", field.id.as_ref().to_shouty_snake_case())
+}
+
+/// The offset constant of the discriminant slot of a union field.
+fn type_offset_id(field: &Field) -> impl ToTokens {
+    format_ident!("VT_{}_TYPE", field.id.as_ref().to_shouty_snake_case())
+}
+
+/// The name of the object type generated for a table or union.
+fn object_id(name: &str) -> String {
+    format!("{}T", name)
+}
+
+/// The name of the discriminant enum generated for a union.
+fn union_type_id(id: &Ident) -> proc_macro2::Ident {
+    format_ident!("{}Type", id.raw)
+}
+
+/// The declaration of `field` in the `Args` of its table, along with that of
+/// its type field if it is a union or a vector of unions.
+fn args_field(field: &Field, scope: Scope) -> TokenStream {
+    let field_id = field_ident(field, scope);
+    let ty = &field.ty;
+    if scope.union(ty).is_some() {
+        let type_id = format_ident!("{}_type", field_id);
+        let union_type = scope.union_type_path(ty);
+        return quote! {
+            pub #type_id: #union_type,
+            pub #field_id: Option<butte::WIPOffset<butte::UnionWIPOffset>>
+        };
+    }
+    let arg_ty = field_type(field, quote!('a), TypeUsage::Write, scope);
+    let type_field = if scope.union_vector(ty).is_some() {
+        let type_id = format_ident!("{}_type", field_id);
+        let types_ty = union_types_vector(ty, quote!('a), TypeUsage::Write, scope);
+        if field.is_required() {
+            quote!(pub #type_id: #types_ty,)
+        } else {
+            quote!(pub #type_id: Option<#types_ty>,)
+        }
+    } else {
+        quote!()
+    };
+    if scope.is_optional_arg(field) {
+        quote!(#type_field pub #field_id: Option<#arg_ty>)
+    } else {
+        quote!(#type_field pub #field_id: #arg_ty)
+    }
+}
+
+/// Check whether `field` has a value in the `Default` of the `Args` of its
+/// table. Offsets can't be defaulted, so required fields that aren't
+/// scalars don't.
+fn has_default_arg(field: &Field, scope: Scope) -> bool {
+    !field.is_required() || scope.is_scalar(&field.ty)
+}
+
+/// The initializer of `field` in the `Default` of the `Args` of its table.
+fn default_arg(field: &Field, scope: Scope) -> TokenStream {
+    let field_id = field_ident(field, scope);
+    let ty = &field.ty;
+    if scope.union(ty).is_some() {
+        let type_id = format_ident!("{}_type", field_id);
+        let union_type = scope.union_type_path(ty);
+        return quote!(#type_id: #union_type::NONE, #field_id: None);
+    }
+    let type_field = if scope.union_vector(ty).is_some() {
+        let type_id = format_ident!("{}_type", field_id);
+        quote!(#type_id: None,)
+    } else {
+        quote!()
+    };
+    if scope.is_optional_arg(field) {
+        quote!(#type_field #field_id: None)
+    } else {
+        let value = scope.default_arg(field);
+        quote!(#type_field #field_id: #value)
+    }
+}
+
+/// Order the calls adding the fields of `table` to its builder, given in
+/// the order the fields are declared along with the sizes of their slots.
+///
+/// Fields are written largest first, which minimizes the padding needed to
+/// align them and makes the layout of a table independent of where in the
+/// buffer it starts. With the `original_order` attribute they are instead
+/// written in reverse, as the buffer is built back to front, so that they
+/// end up in the order they are declared, like with flatc.
+fn add_order(table: &Table, mut calls: Vec<(usize, TokenStream)>) -> Vec<TokenStream> {
+    if table.is_original_order() {
+        calls.reverse();
+    } else {
+        calls.sort_by_key(|&(size, _)| std::cmp::Reverse(size));
+    }
+    calls.into_iter().map(|(_, call)| call).collect()
+}
+
+impl ToTokens for Scoped<'_, '_, Table<'_>> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let Self { item, scope } = self;
+        let Table {
+            id: struct_id,
+            fields,
+            doc,
+            ..
+        } = item;
+        let scope = *scope;
+
+        let raw_struct_name = struct_id.raw;
+
+        // Deprecated fields can still be read from old buffers, but are never
+        // written.
+        let written_fields = || fields.iter().filter(|field| !field.is_deprecated());
+
+        let builder_add_calls = written_fields()
+            .flat_map(|field| {
+                let field_id = field_ident(field, scope);
+                let add_field_method = format_ident!("add_{}", field_id);
+                if scope.union(&field.ty).is_some() {
+                    let type_id = format_ident!("{}_type", field_id);
+                    let add_type_method = format_ident!("add_{}_type", field_id);
+                    vec![
+                        (1, quote!(builder.#add_type_method(args.#type_id);)),
+                        (
+                            butte::SIZE_UOFFSET,
+                            quote! {
+                                if let Some(value) = args.#field_id {
+                                    builder.#add_field_method(value);
+                                }
+                            },
+                        ),
+                    ]
+                } else if scope.union_vector(&field.ty).is_some() {
+                    let type_id = format_ident!("{}_type", field_id);
+                    let add_type_method = format_ident!("add_{}_type", field_id);
+                    let call = if field.is_required() {
+                        quote! {
+                            builder.#add_type_method(args.#type_id);
+                            builder.#add_field_method(args.#field_id);
+                        }
+                    } else {
+                        quote! {
+                            if let Some(value) = args.#type_id {
+                                builder.#add_type_method(value);
+                            }
+                            if let Some(value) = args.#field_id {
+                                builder.#add_field_method(value);
+                            }
+                        }
+                    };
+                    vec![(butte::SIZE_UOFFSET, call)]
+                } else if scope.is_optional_arg(field) {
+                    vec![(
+                        scope.slot_size(field),
+                        quote! {
+                            if let Some(value) = args.#field_id {
+                                builder.#add_field_method(value);
+                            }
+                        },
+                    )]
+                } else {
+                    vec![(
+                        scope.slot_size(field),
+                        quote!(builder.#add_field_method(args.#field_id);),
+                    )]
+                }
+            })
+            .collect::<Vec<_>>();
+        let builder_add_calls = add_order(item, builder_add_calls);
+
+        let args = format_ident!("{}Args", raw_struct_name);
+        let args_fields = written_fields().map(|field| args_field(field, scope));
+        let args_default = written_fields()
+            .all(|field| has_default_arg(field, scope))
+            .then(|| {
+                written_fields()
+                    .map(|field| default_arg(field, scope))
+                    .collect::<Vec<_>>()
+            });
+
+        // Only fields that refer to other data in the buffer borrow from it.
+        let args_borrow = written_fields().any(|Field { ty, .. }| {
+            matches!(ty, Type::String | Type::Array(_)) || scope.is_table(ty) || scope.is_struct(ty)
+        });
+        let (args_generics, args_lifetime) = if args_borrow {
+            (quote!(<'a>), quote!(<'args>))
+        } else {
+            (quote!(), quote!())
+        };
+
+        let args_default = args_default.map(|defaults| {
+            quote! {
+                impl #args_generics core::default::Default for #args #args_generics {
+                    #[inline]
+                    fn default() -> Self {
+                        Self {
+                            #(#defaults),*
+                        }
+                    }
+                }
+            }
+        });
+
+        let builder_type = format_ident!("{}Builder", struct_id.raw);
+
+        // A `[ubyte]` field with the `nested_flatbuffer` attribute holds a
+        // whole buffer, whose root is the table the attribute names.
+        let field_nested_flatbuffers = fields.iter().filter_map(|field| {
+            let root = field.nested_flatbuffer()?;
+            let root_ty = Type::Ident(DottedIdent::from(
+                root.split('.').map(Ident::from).collect::<Vec<_>>(),
+            ));
+            let root_path = scope.type_path(&root_ty);
+            let snake_name = ident(field.id.as_ref().to_snake_case());
+            let method_name = format_ident!("{}_nested_flatbuffer", field.id.as_ref().to_snake_case());
+            let deprecated = if field.is_deprecated() {
+                quote!(#[deprecated])
+            } else {
+                quote!()
+            };
+            Some(if field.is_required() {
+                quote! {
+                    #deprecated
+                    #[inline]
+                    pub fn #method_name(&self) -> butte::Result<#root_path<'a>> {
+                        butte::get_root::<#root_path<'a>>(self.#snake_name()?.safe_slice()?)
+                    }
+                }
+            } else {
+                quote! {
+                    #deprecated
+                    #[inline]
+                    pub fn #method_name(&self) -> butte::Result<Option<#root_path<'a>>> {
+                        match self.#snake_name()? {
+                            Some(data) => butte::get_root::<#root_path<'a>>(data.safe_slice()?).map(Some),
+                            None => Ok(None),
+                        }
+                    }
+                }
+            })
+        });
+
+        // A `[ubyte]` field with the `flexbuffer` attribute holds a
+        // FlexBuffer, whose root is read without a schema.
+        let field_flexbuffers = fields.iter().filter(|field| field.is_flexbuffer()).map(|field| {
+            let snake_name = ident(field.id.as_ref().to_snake_case());
+            let method_name = format_ident!("{}_flexbuffer", field.id.as_ref().to_snake_case());
+            let deprecated = if field.is_deprecated() {
+                quote!(#[deprecated])
+            } else {
+                quote!()
+            };
+            if field.is_required() {
+                quote! {
+                    #deprecated
+                    #[inline]
+                    pub fn #method_name(&self) -> butte::Result<butte::flexbuffers::Reader<'a>> {
+                        butte::flexbuffers::Reader::get_root(self.#snake_name()?.safe_slice()?)
+                    }
+                }
+            } else {
+                quote! {
+                    #deprecated
+                    #[inline]
+                    pub fn #method_name(&self) -> butte::Result<Option<butte::flexbuffers::Reader<'a>>> {
+                        match self.#snake_name()? {
+                            Some(data) => butte::flexbuffers::Reader::get_root(data.safe_slice()?).map(Some),
+                            None => Ok(None),
+                        }
+                    }
+                }
+            }
+        });
+
+        // Optional fields can be checked for presence, which tells a field
+        // that was left out apart from one that was written with its default
+        // value.
+        let field_presence = fields
+            .iter()
+            .filter(|field| !field.is_required())
+            .map(|field| {
+                let offset_name = offset_id(field);
+                let method_name = format_ident!("has_{}", field.id.as_ref().to_snake_case());
+                let doc = format!(
+                    " Whether the `{}` field is present in the buffer.",
+                    field.id.raw
+                );
+                let deprecated = if field.is_deprecated() {
+                    quote!(#[deprecated])
+                } else {
+                    quote!()
+                };
+                quote! {
+                    #[doc = #doc]
+                    #deprecated
+                    #[inline]
+                    pub fn #method_name(&self) -> butte::Result<bool> {
+                        self.table.is_present(#struct_id::#offset_name)
+                    }
+                }
+            });
+
+        // String fields can also be read as bytes, which doesn't fail if
+        // they aren't valid UTF-8.
+        let field_string_bytes =
+            fields
+                .iter()
+                .filter(|field| field.ty == Type::String)
+                .map(|field| {
+                    let offset_name = offset_id(field);
+                    let method_name = format_ident!("{}_bytes", field.id.as_ref().to_snake_case());
+                    let deprecated = if field.is_deprecated() {
+                        quote!(#[deprecated])
+                    } else {
+                        quote!()
+                    };
+                    let offset = if field.is_offset64() {
+                        quote!(butte::ForwardsUOffset64)
+                    } else {
+                        quote!(butte::ForwardsUOffset)
+                    };
+                    let bytes = quote! {
+                        self.table
+                            .get::<#offset<butte::FbString<'a>>>(#struct_id::#offset_name, None)?
+                            .map(|string| string.as_bytes())
+                    };
+                    if field.is_required() {
+                        quote! {
+                            #deprecated
+                            #[inline]
+                            pub fn #method_name(&self) -> butte::Result<&'a [u8]> {
+                                #bytes.ok_or(butte::Error::MissingRequiredField {
+                                    slot: #struct_id::#offset_name,
+                                })
+                            }
+                        }
+                    } else {
+                        quote! {
+                            #deprecated
+                            #[inline]
+                            pub fn #method_name(&self) -> butte::Result<Option<&'a [u8]>> {
+                                Ok(#bytes)
+                            }
+                        }
+                    }
+                });
+
+        // The bytes a field's data is stored in: its value if it is stored
+        // inline, the bytes of a string, the elements of a vector, or the
+        // inline data of a table.
+        let field_range_arms = written_fields()
+            .map(|field| {
+                let offset_name = offset_id(field);
+                let ty = &field.ty;
+                let offset = if field.is_offset64() {
+                    quote!(butte::ForwardsUOffset64)
+                } else {
+                    quote!(butte::ForwardsUOffset)
+                };
+                let range = if ty == &Type::String {
+                    quote! {
+                        Ok(self
+                            .table
+                            .get::<#offset<butte::FbString<'a>>>(slot, None)?
+                            .map(|string| string.byte_range()))
+                    }
+                } else if let Type::Array(_) = ty {
+                    let ty_wrapped = field_type(field, quote!('a), TypeUsage::Follow, scope);
+                    quote! {
+                        Ok(self
+                            .table
+                            .get::<#ty_wrapped>(slot, None)?
+                            .map(|vector| vector.byte_range()))
+                    }
+                } else if scope.is_table(ty) || scope.union(ty).is_some() {
+                    quote! {
+                        self.table
+                            .get::<#offset<butte::Table<'a>>>(slot, None)?
+                            .map(|table| table.inline_range())
+                            .transpose()
+                    }
+                } else {
+                    let ty_wrapped = field_type(field, quote!('a), TypeUsage::Follow, scope);
+                    quote!(self.table.field_range::<#ty_wrapped>(slot))
+                };
+                let type_arm = if scope.union(ty).is_some() {
+                    let type_offset_name = type_offset_id(field);
+                    let union_type = scope.union_type_path(ty);
+                    quote!(#struct_id::#type_offset_name => self.table.field_range::<#union_type>(slot),)
+                } else if let (Some(_), Type::Array(_)) = (scope.union_vector(ty), ty) {
+                    let type_offset_name = type_offset_id(field);
+                    let types_wrapped = union_types_vector(ty, quote!('a), TypeUsage::Follow, scope);
+                    quote! {
+                        #struct_id::#type_offset_name => Ok(self
+                            .table
+                            .get::<#types_wrapped>(slot, None)?
+                            .map(|vector| vector.byte_range())),
+                    }
+                } else {
+                    quote!()
+                };
+                quote! {
+                    #type_arm
+                    #struct_id::#offset_name => #range,
+                }
+            })
+            .collect::<Vec<_>>();
+        let field_range_body = if field_range_arms.is_empty() {
+            quote! {
+                let _ = slot;
+                Ok(None)
+            }
+        } else {
+            quote! {
+                match slot {
+                    #(#field_range_arms)*
+                    _ => Ok(None),
+                }
+            }
+        };
+
+        // Struct fields can also be copied out of the buffer, which unlike
+        // reading them by reference doesn't need them to be aligned.
+        let field_copies = fields
+            .iter()
+            .filter(|field| scope.is_struct(&field.ty))
+            .map(|field| {
+                let offset_name = offset_id(field);
+                let method_name = format_ident!("{}_copied", field.id.as_ref().to_snake_case());
+                let ty = scope.type_path(&field.ty);
+                let doc = format!(
+                    " A copy of the `{}` field, which may be unaligned in the buffer.",
+                    field.id.raw
+                );
+                let deprecated = if field.is_deprecated() {
+                    quote!(#[deprecated])
+                } else {
+                    quote!()
+                };
+                let value = quote! {
+                    self.table
+                        .get::<butte::Copied<#ty>>(#struct_id::#offset_name, None)
+                };
+                if field.is_required() {
+                    quote! {
+                        #[doc = #doc]
+                        #deprecated
+                        #[inline]
+                        pub fn #method_name(&self) -> butte::Result<#ty> {
+                            #value?.ok_or(butte::Error::MissingRequiredField {
+                                slot: #struct_id::#offset_name,
+                            })
+                        }
+                    }
+                } else {
+                    quote! {
+                        #[doc = #doc]
+                        #deprecated
+                        #[inline]
+                        pub fn #method_name(&self) -> butte::Result<Option<#ty>> {
+                            #value
+                        }
+                    }
+                }
+            });
+
+        // How the fields are stored, which `butte::copy_table` copies the
+        // table by. Tables and unions refer to the layouts of their tables
+        // through functions, which lets a table refer to itself.
+        let field_layouts = written_fields().map(|field| {
+            let offset_name = offset_id(field);
+            let ty = &field.ty;
+            let inline = |ty: &Type<'_>| scope.symbols.inline_size_and_align(scope.namespace, ty);
+            let value = match ty {
+                Type::String => quote!(butte::ValueLayout::String),
+                Type::Array(element) if field.is_vector64() => {
+                    let (size, align) = inline(element);
+                    quote!(butte::ValueLayout::Vector64 { size: #size, align: #align })
+                }
+                Type::Array(element) if **element == Type::String => {
+                    quote!(butte::ValueLayout::Strings)
+                }
+                Type::Array(element) if scope.is_table(element) => {
+                    let path = scope.type_path(element);
+                    quote!(butte::ValueLayout::Tables(#path::table_layout))
+                }
+                Type::Array(element) if scope.union(element).is_some() => {
+                    let path = scope.type_path(element);
+                    quote!(butte::ValueLayout::Unions(#path::table_layout))
+                }
+                Type::Array(element) => {
+                    let (size, align) = inline(element);
+                    quote!(butte::ValueLayout::Vector { size: #size, align: #align })
+                }
+                ty if scope.is_table(ty) => {
+                    let path = scope.type_path(ty);
+                    quote!(butte::ValueLayout::Table(#path::table_layout))
+                }
+                ty if scope.union(ty).is_some() => {
+                    let path = scope.type_path(ty);
+                    quote!(butte::ValueLayout::Union(#path::table_layout))
+                }
+                ty => {
+                    let (size, align) = inline(ty);
+                    quote!(butte::ValueLayout::Inline { size: #size, align: #align })
+                }
+            };
+            let offset64 = field.is_offset64() && !field.is_vector64();
+            quote! {
+                butte::FieldLayout {
+                    slot: #struct_id::#offset_name,
+                    offset64: #offset64,
+                    value: #value,
+                },
+            }
+        });
+
+        let builder_field_methods = written_fields().map(|field| {
+            let field_id = field_ident(field, scope);
+            let ty = &field.ty;
+            let add_method_name = format_ident!("add_{}", field_id);
+            let offset = offset_id(field);
+            let field_offset = quote!(#struct_id::#offset);
+            if scope.union(ty).is_some() {
+                let type_id = format_ident!("{}_type", field_id);
+                let add_type_method = format_ident!("add_{}_type", field_id);
+                let type_offset = type_offset_id(field);
+                let union_type = scope.union_type_path(ty);
+                return quote! {
+                    #[inline]
+                    pub fn #add_type_method(&mut self, #type_id: #union_type) {
+                        self.fbb.push_slot_always::<#union_type>(#struct_id::#type_offset, #type_id);
+                    }
+
+                    #[inline]
+                    pub fn #add_method_name(&mut self, #field_id: butte::WIPOffset<butte::UnionWIPOffset>) {
+                        self.fbb.push_slot_always::<butte::WIPOffset<_>>(#field_offset, #field_id);
+                    }
+                };
+            }
+            let add_type_method = scope.union_vector(ty).map(|_| {
+                let type_id = format_ident!("{}_type", field_id);
+                let add_type_method = format_ident!("add_{}_type", field_id);
+                let type_offset = type_offset_id(field);
+                let types_ty = union_types_vector(ty, quote!('b), TypeUsage::Write, scope);
+                quote! {
+                    #[inline]
+                    pub fn #add_type_method(&mut self, #type_id: #types_ty) {
+                        self.fbb.push_slot_always::<butte::WIPOffset<_>>(#struct_id::#type_offset, #type_id);
+                    }
+                }
+            });
+            let arg_ty = field_type(field, quote!('_), TypeUsage::Write, scope);
+            // fields equal to their default value are left out of the buffer
+            let body = match scope.default_value(field) {
+                Some(default_value) => {
+                    quote!(self.fbb.push_slot::<#arg_ty>(#field_offset, #field_id, #default_value))
+                }
+                None => quote!(self.fbb.push_slot_always::<#arg_ty>(#field_offset, #field_id)),
+            };
+            let arg_ty = field_type(field, quote!('b), TypeUsage::Write, scope);
+            // a field with the `hash` attribute can be given the string it
+            // stores the hash of
+            let add_hash_method = field.hash().map(|algorithm| {
+                let add_hash_method = format_ident!("add_{}_hash", field_id);
+                let hash_fn = format_ident!("{}", algorithm.name());
+                let hash = if matches!(ty, Type::Int | Type::Int32 | Type::Long | Type::Int64) {
+                    quote!(butte::hash::#hash_fn(#field_id.as_bytes()) as #arg_ty)
+                } else {
+                    quote!(butte::hash::#hash_fn(#field_id.as_bytes()))
+                };
+                let doc = format!(
+                    " Add the `{}` hash of a string as the `{}` field.",
+                    algorithm.name(),
+                    field.id.raw
+                );
+                quote! {
+                    #[doc = #doc]
+                    #[inline]
+                    pub fn #add_hash_method(&mut self, #field_id: &str) {
+                        self.#add_method_name(#hash);
+                    }
+                }
+            });
+            quote! {
+                #add_type_method
+
+                #[inline]
+                pub fn #add_method_name(&mut self, #field_id: #arg_ty) {
+                    #body;
+                }
+
+                #add_hash_method
+            }
+        });
+
+        // A union field occupies two vtable slots: its discriminant followed
+        // by its value. So does a vector of unions, with a vector of
+        // discriminants.
+        let slots = field_slots(fields, |ty| scope.has_type_field(ty));
+        let known_fields = slots
+            .iter()
+            .map(|&slot| slot as usize + 1)
+            .max()
+            .unwrap_or(0);
+        let field_offset_constants = fields
+            .iter()
+            .zip(slots)
+            .map(|(field, slot)| {
+                let offset_name = offset_id(field);
+                let offset_value = butte::field_index_to_field_offset(slot);
+                let hash = field.hash().map(|algorithm| {
+                    let hash_name =
+                        format_ident!("{}_HASH", field.id.as_ref().to_shouty_snake_case());
+                    let variant = ident(format!("{:?}", algorithm));
+                    let doc = format!(
+                        " The algorithm hashing the strings stored in the `{}` field.",
+                        field.id.raw
+                    );
+                    quote! {
+                        #[doc = #doc]
+                        pub const #hash_name: butte::HashAlgorithm = butte::HashAlgorithm::#variant;
+                    }
+                });
+                if scope.has_type_field(&field.ty) {
+                    let type_offset_name = type_offset_id(field);
+                    let type_offset_value = butte::field_index_to_field_offset(slot - 1);
+                    quote! {
+                        pub const #type_offset_name: butte::VOffsetT = #type_offset_value;
+                        pub const #offset_name: butte::VOffsetT = #offset_value;
+                    }
+                } else {
+                    quote! {
+                        pub const #offset_name: butte::VOffsetT = #offset_value;
+                        #hash
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let field_accessors = fields.iter().map(|field| {
+            let snake_name = ident(field.id.as_ref().to_snake_case());
+            let offset_name = offset_id(field);
+            let ty = &field.ty;
+            let field_doc = &field.doc;
+            let deprecated = if field.is_deprecated() {
+                quote!(#[deprecated])
+            } else {
+                quote!()
+            };
+
+            if let Some(union) = scope.union(ty) {
+                let type_accessor = format_ident!("{}_type", field.id.as_ref().to_snake_case());
+                let type_offset_name = type_offset_id(field);
+                let union_type = scope.union_type_path(ty);
+                let reader = scope.type_path(ty);
+                let variant_accessors = union.values.iter().map(|value| {
+                    let variant = &value.id;
+                    let accessor = format_ident!(
+                        "{}_as_{}",
+                        field.id.as_ref().to_snake_case(),
+                        variant.as_ref().to_snake_case()
+                    );
+                    let variant_path = scope.union_variant_path(ty, value);
+                    quote! {
+                        #deprecated
+                        #[inline]
+                        pub fn #accessor(&self) -> butte::Result<Option<#variant_path<'a>>> {
+                            match self.#snake_name()? {
+                                Some(#reader::#variant(value)) => Ok(Some(value)),
+                                _ => Ok(None),
+                            }
+                        }
+                    }
+                });
+                return quote! {
+                    #deprecated
+                    #[inline]
+                    pub fn #type_accessor(&self) -> butte::Result<#union_type> {
+                        Ok(self
+                            .table
+                            .get::<#union_type>(#struct_id::#type_offset_name, None)?
+                            .unwrap_or(#union_type::NONE))
+                    }
+
+                    #field_doc
+                    #deprecated
+                    #[inline]
+                    pub fn #snake_name(&self) -> butte::Result<Option<#reader<'a>>> {
+                        // the value is read as the type stored next to it, so
+                        // that it can't be read as the wrong table
+                        let ty = match self.#type_accessor()? {
+                            #union_type::NONE => return Ok(None),
+                            ty => ty,
+                        };
+                        Ok(self
+                            .table
+                            .get::<butte::ForwardsUOffset<butte::Table<'a>>>(#struct_id::#offset_name, None)?
+                            .map(|table| <#reader<'a> as butte::UnionReader<'a>>::from_table(ty, table)))
+                    }
+
+                    #(#variant_accessors)*
+                };
+            }
+
+            if scope.union_vector(ty).is_some() {
+                let type_accessor = format_ident!("{}_type", field.id.as_ref().to_snake_case());
+                let type_offset_name = type_offset_id(field);
+                let types_ty = union_types_vector(ty, quote!('a), TypeUsage::Read, scope);
+                let types_wrapped = union_types_vector(ty, quote!('a), TypeUsage::Follow, scope);
+                let read_ty = to_type(ty, quote!('a), TypeUsage::Read, scope);
+                let ty_wrapped = to_type(ty, quote!('a), TypeUsage::Follow, scope);
+                let types = quote!(self.table.get::<#types_wrapped>(#struct_id::#type_offset_name, None));
+                let values = quote!(self.table.get::<#ty_wrapped>(#struct_id::#offset_name, None)?);
+                return if field.is_required() {
+                    quote! {
+                        #deprecated
+                        #[inline]
+                        pub fn #type_accessor(&self) -> butte::Result<#types_ty> {
+                            #types?.ok_or(butte::Error::MissingRequiredField {
+                                slot: #struct_id::#type_offset_name,
+                            })
+                        }
+
+                        #field_doc
+                        #deprecated
+                        #[inline]
+                        pub fn #snake_name(&self) -> butte::Result<#read_ty> {
+                            butte::UnionVector::new(
+                                self.#type_accessor()?,
+                                #values.ok_or(butte::Error::MissingRequiredField {
+                                    slot: #struct_id::#offset_name,
+                                })?,
+                            )
+                        }
+                    }
+                } else {
+                    quote! {
+                        #deprecated
+                        #[inline]
+                        pub fn #type_accessor(&self) -> butte::Result<Option<#types_ty>> {
+                            #types
+                        }
+
+                        #field_doc
+                        #deprecated
+                        #[inline]
+                        pub fn #snake_name(&self) -> butte::Result<Option<#read_ty>> {
+                            butte::UnionVector::from_parts(self.#type_accessor()?, #values)
+                        }
+                    }
+                };
+            }
+
+            let ty_simple_lifetime = field_type(field, quote!('a), TypeUsage::Read, scope);
+            let ty_wrapped = field_type(field, quote!('_), TypeUsage::Follow, scope);
+
+            if let Some(default_value) = scope.default_value(field) {
+                return quote! {
+                    #field_doc
+                    #deprecated
+                    #[inline]
+                    pub fn #snake_name(&self) -> butte::Result<#ty_simple_lifetime> {
+                        Ok(self
+                            .table
+                            .get::<#ty_wrapped>(#struct_id::#offset_name, None)?
+                            .unwrap_or(#default_value))
+                    }
+                };
+            }
+
+            if field.is_required() {
+                return quote! {
+                    #field_doc
+                    #deprecated
+                    #[inline]
+                    pub fn #snake_name(&self) -> butte::Result<#ty_simple_lifetime> {
+                        self.table
+                            .get::<#ty_wrapped>(#struct_id::#offset_name, None)?
+                            .ok_or(butte::Error::MissingRequiredField {
+                                slot: #struct_id::#offset_name,
+                            })
+                    }
+                };
+            }
+
+            quote! {
+                #field_doc
+                #deprecated
+                #[inline]
+                pub fn #snake_name(&self) -> butte::Result<Option<#ty_simple_lifetime>> {
+                    self.table
+                        .get::<#ty_wrapped>(#struct_id::#offset_name, None)
+                }
+            }
+        });
+
+        // Tables are printed field by field, with their decoded values, so
+        // that received messages can be logged. Deprecated fields are left
+        // out.
+        let debug_fields = written_fields().map(|field| {
+            let name = field.id.as_ref().to_snake_case();
+            let snake_name = ident(&name);
+            let Some(union) = scope.union(&field.ty) else {
+                return quote! {
+                    match self.#snake_name() {
+                        Ok(value) => ds.field(#name, &value),
+                        Err(error) => ds.field(#name, &error),
+                    };
+                };
+            };
+            let type_accessor = format_ident!("{}_type", name);
+            let union_type = scope.union_type_path(&field.ty);
+            let variants = union.values.iter().map(|EnumVal { id: variant, .. }| {
+                let accessor = format_ident!("{}_as_{}", name, variant.as_ref().to_snake_case());
+                quote! {
+                    Ok(#union_type::#variant) => match self.#accessor() {
+                        Ok(value) => ds.field(#name, &value),
+                        Err(error) => ds.field(#name, &error),
+                    },
+                }
+            });
+            quote! {
+                match self.#type_accessor() {
+                    #(#variants)*
+                    Ok(_) => ds.field(#name, &None::<()>),
+                    Err(error) => ds.field(#name, &error),
+                };
+            }
+        });
+
+        // Tables are equal if their fields are, no matter where in which
+        // buffer they are. Deprecated fields aren't compared.
+        let eq_fields = written_fields()
+            .map(|field| {
+                let snake_name = ident(field.id.as_ref().to_snake_case());
+                let Some(union) = scope.union(&field.ty) else {
+                    return quote!(self.#snake_name() == other.#snake_name());
+                };
+                let type_accessor = format_ident!("{}_type", snake_name);
+                let union_type = scope.union_type_path(&field.ty);
+                let variants = union.values.iter().map(|EnumVal { id: variant, .. }| {
+                    let accessor =
+                        format_ident!("{}_as_{}", snake_name, variant.as_ref().to_snake_case());
+                    quote!(Ok(#union_type::#variant) => self.#accessor() == other.#accessor(),)
+                });
+                quote! {
+                    self.#type_accessor() == other.#type_accessor()
+                        && match self.#type_accessor() {
+                            #(#variants)*
+                            _ => true,
+                        }
+                }
+            })
+            .collect::<Vec<_>>();
+        let eq_fn = if eq_fields.is_empty() {
+            quote! {
+                fn eq(&self, _other: &Self) -> bool {
+                    true
+                }
+            }
+        } else {
+            quote! {
+                fn eq(&self, other: &Self) -> bool {
+                    #(#eq_fields)&&*
+                }
+            }
+        };
+
+        let struct_offset_enum_name = format_ident!("{}Offset", struct_id.raw);
+
+        let required_fields = fields
+            .iter()
+            .filter(|field| field.is_required())
+            .map(|field| {
+                let snake_name = field.id.as_ref().to_snake_case();
+                let offset_name = offset_id(field);
+                let types = scope.union_vector(&field.ty).map(|_| {
+                    let type_name = format!("{}_type", snake_name);
+                    let type_offset_name = type_offset_id(field);
+                    quote!(self.fbb.required(o, #struct_id::#type_offset_name, #type_name);)
+                });
+                quote! {
+                    #types
+                    self.fbb.required(o, #struct_id::#offset_name, #snake_name);
+                }
+            });
+
+        // Verifying a table verifies each of its fields with the type they
+        // are read as.
+        let verify_fields = written_fields()
+            .map(|field| {
+                let ty = &field.ty;
+                let offset = offset_id(field);
+                let required = field.is_required();
+                if scope.union(ty).is_some() {
+                    let reader = scope.type_path(ty);
+                    let type_offset = type_offset_id(field);
+                    quote! {
+                        table.verify_union::<#reader<'_>>(
+                            verifier,
+                            #struct_id::#type_offset,
+                            #struct_id::#offset,
+                            #required,
+                        )?;
+                    }
+                } else if let (Some(_), Type::Array(element)) = (scope.union_vector(ty), ty) {
+                    let reader = scope.type_path(element);
+                    let type_offset = type_offset_id(field);
+                    quote! {
+                        table.verify_union_vector::<#reader<'_>>(
+                            verifier,
+                            #struct_id::#type_offset,
+                            #struct_id::#offset,
+                            #required,
+                        )?;
+                    }
+                } else {
+                    let ty_wrapped = field_type(field, quote!('_), TypeUsage::Follow, scope);
+                    quote! {
+                        table.verify_field::<#ty_wrapped>(verifier, #struct_id::#offset, #required)?;
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+        let verify_table = if verify_fields.is_empty() {
+            quote!(verifier.enter_table(loc)?;)
+        } else {
+            quote! {
+                let table = verifier.enter_table(loc)?;
+                #(#verify_fields)*
+            }
+        };
+
+        let vis = &scope.options.visibility;
+        (quote! {
+            #vis enum #struct_offset_enum_name {}
+
+            impl butte::TableMarker for #struct_offset_enum_name {
+                type Table<'a> = #struct_id<'a>;
+            }
+
+            #[derive(Copy, Clone)]
+            #doc
+            #vis struct #struct_id<'a> {
+                table: butte::Table<'a>,
+            }
+
+            impl<'a> From<butte::Table<'a>> for #struct_id<'a> {
+                #[inline]
+                fn from(table: butte::Table<'a>) -> Self {
+                    Self { table }
+                }
+            }
+
+            impl<'a> #struct_id<'a> {
+                pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+                    fbb: &'mut_bldr mut butte::FlatBufferBuilder<'bldr>,
+                    args: &'args #args #args_lifetime
+                ) -> butte::WIPOffset<#struct_id<'bldr>> {
+                    let mut builder = #builder_type::new(fbb);
+                    #(#builder_add_calls)*
+                    builder.finish()
+                }
+
+                // field offset constants
+                #(#field_offset_constants)*
+
+                // fields access
+                #(#field_accessors)*
+
+                // nested flatbuffers if applicable
+                #(#field_nested_flatbuffers)*
+
+                #(#field_flexbuffers)*
+
+                #(#field_string_bytes)*
+
+                #(#field_copies)*
+
+                #(#field_presence)*
+
+                /// How the table is stored in a buffer, see `butte::copy_table`.
+                pub fn table_layout() -> &'static butte::TableLayout {
+                    static LAYOUT: butte::TableLayout = butte::TableLayout {
+                        fields: &[#(#field_layouts)*],
+                    };
+                    &LAYOUT
+                }
+
+                /// Copy the table, along with all the data it refers to, to
+                /// `fbb`, without reading the values of its fields.
+                pub fn copy_to<'b>(
+                    &self,
+                    fbb: &mut butte::FlatBufferBuilder<'b>,
+                ) -> butte::Result<butte::WIPOffset<#struct_id<'b>>> {
+                    let offset = butte::copy_table(fbb, &self.table, Self::table_layout())?;
+                    Ok(butte::WIPOffset::new(offset.value()))
+                }
+
+                /// Copy the table, along with all the data it refers to, into a
+                /// buffer of its own of which it is the root.
+                pub fn extract(&self) -> butte::Result<butte::Message<#struct_offset_enum_name>> {
+                    butte::Message::extract(self)
+                }
+
+                /// The table's inline data: the offset of its vtable, followed
+                /// by the fields stored inline, but not the data they refer to.
+                #[inline]
+                pub fn as_bytes(&self) -> butte::Result<&'a [u8]> {
+                    self.table.inline_bytes()
+                }
+
+                /// The size in bytes of the table's inline data.
+                #[inline]
+                pub fn table_size(&self) -> butte::Result<usize> {
+                    Ok(self.table.inline_range()?.len())
+                }
+
+                /// The range of the buffer holding the data of the field in the
+                /// vtable slot `slot`, one of the `VT_` constants, or `None` if
+                /// it isn't present. That's the value of a field stored inline,
+                /// the bytes of a string, the elements of a vector, or the
+                /// inline data of a table.
+                pub fn field_range(
+                    &self,
+                    slot: butte::VOffsetT,
+                ) -> butte::Result<Option<core::ops::Range<usize>>> {
+                    #field_range_body
+                }
+
+                /// The fields present in the table that this reader doesn't
+                /// know, written by a newer version of the schema, see
+                /// `butte::Table::unknown_fields`.
+                pub fn unknown_fields(&self) -> butte::Result<butte::UnknownFields<'a>> {
+                    self.table.unknown_fields(#known_fields)
+                }
+            }
+
+            impl butte::DeepCopy for #struct_offset_enum_name {
+                fn copy_to<'b>(
+                    table: &#struct_id<'_>,
+                    fbb: &mut butte::FlatBufferBuilder<'b>,
+                ) -> butte::Result<butte::WIPOffset<#struct_id<'b>>> {
+                    table.copy_to(fbb)
+                }
+            }
+
+            impl PartialEq for #struct_id<'_> {
+                #eq_fn
+            }
+
+            impl core::fmt::Debug for #struct_id<'_> {
+                fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                    let ds = &mut f.debug_struct(#raw_struct_name);
+                    #(#debug_fields)*
+                    ds.finish()
+                }
+            }
+
+            impl<'a> butte::Follow<'a> for #struct_id<'a> {
+                type Inner = Self;
+
+                #[inline]
+                fn follow(buf: &'a [u8], loc: usize) -> butte::Result<Self::Inner> {
+                    let table = butte::Table { buf, loc };
+                    Ok(Self { table })
+                }
+            }
+
+            // Each table is a level deeper than the table whose field it is.
+            impl butte::Verify for #struct_id<'_> {
+                fn verify(verifier: &mut butte::Verifier<'_>, loc: usize) -> butte::Result<()> {
+                    #verify_table
+                    verifier.exit_table();
+                    Ok(())
+                }
+            }
+
+            // Builder Args
+            #vis struct #args #args_generics {
+                #(#args_fields),*
+            }
+
+            #args_default
+
+            //// builder
+            #[must_use]
+            #vis struct #builder_type<'a, 'b> {
+                fbb: &'b mut butte::FlatBufferBuilder<'a>,
+                start: butte::WIPOffset<butte::TableUnfinishedWIPOffset>,
+            }
+
+            impl<'a: 'b, 'b> #builder_type<'a, 'b> {
+                #(#builder_field_methods)*
+
+                #[inline]
+                pub fn new(fbb: &'b mut butte::FlatBufferBuilder<'a>) -> Self {
+                    let start = fbb.start_table();
+                    #builder_type {
+                        fbb, start
+                    }
+                }
+
+                #[inline]
+                pub fn finish(self) -> butte::WIPOffset<#struct_id<'a>> {
+                    let o = self.fbb.end_table(self.start);
+                    #(#required_fields)*
+                    butte::WIPOffset::new(o.value())
+                }
+            }
+        })
+        .to_tokens(tokens);
+
+        direct::table(item, scope).to_tokens(tokens);
+
+        if scope.options.object_api {
+            object_api::table(item, scope).to_tokens(tokens);
+            #[cfg(feature = "serde")]
+            serde::object(item, scope).to_tokens(tokens);
+        }
+
+        if scope.options.round_trip_tests {
+            round_trip::table(item, scope).to_tokens(tokens);
+        }
+
+        #[cfg(feature = "serde")]
+        serde::table(item, scope).to_tokens(tokens);
+    }
+}
+
+impl ToTokens for Table<'_> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let symbols = SymbolTable::default();
+        let scope = Scope {
+            symbols: &symbols,
+            namespace: &[],
+            options: &Options::default(),
+        };
+        Scoped { item: self, scope }.to_tokens(tokens)
+    }
+}
+
+/// The element type of a fixed-size array, or `ty` itself if it isn't one.
+fn array_element<'t, 'a>(ty: &'t Type<'a>) -> &'t Type<'a> {
+    match ty {
+        Type::FixedArray(element, _) => element,
+        ty => ty,
+    }
+}
+
+impl ToTokens for Scoped<'_, '_, Struct<'_>> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let Self { item, scope } = self;
+        let Struct {
+            id: struct_id,
+            fields,
+            doc,
+            ..
+        } = item;
+
+        let layout = scope.symbols.struct_layout(scope.namespace, item);
+        let align = Literal::usize_unsuffixed(layout.align);
+        // `PartialEq` and `Hash` are implemented by hand to leave out padding,
+        // which may hold anything in a buffer that wasn't written by butte.
+        let hash = scope
+            .options
+            .derives
+            .iter()
+            .any(|path| is_trait(path, "Hash"));
+        let extra_derives = scope
+            .options
+            .derives
+            .iter()
+            .filter(|path| !is_trait(path, "Hash") && !is_trait(path, "PartialEq"))
+            .cloned()
+            .collect::<Vec<_>>();
+        let derives = derives(&["Clone", "Copy", "Debug"], &extra_derives);
+
+        // Padding is made explicit so that every byte of the struct is
+        // initialized when it is copied into a buffer.
+        let mut end = 0;
+        let mut storage_fields = vec![];
+        let mut padding_inits = vec![];
+        let mut pad_to = |offset: usize, end: usize, storage_fields: &mut Vec<TokenStream>| {
+            if offset > end {
+                let padding_id = format_ident!("padding{}__", padding_inits.len());
+                let padding_len = Literal::usize_unsuffixed(offset - end);
+                storage_fields.push(quote!(#padding_id: [u8; #padding_len]));
+                padding_inits.push(quote!(#padding_id: [0; #padding_len]));
+            }
+        };
+        for ((field, &offset), &size) in fields.iter().zip(&layout.offsets).zip(&layout.sizes) {
+            pad_to(offset, end, &mut storage_fields);
+            let storage_id = format_ident!("{}_", field.id.raw.to_snake_case());
+            let storage_type = |ty| match scope.c_style_enum(ty) {
+                // Enums are stored as their base type, as the struct may be
+                // read from a buffer holding a value that isn't in the enum.
+                // Likewise, bools are stored as bytes.
+                Some(e) => e.base_type.to_token_stream(),
+                None if ty == &Type::Bool => quote!(u8),
+                None => scope.type_path(ty),
+            };
+            let ty = match &field.ty {
+                Type::FixedArray(element, len) => {
+                    let element = storage_type(element);
+                    let len = Literal::i128_unsuffixed(*len);
+                    quote!([#element; #len])
+                }
+                ty => storage_type(ty),
+            };
+            storage_fields.push(quote!(#storage_id: #ty));
+            end = offset + size;
+        }
+        pad_to(layout.size, end, &mut storage_fields);
+
+        let constructor_args = fields.iter().map(|field| {
+            let arg_id = ident(field.id.raw.to_snake_case());
+            let ty = scope.type_path(&field.ty);
+            if scope.is_struct(array_element(&field.ty)) {
+                quote!(#arg_id: &#ty)
+            } else {
+                quote!(#arg_id: #ty)
+            }
+        });
+
+        let field_inits = fields.iter().map(|field| {
+            let arg_id = ident(field.id.raw.to_snake_case());
+            let storage_id = format_ident!("{}_", field.id.raw.to_snake_case());
+            let is_array = matches!(field.ty, Type::FixedArray(..));
+            if scope.is_struct(array_element(&field.ty)) {
+                quote!(#storage_id: *#arg_id)
+            } else if let Some(e) = scope.c_style_enum(array_element(&field.ty)) {
+                let base_type = &e.base_type;
+                if is_array {
+                    quote! {
+                        #storage_id: #arg_id.map(|value| {
+                            butte::EndianScalar::to_little_endian(#base_type::from(value))
+                        })
+                    }
+                } else {
+                    quote!(#storage_id: butte::EndianScalar::to_little_endian(#base_type::from(#arg_id)))
+                }
+            } else if array_element(&field.ty) == &Type::Bool {
+                if is_array {
+                    quote!(#storage_id: #arg_id.map(u8::from))
+                } else {
+                    quote!(#storage_id: u8::from(#arg_id))
+                }
+            } else if is_array {
+                quote!(#storage_id: #arg_id.map(butte::EndianScalar::to_little_endian))
+            } else {
+                quote!(#storage_id: butte::EndianScalar::to_little_endian(#arg_id))
+            }
+        });
+
+        let field_accessors = fields.iter().map(|field| {
+            let snake_name = ident(field.id.raw.to_snake_case());
+            let storage_id = format_ident!("{}_", field.id.raw.to_snake_case());
+            let ty = scope.type_path(&field.ty);
+            let field_doc = &field.doc;
+            let element = array_element(&field.ty);
+            // Arrays of structs and single bytes are returned by reference,
+            // other arrays have to be copied to convert them from little
+            // endian, or from the bytes bools are stored as.
+            if scope.is_struct(element)
+                || (element != &field.ty && element.scalar_size() == Some(1) && element != &Type::Bool)
+            {
+                quote! {
+                    #field_doc
+                    #[inline]
+                    #[must_use]
+                    pub fn #snake_name(&self) -> &#ty {
+                        &self.#storage_id
+                    }
+                }
+            } else if let (Type::FixedArray(_, len), Some(e)) =
+                (&field.ty, scope.c_style_enum(element))
+            {
+                let len = Literal::i128_unsuffixed(*len);
+                let base_type = &e.base_type;
+                let element = scope.type_path(element);
+                let first = ident(e.values[0].id.raw);
+                quote! {
+                    #field_doc
+                    #[inline]
+                    pub fn #snake_name(&self) -> butte::Result<#ty> {
+                        let mut values = [#element::#first; #len];
+                        for (value, &raw) in values.iter_mut().zip(&self.#storage_id) {
+                            let raw = butte::EndianScalar::from_little_endian(raw);
+                            *value = <#element as core::convert::TryFrom<#base_type>>::try_from(raw)?;
+                        }
+                        Ok(values)
+                    }
+                }
+            } else if element == &Type::Bool {
+                let value = if element == &field.ty {
+                    quote!(self.#storage_id != 0)
+                } else {
+                    quote!(self.#storage_id.map(|value| value != 0))
+                };
+                quote! {
+                    #field_doc
+                    #[inline]
+                    #[must_use]
+                    pub fn #snake_name(&self) -> #ty {
+                        #value
+                    }
+                }
+            } else if let Type::FixedArray(..) = &field.ty {
+                quote! {
+                    #field_doc
+                    #[inline]
+                    #[must_use]
+                    pub fn #snake_name(&self) -> #ty {
+                        self.#storage_id.map(butte::EndianScalar::from_little_endian)
+                    }
+                }
+            } else if let Some(e) = scope.c_style_enum(&field.ty) {
+                let base_type = &e.base_type;
+                quote! {
+                    #field_doc
+                    #[inline]
+                    pub fn #snake_name(&self) -> butte::Result<#ty> {
+                        let value = butte::EndianScalar::from_little_endian(self.#storage_id);
+                        Ok(<#ty as core::convert::TryFrom<#base_type>>::try_from(value)?)
+                    }
+                }
+            } else {
+                quote! {
+                    #field_doc
+                    #[inline]
+                    #[must_use]
+                    pub fn #snake_name(&self) -> #ty {
+                        butte::EndianScalar::from_little_endian(self.#storage_id)
+                    }
+                }
+            }
+        });
+
+        let bool_offsets = scope.symbols.bool_offsets(scope.namespace, item);
+        let verify_bools = if bool_offsets.is_empty() {
+            quote!()
+        } else {
+            let bool_offsets = bool_offsets.into_iter().map(Literal::usize_unsuffixed);
+            quote!(verifier.bools(loc, &[#(#bool_offsets),*])?;)
+        };
+
+        let field_names = fields
+            .iter()
+            .map(|field| ident(field.id.raw.to_snake_case()))
+            .collect::<Vec<_>>();
+        let hash_impl = if hash {
+            quote! {
+                impl core::hash::Hash for #struct_id {
+                    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+                        #(core::hash::Hash::hash(&self.#field_names(), state);)*
+                    }
+                }
+            }
+        } else {
+            quote!()
+        };
+
+        let vis = &scope.options.visibility;
+        (quote! {
+            #[repr(C, align(#align))]
+            #derives
+            #doc
+            #vis struct #struct_id {
+                #(#storage_fields),*
+            }
+
+            impl PartialEq for #struct_id {
+                fn eq(&self, other: &Self) -> bool {
+                    #(self.#field_names() == other.#field_names())&&*
+                }
+            }
+
+            #hash_impl
+
+            impl butte::SafeSliceAccess for #struct_id {}
+
+            impl<'a> butte::Follow<'a> for #struct_id {
+                type Inner = &'a #struct_id;
+
+                #[inline]
+                fn follow(buf: &'a [u8], loc: usize) -> butte::Result<Self::Inner> {
+                    <&'a #struct_id>::follow(buf, loc)
+                }
+            }
+
+            impl<'a> butte::Follow<'a> for &'a #struct_id {
+                type Inner = &'a #struct_id;
+
+                #[inline]
+                fn follow(buf: &'a [u8], loc: usize) -> butte::Result<Self::Inner> {
+                    butte::follow_cast_ref::<#struct_id>(buf, loc)
+                }
+            }
+
+            impl butte::Verify for #struct_id {
+                #[inline]
+                fn verify(verifier: &mut butte::Verifier<'_>, loc: usize) -> butte::Result<()> {
+                    verifier.range(loc, core::mem::size_of::<#struct_id>())?;
+                    #verify_bools
+                    butte::follow_cast_ref::<#struct_id>(verifier.buf(), loc).map(|_| ())
+                }
+            }
+
+            impl butte::Push for #struct_id {
+                type Output = #struct_id;
+
+                #[inline]
+                fn push(&self, dst: &mut [u8], _rest: &[u8]) {
+                    let src = unsafe {
+                        core::slice::from_raw_parts(
+                            self as *const #struct_id as *const u8,
+                            <Self as butte::Push>::size(),
+                        )
+                    };
+                    dst.copy_from_slice(src);
+                }
+            }
+
+            impl butte::Push for &#struct_id {
+                type Output = #struct_id;
+
+                #[inline]
+                fn push(&self, dst: &mut [u8], rest: &[u8]) {
+                    <#struct_id as butte::Push>::push(*self, dst, rest)
+                }
+            }
+
+            impl #struct_id {
+                #[inline]
+                #[must_use]
+                #[allow(clippy::too_many_arguments)]
+                pub fn new(#(#constructor_args),*) -> Self {
+                    Self {
+                        #(#field_inits,)*
+                        #(#padding_inits,)*
+                    }
+                }
+
+                #(#field_accessors)*
+            }
+        })
+        .to_tokens(tokens);
+
+        #[cfg(feature = "serde")]
+        serde::struct_(item, *scope).to_tokens(tokens);
+    }
+}
+
+impl ToTokens for Struct<'_> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let symbols = SymbolTable::default();
+        let scope = Scope {
+            symbols: &symbols,
+            namespace: &[],
+            options: &Options::default(),
+        };
+        Scoped { item: self, scope }.to_tokens(tokens)
+    }
+}
+
+#[cfg(test)]
+mod product_type_tests {
+    use super::*;
+    use crate::{e_item, enum_};
+
+    #[test]
+    fn test_visit_product_type_table() {
+        let table = table!(
+            MyMessage,
+            [field!(message, String), field!(foo, Float64 = 2.0)]
+        );
+        let result = to_code(table);
+        assert!(!result.is_empty());
+    }
+
+    #[test]
+    fn test_visit_product_type_struct() {
+        let s = Struct::builder()
+            .id(Ident::from("Vec2"))
+            .fields(vec![field!(x, Float32), field!(y, Float32)])
+            .build();
+        let result = to_code(s);
+        assert!(result.contains("repr (C , align (4))"));
+        assert!(result.contains("# [derive (Clone , Copy , Debug)]"));
+        assert!(result.contains("self . x () == other . x () && self . y () == other . y ()"));
+    }
+
+    #[test]
+    fn test_visit_product_type_struct_fixed_arrays() {
+        let schema = crate::parser::parse_schema(
+            "enum Color : byte { Red, Green }
+             struct Vec2 { x: float; y: float; }
+             struct Shape { points: [Vec2:3]; weights: [float:3]; colors: [Color:2]; mask: [ubyte:5]; }",
+        )
+        .unwrap();
+        let result = to_code(schema);
+        assert!(result.contains("points_ : [Vec2 ; 3]"));
+        assert!(result.contains("colors_ : [i8 ; 2]"));
+        assert!(result.contains(
+            "pub fn new (points : & [Vec2 ; 3] , weights : [f32 ; 3] , colors : [Color ; 2] , mask : [u8 ; 5])"
+        ));
+        assert!(
+            result.contains("weights_ : weights . map (butte :: EndianScalar :: to_little_endian)")
+        );
+        assert!(result.contains("pub fn points (& self) -> & [Vec2 ; 3]"));
+        assert!(result.contains("pub fn mask (& self) -> & [u8 ; 5]"));
+        assert!(result.contains(
+            "pub fn weights (& self) -> [f32 ; 3] { self . weights_ . map (butte :: EndianScalar :: from_little_endian) }"
+        ));
+        assert!(result.contains("pub fn colors (& self) -> butte :: Result < [Color ; 2] >"));
+        assert!(result.contains("let mut values = [Color :: Red ; 2] ;"));
+    }
+
+    #[test]
+    fn test_nested_flatbuffer_accessor() {
+        let schema = crate::parser::parse_schema(
+            "namespace a; table Monster { hp: int; }
+             namespace b; table Save { monster: [ubyte] (nested_flatbuffer: \"a.Monster\"); }",
+        )
+        .unwrap();
+        let result = to_code(schema);
+        assert!(result.contains(
+            "pub fn monster_nested_flatbuffer (& self) -> butte :: Result < Option < super :: a :: Monster < 'a >> >"
+        ));
+        assert!(result.contains(
+            "butte :: get_root :: < super :: a :: Monster < 'a >> (data . safe_slice () ?)"
+        ));
+    }
+
+    #[test]
+    fn test_original_order() {
+        let schema = crate::parser::parse_schema(
+            "table A { x: int; }
+             union U { A }
+             table Sized { flag: bool; u: U; count: long; }
+             table Ordered (original_order) { flag: bool; u: U; count: long; }",
+        )
+        .unwrap();
+        let result = to_code(schema);
+        assert!(result.contains(
+            "let mut builder = SizedBuilder :: new (fbb) ; builder . add_count (args . count) ; if let Some (value) = args . u { builder . add_u (value) ; } builder . add_flag (args . flag) ; builder . add_u_type (args . u_type) ; builder . finish ()"
+        ));
+        assert!(result.contains(
+            "let mut builder = OrderedBuilder :: new (fbb) ; builder . add_count (args . count) ; if let Some (value) = args . u { builder . add_u (value) ; } builder . add_u_type (args . u_type) ; builder . add_flag (args . flag) ; builder . finish ()"
+        ));
+    }
+
+    #[test]
+    fn test_struct_field_copies() {
+        let schema = crate::parser::parse_schema(
+            "struct Vec3 { x: float; y: float; z: float; }
+             table Body { position: Vec3; origin: Vec3 (required); mass: float; }",
+        )
+        .unwrap();
+        let result = to_code(schema);
+        assert!(result.contains(
+            "pub fn position_copied (& self) -> butte :: Result < Option < Vec3 >> { self . table . get :: < butte :: Copied < Vec3 >> (Body :: VT_POSITION , None) }"
+        ));
+        assert!(result.contains(
+            "pub fn origin_copied (& self) -> butte :: Result < Vec3 > { self . table . get :: < butte :: Copied < Vec3 >> (Body :: VT_ORIGIN , None) ? . ok_or (butte :: Error :: MissingRequiredField { slot : Body :: VT_ORIGIN , }) }"
+        ));
+        assert!(!result.contains("mass_copied"));
+    }
+
+    #[test]
+    fn test_field_ranges() {
+        let schema = crate::parser::parse_schema(
+            "table A { x: int; }
+             union U { A }
+             table Message { id: int; body: [ubyte]; sender: A; u: U; old: int (deprecated); }",
+        )
+        .unwrap();
+        let result = to_code(schema);
+        assert!(result.contains("pub fn table_size (& self) -> butte :: Result < usize > { Ok (self . table . inline_range () ? . len ()) }"));
+        assert!(
+            result.contains("Message :: VT_ID => self . table . field_range :: < i32 > (slot) ,")
+        );
+        assert!(result.contains("Message :: VT_BODY => Ok (self . table . get :: < butte :: ForwardsUOffset < butte :: Vector < 'a , u8 > > > (slot , None) ? . map (| vector | vector . byte_range ())) ,"));
+        assert!(result.contains("Message :: VT_SENDER => self . table . get :: < butte :: ForwardsUOffset < butte :: Table < 'a >> > (slot , None) ? . map (| table | table . inline_range ()) . transpose () ,"));
+        assert!(result
+            .contains("Message :: VT_U_TYPE => self . table . field_range :: < UType > (slot) ,"));
+        assert!(!result.contains("Message :: VT_OLD =>"));
+        // the deprecated field and the union's type still count as known
+        assert!(result.contains("pub fn unknown_fields (& self) -> butte :: Result < butte :: UnknownFields < 'a >> { self . table . unknown_fields (6usize) }"));
+    }
+
+    #[test]
+    fn test_table_layout() {
+        let schema = crate::parser::parse_schema(
+            "struct Vec2 { x: float; y: float; }
+             table A { x: int; }
+             union U { A }
+             table Message {
+               pos: Vec2; name: string (offset64); tags: [string]; items: [A];
+               data: [ubyte] (vector64); u: U; us: [U]; next: Message; old: int (deprecated);
+             }",
+        )
+        .unwrap();
+        let result = to_code(schema);
+        assert!(result.contains("pub fn table_layout () -> & 'static butte :: TableLayout { static LAYOUT : butte :: TableLayout = butte :: TableLayout { fields : & [butte :: FieldLayout { slot : Message :: VT_POS , offset64 : false , value : butte :: ValueLayout :: Inline { size : 8usize , align : 4usize } , } ,"));
+        assert!(result.contains("slot : Message :: VT_NAME , offset64 : true , value : butte :: ValueLayout :: String ,"));
+        assert!(result.contains("value : butte :: ValueLayout :: Strings ,"));
+        assert!(result.contains("value : butte :: ValueLayout :: Tables (A :: table_layout) ,"));
+        assert!(result.contains("slot : Message :: VT_DATA , offset64 : false , value : butte :: ValueLayout :: Vector64 { size : 1usize , align : 1usize } ,"));
+        assert!(result.contains("value : butte :: ValueLayout :: Union (U :: table_layout) ,"));
+        assert!(result.contains("value : butte :: ValueLayout :: Unions (U :: table_layout) ,"));
+        assert!(
+            result.contains("value : butte :: ValueLayout :: Table (Message :: table_layout) ,")
+        );
+        assert!(!result.contains("slot : Message :: VT_OLD"));
+        assert!(result.contains("pub fn table_layout (ty : u8) -> Option < & 'static butte :: TableLayout > { match ty { 1 => Some (A :: table_layout ()) , _ => None , } }"));
+        assert!(result.contains(
+            "let offset = butte :: copy_table (fbb , & self . table , Self :: table_layout ()) ? ;"
+        ));
+        assert!(result.contains("impl butte :: DeepCopy for MessageOffset { fn copy_to < 'b > (table : & Message < '_ > , fbb : & mut butte :: FlatBufferBuilder < 'b > ,) -> butte :: Result < butte :: WIPOffset < Message < 'b >> > { table . copy_to (fbb) } }"));
+    }
+
+    #[test]
+    fn test_hashed_fields() {
+        let schema = crate::parser::parse_schema(
+            "table Weapon { id: uint (hash: \"fnv1a_32\"); key: long (hash: \"fnv1_64\"); }",
+        )
+        .unwrap();
+        let result = to_code(schema);
+        assert!(result.contains(
+            "pub const ID_HASH : butte :: HashAlgorithm = butte :: HashAlgorithm :: Fnv1a_32 ;"
+        ));
+        assert!(result.contains(
+            "pub fn add_id_hash (& mut self , id : & str) { self . add_id (butte :: hash :: fnv1a_32 (id . as_bytes ())) ; }"
+        ));
+        assert!(result
+            .contains("self . add_key (butte :: hash :: fnv1_64 (key . as_bytes ()) as i64) ;"));
+    }
+
+    #[test]
+    fn test_flexbuffer_accessor() {
+        let schema = crate::parser::parse_schema(
+            "table Event { metadata: [ubyte] (flexbuffer); payload: [ubyte] (flexbuffer, required); }",
+        )
+        .unwrap();
+        let result = to_code(schema);
+        assert!(result.contains(
+            "pub fn metadata_flexbuffer (& self) -> butte :: Result < Option < butte :: flexbuffers :: Reader < 'a >> >"
+        ));
+        assert!(result.contains(
+            "pub fn payload_flexbuffer (& self) -> butte :: Result < butte :: flexbuffers :: Reader < 'a >> { butte :: flexbuffers :: Reader :: get_root (self . payload () ? . safe_slice () ?) }"
+        ));
+    }
+
+    #[test]
+    fn test_visit_product_type_table_offset64() {
+        let schema = crate::parser::parse_schema(
+            "table Blob { name: string (offset64); data: [ubyte] (vector64); sizes: [int] (offset64); }",
+        )
+        .unwrap();
+        let result = to_code(schema);
+        assert!(result.contains(
+            "pub data : Option < butte :: WIPOffset64 < butte :: Vector64 < 'a , u8 > > >"
+        ));
+        assert!(result.contains(
+            "get :: < butte :: ForwardsUOffset64 < & '_ str > > (Blob :: VT_NAME , None)"
+        ));
+        assert!(
+            result.contains("get :: < butte :: ForwardsUOffset64 < butte :: Vector < '_ , i32 >")
+        );
+        assert!(result.contains(
+            ". get :: < butte :: ForwardsUOffset64 < butte :: FbString < 'a >> > (Blob :: VT_NAME , None) ?"
+        ));
+    }
+
+    #[test]
+    fn test_visit_product_type_table_required() {
+        let name = Field::builder()
+            .id(Ident::from("name"))
+            .ty(Type::String)
+            .metadata(Some(Metadata::from(vec![crate::meta!(required)])))
+            .build();
+        let table = table!(Named, [name, field!(nickname, String)]);
+        let result = to_code(table);
+        assert!(result.contains("pub name : butte :: WIPOffset < & 'a str >"));
+        assert!(result.contains("pub nickname : Option < butte :: WIPOffset < & 'a str > >"));
+        assert!(result.contains("self . fbb . required (o , Named :: VT_NAME , \"name\")"));
+        assert!(!result.contains("VT_NICKNAME , \"nickname\""));
+        assert!(result.contains("pub fn name (& self) -> butte :: Result < & 'a str >"));
+        assert!(result.contains("pub fn name_bytes (& self) -> butte :: Result < & 'a [u8] >"));
+        assert!(result
+            .contains("pub fn nickname_bytes (& self) -> butte :: Result < Option < & 'a [u8] >>"));
+        assert!(result.contains(
+            ". get :: < butte :: ForwardsUOffset < butte :: FbString < 'a >> > (Named :: VT_NICKNAME , None) ?"
+        ));
+        assert!(result.contains(
+            "pub fn has_nickname (& self) -> butte :: Result < bool > { self . table . is_present (Named :: VT_NICKNAME) }"
+        ));
+        assert!(!result.contains("fn has_name"));
+        assert!(!result.contains("Default for NamedArgs"));
+        assert!(result.contains("impl butte :: Verify for Named < '_ >"));
+        assert!(result.contains("let table = verifier . enter_table (loc) ? ;"));
+        assert!(result.contains("verifier . exit_table () ; Ok (())"));
+        assert!(result.contains(
+            "table . verify_field :: < butte :: ForwardsUOffset < & '_ str > > (verifier , Named :: VT_NAME , true) ? ;"
+        ));
+        assert!(result.contains(
+            "table . verify_field :: < butte :: ForwardsUOffset < & '_ str > > (verifier , Named :: VT_NICKNAME , false) ? ;"
+        ));
+    }
+
+    #[test]
+    fn test_visit_product_type_table_deprecated() {
+        let name = Field::builder()
+            .id(Ident::from("name"))
+            .ty(Type::String)
+            .metadata(Some(Metadata::from(vec![crate::meta!(deprecated)])))
+            .build();
+        let table = table!(Legacy, [name, field!(hp, Int16)]);
+        let result = to_code(table);
+        assert!(result.contains("pub const VT_NAME : butte :: VOffsetT = 4"));
+        assert!(result.contains("pub const VT_HP : butte :: VOffsetT = 6"));
+        assert!(result.contains("# [deprecated] # [inline] pub fn name (& self)"));
+        assert!(!result.contains("pub name :"));
+        assert!(!result.contains("fn add_name"));
+        assert!(!result.contains("LegacyArgs < 'a >"));
+        assert!(result.contains("match self . hp () { Ok (value) => ds . field (\"hp\" , & value)"));
+        assert!(!result.contains("ds . field (\"name\""));
+        assert!(!result.contains("(Legacy :: VT_NAME , false)"));
+        assert!(result
+            .contains("fn eq (& self , other : & Self) -> bool { self . hp () == other . hp () }"));
+    }
+
+    #[test]
+    fn test_visit_product_type_table_field_ids() {
+        let elements = crate::parser::parse_schema(
+            "union Any { T } table T { a: int (id: 3); b: Any (id: 1); c: short (id: 2); }",
+        )
+        .unwrap()
+        .elements;
+        let result = generate(
+            &Schema::builder().elements(elements).build(),
+            &Options::default(),
+        )
+        .to_string();
+        assert!(result.contains("pub const VT_B_TYPE : butte :: VOffsetT = 4i16"));
+        assert!(result.contains("pub const VT_B : butte :: VOffsetT = 6i16"));
+        assert!(result.contains("pub const VT_C : butte :: VOffsetT = 8i16"));
+        assert!(result.contains("pub const VT_A : butte :: VOffsetT = 10i16"));
+    }
+
+    #[test]
+    fn test_visit_product_type_table_enum_default() {
+        let color = Type::Ident(vec![Ident::from("Color")].into());
+        let primary = Field::builder()
+            .id(Ident::from("primary"))
+            .ty(color.clone())
+            .default(Some(DefaultValue::EnumVal(Ident::from("Green"))))
+            .build();
+        let secondary = Field::builder()
+            .id(Ident::from("secondary"))
+            .ty(color)
+            .default(Some(DefaultValue::from(Scalar::Integer(3))))
+            .build();
+        let opacity = Field::builder()
+            .id(Ident::from("opacity"))
+            .ty(Type::UByte)
+            .default(Some(DefaultValue::Null))
+            .build();
+        let elements = vec![
+            Element::from(enum_!(Color, Byte, [e_item!(Red), e_item!(Green = 3)])),
+            Element::from(table!(Paint, [primary, secondary, opacity])),
+        ];
+        let result = to_code(Schema::builder().elements(elements).build());
+        assert!(result
+            .contains("push_slot :: < Color > (Paint :: VT_PRIMARY , primary , Color :: Green)"));
+        assert!(result.contains(
+            "push_slot :: < Color > (Paint :: VT_SECONDARY , secondary , Color :: Green)"
+        ));
+        assert!(result.contains(". unwrap_or (Color :: Green)"));
+        assert!(result.contains("pub opacity : Option < u8 >"));
+        assert!(result.contains("pub fn opacity (& self) -> butte :: Result < Option < u8 >>"));
+    }
+
+    #[test]
+    fn test_visit_product_type_table_args_default() {
+        let schema = crate::parser::parse_schema(
+            "enum Color : byte { Red = -1, Green = 0, Blue = 1 }
+             enum Size : ubyte { Small = 1, Large = 2 }
+             union Any { Monster }
+             struct Vec3 { x: float; }
+             table Monster {
+               hp: short = 100;
+               mana: short;
+               color: Color;
+               size: Size;
+               scale: float = 1.5;
+               opacity: ubyte = null;
+               name: string;
+               pos: Vec3;
+               friend: Any;
+               friends: [Any];
+             }",
+        )
+        .unwrap();
+        let result = generate(&schema, &Options::default()).to_string();
+        assert!(result.contains(
+            "impl < 'a > core :: default :: Default for MonsterArgs < 'a > { # [inline] fn default () -> Self { Self { \
+             hp : 100 , mana : 0 , color : Color :: Green , \
+             size : Size :: Small , scale : 1.5 , opacity : None , name : None , pos : None , \
+             friend_type : AnyType :: NONE , friend : None , friends_type : None , friends : None } } }"
+        ));
+    }
+}
+
+impl ToTokens for Type<'_> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        match self {
+            Type::Bool => quote!(bool),
+            Type::Byte => quote!(i8),
+            Type::UByte => quote!(u8),
+            Type::Short => quote!(i16),
+            Type::UShort => quote!(u16),
+            Type::Int => quote!(i32),
+            Type::UInt => quote!(u32),
+            Type::Float => quote!(f32),
+            Type::Long => quote!(i64),
+            Type::ULong => quote!(u64),
+            Type::Double => quote!(f64),
+            Type::Int8 => quote!(i8),
+            Type::UInt8 => quote!(u8),
+            Type::Int16 => quote!(i16),
+            Type::UInt16 => quote!(u16),
+            Type::Int32 => quote!(i32),
+            Type::UInt32 => quote!(u32),
+            Type::Int64 => quote!(i64),
+            Type::UInt64 => quote!(u64),
+            Type::Float32 => quote!(f32),
+            Type::Float64 => quote!(f64),
+            Type::String => quote!(String),
+            Type::Array(ty) => quote!(Vec<#ty>),
+            Type::FixedArray(ty, len) => {
+                let len = Literal::i128_unsuffixed(*len);
+                quote!([#ty; #len])
+            }
+            Type::Ident(id) => quote!(#id),
+        }
+        .to_tokens(tokens)
+    }
+}
+
+#[cfg(test)]
+mod type_tests {
+    use super::*;
+
+    #[test]
+    fn test_visit_type() {
+        let result = to_code(Type::Bool);
+        let expected = "bool";
+        assert_eq!(result, expected);
+
+        let result = to_code(Type::Array(Box::new(Type::String)));
+        let expected = "Vec < String >";
+        assert_eq!(result, expected);
+
+        let result = to_code(Type::Ident(DottedIdent::from(vec!["MyType".into()])));
+        let expected = "MyType";
+        assert_eq!(result, expected);
+
+        let result = to_code(Type::FixedArray(Box::new(Type::Float), 4));
+        let expected = "[f32 ; 4]";
+        assert_eq!(result, expected);
+    }
+}
+
+// TODO: Properly implement this.
+// We only generate a trait method right now.
+// TODO: Figure out how this will integrate into tonic.
+impl ToTokens for Scoped<'_, '_, RpcMethod<'_>> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let Self { item, scope } = self;
+        let RpcMethod {
+            id,
+            request_type,
+            response_type,
+            doc,
+            ..
+        } = item;
+        let snake_name = ident(id.raw.to_snake_case());
+        let request_type = scope.type_path(&Type::Ident(request_type.clone()));
+        let response_type = scope.type_path(&Type::Ident(response_type.clone()));
+        let streaming = item.streaming().expect("invalid streaming mode");
+        let request = match streaming {
+            Streaming::Client | Streaming::Bidi => {
+                quote!(requests: impl Iterator<Item = #request_type<'a>>)
+            }
+            Streaming::None | Streaming::Server => quote!(request: #request_type<'a>),
+        };
+        let response = match streaming {
+            Streaming::Server | Streaming::Bidi => {
+                quote!(impl Iterator<Item = #response_type<'a>>)
+            }
+            Streaming::None | Streaming::Client => quote!(#response_type<'a>),
+        };
+        (quote! {
+            #doc
+            fn #snake_name<'a>(#request) -> #response;
+        })
+        .to_tokens(tokens)
+    }
+}
+
+impl ToTokens for Comment<'_> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let doc = self.lines.iter().rev().fold(quote!(), |docs, line| {
+            quote! {
+                #[doc = #line]
+                #docs
+            }
+        });
+        doc.to_tokens(tokens)
+    }
+}
+
+/// A dotted identifier is written as the path it spells out, e.g., `a::b::C`
+/// for `a.b.C`. References to declared types are resolved against the
+/// namespace they are made from instead, see `Scope::type_path`.
+impl ToTokens for DottedIdent<'_> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let parts = &self.parts;
+        quote!(#(#parts)::*).to_tokens(tokens)
+    }
+}
+
+#[cfg(test)]
+mod dotted_ident_tests {
+    use super::*;
+
+    #[test]
+    fn test_visit_dotted_ident() {
+        let id = DottedIdent::from(vec![Ident::from("a"), Ident::from("b"), Ident::from("C")]);
+        assert_eq!(to_code(id), "a :: b :: C");
+        assert_eq!(to_code(DottedIdent::from(vec![Ident::from("C")])), "C");
+    }
+}
+
+/// Services generate a trait with a method per RPC method, where streamed
+/// messages are passed as iterators. With the `tonic` feature, an async gRPC
+/// client and server are generated as well.
+impl ToTokens for Scoped<'_, '_, Rpc<'_>> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let Self { item, scope } = self;
+        let Rpc {
+            id: Ident { raw },
+            methods,
+            doc,
+        } = item;
+        let service_name = format_ident!("{}Service", raw);
+        let scope = *scope;
+        let methods = methods.iter().map(|method| {
+            let attributes = rpc_method_attributes(item, method, scope);
+            let method = Scoped {
+                item: method,
+                scope,
+            };
+            quote!(#attributes #method)
+        });
+        let vis = &scope.options.visibility;
+        (quote! {
+            #doc
+            #vis trait #service_name {
+                #(#methods)*
+            }
+        })
+        .to_tokens(tokens);
+
+        #[cfg(feature = "tonic")]
+        tonic::service(item, scope).to_tokens(tokens);
+    }
+}
+
+/// The attributes of the methods generated for `method` of `rpc`: whether
+/// it's deprecated, followed by those of the RPC method hooks.
+fn rpc_method_attributes(rpc: &Rpc, method: &RpcMethod, scope: Scope) -> TokenStream {
+    let deprecated = if method.is_deprecated() {
+        quote!(#[deprecated])
+    } else {
+        quote!()
+    };
+    let hooks = scope
+        .options
+        .rpc_method_hooks
+        .iter()
+        .map(|hook| hook.attributes(rpc, method));
+    quote! {
+        #deprecated
+        #(#hooks)*
+    }
+}
+
+impl ToTokens for Rpc<'_> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let symbols = SymbolTable::default();
+        let scope = Scope {
+            symbols: &symbols,
+            namespace: &[],
+            options: &Options::default(),
+        };
+        Scoped { item: self, scope }.to_tokens(tokens)
+    }
+}
+
+#[cfg(test)]
+mod rpc_tests {
+    use super::*;
+    use crate::{meta, method, rpc};
+
+    #[test]
+    fn test_streaming_methods() {
+        let elements = vec![
+            Element::from(table!(Request, [field!(x, Int)])),
+            Element::from(table!(Response, [field!(x, Int)])),
+            Element::from(rpc!(
+                Greeter,
+                [
+                    method!(fn Unary(Request) -> Response),
+                    method!(fn Client(Request) -> Response, [meta!(streaming, "client")]),
+                    method!(fn Server(Request) -> Response, [meta!(streaming, "server")]),
+                    method!(fn Bidi(Request) -> Response, [meta!(streaming, "bidi")])
+                ]
+            )),
+        ];
+        let schema = Schema::builder().elements(elements).build();
+        let result = to_code(schema);
+        assert!(result.contains("fn unary < 'a > (request : Request < 'a >) -> Response < 'a > ;"));
+        assert!(result.contains(
+            "fn client < 'a > (requests : impl Iterator < Item = Request < 'a >>) -> Response < 'a > ;"
+        ));
+        assert!(result.contains(
+            "fn server < 'a > (request : Request < 'a >) -> impl Iterator < Item = Response < 'a >> ;"
+        ));
+        assert!(result.contains(
+            "fn bidi < 'a > (requests : impl Iterator < Item = Request < 'a >>) -> impl Iterator < Item = Response < 'a >> ;"
+        ));
+    }
+
+    /// Marks the methods with a `traced` attribute, taking the span's name
+    /// from it.
+    struct Traced;
+
+    impl RpcMethodHook for Traced {
+        fn attributes(&self, rpc: &Rpc, method: &RpcMethod) -> TokenStream {
+            match method.attribute("traced") {
+                Some(Some(Single::String(name))) => {
+                    let name = format!("{}.{}", rpc.id.raw, name);
+                    quote!(#[doc = #name])
+                }
+                _ => quote!(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_method_attributes() {
+        let elements = vec![
+            Element::from(table!(Request, [field!(x, Int)])),
+            Element::from(table!(Response, [field!(x, Int)])),
+            Element::from(rpc!(
+                Greeter,
+                [
+                    method!(fn Old(Request) -> Response, [meta!(deprecated)]),
+                    method!(fn New(Request) -> Response, [meta!(traced, "new")])
+                ]
+            )),
+        ];
+        let schema = Schema::builder().elements(elements).build();
+        let options = Options {
+            rpc_method_hooks: vec![Arc::new(Traced)],
+            ..Options::default()
+        };
+        let result = generate(&schema, &options).to_string();
+        assert!(result.contains(
+            "# [deprecated] fn old < 'a > (request : Request < 'a >) -> Response < 'a > ;"
+        ));
+        assert!(result.contains(
+            "# [doc = \"Greeter.new\"] fn new < 'a > (request : Request < 'a >) -> Response < 'a > ;"
+        ));
+    }
+}
+
+/// An integer literal suffixed with `base_type`, e.g., `255_u8`. Values are
+/// parsed as `i128`, so every value of a `ulong` or `long` is written as is.
+fn lit_int(value: IntegerConstant, base_type: impl Spanned + Display) -> impl ToTokens {
+    let stringified_int = format!("{}_{}", value, base_type);
+    syn::LitInt::new(&stringified_int, base_type.span())
+}
+
+/// Generate a C-style enum along with the impls that let it be read from and
+/// written to a buffer.
+fn c_style_enum(
+    enum_id: &proc_macro2::Ident,
+    values: &[(proc_macro2::Ident, IntegerConstant)],
+    value_docs: &[&Comment],
+    base_type: &Type,
+    doc: &Comment,
+    derives: &[syn::Path],
+    vis: &Visibility,
+) -> TokenStream {
+    let derives = self::derives(
+        &[
+            "Clone",
+            "Copy",
+            "PartialEq",
+            "Eq",
+            "PartialOrd",
+            "Ord",
+            "Hash",
+            "Debug",
+        ],
+        derives,
+    );
+
+    // generate enum variant name => string name of the variant for use in
+    // a match statement
+    let names_to_strings = values.iter().map(|(key, _)| {
+        let raw_key = key.unraw().to_string();
+        quote! {
+            #enum_id::#key => #raw_key
+        }
+    });
+
+    let fields = values.iter().zip(value_docs).map(|((key, value), doc)| {
+        // format the value with the correct type, i.e., base_type
+        let scalar_value = lit_int(*value, base_type.to_token_stream());
+        quote! {
+            #doc
+            #key = #scalar_value
+        }
+    });
+
+    let values_to_variants = values.iter().map(|(key, value)| {
+        let scalar_value = lit_int(*value, base_type.to_token_stream());
+        quote! {
+            #scalar_value => Ok(#enum_id::#key)
+        }
+    });
+
+    let raw_snake_enum_name = enum_id.to_string().to_snake_case();
+    let enum_id_fn_name = format_ident!("enum_name_{}", raw_snake_enum_name);
+
+    // Like flatc, the range and the list of the values are also constants.
+    let shouty_enum_name = enum_id.to_string().to_shouty_snake_case();
+    let min_id = format_ident!("ENUM_MIN_{}", shouty_enum_name);
+    let max_id = format_ident!("ENUM_MAX_{}", shouty_enum_name);
+    let values_id = format_ident!("ENUM_VALUES_{}", shouty_enum_name);
+    let min = values.iter().map(|(_, value)| *value).min().unwrap_or(0);
+    let max = values.iter().map(|(_, value)| *value).max().unwrap_or(0);
+    let min = lit_int(min, base_type.to_token_stream());
+    let max = lit_int(max, base_type.to_token_stream());
+    let num_values = Literal::usize_unsuffixed(values.len());
+    let keys = values.iter().map(|(key, _)| key);
+
+    #[cfg(feature = "serde")]
+    let serialize = serde::enum_(enum_id, values, &enum_id_fn_name);
+    #[cfg(not(feature = "serde"))]
+    let serialize = quote!();
+
+    // TODO: Maybe separate these pieces to avoid variables that used far
+    // away from their definition.
+    quote! {
+        // force a C-style enum
+        #[repr(#base_type)]
+        #[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+        #derives
+        #doc
+        #vis enum #enum_id {
+            #(#fields),*
+        }
+
+        // The value is read as the base type and checked before it becomes
+        // an enum, as a buffer may hold any bit pattern.
+        impl<'a> butte::Follow<'a> for #enum_id {
+            type Inner = Self;
+
+            #[inline]
+            fn follow(buf: &'a [u8], loc: usize) -> butte::Result<Self::Inner> {
+                let value = <#base_type as butte::Follow>::follow(buf, loc)?;
+                Ok(<Self as core::convert::TryFrom<#base_type>>::try_from(value)?)
+            }
+        }
+
+        // Any value of the base type can be verified, unknown values are only
+        // rejected when they are read, unless verification is strict.
+        impl butte::Verify for #enum_id {
+            #[inline]
+            fn verify(verifier: &mut butte::Verifier<'_>, loc: usize) -> butte::Result<()> {
+                <#base_type as butte::Verify>::verify(verifier, loc)?;
+                if verifier.options().strict_enums {
+                    <Self as butte::Follow>::follow(verifier.buf(), loc)?;
+                }
+                Ok(())
+            }
+        }
+
+        impl butte::Push for #enum_id {
+            type Output = Self;
+
+            #[inline]
+            fn push(&self, dst: &mut [u8], _rest: &[u8]) {
+                butte::emplace_scalar::<#base_type>(dst, #base_type::from(*self));
+            }
+        }
+
+        impl core::convert::TryFrom<#base_type> for #enum_id {
+            type Error = butte::UnknownEnumValue<#base_type>;
+
+            #[inline]
+            fn try_from(value: #base_type) -> Result<Self, Self::Error> {
+                match value {
+                    #(#values_to_variants,)*
+                    _ => Err(butte::UnknownEnumValue(value)),
+                }
+            }
+        }
+
+        impl From<#enum_id> for #base_type {
+            #[inline]
+            fn from(value: #enum_id) -> Self {
+                value as #base_type
+            }
+        }
+
+        #[inline]
+        #[must_use]
+        #vis fn #enum_id_fn_name(e: #enum_id) -> &'static str {
+            match e {
+                #(#names_to_strings),*
+            }
+        }
+
+        #vis const #min_id: #base_type = #min;
+        #vis const #max_id: #base_type = #max;
+        #vis const #values_id: [#enum_id; #num_values] = [#(#enum_id::#keys),*];
+
+        impl #enum_id {
+            /// Iterate over the values of the enum, in declaration order.
+            #[inline]
+            pub fn variants() -> impl Iterator<Item = Self> {
+                #values_id.iter().copied()
+            }
+        }
+
+        #serialize
+    }
+}
+
+/// The vtable slot of each field of a table: the one given by its `id`
+/// attribute, or the next free one in declaration order otherwise.
+///
+/// A union field takes up two slots, its discriminant's and then its value's;
+/// the slot of its value is returned. `is_union` tells which fields are
+/// unions. The ids are assumed to have been validated.
+pub(crate) fn field_slots(fields: &[Field], is_union: impl Fn(&Type) -> bool) -> Vec<VOffsetT> {
+    let mut next = 0;
+    fields
+        .iter()
+        .map(|field| match field.id_attribute() {
+            Some(id) => id as VOffsetT,
+            None => {
+                next += if is_union(&field.ty) { 2 } else { 1 };
+                next - 1
+            }
+        })
+        .collect()
+}
+
+/// The discriminant of each value of an enum: the one given in the schema, or
+/// the value's index otherwise.
+pub(crate) fn enum_discriminants<'a>(values: &[EnumVal<'a>]) -> Vec<(Ident<'a>, IntegerConstant)> {
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, EnumVal { id, value, .. })| {
+            let value = value
+                .unwrap_or_else(|| i.try_into().expect("invalid conversion to enum base type"));
+            (*id, value)
+        })
+        .collect()
+}
+
+/// The value each value of an enum is stored as: its discriminant, or the flag
+/// `1 << discriminant` if the enum has the `bit_flags` attribute.
+pub(crate) fn enum_values<'a>(e: &Enum<'a>) -> Vec<(Ident<'a>, IntegerConstant)> {
+    let values = enum_discriminants(&e.values);
+    if !e.is_bit_flags() {
+        return values;
+    }
+    values
+        .into_iter()
+        .map(|(id, bit)| {
+            (
+                id,
+                1u64.checked_shl(bit as u32).unwrap_or(0) as IntegerConstant,
+            )
+        })
+        .collect()
+}
+
+/// Generate a bitflags-style struct for an enum with the `bit_flags`
+/// attribute, with a constant for each flag and the set operations on flags.
+fn bit_flags(
+    enum_id: &proc_macro2::Ident,
+    values: &[(proc_macro2::Ident, IntegerConstant)],
+    value_docs: &[&Comment],
+    base_type: &Type,
+    doc: &Comment,
+    derives: &[syn::Path],
+    vis: &Visibility,
+) -> TokenStream {
+    let derives = self::derives(
+        &[
+            "Clone",
+            "Copy",
+            "PartialEq",
+            "Eq",
+            "PartialOrd",
+            "Ord",
+            "Hash",
+            "Default",
+        ],
+        derives,
+    );
+    let keys = values.iter().map(|(key, _)| key).collect::<Vec<_>>();
+    let names = values.iter().map(|(key, _)| key.to_string());
+    let all = if keys.is_empty() {
+        quote!(0)
+    } else {
+        quote!(#(Self::#keys.0)|*)
+    };
+    let consts = values.iter().zip(value_docs).map(|((key, bit), doc)| {
+        let bit = Literal::i128_unsuffixed(*bit);
+        quote! {
+            #doc
+            pub const #key: Self = Self(1 << #bit);
+        }
+    });
+
+    #[cfg(feature = "serde")]
+    let serialize = serde::bit_flags(enum_id, values);
+    #[cfg(not(feature = "serde"))]
+    let serialize = quote!();
+
+    quote! {
+        #[repr(transparent)]
+        #derives
+        #doc
+        #vis struct #enum_id(#base_type);
+
+        #[allow(non_upper_case_globals)]
+        impl #enum_id {
+            #(#consts)*
+
+            /// No flags set.
+            #[inline]
+            #[must_use]
+            pub const fn empty() -> Self {
+                Self(0)
+            }
+
+            /// All of the flags declared in the schema set.
+            #[inline]
+            #[must_use]
+            pub const fn all() -> Self {
+                Self(#all)
+            }
+
+            /// The flags as an integer.
+            #[inline]
+            #[must_use]
+            pub const fn bits(self) -> #base_type {
+                self.0
+            }
+
+            /// The flags in `bits`, or `None` if a bit that isn't a flag is set.
+            #[inline]
+            #[must_use]
+            pub const fn from_bits(bits: #base_type) -> Option<Self> {
+                if bits & !Self::all().0 == 0 {
+                    Some(Self(bits))
+                } else {
+                    None
+                }
+            }
+
+            /// The flags in `bits`, ignoring the bits that aren't flags.
+            #[inline]
+            #[must_use]
+            pub const fn from_bits_truncate(bits: #base_type) -> Self {
+                Self(bits & Self::all().0)
+            }
+
+            /// Check whether no flags are set.
+            #[inline]
+            #[must_use]
+            pub const fn is_empty(self) -> bool {
+                self.0 == 0
+            }
+
+            /// Check whether all of the flags in `other` are set.
+            #[inline]
+            #[must_use]
+            pub const fn contains(self, other: Self) -> bool {
+                self.0 & other.0 == other.0
+            }
+
+            /// Set the flags in `other`.
+            #[inline]
+            pub fn insert(&mut self, other: Self) {
+                self.0 |= other.0;
+            }
+
+            /// Clear the flags in `other`.
+            #[inline]
+            pub fn remove(&mut self, other: Self) {
+                self.0 &= !other.0;
+            }
+        }
+
+        impl core::ops::BitOr for #enum_id {
+            type Output = Self;
+
+            #[inline]
+
+            fn bitor(self, other: Self) -> Self {
+                Self(self.0 | other.0)
+            }
+        }
+
+        impl core::ops::BitOrAssign for #enum_id {
+            #[inline]
+            fn bitor_assign(&mut self, other: Self) {
+                self.0 |= other.0;
+            }
+        }
+
+        impl core::ops::BitAnd for #enum_id {
+            type Output = Self;
+
+            #[inline]
+
+            fn bitand(self, other: Self) -> Self {
+                Self(self.0 & other.0)
+            }
+        }
+
+        impl core::ops::BitAndAssign for #enum_id {
+            #[inline]
+            fn bitand_assign(&mut self, other: Self) {
+                self.0 &= other.0;
+            }
+        }
+
+        impl core::ops::BitXor for #enum_id {
+            type Output = Self;
+
+            #[inline]
+
+            fn bitxor(self, other: Self) -> Self {
+                Self(self.0 ^ other.0)
+            }
+        }
+
+        impl core::ops::BitXorAssign for #enum_id {
+            #[inline]
+            fn bitxor_assign(&mut self, other: Self) {
+                self.0 ^= other.0;
+            }
+        }
+
+        impl core::ops::Not for #enum_id {
+            type Output = Self;
+
+            #[inline]
+
+            fn not(self) -> Self {
+                Self::from_bits_truncate(!self.0)
+            }
+        }
+
+        impl core::fmt::Debug for #enum_id {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                let mut rest = self.0;
+                let mut first = true;
+                write!(f, "{}(", stringify!(#enum_id))?;
+                #(
+                    if self.contains(Self::#keys) {
+                        if !first {
+                            f.write_str(" | ")?;
+                        }
+                        first = false;
+                        f.write_str(#names)?;
+                        rest &= !Self::#keys.0;
+                    }
+                )*
+                if rest != 0 || first {
+                    if !first {
+                        f.write_str(" | ")?;
+                    }
+                    write!(f, "{:#x}", rest)?;
+                }
+                f.write_str(")")
+            }
+        }
+
+        impl<'a> butte::Follow<'a> for #enum_id {
+            type Inner = Self;
+
+            #[inline]
+            fn follow(buf: &'a [u8], loc: usize) -> butte::Result<Self::Inner> {
+                <#base_type as butte::Follow>::follow(buf, loc).map(Self)
+            }
+        }
+
+        // Bits that aren't flags are kept when they are read, and only
+        // rejected when verification is strict.
+        impl butte::Verify for #enum_id {
+            #[inline]
+            fn verify(verifier: &mut butte::Verifier<'_>, loc: usize) -> butte::Result<()> {
+                <#base_type as butte::Verify>::verify(verifier, loc)?;
+                if verifier.options().strict_enums {
+                    let bits = <#base_type as butte::Follow>::follow(verifier.buf(), loc)?;
+                    Self::from_bits(bits).ok_or(butte::Error::UnknownEnumValue)?;
+                }
+                Ok(())
+            }
+        }
+
+        impl butte::EndianScalar for #enum_id {
+            #[inline]
+            fn to_little_endian(self) -> Self {
+                Self(#base_type::to_le(self.0))
+            }
+
+            #[inline]
+            fn from_little_endian(self) -> Self {
+                Self(#base_type::from_le(self.0))
+            }
+
+            #[inline]
+            fn read_le(bytes: &[u8]) -> Self {
+                Self(<#base_type as butte::EndianScalar>::read_le(bytes))
+            }
+
+            #[inline]
+            fn write_le(self, dst: &mut [u8]) {
+                butte::EndianScalar::write_le(self.0, dst)
+            }
+        }
+
+        impl butte::Push for #enum_id {
+            type Output = Self;
+
+            #[inline]
+            fn push(&self, dst: &mut [u8], _rest: &[u8]) {
+                butte::emplace_scalar::<Self>(dst, *self);
+            }
+        }
+
+        #serialize
+    }
+}
+
+impl ToTokens for Scoped<'_, '_, Enum<'_>> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let Self { item, scope } = self;
+        let Enum {
+            id: enum_id,
+            values,
+            base_type,
+            doc,
+            ..
+        } = item;
+
+        let values = enum_discriminants(values)
+            .into_iter()
+            .map(|(key, value)| (ident(key.raw), value))
+            .collect::<Vec<_>>();
+        let value_docs = item
+            .values
+            .iter()
+            .map(|value| &value.doc)
+            .collect::<Vec<_>>();
+
+        if item.is_bit_flags() {
+            return bit_flags(
+                &ident(enum_id.raw),
+                &values,
+                &value_docs,
+                base_type,
+                doc,
+                &scope.options.derives,
+                &scope.options.visibility,
+            )
+            .to_tokens(tokens);
+        }
+
+        c_style_enum(
+            &ident(enum_id.raw),
+            &values,
+            &value_docs,
+            base_type,
+            doc,
+            &scope.options.derives,
+            &scope.options.visibility,
+        )
+        .to_tokens(tokens)
+    }
+}
+
+impl ToTokens for Enum<'_> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let symbols = SymbolTable::default();
+        let scope = Scope {
+            symbols: &symbols,
+            namespace: &[],
+            options: &Options::default(),
+        };
+        Scoped { item: self, scope }.to_tokens(tokens)
+    }
+}
+
+/// The discriminant of each value of a union: the one given in the schema, or
+/// one more than the previous value's otherwise, like flatc numbers them. The
+/// first value defaults to 1, as 0 is the discriminant of `NONE`.
+pub(crate) fn union_discriminants<'a>(values: &[EnumVal<'a>]) -> Vec<(Ident<'a>, IntegerConstant)> {
+    let mut next = 1;
+    values
+        .iter()
+        .map(|EnumVal { id, value, .. }| {
+            let value = value.unwrap_or(next);
+            next = value + 1;
+            (*id, value)
+        })
+        .collect()
+}
+
+/// Unions generate a discriminant enum, named after the union with a `Type`
+/// suffix. Its `NONE` variant marks the absence of a value, the others name
+/// the table stored in the union.
+impl ToTokens for Scoped<'_, '_, Union<'_>> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let Self { item, scope } = self;
+        let Union {
+            id, values, doc, ..
+        } = item;
+
+        let values = std::iter::once((format_ident!("NONE"), 0))
+            .chain(
+                union_discriminants(values)
+                    .into_iter()
+                    .map(|(key, value)| (ident(key.raw), value)),
+            )
+            .collect::<Vec<_>>();
+        let none_doc = Comment::default();
+        let value_docs = std::iter::once(&none_doc)
+            .chain(item.values.iter().map(|value| &value.doc))
+            .collect::<Vec<_>>();
+
+        c_style_enum(
+            &union_type_id(id),
+            &values,
+            &value_docs,
+            &Type::UByte,
+            doc,
+            &scope.options.derives,
+            &scope.options.visibility,
+        )
+        .to_tokens(tokens);
+
+        union_reader(item, *scope).to_tokens(tokens);
+
+        if scope.options.object_api {
+            object_api::union(item, *scope).to_tokens(tokens);
+            #[cfg(feature = "serde")]
+            serde::union_object(item).to_tokens(tokens);
+        }
+    }
+}
+
+/// Generate the reader of `union`, named after it: an enum with a variant
+/// holding the table of each of its types, which union fields and vectors of
+/// unions are read as.
+fn union_reader(union: &Union, scope: Scope) -> TokenStream {
+    let Union { id, values, .. } = union;
+    let reader_id = ident(id.raw);
+    let vis = &scope.options.visibility;
+    let union_type = union_type_id(id);
+    // The reader is generated in the namespace of the union, which its
+    // variants are resolved relative to.
+    let variant_paths = values
+        .iter()
+        .map(|value| {
+            let ty = Type::Ident(value.union_type());
+            (&value.id, scope.type_path(&ty))
+        })
+        .collect::<Vec<_>>();
+    let variants = variant_paths
+        .iter()
+        .map(|(variant, path)| quote!(#variant(#path<'a>)));
+    let from_table = variant_paths.iter().map(
+        |(variant, path)| quote!(#union_type::#variant => #reader_id::#variant(#path::from(table))),
+    );
+    let verify = variant_paths.iter().map(
+        |(variant, path)| quote!(#union_type::#variant => <#path<'_> as butte::Verify>::verify(verifier, loc)),
+    );
+    let doc = format!(" A value of the `{}` union read from a buffer.", id.raw);
+
+    #[cfg(feature = "serde")]
+    let serialize = {
+        let variants = values.iter().map(|EnumVal { id: variant, .. }| variant);
+        quote! {
+            impl serde::Serialize for #reader_id<'_> {
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where
+                    S: serde::Serializer,
+                {
+                    match self {
+                        #reader_id::NONE => serializer.serialize_none(),
+                        #(#reader_id::#variants(value) => value.serialize(serializer),)*
+                    }
+                }
+            }
+        }
+    };
+    #[cfg(not(feature = "serde"))]
+    let serialize = quote!();
+
+    let layouts = union_discriminants(values)
+        .into_iter()
+        .zip(&variant_paths)
+        .map(|((_, value), (_, path))| {
+            let value = Literal::u8_unsuffixed(value as u8);
+            quote!(#value => Some(#path::table_layout()),)
+        });
+
+    quote! {
+        #[doc = #doc]
+        #[derive(Copy, Clone, Debug, PartialEq)]
+        #[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+        #vis enum #reader_id<'a> {
+            NONE,
+            #(#variants),*
+        }
+
+        impl #reader_id<'_> {
+            /// How the table of the union's type `ty` is stored in a buffer,
+            /// see `butte::copy_table`.
+            pub fn table_layout(ty: u8) -> Option<&'static butte::TableLayout> {
+                match ty {
+                    #(#layouts)*
+                    _ => None,
+                }
+            }
+        }
+
+        impl<'a> butte::UnionReader<'a> for #reader_id<'a> {
+            type Type = #union_type;
+
+            #[inline]
+            fn from_table(ty: #union_type, table: butte::Table<'a>) -> Self {
+                match ty {
+                    #union_type::NONE => #reader_id::NONE,
+                    #(#from_table,)*
+                }
+            }
+
+            #[inline]
+            fn verify(ty: #union_type, verifier: &mut butte::Verifier<'_>, loc: usize) -> butte::Result<()> {
+                match ty {
+                    #union_type::NONE => Ok(()),
+                    #(#verify,)*
+                }
+            }
+        }
+
+        #serialize
+    }
+}
+
+impl ToTokens for Union<'_> {
     fn to_tokens(&self, tokens: &mut TokenStream) {
-        let Self {
-            id: Ident { raw },
-            methods,
-            doc,
-        } = self;
-        let service_name = format_ident!("{}Service", raw);
+        let symbols = SymbolTable::default();
+        let scope = Scope {
+            symbols: &symbols,
+            namespace: &[],
+            options: &Options::default(),
+        };
+        Scoped { item: self, scope }.to_tokens(tokens)
+    }
+}
+
+#[cfg(test)]
+mod sum_type_tests {
+    use super::*;
+    use crate::{e_item, enum_, union};
+
+    #[test]
+    fn test_enum_extremes() {
+        let e = enum_!(
+            Mask,
+            ULong,
+            [e_item!(Low = 0), e_item!(High = u64::MAX.into())]
+        );
+        let result = to_code(e);
+        assert!(result.contains("pub enum Mask { Low = 0_u64 , High = 18446744073709551615_u64 }"));
+        assert!(result.contains("pub const ENUM_MAX_MASK : u64 = 18446744073709551615_u64 ;"));
+
+        let e = enum_!(
+            Offset,
+            Long,
+            [
+                e_item!(Min = i64::MIN.into()),
+                e_item!(Max = i64::MAX.into())
+            ]
+        );
+        let result = to_code(e);
+        assert!(result.contains(
+            "pub enum Offset { Min = - 9223372036854775808_i64 , Max = 9223372036854775807_i64 }"
+        ));
+    }
+
+    #[test]
+    fn test_visit_enum() {
+        let e = enum_!(Color, UByte, [e_item!(Red), e_item!(Blue = 3)]);
+        let result = to_code(e);
+        assert!(result.contains("pub enum Color { Red = 0_u8 , Blue = 3_u8 }"));
+        assert!(result.contains(
+            "impl core :: convert :: TryFrom < u8 > for Color { type Error = butte :: UnknownEnumValue < u8 > ;"
+        ));
+        assert!(result.contains(
+            "match value { 0_u8 => Ok (Color :: Red) , 3_u8 => Ok (Color :: Blue) , _ => Err (butte :: UnknownEnumValue (value)) , }"
+        ));
+        assert!(result.contains("impl From < Color > for u8"));
+        assert!(result
+            .contains("Ok (< Self as core :: convert :: TryFrom < u8 >> :: try_from (value) ?)"));
+        assert!(result.contains("butte :: emplace_scalar :: < u8 > (dst , u8 :: from (* self))"));
+        assert!(!result.contains("EndianScalar"));
+        assert!(result.contains("pub const ENUM_MIN_COLOR : u8 = 0_u8 ;"));
+        assert!(result.contains("pub const ENUM_MAX_COLOR : u8 = 3_u8 ;"));
+        assert!(result.contains(
+            "pub const ENUM_VALUES_COLOR : [Color ; 2] = [Color :: Red , Color :: Blue] ;"
+        ));
+        assert!(result.contains(
+            "pub fn variants () -> impl Iterator < Item = Self > { ENUM_VALUES_COLOR . iter () . copied () }"
+        ));
+    }
+
+    #[test]
+    fn test_visit_member_doc_comments() {
+        let schema = crate::parser::parse_schema(
+            "enum Color: ubyte {
+               /// Not blue.
+               Red,
+               Blue
+             }
+             table Pixel {
+               /// The color of the pixel.
+               color: Color;
+             }
+             union Any {
+               /// A pixel.
+               Pixel
+             }
+             rpc_service Painter {
+               /// Paint a pixel.
+               Paint(Pixel): Pixel;
+             }",
+        )
+        .unwrap();
+        let result = generate(&schema, &Options::default()).to_string();
+        assert!(
+            result.contains("pub enum Color { # [doc = \" Not blue.\"] Red = 0_u8 , Blue = 1_u8 }")
+        );
+        assert!(result
+            .contains("# [doc = \" The color of the pixel.\"] # [inline] pub fn color (& self)"));
+        assert!(result.contains("NONE = 0_u8 , # [doc = \" A pixel.\"] Pixel = 1_u8"));
+        assert!(result.contains("# [doc = \" Paint a pixel.\"] fn paint < 'a >"));
+    }
+
+    #[test]
+    fn test_visit_struct_with_enum() {
+        let schema = crate::parser::parse_schema(
+            "enum Color: short { Red, Blue }
+             struct Pixel { color: Color; }",
+        )
+        .unwrap();
+        let result = generate(&schema, &Options::default()).to_string();
+        assert!(result.contains("pub struct Pixel { color_ : i16 }"));
+        assert!(result
+            .contains("color_ : butte :: EndianScalar :: to_little_endian (i16 :: from (color))"));
+        assert!(result.contains(
+            "pub fn color (& self) -> butte :: Result < Color > { let value = butte :: EndianScalar :: from_little_endian (self . color_) ; Ok (< Color as core :: convert :: TryFrom < i16 >> :: try_from (value) ?) }"
+        ));
+    }
+
+    #[test]
+    fn test_visit_bit_flags() {
+        let schema = crate::parser::parse_schema(
+            "enum Color: ubyte (bit_flags) { Red, Green, Blue = 4 }
+             table Paint { mix: Color = Red; tint: Color = 17; }",
+        )
+        .unwrap();
+        let result = generate(&schema, &Options::default()).to_string();
+        assert!(result.contains("pub struct Color (u8) ;"));
+        assert!(result.contains(
+            "pub const Red : Self = Self (1 << 0) ; pub const Green : Self = Self (1 << 1) ; pub const Blue : Self = Self (1 << 4) ;"
+        ));
+        assert!(result.contains("Self (Self :: Red . 0 | Self :: Green . 0 | Self :: Blue . 0)"));
+        assert!(result.contains("impl core :: ops :: BitOr for Color"));
+        assert!(result.contains("impl butte :: EndianScalar for Color"));
+        assert!(!result.contains("pub enum Color"));
+        assert!(!result.contains("enum_name_color"));
+        assert!(result.contains(". unwrap_or (Color :: Red)"));
+        assert!(result.contains(". unwrap_or (Color :: from_bits_truncate (17))"));
+    }
+
+    #[test]
+    fn test_extra_derives() {
+        let elements = vec![
+            Element::from(enum_!(Color, UByte, [e_item!(Red)])),
+            Element::from(union!(Animal, [e_item!(Cat)])),
+        ];
+        let schema = Schema::builder().elements(elements).build();
+        let options = Options {
+            derives: vec![syn::parse_quote!(Hash), syn::parse_quote!(serde::Serialize)],
+            ..Options::default()
+        };
+        let result = generate(&schema, &options).to_string();
+        assert_eq!(
+            result
+                .matches("Ord , Hash , Debug , serde :: Serialize)]")
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_visit_union() {
+        let u = union!(Animal, [e_item!(Cat), e_item!(Dog)]);
+        let result = to_code(u);
+        assert!(result.contains("pub enum AnimalType { NONE = 0_u8 , Cat = 1_u8 , Dog = 2_u8 }"));
+        assert!(result
+            .contains("pub enum Animal < 'a > { NONE , Cat (Cat < 'a >) , Dog (Dog < 'a >) }"));
+        assert!(result.contains("impl < 'a > butte :: UnionReader < 'a > for Animal < 'a >"));
+        assert!(result.contains(
+            "AnimalType :: NONE => Animal :: NONE , AnimalType :: Cat => Animal :: Cat (Cat :: from (table)) ,"
+        ));
+        assert!(result.contains(
+            "fn verify (ty : AnimalType , verifier : & mut butte :: Verifier < '_ > , loc : usize) -> butte :: Result < () > { match ty { AnimalType :: NONE => Ok (()) , AnimalType :: Cat => < Cat < '_ > as butte :: Verify > :: verify (verifier , loc) ,"
+        ));
+    }
+
+    #[test]
+    fn test_visit_union_aliases() {
+        let schema = crate::parser::parse_schema(
+            "namespace a; table Cat { lives: int; }
+             namespace b; union Animal { Kitty: a.Cat = 3, Tom: a.Cat }",
+        )
+        .unwrap();
+        let result = generate(&schema, &Options::default()).to_string();
+        assert!(result.contains("pub enum AnimalType { NONE = 0_u8 , Kitty = 3_u8 , Tom = 4_u8 }"));
+        assert!(result.contains(
+            "pub enum Animal < 'a > { NONE , Kitty (super :: a :: Cat < 'a >) , Tom (super :: a :: Cat < 'a >) }"
+        ));
+    }
+
+    #[test]
+    fn test_visit_union_field() {
+        let schema = crate::parser::parse_schema(
+            "table Cat { lives: int; }
+             union Animal { Cat }
+             table Pet { animal: Animal; }",
+        )
+        .unwrap();
+        let result = generate(&schema, &Options::default()).to_string();
+        assert!(result.contains(
+            "pub fn animal (& self) -> butte :: Result < Option < Animal < 'a >> > { let ty = match self . animal_type () ? { AnimalType :: NONE => return Ok (None) , ty => ty , } ;"
+        ));
+        assert!(result.contains(
+            "pub fn animal_as_cat (& self) -> butte :: Result < Option < Cat < 'a >> > { match self . animal () ? { Some (Animal :: Cat (value)) => Ok (Some (value)) , _ => Ok (None) , } }"
+        ));
+    }
+
+    #[test]
+    fn test_visit_union_vector() {
+        let schema = crate::parser::parse_schema(
+            "table Cat { lives: int; }
+             union Animal { Cat }
+             table Shelter { animals: [Animal]; cats: [Animal] (required); }",
+        )
+        .unwrap();
+        let result = generate(&schema, &Options::default()).to_string();
+        assert!(result.contains(
+            "pub const VT_ANIMALS_TYPE : butte :: VOffsetT = 4i16 ; pub const VT_ANIMALS : butte :: VOffsetT = 6i16 ; pub const VT_CATS_TYPE : butte :: VOffsetT = 8i16 ;"
+        ));
+        assert!(result.contains(
+            "pub fn animals_type (& self) -> butte :: Result < Option < butte :: Vector < 'a , AnimalType > >>"
+        ));
+        assert!(result.contains(
+            "pub fn animals (& self) -> butte :: Result < Option < butte :: UnionVector < 'a , Animal < 'a >> >> { butte :: UnionVector :: from_parts (self . animals_type () ? ,"
+        ));
+        assert!(result.contains(
+            "pub fn cats (& self) -> butte :: Result < butte :: UnionVector < 'a , Animal < 'a >> > { butte :: UnionVector :: new (self . cats_type () ? ,"
+        ));
+        assert!(result.contains(
+            "pub animals_type : Option < butte :: WIPOffset < butte :: Vector < 'a , AnimalType > > > , pub animals : Option < butte :: WIPOffset < butte :: Vector < 'a , butte :: ForwardsUOffset < butte :: UnionWIPOffset >> > >"
+        ));
+        assert!(result.contains(
+            "pub fn add_cats_type (& mut self , cats_type : butte :: WIPOffset < butte :: Vector < 'b , AnimalType > >)"
+        ));
+        assert!(result.contains(
+            "self . fbb . required (o , Shelter :: VT_CATS_TYPE , \"cats_type\") ; self . fbb . required (o , Shelter :: VT_CATS , \"cats\") ;"
+        ));
+    }
+}
+
+impl ToTokens for Scoped<'_, '_, FileIdentifier<'_>> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let FileIdentifier { id, doc } = self.item;
+        let id = id.iter().collect::<String>();
+        let vis = &self.scope.options.visibility;
         (quote! {
             #doc
-            pub trait #service_name {
-                #(#methods)*
-            }
+            #vis const FILE_IDENTIFIER: butte::FileIdent = butte::FileIdent::new(#id);
         })
         .to_tokens(tokens)
     }
 }
 
-fn lit_int(value: impl Display, base_type: impl Spanned + Display) -> impl ToTokens {
-    let stringified_int = format!("{}_{}", value, base_type);
-    syn::LitInt::new(&stringified_int, base_type.span())
+impl ToTokens for Scoped<'_, '_, FileExtension<'_>> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let FileExtension { ext, doc } = self.item;
+        let vis = &self.scope.options.visibility;
+        (quote! {
+            #doc
+            #vis const FILE_EXTENSION: &str = #ext;
+        })
+        .to_tokens(tokens)
+    }
 }
 
-// TODO: Unions. Though see structs, because those overlap with unions in a way
-// that isn't clear to me ATM. It seems that unions can be enum-like or
-// struct-like depending on the element types.
-impl ToTokens for Enum<'_> {
+/// Generate the functions for reading and finishing buffers whose root is
+/// the root type of the schema.
+impl ToTokens for Scoped<'_, '_, Root<'_>> {
     fn to_tokens(&self, tokens: &mut TokenStream) {
-        let Self {
-            id: enum_id,
-            values,
-            base_type,
-            doc,
-            ..
-        } = self;
+        let Self { item, scope } = self;
+        let Root { typename, doc } = item;
+        let ty = Type::Ident(DottedIdent::from(vec![*typename]));
+        let root_ty = to_type(&ty, quote!('a), TypeUsage::Read, *scope);
+        let snake_name = typename.raw.to_snake_case();
+        let get_root = format_ident!("get_root_as_{}", snake_name);
+        let get_size_prefixed_root = format_ident!("get_size_prefixed_root_as_{}", snake_name);
+        let verified_root = format_ident!("verified_root_as_{}", snake_name);
+        let verified_size_prefixed_root =
+            format_ident!("verified_size_prefixed_root_as_{}", snake_name);
+        let finish = format_ident!("finish_{}_buffer", snake_name);
+        let finish_size_prefixed = format_ident!("finish_size_prefixed_{}_buffer", snake_name);
 
-        // generate enum variant name => string name of the variant for use in
-        // a match statement
-        let names_to_strings = values.iter().map(|EnumVal { id: key, .. }| {
-            let raw_key = key.raw;
+        let vis = &scope.options.visibility;
+        let file_identifier = scope
+            .symbols
+            .file_identifier
+            .as_ref()
+            .map(|id| quote!(butte::FileIdent::new(#id)));
+        let identifier_arg = match &file_identifier {
+            Some(id) => quote!(Some(#id)),
+            None => quote!(None),
+        };
+        let has_identifier = file_identifier.map(|id| {
+            let has_identifier = format_ident!("{}_buffer_has_identifier", snake_name);
+            let size_prefixed_has_identifier =
+                format_ident!("{}_size_prefixed_buffer_has_identifier", snake_name);
+            let get_root_checked = format_ident!("{}_checked", get_root);
+            let get_size_prefixed_root_checked =
+                format_ident!("{}_checked", get_size_prefixed_root);
             quote! {
-                #enum_id::#key => #raw_key
-            }
-        });
+                #[inline]
+                #vis fn #has_identifier(buf: &[u8]) -> bool {
+                    butte::buffer_has_identifier(buf, #id, false)
+                }
 
-        // assign a value to the key if one was given, otherwise give it the
-        // enumerated index's value
-        let fields = values
-            .iter()
-            .enumerate()
-            .map(|(i, EnumVal { id: key, value })| {
-                // format the value with the correct type, i.e., base_type
-                let scalar_value = lit_int(
-                    if let Some(constant) = *value {
-                        constant
-                    } else {
-                        i.try_into().expect("invalid conversion to enum base type")
-                    },
-                    base_type.to_token_stream(),
-                );
-                quote! {
-                    #key = #scalar_value
+                #[inline]
+                #vis fn #size_prefixed_has_identifier(buf: &[u8]) -> bool {
+                    butte::buffer_has_identifier(buf, #id, true)
+                }
+
+                /// Read the root of the buffer, after checking that it holds
+                /// the file identifier of the schema.
+                #[inline]
+                #vis fn #get_root_checked<'a>(buf: &'a [u8]) -> butte::Result<#root_ty> {
+                    if !#has_identifier(buf) {
+                        return Err(butte::Error::WrongFileIdentifier);
+                    }
+                    butte::get_root::<#root_ty>(buf)
                 }
-            });
 
-        let raw_snake_enum_name = enum_id.raw.to_snake_case();
-        let enum_id_fn_name = format_ident!("enum_name_{}", raw_snake_enum_name);
+                #[inline]
+                #vis fn #get_size_prefixed_root_checked<'a>(
+                    buf: &'a [u8],
+                ) -> butte::Result<#root_ty> {
+                    if !#size_prefixed_has_identifier(buf) {
+                        return Err(butte::Error::WrongFileIdentifier);
+                    }
+                    butte::get_size_prefixed_root::<#root_ty>(buf)
+                }
+            }
+        });
 
-        // TODO: Maybe separate these pieces to avoid variables that used far
-        // away from their definition.
         (quote! {
-            // force a C-style enum
-            #[repr(#base_type)]
-            #[allow(non_camel_case_types)]
-            #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
             #doc
-            pub enum #enum_id {
-                #(#fields),*
+            #[inline]
+            #vis fn #get_root<'a>(buf: &'a [u8]) -> butte::Result<#root_ty> {
+                butte::get_root::<#root_ty>(buf)
             }
 
-            impl<'a> butte::Follow<'a> for #enum_id {
-                type Inner = Self;
-
-                fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
-                    butte::read_scalar_at::<Self>(buf, loc)
-                }
+            #[inline]
+            #vis fn #get_size_prefixed_root<'a>(buf: &'a [u8]) -> butte::Result<#root_ty> {
+                butte::get_size_prefixed_root::<#root_ty>(buf)
             }
 
-            impl butte::EndianScalar for #enum_id {
-                #[inline]
-                fn to_little_endian(self) -> Self {
-                    let n = #base_type::to_le(self as #base_type);
-                    let p = &n as *const #base_type as *const Self;
-                    unsafe { *p }
-                }
+            /// Verify the whole buffer before reading its root, so that
+            /// reading it can't fail because the buffer is malformed.
+            #[inline]
+            #vis fn #verified_root<'a>(buf: &'a [u8]) -> butte::Result<#root_ty> {
+                butte::verified_root::<#root_ty>(buf)
+            }
 
-                #[inline]
-                fn from_little_endian(self) -> Self {
-                    let n = #base_type::from_le(self as #base_type);
-                    let p = &n as *const #base_type as *const Self;
-                    unsafe { *p }
-                }
+            #[inline]
+            #vis fn #verified_size_prefixed_root<'a>(buf: &'a [u8]) -> butte::Result<#root_ty> {
+                butte::verified_size_prefixed_root::<#root_ty>(buf)
             }
 
-            impl butte::Push for #enum_id {
-                type Output = Self;
+            #has_identifier
 
-                #[inline]
-                fn push(&self, dst: &mut [u8], _rest: &[u8]) {
-                    butte::emplace_scalar::<Self>(dst, *self);
-                }
+            #[inline]
+            #vis fn #finish<'a>(
+                fbb: &mut butte::FlatBufferBuilder<'a>,
+                root: butte::WIPOffset<#root_ty>,
+            ) {
+                fbb.finish(root, #identifier_arg);
             }
 
-            pub fn #enum_id_fn_name(e: #enum_id) -> &'static str {
-                match e {
-                    #(#names_to_strings),*
-                }
+            #[inline]
+            #vis fn #finish_size_prefixed<'a>(
+                fbb: &mut butte::FlatBufferBuilder<'a>,
+                root: butte::WIPOffset<#root_ty>,
+            ) {
+                fbb.finish_size_prefixed(root, #identifier_arg);
             }
         })
         .to_tokens(tokens)
     }
 }
 
+#[cfg(test)]
+mod root_type_tests {
+    use super::*;
+
+    fn schema_code(elements: Vec<Element>) -> String {
+        to_code(Schema::builder().elements(elements).build())
+    }
+
+    #[test]
+    fn test_root_type_without_identifier() {
+        let result = schema_code(vec![
+            Element::from(table!(Monster, [field!(hp, Int16)])),
+            Element::from(Root::builder().typename("Monster").build()),
+        ]);
+        assert!(result.contains(
+            "pub fn get_root_as_monster < 'a > (buf : & 'a [u8]) -> butte :: Result < Monster < 'a > >"
+        ));
+        assert!(result.contains("fn get_size_prefixed_root_as_monster"));
+        assert!(result.contains("fbb . finish (root , None)"));
+        assert!(result.contains("pub fn finish_size_prefixed_monster_buffer"));
+        assert!(result.contains("fbb . finish_size_prefixed (root , None)"));
+        assert!(!result.contains("monster_buffer_has_identifier"));
+        assert!(!result.contains("get_root_as_monster_checked"));
+        assert!(result.contains(
+            "pub fn verified_root_as_monster < 'a > (buf : & 'a [u8]) -> butte :: Result < Monster < 'a > > { butte :: verified_root :: < Monster < 'a > > (buf) }"
+        ));
+        assert!(result.contains("fn verified_size_prefixed_root_as_monster"));
+    }
+
+    #[test]
+    fn test_root_type_with_identifier() {
+        let result = schema_code(vec![
+            Element::from(table!(Monster, [field!(hp, Int16)])),
+            Element::from(Root::builder().typename("Monster").build()),
+            Element::from(FileIdentifier::builder().id(['M', 'O', 'N', 'S']).build()),
+            Element::from(FileExtension::builder().ext("mon").build()),
+        ]);
+        assert!(result.contains(
+            "pub const FILE_IDENTIFIER : butte :: FileIdent = butte :: FileIdent :: new (\"MONS\") ;"
+        ));
+        assert!(result.contains("pub const FILE_EXTENSION : & str = \"mon\""));
+        assert!(
+            result.contains("fbb . finish (root , Some (butte :: FileIdent :: new (\"MONS\")))")
+        );
+        assert!(result.contains(
+            "fbb . finish_size_prefixed (root , Some (butte :: FileIdent :: new (\"MONS\")))"
+        ));
+        assert!(result.contains(
+            "pub fn monster_buffer_has_identifier (buf : & [u8]) -> bool { butte :: buffer_has_identifier (buf , butte :: FileIdent :: new (\"MONS\") , false) }"
+        ));
+        assert!(result.contains("fn monster_size_prefixed_buffer_has_identifier"));
+        assert!(result.contains(
+            "pub fn get_root_as_monster_checked < 'a > (buf : & 'a [u8]) -> butte :: Result < Monster < 'a > > { if ! monster_buffer_has_identifier (buf) { return Err (butte :: Error :: WrongFileIdentifier) ; }"
+        ));
+        assert!(result.contains("fn get_size_prefixed_root_as_monster_checked"));
+    }
+}
+
 // TODO: better error messages for things that aren't implemented
-impl ToTokens for Element<'_> {
+impl ToTokens for Scoped<'_, '_, Element<'_>> {
     fn to_tokens(&self, tokens: &mut TokenStream) {
-        // the following constructs are (or should be) handled at the file
-        // level:
-        // * Namespaces
-        // * Root types
-        // * File extensions
-        // * File identifiers
+        let Self { item, scope } = self;
+        let scope = *scope;
+        // namespaces are handled at the file level
         //
         // Additionally, attributes do not have corresponding concrete code
         // generated, they are used to *affect* codegen of other items.
-        match self {
-            Element::Table(t) => t.to_tokens(tokens),
-            Element::Struct(_) => unimplemented!(),
-            Element::Enum(e) => e.to_tokens(tokens),
-
-            Element::Root(_) => unimplemented!(),
-            Element::FileExtension(_) => unimplemented!(),
-            Element::FileIdentifier(_) => unimplemented!(),
-            Element::Attribute(_) => unimplemented!(),
-            Element::Rpc(rpc) => rpc.to_tokens(tokens),
+        match item {
+            Element::Table(item) => Scoped { item, scope }.to_tokens(tokens),
+            Element::Struct(item) => Scoped { item, scope }.to_tokens(tokens),
+            Element::Enum(item) => Scoped { item, scope }.to_tokens(tokens),
+            Element::Union(item) => Scoped { item, scope }.to_tokens(tokens),
+
+            Element::Root(item) => Scoped { item, scope }.to_tokens(tokens),
+            Element::FileExtension(item) => Scoped { item, scope }.to_tokens(tokens),
+            Element::FileIdentifier(item) => Scoped { item, scope }.to_tokens(tokens),
+            Element::Attribute(_) => {}
+            Element::Rpc(item) => Scoped { item, scope }.to_tokens(tokens),
             Element::Object(_) => unimplemented!(),
             element => panic!("{:?}", element),
         }
+
+        let context = ItemContext { scope };
+        for plugin in &scope.options.plugins {
+            match item {
+                Element::Table(item) => plugin.table(item, &context),
+                Element::Struct(item) => plugin.struct_(item, &context),
+                Element::Enum(item) => plugin.enum_(item, &context),
+                Element::Union(item) => plugin.union(item, &context),
+                Element::Rpc(item) => plugin.rpc(item, &context),
+                _ => continue,
+            }
+            .to_tokens(tokens);
+        }
     }
 }
 
-// TODO: actually open up a file
-// this will need some notion of a default include path to search for fbs
-// files.
-impl ToTokens for Include<'_> {
+impl ToTokens for Element<'_> {
     fn to_tokens(&self, tokens: &mut TokenStream) {
-        let Self { stem, doc, .. } = self;
-        let id = format_ident!("{}", stem);
-        (quote! {
-            #doc
-            use #id::*;
-        })
-        .to_tokens(tokens)
+        let symbols = SymbolTable::default();
+        let scope = Scope {
+            symbols: &symbols,
+            namespace: &[],
+            options: &Options::default(),
+        };
+        Scoped { item: self, scope }.to_tokens(tokens)
     }
 }
 
-// TODO:
-// * Root types
-// * File identifiers
-// * File extensions
-// * Parsing included files
-impl ToTokens for Schema<'_> {
-    fn to_tokens(&self, tokens: &mut TokenStream) {
-        let Self { includes, elements } = self;
+/// The module generated for a namespace, along with the modules of the
+/// namespaces nested in it.
+///
+/// Elements and nested modules are kept in the order they are declared in,
+/// so that the generated code follows the schema and doesn't change unless
+/// the schema does.
+#[derive(Debug, Default)]
+struct Module<'a, 'b> {
+    doc: Option<&'b Comment<'a>>,
+    elements: Vec<&'b Element<'a>>,
+    children: Vec<(&'a str, Module<'a, 'b>)>,
+}
+
+impl<'a, 'b> Module<'a, 'b> {
+    /// Build the module tree of `elements`, where namespaces precede all of
+    /// their contents.
+    fn new(elements: &'b [Element<'a>]) -> Self {
+        let mut root = Self::default();
+        let mut namespace = vec![];
+        for element in elements {
+            if let Element::Namespace(Namespace { ident, doc }) = element {
+                namespace = ident.parts.iter().map(|part| part.raw).collect();
+                let module = root.module_mut(&namespace);
+                if !doc.lines.is_empty() {
+                    module.doc = Some(doc);
+                }
+            } else {
+                root.module_mut(&namespace).elements.push(element);
+            }
+        }
+        root
+    }
+
+    /// The module at `path`, which is added after its siblings if it's
+    /// new.
+    fn module_mut(&mut self, path: &[&'a str]) -> &mut Self {
+        path.iter().fold(self, |module, &part| {
+            let index = match module.children.iter().position(|(name, _)| *name == part) {
+                Some(index) => index,
+                None => {
+                    module.children.push((part, Self::default()));
+                    module.children.len() - 1
+                }
+            };
+            &mut module.children[index].1
+        })
+    }
+
+    /// Generate the contents of the module for `namespace`.
+    fn to_tokens(
+        &self,
+        symbols: &SymbolTable<'a>,
+        namespace: &[&'a str],
+        options: &Options,
+    ) -> TokenStream {
+        let scope = Scope {
+            symbols,
+            namespace,
+            options,
+        };
+        let elements = self.elements.iter().map(|&item| Scoped { item, scope });
+        let children = self.children.iter().map(|&(name, ref module)| {
+            let doc = module.doc;
+            let id = Ident::from(name);
+            let body = module.to_tokens(symbols, &[namespace, &[name]].concat(), options);
+            let attrs = module_attrs();
+            let vis = &options.visibility;
+            quote! {
+                #doc
+                #attrs
+                #vis mod #id {
+                    #body
+                }
+            }
+        });
+        quote! {
+            #(#elements)*
+            #(#children)*
+        }
+    }
 
-        // namespaces precede all of their contents, so track the current namespace and accumulate
-        // its change into the key of a map of namespace -> elements contained within that
-        // namespace.
-        let code = elements
+    /// Generate a file for the module of `namespace` at `path`, and files
+    /// for the modules nested in it, which it includes from the directory
+    /// named after it.
+    fn files(
+        &self,
+        symbols: &SymbolTable<'a>,
+        namespace: &[&'a str],
+        path: PathBuf,
+        options: &Options,
+        files: &mut Vec<(PathBuf, TokenStream)>,
+    ) {
+        let scope = Scope {
+            symbols,
+            namespace,
+            options,
+        };
+        let elements = self.elements.iter().map(|&item| Scoped { item, scope });
+        let dir = match namespace.last() {
+            Some(name) => PathBuf::from(name),
+            None => PathBuf::new(),
+        };
+        let children = self
+            .children
             .iter()
-            .scan((None, None), |ns_item_pair, element| {
-                *ns_item_pair = if element.is_namespace() {
-                    (element.namespace(), None)
-                } else {
-                    (ns_item_pair.0, Some(element))
-                };
-                Some(*ns_item_pair)
+            .map(|&(name, ref module)| {
+                let doc = module.doc;
+                let id = Ident::from(name);
+                let child = dir.join(format!("{}.rs", name));
+                let include = child.to_string_lossy().replace('\\', "/");
+                module.files(
+                    symbols,
+                    &[namespace, &[name]].concat(),
+                    path.parent().unwrap_or(Path::new("")).join(&child),
+                    options,
+                    files,
+                );
+                let attrs = module_attrs();
+                let vis = &options.visibility;
+                quote! {
+                    #doc
+                    #attrs
+                    #vis mod #id {
+                        include!(#include);
+                    }
+                }
             })
-            .filter(|(_, element)| element.is_some())
-            .into_group_map()
+            .collect::<Vec<_>>();
+        files.push((
+            path,
+            quote! {
+                #(#elements)*
+                #(#children)*
+            },
+        ));
+    }
+}
+
+#[cfg(test)]
+mod module_tests {
+    use super::*;
+    use crate::namespace;
+
+    #[test]
+    fn test_nested_namespaces_share_modules() {
+        let schema = Schema::builder()
+            .elements(vec![
+                Element::from(namespace!(a)),
+                Element::from(table!(Outer, [field!(x, Int)])),
+                Element::from(namespace!(a::b)),
+                Element::from(table!(Inner, [field!(x, Int)])),
+                Element::from(namespace!(a)),
+                Element::from(table!(Later, [field!(x, Int)])),
+            ])
+            .build();
+        let result = to_code(schema);
+        assert_eq!(result.matches("pub mod a").count(), 1);
+        assert_eq!(result.matches("pub mod b").count(), 1);
+        assert!(result.find("pub struct Later").unwrap() < result.find("pub mod b").unwrap());
+    }
+
+    #[test]
+    fn test_declaration_order() {
+        let schema = Schema::builder()
+            .elements(vec![
+                Element::from(namespace!(z)),
+                Element::from(table!(Zebra, [field!(x, Int)])),
+                Element::from(table!(Yak, [field!(x, Int)])),
+                Element::from(namespace!(a)),
+                Element::from(table!(Ant, [field!(x, Int)])),
+                Element::from(namespace!(m)),
+                Element::from(table!(Moth, [field!(x, Int)])),
+            ])
+            .build();
+        let result = to_code(&schema);
+        let positions = [
+            "pub mod z",
+            "pub struct Zebra",
+            "pub struct Yak",
+            "pub mod a",
+            "pub mod m",
+        ]
+        .iter()
+        .map(|item| result.find(item).unwrap())
+        .collect::<Vec<_>>();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(result, to_code(&schema));
+    }
+
+    #[test]
+    fn test_files_per_namespace() {
+        let schema = Schema::builder()
+            .elements(vec![
+                Element::from(table!(Root, [field!(x, Int)])),
+                Element::from(namespace!(a)),
+                Element::from(table!(Outer, [field!(x, Int)])),
+                Element::from(namespace!(a::b)),
+                Element::from(table!(Inner, [field!(x, Int)])),
+            ])
+            .build();
+        let files = generate_files(&schema, &Options::default())
             .into_iter()
-            // for each non-None namespace and elements contained within:
-            // reverse fold over the namespace pieces to generate nested modules:
-            // if namespace parts is [a, b, c]
-            // and body is struct Foo { ... }
-            // then
-            // reverse:
-            //   [c, b, a]
-            // fold:
-            //    init: struct Foo { ... }
-            //    next: pub mod c { struct Foo { ... } }
-            //    next: pub mod b { pub mod c { struct Foo { ... } } }
-            //   final: pub mod a { pub mod b { pub mod c { struct Foo { ... } } } }
-            .map(|(namespace, elements)| {
-                let base_body = quote! { #(#elements)* };
-                if let Some(Namespace { ident, doc }) = namespace {
-                    let nested =
-                        ident
-                            .parts
-                            .iter()
-                            .rev()
-                            .fold(base_body, |module_body, module_name| {
-                                quote! {
-                                    pub mod #module_name {
-                                        #module_body
-                                    }
-                                }
-                            });
-                    quote! {
-                        #doc
-                        #nested
+            .map(|(path, code)| (path.to_string_lossy().into_owned(), code.to_string()))
+            .collect::<Vec<_>>();
+        let paths = files
+            .iter()
+            .map(|(path, _)| path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, vec!["a/b.rs", "a.rs", "mod.rs"]);
+        assert!(files[2].1.contains("pub struct Root"));
+        assert!(files[2].1.contains("pub mod a { include ! (\"a.rs\") ; }"));
+        assert!(files[1].1.contains("pub struct Outer"));
+        assert!(files[1]
+            .1
+            .contains("pub mod b { include ! (\"a/b.rs\") ; }"));
+        assert!(files[0].1.contains("pub struct Inner"));
+        assert!(!files[0].1.contains("include !"));
+    }
+
+    #[test]
+    fn test_modules_allow_lints() {
+        let schema = Schema::builder()
+            .elements(vec![
+                Element::from(namespace!(a::b)),
+                Element::from(table!(Inner, [field!(x, Int)])),
+            ])
+            .build();
+        let allow = "# [allow (clippy :: all , clippy :: pedantic , clippy :: nursery)]";
+        let result = to_code(&schema);
+        assert!(result.contains(&format!("{} pub mod a", allow)));
+        assert!(result.contains(&format!("{} pub mod b", allow)));
+        assert!(result.contains("# [must_use] pub struct InnerBuilder < 'a , 'b >"));
+        let files = generate_files(&schema, &Options::default());
+        assert!(files[2]
+            .1
+            .to_string()
+            .contains(&format!("{} pub mod a", allow)));
+    }
+
+    #[test]
+    fn test_visibility() {
+        let schema = Schema::builder()
+            .elements(vec![
+                Element::from(table!(Root, [field!(x, Int)])),
+                Element::from(namespace!(a)),
+                Element::from(table!(Inner, [field!(x, Int)])),
+                Element::from(Root::builder().typename(Ident::from("Inner")).build()),
+                Element::from(FileIdentifier::builder().id(['I', 'N', 'N', 'R']).build()),
+            ])
+            .build();
+        let options = Options {
+            visibility: Visibility::Restricted(syn::parse_quote!(crate::schemas)),
+            ..Options::default()
+        };
+        let result = generate(&schema, &options).to_string();
+        for item in [
+            "mod a",
+            "struct Root < 'a >",
+            "struct Inner < 'a >",
+            "struct InnerArgs",
+            "struct InnerBuilder",
+            "enum InnerOffset",
+            "fn get_root_as_inner",
+            "fn inner_buffer_has_identifier",
+            "const FILE_IDENTIFIER",
+        ] {
+            assert!(
+                result.contains(&format!("pub (in crate :: schemas) {}", item)),
+                "{}",
+                item
+            );
+        }
+        assert!(!result.contains("pub struct"));
+        assert!(result.contains("pub fn x (& self)"));
+    }
+
+    #[test]
+    fn test_prelude() {
+        let schema = crate::parser::parse_schema(
+            "namespace a;
+             table Shared { x: int; }
+             table Unused { x: int; }
+             namespace b;
+             table Shared { x: int; }
+             struct Point { x: int; }
+             table Cat { lives: int; }
+             union Pet { Cat }
+             table Home { pet: Pet; at: [Point]; shared: a.Shared; }
+             root_type Home;",
+        )
+        .unwrap();
+        let options = Options {
+            prelude: true,
+            module: Some(format_ident!("generated")),
+            ..Options::default()
+        };
+        let result = generate(&schema, &options).to_string();
+        assert!(result.starts_with(
+            "# [allow (clippy :: all , clippy :: pedantic , clippy :: nursery)] pub mod generated {"
+        ));
+        assert!(result.contains(
+            "pub mod prelude { pub use super :: a :: Shared ; pub use super :: b :: Point ; \
+             pub use super :: b :: Cat ; pub use super :: b :: Pet ; pub use super :: b :: PetType ; \
+             pub use super :: b :: Home ; }"
+        ));
+
+        // without a root type every type is re-exported, except the ones
+        // whose names clash
+        let schema = crate::parser::parse_schema(
+            "namespace a; table Shared { x: int; } table Unused { x: int; }
+             namespace b; table Shared { x: int; }",
+        )
+        .unwrap();
+        let result = generate(&schema, &options).to_string();
+        assert!(result.contains("pub mod prelude { pub use super :: a :: Unused ; }"));
+    }
+}
+
+#[cfg(test)]
+mod plugin_tests {
+    use super::*;
+
+    /// Gives every table a function returning the types of its fields.
+    struct FieldTypes;
+
+    impl CodegenPlugin for FieldTypes {
+        fn table<'a>(&self, table: &Table<'a>, context: &ItemContext<'a, '_>) -> TokenStream {
+            let id = format_ident!("{}_field_types", table.id.raw.to_snake_case());
+            let paths = table
+                .fields
+                .iter()
+                .map(|field| context.type_path(&field.ty).to_string());
+            let namespace = context.namespace().join(".");
+            quote! {
+                pub fn #id() -> (&'static str, &'static [&'static str]) {
+                    (#namespace, &[#(#paths),*])
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_plugin() {
+        let schema = crate::parser::parse_schema(
+            "namespace a; table Outer { inner: a.b.Inner; }
+             namespace a.b; table Inner { x: int; }",
+        )
+        .unwrap();
+        let options = Options {
+            plugins: vec![Arc::new(FieldTypes)],
+            ..Options::default()
+        };
+        let result = generate(&schema, &options).to_string();
+        assert!(result.contains(
+            "pub fn outer_field_types () -> (& 'static str , & 'static [& 'static str]) { (\"a\" , & [\"b :: Inner\"]) }"
+        ));
+        assert!(result.contains(
+            "pub fn inner_field_types () -> (& 'static str , & 'static [& 'static str]) { (\"a.b\" , & [\"i32\"]) }"
+        ));
+        assert!(
+            result.find("pub fn inner_field_types").unwrap() > result.find("pub mod b").unwrap()
+        );
+    }
+
+    /// Gives every table a function returning the priority of its fields,
+    /// given by the custom `priority` attribute.
+    struct Priorities;
+
+    impl CodegenPlugin for Priorities {
+        fn table<'a>(&self, table: &Table<'a>, context: &ItemContext<'a, '_>) -> TokenStream {
+            if !context.declared_attributes().contains(&"priority") {
+                return quote!();
+            }
+            let id = format_ident!("{}_priorities", table.id.raw.to_snake_case());
+            let priorities = table.fields.iter().filter_map(|field| {
+                let name = field.id.raw;
+                match field.attribute("priority")? {
+                    Some(Single::Scalar(Scalar::Integer(priority))) => {
+                        let priority = Literal::i128_unsuffixed(*priority);
+                        Some(quote!((#name, #priority)))
                     }
-                } else {
-                    base_body
+                    _ => None,
                 }
             });
+            quote! {
+                pub fn #id() -> &'static [(&'static str, i64)] {
+                    &[#(#priorities),*]
+                }
+            }
+        }
+    }
 
-        (quote! {
-            #(#includes)*
-            #(#code)*
-        })
-        .to_tokens(tokens)
+    #[test]
+    fn test_plugin_custom_attributes() {
+        let schema = crate::parser::parse_schema(
+            "attribute \"priority\";
+             table Task (tracked) { name: string (priority: 2); due: long; owner: string (priority: 1); }",
+        )
+        .unwrap();
+        let options = Options {
+            plugins: vec![Arc::new(Priorities)],
+            ..Options::default()
+        };
+        let result = generate(&schema, &options).to_string();
+        assert!(result.contains(
+            "pub fn task_priorities () -> & 'static [(& 'static str , i64)] { & [(\"name\" , 2) , (\"owner\" , 1)] }"
+        ));
+
+        let table = schema.elements.iter().find_map(|element| match element {
+            Element::Table(table) => Some(table),
+            _ => None,
+        });
+        let metadata = table.unwrap().metadata.as_ref().unwrap();
+        assert_eq!(metadata.custom_attributes(), vec![("tracked", None)]);
+    }
+}
+
+/// Generate code for a schema whose includes have already been resolved, see
+/// [`SchemaFiles`](crate::include::SchemaFiles).
+pub fn generate(schema: &Schema, options: &Options) -> TokenStream {
+    let names = TypeNames::new(schema, options.naming);
+    let schema = names.rename(schema);
+    let symbols = SymbolTable::new(&schema.elements);
+    let code = Module::new(&schema.elements).to_tokens(&symbols, &[], options);
+    top_level(code, &schema.elements, &symbols, options)
+}
+
+/// Generate adapters from the `old` version of a schema, whose code is in
+/// `old_module`, to the `new` version, whose code is in `new_module`, like
+/// `super::v1`. Their includes must have already been resolved.
+///
+/// Fails with the changes between the versions that can't be adapted, like a
+/// field whose type changed. See the `adapter` module for how values are
+/// adapted.
+pub fn generate_adapters<'a>(
+    old: &Schema<'a>,
+    old_module: &syn::Path,
+    new: &Schema<'a>,
+    new_module: &syn::Path,
+    options: &Options,
+) -> Result<TokenStream, Vec<crate::validate::SchemaError<'a>>> {
+    adapter::adapters(old, old_module, new, new_module, options)
+}
+
+/// Add the prelude to `code`, the code of the root namespace, and put it in
+/// the top-level module, as `options` say.
+fn top_level(
+    code: TokenStream,
+    elements: &[Element],
+    symbols: &SymbolTable,
+    options: &Options,
+) -> TokenStream {
+    let prelude = if options.prelude {
+        prelude::prelude(elements, symbols, options)
+    } else {
+        quote!()
+    };
+    match &options.module {
+        Some(module) => {
+            let attrs = module_attrs();
+            let vis = &options.visibility;
+            quote! {
+                #attrs
+                #vis mod #module {
+                    #code
+                    #prelude
+                }
+            }
+        }
+        None => quote! {
+            #code
+            #prelude
+        },
+    }
+}
+
+/// Generate code for a schema like [`generate`], but with a file per
+/// namespace rather than a single module tree.
+///
+/// The code of the root namespace is in `mod.rs`, and that of a namespace
+/// like `a.b` in `a/b.rs`, next to `a.rs`. Each file includes the files of
+/// the namespaces nested in it, so only `mod.rs` needs to be included.
+pub fn generate_files(schema: &Schema, options: &Options) -> Vec<(PathBuf, TokenStream)> {
+    let names = TypeNames::new(schema, options.naming);
+    let schema = names.rename(schema);
+    let symbols = SymbolTable::new(&schema.elements);
+    let mut files = vec![];
+    Module::new(&schema.elements).files(
+        &symbols,
+        &[],
+        PathBuf::from("mod.rs"),
+        options,
+        &mut files,
+    );
+    files.sort_by(|(a, _), (b, _)| a.cmp(b));
+    if let Some((_, code)) = files
+        .iter_mut()
+        .find(|(path, _)| path == Path::new("mod.rs"))
+    {
+        *code = top_level(std::mem::take(code), &schema.elements, &symbols, options);
+    }
+    files
+}
+
+impl ToTokens for Schema<'_> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        generate(self, &Options::default()).to_tokens(tokens)
     }
 }
 
@@ -0,0 +1,902 @@
+//! Adapters from one version of a schema to another, which migrate buffers
+//! written with the old version to the new one.
+//!
+//! For every table, enum, struct and union declared under the same name in
+//! both versions, this generates a function converting a value of the old
+//! version to one of the new. Tables are rebuilt field by field, matching
+//! fields by name: a field of both versions is copied, a field only of the
+//! old version is dropped, and a field only of the new version is left out,
+//! so that it reads as its default. The values of enums and unions are
+//! matched by name too. Structs can't change between versions, and are
+//! copied as they are.
+//!
+//! If both versions have the same root type, `adapt_buffer` migrates a whole
+//! buffer.
+use super::*;
+use crate::validate::SchemaError;
+
+/// A type of one version of a schema, which a type of the other version is
+/// matched with.
+#[derive(Debug, Clone, PartialEq)]
+enum Kind<'a> {
+    /// A scalar, by the Rust type it is read as.
+    Scalar(String),
+    String,
+    Enum(Vec<&'a str>),
+    Struct(Vec<&'a str>),
+    Table(Vec<&'a str>),
+    Union(Vec<&'a str>),
+    Vector(Box<Kind<'a>>),
+    Array(Box<Kind<'a>>, IntegerConstant),
+}
+
+/// A version of a schema, along with the module its code is in.
+struct Version<'a, 'b> {
+    symbols: SymbolTable<'a>,
+    names: TypeNames<'a>,
+    module: &'b syn::Path,
+    options: &'b Options,
+}
+
+impl<'a, 'b> Version<'a, 'b> {
+    fn new(schema: &Schema<'a>, module: &'b syn::Path, options: &'b Options) -> Self {
+        Self {
+            symbols: SymbolTable::new(&schema.elements),
+            names: TypeNames::new(schema, options.naming),
+            module,
+            options,
+        }
+    }
+
+    fn scope<'c>(&'c self, namespace: &'c [&'a str]) -> Scope<'a, 'c> {
+        Scope {
+            symbols: &self.symbols,
+            namespace,
+            options: self.options,
+        }
+    }
+
+    /// The kind of `ty`, referred to from `namespace`.
+    fn kind(&self, namespace: &[&'a str], ty: &Type<'a>) -> Kind<'a> {
+        match ty {
+            Type::String => Kind::String,
+            Type::Array(element) => Kind::Vector(Box::new(self.kind(namespace, element))),
+            Type::FixedArray(element, len) => {
+                Kind::Array(Box::new(self.kind(namespace, element)), *len)
+            }
+            Type::Ident(id) => match self.symbols.resolve(namespace, id) {
+                Some((name, Declaration::Table)) => Kind::Table(name.to_vec()),
+                Some((name, Declaration::Struct(_))) => Kind::Struct(name.to_vec()),
+                Some((name, Declaration::Enum(_))) => Kind::Enum(name.to_vec()),
+                Some((name, Declaration::Union(_))) => Kind::Union(name.to_vec()),
+                None => panic!("unknown type: {:?}", id),
+            },
+            ty => Kind::Scalar(ty.to_token_stream().to_string()),
+        }
+    }
+
+    /// The path to the item named `last` in the namespace of the type `name`.
+    fn path(&self, name: &[&'a str], last: impl Display) -> TokenStream {
+        let module = self.module;
+        let namespace = name[..name.len() - 1].iter().map(ident);
+        let last = ident(last.to_string());
+        quote!(#module #(::#namespace)*::#last)
+    }
+
+    /// The path to the type `name`.
+    fn type_path(&self, name: &[&'a str]) -> TokenStream {
+        self.path(name, self.names.name(name))
+    }
+
+    /// The path to the discriminant enum of the union `name`.
+    fn union_type_path(&self, name: &[&'a str]) -> TokenStream {
+        self.path(name, union_type_id(&Ident::from(self.names.name(name))))
+    }
+
+    /// The fully qualified name of the root type, if there is one.
+    fn root(&self, elements: &[Element<'a>]) -> Option<Vec<&'a str>> {
+        let mut namespace = vec![];
+        for element in elements {
+            match element {
+                Element::Namespace(Namespace { ident, .. }) => {
+                    namespace = ident.parts.iter().map(|part| part.raw).collect();
+                }
+                Element::Root(Root { typename, .. }) => {
+                    return self
+                        .symbols
+                        .resolve(&namespace, &DottedIdent::from(typename.raw))
+                        .map(|(name, _)| name.to_vec());
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+/// The name of the adapter of the type `name`.
+fn adapter_id(name: &[&str]) -> proc_macro2::Ident {
+    ident(name.join("_").to_snake_case())
+}
+
+/// The fully qualified name `name` as it is written in a schema.
+fn dotted(name: &[&str]) -> String {
+    name.join(".")
+}
+
+struct Adapters<'a, 'b> {
+    old: Version<'a, 'b>,
+    new: Version<'a, 'b>,
+    errors: Vec<SchemaError<'a>>,
+}
+
+/// Generate the adapters from the `old` version of a schema, whose code is in
+/// `old_module`, to the `new` version, whose code is in `new_module`. Fails
+/// with the changes that can't be adapted.
+pub(super) fn adapters<'a>(
+    old: &Schema<'a>,
+    old_module: &syn::Path,
+    new: &Schema<'a>,
+    new_module: &syn::Path,
+    options: &Options,
+) -> Result<TokenStream, Vec<SchemaError<'a>>> {
+    let mut adapters = Adapters {
+        old: Version::new(old, old_module, options),
+        new: Version::new(new, new_module, options),
+        errors: vec![],
+    };
+
+    let mut old_tables = HashMap::new();
+    let mut namespace = vec![];
+    for element in &old.elements {
+        match element {
+            Element::Namespace(Namespace { ident, .. }) => {
+                namespace = ident.parts.iter().map(|part| part.raw).collect();
+            }
+            Element::Table(table) => {
+                old_tables.insert([namespace.as_slice(), &[table.id.raw]].concat(), table);
+            }
+            _ => {}
+        }
+    }
+
+    let mut code = vec![];
+    let mut namespace = vec![];
+    for element in &new.elements {
+        if let Element::Namespace(Namespace { ident, .. }) = element {
+            namespace = ident.parts.iter().map(|part| part.raw).collect();
+            continue;
+        }
+        let adapter = match element {
+            Element::Table(table) => {
+                let name = [namespace.as_slice(), &[table.id.raw]].concat();
+                old_tables
+                    .get(&name)
+                    .map(|old_table| adapters.table(&name, old_table, table))
+            }
+            Element::Struct(s) => {
+                let name = [namespace.as_slice(), &[s.id.raw]].concat();
+                match adapters.old.symbols.declarations.get(&name) {
+                    Some(Declaration::Struct(old_struct)) => {
+                        adapters.struct_(&name, &old_struct.clone(), s)
+                    }
+                    _ => None,
+                }
+            }
+            Element::Enum(e) => {
+                let name = [namespace.as_slice(), &[e.id.raw]].concat();
+                match adapters.old.symbols.declarations.get(&name) {
+                    Some(Declaration::Enum(old_enum)) => {
+                        adapters.enum_(&name, &old_enum.clone(), e)
+                    }
+                    _ => None,
+                }
+            }
+            Element::Union(u) => {
+                let name = [namespace.as_slice(), &[u.id.raw]].concat();
+                match adapters.old.symbols.declarations.get(&name) {
+                    Some(Declaration::Union(old_union)) => {
+                        adapters.union(&name, &old_union.clone(), u)
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        code.extend(adapter);
+    }
+
+    let root = adapters.old.root(&old.elements);
+    let adapt_buffer = match root {
+        Some(root)
+            if adapters.new.root(&new.elements).as_ref() == Some(&root)
+                && old_tables.contains_key(&root) =>
+        {
+            adapters.adapt_buffer(&root)
+        }
+        _ => quote!(),
+    };
+
+    if !adapters.errors.is_empty() {
+        return Err(adapters.errors);
+    }
+    Ok(quote! {
+        #(#code)*
+        #adapt_buffer
+    })
+}
+
+impl<'a> Adapters<'a, '_> {
+    fn error(&mut self, message: String, at: &'a str) {
+        self.errors.push(SchemaError { message, at });
+    }
+
+    /// The adapter of the table `name`, which reads an `old` table and builds
+    /// a `new` one.
+    fn table(&mut self, name: &[&'a str], old: &Table<'a>, new: &Table<'a>) -> TokenStream {
+        let namespace = &name[..name.len() - 1];
+        let mut reads = vec![];
+        let mut calls = vec![];
+        for field in new.fields.iter().filter(|field| !field.is_deprecated()) {
+            let old_field = old
+                .fields
+                .iter()
+                .find(|old_field| !old_field.is_deprecated() && old_field.id.raw == field.id.raw);
+            let old_field = match old_field {
+                Some(old_field) => old_field,
+                None => {
+                    if field.is_required() {
+                        self.error(
+                            format!(
+                                "field `{}` of table `{}` is required, but isn't in the old version",
+                                field.id.raw,
+                                dotted(name)
+                            ),
+                            field.id.raw,
+                        );
+                    }
+                    continue;
+                }
+            };
+            if field.is_required() && !old_field.is_required() {
+                self.error(
+                    format!(
+                        "field `{}` of table `{}` is required, but is optional in the old version",
+                        field.id.raw,
+                        dotted(name)
+                    ),
+                    field.id.raw,
+                );
+                continue;
+            }
+            let kind = self.new.kind(namespace, &field.ty);
+            if self.old.kind(namespace, &old_field.ty) != kind {
+                self.error(
+                    format!(
+                        "the type of field `{}` of table `{}` changed",
+                        field.id.raw,
+                        dotted(name)
+                    ),
+                    field.id.raw,
+                );
+                continue;
+            }
+            let (read, field_calls) = self.field(namespace, old_field, field, &kind);
+            reads.push(read);
+            calls.extend(field_calls);
+        }
+
+        let adapter = adapter_id(name);
+        let old_path = self.old.type_path(name);
+        let new_path = self.new.type_path(name);
+        let builder = self
+            .new
+            .path(name, format!("{}Builder", self.new.names.name(name)));
+        let body = if calls.is_empty() {
+            quote! {
+                let _ = old;
+                Ok(#builder::new(fbb).finish())
+            }
+        } else {
+            let calls = add_order(new, calls);
+            quote! {
+                #(#reads)*
+                let mut builder = #builder::new(fbb);
+                #(#calls)*
+                Ok(builder.finish())
+            }
+        };
+        let doc = format!(
+            " Adapt a `{}` table of the old version of the schema to the new one.",
+            dotted(name)
+        );
+        let vis = &self.new.options.visibility;
+        quote! {
+            #[doc = #doc]
+            #vis fn #adapter<'b>(fbb: &mut butte::FlatBufferBuilder<'b>, old: &#old_path<'_>) -> butte::Result<butte::WIPOffset<#new_path<'b>>> {
+                #body
+            }
+        }
+    }
+
+    /// The statement reading `old_field` and writing the data it refers to,
+    /// and the calls adding it to the builder of the new table, along with
+    /// the sizes of the slots they write.
+    fn field(
+        &self,
+        namespace: &[&'a str],
+        old_field: &Field<'a>,
+        field: &Field<'a>,
+        kind: &Kind<'a>,
+    ) -> (TokenStream, Vec<(usize, TokenStream)>) {
+        let old_scope = self.old.scope(namespace);
+        let new_scope = self.new.scope(namespace);
+        let accessor = ident(old_field.id.as_ref().to_snake_case());
+        let field_id = field_ident(field, new_scope);
+        let add_method = format_ident!("add_{}", field_id);
+        let add_type_method = format_ident!("add_{}_type", field_id);
+        let local = format_ident!("{}_value", field.id.as_ref().to_snake_case());
+
+        // a union is read as its value, and written as its discriminant and
+        // the offset of its table
+        if let Kind::Union(union) = kind {
+            let adapter = adapter_id(union);
+            let union_type = self.new.union_type_path(union);
+            let type_local = format_ident!("{}_type", local);
+            let read = quote! {
+                let (#type_local, #local) = match old.#accessor()? {
+                    Some(value) => #adapter(fbb, value)?,
+                    None => (#union_type::NONE, None),
+                };
+            };
+            return (
+                read,
+                vec![
+                    (1, quote!(builder.#add_type_method(#type_local);)),
+                    (
+                        butte::SIZE_UOFFSET,
+                        quote! {
+                            if let Some(value) = #local {
+                                builder.#add_method(value);
+                            }
+                        },
+                    ),
+                ],
+            );
+        }
+
+        let always_present =
+            old_scope.default_value(old_field).is_some() || old_field.is_required();
+        let (value, fallible) = match kind {
+            // a vector of unions is written along with the vector of their
+            // discriminants
+            Kind::Vector(element) if matches!(element.as_ref(), Kind::Union(_)) => {
+                let Kind::Union(union) = element.as_ref() else {
+                    unreachable!()
+                };
+                let adapter = adapter_id(union);
+                let value = quote! {{
+                    let mut types = Vec::with_capacity(value.len());
+                    let mut items = Vec::with_capacity(value.len());
+                    for item in value.iter() {
+                        let (ty, item) = #adapter(fbb, item?)?;
+                        types.push(ty);
+                        items.push(item);
+                    }
+                    (fbb.create_vector(&types), fbb.create_vector(&items))
+                }};
+                let call = if always_present {
+                    quote! {
+                        builder.#add_type_method(#local.0);
+                        builder.#add_method(#local.1);
+                    }
+                } else {
+                    quote! {
+                        if let Some((types, values)) = #local {
+                            builder.#add_type_method(types);
+                            builder.#add_method(values);
+                        }
+                    }
+                };
+                let read = read(&local, &accessor, value, true, always_present);
+                return (read, vec![(butte::SIZE_UOFFSET, call)]);
+            }
+            kind => self.value(kind, field),
+        };
+
+        let read = match kind {
+            Kind::Enum(name) if !always_present => {
+                let adapter = adapter_id(name);
+                quote!(let #local = old.#accessor()?.map(#adapter);)
+            }
+            _ => read(&local, &accessor, value, fallible, always_present),
+        };
+        // structs are added by reference, so they must outlive the builder
+        let reference = if matches!(kind, Kind::Struct(_)) {
+            quote!(&)
+        } else {
+            quote!()
+        };
+        let call = if always_present {
+            quote!(builder.#add_method(#reference #local);)
+        } else {
+            quote! {
+                if let Some(value) = #reference #local {
+                    builder.#add_method(value);
+                }
+            }
+        };
+        (read, vec![(new_scope.slot_size(field), call)])
+    }
+
+    /// The expression converting `value`, the value of a field of the old
+    /// table of kind `kind`, to the value of `field` of the new table, and
+    /// whether it uses `?`.
+    fn value(&self, kind: &Kind<'a>, field: &Field<'a>) -> (TokenStream, bool) {
+        let (value, fallible) = match kind {
+            Kind::Scalar(_) => return (quote!(value), false),
+            Kind::Enum(name) => {
+                let adapter = adapter_id(name);
+                return (quote!(#adapter(value)), false);
+            }
+            Kind::Struct(name) => {
+                let adapter = adapter_id(name);
+                return (quote!(#adapter(value)?), true);
+            }
+            Kind::String => (quote!(fbb.create_string(value)), false),
+            Kind::Table(name) => {
+                let adapter = adapter_id(name);
+                (quote!(#adapter(fbb, &value)?), true)
+            }
+            Kind::Vector(element) => {
+                let create = if field.is_vector64() {
+                    quote!(create_vector64)
+                } else {
+                    quote!(create_vector)
+                };
+                let value = match element.as_ref() {
+                    Kind::Scalar(ty) if ty != "bool" => {
+                        quote!(fbb.#create(&value.to_slice()?))
+                    }
+                    Kind::String => quote! {{
+                        let mut items = Vec::with_capacity(value.len());
+                        for item in value.iter() {
+                            items.push(item?);
+                        }
+                        fbb.create_vector_of_strings(&items)
+                    }},
+                    element => {
+                        let item = match element {
+                            Kind::Enum(name) => {
+                                let adapter = adapter_id(name);
+                                quote!(#adapter(item?))
+                            }
+                            Kind::Struct(name) => {
+                                let adapter = adapter_id(name);
+                                quote!(#adapter(item?)?)
+                            }
+                            Kind::Table(name) => {
+                                let adapter = adapter_id(name);
+                                quote!(#adapter(fbb, &item?)?)
+                            }
+                            _ => quote!(item?),
+                        };
+                        quote! {{
+                            let mut items = Vec::with_capacity(value.len());
+                            for item in value.iter() {
+                                items.push(#item);
+                            }
+                            fbb.#create(&items)
+                        }}
+                    }
+                };
+                (value, true)
+            }
+            Kind::Union(_) | Kind::Array(..) => unreachable!("not a field value: {:?}", kind),
+        };
+        if field.is_offset64() && !field.is_vector64() {
+            (quote!(butte::WIPOffset64::from(#value)), fallible)
+        } else {
+            (value, fallible)
+        }
+    }
+
+    /// The adapter of the enum `name`, which maps the values of the `old` enum
+    /// to the values of the `new` one with the same names.
+    fn enum_(&mut self, name: &[&'a str], old: &Enum<'a>, new: &Enum<'a>) -> Option<TokenStream> {
+        if old.is_bit_flags() != new.is_bit_flags() {
+            self.error(
+                format!(
+                    "enum `{}` has the `bit_flags` attribute in only one version",
+                    dotted(name)
+                ),
+                new.id.raw,
+            );
+            return None;
+        }
+        let mut removed = false;
+        for value in &old.values {
+            if !new.values.iter().any(|new_value| new_value.id == value.id) {
+                self.error(
+                    format!(
+                        "value `{}` of enum `{}` isn't in the new version",
+                        value.id.raw,
+                        dotted(name)
+                    ),
+                    value.id.raw,
+                );
+                removed = true;
+            }
+        }
+        if removed {
+            return None;
+        }
+
+        let adapter = adapter_id(name);
+        let old_path = self.old.type_path(name);
+        let new_path = self.new.type_path(name);
+        let keys = old
+            .values
+            .iter()
+            .map(|value| ident(value.id.raw))
+            .collect::<Vec<_>>();
+        let doc = format!(
+            " Adapt a value of the `{}` enum of the old version of the schema to the new one.",
+            dotted(name)
+        );
+        let vis = &self.new.options.visibility;
+        let body = if old.is_bit_flags() {
+            quote! {
+                let mut flags = #new_path::empty();
+                #(
+                    if value.contains(#old_path::#keys) {
+                        flags |= #new_path::#keys;
+                    }
+                )*
+                flags
+            }
+        } else {
+            quote! {
+                match value {
+                    #(#old_path::#keys => #new_path::#keys,)*
+                }
+            }
+        };
+        Some(quote! {
+            #[doc = #doc]
+            #vis fn #adapter(value: #old_path) -> #new_path {
+                #body
+            }
+        })
+    }
+
+    /// The adapter of the struct `name`, which copies an `old` struct to a
+    /// `new` one, as long as they are the same.
+    fn struct_(
+        &mut self,
+        name: &[&'a str],
+        old: &Struct<'a>,
+        new: &Struct<'a>,
+    ) -> Option<TokenStream> {
+        if !self.same_struct(name, old, new) {
+            self.error(
+                format!(
+                    "struct `{}` changed, but structs can't change between versions",
+                    dotted(name)
+                ),
+                new.id.raw,
+            );
+            return None;
+        }
+
+        let adapter = adapter_id(name);
+        let old_path = self.old.type_path(name);
+        let new_path = self.new.type_path(name);
+        let doc = format!(
+            " Adapt a `{}` struct of the old version of the schema to the new one.",
+            dotted(name)
+        );
+        let vis = &self.new.options.visibility;
+        Some(quote! {
+            #[doc = #doc]
+            #vis fn #adapter(value: &#old_path) -> butte::Result<#new_path> {
+                let mut bytes = [0; core::mem::size_of::<#new_path>()];
+                butte::Push::push(value, &mut bytes, &[]);
+                butte::follow_cast_copy::<#new_path>(&bytes, 0)
+            }
+        })
+    }
+
+    /// Check whether the `old` and `new` versions of the struct `name` have
+    /// the same fields and layout, so that its bytes read the same in both.
+    fn same_struct(&self, name: &[&'a str], old: &Struct<'a>, new: &Struct<'a>) -> bool {
+        let namespace = &name[..name.len() - 1];
+        if old.fields.len() != new.fields.len()
+            || self.old.symbols.struct_layout(namespace, old)
+                != self.new.symbols.struct_layout(namespace, new)
+        {
+            return false;
+        }
+        old.fields
+            .iter()
+            .zip(&new.fields)
+            .all(|(old_field, field)| {
+                let kind = self.new.kind(namespace, &field.ty);
+                if old_field.id != field.id || self.old.kind(namespace, &old_field.ty) != kind {
+                    return false;
+                }
+                let element = match &kind {
+                    Kind::Array(element, _) => element.as_ref(),
+                    kind => kind,
+                };
+                match element {
+                    Kind::Struct(name) => match (
+                        self.old.symbols.declarations.get(name),
+                        self.new.symbols.declarations.get(name),
+                    ) {
+                        (Some(Declaration::Struct(old)), Some(Declaration::Struct(new))) => {
+                            self.same_struct(name, old, new)
+                        }
+                        _ => false,
+                    },
+                    // the values of enums are stored as they are
+                    Kind::Enum(name) => match (
+                        self.old.symbols.declarations.get(name),
+                        self.new.symbols.declarations.get(name),
+                    ) {
+                        (Some(Declaration::Enum(old)), Some(Declaration::Enum(new))) => {
+                            enum_values(old) == enum_values(new)
+                        }
+                        _ => false,
+                    },
+                    _ => true,
+                }
+            })
+    }
+
+    /// The adapter of the union `name`, which adapts the table of a value of
+    /// the `old` union and returns it along with its discriminant in the
+    /// `new` one.
+    fn union(&mut self, name: &[&'a str], old: &Union<'a>, new: &Union<'a>) -> Option<TokenStream> {
+        let namespace = &name[..name.len() - 1];
+        let mut keys = vec![];
+        let mut adapters = vec![];
+        let mut valid = true;
+        for value in &old.values {
+            let new_value = new.values.iter().find(|new_value| new_value.id == value.id);
+            let new_value = match new_value {
+                Some(new_value) => new_value,
+                None => {
+                    self.error(
+                        format!(
+                            "value `{}` of union `{}` isn't in the new version",
+                            value.id.raw,
+                            dotted(name)
+                        ),
+                        value.id.raw,
+                    );
+                    valid = false;
+                    continue;
+                }
+            };
+            let table = self.old.kind(namespace, &Type::Ident(value.union_type()));
+            if self
+                .new
+                .kind(namespace, &Type::Ident(new_value.union_type()))
+                != table
+            {
+                self.error(
+                    format!(
+                        "value `{}` of union `{}` holds a different table in the new version",
+                        value.id.raw,
+                        dotted(name)
+                    ),
+                    new_value.id.raw,
+                );
+                valid = false;
+                continue;
+            }
+            let Kind::Table(table) = table else {
+                unreachable!("union of {:?}", table)
+            };
+            keys.push(ident(value.id.raw));
+            adapters.push(adapter_id(&table));
+        }
+        if !valid {
+            return None;
+        }
+
+        let adapter = adapter_id(name);
+        let old_path = self.old.type_path(name);
+        let new_type = self.new.union_type_path(name);
+        let doc = format!(
+            " Adapt a value of the `{}` union of the old version of the schema to the new one, \
+             returning its discriminant and the offset of its table.",
+            dotted(name)
+        );
+        let vis = &self.new.options.visibility;
+        Some(quote! {
+            #[doc = #doc]
+            #vis fn #adapter(fbb: &mut butte::FlatBufferBuilder<'_>, value: #old_path<'_>) -> butte::Result<(#new_type, Option<butte::WIPOffset<butte::UnionWIPOffset>>)> {
+                Ok(match value {
+                    #old_path::NONE => (#new_type::NONE, None),
+                    #(
+                        #old_path::#keys(value) => (
+                            #new_type::#keys,
+                            Some(#adapters(fbb, &value)?.as_union_value()),
+                        ),
+                    )*
+                })
+            }
+        })
+    }
+
+    /// `adapt_buffer`, which migrates a buffer whose root is the table `root`
+    /// in both versions.
+    fn adapt_buffer(&self, root: &[&'a str]) -> TokenStream {
+        let adapter = adapter_id(root);
+        let old_path = self.old.type_path(root);
+        let file_identifier = match &self.new.symbols.file_identifier {
+            Some(id) => quote!(Some(butte::FileIdent::new(#id))),
+            None => quote!(None),
+        };
+        let doc = format!(
+            " Adapt a buffer of the old version of the schema, whose root is a `{}` table, to \
+             the new one. The buffer is verified before it is read.",
+            dotted(root)
+        );
+        let vis = &self.new.options.visibility;
+        quote! {
+            #[doc = #doc]
+            #vis fn adapt_buffer(buf: &[u8]) -> butte::Result<Vec<u8>> {
+                let old = butte::verified_root::<#old_path<'_>>(buf)?;
+                let mut fbb = butte::FlatBufferBuilder::new();
+                let root = #adapter(&mut fbb, &old)?;
+                fbb.finish(root, #file_identifier);
+                Ok(fbb.finished_data().to_vec())
+            }
+        }
+    }
+}
+
+/// The statement reading the field with `accessor` of the old table into
+/// `local`, converted by `value`, which uses `?` if `fallible`.
+fn read(
+    local: &proc_macro2::Ident,
+    accessor: &proc_macro2::Ident,
+    value: TokenStream,
+    fallible: bool,
+    always_present: bool,
+) -> TokenStream {
+    if value.to_string() == "value" {
+        quote!(let #local = old.#accessor()?;)
+    } else if always_present {
+        quote! {
+            let #local = {
+                let value = old.#accessor()?;
+                #value
+            };
+        }
+    } else if fallible {
+        quote! {
+            let #local = match old.#accessor()? {
+                Some(value) => Some(#value),
+                None => None,
+            };
+        }
+    } else {
+        quote!(let #local = old.#accessor()?.map(|value| #value);)
+    }
+}
+
+#[cfg(test)]
+mod adapter_tests {
+    use super::*;
+    use crate::parser::parse_schema;
+
+    fn generate_adapters<'a>(old: &Schema<'a>, new: &Schema<'a>) -> Result<String, Vec<String>> {
+        adapters(
+            old,
+            &syn::parse_quote!(super::v1),
+            new,
+            &syn::parse_quote!(super::v2),
+            &Options::default(),
+        )
+        .map(|code| code.to_string())
+        .map_err(|errors| errors.into_iter().map(|error| error.message).collect())
+    }
+
+    #[test]
+    fn test_table_adapters() {
+        let old = parse_schema(
+            "namespace shop;
+             enum Status: byte { Active, Closed }
+             struct Point { x: int; y: int; }
+             table Item { name: string; }
+             table Store { name: string (required); status: Status; location: Point;
+                           items: [Item]; tags: [string]; legacy: int; }
+             root_type Store;",
+        )
+        .unwrap();
+        let new = parse_schema(
+            "namespace shop;
+             enum Status: byte { Pending, Active, Closed }
+             struct Point { x: int; y: int; }
+             table Item { name: string; price: int = 10; }
+             table Store { items: [Item]; name: string (required); status: Status;
+                           location: Point; tags: [string]; rating: float; }
+             root_type Store;
+             file_identifier \"SHOP\";",
+        )
+        .unwrap();
+        let code = generate_adapters(&old, &new).unwrap();
+        assert!(code.contains("pub fn shop_store < 'b > (fbb : & mut butte :: FlatBufferBuilder < 'b > , old : & super :: v1 :: shop :: Store < '_ >) -> butte :: Result < butte :: WIPOffset < super :: v2 :: shop :: Store < 'b >> >"));
+        assert!(code.contains(
+            "let name_value = { let value = old . name () ? ; fbb . create_string (value) } ;"
+        ));
+        // an enum without a default value defaults to its zero variant
+        assert!(code.contains(
+            "let status_value = { let value = old . status () ? ; shop_status (value) } ;"
+        ));
+        assert!(code.contains("let location_value = match old . location () ? { Some (value) => Some (shop_point (value) ?) , None => None , } ;"));
+        assert!(code.contains("items . push (shop_item (fbb , & item ?) ?) ;"));
+        assert!(code.contains(
+            "if let Some (value) = & location_value { builder . add_location (value) ; }"
+        ));
+        // fields only of the old version are dropped, those only of the new
+        // one are left out
+        assert!(!code.contains("legacy"));
+        assert!(!code.contains("rating"));
+        assert!(!code.contains("price"));
+        assert!(code.contains("match value { super :: v1 :: shop :: Status :: Active => super :: v2 :: shop :: Status :: Active , super :: v1 :: shop :: Status :: Closed => super :: v2 :: shop :: Status :: Closed , }"));
+        assert!(code.contains(
+            "let mut bytes = [0 ; core :: mem :: size_of :: < super :: v2 :: shop :: Point > ()] ;"
+        ));
+        assert!(
+            code.contains("pub fn adapt_buffer (buf : & [u8]) -> butte :: Result < Vec < u8 >>")
+        );
+        assert!(
+            code.contains("fbb . finish (root , Some (butte :: FileIdent :: new (\"SHOP\"))) ;")
+        );
+    }
+
+    #[test]
+    fn test_union_adapters() {
+        let schema = "table A { x: int; } table B { y: int; } union U { A, B }
+                      table T { u: U; us: [U]; }";
+        let old = parse_schema(schema).unwrap();
+        let new = parse_schema(schema).unwrap();
+        let code = generate_adapters(&old, &new).unwrap();
+        assert!(code.contains("super :: v1 :: U :: B (value) => (super :: v2 :: UType :: B , Some (b (fbb , & value) ? . as_union_value ()) ,) ,"));
+        assert!(code.contains("let (u_value_type , u_value) = match old . u () ? { Some (value) => u (fbb , value) ? , None => (super :: v2 :: UType :: NONE , None) , } ;"));
+        assert!(code.contains("if let Some ((types , values)) = us_value { builder . add_us_type (types) ; builder . add_us (values) ; }"));
+        // without a root type there is no adapter for whole buffers
+        assert!(!code.contains("adapt_buffer"));
+    }
+
+    #[test]
+    fn test_incompatible_changes() {
+        let old = parse_schema(
+            "enum E: byte { A, B } union U { T } struct S { x: int; }
+             table T { a: int; b: string; c: [int]; }",
+        )
+        .unwrap();
+        let new = parse_schema(
+            "enum E: byte { A } table V { x: int; } union U { T: V } struct S { x: long; }
+             table T { a: long; b: string (required); c: [int]; d: [ubyte] (required); }",
+        )
+        .unwrap();
+        let errors = generate_adapters(&old, &new).unwrap_err();
+        assert_eq!(
+            errors,
+            [
+                "value `B` of enum `E` isn't in the new version",
+                "value `T` of union `U` holds a different table in the new version",
+                "struct `S` changed, but structs can't change between versions",
+                "the type of field `a` of table `T` changed",
+                "field `b` of table `T` is required, but is optional in the old version",
+                "field `d` of table `T` is required, but isn't in the old version",
+            ]
+        );
+    }
+}
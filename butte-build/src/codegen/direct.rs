@@ -0,0 +1,272 @@
+//! Constructors of tables from Rust values, like flatc's `CreateMonsterDirect`.
+//!
+//! For a table `Monster` this generates a `MonsterDirectArgs` struct, which
+//! holds `&str`s and slices where `MonsterArgs` holds the offsets of strings
+//! and vectors, and `Monster::create_direct` to write them before the table
+//! that refers to them. With the object API, tables and unions are given as
+//! references to their object types, which are packed the same way.
+use super::*;
+
+/// How a field is given to `create_direct`, when that differs from how it is
+/// given to `create`.
+struct Direct {
+    /// The type of the field in the direct args, without the `Option` of
+    /// optional fields.
+    ty: TokenStream,
+    /// The expression writing `value`, of type `ty`, and evaluating to its
+    /// offset in the args.
+    write: TokenStream,
+}
+
+/// How `field` is given to `create_direct`, or `None` if that's the same as
+/// for `create`.
+fn direct(field: &Field, scope: Scope) -> Option<Direct> {
+    let ty = &field.ty;
+    let object_api = scope.options.object_api;
+    let (direct_ty, write) = match ty {
+        Type::String => (quote!(&'a str), quote!(fbb.create_string(value))),
+        Type::Array(element) => match element.as_ref() {
+            Type::String => (
+                quote!(&'a [&'a str]),
+                quote!(fbb.create_vector_of_strings(value)),
+            ),
+            element if scope.is_table(element) || scope.union(element).is_some() => {
+                if !object_api {
+                    return None;
+                }
+                let object_path = scope.object_path(element);
+                let write = object_api::pack_value(ty, quote!(value), scope)?;
+                (quote!(&'a [#object_path]), write)
+            }
+            element => {
+                let element = to_type(element, quote!('a), TypeUsage::Follow, scope);
+                let write = if field.is_vector64() {
+                    quote!(fbb.create_vector64(value))
+                } else {
+                    quote!(fbb.create_vector(value))
+                };
+                (quote!(&'a [#element]), write)
+            }
+        },
+        ty if object_api && scope.is_table(ty) => {
+            let object_path = scope.object_path(ty);
+            (quote!(&'a #object_path), quote!(value.pack(fbb)))
+        }
+        _ => return None,
+    };
+    let write = if field.is_offset64() && !field.is_vector64() {
+        quote!(butte::WIPOffset64::from(#write))
+    } else {
+        write
+    };
+    Some(Direct {
+        ty: direct_ty,
+        write: object_api::share_empty(field, quote!(value), write, scope),
+    })
+}
+
+/// Check whether `field` is a union given as a reference to its object
+/// type, which stands for both the union's value and its type field.
+fn is_object_union(field: &Field, scope: Scope) -> bool {
+    scope.options.object_api && scope.union(&field.ty).is_some()
+}
+
+/// Generate the direct args of `table` and its `create_direct`, unless all of
+/// its fields are given to `create_direct` the same way as to `create`.
+pub(super) fn table(table: &Table, scope: Scope) -> TokenStream {
+    let Table { id, fields, .. } = table;
+    let fields = fields
+        .iter()
+        .filter(|field| !field.is_deprecated())
+        .collect::<Vec<_>>();
+    if !fields
+        .iter()
+        .any(|field| is_object_union(field, scope) || direct(field, scope).is_some())
+    {
+        return quote!();
+    }
+
+    let reader_id = ident(id.raw);
+    let args_id = format_ident!("{}Args", id.raw);
+    let direct_args_id = format_ident!("{}DirectArgs", id.raw);
+
+    // The data the fields refer to is written in the order of the fields,
+    // before the table.
+    let mut direct_fields = vec![];
+    let mut offsets = vec![];
+    let mut args = vec![];
+    let mut defaults = vec![];
+    for field in &fields {
+        let field_id = field_ident(field, scope);
+        let type_id = format_ident!("{}_type", field_id);
+        let local = format_ident!("{}_offset", field_id);
+        let (ty, write) = if is_object_union(field, scope) {
+            let object_path = scope.object_path(&field.ty);
+            (
+                quote!(&'a #object_path),
+                quote!((value.union_type(), value.pack(fbb))),
+            )
+        } else if let Some(Direct { ty, write }) = direct(field, scope) {
+            (ty, write)
+        } else {
+            direct_fields.push(args_field(field, scope));
+            if scope.has_type_field(&field.ty) {
+                args.push(quote!(#type_id: args.#type_id, #field_id: args.#field_id));
+            } else {
+                args.push(quote!(#field_id: args.#field_id));
+            }
+            defaults.push(default_arg(field, scope));
+            continue;
+        };
+
+        let optional = scope.is_optional_arg(field);
+        if optional {
+            direct_fields.push(quote!(pub #field_id: Option<#ty>));
+            offsets.push(if object_api::omits_empty(field, scope) {
+                quote! {
+                    let #local = args
+                        .#field_id
+                        .filter(|value| !value.is_empty())
+                        .map(|value| #write);
+                }
+            } else {
+                quote!(let #local = args.#field_id.map(|value| #write);)
+            });
+            defaults.push(quote!(#field_id: None));
+        } else {
+            direct_fields.push(quote!(pub #field_id: #ty));
+            offsets.push(quote! {
+                let #local = {
+                    let value = args.#field_id;
+                    #write
+                };
+            });
+        }
+        args.push(if is_object_union(field, scope) {
+            let union_type = scope.union_type_path(&field.ty);
+            if field.is_required() {
+                quote!(#type_id: #local.0, #field_id: #local.1)
+            } else {
+                quote! {
+                    #type_id: #local.map_or(#union_type::NONE, |(ty, _)| ty),
+                    #field_id: #local.and_then(|(_, value)| value)
+                }
+            }
+        } else if scope.union_vector(&field.ty).is_some() {
+            // a vector of unions is written along with the vector of their
+            // discriminants
+            if optional {
+                quote! {
+                    #type_id: #local.map(|(types, _)| types),
+                    #field_id: #local.map(|(_, values)| values)
+                }
+            } else {
+                quote!(#type_id: #local.0, #field_id: #local.1)
+            }
+        } else {
+            quote!(#field_id: #local)
+        });
+    }
+
+    let default = fields
+        .iter()
+        .all(|field| has_default_arg(field, scope))
+        .then(|| {
+            quote! {
+                impl core::default::Default for #direct_args_id<'_> {
+                    #[inline]
+                    fn default() -> Self {
+                        Self {
+                            #(#defaults),*
+                        }
+                    }
+                }
+            }
+        });
+
+    let doc = format!(
+        " The fields of a [`{}`] table, given as Rust values rather than as offsets, see [`{}::create_direct`].",
+        id.raw, id.raw
+    );
+    let vis = &scope.options.visibility;
+    quote! {
+        #[doc = #doc]
+        #vis struct #direct_args_id<'a> {
+            #(#direct_fields),*
+        }
+
+        #default
+
+        impl #reader_id<'_> {
+            /// Write a table from Rust values, along with the strings,
+            /// vectors and tables it refers to.
+            pub fn create_direct<'bldr>(
+                fbb: &mut butte::FlatBufferBuilder<'bldr>,
+                args: &#direct_args_id<'_>,
+            ) -> butte::WIPOffset<#reader_id<'bldr>> {
+                #(#offsets)*
+                let table_args = #args_id {
+                    #(#args),*
+                };
+                #reader_id::create(fbb, &table_args)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod direct_tests {
+    use super::*;
+
+    fn generate_direct(source: &str, object_api: bool) -> String {
+        let schema = crate::parser::parse_schema(source).unwrap();
+        let options = Options {
+            object_api,
+            ..Options::default()
+        };
+        generate(&schema, &options).to_string()
+    }
+
+    #[test]
+    fn test_direct_args() {
+        let result = generate_direct(
+            "table Item { name: string (required); count: int; }
+             table Monster { name: string; tags: [string]; hp: short; inventory: [ubyte]; item: Item; }",
+            false,
+        );
+        assert!(result.contains(
+            "pub struct ItemDirectArgs < 'a > { pub name : & 'a str , pub count : i32 }"
+        ));
+        assert!(result.contains(
+            "let name_offset = { let value = args . name ; fbb . create_string (value) } ;"
+        ));
+        assert!(result.contains("pub struct MonsterDirectArgs < 'a > { pub name : Option < & 'a str > , pub tags : Option < & 'a [& 'a str] > , pub hp : i16 , pub inventory : Option < & 'a [u8] > , pub item : Option < butte :: WIPOffset < Item < 'a > > > }"));
+        assert!(result.contains("impl core :: default :: Default for MonsterDirectArgs < '_ >"));
+        assert!(result.contains("let tags_offset = args . tags . map (| value | fbb . create_vector_of_strings (value)) ;"));
+        assert!(result.contains("let table_args = MonsterArgs { name : name_offset , tags : tags_offset , hp : args . hp , inventory : inventory_offset , item : args . item } ;"));
+        // the required string leaves no default
+        assert!(!result.contains("Default for ItemDirectArgs"));
+    }
+
+    #[test]
+    fn test_direct_args_unchanged() {
+        let result = generate_direct("table Point { x: int; y: int; }", false);
+        assert!(!result.contains("PointDirectArgs"));
+    }
+
+    #[test]
+    fn test_direct_args_object_api() {
+        let result = generate_direct(
+            "table Item { count: int; }
+             union Any { Item }
+             table Monster { item: Item; items: [Item]; any: Any; }",
+            true,
+        );
+        assert!(result.contains("pub struct MonsterDirectArgs < 'a > { pub item : Option < & 'a ItemT > , pub items : Option < & 'a [ItemT] > , pub any : Option < & 'a AnyT > }"));
+        assert!(
+            result.contains("let item_offset = args . item . map (| value | value . pack (fbb)) ;")
+        );
+        assert!(result.contains("let any_offset = args . any . map (| value | (value . union_type () , value . pack (fbb))) ;"));
+        assert!(result.contains("any_type : any_offset . map_or (AnyType :: NONE , | (ty , _) | ty) , any : any_offset . and_then (| (_ , value) | value)"));
+    }
+}
@@ -0,0 +1,320 @@
+//! The naming policy of the generated code, see [`Naming`].
+
+use super::*;
+use heck::CamelCase;
+
+/// How the types and fields of a schema are named in the generated code.
+///
+/// Methods, like the accessors of table fields, and constants are always
+/// named after Rust's conventions. Whatever the policy, items and fields
+/// with the `preserve_case` attribute keep the names they have in the
+/// schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Naming {
+    /// Keep the names of types and fields as they are in the schema.
+    #[default]
+    Schema,
+    /// Name types in PascalCase, e.g. `MyMessage` for a table `myMessage`,
+    /// and the fields of args structs, builders and object types in
+    /// snake_case.
+    Idiomatic,
+}
+
+fn preserves_case(metadata: &Option<Metadata>) -> bool {
+    metadata
+        .as_ref()
+        .is_some_and(|metadata| metadata.values.contains_key(&Ident::from("preserve_case")))
+}
+
+/// The Rust identifier for the name of `field` in args structs, builders
+/// and object types.
+pub(super) fn field_ident(field: &Field, scope: Scope) -> proc_macro2::Ident {
+    match scope.options.naming {
+        Naming::Idiomatic if !preserves_case(&field.metadata) => {
+            ident(field.id.raw.to_snake_case())
+        }
+        _ => ident(field.id.raw),
+    }
+}
+
+/// The new names of the types of a schema.
+#[derive(Debug, Default)]
+pub(super) struct TypeNames<'a> {
+    /// The new name of every renamed type, by its fully qualified name.
+    types: HashMap<Vec<&'a str>, String>,
+    /// The new value of the type names given as strings, like that of the
+    /// `nested_flatbuffer` attribute, by the namespace they are given in and
+    /// their value in the schema.
+    strings: HashMap<(Vec<&'a str>, &'a str), String>,
+}
+
+impl<'a> TypeNames<'a> {
+    /// Name the types of `schema` according to `naming`.
+    pub(super) fn new(schema: &Schema<'a>, naming: Naming) -> Self {
+        let mut names = Self::default();
+        if naming == Naming::Schema {
+            return names;
+        }
+
+        let mut namespace = vec![];
+        for element in &schema.elements {
+            let (id, metadata) = match element {
+                Element::Namespace(Namespace { ident, .. }) => {
+                    namespace = ident.parts.iter().map(|part| part.raw).collect();
+                    continue;
+                }
+                Element::Table(t) => (t.id, &t.metadata),
+                Element::Struct(s) => (s.id, &s.metadata),
+                Element::Enum(e) => (e.id, &e.metadata),
+                Element::Union(u) => (u.id, &u.metadata),
+                _ => continue,
+            };
+            let name = id.raw.to_camel_case();
+            if !preserves_case(metadata) && name != id.raw {
+                let mut path = namespace.clone();
+                path.push(id.raw);
+                names.types.insert(path, name);
+            }
+        }
+
+        // Type names given as strings are renamed along with the types they
+        // resolve to.
+        let symbols = SymbolTable::new(&schema.elements);
+        let mut namespace = vec![];
+        for element in &schema.elements {
+            let strings: Vec<&'a str> = match element {
+                Element::Namespace(Namespace { ident, .. }) => {
+                    namespace = ident.parts.iter().map(|part| part.raw).collect();
+                    continue;
+                }
+                Element::Root(Root { typename, .. }) => vec![typename.raw],
+                Element::Table(t) => t
+                    .fields
+                    .iter()
+                    .filter_map(|field| field.nested_flatbuffer())
+                    .collect(),
+                _ => continue,
+            };
+            for string in strings {
+                let id = DottedIdent::from(string.split('.').map(Ident::from).collect::<Vec<_>>());
+                let renamed = names.resolve(&symbols, &namespace, &id);
+                if let Some(renamed) = renamed {
+                    let renamed = renamed
+                        .parts
+                        .iter()
+                        .map(|part| part.raw)
+                        .collect::<Vec<_>>()
+                        .join(".");
+                    names.strings.insert((namespace.clone(), string), renamed);
+                }
+            }
+        }
+        names
+    }
+
+    /// The name of the type whose fully qualified name is `path`, which is
+    /// its new name if it is renamed.
+    pub(super) fn name(&self, path: &[&'a str]) -> &str {
+        match self.types.get(path) {
+            Some(name) => name,
+            None => path.last().expect("empty name"),
+        }
+    }
+
+    /// The renamed reference to the type `id`, if that type is renamed.
+    fn resolve<'b>(
+        &'b self,
+        symbols: &SymbolTable<'b>,
+        namespace: &[&'b str],
+        id: &DottedIdent<'b>,
+    ) -> Option<DottedIdent<'b>>
+    where
+        'a: 'b,
+    {
+        let (path, _) = symbols.resolve(namespace, id)?;
+        let name = self.types.get(path)?;
+        let mut parts = id.parts.clone();
+        *parts.last_mut()? = Ident::from(name.as_str());
+        Some(DottedIdent::from(parts))
+    }
+
+    fn rename_type<'b>(
+        &'b self,
+        symbols: &SymbolTable<'b>,
+        namespace: &[&'b str],
+        ty: &mut Type<'b>,
+    ) where
+        'a: 'b,
+    {
+        match ty {
+            Type::Ident(id) => {
+                if let Some(renamed) = self.resolve(symbols, namespace, id) {
+                    *id = renamed;
+                }
+            }
+            Type::Array(element) | Type::FixedArray(element, _) => {
+                self.rename_type(symbols, namespace, element)
+            }
+            _ => {}
+        }
+    }
+
+    fn rename_string<'b>(&'b self, namespace: &[&'b str], string: &mut &'b str)
+    where
+        'a: 'b,
+    {
+        if let Some(renamed) = self.strings.get(&(namespace.to_vec(), *string)) {
+            *string = renamed;
+        }
+    }
+
+    /// A copy of `schema` with its types, and all references to them, renamed.
+    pub(super) fn rename<'b>(&'b self, schema: &Schema<'b>) -> Schema<'b>
+    where
+        'a: 'b,
+    {
+        let mut schema = schema.clone();
+        if self.types.is_empty() {
+            return schema;
+        }
+
+        let symbols = SymbolTable::new(&schema.elements);
+        let mut namespace = vec![];
+        for element in &mut schema.elements {
+            let rename_id = |id: &mut Ident<'b>, namespace: &Vec<&'b str>| {
+                let mut path = namespace.clone();
+                path.push(id.raw);
+                if let Some(name) = self.types.get(&path) {
+                    id.raw = name;
+                }
+            };
+            match element {
+                Element::Namespace(Namespace { ident, .. }) => {
+                    namespace = ident.parts.iter().map(|part| part.raw).collect();
+                }
+                Element::Table(t) => {
+                    rename_id(&mut t.id, &namespace);
+                    for field in &mut t.fields {
+                        self.rename_type(&symbols, &namespace, &mut field.ty);
+                        let nested_flatbuffer = field.metadata.as_mut().and_then(|metadata| {
+                            metadata.values.get_mut(&Ident::from("nested_flatbuffer"))
+                        });
+                        if let Some(Some(Single::String(root))) = nested_flatbuffer {
+                            self.rename_string(&namespace, root);
+                        }
+                    }
+                }
+                Element::Struct(s) => {
+                    rename_id(&mut s.id, &namespace);
+                    for field in &mut s.fields {
+                        self.rename_type(&symbols, &namespace, &mut field.ty);
+                    }
+                }
+                Element::Enum(e) => rename_id(&mut e.id, &namespace),
+                Element::Union(u) => {
+                    rename_id(&mut u.id, &namespace);
+                    for value in &mut u.values {
+                        if let Some(renamed) =
+                            self.resolve(&symbols, &namespace, &value.union_type())
+                        {
+                            value.ty = Some(renamed);
+                        }
+                    }
+                }
+                Element::Root(Root { typename, .. }) => {
+                    self.rename_string(&namespace, &mut typename.raw)
+                }
+                // Services keep their names, which are part of the paths of
+                // their methods, but the tables they take and return are
+                // renamed.
+                Element::Rpc(rpc) => {
+                    for method in &mut rpc.methods {
+                        for ty in [&mut method.request_type, &mut method.response_type] {
+                            if let Some(renamed) = self.resolve(&symbols, &namespace, ty) {
+                                *ty = renamed;
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        schema
+    }
+}
+
+#[cfg(test)]
+mod naming_tests {
+    use super::*;
+
+    fn generate_idiomatic(source: &str) -> String {
+        let schema = crate::parser::parse_schema(source).unwrap();
+        let options = Options {
+            naming: Naming::Idiomatic,
+            object_api: true,
+            ..Options::default()
+        };
+        generate(&schema, &options).to_string()
+    }
+
+    #[test]
+    fn test_schema_naming() {
+        let schema = crate::parser::parse_schema(
+            "table myMessage { fullName: string; }
+             root_type myMessage;",
+        )
+        .unwrap();
+        let result = generate(&schema, &Options::default()).to_string();
+        assert!(result.contains("pub struct myMessage < 'a >"));
+        assert!(result.contains("pub struct myMessageArgs < 'a > { pub fullName :"));
+        assert!(result.contains("pub fn get_root_as_my_message"));
+    }
+
+    #[test]
+    fn test_idiomatic_naming() {
+        let result = generate_idiomatic(
+            "namespace my_game;
+             enum colorKind : ubyte { red, green }
+             struct vec2 { x: float; y: float; }
+             table myMessage {
+               fullName: string;
+               kind: colorKind;
+               points: [vec2];
+               inner: inner_table;
+             }
+             table inner_table { hitPoints: int; }
+             union anyThing { inner_table, alias: myMessage }
+             table holder { thing: anyThing; rawName: int (preserve_case); }
+             table keep_me (preserve_case) { a: int; }
+             root_type myMessage;",
+        );
+        assert!(result.contains("pub mod my_game {"));
+        assert!(result.contains("pub struct MyMessage < 'a >"));
+        assert!(result.contains("pub struct MyMessageArgs < 'a > { pub full_name :"));
+        assert!(result.contains("pub struct MyMessageBuilder"));
+        assert!(result.contains("pub fn add_full_name"));
+        assert!(result.contains("pub struct MyMessageT { pub full_name :"));
+        assert!(result.contains("pub enum ColorKind { red = 0_u8 , green = 1_u8 }"));
+        assert!(result.contains("pub struct Vec2 {"));
+        assert!(result.contains("pub points : Option < Vec < Vec2 > >"));
+        assert!(result.contains("pub inner : Option < butte :: WIPOffset < InnerTable < 'a > > >"));
+        assert!(result.contains("pub enum AnyThing < 'a >"));
+        assert!(result.contains("inner_table (InnerTable < 'a >) , alias (MyMessage < 'a >) }"));
+        assert!(
+            result.contains("pub struct HolderArgs { pub thing_type : AnyThingType , pub thing :")
+        );
+        assert!(result.contains("pub rawName : i32"));
+        assert!(result.contains("pub struct keep_me < 'a >"));
+        assert!(result.contains("pub fn get_root_as_my_message < 'a > (buf : & 'a [u8]) -> butte :: Result < MyMessage < 'a > >"));
+    }
+
+    #[test]
+    fn test_nested_flatbuffer_naming() {
+        let result = generate_idiomatic(
+            "namespace a;
+             table monsterStats { hp: int; }
+             table monster { stats: [ubyte] (nested_flatbuffer: \"a.monsterStats\"); }",
+        );
+        assert!(result.contains("-> butte :: Result < Option < MonsterStats < 'a >> >"));
+    }
+}
@@ -0,0 +1,613 @@
+//! Owned object types for tables and unions, like flatc's object API.
+//!
+//! For a table `Monster` this generates a `MonsterT` struct holding plain Rust
+//! values, `Monster::unpack` to copy a table out of a buffer and
+//! `MonsterT::pack` to write it to a builder. Unions get an enum with a
+//! variant holding the object of each of their tables, which is converted
+//! from and to the union's reader enum.
+use super::*;
+
+/// How the object API and `create_direct` write strings and vectors that are
+/// empty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmptyCollections {
+    /// Write every empty string and vector, like any other.
+    #[default]
+    Written,
+    /// Refer to a single empty string or vector per buffer, created with
+    /// `FlatBufferBuilder::create_empty_string` and `create_empty_vector`.
+    /// Strings and vectors with 64-bit offsets are still written.
+    Shared,
+    /// Leave out empty strings and vectors of optional fields, and unpack
+    /// ones that are empty as `None`, so that an empty value and a missing
+    /// one are the same. Those of fields that are always present are shared.
+    Omitted,
+}
+
+/// Check whether `field` is a string or vector, whose empty values are
+/// written as `Options::empty_collections` says.
+fn is_collection(field: &Field) -> bool {
+    matches!(field.ty, Type::String | Type::Array(_))
+}
+
+/// Check whether empty values of `field` are left out of its table, and read
+/// as `None`.
+pub(super) fn omits_empty(field: &Field, scope: Scope) -> bool {
+    scope.options.empty_collections == EmptyCollections::Omitted
+        && is_collection(field)
+        && !is_always_present(field, scope)
+}
+
+/// Wrap `write`, the expression writing the string or vector `value` of
+/// `field`, to refer to the shared empty string or vector if it is empty.
+pub(super) fn share_empty(
+    field: &Field,
+    value: TokenStream,
+    write: TokenStream,
+    scope: Scope,
+) -> TokenStream {
+    if scope.options.empty_collections == EmptyCollections::Written
+        || !is_collection(field)
+        || field.is_offset64()
+        || field.is_vector64()
+    {
+        return write;
+    }
+    let empty = match &field.ty {
+        Type::String => quote!(fbb.create_empty_string()),
+        // a vector of unions is written along with the vector of their
+        // discriminants
+        ty if scope.union_vector(ty).is_some() => {
+            quote!((fbb.create_empty_vector(), fbb.create_empty_vector()))
+        }
+        _ => quote!(fbb.create_empty_vector()),
+    };
+    quote! {
+        if #value.is_empty() {
+            #empty
+        } else {
+            #write
+        }
+    }
+}
+
+/// Generate the object type of `table` and the methods converting to and
+/// from it.
+pub(super) fn table(table: &Table, scope: Scope) -> TokenStream {
+    let Table { id, fields, .. } = table;
+    let reader_id = ident(id.raw);
+    let object_id = ident(object_id(id.raw));
+    let builder_id = format_ident!("{}Builder", id.raw);
+    let fields = fields
+        .iter()
+        .filter(|field| !field.is_deprecated())
+        .collect::<Vec<_>>();
+
+    let object_fields = fields.iter().map(|field| {
+        let field_id = field_ident(field, scope);
+        let ty = object_field_type(field, scope);
+        let doc = &field.doc;
+        quote!(#doc pub #field_id: #ty)
+    });
+
+    let unpacked_fields = fields.iter().map(|field| {
+        let field_id = field_ident(field, scope);
+        let value = unpack_field(field, scope);
+        quote!(#field_id: #value)
+    });
+
+    let offsets = fields.iter().filter_map(|field| pack_offset(field, scope));
+
+    // Fields are added in the same order as in `create`.
+    let add_calls = fields
+        .iter()
+        .flat_map(|field| add_calls(field, scope))
+        .collect::<Vec<_>>();
+    let add_calls = add_order(table, add_calls);
+
+    let doc = format!(" An owned copy of a [`{}`] table.", id.raw);
+    let derives = derives(
+        &["Debug", "Clone", "PartialEq"],
+        &scope.options.object_derives,
+    );
+    let vis = &scope.options.visibility;
+    quote! {
+        #[doc = #doc]
+        #derives
+        #vis struct #object_id {
+            #(#object_fields),*
+        }
+
+        impl #reader_id<'_> {
+            /// Copy the table out of its buffer.
+            pub fn unpack(&self) -> butte::Result<#object_id> {
+                Ok(#object_id {
+                    #(#unpacked_fields),*
+                })
+            }
+        }
+
+        impl #object_id {
+            /// Write the object to `fbb` as a table.
+            pub fn pack<'b>(
+                &self,
+                fbb: &mut butte::FlatBufferBuilder<'b>
+            ) -> butte::WIPOffset<#reader_id<'b>> {
+                #(#offsets)*
+                let mut builder = #builder_id::new(fbb);
+                #(#add_calls)*
+                builder.finish()
+            }
+        }
+    }
+}
+
+/// Generate the object type of `union`, an enum with a variant for each of
+/// its tables.
+pub(super) fn union(union: &Union, scope: Scope) -> TokenStream {
+    let Union { id, values, .. } = union;
+    let object_id = ident(object_id(id.raw));
+    let union_ty = Type::Ident(DottedIdent::from(vec![*id]));
+    let variants = values.iter().map(|value| {
+        let variant = &value.id;
+        let path = scope.union_variant_object_path(&union_ty, value);
+        quote!(#variant(Box<#path>))
+    });
+    let reader_id = ident(id.raw);
+    let union_type = union_type_id(id);
+    let keys = values
+        .iter()
+        .map(|EnumVal { id: variant, .. }| variant)
+        .collect::<Vec<_>>();
+    let doc = format!(" An owned copy of a value of the `{}` union.", id.raw);
+    let derives = derives(
+        &["Debug", "Clone", "PartialEq", "Default"],
+        &scope.options.object_derives,
+    );
+    let vis = &scope.options.visibility;
+    quote! {
+        #[doc = #doc]
+        #derives
+        #[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+        #vis enum #object_id {
+            #[default]
+            NONE,
+            #(#variants),*
+        }
+
+        impl #reader_id<'_> {
+            /// Copy the value out of its buffer.
+            pub fn unpack(&self) -> butte::Result<#object_id> {
+                Ok(match self {
+                    #reader_id::NONE => #object_id::NONE,
+                    #(#reader_id::#keys(value) => #object_id::#keys(Box::new(value.unpack()?)),)*
+                })
+            }
+        }
+
+        impl #object_id {
+            /// The discriminant of the value.
+            #[inline]
+            pub fn union_type(&self) -> #union_type {
+                match self {
+                    #object_id::NONE => #union_type::NONE,
+                    #(#object_id::#keys(_) => #union_type::#keys,)*
+                }
+            }
+
+            /// Write the table holding the value to `fbb`, if there is one.
+            pub fn pack(
+                &self,
+                fbb: &mut butte::FlatBufferBuilder<'_>
+            ) -> Option<butte::WIPOffset<butte::UnionWIPOffset>> {
+                match self {
+                    #object_id::NONE => None,
+                    #(#object_id::#keys(value) => Some(value.pack(fbb).as_union_value()),)*
+                }
+            }
+        }
+    }
+}
+
+/// Check whether the accessor of `field` returns a value rather than an
+/// `Option`.
+fn is_always_present(field: &Field, scope: Scope) -> bool {
+    scope.default_value(field).is_some() || field.is_required()
+}
+
+/// The type of `field` in the object type of its table.
+fn object_field_type(field: &Field, scope: Scope) -> TokenStream {
+    let ty = &field.ty;
+    if scope.union(ty).is_some() {
+        return scope.object_path(ty);
+    }
+    let object_ty = if scope.is_table(ty) {
+        let path = scope.object_path(ty);
+        quote!(Box<#path>)
+    } else {
+        object_type(ty, scope)
+    };
+    if is_always_present(field, scope) {
+        object_ty
+    } else {
+        quote!(Option<#object_ty>)
+    }
+}
+
+/// The owned type of a value of type `ty`.
+fn object_type(ty: &Type, scope: Scope) -> TokenStream {
+    match ty {
+        Type::String => quote!(String),
+        Type::Array(element) => {
+            let element = object_type(element, scope);
+            quote!(Vec<#element>)
+        }
+        ty if scope.is_table(ty) || scope.union(ty).is_some() => scope.object_path(ty),
+        ty => scope.type_path(ty),
+    }
+}
+
+/// The expression that reads `field` into its object type.
+fn unpack_field(field: &Field, scope: Scope) -> TokenStream {
+    let ty = &field.ty;
+    let accessor = ident(field.id.as_ref().to_snake_case());
+    if let Some(union) = scope.union(ty) {
+        let type_accessor = format_ident!("{}_type", field.id.as_ref().to_snake_case());
+        let union_type = scope.union_type_path(ty);
+        let object_path = scope.object_path(ty);
+        let variants = union.values.iter().map(|EnumVal { id: variant, .. }| {
+            let variant_accessor = format_ident!(
+                "{}_as_{}",
+                field.id.as_ref().to_snake_case(),
+                variant.as_ref().to_snake_case()
+            );
+            quote! {
+                #union_type::#variant => match self.#variant_accessor()? {
+                    Some(value) => #object_path::#variant(Box::new(value.unpack()?)),
+                    None => #object_path::NONE,
+                }
+            }
+        });
+        return quote! {
+            match self.#type_accessor()? {
+                #(#variants,)*
+                _ => #object_path::NONE,
+            }
+        };
+    }
+
+    let accessor = if omits_empty(field, scope) {
+        quote!(self.#accessor()?.filter(|value| !value.is_empty()))
+    } else {
+        quote!(self.#accessor()?)
+    };
+    if scope.is_struct(ty) {
+        return if is_always_present(field, scope) {
+            quote!(*#accessor)
+        } else {
+            quote!(#accessor.copied())
+        };
+    }
+    let (value, fallible) = match unpack_value(ty, quote!(value), scope) {
+        Some(conversion) => conversion,
+        None => return accessor,
+    };
+    let value = if scope.is_table(ty) {
+        quote!(Box::new(#value))
+    } else {
+        value
+    };
+    if is_always_present(field, scope) {
+        quote! {{
+            let value = #accessor;
+            #value
+        }}
+    } else if fallible {
+        quote! {
+            match #accessor {
+                Some(value) => Some(#value),
+                None => None,
+            }
+        }
+    } else {
+        quote!(#accessor.map(|value| #value))
+    }
+}
+
+/// The expression that converts `value`, as returned by an accessor of type
+/// `ty`, to its object type, and whether it uses `?`. Returns `None` if the
+/// accessor already returns the object type.
+fn unpack_value(ty: &Type, value: TokenStream, scope: Scope) -> Option<(TokenStream, bool)> {
+    Some(match ty {
+        Type::String => (quote!(#value.to_string()), false),
+        Type::Array(element) => {
+            // vectors of scalars are copied in one go where they can be
+            if element.is_scalar() && **element != Type::Bool {
+                return Some((quote!(#value.to_slice()?.into_owned()), true));
+            }
+            let convert = match element.as_ref() {
+                Type::String => quote!(.map(|item| item.map(str::to_string))),
+                ty if scope.is_table(ty) || scope.union(ty).is_some() => {
+                    quote!(.map(|item| item.and_then(|item| item.unpack())))
+                }
+                ty if scope.is_struct(ty) => quote!(.map(|item| item.copied())),
+                _ => quote!(),
+            };
+            (
+                quote!(#value.iter()#convert.collect::<butte::Result<Vec<_>>>()?),
+                true,
+            )
+        }
+        ty if scope.is_table(ty) => (quote!(#value.unpack()?), true),
+        _ => return None,
+    })
+}
+
+/// The name of the local variable holding the offset of `field` while its
+/// table is packed.
+fn offset_local(field: &Field) -> proc_macro2::Ident {
+    format_ident!("{}_offset", field.id.as_ref().to_snake_case())
+}
+
+/// The statement writing the data `field` refers to, before its table is
+/// started. Inline fields don't need one.
+fn pack_offset(field: &Field, scope: Scope) -> Option<TokenStream> {
+    let field_id = field_ident(field, scope);
+    let local = offset_local(field);
+    let ty = &field.ty;
+    if scope.union(ty).is_some() {
+        let type_local = format_ident!("{}_type", local);
+        let union = scope.union(ty).expect("union");
+        let union_type = scope.union_type_path(ty);
+        let object_path = scope.object_path(ty);
+        let variants = union.values.iter().map(|EnumVal { id: variant, .. }| {
+            quote! {
+                #object_path::#variant(value) => (
+                    #union_type::#variant,
+                    Some(value.pack(fbb).as_union_value()),
+                )
+            }
+        });
+        return Some(quote! {
+            let (#type_local, #local) = match &self.#field_id {
+                #object_path::NONE => (#union_type::NONE, None),
+                #(#variants),*
+            };
+        });
+    }
+
+    let value = if field.is_vector64() {
+        quote!(fbb.create_vector64(value))
+    } else if field.is_offset64() {
+        let value = pack_value(ty, quote!(value), scope)?;
+        quote!(butte::WIPOffset64::from(#value))
+    } else {
+        pack_value(ty, quote!(value), scope)?
+    };
+    let value = share_empty(field, quote!(value), value, scope);
+    Some(if is_always_present(field, scope) {
+        quote! {
+            let #local = {
+                let value = &self.#field_id;
+                #value
+            };
+        }
+    } else if omits_empty(field, scope) {
+        quote! {
+            let #local = self
+                .#field_id
+                .as_ref()
+                .filter(|value| !value.is_empty())
+                .map(|value| #value);
+        }
+    } else {
+        quote! {
+            let #local = self.#field_id.as_ref().map(|value| #value);
+        }
+    })
+}
+
+/// The expression that writes `value`, a reference to an object value of
+/// type `ty`, to `fbb`, if it is stored behind an offset.
+pub(super) fn pack_value(ty: &Type, value: TokenStream, scope: Scope) -> Option<TokenStream> {
+    Some(match ty {
+        Type::String => quote!(fbb.create_string(#value)),
+        Type::Array(element) => match element.as_ref() {
+            Type::String => quote! {{
+                let items = #value.iter().map(String::as_str).collect::<Vec<_>>();
+                fbb.create_vector_of_strings(&items)
+            }},
+            ty if scope.is_table(ty) => quote! {{
+                let items = #value.iter().map(|item| item.pack(fbb)).collect::<Vec<_>>();
+                fbb.create_vector(&items)
+            }},
+            // a vector of unions is written along with the vector of their
+            // discriminants
+            ty if scope.union(ty).is_some() => {
+                let object_path = scope.object_path(ty);
+                quote! {{
+                    let types = #value.iter().map(#object_path::union_type).collect::<Vec<_>>();
+                    let items = #value.iter().map(|item| item.pack(fbb)).collect::<Vec<_>>();
+                    (fbb.create_vector(&types), fbb.create_vector(&items))
+                }}
+            }
+            _ => quote!(fbb.create_vector(#value)),
+        },
+        ty if scope.is_table(ty) => quote!(#value.pack(fbb)),
+        _ => return None,
+    })
+}
+
+/// The calls adding `field` to the table builder, along with the size of the
+/// slots they write.
+fn add_calls(field: &Field, scope: Scope) -> Vec<(usize, TokenStream)> {
+    let field_id = field_ident(field, scope);
+    let ty = &field.ty;
+    let add_method = format_ident!("add_{}", field_id);
+    let local = offset_local(field);
+    if scope.union(ty).is_some() {
+        let add_type_method = format_ident!("add_{}_type", field_id);
+        let type_local = format_ident!("{}_type", local);
+        return vec![
+            (1, quote!(builder.#add_type_method(#type_local);)),
+            (
+                butte::SIZE_UOFFSET,
+                quote! {
+                    if let Some(value) = #local {
+                        builder.#add_method(value);
+                    }
+                },
+            ),
+        ];
+    }
+
+    if scope.union_vector(ty).is_some() {
+        let add_type_method = format_ident!("add_{}_type", field_id);
+        let call = if is_always_present(field, scope) {
+            quote! {
+                builder.#add_type_method(#local.0);
+                builder.#add_method(#local.1);
+            }
+        } else {
+            quote! {
+                if let Some((types, values)) = #local {
+                    builder.#add_type_method(types);
+                    builder.#add_method(values);
+                }
+            }
+        };
+        return vec![(butte::SIZE_UOFFSET, call)];
+    }
+
+    let size = scope.slot_size(field);
+    let inline = pack_value(ty, quote!(value), scope).is_none();
+    let value = match (inline, scope.is_struct(ty)) {
+        (false, _) => quote!(#local),
+        (true, true) => quote!(&self.#field_id),
+        (true, false) => quote!(self.#field_id),
+    };
+    let call = if is_always_present(field, scope) {
+        quote!(builder.#add_method(#value);)
+    } else {
+        quote! {
+            if let Some(value) = #value {
+                builder.#add_method(value);
+            }
+        }
+    };
+    vec![(size, call)]
+}
+
+#[cfg(test)]
+mod object_api_tests {
+    use super::*;
+    use crate::parser::parse_schema;
+
+    fn generate_objects(object_api: bool) -> String {
+        let schema = parse_schema(
+            "table Cat { name: string (required); lives: int = 9; }
+             table Dog { names: [string]; friend: Dog; }
+             union Animal { Cat, Dog }
+             table Pet { animal: Animal; }
+             table Shelter { animals: [Animal]; }
+             table Kennel { sizes: [ushort]; open: [bool]; }",
+        )
+        .unwrap();
+        let options = Options {
+            object_api,
+            ..Options::default()
+        };
+        generate(&schema, &options).to_string()
+    }
+
+    #[test]
+    fn test_object_types() {
+        let code = generate_objects(true);
+        assert!(code.contains("pub struct CatT { pub name : String , pub lives : i32 }"));
+        assert!(code.contains(
+            "pub struct DogT { pub names : Option < Vec < String > > , pub friend : Option < Box < DogT > > }"
+        ));
+        assert!(code.contains(
+            "pub enum AnimalT { # [default] NONE , Cat (Box < CatT >) , Dog (Box < DogT >) }"
+        ));
+        assert!(code.contains("pub struct PetT { pub animal : AnimalT }"));
+    }
+
+    #[test]
+    fn test_pack_and_unpack() {
+        let code = generate_objects(true);
+        assert!(code.contains("pub fn unpack (& self) -> butte :: Result < CatT >"));
+        assert!(code.contains("pub fn pack < 'b > (& self , fbb : & mut butte :: FlatBufferBuilder < 'b >) -> butte :: WIPOffset < Cat < 'b >>"));
+        assert!(code.contains("AnimalT :: Cat (value) => (AnimalType :: Cat , Some (value . pack (fbb) . as_union_value ()) ,)"));
+    }
+
+    #[test]
+    fn test_union_vectors() {
+        let code = generate_objects(true);
+        assert!(code.contains("pub struct ShelterT { pub animals : Option < Vec < AnimalT > > }"));
+        assert!(code.contains(
+            "Animal :: Cat (value) => AnimalT :: Cat (Box :: new (value . unpack () ?))"
+        ));
+        assert!(code.contains("AnimalT :: Dog (_) => AnimalType :: Dog"));
+        assert!(code.contains("let types = value . iter () . map (AnimalT :: union_type) . collect :: < Vec < _ >> () ;"));
+        assert!(code.contains("if let Some ((types , values)) = animals_offset { builder . add_animals_type (types) ; builder . add_animals (values) ; }"));
+    }
+
+    #[test]
+    fn test_scalar_vectors() {
+        let code = generate_objects(true);
+        assert!(code.contains("Some (value . to_slice () ? . into_owned ())"));
+        assert!(code
+            .contains("Some (value . iter () . collect :: < butte :: Result < Vec < _ >> > () ?)"));
+    }
+
+    #[test]
+    fn test_object_derives() {
+        let schema = parse_schema("table Cat { lives: int; } union Animal { Cat }").unwrap();
+        let options = Options {
+            object_api: true,
+            object_derives: vec![syn::parse_quote!(Eq), syn::parse_quote!(Hash)],
+            ..Options::default()
+        };
+        let code = generate(&schema, &options).to_string();
+        assert!(code.contains("# [derive (Debug , Clone , PartialEq , Eq , Hash)] pub struct CatT"));
+        assert!(code.contains(
+            "# [derive (Debug , Clone , PartialEq , Default , Eq , Hash)] # [allow (non_camel_case_types , clippy :: upper_case_acronyms)] pub enum AnimalT"
+        ));
+    }
+
+    #[test]
+    fn test_empty_collections() {
+        let schema =
+            parse_schema("table Dog { name: string (required); tags: [string]; }").unwrap();
+        let generate_objects = |empty_collections| {
+            let options = Options {
+                object_api: true,
+                empty_collections,
+                ..Options::default()
+            };
+            generate(&schema, &options).to_string()
+        };
+
+        let code = generate_objects(EmptyCollections::Written);
+        assert!(!code.contains("is_empty"));
+
+        let code = generate_objects(EmptyCollections::Shared);
+        assert!(code.contains(
+            "let name_offset = { let value = & self . name ; if value . is_empty () { fbb . create_empty_string () } else { fbb . create_string (value) } } ;"
+        ));
+        assert!(code.contains("if value . is_empty () { fbb . create_empty_vector () }"));
+
+        let code = generate_objects(EmptyCollections::Omitted);
+        assert!(code.contains(
+            "let tags_offset = self . tags . as_ref () . filter (| value | ! value . is_empty ())"
+        ));
+        assert!(code.contains("self . tags () ? . filter (| value | ! value . is_empty ())"));
+        assert!(code.contains("fbb . create_empty_string ()"));
+    }
+
+    #[test]
+    fn test_disabled_by_default() {
+        assert!(!generate_objects(false).contains("CatT"));
+    }
+}
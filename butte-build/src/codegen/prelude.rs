@@ -0,0 +1,144 @@
+//! A `prelude` module re-exporting the readers of a schema, see
+//! `Options::prelude`, so they can be imported without spelling out the
+//! namespaces they are declared in.
+use std::collections::HashSet;
+
+use super::*;
+
+/// A type declared by the schema, in the namespace it is declared in.
+struct Declared<'a, 'b> {
+    namespace: Vec<&'a str>,
+    element: &'b Element<'a>,
+}
+
+impl<'a> Declared<'a, '_> {
+    fn name(&self) -> Vec<&'a str> {
+        let id = match self.element {
+            Element::Table(Table { id, .. })
+            | Element::Struct(Struct { id, .. })
+            | Element::Enum(Enum { id, .. })
+            | Element::Union(Union { id, .. }) => id,
+            _ => unreachable!(),
+        };
+        [self.namespace.as_slice(), &[id.raw]].concat()
+    }
+
+    /// The types the code of the type refers to.
+    fn references(&self) -> Vec<DottedIdent<'a>> {
+        let field_type = |ty: &Type<'a>| match ty {
+            Type::Array(element) | Type::FixedArray(element, _) => match element.as_ref() {
+                Type::Ident(id) => Some(id.clone()),
+                _ => None,
+            },
+            Type::Ident(id) => Some(id.clone()),
+            _ => None,
+        };
+        match self.element {
+            Element::Table(Table { fields, .. }) => fields
+                .iter()
+                .flat_map(|field| {
+                    let nested = field.nested_flatbuffer().map(DottedIdent::from);
+                    field_type(&field.ty).into_iter().chain(nested)
+                })
+                .collect(),
+            Element::Struct(Struct { fields, .. }) => fields
+                .iter()
+                .filter_map(|field| field_type(&field.ty))
+                .collect(),
+            Element::Union(Union { values, .. }) => {
+                values.iter().map(|value| value.union_type()).collect()
+            }
+            _ => vec![],
+        }
+    }
+}
+
+/// Generate the `prelude` module of a schema, which re-exports the readers of
+/// the tables, structs, enums and unions reachable from the schema's root
+/// type, or all of them if it has none. Names declared in more than one
+/// namespace are left out, since they would clash.
+pub(super) fn prelude(
+    elements: &[Element],
+    symbols: &SymbolTable,
+    options: &Options,
+) -> TokenStream {
+    let mut namespace = vec![];
+    let mut declared = vec![];
+    let mut roots = vec![];
+    for element in elements {
+        match element {
+            Element::Namespace(Namespace { ident, .. }) => {
+                namespace = ident.parts.iter().map(|part| part.raw).collect();
+            }
+            Element::Table(_) | Element::Struct(_) | Element::Enum(_) | Element::Union(_) => {
+                declared.push(Declared {
+                    namespace: namespace.clone(),
+                    element,
+                });
+            }
+            Element::Root(Root { typename, .. }) => {
+                roots.push((namespace.clone(), DottedIdent::from(vec![*typename])));
+            }
+            _ => {}
+        }
+    }
+
+    // Walk the types referred to from the root type, resolving each reference
+    // from the namespace of the type making it.
+    let exported = if roots.is_empty() {
+        declared.iter().collect::<Vec<_>>()
+    } else {
+        let mut reachable = HashSet::new();
+        let mut pending = roots;
+        while let Some((namespace, id)) = pending.pop() {
+            let name = match symbols.resolve(&namespace, &id) {
+                Some((name, _)) => name.to_vec(),
+                None => continue,
+            };
+            if !reachable.insert(name.clone()) {
+                continue;
+            }
+            if let Some(item) = declared.iter().find(|item| item.name() == name) {
+                pending.extend(
+                    item.references()
+                        .into_iter()
+                        .map(|id| (item.namespace.clone(), id)),
+                );
+            }
+        }
+        declared
+            .iter()
+            .filter(|item| reachable.contains(&item.name()))
+            .collect()
+    };
+
+    let exports = exported
+        .iter()
+        .flat_map(|item| {
+            let path = item.namespace.iter().map(|&part| Ident::from(part));
+            let path = quote!(super #(::#path)*);
+            match item.element {
+                Element::Union(Union { id, .. }) => {
+                    let type_id = union_type_id(id);
+                    vec![(ident(id.raw), path.clone()), (type_id, path)]
+                }
+                Element::Table(Table { id, .. })
+                | Element::Struct(Struct { id, .. })
+                | Element::Enum(Enum { id, .. }) => vec![(ident(id.raw), path)],
+                _ => unreachable!(),
+            }
+        })
+        .collect::<Vec<_>>();
+    let exports = exports
+        .iter()
+        .filter(|(id, _)| exports.iter().filter(|(other, _)| other == id).count() == 1);
+    let vis = &options.visibility;
+    let uses = exports.map(|(id, path)| quote!(#vis use #path::#id;));
+    quote! {
+        /// The readers of the schema, re-exported from their namespaces.
+        #[allow(unused_imports)]
+        #vis mod prelude {
+            #(#uses)*
+        }
+    }
+}
@@ -0,0 +1,265 @@
+//! Round-trip tests for tables, see `Options::round_trip_tests`.
+//!
+//! Each table gets a [proptest](https://docs.rs/proptest) test that builds it
+//! from `Args` generated by strategies derived from the schema, reads the
+//! buffer back and checks each field against the value it was built with.
+//! Scalars, enums, strings and vectors of them are filled in, the other
+//! fields are left out. A failure is shrunk to a minimal table, and the crate
+//! the tests are generated into needs `proptest` as a dev-dependency.
+use super::*;
+
+/// The pieces of a test that deal with a single field.
+struct FieldTest {
+    /// The argument of the test holding the value of the field, with the
+    /// strategy generating it.
+    argument: TokenStream,
+
+    /// Statements writing what the field refers to, e.g. a string, to the
+    /// builder.
+    create: TokenStream,
+
+    /// The field's initializers in the `Args` of the table.
+    args: TokenStream,
+
+    /// Statements checking the value read back.
+    check: TokenStream,
+}
+
+/// Generate the round-trip test of `table`, unless none of its fields can be
+/// filled in or it has a required field that can't be.
+pub(super) fn table(table: &Table, scope: Scope) -> TokenStream {
+    let Table { id, fields, .. } = table;
+    let table_id = ident(id.raw);
+    let args_id = format_ident!("{}Args", id.raw);
+    let test_id = format_ident!("{}_round_trip", id.raw.to_snake_case());
+
+    let fields = match fields
+        .iter()
+        .filter(|field| !field.is_deprecated())
+        .map(|field| field_test(field, scope))
+        .collect::<Option<Vec<_>>>()
+    {
+        Some(fields) => fields,
+        None => return quote!(),
+    };
+    if fields.iter().all(|field| field.check.is_empty()) {
+        return quote!();
+    }
+    let arguments = fields
+        .iter()
+        .map(|field| &field.argument)
+        .filter(|argument| !argument.is_empty());
+    let create = fields.iter().map(|field| &field.create);
+    let args = fields.iter().map(|field| &field.args);
+    let checks = fields.iter().map(|field| &field.check);
+
+    quote! {
+        #[cfg(test)]
+        proptest::proptest! {
+            #[test]
+            fn #test_id(#(#arguments),*) {
+                let mut builder = butte::FlatBufferBuilder::new();
+                #(#create)*
+                let root = #table_id::create(&mut builder, &#args_id { #(#args),* });
+                builder.finish_minimal(root);
+                let table = butte::get_root::<#table_id<'_>>(builder.finished_data()).unwrap();
+                #(#checks)*
+            }
+        }
+    }
+}
+
+/// The test of `field`, or `None` if the field is required but can't be
+/// filled in.
+fn field_test(field: &Field, scope: Scope) -> Option<FieldTest> {
+    let field_id = field_ident(field, scope);
+    let snake_name = ident(field.id.raw.to_snake_case());
+    let value_id = format_ident!("{}_value", field.id.raw.to_snake_case());
+    let offset_id = format_ident!("{}_offset", field.id.raw.to_snake_case());
+    let ty = &field.ty;
+    let optional = scope.is_optional_arg(field);
+    // Optional fields may be left out of a table.
+    let maybe = |strategy: TokenStream| {
+        if optional {
+            quote!(#value_id in proptest::option::of(#strategy))
+        } else {
+            quote!(#value_id in #strategy)
+        }
+    };
+
+    if scope.is_scalar(ty) {
+        return Some(FieldTest {
+            argument: maybe(scalar_strategy(ty, scope)),
+            create: quote!(),
+            args: quote!(#field_id: #value_id),
+            check: quote!(proptest::prop_assert_eq!(table.#snake_name().unwrap(), #value_id);),
+        });
+    }
+
+    if field.is_offset64() {
+        return left_out(field, scope);
+    }
+
+    match ty {
+        Type::String => {
+            let (offset, expected) = if optional {
+                (
+                    quote!(#value_id.as_deref().map(|value| builder.create_string(value))),
+                    quote!(#value_id.as_deref()),
+                )
+            } else {
+                (quote!(builder.create_string(&#value_id)), quote!(#value_id))
+            };
+            Some(FieldTest {
+                argument: maybe(quote!(proptest::prelude::any::<String>())),
+                create: quote!(let #offset_id = #offset;),
+                args: quote!(#field_id: #offset_id),
+                check: quote!(proptest::prop_assert_eq!(table.#snake_name().unwrap(), #expected);),
+            })
+        }
+        Type::Array(element) if scope.is_scalar(element) || **element == Type::String => {
+            let (element_strategy, create, read) = if **element == Type::String {
+                (
+                    quote!(proptest::prelude::any::<String>()),
+                    quote! {{
+                        let offsets = value
+                            .iter()
+                            .map(|s| builder.create_string(s))
+                            .collect::<Vec<_>>();
+                        builder.create_vector(&offsets)
+                    }},
+                    quote!(|v| v.iter().map(|s| s.unwrap().to_string()).collect::<Vec<_>>()),
+                )
+            } else {
+                (
+                    scalar_strategy(element, scope),
+                    quote!(builder.create_vector(value)),
+                    quote!(|v| v.iter().collect::<butte::Result<Vec<_>>>().unwrap()),
+                )
+            };
+            let (offset, check) = if optional {
+                (
+                    quote!(#value_id.as_ref().map(|value| #create)),
+                    quote! {
+                        proptest::prop_assert_eq!(table.#snake_name().unwrap().map(#read), #value_id);
+                    },
+                )
+            } else {
+                (
+                    quote!({ let value = &#value_id; #create }),
+                    quote! {
+                        proptest::prop_assert_eq!((#read)(table.#snake_name().unwrap()), #value_id);
+                    },
+                )
+            };
+            Some(FieldTest {
+                argument: maybe(quote!(proptest::collection::vec(#element_strategy, 0..8))),
+                create: quote!(let #offset_id = #offset;),
+                args: quote!(#field_id: #offset_id),
+                check,
+            })
+        }
+        _ => left_out(field, scope),
+    }
+}
+
+/// The test of a field that isn't filled in, which must not be required.
+fn left_out(field: &Field, scope: Scope) -> Option<FieldTest> {
+    if field.is_required() {
+        return None;
+    }
+    let field_id = field_ident(field, scope);
+    let args = if scope.union(&field.ty).is_some() {
+        let type_id = format_ident!("{}_type", field_id);
+        let union_type = scope.union_type_path(&field.ty);
+        quote!(#type_id: #union_type::NONE, #field_id: None)
+    } else if scope.union_vector(&field.ty).is_some() {
+        let type_id = format_ident!("{}_type", field_id);
+        quote!(#type_id: None, #field_id: None)
+    } else {
+        quote!(#field_id: None)
+    };
+    Some(FieldTest {
+        argument: quote!(),
+        create: quote!(),
+        args,
+        check: quote!(),
+    })
+}
+
+/// A strategy generating values of the scalar or enum `ty`. Floats are never
+/// NaN, which doesn't compare equal to itself.
+fn scalar_strategy(ty: &Type, scope: Scope) -> TokenStream {
+    let path = scope.type_path(ty);
+    match ty {
+        Type::Float | Type::Double | Type::Float32 | Type::Float64 => quote! {
+            proptest::num::#path::POSITIVE
+                | proptest::num::#path::NEGATIVE
+                | proptest::num::#path::NORMAL
+                | proptest::num::#path::SUBNORMAL
+                | proptest::num::#path::ZERO
+                | proptest::num::#path::INFINITE
+        },
+        Type::Ident(id) => match scope.resolve(id) {
+            Some(Declaration::Enum(e)) if e.is_bit_flags() => {
+                let bits = scalar_strategy(&e.base_type, scope);
+                quote!(proptest::strategy::Strategy::prop_map(#bits, #path::from_bits_truncate))
+            }
+            Some(Declaration::Enum(_)) => {
+                quote!(proptest::sample::select(#path::variants().collect::<Vec<_>>()))
+            }
+            _ => unreachable!("scalar type that isn't an enum: {:?}", ty),
+        },
+        _ => quote!(proptest::prelude::any::<#path>()),
+    }
+}
+
+#[cfg(test)]
+mod round_trip_tests {
+    use super::*;
+    use crate::parser::parse_schema;
+
+    fn generate_tests(schema: &str) -> String {
+        let schema = parse_schema(schema).unwrap();
+        let options = Options {
+            round_trip_tests: true,
+            ..Options::default()
+        };
+        generate(&schema, &options).to_string()
+    }
+
+    #[test]
+    fn test_round_trip_test() {
+        let code = generate_tests(
+            "enum Color: byte { Red, Blue }
+             table Point { x: float; name: string (required); colors: [Color]; other: Point; }",
+        );
+        assert!(
+            code.contains("# [cfg (test)] proptest :: proptest ! { # [test] fn point_round_trip (")
+        );
+        assert!(code.contains("x_value in proptest :: num :: f32 :: POSITIVE"));
+        assert!(code.contains("name_value in proptest :: prelude :: any :: < String > ()"));
+        assert!(code.contains("colors_value in proptest :: option :: of (proptest :: collection :: vec (proptest :: sample :: select (Color :: variants () . collect :: < Vec < _ >> ()) , 0 .. 8))"));
+        assert!(code.contains("let name_offset = builder . create_string (& name_value) ;"));
+        assert!(code.contains(
+            "& PointArgs { x : x_value , name : name_offset , colors : colors_offset , other : None }"
+        ));
+        assert!(
+            code.contains("proptest :: prop_assert_eq ! (table . x () . unwrap () , x_value) ;")
+        );
+    }
+
+    #[test]
+    fn test_unfilled_required_field() {
+        let code = generate_tests("table A { x: int; } table B { a: A (required); }");
+        assert!(code.contains("fn a_round_trip"));
+        assert!(!code.contains("fn b_round_trip"));
+    }
+
+    #[test]
+    fn test_disabled_by_default() {
+        let schema = parse_schema("table A { x: int; }").unwrap();
+        let code = generate(&schema, &Options::default()).to_string();
+        assert!(!code.contains("round_trip"));
+    }
+}
@@ -0,0 +1,627 @@
+//! `serde::Serialize` impls for readers, and `serde::Deserialize` impls for
+//! the owned object API.
+//!
+//! Tables and structs are serialized as structs whose fields are named like
+//! in the schema, and enums as the names of their values. Fields are read
+//! from the buffer while they are serialized, so no intermediate copy is
+//! made. Besides `serde`, the generated code needs the `serde` feature of
+//! `butte`, which serializes vectors.
+//!
+//! Object types are deserialized from the same representation, so a buffer
+//! can be converted to any serde format and back by way of its object type.
+use super::*;
+
+/// Generate the `Serialize` impl of the reader of `table`.
+///
+/// Fields missing from the buffer are skipped, as are deprecated fields.
+/// Union fields are serialized as their discriminant followed by their value,
+/// and vectors of unions as the vector of their discriminants followed by the
+/// vector of their values.
+pub(super) fn table(table: &Table, scope: Scope) -> TokenStream {
+    let Table { id, fields, .. } = table;
+    let reader_id = ident(id.raw);
+    let name = id.raw;
+    let fields = fields
+        .iter()
+        .filter(|field| !field.is_deprecated())
+        .collect::<Vec<_>>();
+    let len = fields
+        .iter()
+        .map(|field| {
+            if scope.has_type_field(&field.ty) {
+                2
+            } else {
+                1
+            }
+        })
+        .sum::<usize>();
+
+    let serialize_fields = fields.iter().map(|field| {
+        let raw_name = field.id.raw;
+        let accessor = ident(field.id.as_ref().to_snake_case());
+
+        if let Some(union) = scope.union(&field.ty) {
+            let type_name = format!("{}_type", raw_name);
+            let type_accessor = format_ident!("{}_type", field.id.as_ref().to_snake_case());
+            let union_type = scope.union_type_path(&field.ty);
+            let variants = union.values.iter().map(|EnumVal { id: variant, .. }| {
+                let variant_accessor = format_ident!(
+                    "{}_as_{}",
+                    field.id.as_ref().to_snake_case(),
+                    variant.as_ref().to_snake_case()
+                );
+                quote! {
+                    #union_type::#variant => match self.#variant_accessor().map_err(S::Error::custom)? {
+                        Some(value) => state.serialize_field(#raw_name, &value)?,
+                        None => state.skip_field(#raw_name)?,
+                    }
+                }
+            });
+            return quote! {
+                let #type_accessor = self.#type_accessor().map_err(S::Error::custom)?;
+                state.serialize_field(#type_name, &#type_accessor)?;
+                match #type_accessor {
+                    #(#variants,)*
+                    _ => state.skip_field(#raw_name)?,
+                }
+            };
+        }
+
+        let types = scope.union_vector(&field.ty).map(|_| {
+            let type_name = format!("{}_type", raw_name);
+            let type_accessor = format_ident!("{}_type", field.id.as_ref().to_snake_case());
+            if field.is_required() {
+                quote! {
+                    state.serialize_field(#type_name, &self.#type_accessor().map_err(S::Error::custom)?)?;
+                }
+            } else {
+                quote! {
+                    match self.#type_accessor().map_err(S::Error::custom)? {
+                        Some(value) => state.serialize_field(#type_name, &value)?,
+                        None => state.skip_field(#type_name)?,
+                    }
+                }
+            }
+        });
+
+        if scope.default_value(field).is_some() || field.is_required() {
+            quote! {
+                #types
+                state.serialize_field(#raw_name, &self.#accessor().map_err(S::Error::custom)?)?;
+            }
+        } else {
+            quote! {
+                #types
+                match self.#accessor().map_err(S::Error::custom)? {
+                    Some(value) => state.serialize_field(#raw_name, &value)?,
+                    None => state.skip_field(#raw_name)?,
+                }
+            }
+        }
+    });
+
+    quote! {
+        impl serde::Serialize for #reader_id<'_> {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                use serde::ser::{Error as _, SerializeStruct as _};
+
+                let mut state = serializer.serialize_struct(#name, #len)?;
+                #(#serialize_fields)*
+                state.end()
+            }
+        }
+    }
+}
+
+/// Generate the `Deserialize` impl of the object type of `table`.
+///
+/// Missing fields take their default value, or are `None` if they don't have
+/// one. The discriminant of a union must come before its value, like the
+/// discriminants of a vector of unions before the vector.
+pub(super) fn object(table: &Table, scope: Scope) -> TokenStream {
+    let Table { id, fields, .. } = table;
+    let object_id = ident(object_id(id.raw));
+    let keys = fields
+        .iter()
+        .flat_map(|field| {
+            let raw_name = field.id.raw;
+            if scope.has_type_field(&field.ty) {
+                vec![format!("{}_type", raw_name), raw_name.to_string()]
+            } else {
+                vec![raw_name.to_string()]
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut locals = vec![];
+    let mut arms = vec![];
+    let mut inits = vec![];
+    for field in fields {
+        let field_id = field_ident(field, scope);
+        let raw_name = field.id.raw;
+        let local = value_local(field.id.raw);
+        if field.is_deprecated() {
+            arms.push(quote! {
+                #raw_name => {
+                    map.next_value::<serde::de::IgnoredAny>()?;
+                }
+            });
+            continue;
+        }
+        locals.push(quote!(let mut #local = None;));
+
+        if let Some(union) = scope.union(&field.ty) {
+            let type_name = format!("{}_type", raw_name);
+            let type_local = value_local(&type_name);
+            let union_type = scope.union_type_path(&field.ty);
+            let object_path = scope.object_path(&field.ty);
+            let message = format!("`{}` must come after `{}`", raw_name, type_name);
+            let variants = union.values.iter().map(|EnumVal { id: variant, .. }| {
+                quote!(Some(#union_type::#variant) => #object_path::#variant(map.next_value()?))
+            });
+            locals.push(quote!(let mut #type_local = None;));
+            arms.push(quote! {
+                #type_name => #type_local = Some(map.next_value::<#union_type>()?),
+                #raw_name => {
+                    #local = Some(match #type_local {
+                        #(#variants,)*
+                        _ => return Err(A::Error::custom(#message)),
+                    })
+                }
+            });
+            inits.push(quote!(#field_id: #local.unwrap_or_default()));
+            continue;
+        }
+
+        if let Type::Array(element) = &field.ty {
+            if scope.union(element).is_some() {
+                let type_name = format!("{}_type", raw_name);
+                let type_local = value_local(&type_name);
+                let union_type = scope.union_type_path(element);
+                let object_path = scope.object_path(element);
+                let message = format!("`{}` must come after `{}`", raw_name, type_name);
+                locals.push(quote!(let mut #type_local = None;));
+                arms.push(quote! {
+                    #type_name => #type_local = map.next_value::<Option<Vec<#union_type>>>()?,
+                    #raw_name => {
+                        let types = #type_local.as_deref().ok_or_else(|| A::Error::custom(#message))?;
+                        #local = Some(map.next_value_seed(#object_path::vector_seed(types))?);
+                    }
+                });
+                inits.push(if field.is_required() {
+                    quote!(#field_id: #local.ok_or_else(|| A::Error::missing_field(#raw_name))?)
+                } else {
+                    quote!(#field_id: #local)
+                });
+                continue;
+            }
+        }
+
+        if let Some(default_value) = scope.default_value(field) {
+            arms.push(quote!(#raw_name => #local = Some(map.next_value()?)));
+            inits.push(quote!(#field_id: #local.unwrap_or(#default_value)));
+        } else if field.is_required() {
+            arms.push(quote!(#raw_name => #local = Some(map.next_value()?)));
+            inits
+                .push(quote!(#field_id: #local.ok_or_else(|| A::Error::missing_field(#raw_name))?));
+        } else {
+            arms.push(quote!(#raw_name => #local = map.next_value()?));
+            inits.push(quote!(#field_id: #local));
+        }
+    }
+
+    deserialize(
+        &object_id,
+        id.raw,
+        &keys,
+        quote! {
+            #(#locals)*
+            while let Some(key) = map.next_key::<String>()? {
+                match key.as_str() {
+                    #(#arms,)*
+                    _ => return Err(A::Error::unknown_field(&key, FIELDS)),
+                }
+            }
+            Ok(#object_id {
+                #(#inits),*
+            })
+        },
+    )
+}
+
+/// Generate the function deserializing a vector of values of the object type
+/// of `union`, given their discriminants, which are deserialized first.
+pub(super) fn union_object(union: &Union) -> TokenStream {
+    let Union { id, values, .. } = union;
+    let object_id = ident(object_id(id.raw));
+    let union_type = union_type_id(id);
+    let keys = values.iter().map(|EnumVal { id: variant, .. }| variant);
+    let expecting = format!("a sequence of {{}} `{}` values", id.raw);
+    quote! {
+        impl #object_id {
+            /// The seed deserializing a vector of values whose discriminants
+            /// are `types`.
+            #[doc(hidden)]
+            pub fn vector_seed<'de>(
+                types: &[#union_type]
+            ) -> impl serde::de::DeserializeSeed<'de, Value = Vec<Self>> + '_ {
+                struct Seed<'t>(&'t [#union_type]);
+
+                impl<'de> serde::de::DeserializeSeed<'de> for Seed<'_> {
+                    type Value = Vec<#object_id>;
+
+                    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+                    where
+                        D: serde::Deserializer<'de>,
+                    {
+                        deserializer.deserialize_seq(self)
+                    }
+                }
+
+                impl<'de> serde::de::Visitor<'de> for Seed<'_> {
+                    type Value = Vec<#object_id>;
+
+                    fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                        write!(f, #expecting, self.0.len())
+                    }
+
+                    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+                    where
+                        A: serde::de::SeqAccess<'de>,
+                    {
+                        use serde::de::Error as _;
+
+                        let mut values = Vec::with_capacity(self.0.len());
+                        for ty in self.0 {
+                            let value = match ty {
+                                #union_type::NONE => seq
+                                    .next_element::<serde::de::IgnoredAny>()?
+                                    .map(|_| #object_id::NONE),
+                                #(#union_type::#keys => seq.next_element()?.map(#object_id::#keys),)*
+                            };
+                            match value {
+                                Some(value) => values.push(value),
+                                None => return Err(A::Error::invalid_length(values.len(), &self)),
+                            }
+                        }
+                        if seq.next_element::<serde::de::IgnoredAny>()?.is_some() {
+                            return Err(A::Error::invalid_length(values.len() + 1, &self));
+                        }
+                        Ok(values)
+                    }
+                }
+
+                Seed(types)
+            }
+        }
+    }
+}
+
+/// The name of the local variable holding the value of the field `name`
+/// while it is deserialized.
+fn value_local(name: &str) -> proc_macro2::Ident {
+    format_ident!("{}_value", name.to_snake_case())
+}
+
+/// Generate a `Deserialize` impl for `target` from a struct called `name`
+/// with the fields `keys`. `visit_map` is the body of the visitor method
+/// reading the fields from `map`.
+fn deserialize(
+    target: &proc_macro2::Ident,
+    name: &str,
+    keys: &[String],
+    visit_map: TokenStream,
+) -> TokenStream {
+    let expecting = format!("a `{}`", name);
+    quote! {
+        impl<'de> serde::Deserialize<'de> for #target {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                const FIELDS: &[&str] = &[#(#keys),*];
+
+                struct Visitor;
+
+                impl<'de> serde::de::Visitor<'de> for Visitor {
+                    type Value = #target;
+
+                    fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                        f.write_str(#expecting)
+                    }
+
+                    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+                    where
+                        A: serde::de::MapAccess<'de>,
+                    {
+                        use serde::de::Error as _;
+
+                        #visit_map
+                    }
+                }
+
+                deserializer.deserialize_struct(#name, FIELDS, Visitor)
+            }
+        }
+    }
+}
+
+/// Generate the `Serialize` and `Deserialize` impls of `strukt`.
+pub(super) fn struct_(strukt: &Struct, scope: Scope) -> TokenStream {
+    let Struct { id, fields, .. } = strukt;
+    let struct_id = ident(id.raw);
+    let name = id.raw;
+    let len = fields.len();
+    let serialize_fields = fields.iter().map(|field| {
+        let raw_name = field.id.raw;
+        let accessor = ident(field.id.raw.to_snake_case());
+        let value = if scope.c_style_enum(array_element(&field.ty)).is_some() {
+            quote!(self.#accessor().map_err(serde::ser::Error::custom)?)
+        } else {
+            quote!(self.#accessor())
+        };
+        // Arrays are serialized as slices, as serde only implements its traits
+        // for arrays of up to 32 elements.
+        if let Type::FixedArray(..) = &field.ty {
+            quote!(state.serialize_field(#raw_name, &#value[..])?;)
+        } else {
+            quote!(state.serialize_field(#raw_name, &#value)?;)
+        }
+    });
+
+    let keys = fields
+        .iter()
+        .map(|field| field.id.raw.to_string())
+        .collect::<Vec<_>>();
+    let locals = fields.iter().map(|field| {
+        let local = value_local(field.id.raw);
+        let ty = match &field.ty {
+            Type::FixedArray(element, _) => {
+                let element = scope.type_path(element);
+                quote!(Vec<#element>)
+            }
+            ty => scope.type_path(ty),
+        };
+        quote!(let mut #local: Option<#ty> = None;)
+    });
+    let arms = fields.iter().map(|field| {
+        let raw_name = field.id.raw;
+        let local = value_local(field.id.raw);
+        quote!(#raw_name => #local = Some(map.next_value()?))
+    });
+    let args = fields.iter().map(|field| {
+        let raw_name = field.id.raw;
+        let local = value_local(field.id.raw);
+        let mut value = quote!(#local.ok_or_else(|| A::Error::missing_field(#raw_name))?);
+        if let Type::FixedArray(_, len) = &field.ty {
+            let expected = format!("an array of {} values", len);
+            value = quote! {{
+                let values = #value;
+                let len = values.len();
+                core::convert::TryFrom::try_from(values)
+                    .map_err(|_| A::Error::invalid_length(len, &#expected))?
+            }};
+        }
+        if scope.is_struct(array_element(&field.ty)) {
+            quote!(&#value)
+        } else {
+            value
+        }
+    });
+    let deserialize = deserialize(
+        &struct_id,
+        id.raw,
+        &keys,
+        quote! {
+            #(#locals)*
+            while let Some(key) = map.next_key::<String>()? {
+                match key.as_str() {
+                    #(#arms,)*
+                    _ => return Err(A::Error::unknown_field(&key, FIELDS)),
+                }
+            }
+            Ok(#struct_id::new(#(#args),*))
+        },
+    );
+
+    quote! {
+        impl serde::Serialize for #struct_id {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                use serde::ser::SerializeStruct as _;
+
+                let mut state = serializer.serialize_struct(#name, #len)?;
+                #(#serialize_fields)*
+                state.end()
+            }
+        }
+
+        #deserialize
+    }
+}
+
+/// Generate the `Serialize` and `Deserialize` impls of an enum, given its
+/// values and the function returning their names.
+pub(super) fn enum_(
+    enum_id: &proc_macro2::Ident,
+    values: &[(proc_macro2::Ident, IntegerConstant)],
+    name_fn: &proc_macro2::Ident,
+) -> TokenStream {
+    let names = values
+        .iter()
+        .map(|(key, _)| key.to_string())
+        .collect::<Vec<_>>();
+    let keys = values.iter().map(|(key, _)| key);
+    quote! {
+        impl serde::Serialize for #enum_id {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                serializer.serialize_str(#name_fn(*self))
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for #enum_id {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                const VARIANTS: &[&str] = &[#(#names),*];
+
+                let name = String::deserialize(deserializer)?;
+                match name.as_str() {
+                    #(#names => Ok(#enum_id::#keys),)*
+                    _ => Err(serde::de::Error::unknown_variant(&name, VARIANTS)),
+                }
+            }
+        }
+    }
+}
+
+/// Generate the `Serialize` and `Deserialize` impls of a `bit_flags` enum,
+/// given its flags.
+///
+/// Flags are serialized like flatc does in JSON, as the names of the flags
+/// that are set separated by spaces. Bits that aren't flags are kept as an
+/// integer.
+pub(super) fn bit_flags(
+    enum_id: &proc_macro2::Ident,
+    values: &[(proc_macro2::Ident, IntegerConstant)],
+) -> TokenStream {
+    let names = values
+        .iter()
+        .map(|(key, _)| key.to_string())
+        .collect::<Vec<_>>();
+    let keys = values.iter().map(|(key, _)| key).collect::<Vec<_>>();
+    quote! {
+        impl serde::Serialize for #enum_id {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                let mut rest = self.bits();
+                let mut names = vec![];
+                #(
+                    if self.contains(#enum_id::#keys) {
+                        names.push(#names.to_string());
+                        rest &= !#enum_id::#keys.bits();
+                    }
+                )*
+                if rest != 0 {
+                    names.push(rest.to_string());
+                }
+                serializer.serialize_str(&names.join(" "))
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for #enum_id {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                const VARIANTS: &[&str] = &[#(#names),*];
+
+                let names = String::deserialize(deserializer)?;
+                let mut flags = #enum_id::empty();
+                for name in names.split_whitespace() {
+                    flags |= match name {
+                        #(#names => #enum_id::#keys,)*
+                        _ => match name.parse() {
+                            Ok(bits) => #enum_id(bits),
+                            Err(_) => return Err(serde::de::Error::unknown_variant(name, VARIANTS)),
+                        },
+                    };
+                }
+                Ok(flags)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod serde_tests {
+    use super::*;
+    use crate::parser::parse_schema;
+
+    fn generate_impls(object_api: bool) -> String {
+        let schema = parse_schema(
+            "enum Color: byte { Red, Green }
+             struct Point { x: float; y: float; }
+             table Cat { name: string (required); color: Color = Green; }
+             union Animal { Cat }
+             table Pet { animal: Animal; position: Point; }
+             table Shelter { animals: [Animal]; }",
+        )
+        .unwrap();
+        let options = Options {
+            object_api,
+            ..Options::default()
+        };
+        generate(&schema, &options).to_string()
+    }
+
+    #[test]
+    fn test_serialize_tables() {
+        let code = generate_impls(false);
+        assert!(code.contains("impl serde :: Serialize for Cat < '_ >"));
+        assert!(code.contains("serializer . serialize_struct (\"Cat\" , 2usize) ?"));
+        assert!(code.contains(
+            "state . serialize_field (\"name\" , & self . name () . map_err (S :: Error :: custom) ?) ?"
+        ));
+        assert!(code.contains("serializer . serialize_struct (\"Pet\" , 3usize) ?"));
+        assert!(code.contains("state . serialize_field (\"animal_type\" , & animal_type) ?"));
+        assert!(code.contains("None => state . skip_field (\"position\") ?"));
+    }
+
+    #[test]
+    fn test_serialize_structs_and_enums() {
+        let code = generate_impls(false);
+        assert!(code.contains("impl serde :: Serialize for Point"));
+        assert!(code.contains("state . serialize_field (\"x\" , & self . x ()) ?"));
+        assert!(code.contains(
+            "impl serde :: Serialize for Color { fn serialize < S > (& self , serializer : S) -> Result < S :: Ok , S :: Error > where S : serde :: Serializer , { serializer . serialize_str (enum_name_color (* self)) } }"
+        ));
+        assert!(code.contains("serializer . serialize_str (enum_name_animal_type (* self))"));
+    }
+
+    #[test]
+    fn test_deserialize_objects() {
+        assert!(!generate_impls(false).contains("Deserialize < 'de > for CatT"));
+        let code = generate_impls(true);
+        assert!(code.contains("impl < 'de > serde :: Deserialize < 'de > for CatT"));
+        assert!(code.contains("const FIELDS : & [& str] = & [\"name\" , \"color\"] ;"));
+        assert!(code.contains("color : color_value . unwrap_or (Color :: Green)"));
+        assert!(code.contains(
+            "name : name_value . ok_or_else (|| A :: Error :: missing_field (\"name\")) ?"
+        ));
+        assert!(code.contains(
+            "\"animal_type\" => animal_type_value = Some (map . next_value :: < AnimalType > () ?)"
+        ));
+        assert!(code.contains("Some (AnimalType :: Cat) => AnimalT :: Cat (map . next_value () ?)"));
+        assert!(code.contains("Ok (Point :: new (x_value"));
+    }
+
+    #[test]
+    fn test_union_vectors() {
+        let code = generate_impls(false);
+        assert!(code.contains("Animal :: NONE => serializer . serialize_none ()"));
+        assert!(code.contains("serializer . serialize_struct (\"Shelter\" , 2usize) ?"));
+        assert!(code.contains("None => state . skip_field (\"animals_type\") ?"));
+        assert!(!code.contains("vector_seed"));
+
+        let code = generate_impls(true);
+        assert!(code.contains("pub fn vector_seed < 'de > (types : & [AnimalType]) -> impl serde :: de :: DeserializeSeed < 'de , Value = Vec < Self >> + '_"));
+        assert!(
+            code.contains("AnimalType :: Cat => seq . next_element () ? . map (AnimalT :: Cat) ,")
+        );
+        assert!(code.contains(
+            "animals_value = Some (map . next_value_seed (AnimalT :: vector_seed (types)) ?) ;"
+        ));
+    }
+}
@@ -0,0 +1,494 @@
+//! gRPC clients and servers for RPC services, built on
+//! [tonic](https://docs.rs/tonic).
+//!
+//! Messages are sent as `butte::Message`s, which are encoded as the raw bytes
+//! of the buffer. The generated code is written for `tonic` 0.8, and also
+//! depends on `bytes` and `futures-core`. The `grpc` test of butte-examples,
+//! run with its `tonic` feature, builds a client and server and calls them.
+use super::*;
+
+/// Generate the `<service>_grpc` module of `rpc`.
+pub(super) fn service(rpc: &Rpc, scope: Scope) -> TokenStream {
+    let Rpc { id, methods, doc } = rpc;
+    let module = format_ident!("{}_grpc", id.raw.to_snake_case());
+    let trait_id = ident(id.raw);
+    let server_id = format_ident!("{}Server", id.raw);
+    let client_id = format_ident!("{}Client", id.raw);
+    let service_name = [scope.namespace, &[id.raw]].concat().join(".");
+
+    let methods = methods
+        .iter()
+        .map(|method| Method::new(rpc, method, &service_name, scope))
+        .collect::<Vec<_>>();
+
+    let stream_types = methods.iter().filter_map(|method| {
+        let Method {
+            response,
+            streaming,
+            stream_id,
+            ..
+        } = method;
+        match streaming {
+            Streaming::Server | Streaming::Bidi => Some(quote! {
+                /// The stream of responses returned by the method.
+                type #stream_id: futures_core::Stream<Item = Result<#response, tonic::Status>>
+                    + Send
+                    + 'static;
+            }),
+            Streaming::None | Streaming::Client => None,
+        }
+    });
+
+    let trait_methods = methods.iter().map(|method| {
+        let Method {
+            doc,
+            attributes,
+            name,
+            request,
+            response,
+            streaming,
+            stream_id,
+            ..
+        } = method;
+        let request = match streaming {
+            Streaming::Client | Streaming::Bidi => quote!(tonic::Streaming<#request>),
+            Streaming::None | Streaming::Server => quote!(#request),
+        };
+        let response = match streaming {
+            Streaming::Server | Streaming::Bidi => quote!(Self::#stream_id),
+            Streaming::None | Streaming::Client => quote!(#response),
+        };
+        quote! {
+            #doc
+            #attributes
+            async fn #name(
+                &self,
+                request: tonic::Request<#request>
+            ) -> Result<tonic::Response<#response>, tonic::Status>;
+        }
+    });
+
+    let routes = methods.iter().map(|method| {
+        let Method {
+            path,
+            request,
+            response,
+            streaming,
+            stream_id,
+            server_call,
+            ..
+        } = method;
+        let (service, request, response, call) = match streaming {
+            Streaming::None => (
+                quote!(UnaryService<#request>),
+                quote!(#request),
+                quote!(type Response = #response;),
+                quote!(unary),
+            ),
+            Streaming::Client => (
+                quote!(ClientStreamingService<#request>),
+                quote!(tonic::Streaming<#request>),
+                quote!(type Response = #response;),
+                quote!(client_streaming),
+            ),
+            Streaming::Server => (
+                quote!(ServerStreamingService<#request>),
+                quote!(#request),
+                quote! {
+                    type Response = #response;
+                    type ResponseStream = T::#stream_id;
+                },
+                quote!(server_streaming),
+            ),
+            Streaming::Bidi => (
+                quote!(StreamingService<#request>),
+                quote!(tonic::Streaming<#request>),
+                quote! {
+                    type Response = #response;
+                    type ResponseStream = T::#stream_id;
+                },
+                quote!(streaming),
+            ),
+        };
+        let future_response = match streaming {
+            Streaming::Server | Streaming::Bidi => quote!(Self::ResponseStream),
+            Streaming::None | Streaming::Client => quote!(Self::Response),
+        };
+        quote! {
+            #path => {
+                struct Svc<T: #trait_id>(Arc<T>);
+
+                impl<T: #trait_id> tonic::server::#service for Svc<T> {
+                    #response
+                    type Future = BoxFuture<tonic::Response<#future_response>, tonic::Status>;
+
+                    #[allow(deprecated)]
+                    fn call(&mut self, request: tonic::Request<#request>) -> Self::Future {
+                        let inner = self.0.clone();
+                        Box::pin(#server_call)
+                    }
+                }
+
+                Box::pin(async move {
+                    let mut grpc = tonic::server::Grpc::new(FlatBufferCodec::default());
+                    Ok(grpc.#call(Svc(inner), request).await)
+                })
+            }
+        }
+    });
+
+    let client_methods = methods.iter().map(|method| {
+        let Method {
+            doc,
+            attributes,
+            name,
+            path,
+            request,
+            response,
+            streaming,
+            ..
+        } = method;
+        let (request, into_request) = match streaming {
+            Streaming::Client | Streaming::Bidi => (
+                quote!(impl tonic::IntoStreamingRequest<Message = #request>),
+                quote!(into_streaming_request),
+            ),
+            Streaming::None | Streaming::Server => (
+                quote!(impl tonic::IntoRequest<#request>),
+                quote!(into_request),
+            ),
+        };
+        let response = match streaming {
+            Streaming::Server | Streaming::Bidi => quote!(tonic::codec::Streaming<#response>),
+            Streaming::None | Streaming::Client => quote!(#response),
+        };
+        let call = match streaming {
+            Streaming::None => quote!(unary),
+            Streaming::Client => quote!(client_streaming),
+            Streaming::Server => quote!(server_streaming),
+            Streaming::Bidi => quote!(streaming),
+        };
+        quote! {
+            #doc
+            #attributes
+            pub async fn #name(
+                &mut self,
+                request: #request
+            ) -> Result<tonic::Response<#response>, tonic::Status> {
+                self.inner.ready().await.map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+                let path = http::uri::PathAndQuery::from_static(#path);
+                self.inner
+                    .#call(request.#into_request(), path, FlatBufferCodec::default())
+                    .await
+            }
+        }
+    });
+
+    let vis = &scope.options.visibility;
+    quote! {
+        #doc
+        #vis mod #module {
+            use tonic::codegen::*;
+
+            /// Encodes and decodes flatbuffer messages as their raw bytes.
+            #[derive(Debug)]
+            pub struct FlatBufferCodec<T, U>(std::marker::PhantomData<fn() -> (T, U)>);
+
+            impl<T, U> Default for FlatBufferCodec<T, U> {
+                fn default() -> Self {
+                    Self(std::marker::PhantomData)
+                }
+            }
+
+            impl<T: 'static, U: 'static> tonic::codec::Codec for FlatBufferCodec<T, U> {
+                type Encode = butte::Message<T>;
+                type Decode = butte::Message<U>;
+                type Encoder = FlatBufferCodec<T, U>;
+                type Decoder = FlatBufferCodec<T, U>;
+
+                fn encoder(&mut self) -> Self::Encoder {
+                    Self::default()
+                }
+
+                fn decoder(&mut self) -> Self::Decoder {
+                    Self::default()
+                }
+            }
+
+            impl<T, U> tonic::codec::Encoder for FlatBufferCodec<T, U> {
+                type Item = butte::Message<T>;
+                type Error = tonic::Status;
+
+                fn encode(
+                    &mut self,
+                    item: Self::Item,
+                    dst: &mut tonic::codec::EncodeBuf<'_>,
+                ) -> Result<(), Self::Error> {
+                    bytes::BufMut::put_slice(dst, item.data());
+                    Ok(())
+                }
+            }
+
+            impl<T, U> tonic::codec::Decoder for FlatBufferCodec<T, U> {
+                type Item = butte::Message<U>;
+                type Error = tonic::Status;
+
+                fn decode(
+                    &mut self,
+                    src: &mut tonic::codec::DecodeBuf<'_>,
+                ) -> Result<Option<Self::Item>, Self::Error> {
+                    let len = bytes::Buf::remaining(src);
+                    let data = bytes::Buf::copy_to_bytes(src, len);
+                    Ok(Some(butte::Message::new(data.to_vec())))
+                }
+            }
+
+            #[async_trait]
+            pub trait #trait_id: Send + Sync + 'static {
+                #(#stream_types)*
+
+                #(#trait_methods)*
+            }
+
+            #[derive(Debug)]
+            pub struct #server_id<T: #trait_id> {
+                inner: Arc<T>,
+            }
+
+            impl<T: #trait_id> #server_id<T> {
+                pub fn new(inner: T) -> Self {
+                    Self::from_arc(Arc::new(inner))
+                }
+
+                pub fn from_arc(inner: Arc<T>) -> Self {
+                    Self { inner }
+                }
+            }
+
+            impl<T: #trait_id> Clone for #server_id<T> {
+                fn clone(&self) -> Self {
+                    Self {
+                        inner: self.inner.clone(),
+                    }
+                }
+            }
+
+            impl<T, B> Service<http::Request<B>> for #server_id<T>
+            where
+                T: #trait_id,
+                B: Body + Send + 'static,
+                B::Error: Into<StdError> + Send + 'static,
+            {
+                type Response = http::Response<tonic::body::BoxBody>;
+                type Error = std::convert::Infallible;
+                type Future = BoxFuture<Self::Response, Self::Error>;
+
+                fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+                    Poll::Ready(Ok(()))
+                }
+
+                fn call(&mut self, request: http::Request<B>) -> Self::Future {
+                    let inner = self.inner.clone();
+                    match request.uri().path() {
+                        #(#routes)*
+                        _ => Box::pin(async move {
+                            Ok(http::Response::builder()
+                                .status(200)
+                                .header("grpc-status", "12")
+                                .header("content-type", "application/grpc")
+                                .body(empty_body())
+                                .unwrap())
+                        }),
+                    }
+                }
+            }
+
+            impl<T: #trait_id> tonic::server::NamedService for #server_id<T> {
+                const NAME: &'static str = #service_name;
+            }
+
+            #[derive(Debug, Clone)]
+            pub struct #client_id<T> {
+                inner: tonic::client::Grpc<T>,
+            }
+
+            impl #client_id<tonic::transport::Channel> {
+                /// Connect to a server at `dst`.
+                pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
+                where
+                    D: std::convert::TryInto<tonic::transport::Endpoint>,
+                    D::Error: Into<StdError>,
+                {
+                    let conn = tonic::transport::Endpoint::new(dst)?.connect().await?;
+                    Ok(Self::new(conn))
+                }
+            }
+
+            impl<T> #client_id<T>
+            where
+                T: tonic::client::GrpcService<tonic::body::BoxBody>,
+                T::Error: Into<StdError>,
+                T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+                <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+            {
+                pub fn new(inner: T) -> Self {
+                    Self {
+                        inner: tonic::client::Grpc::new(inner),
+                    }
+                }
+
+                #(#client_methods)*
+            }
+        }
+    }
+}
+
+/// The parts of an RPC method used in the generated gRPC code.
+struct Method<'a, 'b> {
+    doc: &'b Comment<'a>,
+    /// The attributes of the generated methods, see `rpc_method_attributes`.
+    attributes: TokenStream,
+    name: proc_macro2::Ident,
+    /// The gRPC path of the method, `/<package>.<Service>/<Method>`.
+    path: String,
+    request: TokenStream,
+    response: TokenStream,
+    streaming: Streaming,
+    /// The name of the trait's associated type for streamed responses.
+    stream_id: proc_macro2::Ident,
+    /// The future that handles a request to the method in the server, as
+    /// wrapped by the RPC method hooks.
+    server_call: TokenStream,
+}
+
+impl<'a, 'b> Method<'a, 'b> {
+    fn new(
+        rpc: &Rpc<'a>,
+        method: &'b RpcMethod<'a>,
+        service_name: &str,
+        scope: Scope<'a, '_>,
+    ) -> Self {
+        let name = ident(method.id.raw.to_snake_case());
+        let server_call = scope.options.rpc_method_hooks.iter().fold(
+            quote!(async move { inner.#name(request).await }),
+            |call, hook| hook.server_call(rpc, method, call),
+        );
+        Self {
+            doc: &method.doc,
+            attributes: rpc_method_attributes(rpc, method, scope),
+            name,
+            path: format!("/{}/{}", service_name, method.id.raw),
+            request: message_type(&method.request_type, scope),
+            response: message_type(&method.response_type, scope),
+            streaming: method.streaming().expect("invalid streaming mode"),
+            stream_id: format_ident!("{}Stream", method.id.raw),
+            server_call,
+        }
+    }
+}
+
+/// The message type of a request or response of type `id`, as seen from the
+/// `<service>_grpc` module.
+fn message_type<'a>(id: &DottedIdent<'a>, scope: Scope<'a, '_>) -> TokenStream {
+    let (name, _) = scope
+        .symbols
+        .resolve(scope.namespace, id)
+        .unwrap_or_else(|| panic!("unknown type: {:?}", id));
+    let (last, namespace) = name.split_last().expect("empty name");
+    let marker = scope.relative_path(namespace, format!("{}Offset", last));
+    quote!(butte::Message<super::#marker>)
+}
+
+#[cfg(test)]
+mod tonic_tests {
+    use super::*;
+    use crate::{meta, method, namespace, rpc};
+
+    fn generate_service() -> String {
+        let elements = vec![
+            Element::from(namespace!(a)),
+            Element::from(table!(Request, [field!(x, Int)])),
+            Element::from(table!(Response, [field!(x, Int)])),
+            Element::from(namespace!(a::b)),
+            Element::from(rpc!(
+                Greeter,
+                [
+                    method!(fn SayHello(Request) -> Response),
+                    method!(fn SayManyHellos(Request) -> Response, [meta!(streaming, "server")]),
+                    method!(fn Chat(Request) -> Response, [meta!(streaming, "bidi")])
+                ]
+            )),
+        ];
+        let schema = Schema::builder().elements(elements).build();
+        generate(&schema, &Options::default()).to_string()
+    }
+
+    #[test]
+    fn test_service_trait() {
+        let code = generate_service();
+        assert!(code.contains("pub mod greeter_grpc"));
+        assert!(code.contains(
+            "async fn say_hello (& self , request : tonic :: Request < butte :: Message < super :: super :: RequestOffset > >) -> Result < tonic :: Response < butte :: Message < super :: super :: ResponseOffset > > , tonic :: Status >"
+        ));
+    }
+
+    #[test]
+    fn test_service_paths() {
+        let code = generate_service();
+        assert!(code.contains("\"/a.b.Greeter/SayHello\" =>"));
+        assert!(code.contains("const NAME : & 'static str = \"a.b.Greeter\""));
+        assert!(code.contains("PathAndQuery :: from_static (\"/a.b.Greeter/SayHello\")"));
+    }
+
+    #[test]
+    fn test_streaming_service() {
+        let code = generate_service();
+        assert!(code.contains(
+            "type SayManyHellosStream : futures_core :: Stream < Item = Result < butte :: Message < super :: super :: ResponseOffset > , tonic :: Status >> + Send + 'static ;"
+        ));
+        assert!(code.contains(
+            "async fn chat (& self , request : tonic :: Request < tonic :: Streaming < butte :: Message < super :: super :: RequestOffset > > >) -> Result < tonic :: Response < Self :: ChatStream > , tonic :: Status > ;"
+        ));
+        assert!(code.contains("tonic :: server :: ServerStreamingService < butte :: Message < super :: super :: RequestOffset > >"));
+        assert!(code.contains("grpc . streaming (Svc (inner) , request)"));
+        assert!(code.contains("request : impl tonic :: IntoStreamingRequest < Message = butte :: Message < super :: super :: RequestOffset > >"));
+    }
+
+    /// Handles requests in a span named after the method.
+    struct Traced;
+
+    impl RpcMethodHook for Traced {
+        fn server_call(&self, rpc: &Rpc, method: &RpcMethod, call: TokenStream) -> TokenStream {
+            let name = format!("{}.{}", rpc.id.raw, method.id.raw);
+            quote!(tracing::Instrument::instrument(#call, tracing::info_span!(#name)))
+        }
+    }
+
+    #[test]
+    fn test_method_hooks() {
+        let elements = vec![
+            Element::from(table!(Request, [field!(x, Int)])),
+            Element::from(table!(Response, [field!(x, Int)])),
+            Element::from(rpc!(
+                Greeter,
+                [method!(fn SayHello(Request) -> Response, [meta!(deprecated)])]
+            )),
+        ];
+        let schema = Schema::builder().elements(elements).build();
+        let options = Options {
+            rpc_method_hooks: vec![Arc::new(Traced)],
+            ..Options::default()
+        };
+        let code = generate(&schema, &options).to_string();
+        assert!(code.contains("# [deprecated] async fn say_hello (& self ,"));
+        assert!(code.contains("# [deprecated] pub async fn say_hello (& mut self ,"));
+        assert!(code.contains(
+            "Box :: pin (tracing :: Instrument :: instrument (async move { inner . say_hello (request) . await } , tracing :: info_span ! (\"Greeter.SayHello\")))"
+        ));
+    }
+}
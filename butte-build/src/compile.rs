@@ -1,65 +1,576 @@
 /// Compile flatbuffers files
 use std::io::{self, Write};
 use std::{
+    fmt,
     path::{Path, PathBuf},
     process::{Command, Stdio},
+    sync::Arc,
 };
 
 use anyhow::{anyhow, Result};
-use quote::ToTokens;
+use proc_macro2::TokenStream;
+
+use crate::{
+    codegen::{
+        generate, generate_adapters, generate_files, CodegenPlugin, EmptyCollections, Naming,
+        Options, RpcMethodHook, Visibility,
+    },
+    include::SchemaFiles,
+    types::Schema,
+    validate::validate,
+};
 
 /// Generate Rust code for a single flatbuffer schema file from arbitrary input and to arbitrary
 /// output.
+///
+/// Files included by the schema are looked up in `include_dirs`.
 pub fn compile_fbs_generic(
-    ugly: bool,
+    format: Format,
+    include_dirs: &[PathBuf],
     mut input: Box<dyn io::Read>,
-    mut output: Box<dyn io::Write>,
+    output: Box<dyn io::Write>,
 ) -> Result<()> {
     let mut schema_text = String::new();
     input.read_to_string(&mut schema_text)?;
+    let files = SchemaFiles::from_text(schema_text, None, include_dirs)?;
+    write_code(format, &files, &Options::default(), output)
+}
 
-    // parse the schema
-    let (_, schema) =
-        crate::parser::schema_decl(schema_text.as_str()).map_err(|_| anyhow!("parse failed"))?;
-
-    let code = format!("{}", schema.to_token_stream());
-
-    let text_output = if !ugly {
-        let mut cmd = Command::new("rustfmt")
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .arg("--edition")
-            .arg("2018")
-            .arg("--config")
-            .arg("normalize_doc_attributes=true")
-            .spawn()?;
-        cmd.stdin
-            .as_mut()
-            .ok_or_else(|| anyhow!("cannot access stdin"))?
-            .write_all(code.as_bytes())?;
-        cmd.wait_with_output()?.stdout
-    } else {
-        Vec::from(code)
-    };
-    output.write_all(&text_output[..])?;
+/// Generate Rust code for the flatbuffer schema file at `path` to arbitrary output.
+///
+/// Files included by the schema are looked up relative to the including file and then in
+/// `include_dirs`. Unlike `compile_fbs_generic`, errors point into the schema file by name.
+pub fn compile_fbs_file(
+    format: Format,
+    include_dirs: &[PathBuf],
+    path: impl AsRef<Path>,
+    output: Box<dyn io::Write>,
+) -> Result<()> {
+    let files = SchemaFiles::load(path, include_dirs)?;
+    write_code(format, &files, &Options::default(), output)
+}
+
+/// Generate the Rust code for a schema and all of the files it includes, after checking that
+/// the schema is valid.
+pub fn generate_code(files: &SchemaFiles, options: &Options) -> Result<TokenStream> {
+    Ok(generate(
+        &checked_schema(files, options.root_type.as_deref())?,
+        options,
+    ))
+}
+
+/// Parse a schema and all of the files it includes, reporting every validation error. The
+/// root type is `root_type` if given, see `SchemaFiles::parse_with_root`.
+pub(crate) fn checked_schema<'a>(
+    files: &'a SchemaFiles,
+    root_type: Option<&'a str>,
+) -> Result<Schema<'a>> {
+    let schema = files.parse_with_root(root_type)?;
+    let errors = validate(&schema);
+    if !errors.is_empty() {
+        let diagnostics = errors
+            .into_iter()
+            .map(|error| files.diagnostic(error.message, error.at).to_string())
+            .collect::<Vec<_>>();
+        return Err(anyhow!("{}", diagnostics.join("\n\n")));
+    }
+    Ok(schema)
+}
+
+/// How to format generated code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// Write the token stream as is, on a single line.
+    Ugly,
+
+    /// Pretty-print the code in process with `prettyplease`. This is the default.
+    #[default]
+    Pretty,
+
+    /// Pipe the code through an external `rustfmt` process, which must be on the `PATH`.
+    Rustfmt,
+}
+
+impl Format {
+    /// Format the generated `code` as Rust source text.
+    fn apply(self, code: TokenStream) -> Result<String> {
+        match self {
+            Format::Ugly => Ok(code.to_string()),
+            Format::Pretty => Ok(prettyplease::unparse(&syn::parse2(code)?)),
+            Format::Rustfmt => rustfmt(&code.to_string()),
+        }
+    }
+}
+
+/// Format `code` with an external `rustfmt` process.
+fn rustfmt(code: &str) -> Result<String> {
+    let mut cmd = Command::new("rustfmt")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .arg("--edition")
+        .arg("2018")
+        .arg("--config")
+        .arg("normalize_doc_attributes=true")
+        .spawn()
+        .map_err(|e| anyhow!("cannot run rustfmt: {}", e))?;
+    cmd.stdin
+        .as_mut()
+        .ok_or_else(|| anyhow!("cannot access stdin"))?
+        .write_all(code.as_bytes())?;
+    let output = cmd.wait_with_output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "rustfmt failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Generate Rust code for a schema and all of the files it includes.
+fn write_code(
+    format: Format,
+    files: &SchemaFiles,
+    options: &Options,
+    mut output: Box<dyn io::Write>,
+) -> Result<()> {
+    let code = format.apply(generate_code(files, options)?)?;
+    output.write_all(code.as_bytes())?;
+    Ok(())
+}
+
+/// Generate Rust code for a schema and all of the files it includes, with a file per
+/// namespace in `dir`, see `generate_files`.
+fn write_files(format: Format, files: &SchemaFiles, options: &Options, dir: &Path) -> Result<()> {
+    let schema = checked_schema(files, options.root_type.as_deref())?;
+    for (path, code) in generate_files(&schema, options) {
+        let path = dir.join(path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, format.apply(code)?)?;
+    }
     Ok(())
 }
 
 /// Generate Rust code for a single flatbuffer schema file.
 pub fn compile_fbs(path: impl AsRef<Path>) -> Result<()> {
-    let out_dir = PathBuf::from(std::env::var("OUT_DIR")?);
-    let path_ref = path.as_ref();
-    let output_path = out_dir.join(
-        path_ref
-            .with_extension("rs")
-            .file_name()
-            .ok_or_else(|| anyhow!("path has no file_name: {:?}", path_ref))?,
+    Config::new().input(path).compile()
+}
+
+/// Generate Rust code for a single flatbuffer schema file, looking up the files it includes
+/// relative to the including file and then in `include_dirs`.
+pub fn compile_fbs_with_include_dirs(
+    path: impl AsRef<Path>,
+    include_dirs: &[PathBuf],
+) -> Result<()> {
+    let mut config = Config::new();
+    for dir in include_dirs {
+        config.include_dir(dir);
+    }
+    config.input(path).compile()
+}
+
+/// Settings for generating Rust code from flatbuffer schemas in a build script.
+///
+/// # Examples
+///
+/// ```no_run
+/// // build.rs
+/// fn main() -> anyhow::Result<()> {
+///     butte_build::Config::new()
+///         .input("fbs/monster.fbs")
+///         .input_as("fbs/v2/monster.fbs", "monster_v2")
+///         .include_dir("fbs/shared")
+///         .derive("Hash")
+///         .compile()
+/// }
+/// ```
+///
+/// The code generated for each input is then included with `include_fbs!` and the input's
+/// module name.
+#[derive(Clone)]
+pub struct Config {
+    inputs: Vec<(PathBuf, Option<String>)>,
+    include_dirs: Vec<PathBuf>,
+    out_dir: Option<PathBuf>,
+    format: Format,
+    derives: Vec<String>,
+    object_api: bool,
+    object_derives: Vec<String>,
+    round_trip_tests: bool,
+    split_namespaces: bool,
+    naming: Naming,
+    empty_collections: EmptyCollections,
+    root_type: Option<String>,
+    visibility: Option<String>,
+    prelude: bool,
+    module: Option<String>,
+    adapters: Vec<(String, String)>,
+    rpc_method_hooks: Vec<Arc<dyn RpcMethodHook>>,
+    plugins: Vec<Arc<dyn CodegenPlugin>>,
+}
+
+impl fmt::Debug for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Config")
+            .field("inputs", &self.inputs)
+            .field("include_dirs", &self.include_dirs)
+            .field("out_dir", &self.out_dir)
+            .field("format", &self.format)
+            .field("derives", &self.derives)
+            .field("object_api", &self.object_api)
+            .field("object_derives", &self.object_derives)
+            .field("round_trip_tests", &self.round_trip_tests)
+            .field("split_namespaces", &self.split_namespaces)
+            .field("naming", &self.naming)
+            .field("empty_collections", &self.empty_collections)
+            .field("root_type", &self.root_type)
+            .field("visibility", &self.visibility)
+            .field("prelude", &self.prelude)
+            .field("module", &self.module)
+            .field("adapters", &self.adapters)
+            .field("rpc_method_hooks", &self.rpc_method_hooks.len())
+            .field("plugins", &self.plugins.len())
+            .finish()
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Config {
+    pub fn new() -> Self {
+        Self {
+            inputs: vec![],
+            include_dirs: vec![],
+            out_dir: None,
+            format: Format::default(),
+            derives: vec![],
+            object_api: false,
+            object_derives: vec![],
+            round_trip_tests: false,
+            split_namespaces: false,
+            naming: Naming::default(),
+            empty_collections: EmptyCollections::default(),
+            root_type: None,
+            visibility: None,
+            prelude: false,
+            module: None,
+            adapters: vec![],
+            rpc_method_hooks: vec![],
+            plugins: vec![],
+        }
+    }
+
+    /// Add a schema file to generate code for. Its module name is the file stem of `path`.
+    pub fn input(&mut self, path: impl AsRef<Path>) -> &mut Self {
+        self.inputs.push((path.as_ref().to_path_buf(), None));
+        self
+    }
+
+    /// Add a schema file to generate code for, with the given module name.
+    ///
+    /// This is useful when the file stems of several inputs are the same.
+    pub fn input_as(&mut self, path: impl AsRef<Path>, module: impl Into<String>) -> &mut Self {
+        self.inputs
+            .push((path.as_ref().to_path_buf(), Some(module.into())));
+        self
+    }
+
+    /// Add a directory to look up included files in, after the directory of the including
+    /// file.
+    pub fn include_dir(&mut self, dir: impl AsRef<Path>) -> &mut Self {
+        self.include_dirs.push(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the directory to write generated code to. Defaults to `OUT_DIR`.
+    pub fn out_dir(&mut self, dir: impl AsRef<Path>) -> &mut Self {
+        self.out_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set how to format generated code. Defaults to `Format::Pretty`, which doesn't need
+    /// `rustfmt` to be installed.
+    pub fn format(&mut self, format: Format) -> &mut Self {
+        self.format = format;
+        self
+    }
+
+    /// Derive `path` for all generated structs and enums, e.g. `"Hash"` or
+    /// `"serde::Serialize"`.
+    pub fn derive(&mut self, path: impl Into<String>) -> &mut Self {
+        self.derives.push(path.into());
+        self
+    }
+
+    /// Set whether to generate owned object types with `pack` and `unpack` methods for
+    /// tables and unions. Defaults to `false`.
+    pub fn object_api(&mut self, object_api: bool) -> &mut Self {
+        self.object_api = object_api;
+        self
+    }
+
+    /// Derive `path` for the object types generated by `object_api`, e.g. `"Eq"` or
+    /// `"serde::Serialize"`.
+    pub fn object_derive(&mut self, path: impl Into<String>) -> &mut Self {
+        self.object_derives.push(path.into());
+        self
+    }
+
+    /// Set whether to generate a `#[cfg(test)]` round-trip test for every table, which builds
+    /// the table from generated values and checks that they read back the same. Defaults to
+    /// `false`. The tests use `proptest`, which the crate then needs as a dev-dependency.
+    pub fn round_trip_tests(&mut self, round_trip_tests: bool) -> &mut Self {
+        self.round_trip_tests = round_trip_tests;
+        self
+    }
+
+    /// Set whether to write the code of each namespace to a file of its own, in a directory
+    /// named after the input's module, rather than all of it to `<module>.rs`. The files are
+    /// then included with `include_generated!`. Defaults to `false`.
+    ///
+    /// This keeps the files of large schemas with many namespaces small.
+    pub fn split_namespaces(&mut self, split_namespaces: bool) -> &mut Self {
+        self.split_namespaces = split_namespaces;
+        self
+    }
+
+    /// Set how generated types and fields are named, see [`Naming`]. Defaults to keeping the
+    /// names of the schema.
+    pub fn naming(&mut self, naming: Naming) -> &mut Self {
+        self.naming = naming;
+        self
+    }
+
+    /// Set how the object API and `create_direct` write strings and vectors that are empty, see
+    /// [`EmptyCollections`]. Defaults to writing them like any other.
+    pub fn empty_collections(&mut self, empty_collections: EmptyCollections) -> &mut Self {
+        self.empty_collections = empty_collections;
+        self
+    }
+
+    /// Set the root type of every input, a name like `"my.ns.Monster"`, for which the functions
+    /// reading and finishing buffers are generated. This overrides the root types declared by
+    /// the inputs and the files they include, which is needed when the included files declare
+    /// different ones.
+    ///
+    /// By default the root type is the one declared by the input or, if it declares none, the
+    /// one declared by the files it includes.
+    pub fn root_type(&mut self, root_type: impl Into<String>) -> &mut Self {
+        self.root_type = Some(root_type.into());
+        self
+    }
+
+    /// Set the visibility of generated types, functions, constants and modules, which is one of
+    /// `"pub"`, `"pub(crate)"` or `"pub(in crate::path)"`. Defaults to `pub`.
+    ///
+    /// Generated code that is only visible within the crate lets a library use a schema as an
+    /// implementation detail, without making the generated API part of its own.
+    pub fn visibility(&mut self, visibility: impl Into<String>) -> &mut Self {
+        self.visibility = Some(visibility.into());
+        self
+    }
+
+    /// Set whether to generate a `prelude` module re-exporting the readers of the tables,
+    /// structs, enums and unions reachable from the root type, or of all of them if there is
+    /// none, so they can be imported with `use prelude::*` whatever their namespace. Names
+    /// declared in more than one namespace aren't re-exported. Defaults to `false`.
+    pub fn prelude(&mut self, prelude: bool) -> &mut Self {
+        self.prelude = prelude;
+        self
+    }
+
+    /// Put all of the generated code, including the prelude, in a module named `module`, so
+    /// that it can be included at the root of a crate as `crate::<module>`. By default the
+    /// code starts with the items of the root namespace, and is included in a module of the
+    /// crate's own.
+    pub fn module(&mut self, module: impl Into<String>) -> &mut Self {
+        self.module = Some(module.into());
+        self
+    }
+
+    /// Generate adapters migrating buffers of the input named `old_module` to the input named
+    /// `new_module`, two versions of a schema, and write them to `<old_module>_to_<new_module>.rs`.
+    ///
+    /// For every table, struct, enum and union of both versions, the adapters have a function
+    /// converting a value of the old version to the new one, named after its fully qualified
+    /// name, like `my_ns_monster`. Fields only in the new version are left out, so they read as
+    /// their defaults, and fields only in the old one are dropped. If both versions have the same
+    /// root type, `adapt_buffer` migrates a whole buffer. Changes that can't be adapted, like a
+    /// field whose type changed, are reported as errors.
+    ///
+    /// The adapters are included with `include_fbs!` in a module next to the modules of the two
+    /// inputs, since they refer to them as `super::<module>`.
+    pub fn adapters(
+        &mut self,
+        old_module: impl Into<String>,
+        new_module: impl Into<String>,
+    ) -> &mut Self {
+        self.adapters.push((old_module.into(), new_module.into()));
+        self
+    }
+
+    /// Add a hook that maps the attributes of RPC methods, including custom ones, to generated
+    /// code. Hooks are applied in the order they are added.
+    pub fn rpc_method_hook(&mut self, hook: impl RpcMethodHook + 'static) -> &mut Self {
+        self.rpc_method_hooks.push(Arc::new(hook));
+        self
+    }
+
+    /// Add a plugin that appends code of its own to the code generated for tables, structs,
+    /// enums, unions and RPC services. Plugins are applied in the order they are added.
+    pub fn plugin(&mut self, plugin: impl CodegenPlugin + 'static) -> &mut Self {
+        self.plugins.push(Arc::new(plugin));
+        self
+    }
+
+    /// Generate code for every input, writing it to `<module>.rs` in the output directory, or
+    /// to `<module>/mod.rs` and a file per namespace if `split_namespaces` is set.
+    pub fn compile(&self) -> Result<()> {
+        let out_dir = match &self.out_dir {
+            Some(dir) => dir.clone(),
+            None => PathBuf::from(std::env::var("OUT_DIR")?),
+        };
+        let options = Options {
+            derives: parse_derives(&self.derives)?,
+            object_api: self.object_api,
+            object_derives: parse_derives(&self.object_derives)?,
+            round_trip_tests: self.round_trip_tests,
+            rpc_method_hooks: self.rpc_method_hooks.clone(),
+            plugins: self.plugins.clone(),
+            naming: self.naming,
+            empty_collections: self.empty_collections,
+            root_type: self.root_type.clone(),
+            visibility: match &self.visibility {
+                Some(visibility) => parse_visibility(visibility)?,
+                None => Visibility::Public,
+            },
+            prelude: self.prelude,
+            module: match &self.module {
+                Some(module) => Some(
+                    syn::parse_str(module)
+                        .map_err(|_| anyhow!("invalid module name: {}", module))?,
+                ),
+                None => None,
+            },
+        };
+        let mut inputs = vec![];
+        for (path, module) in &self.inputs {
+            let module = match module {
+                Some(module) => module.clone(),
+                None => path
+                    .file_stem()
+                    .ok_or_else(|| anyhow!("path has no file_name: {:?}", path))?
+                    .to_string_lossy()
+                    .into_owned(),
+            };
+            inputs.push((path, module.clone()));
+            let files = SchemaFiles::load(path, &self.include_dirs)?;
+            if self.split_namespaces {
+                write_files(self.format, &files, &options, &out_dir.join(module))?;
+                continue;
+            }
+            let output = std::fs::File::create(out_dir.join(format!("{}.rs", module)))?;
+            write_code(self.format, &files, &options, Box::new(output))?;
+        }
+        for (old, new) in &self.adapters {
+            let load = |module: &str| match inputs.iter().find(|(_, name)| name == module) {
+                Some((path, _)) => SchemaFiles::load(path, &self.include_dirs),
+                None => Err(anyhow!(
+                    "no input named {} to generate adapters for",
+                    module
+                )),
+            };
+            let (old_files, new_files) = (load(old)?, load(new)?);
+            let code = adapter_code(&old_files, old, &new_files, new, &options)?;
+            std::fs::write(
+                out_dir.join(format!("{}_to_{}.rs", old, new)),
+                self.format.apply(code)?,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Generate the adapters from the schema of `old_files`, whose module is named `old`, to the
+/// schema of `new_files`, whose module is named `new`, see `Config::adapters`.
+fn adapter_code(
+    old_files: &SchemaFiles,
+    old: &str,
+    new_files: &SchemaFiles,
+    new: &str,
+    options: &Options,
+) -> Result<TokenStream> {
+    let root_type = options.root_type.as_deref();
+    let (old_schema, new_schema) = (
+        checked_schema(old_files, root_type)?,
+        checked_schema(new_files, root_type)?,
     );
-    let ugly = false;
-    compile_fbs_generic(
-        ugly,
-        Box::new(std::fs::File::open(path_ref)?),
-        Box::new(std::fs::File::create(output_path)?),
-    )?;
-    Ok(())
+    let module_path = |name: &str| {
+        let path = match &options.module {
+            Some(module) => format!("super::{}::{}", name, module),
+            None => format!("super::{}", name),
+        };
+        syn::parse_str::<syn::Path>(&path).map_err(|_| anyhow!("invalid module name: {}", name))
+    };
+    let (old_path, new_path) = (module_path(old)?, module_path(new)?);
+    generate_adapters(&old_schema, &old_path, &new_schema, &new_path, options).map_err(|errors| {
+        let diagnostics = errors
+            .into_iter()
+            .map(|error| {
+                // Most errors are about the new version, but removed values are in the old
+                // one.
+                let diagnostic = new_files.diagnostic(error.message, error.at);
+                match diagnostic.location {
+                    Some(_) => diagnostic,
+                    None => old_files.diagnostic(diagnostic.message, error.at),
+                }
+                .to_string()
+            })
+            .collect::<Vec<_>>();
+        anyhow!("{}", diagnostics.join("\n\n"))
+    })
+}
+
+/// Parse a visibility like `pub(crate)`. Visibilities relative to the module
+/// of an item, like `pub(super)`, aren't accepted, as generated items are in
+/// modules of different depths.
+fn parse_visibility(visibility: &str) -> Result<Visibility> {
+    let invalid = || {
+        anyhow!(
+            "invalid visibility, expected `pub`, `pub(crate)` or `pub(in crate::path)`: {}",
+            visibility
+        )
+    };
+    match syn::parse_str(visibility).map_err(|_| invalid())? {
+        syn::Visibility::Public(_) => Ok(Visibility::Public),
+        syn::Visibility::Crate(_) => Ok(Visibility::Crate),
+        syn::Visibility::Restricted(restricted) if restricted.in_token.is_none() => {
+            if restricted.path.is_ident("crate") {
+                Ok(Visibility::Crate)
+            } else {
+                Err(invalid())
+            }
+        }
+        syn::Visibility::Restricted(restricted) => match restricted.path.segments.first() {
+            Some(first) if restricted.path.leading_colon.is_none() && first.ident == "crate" => {
+                Ok(Visibility::Restricted(*restricted.path))
+            }
+            _ => Err(invalid()),
+        },
+        syn::Visibility::Inherited => Err(invalid()),
+    }
+}
+
+fn parse_derives(paths: &[String]) -> Result<Vec<syn::Path>> {
+    paths
+        .iter()
+        .map(|path| syn::parse_str(path).map_err(|_| anyhow!("invalid derive path: {}", path)))
+        .collect()
 }
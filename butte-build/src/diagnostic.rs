@@ -0,0 +1,114 @@
+//! Error messages that point into schema source text.
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// An error in a schema, along with where in the schema it occurred.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub location: Option<Location>,
+}
+
+/// A position in a schema file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    /// The file the position is in, if the schema was read from a file.
+    pub path: Option<PathBuf>,
+
+    /// The line of the position, starting at 1.
+    pub line: usize,
+
+    /// The column of the position in characters, starting at 1.
+    pub column: usize,
+
+    /// The full text of the line.
+    pub line_text: String,
+
+    /// The length of the highlighted text in characters.
+    pub len: usize,
+}
+
+impl Location {
+    /// Locate `fragment` in `text`, if `fragment` is a slice of `text`.
+    pub fn find(path: Option<&Path>, text: &str, fragment: &str) -> Option<Self> {
+        let start = text.as_ptr() as usize;
+        let offset = (fragment.as_ptr() as usize).checked_sub(start)?;
+        if offset + fragment.len() > text.len() {
+            return None;
+        }
+        let before = &text[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let line_end = text[offset..].find('\n').map_or(text.len(), |i| offset + i);
+        let line_text = text[line_start..line_end].trim_end_matches('\r');
+        let len = fragment.chars().take_while(|&c| c != '\n').count().max(1);
+        Some(Self {
+            path: path.map(Path::to_path_buf),
+            line: before.matches('\n').count() + 1,
+            column: text[line_start..offset].chars().count() + 1,
+            line_text: line_text.to_string(),
+            len,
+        })
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "error: {}", self.message)?;
+        if let Some(location) = &self.location {
+            let path = location
+                .path
+                .as_ref()
+                .map_or_else(|| "<input>".to_string(), |path| path.display().to_string());
+            let line_number = location.line.to_string();
+            let gutter = " ".repeat(line_number.len());
+            write!(
+                f,
+                "\n{gutter}--> {}:{}:{}\n{gutter} |\n{} | {}\n{gutter} | {}{}",
+                path,
+                location.line,
+                location.column,
+                line_number,
+                location.line_text,
+                " ".repeat(location.column - 1),
+                "^".repeat(location.len),
+                gutter = gutter,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+#[cfg(test)]
+mod diagnostic_tests {
+    use super::*;
+
+    #[test]
+    fn test_find_location() {
+        let text = "table A {\n  x: Colr;\n}";
+        let fragment = &text[15..19];
+        assert_eq!(fragment, "Colr");
+        let location = Location::find(None, text, fragment).unwrap();
+        assert_eq!((location.line, location.column), (2, 6));
+        assert_eq!(location.line_text, "  x: Colr;");
+        assert_eq!(location.len, 4);
+
+        assert!(Location::find(None, text, "Colr").is_none());
+    }
+
+    #[test]
+    fn test_display() {
+        let text = "table A {\n  x: Colr;\n}";
+        let diagnostic = Diagnostic {
+            message: "unknown type `Colr`".to_string(),
+            location: Location::find(Some(Path::new("a.fbs")), text, &text[15..19]),
+        };
+        assert_eq!(
+            diagnostic.to_string(),
+            "error: unknown type `Colr`\n --> a.fbs:2:6\n  |\n2 |   x: Colr;\n  |      ^^^^"
+        );
+    }
+}
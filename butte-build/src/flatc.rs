@@ -0,0 +1,209 @@
+//! Cross-checking butte against `flatc`, the reference implementation.
+//!
+//! [`Flatc`] runs an installed `flatc` to build buffers from JSON and to print
+//! buffers as JSON, so tests can check that both implementations agree: that
+//! butte reads buffers built by `flatc` like `flatc` does, and that `flatc`
+//! accepts and reads buffers built by butte like butte does. Since
+//! `butte::json::to_json` prints buffers like `flatc --json --strict-json`,
+//! reading the same buffer must give the same text.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use butte_build::flatc::Flatc;
+//!
+//! // Fail, rather than pass without checking anything, when flatc isn't
+//! // installed.
+//! let flatc = Flatc::find().ok_or_else(|| anyhow::anyhow!("flatc not found"))?;
+//! flatc.check_reads("monster.fbs", r#"{ name: "Orc", hp: 80 }"#)?;
+//! # Ok(())
+//! # }
+//! ```
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use anyhow::{anyhow, Result};
+
+use crate::{include::SchemaFiles, json_schema};
+
+/// An installed `flatc`, along with the directories it looks up included
+/// schema files in.
+#[derive(Debug, Clone)]
+pub struct Flatc {
+    program: PathBuf,
+    include_dirs: Vec<PathBuf>,
+}
+
+impl Flatc {
+    /// Use the `flatc` at `program`, which may also be a name to look up on
+    /// the `PATH`.
+    pub fn new(program: impl Into<PathBuf>) -> Self {
+        Self {
+            program: program.into(),
+            include_dirs: vec![],
+        }
+    }
+
+    /// Find the `flatc` named by the `FLATC` environment variable, or else
+    /// the one on the `PATH`, if it can be run.
+    pub fn find() -> Option<Self> {
+        let program = std::env::var_os("FLATC").unwrap_or_else(|| "flatc".into());
+        let flatc = Self::new(program);
+        flatc.version().ok().map(|_| flatc)
+    }
+
+    /// Add a directory to look up included files in, for both `flatc` and
+    /// butte.
+    pub fn include_dir(&mut self, dir: impl AsRef<Path>) -> &mut Self {
+        self.include_dirs.push(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// The version `flatc` reports, e.g. `flatc version 24.3.25`.
+    pub fn version(&self) -> Result<String> {
+        let output = self.run(Command::new(&self.program).arg("--version"))?;
+        Ok(String::from_utf8(output)?.trim().to_string())
+    }
+
+    /// Build the buffer described by `json`, whose root is the root table of
+    /// `schema`, with `flatc --binary`.
+    pub fn binary(&self, schema: impl AsRef<Path>, json: &str) -> Result<Vec<u8>> {
+        let dir = TempDir::new()?;
+        let input = dir.path.join("buffer.json");
+        std::fs::write(&input, json)?;
+        self.run(
+            self.command(&dir)
+                .arg("--binary")
+                .arg(schema.as_ref())
+                .arg(&input),
+        )?;
+        Ok(std::fs::read(dir.path.join("buffer.bin"))?)
+    }
+
+    /// Print `buf`, a buffer whose root is the root table of `schema`, with
+    /// `flatc --json --strict-json`. `flatc` verifies the buffer first, so
+    /// this fails for malformed buffers.
+    pub fn json(&self, schema: impl AsRef<Path>, buf: &[u8]) -> Result<String> {
+        let dir = TempDir::new()?;
+        let input = dir.path.join("buffer.bin");
+        std::fs::write(&input, buf)?;
+        self.run(
+            self.command(&dir)
+                .args(["--json", "--strict-json", "--raw-binary"])
+                .arg(schema.as_ref())
+                .arg("--")
+                .arg(&input),
+        )?;
+        Ok(std::fs::read_to_string(dir.path.join("buffer.json"))?)
+    }
+
+    /// Check that butte reads the buffer `flatc` builds from `json` like
+    /// `flatc` does.
+    pub fn check_reads(&self, schema: impl AsRef<Path>, json: &str) -> Result<()> {
+        let schema = schema.as_ref();
+        let buf = self.binary(schema, json)?;
+        self.compare(schema, &buf)
+    }
+
+    /// Check that `flatc` accepts `buf`, a buffer built by butte, and reads it
+    /// like butte does.
+    pub fn check_buffer(&self, schema: impl AsRef<Path>, buf: &[u8]) -> Result<()> {
+        self.compare(schema.as_ref(), buf)
+    }
+
+    /// Compare how butte and `flatc` print `buf`.
+    fn compare(&self, schema: &Path, buf: &[u8]) -> Result<()> {
+        let files = SchemaFiles::load(schema, &self.include_dirs)?;
+        let expected = self.json(schema, buf)?;
+        let actual = butte::json::to_json(&json_schema(&files)?, buf)?;
+        if actual.trim_end() != expected.trim_end() {
+            return Err(anyhow!(
+                "butte and flatc read the buffer differently\n\
+                 butte:\n{}\nflatc:\n{}",
+                actual.trim_end(),
+                expected.trim_end()
+            ));
+        }
+        Ok(())
+    }
+
+    /// A `flatc` command writing its output to `dir`.
+    fn command(&self, dir: &TempDir) -> Command {
+        let mut command = Command::new(&self.program);
+        for include_dir in &self.include_dirs {
+            command.arg("-I").arg(include_dir);
+        }
+        command.arg("-o").arg(&dir.path);
+        command
+    }
+
+    /// Run `command`, returning its standard output.
+    fn run(&self, command: &mut Command) -> Result<Vec<u8>> {
+        let output = command
+            .output()
+            .map_err(|e| anyhow!("cannot run {}: {}", self.program.display(), e))?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "flatc failed ({}): {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(output.stdout)
+    }
+}
+
+/// A directory for the files passed to and written by `flatc`, which is
+/// removed when dropped.
+struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    fn new() -> Result<Self> {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "butte-flatc-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&path)?;
+        Ok(Self { path })
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod flatc_tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_flatc() {
+        let flatc = Flatc::new("/nonexistent/flatc");
+        let error = flatc.version().unwrap_err().to_string();
+        assert!(
+            error.starts_with("cannot run /nonexistent/flatc:"),
+            "{}",
+            error
+        );
+        assert!(flatc.binary("monster.fbs", "{}").is_err());
+    }
+
+    #[test]
+    fn test_failing_flatc() {
+        // `false` stands in for a flatc that rejects its input.
+        let error = Flatc::new("false").json("monster.fbs", &[]).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("flatc failed (exit status: 1)"));
+    }
+}
@@ -0,0 +1,852 @@
+//! Canonical formatting of schemas, like `rustfmt` for `.fbs` files.
+//!
+//! Declarations are printed in the order they are declared, one blank line
+//! apart, with their bodies indented by two spaces. The attributes of the
+//! fields of a table or struct are aligned, within each run of fields that
+//! isn't broken up by a blank line. Blank lines between fields, enum values
+//! and RPC methods are kept, but collapsed into one.
+//!
+//! The parser keeps documentation comments only, so the other comments of
+//! the source are put back in front of the declaration, field or value that
+//! follows them, on lines of their own.
+//!
+//! Schemas built in code, from the types of [`crate::types`], are printed
+//! the same way, by [`print_schema`] or their `Display` implementation:
+//!
+//! ```
+//! use butte_build::types::*;
+//!
+//! let monster = Table::builder()
+//!     .id("Monster")
+//!     .fields(vec![
+//!         Field::builder()
+//!             .id("hp")
+//!             .ty(Type::Short)
+//!             .default(Some(DefaultValue::from(Scalar::Integer(100))))
+//!             .build(),
+//!         Field::builder()
+//!             .id("name")
+//!             .ty(Type::String)
+//!             .metadata(Some(Metadata::from(vec![("required".into(), None)])))
+//!             .build(),
+//!     ])
+//!     .doc(vec![" A monster."])
+//!     .build();
+//! let schema = Schema::builder()
+//!     .elements(vec![
+//!         Namespace::builder().ident("my.game").build().into(),
+//!         monster.into(),
+//!         Root::builder().typename("Monster").build().into(),
+//!     ])
+//!     .build();
+//! assert_eq!(
+//!     schema.to_string(),
+//!     "namespace my.game;\n\n/// A monster.\ntable Monster {\n  hp: short = 100;\n  name: string (required);\n}\n\nroot_type Monster;\n"
+//! );
+//! ```
+use std::{fmt, path::Path};
+
+use anyhow::Result;
+
+use crate::{
+    diagnostic::{Diagnostic, Location},
+    parser::parse_schema,
+    types::*,
+};
+
+/// The indentation of the bodies of declarations.
+const INDENT: &str = "  ";
+
+/// Format the schema `text` canonically.
+///
+/// `path` is where `text` was read from, if anywhere, which syntax errors
+/// refer to.
+///
+/// # Examples
+///
+/// ```
+/// let text = "table Monster{hp:short=100;name:string(required);}";
+/// let formatted = butte_build::format::format_schema(text, None).unwrap();
+/// assert_eq!(
+///     formatted,
+///     "table Monster {\n  hp: short = 100;\n  name: string (required);\n}\n"
+/// );
+/// ```
+pub fn format_schema(text: &str, path: Option<&Path>) -> Result<String> {
+    let schema = parse_schema(text).map_err(|error| {
+        anyhow::Error::new(Diagnostic {
+            message: error.message,
+            location: Location::find(path, text, error.at),
+        })
+    })?;
+    let mut printer = Printer::new(Some(text));
+    printer.schema(&schema);
+    Ok(printer.out)
+}
+
+/// Print `schema` canonically, without any of the comments of its source
+/// other than documentation comments.
+pub fn print_schema(schema: &Schema) -> String {
+    let mut printer = Printer::new(None);
+    printer.schema(schema);
+    printer.out
+}
+
+impl fmt::Display for Schema<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&print_schema(self))
+    }
+}
+
+impl fmt::Display for Element<'_> {
+    /// Print a single declaration, with a trailing newline.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut printer = Printer::new(None);
+        printer.element(self, None);
+        f.write_str(&printer.out)
+    }
+}
+
+impl fmt::Display for Type<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&type_name(self))
+    }
+}
+
+impl fmt::Display for DottedIdent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&dotted(self))
+    }
+}
+
+/// The comments and closing braces of a schema's source, which are what the
+/// parser leaves out of the schema and the printer needs to know about.
+#[derive(Debug, Default)]
+struct Source<'a> {
+    text: &'a str,
+    /// The comments that aren't documentation comments, with their offsets.
+    comments: Vec<(usize, &'a str)>,
+    /// The offsets of the `}` that aren't in strings or comments.
+    closing_braces: Vec<usize>,
+}
+
+impl<'a> Source<'a> {
+    fn scan(text: &'a str) -> Self {
+        let bytes = text.as_bytes();
+        let mut source = Self {
+            text,
+            ..Self::default()
+        };
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'"' => {
+                    i += 1;
+                    while i < bytes.len() && bytes[i] != b'"' {
+                        if bytes[i] == b'\\' {
+                            i += 1;
+                        }
+                        i += 1;
+                    }
+                }
+                b'/' if text[i..].starts_with("//") => {
+                    let end = text[i..].find('\n').map_or(text.len(), |end| i + end);
+                    // documentation comments are part of the schema
+                    if !text[i..].starts_with("///") {
+                        source.comments.push((i, text[i..end].trim_end()));
+                    }
+                    i = end;
+                }
+                b'}' => source.closing_braces.push(i),
+                _ => {}
+            }
+            i += 1;
+        }
+        source
+    }
+
+    /// The offset of `fragment` in the source, if it is a slice of it.
+    fn offset(&self, fragment: &str) -> Option<usize> {
+        let offset = (fragment.as_ptr() as usize).checked_sub(self.text.as_ptr() as usize)?;
+        (offset + fragment.len() <= self.text.len()).then_some(offset)
+    }
+}
+
+struct Printer<'a> {
+    out: String,
+    source: Source<'a>,
+    /// The number of comments of the source that have been printed.
+    printed_comments: usize,
+}
+
+impl<'a> Printer<'a> {
+    fn new(text: Option<&'a str>) -> Self {
+        Self {
+            out: String::new(),
+            source: text.map(Source::scan).unwrap_or_default(),
+            printed_comments: 0,
+        }
+    }
+
+    fn line(&mut self, indent: &str, line: &str) {
+        self.out.push_str(indent);
+        self.out.push_str(line);
+        self.out.push('\n');
+    }
+
+    fn blank_line(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    /// Print the comments of the source before `offset`.
+    fn comments_before(&mut self, offset: Option<usize>, indent: &str) {
+        let offset = match offset {
+            Some(offset) => offset,
+            None => return,
+        };
+        while let Some(&(at, comment)) = self.source.comments.get(self.printed_comments) {
+            if at >= offset {
+                break;
+            }
+            self.line(indent, comment);
+            self.printed_comments += 1;
+        }
+    }
+
+    /// Check whether there is a blank line in the source between `from` and
+    /// `to`.
+    fn has_blank_line(&self, from: Option<usize>, to: Option<usize>) -> bool {
+        match (from, to) {
+            (Some(from), Some(to)) if from < to => {
+                // the lines in between, without those of `from` and `to`
+                let lines = self.source.text[from..to].split('\n').collect::<Vec<_>>();
+                lines.len() > 2
+                    && lines[1..lines.len() - 1]
+                        .iter()
+                        .any(|line| line.trim().is_empty())
+            }
+            _ => false,
+        }
+    }
+
+    /// Print the documentation comment of an item, preceded by the comments
+    /// of the source that come before the item.
+    fn item_start(&mut self, doc: &Comment<'a>, anchor: Option<usize>, indent: &str) {
+        let anchor = doc
+            .lines
+            .first()
+            .and_then(|line| self.source.offset(line))
+            .or(anchor);
+        self.comments_before(anchor, indent);
+        for line in &doc.lines {
+            self.line(indent, &format!("///{}", line.trim_end()));
+        }
+    }
+
+    /// Print the closing brace of a body whose last item is at `last`,
+    /// preceded by the comments that come after that item in the body.
+    fn close_body(&mut self, last: Option<usize>) {
+        let brace = last.and_then(|last| {
+            self.source
+                .closing_braces
+                .iter()
+                .copied()
+                .find(|&brace| brace > last)
+        });
+        self.comments_before(brace, INDENT);
+        self.line("", "}");
+    }
+
+    fn schema(&mut self, schema: &Schema<'a>) {
+        for include in &schema.includes {
+            let path = include.path.to_string_lossy();
+            let anchor = include
+                .path
+                .to_str()
+                .and_then(|path| self.source.offset(path));
+            self.item_start(&include.doc, anchor, "");
+            self.line("", &format!("include \"{}\";", path));
+        }
+
+        let mut previous: Option<(&Element, Option<usize>)> = None;
+        for element in &schema.elements {
+            let anchor = self.anchor(element);
+            if let Some((previous, previous_anchor)) = previous {
+                let same_kind = std::mem::discriminant(previous) == std::mem::discriminant(element);
+                if !(same_kind && is_one_line(element))
+                    || self.has_blank_line(previous_anchor, anchor)
+                {
+                    self.blank_line();
+                }
+            } else if !schema.includes.is_empty() {
+                self.blank_line();
+            }
+            self.element(element, anchor);
+            previous = Some((element, anchor));
+        }
+
+        // comments at the end of the schema
+        self.comments_before(Some(usize::MAX), "");
+    }
+
+    /// The offset of the name of `element` in the source, which the comments
+    /// before it come before.
+    fn anchor(&self, element: &Element<'a>) -> Option<usize> {
+        let name = match element {
+            Element::Namespace(Namespace { ident, .. }) => ident.parts.first()?.raw,
+            Element::Table(t) => t.id.raw,
+            Element::Struct(s) => s.id.raw,
+            Element::Enum(e) => e.id.raw,
+            Element::Union(u) => u.id.raw,
+            Element::Root(Root { typename, .. }) => typename.raw,
+            Element::FileExtension(FileExtension { ext, .. }) => ext,
+            Element::Attribute(Attribute { attr, .. }) => attr.raw,
+            Element::Rpc(rpc) => rpc.id.raw,
+            Element::FileIdentifier(_) | Element::Object(_) => return None,
+        };
+        self.source.offset(name)
+    }
+
+    fn element(&mut self, element: &Element<'a>, anchor: Option<usize>) {
+        match element {
+            Element::Namespace(Namespace { ident, doc }) => {
+                self.item_start(doc, anchor, "");
+                self.line("", &format!("namespace {};", dotted(ident)));
+            }
+            Element::Table(t) => {
+                self.item_start(&t.doc, anchor, "");
+                self.line(
+                    "",
+                    &format!("table {}{} {{", t.id.raw, self.metadata(&t.metadata)),
+                );
+                self.fields(&t.fields);
+            }
+            Element::Struct(s) => {
+                self.item_start(&s.doc, anchor, "");
+                self.line(
+                    "",
+                    &format!("struct {}{} {{", s.id.raw, self.metadata(&s.metadata)),
+                );
+                self.fields(&s.fields);
+            }
+            Element::Enum(e) => {
+                self.item_start(&e.doc, anchor, "");
+                self.line(
+                    "",
+                    &format!(
+                        "enum {} : {}{} {{",
+                        e.id.raw,
+                        type_name(&e.base_type),
+                        self.metadata(&e.metadata)
+                    ),
+                );
+                self.values(&e.values, |value| match value.value {
+                    Some(n) => format!("{} = {}", value.id.raw, n),
+                    None => value.id.raw.to_string(),
+                });
+            }
+            Element::Union(u) => {
+                self.item_start(&u.doc, anchor, "");
+                self.line(
+                    "",
+                    &format!("union {}{} {{", u.id.raw, self.metadata(&u.metadata)),
+                );
+                self.values(&u.values, |value| {
+                    let mut line = value.id.raw.to_string();
+                    if let Some(ty) = &value.ty {
+                        line.push_str(&format!(": {}", dotted(ty)));
+                    }
+                    if let Some(n) = value.value {
+                        line.push_str(&format!(" = {}", n));
+                    }
+                    line
+                });
+            }
+            Element::Root(Root { typename, doc }) => {
+                self.item_start(doc, anchor, "");
+                self.line("", &format!("root_type {};", typename.raw));
+            }
+            Element::FileExtension(FileExtension { ext, doc }) => {
+                self.item_start(doc, anchor, "");
+                self.line("", &format!("file_extension \"{}\";", ext));
+            }
+            Element::FileIdentifier(FileIdentifier { id, doc }) => {
+                self.item_start(doc, anchor, "");
+                let id = id.iter().collect::<String>();
+                self.line("", &format!("file_identifier \"{}\";", id));
+            }
+            Element::Attribute(Attribute { attr, doc }) => {
+                self.item_start(doc, anchor, "");
+                self.line("", &format!("attribute \"{}\";", attr.raw));
+            }
+            Element::Rpc(rpc) => {
+                self.item_start(&rpc.doc, anchor, "");
+                self.line("", &format!("rpc_service {} {{", rpc.id.raw));
+                let mut previous = None;
+                for method in &rpc.methods {
+                    let anchor = self.source.offset(method.id.raw);
+                    if self.has_blank_line(previous, anchor) {
+                        self.blank_line();
+                    }
+                    self.item_start(&method.doc, anchor, INDENT);
+                    let line = format!(
+                        "{}({}): {}{};",
+                        method.id.raw,
+                        dotted(&method.request_type),
+                        dotted(&method.response_type),
+                        self.metadata(&method.metadata)
+                    );
+                    self.line(INDENT, &line);
+                    previous = anchor;
+                }
+                self.close_body(previous);
+            }
+            Element::Object(object) => {
+                let object = self.object(object);
+                self.line("", &object);
+            }
+        }
+    }
+
+    /// Print the fields of a table or struct and the closing brace of its
+    /// body.
+    fn fields(&mut self, fields: &[Field<'a>]) {
+        // the fields are printed in runs, separated by blank lines, in which
+        // their attributes are aligned
+        let mut runs: Vec<Vec<&Field<'a>>> = vec![];
+        let mut previous = None;
+        for field in fields {
+            let anchor = self.source.offset(field.id.raw);
+            match runs.last_mut() {
+                Some(run) if !self.has_blank_line(previous, anchor) => run.push(field),
+                _ => runs.push(vec![field]),
+            }
+            previous = anchor;
+        }
+
+        for (i, run) in runs.iter().enumerate() {
+            if i > 0 {
+                self.blank_line();
+            }
+            let declarations = run
+                .iter()
+                .map(|field| field_declaration(field))
+                .collect::<Vec<_>>();
+            let width = run
+                .iter()
+                .zip(&declarations)
+                .filter(|(field, _)| field.metadata.is_some())
+                .map(|(_, declaration)| declaration.chars().count())
+                .max()
+                .unwrap_or(0);
+            for (field, declaration) in run.iter().zip(declarations) {
+                let anchor = self.source.offset(field.id.raw);
+                self.item_start(&field.doc, anchor, INDENT);
+                let metadata = self.metadata(&field.metadata);
+                let line = if metadata.is_empty() {
+                    format!("{};", declaration)
+                } else {
+                    format!("{:width$}{};", declaration, metadata, width = width)
+                };
+                self.line(INDENT, &line);
+            }
+        }
+        self.close_body(previous);
+    }
+
+    /// Print the values of an enum or union, as given by `value`, and the
+    /// closing brace of its body.
+    fn values(&mut self, values: &[EnumVal<'a>], value: impl Fn(&EnumVal<'a>) -> String) {
+        let mut previous = None;
+        for (i, enum_val) in values.iter().enumerate() {
+            let anchor = self.source.offset(enum_val.id.raw);
+            if self.has_blank_line(previous, anchor) {
+                self.blank_line();
+            }
+            self.item_start(&enum_val.doc, anchor, INDENT);
+            let separator = if i + 1 < values.len() { "," } else { "" };
+            self.line(INDENT, &format!("{}{}", value(enum_val), separator));
+            previous = anchor;
+        }
+        self.close_body(previous);
+    }
+
+    /// The attributes in `metadata`, preceded by a space, in the order they
+    /// are given in the source, or else sorted by name.
+    fn metadata(&self, metadata: &Option<Metadata<'a>>) -> String {
+        let metadata = match metadata {
+            Some(metadata) if !metadata.values.is_empty() => metadata,
+            _ => return String::new(),
+        };
+        let mut attributes = metadata.values.iter().collect::<Vec<_>>();
+        attributes.sort_by_key(|(id, _)| (self.source.offset(id.raw), id.raw));
+        let attributes = attributes
+            .into_iter()
+            .map(|(id, value)| match value {
+                Some(value) => format!("{}: {}", id.raw, single(value)),
+                None => id.raw.to_string(),
+            })
+            .collect::<Vec<_>>();
+        format!(" ({})", attributes.join(", "))
+    }
+
+    fn object(&self, object: &Object<'a>) -> String {
+        let mut values = object.values.iter().collect::<Vec<_>>();
+        values.sort_by_key(|(id, _)| (self.source.offset(id.raw), id.raw));
+        let values = values
+            .into_iter()
+            .map(|(id, value)| format!("{}: {}", id.raw, self.value(value)))
+            .collect::<Vec<_>>();
+        if values.is_empty() {
+            "{}".to_string()
+        } else {
+            format!("{{ {} }}", values.join(", "))
+        }
+    }
+
+    fn value(&self, value: &Value<'a>) -> String {
+        match value {
+            Value::Single(value) => single(value),
+            Value::Object(object) => self.object(object),
+            Value::List(values) => {
+                let values = values
+                    .iter()
+                    .map(|value| self.value(value))
+                    .collect::<Vec<_>>();
+                format!("[{}]", values.join(", "))
+            }
+        }
+    }
+}
+
+/// Check whether `element` is a declaration printed on a single line, which
+/// needs no blank line between it and another of its kind.
+fn is_one_line(element: &Element) -> bool {
+    matches!(
+        element,
+        Element::Namespace(_)
+            | Element::Root(_)
+            | Element::FileExtension(_)
+            | Element::FileIdentifier(_)
+            | Element::Attribute(_)
+    )
+}
+
+/// A field without its attributes, e.g. `hp: short = 100`.
+fn field_declaration(field: &Field) -> String {
+    let mut declaration = format!("{}: {}", field.id.raw, type_name(&field.ty));
+    match &field.default {
+        Some(DefaultValue::Scalar(value)) => {
+            declaration.push_str(&format!(" = {}", scalar(value)));
+        }
+        Some(DefaultValue::EnumVal(id)) => declaration.push_str(&format!(" = {}", id.raw)),
+        Some(DefaultValue::Null) => declaration.push_str(" = null"),
+        None => {}
+    }
+    declaration
+}
+
+fn dotted(id: &DottedIdent) -> String {
+    id.parts
+        .iter()
+        .map(|part| part.raw)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// The name of `ty` in a schema.
+fn type_name(ty: &Type) -> String {
+    let name = match ty {
+        Type::Bool => "bool",
+        Type::Byte => "byte",
+        Type::UByte => "ubyte",
+        Type::Short => "short",
+        Type::UShort => "ushort",
+        Type::Int => "int",
+        Type::UInt => "uint",
+        Type::Float => "float",
+        Type::Long => "long",
+        Type::ULong => "ulong",
+        Type::Double => "double",
+        Type::Int8 => "int8",
+        Type::UInt8 => "uint8",
+        Type::Int16 => "int16",
+        Type::UInt16 => "uint16",
+        Type::Int32 => "int32",
+        Type::UInt32 => "uint32",
+        Type::Int64 => "int64",
+        Type::UInt64 => "uint64",
+        Type::Float32 => "float32",
+        Type::Float64 => "float64",
+        Type::String => "string",
+        Type::Array(element) => return format!("[{}]", type_name(element)),
+        Type::FixedArray(element, len) => return format!("[{}:{}]", type_name(element), len),
+        Type::Ident(id) => return dotted(id),
+    };
+    name.to_string()
+}
+
+fn scalar(value: &Scalar) -> String {
+    match value {
+        Scalar::Integer(n) => n.to_string(),
+        Scalar::Float(x) if x.is_nan() => "nan".to_string(),
+        Scalar::Float(x) if x.is_infinite() => {
+            if x.is_sign_positive() { "inf" } else { "-inf" }.to_string()
+        }
+        // unlike `Display`, `Debug` always prints a decimal point or an
+        // exponent, which keeps the value a float
+        Scalar::Float(x) => format!("{:?}", x),
+        Scalar::Boolean(b) => b.to_string(),
+    }
+}
+
+fn single(value: &Single) -> String {
+    match value {
+        Single::Scalar(value) => scalar(value),
+        Single::String(string) => format!("\"{}\"", string),
+    }
+}
+
+#[cfg(test)]
+mod format_tests {
+    use super::*;
+
+    fn format(text: &str) -> String {
+        format_schema(text, None).unwrap()
+    }
+
+    #[test]
+    fn test_format_declarations() {
+        let input = "\
+include \"a.fbs\";include \"b.fbs\";
+namespace my.game;
+attribute priority;attribute \"internal\";
+/// A color.
+enum Color:ubyte(bit_flags){Red,Green=2,
+  Blue}
+union Any{Monster,Alias:my.game.Weapon=3}
+struct Vec3(force_align:16){x:float;y:float;z:float;path:[ubyte:4];}
+table Monster
+{
+  ///  The position.
+  pos:Vec3;mana:short=150;hp:short=100(deprecated);
+  name:string(required,priority:1);
+
+
+  inventory:[ubyte];color:Color=Blue;ratio:double=1e100;speed:float=inf;
+  friendly:bool=false(priority:2);fallback:int=null;
+}
+table Weapon{damage:int;}
+rpc_service Game{Attack(Weapon):Monster(streaming:\"server\");}
+root_type Monster;
+file_identifier \"MONS\";file_extension \"mon\";
+";
+        let expected = "\
+include \"a.fbs\";
+include \"b.fbs\";
+
+namespace my.game;
+
+attribute \"priority\";
+attribute \"internal\";
+
+/// A color.
+enum Color : ubyte (bit_flags) {
+  Red,
+  Green = 2,
+  Blue
+}
+
+union Any {
+  Monster,
+  Alias: my.game.Weapon = 3
+}
+
+struct Vec3 (force_align: 16) {
+  x: float;
+  y: float;
+  z: float;
+  path: [ubyte:4];
+}
+
+table Monster {
+  ///  The position.
+  pos: Vec3;
+  mana: short = 150;
+  hp: short = 100 (deprecated);
+  name: string    (required, priority: 1);
+
+  inventory: [ubyte];
+  color: Color = Blue;
+  ratio: double = 1e100;
+  speed: float = inf;
+  friendly: bool = false (priority: 2);
+  fallback: int = null;
+}
+
+table Weapon {
+  damage: int;
+}
+
+rpc_service Game {
+  Attack(Weapon): Monster (streaming: \"server\");
+}
+
+root_type Monster;
+
+file_identifier \"MONS\";
+
+file_extension \"mon\";
+";
+        assert_eq!(format(input), expected);
+        assert_eq!(format(expected), expected);
+    }
+
+    #[test]
+    fn test_format_comments() {
+        let input = "\
+// The schema of a game.
+namespace game;
+
+// Monsters.
+table Monster { // opening
+  // The health.
+  hp: short; name: string (note: \"a // b\"); // not a field
+  // at the end
+}
+// at the end of the file
+";
+        let expected = "\
+// The schema of a game.
+namespace game;
+
+// Monsters.
+table Monster {
+  // opening
+  // The health.
+  hp: short;
+  name: string (note: \"a // b\");
+  // not a field
+  // at the end
+}
+// at the end of the file
+";
+        assert_eq!(format(input), expected);
+        assert_eq!(format(expected), expected);
+    }
+
+    #[test]
+    fn test_format_syntax_error() {
+        let error = format_schema("table T { a: int }", Some(Path::new("t.fbs"))).unwrap_err();
+        assert!(error.to_string().contains("t.fbs:1:"));
+    }
+
+    #[test]
+    fn test_print_schema() {
+        let schema = parse_schema("table T { a: int = 1 (deprecated); } root_type T;").unwrap();
+        assert_eq!(
+            print_schema(&schema),
+            "table T {\n  a: int = 1 (deprecated);\n}\n\nroot_type T;\n"
+        );
+    }
+
+    #[test]
+    fn test_print_built_schema() {
+        let color = Enum::builder()
+            .id("Color")
+            .base_type(Type::UByte)
+            .values(vec![
+                EnumVal::builder().id("Red").build(),
+                EnumVal::builder().id("Green").value(Some(2)).build(),
+            ])
+            .build();
+        let point = Struct::builder()
+            .id("Point")
+            .fields(vec![
+                Field::builder().id("x").ty(Type::Float).build(),
+                Field::builder().id("y").ty(Type::Float).build(),
+            ])
+            .build();
+        let shape = Table::builder()
+            .id("Shape")
+            .fields(vec![
+                Field::builder()
+                    .id("color")
+                    .ty(Type::Ident("Color".into()))
+                    .default(Some(DefaultValue::EnumVal("Green".into())))
+                    .build(),
+                Field::builder()
+                    .id("points")
+                    .ty([Type::Ident("Point".into())])
+                    .metadata(Some(Metadata::from(vec![("id".into(), Some(1.into()))])))
+                    .build(),
+                Field::builder()
+                    .id("scale")
+                    .ty(Type::Double)
+                    .default(Some(DefaultValue::from(Scalar::Float(1.0))))
+                    .metadata(Some(Metadata::from(vec![("id".into(), Some(0.into()))])))
+                    .build(),
+            ])
+            .build();
+        let schema = Schema::builder()
+            .elements(vec![color.into(), point.into(), shape.into()])
+            .build();
+        let text = schema.to_string();
+        assert_eq!(
+            text,
+            "\
+enum Color : ubyte {
+  Red,
+  Green = 2
+}
+
+struct Point {
+  x: float;
+  y: float;
+}
+
+table Shape {
+  color: Color = Green;
+  points: [Point]     (id: 1);
+  scale: double = 1.0 (id: 0);
+}
+"
+        );
+        assert_eq!(parse_schema(&text).unwrap(), schema);
+        assert_eq!(Type::from([Type::Ident("a.B".into())]).to_string(), "[a.B]");
+        assert_eq!(
+            Element::from(Root::builder().typename("Shape").build()).to_string(),
+            "root_type Shape;\n"
+        );
+    }
+
+    /// Formatting the example schemas keeps their declarations, and
+    /// formatting them again changes nothing.
+    #[test]
+    fn test_format_examples() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../butte-examples/fbs");
+        let mut paths = vec![];
+        for entry in std::fs::read_dir(dir).unwrap() {
+            for entry in std::fs::read_dir(entry.unwrap().path()).unwrap() {
+                paths.push(entry.unwrap().path());
+            }
+        }
+        assert!(!paths.is_empty());
+        for path in paths
+            .iter()
+            .filter(|path| path.extension().is_some_and(|ext| ext == "fbs"))
+        {
+            let text = std::fs::read_to_string(path).unwrap();
+            let formatted = format_schema(&text, Some(path)).unwrap();
+            assert_eq!(
+                parse_schema(&formatted).unwrap(),
+                parse_schema(&text).unwrap(),
+                "{}",
+                path.display()
+            );
+            assert_eq!(format(&formatted), formatted, "{}", path.display());
+        }
+    }
+}
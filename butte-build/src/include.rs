@@ -0,0 +1,501 @@
+//! Resolution of `include` declarations across schema files.
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+
+use crate::{
+    diagnostic::{Diagnostic, Location},
+    parser::parse_schema,
+    types::{DottedIdent, Element, Namespace, Root, Schema},
+};
+
+/// A schema file together with every file it includes, directly or
+/// indirectly.
+///
+/// The files are ordered such that every file comes after the files it
+/// includes, so the root file is always the last one. A file included by
+/// several others, or by several paths, is told apart by its canonical path
+/// and appears once, so its declarations are generated once.
+#[derive(Debug, Clone)]
+pub struct SchemaFiles {
+    files: Vec<SchemaFile>,
+}
+
+#[derive(Debug, Clone)]
+struct SchemaFile {
+    path: Option<PathBuf>,
+    text: String,
+}
+
+impl SchemaFiles {
+    /// Load the schema at `path` and all of the files it includes.
+    ///
+    /// Includes are looked up relative to the directory of the including
+    /// file first, and then relative to each of `include_dirs` in order.
+    pub fn load(path: impl AsRef<Path>, include_dirs: &[PathBuf]) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("cannot read {}: {}", path.display(), e))?;
+        Self::from_text(text, Some(path), include_dirs)
+    }
+
+    /// Load the schema `text` and all of the files it includes.
+    ///
+    /// `path` is where `text` was read from, if anywhere. Without a path,
+    /// includes of the root schema are only looked up in `include_dirs`.
+    pub fn from_text(text: String, path: Option<&Path>, include_dirs: &[PathBuf]) -> Result<Self> {
+        let mut loader = Loader {
+            include_dirs,
+            files: vec![],
+            visited: HashSet::new(),
+            stack: vec![],
+        };
+        let path = path.map(canonical_path).transpose()?;
+        loader.visit(SchemaFile { path, text })?;
+        Ok(Self {
+            files: loader.files,
+        })
+    }
+
+    /// The paths of all files that make up the schema, if they were read from
+    /// disk.
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().filter_map(|file| file.path.as_deref())
+    }
+
+    /// Create a diagnostic for an error at `fragment`, which is located if it
+    /// is a slice of the text of one of the files.
+    pub fn diagnostic(&self, message: impl Into<String>, fragment: &str) -> Diagnostic {
+        let location = self
+            .files
+            .iter()
+            .find_map(|file| Location::find(file.path.as_deref(), &file.text, fragment));
+        Diagnostic {
+            message: message.into(),
+            location,
+        }
+    }
+
+    /// Parse all files and merge them into a single schema.
+    ///
+    /// Included files contribute their declarations only, their file
+    /// identifier and file extension are ignored like they are by flatc.
+    /// Every file starts out in the root namespace. The root type is chosen
+    /// as described by [`parse_with_root`](Self::parse_with_root).
+    pub fn parse(&self) -> Result<Schema<'_>> {
+        self.parse_with_root(None)
+    }
+
+    /// Parse all files and merge them into a single schema, whose root type
+    /// is `root_type`, a name like `my.ns.Monster`, if one is given.
+    ///
+    /// Otherwise the root type is the one declared by the root file or, if it
+    /// doesn't declare one, the one declared by the files it includes. It's
+    /// an error for the root file, or the included files, to declare
+    /// different root types, which can be resolved by giving `root_type`.
+    /// The root types declared by files are ignored if it is given.
+    pub fn parse_with_root<'a>(&'a self, root_type: Option<&'a str>) -> Result<Schema<'a>> {
+        let schemas = self
+            .files
+            .iter()
+            .map(SchemaFile::parse)
+            .collect::<Result<Vec<_>>>()?;
+        let root = match root_type {
+            Some(_) => None,
+            None => self.declared_root(&schemas)?,
+        };
+
+        let last = schemas.len() - 1;
+        let mut elements = vec![];
+        for (i, schema) in schemas.into_iter().enumerate() {
+            elements.push(root_namespace());
+            elements.extend(
+                schema
+                    .elements
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(j, element)| match element {
+                        Element::Root(_) => root == Some((i, *j)),
+                        _ => i == last || !is_file_attribute(element),
+                    })
+                    .map(|(_, element)| element),
+            );
+        }
+        if let Some(root_type) = root_type {
+            let mut parts = DottedIdent::from(root_type).parts;
+            let typename = parts.pop().expect("empty root type");
+            elements.push(Element::from(
+                Namespace::builder().ident(DottedIdent::from(parts)).build(),
+            ));
+            elements.push(Element::from(Root::builder().typename(typename).build()));
+        }
+        Ok(Schema::builder().elements(elements).build())
+    }
+
+    /// The root type declared by the files, given as the index of the file
+    /// and of the element declaring it.
+    fn declared_root(&self, schemas: &[Schema<'_>]) -> Result<Option<(usize, usize)>> {
+        let last = schemas.len() - 1;
+        // The root types declared by the root file, and by the included files,
+        // along with their namespace.
+        let mut own = vec![];
+        let mut included = vec![];
+        for (i, schema) in schemas.iter().enumerate() {
+            let mut namespace = vec![];
+            for (j, element) in schema.elements.iter().enumerate() {
+                match element {
+                    Element::Namespace(ns) => {
+                        namespace = ns.ident.parts.iter().map(|part| part.raw).collect();
+                    }
+                    Element::Root(Root { typename, .. }) => {
+                        let mut name = namespace.clone();
+                        name.push(typename.raw);
+                        let roots = if i == last { &mut own } else { &mut included };
+                        roots.push(((i, j), name.join("."), typename.raw));
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let (roots, context) = if own.is_empty() {
+            (included, "included files declare")
+        } else {
+            (own, "the schema declares")
+        };
+        if let Some((_, name, _)) = roots.first() {
+            if let Some((_, other, at)) = roots.iter().find(|(_, other, _)| other != name) {
+                let message = format!(
+                    "{} conflicting root types `{}` and `{}`, choose one with `Config::root_type`",
+                    context, name, other
+                );
+                return Err(anyhow::Error::new(self.diagnostic(message, at)));
+            }
+        }
+        Ok(roots.last().map(|(index, _, _)| *index))
+    }
+}
+
+impl SchemaFile {
+    fn parse(&self) -> Result<Schema<'_>> {
+        parse_schema(&self.text).map_err(|error| {
+            anyhow::Error::new(Diagnostic {
+                message: error.message,
+                location: Location::find(self.path.as_deref(), &self.text, error.at),
+            })
+        })
+    }
+}
+
+/// Depth-first traversal of the include graph.
+struct Loader<'a> {
+    include_dirs: &'a [PathBuf],
+    files: Vec<SchemaFile>,
+    visited: HashSet<PathBuf>,
+    /// The files currently being visited, used to detect cycles.
+    stack: Vec<PathBuf>,
+}
+
+impl Loader<'_> {
+    fn visit(&mut self, file: SchemaFile) -> Result<()> {
+        let includes = file
+            .parse()?
+            .includes
+            .iter()
+            .map(|include| self.find(include.path, file.path.as_deref()))
+            .collect::<Result<Vec<_>>>()?;
+
+        if let Some(path) = &file.path {
+            self.stack.push(path.clone());
+        }
+        for include in includes {
+            if let Some(start) = self.stack.iter().position(|path| *path == include) {
+                let cycle = self.stack[start..]
+                    .iter()
+                    .chain(std::iter::once(&include))
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<_>>();
+                return Err(anyhow!("include cycle: {}", cycle.join(" -> ")));
+            }
+            if self.visited.insert(include.clone()) {
+                let text = std::fs::read_to_string(&include)
+                    .map_err(|e| anyhow!("cannot read {}: {}", include.display(), e))?;
+                self.visit(SchemaFile {
+                    path: Some(include),
+                    text,
+                })?;
+            }
+        }
+        if let Some(path) = &file.path {
+            self.stack.pop();
+            self.visited.insert(path.clone());
+        }
+        self.files.push(file);
+        Ok(())
+    }
+
+    /// Find the file included as `include` from the file at `from`.
+    fn find(&self, include: &Path, from: Option<&Path>) -> Result<PathBuf> {
+        from.and_then(Path::parent)
+            .into_iter()
+            .chain(self.include_dirs.iter().map(PathBuf::as_path))
+            .map(|dir| dir.join(include))
+            .find(|candidate| candidate.is_file())
+            .map(|path| canonical_path(&path))
+            .unwrap_or_else(|| Err(anyhow!("cannot find included file {}", include.display())))
+    }
+}
+
+fn canonical_path(path: &Path) -> Result<PathBuf> {
+    path.canonicalize()
+        .map_err(|e| anyhow!("cannot resolve {}: {}", path.display(), e))
+}
+
+fn root_namespace<'a>() -> Element<'a> {
+    Element::from(
+        Namespace::builder()
+            .ident(DottedIdent::from(vec![]))
+            .build(),
+    )
+}
+
+/// Check whether `element` only applies to the file it is declared in.
+fn is_file_attribute(element: &Element) -> bool {
+    matches!(
+        element,
+        Element::Root(_) | Element::FileIdentifier(_) | Element::FileExtension(_)
+    )
+}
+
+#[cfg(test)]
+mod include_tests {
+    use super::*;
+
+    /// Write `files` to a fresh directory and return its path.
+    fn write_files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("butte-include-{}-{}", name, std::process::id()));
+        for (path, text) in files {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, text).unwrap();
+        }
+        dir
+    }
+
+    fn table_names(schema: &Schema) -> Vec<String> {
+        schema
+            .elements
+            .iter()
+            .filter_map(|element| match element {
+                Element::Table(t) => Some(t.id.raw.to_string()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_includes_come_first() {
+        let dir = write_files(
+            "order",
+            &[
+                (
+                    "root.fbs",
+                    "include \"a.fbs\";\ninclude \"b.fbs\";\ntable Root { x: int; }",
+                ),
+                (
+                    "a.fbs",
+                    "include \"b.fbs\";\ntable A { x: int; }\nroot_type A;",
+                ),
+                ("b.fbs", "table B { x: int; }"),
+            ],
+        );
+        let files = SchemaFiles::load(dir.join("root.fbs"), &[]).unwrap();
+        assert_eq!(files.paths().count(), 3);
+        let schema = files.parse().unwrap();
+        assert_eq!(table_names(&schema), ["B", "A", "Root"]);
+    }
+
+    fn root_types(schema: &Schema) -> Vec<String> {
+        schema
+            .elements
+            .iter()
+            .filter_map(|element| match element {
+                Element::Root(Root { typename, .. }) => Some(typename.raw.to_string()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_root_types() {
+        let dir = write_files(
+            "roots",
+            &[
+                (
+                    "root.fbs",
+                    "include \"a.fbs\";\ntable Root { x: int; }\nroot_type Root;",
+                ),
+                (
+                    "a.fbs",
+                    "include \"b.fbs\";\nnamespace ns;\ntable A { x: int; }\nroot_type A;\nfile_identifier \"AAAA\";",
+                ),
+                ("b.fbs", "namespace ns;\ntable B { x: int; }\nroot_type A;"),
+                ("c.fbs", "include \"a.fbs\";\ntable C { x: int; }"),
+                (
+                    "d.fbs",
+                    "include \"c.fbs\";\ninclude \"g.fbs\";\ntable D { x: int; }",
+                ),
+                ("g.fbs", "namespace ns;\ntable G { x: int; }\nroot_type G;"),
+            ],
+        );
+
+        // the root file's root type wins over those of the files it includes
+        let files = SchemaFiles::load(dir.join("root.fbs"), &[]).unwrap();
+        let schema = files.parse().unwrap();
+        assert_eq!(root_types(&schema), ["Root"]);
+        assert!(!schema
+            .elements
+            .iter()
+            .any(|element| matches!(element, Element::FileIdentifier(_))));
+
+        // without one, the root type declared by the included files is used
+        let files = SchemaFiles::load(dir.join("c.fbs"), &[]).unwrap();
+        assert_eq!(root_types(&files.parse().unwrap()), ["A"]);
+
+        // and one given explicitly overrides them
+        let schema = files.parse_with_root(Some("ns.B")).unwrap();
+        assert_eq!(root_types(&schema), ["B"]);
+        assert_eq!(
+            schema.elements[schema.elements.len() - 2],
+            Element::from(Namespace::builder().ident(DottedIdent::from("ns")).build())
+        );
+        assert!(crate::validate::validate(&schema).is_empty());
+
+        // the included files must agree on their root type, which is
+        // compared along with its namespace
+        let error = SchemaFiles::load(dir.join("d.fbs"), &[])
+            .unwrap()
+            .parse()
+            .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("included files declare conflicting root types `ns.A` and `ns.G`"),
+            "{}",
+            error
+        );
+
+        let files = SchemaFiles::from_text(
+            "table A { x: int; }\ntable B { x: int; }\nroot_type A;\nroot_type B;".to_string(),
+            None,
+            &[],
+        )
+        .unwrap();
+        let error = files.parse().unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("the schema declares conflicting root types `A` and `B`"));
+        assert_eq!(
+            root_types(&files.parse_with_root(Some("B")).unwrap()),
+            ["B"]
+        );
+    }
+
+    #[test]
+    fn test_diamond_includes() {
+        let dir = write_files(
+            "diamond",
+            &[
+                (
+                    "a.fbs",
+                    "include \"b.fbs\";\ninclude \"sub/c.fbs\";\ntable A { b: B; c: C; d: ns.D; }",
+                ),
+                ("b.fbs", "include \"d.fbs\";\ntable B { d: ns.D; }"),
+                // the same file, by another path
+                ("sub/c.fbs", "include \"../d.fbs\";\ntable C { d: ns.D; }"),
+                ("d.fbs", "namespace ns;\ntable D { x: int; }"),
+            ],
+        );
+        let files = SchemaFiles::load(dir.join("a.fbs"), &[]).unwrap();
+        assert_eq!(files.paths().count(), 4);
+        let schema = files.parse().unwrap();
+        assert_eq!(table_names(&schema), ["D", "B", "C", "A"]);
+        assert!(crate::validate::validate(&schema).is_empty());
+    }
+
+    #[test]
+    fn test_include_dirs() {
+        let dir = write_files(
+            "dirs",
+            &[
+                (
+                    "schemas/root.fbs",
+                    "include \"common/a.fbs\";\ntable Root { x: int; }",
+                ),
+                ("shared/common/a.fbs", "table A { x: int; }"),
+            ],
+        );
+        let missing = SchemaFiles::load(dir.join("schemas/root.fbs"), &[]);
+        assert!(missing.is_err());
+
+        let files = SchemaFiles::load(dir.join("schemas/root.fbs"), &[dir.join("shared")]).unwrap();
+        assert_eq!(table_names(&files.parse().unwrap()), ["A", "Root"]);
+    }
+
+    #[test]
+    fn test_diagnostic_location() {
+        let dir = write_files(
+            "diagnostic",
+            &[
+                ("root.fbs", "include \"a.fbs\";\ntable Root { x: int; }"),
+                ("a.fbs", "table A {\n  x: Colr;\n}"),
+            ],
+        );
+        let files = SchemaFiles::load(dir.join("root.fbs"), &[]).unwrap();
+        let schema = files.parse().unwrap();
+        let error = &crate::validate::validate(&schema)[0];
+        let location = files.diagnostic(&error.message, error.at).location.unwrap();
+        assert_eq!(
+            location.path,
+            Some(dir.join("a.fbs").canonicalize().unwrap())
+        );
+        assert_eq!((location.line, location.column), (2, 6));
+    }
+
+    #[test]
+    fn test_parse_error_location() {
+        let dir = write_files(
+            "syntax",
+            &[
+                ("root.fbs", "include \"a.fbs\";\ntable Root { x: int; }"),
+                ("a.fbs", "table A {\n  x: int;\n  y int;\n}"),
+            ],
+        );
+        let error = SchemaFiles::load(dir.join("root.fbs"), &[]).unwrap_err();
+        let diagnostic = error.downcast_ref::<Diagnostic>().unwrap();
+        assert_eq!(diagnostic.message, "invalid table declaration");
+        let location = diagnostic.location.as_ref().unwrap();
+        assert_eq!(
+            location.path,
+            Some(dir.join("a.fbs").canonicalize().unwrap())
+        );
+        assert_eq!((location.line, location.column), (3, 3));
+    }
+
+    #[test]
+    fn test_include_cycle() {
+        let dir = write_files(
+            "cycle",
+            &[
+                ("a.fbs", "include \"b.fbs\";\ntable A { x: int; }"),
+                ("b.fbs", "include \"a.fbs\";\ntable B { x: int; }"),
+            ],
+        );
+        let error = SchemaFiles::load(dir.join("a.fbs"), &[]).unwrap_err();
+        assert!(error.to_string().starts_with("include cycle: "));
+    }
+}
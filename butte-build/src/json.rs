@@ -0,0 +1,395 @@
+//! Descriptions of schemas for converting buffers to and from JSON at runtime
+//! with `butte::json`.
+use std::collections::HashMap;
+
+use anyhow::Result;
+use butte::json;
+
+use crate::{
+    codegen::{enum_values, field_slots, union_discriminants, Declaration, SymbolTable},
+    compile::checked_schema,
+    include::SchemaFiles,
+    types::*,
+};
+
+/// Describe the types of a schema and all of the files it includes, after
+/// checking that the schema is valid.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> anyhow::Result<()> {
+/// let files = butte_build::include::SchemaFiles::load("monster.fbs", &[])?;
+/// let schema = butte_build::json_schema(&files)?;
+/// let buf = std::fs::read("monster.bin")?;
+/// print!("{}", butte::json::to_json(&schema, &buf)?);
+/// # Ok(())
+/// # }
+/// ```
+pub fn json_schema(files: &SchemaFiles) -> Result<json::Schema> {
+    Ok(describe(&checked_schema(files, None)?.elements))
+}
+
+/// Describe the types of a schema that has already been checked.
+pub(crate) fn describe(elements: &[Element]) -> json::Schema {
+    Describer::new(elements).describe(elements)
+}
+
+/// Builds the description of a schema.
+struct Describer<'a> {
+    symbols: SymbolTable<'a>,
+    /// The index of every table, struct, enum and union in its list of the
+    /// description, by fully qualified name.
+    indices: HashMap<Vec<&'a str>, usize>,
+}
+
+impl<'a> Describer<'a> {
+    fn new(elements: &[Element<'a>]) -> Self {
+        let mut indices = HashMap::new();
+        let (mut tables, mut structs, mut enums) = (0, 0, 0);
+        for (namespace, element) in with_namespaces(elements) {
+            let (id, counter) = match element {
+                Element::Table(t) => (t.id, &mut tables),
+                Element::Struct(s) => (s.id, &mut structs),
+                Element::Enum(e) => (e.id, &mut enums),
+                Element::Union(u) => (u.id, &mut enums),
+                _ => continue,
+            };
+            let mut name = namespace;
+            name.push(id.raw);
+            indices.insert(name, *counter);
+            *counter += 1;
+        }
+        Self {
+            symbols: SymbolTable::new(elements),
+            indices,
+        }
+    }
+
+    fn describe(&self, elements: &[Element<'a>]) -> json::Schema {
+        let mut schema = json::Schema::default();
+        for (namespace, element) in with_namespaces(elements) {
+            match element {
+                Element::Table(t) => schema.tables.push(self.table(&namespace, t)),
+                Element::Struct(s) => schema.structs.push(self.struct_(&namespace, s)),
+                Element::Enum(e) => schema.enums.push(self.enum_(&namespace, e)),
+                Element::Union(u) => schema.enums.push(self.union(&namespace, u)),
+                Element::Root(Root { typename, .. }) => {
+                    let ty = Type::Ident(DottedIdent::from(vec![*typename]));
+                    if let json::Type::Table(index) = self.ty(&namespace, &ty) {
+                        schema.root_table = Some(index);
+                    }
+                }
+                Element::FileIdentifier(FileIdentifier { id, .. }) => {
+                    schema.file_identifier = Some(id.iter().collect());
+                }
+                _ => {}
+            }
+        }
+        schema
+    }
+
+    fn resolve(&self, namespace: &[&'a str], id: &DottedIdent<'a>) -> (usize, &Declaration<'a>) {
+        let (name, declaration) = self
+            .symbols
+            .resolve(namespace, id)
+            .unwrap_or_else(|| panic!("unknown type {:?}", id));
+        (self.indices[name], declaration)
+    }
+
+    fn ty(&self, namespace: &[&'a str], ty: &Type<'a>) -> json::Type {
+        match ty {
+            Type::Bool => json::Type::Bool,
+            Type::Byte | Type::Int8 => json::Type::Byte,
+            Type::UByte | Type::UInt8 => json::Type::UByte,
+            Type::Short | Type::Int16 => json::Type::Short,
+            Type::UShort | Type::UInt16 => json::Type::UShort,
+            Type::Int | Type::Int32 => json::Type::Int,
+            Type::UInt | Type::UInt32 => json::Type::UInt,
+            Type::Long | Type::Int64 => json::Type::Long,
+            Type::ULong | Type::UInt64 => json::Type::ULong,
+            Type::Float | Type::Float32 => json::Type::Float,
+            Type::Double | Type::Float64 => json::Type::Double,
+            Type::String => json::Type::String,
+            Type::Array(element) => json::Type::Vector(Box::new(self.ty(namespace, element))),
+            Type::FixedArray(element, len) => {
+                json::Type::Array(Box::new(self.ty(namespace, element)), *len as usize)
+            }
+            Type::Ident(id) => match self.resolve(namespace, id) {
+                (index, Declaration::Table) => json::Type::Table(index),
+                (index, Declaration::Struct(_)) => json::Type::Struct(index),
+                (index, Declaration::Enum(_)) => json::Type::Enum(index),
+                (index, Declaration::Union(_)) => json::Type::Union(index),
+            },
+        }
+    }
+
+    fn table(&self, namespace: &[&'a str], table: &Table<'a>) -> json::TableDef {
+        let mut fields = vec![];
+        let slots = field_slots(&table.fields, |ty| match self.ty(namespace, ty) {
+            json::Type::Union(_) => true,
+            json::Type::Vector(element) => matches!(*element, json::Type::Union(_)),
+            _ => false,
+        });
+        for (field, slot) in table.fields.iter().zip(slots) {
+            let ty = match self.ty(namespace, &field.ty) {
+                json::Type::Vector(element) if field.is_vector64() => json::Type::Vector64(element),
+                ty => ty,
+            };
+            let mut def = json::FieldDef {
+                name: field.id.raw.to_string(),
+                ty: ty.clone(),
+                id: slot,
+                default_integer: 0,
+                default_real: 0.0,
+                optional: false,
+                required: field.is_required(),
+                deprecated: field.is_deprecated(),
+                offset64: field.is_offset64(),
+            };
+            // The discriminant of a union takes up the slot before its value,
+            // and the discriminants of a vector of unions are a vector too.
+            let type_ty = match &ty {
+                json::Type::Union(index) => Some(json::Type::Enum(*index)),
+                json::Type::Vector(element) => match element.as_ref() {
+                    json::Type::Union(index) => {
+                        Some(json::Type::Vector(Box::new(json::Type::Enum(*index))))
+                    }
+                    _ => None,
+                },
+                _ => None,
+            };
+            if let Some(type_ty) = type_ty {
+                let required = def.required && matches!(ty, json::Type::Vector(_));
+                fields.push(json::FieldDef {
+                    name: format!("{}_type", field.id.raw),
+                    ty: type_ty,
+                    id: slot - 1,
+                    required,
+                    ..def.clone()
+                });
+            }
+            match &field.default {
+                Some(DefaultValue::Scalar(Scalar::Integer(value))) => {
+                    // A `ulong` above `i64::MAX` keeps its bits, which is how
+                    // it is read back.
+                    def.default_integer = *value as i64;
+                    def.default_real = *value as f64;
+                }
+                Some(DefaultValue::Scalar(Scalar::Float(value))) => def.default_real = *value,
+                Some(DefaultValue::Scalar(Scalar::Boolean(value))) => {
+                    def.default_integer = i64::from(*value)
+                }
+                Some(DefaultValue::EnumVal(variant)) => {
+                    if let Type::Ident(id) = &field.ty {
+                        if let (_, Declaration::Enum(e)) = self.resolve(namespace, id) {
+                            def.default_integer = enum_values(e)
+                                .into_iter()
+                                .find(|(id, _)| id == variant)
+                                .map_or(0, |(_, value)| value as i64);
+                        }
+                    }
+                }
+                Some(DefaultValue::Null) => def.optional = true,
+                None => {}
+            }
+            fields.push(def);
+        }
+        json::TableDef {
+            name: qualified_name(namespace, table.id),
+            fields,
+        }
+    }
+
+    fn struct_(&self, namespace: &[&'a str], s: &Struct<'a>) -> json::StructDef {
+        let layout = self.symbols.struct_layout(namespace, s);
+        json::StructDef {
+            name: qualified_name(namespace, s.id),
+            fields: s
+                .fields
+                .iter()
+                .zip(&layout.offsets)
+                .map(|(field, offset)| json::StructFieldDef {
+                    name: field.id.raw.to_string(),
+                    ty: self.ty(namespace, &field.ty),
+                    offset: *offset,
+                })
+                .collect(),
+            size: layout.size,
+            align: layout.align,
+        }
+    }
+
+    fn enum_(&self, namespace: &[&'a str], e: &Enum<'a>) -> json::EnumDef {
+        json::EnumDef {
+            name: qualified_name(namespace, e.id),
+            underlying_type: self.ty(namespace, &e.base_type),
+            values: enum_values(e)
+                .into_iter()
+                .map(|(id, value)| json::EnumValDef {
+                    name: id.raw.to_string(),
+                    value: value as i64,
+                    union_table: None,
+                })
+                .collect(),
+        }
+    }
+
+    fn union(&self, namespace: &[&'a str], u: &Union<'a>) -> json::EnumDef {
+        let none = json::EnumValDef {
+            name: "NONE".to_string(),
+            value: 0,
+            union_table: None,
+        };
+        let values = union_discriminants(&u.values)
+            .into_iter()
+            .zip(&u.values)
+            .map(|((id, value), union_value)| {
+                let (index, _) = self.resolve(namespace, &union_value.union_type());
+                json::EnumValDef {
+                    name: id.raw.to_string(),
+                    value: value as i64,
+                    union_table: Some(index),
+                }
+            });
+        json::EnumDef {
+            name: qualified_name(namespace, u.id),
+            underlying_type: json::Type::UByte,
+            values: std::iter::once(none).chain(values).collect(),
+        }
+    }
+}
+
+/// Pair every element with the namespace it is declared in.
+fn with_namespaces<'a, 'b>(
+    elements: &'b [Element<'a>],
+) -> impl Iterator<Item = (Vec<&'a str>, &'b Element<'a>)> {
+    let mut namespace = vec![];
+    elements.iter().map(move |element| {
+        if let Element::Namespace(Namespace { ident, .. }) = element {
+            namespace = ident.parts.iter().map(|part| part.raw).collect();
+        }
+        (namespace.clone(), element)
+    })
+}
+
+fn qualified_name(namespace: &[&str], id: Ident) -> String {
+    namespace
+        .iter()
+        .copied()
+        .chain(std::iter::once(id.raw))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+#[cfg(test)]
+mod json_tests {
+    use super::*;
+    use crate::parser::parse_schema;
+
+    fn describe_schema(input: &str) -> json::Schema {
+        describe(&parse_schema(input).unwrap().elements)
+    }
+
+    #[test]
+    fn test_describe_tables() {
+        let schema = describe_schema(
+            "namespace a.b;
+             enum Color: short { Red, Green, Blue = 4 }
+             table Cat { lives: int = 9; color: Color = Green; }
+             union Animal { Cat }
+             table Pet { name: string (required); animal: Animal; tags: [string]; friends: [Animal]; }
+             root_type Pet;
+             file_identifier \"PETS\";",
+        );
+        assert_eq!(schema.root_table, Some(1));
+        assert_eq!(schema.file_identifier.as_deref(), Some("PETS"));
+        assert_eq!(schema.enums[0].name, "a.b.Color");
+        assert_eq!(schema.enums[0].underlying_type, json::Type::Short);
+        assert_eq!(schema.enums[0].values[2].value, 4);
+        assert_eq!(schema.enums[1].values[1].union_table, Some(0));
+
+        let cat = &schema.tables[0];
+        assert_eq!(cat.fields[0].default_integer, 9);
+        assert_eq!(cat.fields[1].default_integer, 1);
+
+        let pet = &schema.tables[1];
+        let fields = pet
+            .fields
+            .iter()
+            .map(|field| (field.name.as_str(), field.id, field.ty.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            [
+                ("name", 0, json::Type::String),
+                ("animal_type", 1, json::Type::Enum(1)),
+                ("animal", 2, json::Type::Union(1)),
+                ("tags", 3, json::Type::Vector(Box::new(json::Type::String))),
+                (
+                    "friends_type",
+                    4,
+                    json::Type::Vector(Box::new(json::Type::Enum(1)))
+                ),
+                (
+                    "friends",
+                    5,
+                    json::Type::Vector(Box::new(json::Type::Union(1)))
+                ),
+            ]
+        );
+        assert!(pet.fields[0].required);
+    }
+
+    #[test]
+    fn test_describe_field_ids() {
+        let schema = describe_schema(
+            "table Cat { lives: int; }
+             union Animal { Cat }
+             table Pet { tags: [string] (id: 0); animal: Animal (id: 3); name: string (id: 1); }",
+        );
+        let ids = schema.tables[1]
+            .fields
+            .iter()
+            .map(|field| (field.name.as_str(), field.id))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            [("tags", 0), ("animal_type", 2), ("animal", 3), ("name", 1)]
+        );
+    }
+
+    #[test]
+    fn test_describe_structs() {
+        let schema = describe_schema(
+            "namespace geo;
+             struct Vec2 { x: float; y: float; }
+             struct Ray { origin: Vec2; length: double; flag: bool; }",
+        );
+        let ray = &schema.structs[1];
+        assert_eq!(ray.name, "geo.Ray");
+        assert_eq!((ray.size, ray.align), (24, 8));
+        let offsets = ray
+            .fields
+            .iter()
+            .map(|field| field.offset)
+            .collect::<Vec<_>>();
+        assert_eq!(offsets, [0, 8, 16]);
+        assert_eq!(ray.fields[0].ty, json::Type::Struct(0));
+    }
+
+    #[test]
+    fn test_describe_fixed_arrays() {
+        let schema = describe_schema(
+            "struct Vec2 { x: float; y: float; }
+             struct Path { flags: [ubyte:3]; points: [Vec2:2]; }",
+        );
+        let path = &schema.structs[1];
+        assert_eq!((path.size, path.align), (20, 4));
+        assert_eq!(path.fields[1].offset, 4);
+        assert_eq!(
+            path.fields[1].ty,
+            json::Type::Array(Box::new(json::Type::Struct(0)), 2)
+        );
+    }
+}
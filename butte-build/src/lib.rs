@@ -1,5 +1,10 @@
 pub mod codegen;
 mod compile;
+pub mod diagnostic;
+pub mod flatc;
+pub mod format;
+pub mod include;
+mod json;
 
 mod macros;
 
@@ -7,6 +12,12 @@ mod macros;
 mod parser_macros;
 
 pub mod parser;
+mod reflection;
 pub mod types;
+pub mod validate;
 
-pub use crate::compile::{compile_fbs, compile_fbs_generic};
+pub use crate::compile::{
+    compile_fbs, compile_fbs_file, compile_fbs_generic, compile_fbs_with_include_dirs,
+    generate_code, Config, Format,
+};
+pub use crate::{json::json_schema, reflection::reflection_schema};
@@ -1,5 +1,9 @@
 /// Include generated flatbuffer code.
 ///
+/// The code starts with the items of the schema's root namespace, so it is
+/// usually included in a module of its own. Code generated with
+/// `Config::module` is in a module already, and can be included anywhere.
+///
 /// # Examples
 ///
 /// ```compile_fail
@@ -14,3 +18,21 @@ macro_rules! include_fbs {
         include!(concat!(env!("OUT_DIR"), "/", $package, ".rs"));
     };
 }
+
+/// Include flatbuffer code generated with a file per namespace, see
+/// `Config::split_namespaces`.
+///
+/// # Examples
+///
+/// ```compile_fail
+/// # // This doesn't compile, because OUT_DIR isn't set
+/// mod greeter {
+///     butte_build::include_generated!("greeter");
+/// }
+/// ```
+#[macro_export]
+macro_rules! include_generated {
+    ($package:tt) => {
+        include!(concat!(env!("OUT_DIR"), "/", $package, "/mod.rs"));
+    };
+}
@@ -1,42 +1,261 @@
 /// Compile flatbuffers files from the command line.
-use anyhow;
-use butte_build::compile_fbs_generic;
+use std::{
+    io::{Read, Write},
+    path::PathBuf,
+};
+
+use butte_build::{
+    codegen::Naming, compile_fbs_file, compile_fbs_generic, format::format_schema,
+    include::SchemaFiles, reflection_schema, Config, Format,
+};
 
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
 #[structopt(
     name = "butte",
-    about = "Generate Rust code from a flatbuffer schema file."
+    about = "Generate Rust code from flatbuffer schema files."
 )]
-struct Opt {
+enum Opt {
+    /// Generate a `<module>.rs` file for every schema, like a build script
+    /// using `butte_build::Config` does
+    Compile(Compile),
+
+    /// Generate Rust code for a single schema, from a file or stdin to a file
+    /// or stdout
+    Generate(Generate),
+
+    /// Format schema files canonically, in place, or stdin to stdout
+    Fmt(Fmt),
+}
+
+#[derive(Debug, StructOpt)]
+struct Compile {
+    /// Schema files, each generating a module named after the file stem
+    #[structopt(required = true, parse(from_os_str))]
+    inputs: Vec<PathBuf>,
+
+    /// Directory to write generated code to
+    #[structopt(short, long = "out", default_value = ".", parse(from_os_str))]
+    out_dir: PathBuf,
+
+    /// Directories to search for included files
+    #[structopt(
+        short = "I",
+        long = "include",
+        number_of_values = 1,
+        parse(from_os_str)
+    )]
+    include_dirs: Vec<PathBuf>,
+
+    /// Don't format generated code
+    #[structopt(short, long)]
+    ugly: bool,
+
+    /// Format generated code with an external `rustfmt` instead of in process
+    #[structopt(long, conflicts_with = "ugly")]
+    rustfmt: bool,
+
+    /// Extra traits to derive for generated structs and enums, e.g. `Hash`
+    #[structopt(short, long = "derive", number_of_values = 1)]
+    derives: Vec<String>,
+
+    /// Generate owned object types with `pack` and `unpack` methods
+    #[structopt(long)]
+    object_api: bool,
+
+    /// Extra traits to derive for object types, e.g. `Eq`
+    #[structopt(long = "object-derive", number_of_values = 1)]
+    object_derives: Vec<String>,
+
+    /// Generate a round-trip test for every table
+    #[structopt(long)]
+    round_trip_tests: bool,
+
+    /// Name types in PascalCase and fields in snake_case, whatever their case in the schema
+    #[structopt(long)]
+    idiomatic_names: bool,
+
+    /// The root type of every schema, e.g. `my.ns.Monster`, overriding the ones they declare
+    #[structopt(long)]
+    root_type: Option<String>,
+
+    /// The visibility of generated items, `pub`, `pub(crate)` or `pub(in crate::path)`
+    #[structopt(long)]
+    visibility: Option<String>,
+
+    /// Generate a `prelude` module re-exporting the types reachable from the root type
+    #[structopt(long)]
+    prelude: bool,
+
+    /// The module to put all of the generated code in
+    #[structopt(long)]
+    module: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+struct Generate {
     /// Input file, stdin if not present
     #[structopt(parse(from_os_str))]
-    input: Option<std::path::PathBuf>,
+    input: Option<PathBuf>,
 
     /// Output file, stdout if not present
     #[structopt(parse(from_os_str))]
-    output: Option<std::path::PathBuf>,
+    output: Option<PathBuf>,
 
-    /// Format generated code using `rustfmt`.
+    /// Don't format generated code
     #[structopt(short, long)]
     ugly: bool,
+
+    /// Format generated code with an external `rustfmt` instead of in process
+    #[structopt(long, conflicts_with = "ugly")]
+    rustfmt: bool,
+
+    /// Directories to search for included files
+    #[structopt(
+        short = "I",
+        long = "include",
+        number_of_values = 1,
+        parse(from_os_str)
+    )]
+    include_dirs: Vec<PathBuf>,
+
+    /// Write a binary schema (`.bfbs`) instead of Rust code
+    #[structopt(short, long)]
+    binary_schema: bool,
 }
 
-fn main() -> anyhow::Result<()> {
-    let opt = Opt::from_args();
+#[derive(Debug, StructOpt)]
+struct Fmt {
+    /// Schema files to format, stdin if not present
+    #[structopt(parse(from_os_str))]
+    inputs: Vec<PathBuf>,
 
-    let input: Box<dyn std::io::Read> = if let Some(input) = opt.input {
-        Box::new(std::fs::File::open(input)?)
+    /// Don't write the files, fail if any of them isn't formatted
+    #[structopt(long)]
+    check: bool,
+}
+
+/// The format selected by the `--ugly` and `--rustfmt` flags.
+fn format(ugly: bool, rustfmt: bool) -> Format {
+    if ugly {
+        Format::Ugly
+    } else if rustfmt {
+        Format::Rustfmt
     } else {
-        Box::new(std::io::stdin())
-    };
+        Format::Pretty
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    match Opt::from_args() {
+        Opt::Compile(opt) => compile(opt),
+        Opt::Generate(opt) => generate(opt),
+        Opt::Fmt(opt) => fmt(opt),
+    }
+}
+
+fn compile(opt: Compile) -> anyhow::Result<()> {
+    std::fs::create_dir_all(&opt.out_dir)?;
+    let mut config = Config::new();
+    config
+        .out_dir(&opt.out_dir)
+        .format(format(opt.ugly, opt.rustfmt))
+        .object_api(opt.object_api)
+        .round_trip_tests(opt.round_trip_tests)
+        .prelude(opt.prelude)
+        .naming(if opt.idiomatic_names {
+            Naming::Idiomatic
+        } else {
+            Naming::Schema
+        });
+    for input in &opt.inputs {
+        config.input(input);
+    }
+    for dir in &opt.include_dirs {
+        config.include_dir(dir);
+    }
+    for path in opt.derives {
+        config.derive(path);
+    }
+    for path in opt.object_derives {
+        config.object_derive(path);
+    }
+    if let Some(root_type) = opt.root_type {
+        config.root_type(root_type);
+    }
+    if let Some(visibility) = opt.visibility {
+        config.visibility(visibility);
+    }
+    if let Some(module) = opt.module {
+        config.module(module);
+    }
+    config.compile()
+}
 
+fn generate(opt: Generate) -> anyhow::Result<()> {
     let output: Box<dyn std::io::Write> = if let Some(output) = opt.output {
         Box::new(std::fs::File::create(output)?)
     } else {
         Box::new(std::io::stdout())
     };
 
-    compile_fbs_generic(opt.ugly, input, output)
+    if opt.binary_schema {
+        let files = if let Some(input) = opt.input {
+            SchemaFiles::load(input, &opt.include_dirs)?
+        } else {
+            let mut schema_text = String::new();
+            std::io::stdin().read_to_string(&mut schema_text)?;
+            SchemaFiles::from_text(schema_text, None, &opt.include_dirs)?
+        };
+        let mut output = output;
+        output.write_all(&reflection_schema(&files)?)?;
+        return Ok(());
+    }
+
+    let format = format(opt.ugly, opt.rustfmt);
+    if let Some(input) = opt.input {
+        compile_fbs_file(format, &opt.include_dirs, input, output)
+    } else {
+        compile_fbs_generic(
+            format,
+            &opt.include_dirs,
+            Box::new(std::io::stdin()),
+            output,
+        )
+    }
+}
+
+fn fmt(opt: Fmt) -> anyhow::Result<()> {
+    if opt.inputs.is_empty() {
+        let mut text = String::new();
+        std::io::stdin().read_to_string(&mut text)?;
+        let formatted = format_schema(&text, None)?;
+        if opt.check && formatted != text {
+            anyhow::bail!("<stdin> is not formatted");
+        }
+        if !opt.check {
+            std::io::stdout().write_all(formatted.as_bytes())?;
+        }
+        return Ok(());
+    }
+
+    let mut unformatted = vec![];
+    for path in &opt.inputs {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("cannot read {}: {}", path.display(), e))?;
+        let formatted = format_schema(&text, Some(path))?;
+        if formatted == text {
+            continue;
+        }
+        if opt.check {
+            unformatted.push(path.display().to_string());
+        } else {
+            std::fs::write(path, formatted)?;
+        }
+    }
+    if !unformatted.is_empty() {
+        anyhow::bail!("not formatted: {}", unformatted.join(", "));
+    }
+    Ok(())
 }
@@ -116,7 +116,7 @@ pub fn right_square_bracket(input: &str) -> IResult<&str, char> {
     char(']')(input)
 }
 
-pub fn ident(input: &str) -> IResult<&str, Ident> {
+pub fn ident(input: &str) -> IResult<&str, Ident<'_>> {
     map(
         recognize(preceded(
             take_while_m_n(1, 1, |c: char| c.is_alphabetic() || c == '_'),
@@ -200,7 +200,7 @@ mod string_constant_tests {
     }
 }
 
-pub fn element(input: &str) -> IResult<&str, Element> {
+pub fn element(input: &str) -> IResult<&str, Element<'_>> {
     alt((
         map(namespace_decl, Element::from),
         map(table_decl, Element::from),
@@ -258,7 +258,7 @@ pub fn comment_or_space1(input: &str) -> IResult<&str, ()> {
 }
 
 /// Parse a flatbuffer schema.
-pub fn schema_decl(input: &str) -> IResult<&str, Schema> {
+pub fn schema_decl(input: &str) -> IResult<&str, Schema<'_>> {
     map(
         tuple((
             many0(delimited(
@@ -272,10 +272,126 @@ pub fn schema_decl(input: &str) -> IResult<&str, Schema> {
     )(input)
 }
 
+/// A syntax error in a schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError<'a> {
+    pub message: String,
+
+    /// The text in the schema where parsing failed. It is a slice of the
+    /// schema text, which allows locating the error in its source.
+    pub at: &'a str,
+}
+
+/// Parse a complete flatbuffer schema.
+///
+/// Unlike `schema_decl`, this fails unless all of `input` is parsed, and
+/// reports where in `input` parsing went wrong.
+pub fn parse_schema(input: &str) -> std::result::Result<Schema<'_>, ParseError<'_>> {
+    let rest = match terminated(schema_decl, comment_or_space0)(input) {
+        Ok(("", schema)) => return Ok(schema),
+        Ok((rest, _)) => rest,
+        Err(nom::Err::Error((rest, _))) | Err(nom::Err::Failure((rest, _))) => rest,
+        Err(nom::Err::Incomplete(_)) => &input[input.len()..],
+    };
+
+    if include_decl(rest).is_ok() {
+        return Err(ParseError {
+            message: "includes must come before all other declarations".to_string(),
+            at: token(rest),
+        });
+    }
+
+    // Every declaration failed to parse at `rest`. The declaration that got
+    // the furthest before failing is most likely the one that was intended.
+    let attempts = [
+        ("namespace", error_position(namespace_decl, rest)),
+        ("table", error_position(table_decl, rest)),
+        ("struct", error_position(struct_decl, rest)),
+        ("enum", error_position(enum_decl, rest)),
+        ("union", error_position(union_decl, rest)),
+        ("root_type", error_position(root_decl, rest)),
+        ("file_extension", error_position(file_extension_decl, rest)),
+        (
+            "file_identifier",
+            error_position(file_identifier_decl, rest),
+        ),
+        ("attribute", error_position(attribute_decl, rest)),
+        ("rpc_service", error_position(rpc_decl, rest)),
+        ("object", error_position(object, rest)),
+    ];
+    let furthest = attempts
+        .iter()
+        .filter_map(|&(kind, at)| Some((kind, at?)))
+        .min_by_key(|(_, at)| at.len())
+        .filter(|(_, at)| at.len() < rest.len());
+    Err(match furthest {
+        Some((kind, at)) => ParseError {
+            message: format!("invalid {} declaration", kind),
+            at: token(at),
+        },
+        None => ParseError {
+            message: "expected a declaration".to_string(),
+            at: token(rest),
+        },
+    })
+}
+
+/// The input at which `parser` failed, if it failed.
+fn error_position<'a, O>(
+    parser: impl Fn(&'a str) -> IResult<&'a str, O>,
+    input: &'a str,
+) -> Option<&'a str> {
+    match parser(input) {
+        Err(nom::Err::Error((at, _))) | Err(nom::Err::Failure((at, _))) => Some(at),
+        _ => None,
+    }
+}
+
+/// The token at the start of `input`, used to highlight parse errors.
+fn token(input: &str) -> &str {
+    let end = input
+        .char_indices()
+        .find(|&(i, c)| c.is_whitespace() || (i > 0 && !is_ident_char(c)))
+        .map_or(input.len(), |(i, _)| i);
+    &input[..end.max(input.chars().next().map_or(0, char::len_utf8))]
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
 #[cfg(test)]
 mod schema_tests {
     use super::*;
 
+    #[test]
+    fn test_parse_schema_errors() {
+        let errors = |input| {
+            let error = parse_schema(input).unwrap_err();
+            (error.message, error.at)
+        };
+        assert_eq!(
+            errors("table A { x: int; }\ntable B {\n  y: int = ;\n}"),
+            ("invalid table declaration".to_string(), "=")
+        );
+        assert_eq!(
+            errors("enum E : byte { X, Y }\nenum F : byte { X Y }"),
+            ("invalid enum declaration".to_string(), "Y")
+        );
+        assert_eq!(
+            errors("table A { x: int; }\n}"),
+            ("expected a declaration".to_string(), "}")
+        );
+        assert_eq!(
+            errors("table A { x: int; }\ninclude \"b.fbs\";"),
+            (
+                "includes must come before all other declarations".to_string(),
+                "include"
+            )
+        );
+        assert!(parse_schema("// only a comment\n").is_ok());
+    }
+
     #[test]
     fn test_simple_include_with_comments() {
         let input = "//baz\ninclude \"a\"; // bar\n";
@@ -426,7 +542,7 @@ table Hello{
     }
 }
 
-pub fn include_decl(input: &str) -> IResult<&str, Include> {
+pub fn include_decl(input: &str) -> IResult<&str, Include<'_>> {
     map_res(
         tuple((
             doc_comment,
@@ -509,7 +625,7 @@ mod include_tests {
     }
 }
 
-pub fn dotted_ident(input: &str) -> IResult<&str, DottedIdent> {
+pub fn dotted_ident(input: &str) -> IResult<&str, DottedIdent<'_>> {
     map(
         separated_nonempty_list(
             delimited(comment_or_space0, tag("."), comment_or_space0),
@@ -519,7 +635,7 @@ pub fn dotted_ident(input: &str) -> IResult<&str, DottedIdent> {
     )(input)
 }
 
-pub fn namespace_decl(input: &str) -> IResult<&str, Namespace> {
+pub fn namespace_decl(input: &str) -> IResult<&str, Namespace<'_>> {
     map(
         tuple((
             doc_comment,
@@ -574,7 +690,7 @@ mod namespace_tests {
     }
 }
 
-pub fn attribute_decl(input: &str) -> IResult<&str, Attribute> {
+pub fn attribute_decl(input: &str) -> IResult<&str, Attribute<'_>> {
     map(
         tuple((
             doc_comment,
@@ -628,7 +744,7 @@ mod attribute_tests {
     }
 }
 
-pub fn enum_body(input: &str) -> IResult<&str, Vec<EnumVal>> {
+pub fn enum_body(input: &str) -> IResult<&str, Vec<EnumVal<'_>>> {
     delimited(
         delimited(comment_or_space0, left_brace, comment_or_space0),
         separated_nonempty_list(
@@ -639,7 +755,18 @@ pub fn enum_body(input: &str) -> IResult<&str, Vec<EnumVal>> {
     )(input)
 }
 
-pub fn enum_decl(input: &str) -> IResult<&str, Enum> {
+pub fn union_body(input: &str) -> IResult<&str, Vec<EnumVal<'_>>> {
+    delimited(
+        delimited(comment_or_space0, left_brace, comment_or_space0),
+        separated_nonempty_list(
+            delimited(comment_or_space0, comma, comment_or_space0),
+            unionval_decl,
+        ),
+        preceded(comment_or_space0, right_brace),
+    )(input)
+}
+
+pub fn enum_decl(input: &str) -> IResult<&str, Enum<'_>> {
     let parser = tuple((
         doc_comment,
         preceded(
@@ -647,7 +774,7 @@ pub fn enum_decl(input: &str) -> IResult<&str, Enum> {
             delimited(comment_or_space1, ident, comment_or_space0),
         ),
         preceded(colon, preceded(comment_or_space0, type_)),
-        metadata,
+        preceded(comment_or_space0, metadata),
         enum_body,
     ));
     map(parser, |(comment, name, base_type, metadata, values)| {
@@ -686,9 +813,41 @@ mod enum_tests {
         let expected = enum_!(MyEnum, Int32, [e_item!(foo = 1), e_item!(bar)]);
         assert_successful_parse!(result, expected);
     }
+
+    #[test]
+    fn test_enum_with_metadata() {
+        let input = "enum MyEnum : uint8 (bit_flags) { foo, bar }";
+        let result = enum_decl(input);
+        let expected = Enum::builder()
+            .id(Ident::from("MyEnum"))
+            .base_type(Type::UInt8)
+            .values(vec![e_item!(foo), e_item!(bar)])
+            .metadata(Some(Metadata::from(vec![meta!(bit_flags)])))
+            .build();
+        assert_successful_parse!(result, expected);
+    }
+
+    #[test]
+    fn test_enum_value_doc_comments() {
+        let input = "\
+enum MyEnum : int32 {
+  /// The first one.
+  /// Really.
+  foo = 1,
+  bar
+}";
+        let result = enum_decl(input);
+        let foo = EnumVal::builder()
+            .id(Ident::from("foo"))
+            .value(Some(1))
+            .doc(doc!(" The first one.\n Really."))
+            .build();
+        let expected = enum_!(MyEnum, Int32, [foo, e_item!(bar)]);
+        assert_successful_parse!(result, expected);
+    }
 }
 
-pub fn union_decl(input: &str) -> IResult<&str, Union> {
+pub fn union_decl(input: &str) -> IResult<&str, Union<'_>> {
     let parser = tuple((
         doc_comment,
         preceded(
@@ -696,7 +855,7 @@ pub fn union_decl(input: &str) -> IResult<&str, Union> {
             delimited(comment_or_space1, ident, comment_or_space0),
         ),
         metadata,
-        enum_body,
+        union_body,
     ));
     map(parser, |(comment, name, metadata, values)| {
         Union::builder()
@@ -737,9 +896,43 @@ mod union_tests {
         );
         assert_successful_parse!(result, expected);
     }
+
+    #[test]
+    fn test_union_aliases() {
+        let input = "union Any { Alias: my.ns.Type = 5, Other, Second : Type }";
+        let result = union_decl(input);
+        let alias = EnumVal::builder()
+            .id(Ident::from("Alias"))
+            .value(Some(5))
+            .ty(Some(DottedIdent::from(vec![
+                Ident::from("my"),
+                Ident::from("ns"),
+                Ident::from("Type"),
+            ])))
+            .build();
+        let second = EnumVal::builder()
+            .id(Ident::from("Second"))
+            .ty(Some(DottedIdent::from(vec![Ident::from("Type")])))
+            .build();
+        let expected = union!(Any, [alias, e_item!(Other), second]);
+        assert_successful_parse!(result, expected);
+    }
+
+    #[test]
+    fn test_union_value_doc_comments() {
+        let input = "union Any {\n  /// An alias.\n  Alias: Type,\n  Other\n}";
+        let result = union_decl(input);
+        let alias = EnumVal::builder()
+            .id(Ident::from("Alias"))
+            .ty(Some(DottedIdent::from(vec![Ident::from("Type")])))
+            .doc(doc!(" An alias."))
+            .build();
+        let expected = union!(Any, [alias, e_item!(Other)]);
+        assert_successful_parse!(result, expected);
+    }
 }
 
-pub fn root_decl(input: &str) -> IResult<&str, Root> {
+pub fn root_decl(input: &str) -> IResult<&str, Root<'_>> {
     map(
         tuple((
             doc_comment,
@@ -765,7 +958,7 @@ mod root_tests {
     }
 }
 
-pub fn field_decl(input: &str) -> IResult<&str, Field> {
+pub fn field_decl(input: &str) -> IResult<&str, Field<'_>> {
     map(
         terminated(
             tuple((
@@ -774,18 +967,18 @@ pub fn field_decl(input: &str) -> IResult<&str, Field> {
                 type_,
                 opt(preceded(
                     tuple((comment_or_space0, equals, comment_or_space0)),
-                    terminated(scalar, comment_or_space0),
+                    terminated(default_value, comment_or_space0),
                 )),
-                metadata,
+                preceded(comment_or_space0, metadata),
             )),
             tuple((comment_or_space0, semicolon)),
         ),
-        |(comment, name, ty, scalar, metadata)| {
+        |(comment, name, ty, default, metadata)| {
             Field::builder()
                 .doc(comment)
                 .id(name)
                 .ty(ty)
-                .scalar(scalar)
+                .default(default)
                 .metadata(metadata)
                 .build()
         },
@@ -830,6 +1023,26 @@ foo // bar
         assert_successful_parse!(result, expected);
     }
 
+    #[test]
+    fn test_field_decl_bool() {
+        let input = "foo: bool = true;\nbar: int;";
+        let result = field_decl(input);
+        let expected = field!(foo, Bool = true);
+        assert_eq!(result, Ok(("\nbar: int;", expected)));
+    }
+
+    #[test]
+    fn test_field_decl_metadata() {
+        let input = "name: string (required);";
+        let result = field_decl(input);
+        let expected = Field::builder()
+            .id(Ident::from("name"))
+            .ty(Type::String)
+            .metadata(Some(Metadata::from(vec![meta!(required)])))
+            .build();
+        assert_successful_parse!(result, expected);
+    }
+
     #[test]
     fn test_field_decl_no_scalar() {
         let input = "foo:float64    //faz\n;";
@@ -837,9 +1050,31 @@ foo // bar
         let expected = field!(foo, Float64);
         assert_successful_parse!(result, expected);
     }
+
+    #[test]
+    fn test_field_decl_fixed_array() {
+        let input = "pos: [float:3];";
+        let result = field_decl(input);
+        let expected = Field::builder()
+            .id(Ident::from("pos"))
+            .ty(Type::FixedArray(Box::new(Type::Float), 3))
+            .build();
+        assert_successful_parse!(result, expected);
+    }
+
+    #[test]
+    fn test_field_decl_vector() {
+        let input = "pos: [float];";
+        let result = field_decl(input);
+        let expected = Field::builder()
+            .id(Ident::from("pos"))
+            .ty(Type::from([Type::Float]))
+            .build();
+        assert_successful_parse!(result, expected);
+    }
 }
 
-pub fn rpc_decl(input: &str) -> IResult<&str, Rpc> {
+pub fn rpc_decl(input: &str) -> IResult<&str, Rpc<'_>> {
     map(
         tuple((
             doc_comment,
@@ -949,9 +1184,10 @@ mod dotted_ident_tests {
     }
 }
 
-pub fn rpc_method(input: &str) -> IResult<&str, RpcMethod> {
+pub fn rpc_method(input: &str) -> IResult<&str, RpcMethod<'_>> {
     map(
         tuple((
+            doc_comment,
             terminated(ident, comment_or_space0),
             delimited(
                 left_paren,
@@ -966,8 +1202,9 @@ pub fn rpc_method(input: &str) -> IResult<&str, RpcMethod> {
                 ),
             ),
         )),
-        |(name, request_type, (response_type, metadata))| {
+        |(comment, name, request_type, (response_type, metadata))| {
             RpcMethod::builder()
+                .doc(comment)
                 .id(name)
                 .request_type(request_type)
                 .response_type(response_type)
@@ -1000,6 +1237,19 @@ SayHello // foo
         assert_successful_parse!(result, expected);
     }
 
+    #[test]
+    fn test_rpc_method_doc_comment() {
+        let input = "/// Say hello.\nSayHello(HelloRequest):HelloReply;";
+        let result = rpc_method(input);
+        let expected = RpcMethod::builder()
+            .id(Ident::from("SayHello"))
+            .request_type(DottedIdent::from(vec![Ident::from("HelloRequest")]))
+            .response_type(DottedIdent::from(vec![Ident::from("HelloReply")]))
+            .doc(doc!(" Say hello."))
+            .build();
+        assert_successful_parse!(result, expected);
+    }
+
     #[test]
     fn test_rpc_method() {
         let input = "SayHello(HelloRequest):HelloReply;";
@@ -1030,7 +1280,7 @@ SayHello // foo
     }
 }
 
-pub fn type_(input: &str) -> IResult<&str, Type> {
+pub fn type_(input: &str) -> IResult<&str, Type<'_>> {
     alt((
         alt((
             value(Type::Bool, tag("bool")),
@@ -1057,27 +1307,68 @@ pub fn type_(input: &str) -> IResult<&str, Type> {
         )),
         value(Type::String, tag("string")),
         map(
-            delimited(left_square_bracket, type_, right_square_bracket),
-            |t| Type::from([t]),
+            delimited(
+                left_square_bracket,
+                tuple((
+                    type_,
+                    opt(preceded(
+                        delimited(comment_or_space0, colon, comment_or_space0),
+                        integer_constant,
+                    )),
+                )),
+                right_square_bracket,
+            ),
+            |(t, len)| match len {
+                Some(len) => Type::FixedArray(Box::new(t), len),
+                None => Type::from([t]),
+            },
         ),
         map(dotted_ident, Type::Ident),
     ))(input)
 }
 
 /// Parse the individual items of an enum or union.
-pub fn enumval_decl(input: &str) -> IResult<&str, EnumVal> {
+pub fn enumval_decl(input: &str) -> IResult<&str, EnumVal<'_>> {
+    let parser = tuple((
+        doc_comment,
+        ident,
+        opt(preceded(
+            comment_or_space0,
+            preceded(equals, preceded(comment_or_space0, integer_constant)),
+        )),
+    ));
+    map(parser, |(comment, id, value)| {
+        EnumVal::builder().doc(comment).id(id).value(value).build()
+    })(input)
+}
+
+/// Parse the individual items of a union, whose names can be aliases of the
+/// tables they hold, e.g., `Enemy: my.ns.Monster = 2`.
+pub fn unionval_decl(input: &str) -> IResult<&str, EnumVal<'_>> {
     let parser = tuple((
+        doc_comment,
         ident,
+        opt(preceded(
+            comment_or_space0,
+            preceded(colon, preceded(comment_or_space0, dotted_ident)),
+        )),
         opt(preceded(
             comment_or_space0,
             preceded(equals, preceded(comment_or_space0, integer_constant)),
         )),
     ));
-    map(parser, EnumVal::from)(input)
+    map(parser, |(comment, id, ty, value)| {
+        EnumVal::builder()
+            .doc(comment)
+            .id(id)
+            .value(value)
+            .ty(ty)
+            .build()
+    })(input)
 }
 
 /// Parse key-value metadata pairs.
-pub fn raw_metadata(input: &str) -> IResult<&str, Metadata> {
+pub fn raw_metadata(input: &str) -> IResult<&str, Metadata<'_>> {
     map(
         delimited(
             terminated(left_paren, comment_or_space0),
@@ -1098,7 +1389,7 @@ pub fn raw_metadata(input: &str) -> IResult<&str, Metadata> {
 }
 
 /// Optionally parse key-value pairs. Metadata is never required wherever it's allowed.
-pub fn metadata(input: &str) -> IResult<&str, Option<Metadata>> {
+pub fn metadata(input: &str) -> IResult<&str, Option<Metadata<'_>>> {
     opt(raw_metadata)(input)
 }
 
@@ -1164,6 +1455,19 @@ mod metadata_tests {
         ]));
         assert_successful_parse!(result, expected);
     }
+
+    #[test]
+    fn test_hex_and_special_metadata() {
+        let input = "(a: 0xFF, b: 1e-5, c: inf, d: -inf)";
+        let result = metadata(input);
+        let expected = Some(Metadata::from(vec![
+            meta!(a, 255),
+            meta!(b, 1e-5),
+            meta!(c, f64::INFINITY),
+            meta!(d, f64::NEG_INFINITY),
+        ]));
+        assert_successful_parse!(result, expected);
+    }
 }
 
 /// Parse float, integer or boolean constants.
@@ -1175,8 +1479,81 @@ pub fn scalar(input: &str) -> IResult<&str, Scalar> {
     ))(input)
 }
 
+/// Parse the default value of a field: a scalar, `null` or the name of an
+/// enum value.
+pub fn default_value(input: &str) -> IResult<&str, DefaultValue<'_>> {
+    alt((
+        map(scalar, DefaultValue::from),
+        value(DefaultValue::Null, |input: &str| {
+            nom::re_find!(input, r"^null\b")
+        }),
+        map(ident, DefaultValue::EnumVal),
+    ))(input)
+}
+
+#[cfg(test)]
+mod default_value_tests {
+    use super::*;
+
+    #[test]
+    fn test_default_value() {
+        assert_successful_parse!(default_value("1"), DefaultValue::Scalar(Scalar::Integer(1)));
+        assert_successful_parse!(
+            default_value("true"),
+            DefaultValue::Scalar(Scalar::Boolean(true))
+        );
+        assert_successful_parse!(default_value("null"), DefaultValue::Null);
+        assert_successful_parse!(
+            default_value("0xFF"),
+            DefaultValue::Scalar(Scalar::Integer(255))
+        );
+        assert_successful_parse!(
+            default_value("2.5e3"),
+            DefaultValue::Scalar(Scalar::Float(2500.0))
+        );
+        assert_successful_parse!(
+            default_value("-inf"),
+            DefaultValue::Scalar(Scalar::Float(f64::NEG_INFINITY))
+        );
+        assert_successful_parse!(
+            default_value("nan"),
+            DefaultValue::Scalar(Scalar::Float(f64::NAN))
+        );
+        assert_successful_parse!(
+            default_value("Green"),
+            DefaultValue::EnumVal(Ident::from("Green"))
+        );
+        assert_successful_parse!(
+            default_value("nullable"),
+            DefaultValue::EnumVal(Ident::from("nullable"))
+        );
+    }
+
+    #[test]
+    fn test_field_decl_enum_default() {
+        let result = field_decl("color: Color = Green;");
+        let expected = Field::builder()
+            .id(Ident::from("color"))
+            .ty(Type::Ident(vec![Ident::from("Color")].into()))
+            .default(Some(DefaultValue::EnumVal(Ident::from("Green"))))
+            .build();
+        assert_successful_parse!(result, expected);
+    }
+
+    #[test]
+    fn test_field_decl_null_default() {
+        let result = field_decl("hp: short = null;");
+        let expected = Field::builder()
+            .id(Ident::from("hp"))
+            .ty(Type::Short)
+            .default(Some(DefaultValue::Null))
+            .build();
+        assert_successful_parse!(result, expected);
+    }
+}
+
 /// Parse JSON object-like data.
-pub fn object(input: &str) -> IResult<&str, Object> {
+pub fn object(input: &str) -> IResult<&str, Object<'_>> {
     map(
         delimited(
             terminated(left_brace, comment_or_space0),
@@ -1242,7 +1619,7 @@ mod object_tests {
 }
 
 /// `Single`s are `Scalar`s or `StringConstant`s.
-pub fn single(input: &str) -> IResult<&str, Single> {
+pub fn single(input: &str) -> IResult<&str, Single<'_>> {
     alt((
         map(scalar, Single::from),
         map(string_constant, Single::from),
@@ -1250,7 +1627,7 @@ pub fn single(input: &str) -> IResult<&str, Single> {
 }
 
 /// A value list is a comma-separated list of `Value`s.
-pub fn value_list(input: &str) -> IResult<&str, Vec<Value>> {
+pub fn value_list(input: &str) -> IResult<&str, Vec<Value<'_>>> {
     delimited(
         terminated(left_square_bracket, comment_or_space0),
         separated_list(
@@ -1291,7 +1668,7 @@ mod value_list_tests {
 }
 
 /// `Value`s are `Single`s or `Object`s.
-pub fn value_(input: &str) -> IResult<&str, Value> {
+pub fn value_(input: &str) -> IResult<&str, Value<'_>> {
     alt((
         map(single, Value::from),
         map(object, Value::from),
@@ -1359,7 +1736,7 @@ mod value_tests {
 type ProductTypeTriple<'a> = (Ident<'a>, Option<Metadata<'a>>, Vec<Field<'a>>);
 
 /// Parse the body of a table or struct.
-pub fn product_type_body(input: &str) -> IResult<&str, ProductTypeTriple> {
+pub fn product_type_body(input: &str) -> IResult<&str, ProductTypeTriple<'_>> {
     tuple((
         delimited(comment_or_space1, ident, comment_or_space0),
         terminated(metadata, comment_or_space0),
@@ -1372,7 +1749,7 @@ pub fn product_type_body(input: &str) -> IResult<&str, ProductTypeTriple> {
 }
 
 /// Parse a struct declaration.
-pub fn struct_decl(input: &str) -> IResult<&str, Struct> {
+pub fn struct_decl(input: &str) -> IResult<&str, Struct<'_>> {
     map(
         tuple((doc_comment, preceded(tag("struct"), product_type_body))),
         |(comment, (name, metadata, fields))| {
@@ -1387,7 +1764,7 @@ pub fn struct_decl(input: &str) -> IResult<&str, Struct> {
 }
 
 /// Parse a table declaration.
-pub fn table_decl(input: &str) -> IResult<&str, Table> {
+pub fn table_decl(input: &str) -> IResult<&str, Table<'_>> {
     map(
         tuple((doc_comment, preceded(tag("table"), product_type_body))),
         |(comment, (name, metadata, fields))| {
@@ -1458,6 +1835,24 @@ table HelloReply
         assert_successful_parse!(result, expected);
     }
 
+    #[test]
+    fn test_table_field_doc_comments() {
+        let input = "\
+table HelloReply {
+  /// The greeting.
+  message: string;
+  foo: uint;
+}";
+        let result = table_decl(input);
+        let message = Field::builder()
+            .id(Ident::from("message"))
+            .ty(Type::String)
+            .doc(doc!(" The greeting."))
+            .build();
+        let expected = table!(HelloReply, [message, field!(foo, UInt)]);
+        assert_successful_parse!(result, expected);
+    }
+
     #[test]
     fn test_table() {
         let input = "\
@@ -1489,6 +1884,12 @@ mod dec_integer_constant_tests {
 
         let result = dec_integer_constant("-1234");
         assert_successful_parse!(result, -1234);
+
+        let result = dec_integer_constant("18446744073709551615");
+        assert_successful_parse!(result, u64::MAX.into());
+
+        let result = dec_integer_constant("-9223372036854775808");
+        assert_successful_parse!(result, i64::MIN.into());
     }
 }
 
@@ -1527,6 +1928,12 @@ mod hex_integer_constant_tests {
 
         let result = hex_integer_constant("-0x1234ABCDEFabcdef");
         assert_successful_parse!(result, -0x1234_ABCD_EFAB_CDEF);
+
+        let result = hex_integer_constant("0xFFFFFFFFFFFFFFFF");
+        assert_successful_parse!(result, u64::MAX.into());
+
+        let result = hex_integer_constant("-0x8000000000000000");
+        assert_successful_parse!(result, i64::MIN.into());
     }
 
     #[test]
@@ -1537,11 +1944,11 @@ mod hex_integer_constant_tests {
 }
 
 pub fn true_(input: &str) -> IResult<&str, BooleanConstant> {
-    value(true, |input: &str| nom::re_match!(input, r"\btrue\b"))(input)
+    value(true, |input: &str| nom::re_find!(input, r"^true\b"))(input)
 }
 
 pub fn false_(input: &str) -> IResult<&str, BooleanConstant> {
-    value(false, |input: &str| nom::re_match!(input, r"\bfalse\b"))(input)
+    value(false, |input: &str| nom::re_find!(input, r"^false\b"))(input)
 }
 
 #[cfg(test)]
@@ -1557,7 +1964,7 @@ mod true_false_tests {
     #[test]
     fn test_invalid_true() {
         let result = true_("truez");
-        assert_failed_parse!(result, "truez", RegexpMatch);
+        assert_failed_parse!(result, "truez", RegexpFind);
     }
 
     #[test]
@@ -1566,10 +1973,16 @@ mod true_false_tests {
         assert_successful_parse!(result, false);
     }
 
+    #[test]
+    fn test_true_false_leave_remaining_input() {
+        assert_eq!(true_("true; x"), Ok(("; x", true)));
+        assert_eq!(false_("false)"), Ok((")", false)));
+    }
+
     #[test]
     fn test_invalid_false() {
         let result = false_("falsez");
-        assert_failed_parse!(result, "falsez", RegexpMatch);
+        assert_failed_parse!(result, "falsez", RegexpFind);
     }
 }
 
@@ -1595,7 +2008,7 @@ mod boolean_constant_tests {
     #[test]
     fn test_invalid_boolean_constant() {
         let result = boolean_constant("waltz");
-        assert_failed_parse!(result, "waltz", RegexpMatch);
+        assert_failed_parse!(result, "waltz", RegexpFind);
     }
 }
 
@@ -1629,6 +2042,15 @@ mod dec_float_constant_tests {
     fn test_dec_float_constant() {
         let result = dec_float_constant("-2.1");
         assert_successful_parse!(result, -2.1);
+
+        let result = dec_float_constant("1e-5");
+        assert_successful_parse!(result, 1e-5);
+
+        let result = dec_float_constant("2.5e3");
+        assert_successful_parse!(result, 2500.0);
+
+        let result = dec_float_constant(".5E+1");
+        assert_successful_parse!(result, 5.0);
     }
 }
 
@@ -1670,25 +2092,25 @@ mod float_constant_tests {
     #[test]
     fn test_float_constant_inf() {
         let result = float_constant("inf");
-        assert_successful_parse!(result, std::f64::INFINITY);
+        assert_successful_parse!(result, f64::INFINITY);
 
         let result = float_constant("+inf");
-        assert_successful_parse!(result, std::f64::INFINITY);
+        assert_successful_parse!(result, f64::INFINITY);
 
         let result = float_constant("-inf");
-        assert_successful_parse!(result, std::f64::NEG_INFINITY);
+        assert_successful_parse!(result, f64::NEG_INFINITY);
     }
 
     #[test]
     fn test_float_constant_infinity() {
         let result = float_constant("infinity");
-        assert_successful_parse!(result, std::f64::INFINITY);
+        assert_successful_parse!(result, f64::INFINITY);
 
         let result = float_constant("+infinity");
-        assert_successful_parse!(result, std::f64::INFINITY);
+        assert_successful_parse!(result, f64::INFINITY);
 
         let result = float_constant("-infinity");
-        assert_successful_parse!(result, std::f64::NEG_INFINITY);
+        assert_successful_parse!(result, f64::NEG_INFINITY);
     }
 }
 
@@ -1716,7 +2138,16 @@ pub fn hex_float_constant(input: &str) -> IResult<&str, FloatingConstant> {
                 ),
                 terminated(hex_digit1, hex_float_exponent),
             ))),
-            |value| parse_hexf64(value, false),
+            |value: &str| {
+                // `parse_hexf64` wants the prefix, a point and an exponent,
+                // which are optional in schemas
+                let (mantissa, exponent) = match value.find(['p', 'P']) {
+                    Some(index) => value.split_at(index),
+                    None => (value, "p0"),
+                };
+                let point = if mantissa.contains('.') { "" } else { "." };
+                parse_hexf64(&format!("0x{}{}{}", mantissa, point, exponent), false)
+            },
         ),
     ));
     map(
@@ -1731,17 +2162,40 @@ pub fn hex_float_constant(input: &str) -> IResult<&str, FloatingConstant> {
     )(input)
 }
 
+#[cfg(test)]
+mod hex_float_constant_tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_float_constant() {
+        let result = hex_float_constant("0x1p-1");
+        assert_successful_parse!(result, 0.5);
+
+        let result = hex_float_constant("-0x1.8p1");
+        assert_successful_parse!(result, -3.0);
+
+        let result = hex_float_constant("0xA.");
+        assert_successful_parse!(result, 10.0);
+
+        let result = hex_float_constant("0x.8");
+        assert_successful_parse!(result, 0.5);
+    }
+
+    #[test]
+    fn test_hex_integer_is_not_a_float() {
+        assert!(hex_float_constant("0xFF").is_err());
+    }
+}
+
 /// Parse `nan`
 pub fn nan(input: &str) -> IResult<&str, FloatingConstant> {
     map(
-        terminated(opt(plus_or_minus), |input| {
-            nom::re_match!(input, r"\bnan\b")
-        }),
+        terminated(opt(plus_or_minus), |input| nom::re_find!(input, r"^nan\b")),
         |sign| {
             if let Some('-') = sign {
-                -std::f64::NAN
+                -f64::NAN
             } else {
-                std::f64::NAN
+                f64::NAN
             }
         },
     )(input)
@@ -1775,7 +2229,7 @@ mod nan_tests {
     #[test]
     fn test_invalid_nan() {
         let result = nan("nanz");
-        assert_failed_parse!(result, "nanz", RegexpMatch);
+        assert_failed_parse!(result, "nanz", RegexpFind);
     }
 }
 
@@ -1783,13 +2237,13 @@ mod nan_tests {
 pub fn inf_or_infinity(input: &str) -> IResult<&str, FloatingConstant> {
     map(
         terminated(opt(plus_or_minus), |input| {
-            nom::re_match!(input, r"\binf(inity)?\b")
+            nom::re_find!(input, r"^inf(inity)?\b")
         }),
         |sign| {
             if let Some('-') = sign {
-                std::f64::NEG_INFINITY
+                f64::NEG_INFINITY
             } else {
-                std::f64::INFINITY
+                f64::INFINITY
             }
         },
     )(input)
@@ -1802,22 +2256,22 @@ mod inf_or_infinity_tests {
     #[test]
     fn test_inf_or_infinity_infinity() {
         let result = inf_or_infinity("inf");
-        assert_successful_parse!(result, std::f64::INFINITY);
+        assert_successful_parse!(result, f64::INFINITY);
 
         let result = inf_or_infinity("+inf");
-        assert_successful_parse!(result, std::f64::INFINITY);
+        assert_successful_parse!(result, f64::INFINITY);
 
         let result = inf_or_infinity("-inf");
-        assert_successful_parse!(result, std::f64::NEG_INFINITY);
+        assert_successful_parse!(result, f64::NEG_INFINITY);
 
         let result = inf_or_infinity("infinity");
-        assert_successful_parse!(result, std::f64::INFINITY);
+        assert_successful_parse!(result, f64::INFINITY);
 
         let result = inf_or_infinity("+infinity");
-        assert_successful_parse!(result, std::f64::INFINITY);
+        assert_successful_parse!(result, f64::INFINITY);
 
         let result = inf_or_infinity("-infinity");
-        assert_successful_parse!(result, std::f64::NEG_INFINITY);
+        assert_successful_parse!(result, f64::NEG_INFINITY);
     }
 }
 
@@ -1854,19 +2308,19 @@ mod special_float_constant_tests {
     #[test]
     fn test_special_float_constant_inf() {
         let result = special_float_constant("inf");
-        assert_successful_parse!(result, std::f64::INFINITY);
+        assert_successful_parse!(result, f64::INFINITY);
 
         let result = special_float_constant("-inf");
-        assert_successful_parse!(result, std::f64::NEG_INFINITY);
+        assert_successful_parse!(result, f64::NEG_INFINITY);
     }
 
     #[test]
     fn test_special_float_constant_infinity() {
         let result = special_float_constant("infinity");
-        assert_successful_parse!(result, std::f64::INFINITY);
+        assert_successful_parse!(result, f64::INFINITY);
 
         let result = special_float_constant("-infinity");
-        assert_successful_parse!(result, std::f64::NEG_INFINITY);
+        assert_successful_parse!(result, f64::NEG_INFINITY);
     }
 }
 
@@ -1894,7 +2348,7 @@ mod integer_constant_tests {
     }
 }
 
-pub fn file_extension_decl(input: &str) -> IResult<&str, FileExtension> {
+pub fn file_extension_decl(input: &str) -> IResult<&str, FileExtension<'_>> {
     map(
         tuple((
             doc_comment,
@@ -1952,7 +2406,7 @@ mod file_extension_tests {
     }
 }
 
-pub fn file_identifier_decl(input: &str) -> IResult<&str, FileIdentifier> {
+pub fn file_identifier_decl(input: &str) -> IResult<&str, FileIdentifier<'_>> {
     map(
         tuple((
             doc_comment,
@@ -2027,13 +2481,14 @@ pub fn raw_doc_comment(input: &str) -> IResult<&str, &str> {
     preceded(tag("///"), not_line_ending)(input)
 }
 
-/// Parse zero or more lines of documentation comments
+/// Parse zero or more lines of documentation comments, along with the
+/// indentation of whatever follows each of them.
 pub fn doc_comment_lines(input: &str) -> IResult<&str, Vec<&str>> {
-    many0(terminated(raw_doc_comment, line_ending))(input)
+    many0(terminated(raw_doc_comment, pair(line_ending, space0)))(input)
 }
 
 /// Wrap zero or more lines of documentation comments in an AST node.
-pub fn doc_comment(input: &str) -> IResult<&str, Comment> {
+pub fn doc_comment(input: &str) -> IResult<&str, Comment<'_>> {
     map(doc_comment_lines, Comment::from)(input)
 }
 
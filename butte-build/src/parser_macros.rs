@@ -125,7 +125,9 @@ macro_rules! field {
         $crate::types::Field::builder()
             .id($crate::types::Ident::from(stringify!($name)))
             .ty($crate::types::Type::$ty)
-            .scalar(Some($crate::scalar!($default)))
+            .default(Some($crate::types::DefaultValue::from($crate::scalar!(
+                $default
+            ))))
             .build()
     };
     ($name:ident, [ $ty:ident ]) => {
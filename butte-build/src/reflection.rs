@@ -0,0 +1,437 @@
+//! Binary schemas (`.bfbs` files), which describe the types of a schema as a
+//! FlatBuffer that `butte::reflection` reads at runtime.
+use anyhow::Result;
+use butte::{
+    json,
+    reflection::{self, BaseType, Enum, EnumVal, Field, Object, Schema, Type},
+    FlatBufferBuilder, WIPOffset,
+};
+
+use crate::{compile::checked_schema, include::SchemaFiles, json::describe, types::*};
+
+/// Generate the binary schema of a schema and all of the files it includes,
+/// like `flatc --schema --binary` does.
+///
+/// Tables and structs, enums and unions, and the fields of tables and structs
+/// are sorted by name, so that `butte::reflection` can look them up by name.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> anyhow::Result<()> {
+/// let files = butte_build::include::SchemaFiles::load("monster.fbs", &[])?;
+/// std::fs::write("monster.bfbs", butte_build::reflection_schema(&files)?)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn reflection_schema(files: &SchemaFiles) -> Result<Vec<u8>> {
+    let schema = checked_schema(files, None)?;
+    let file_extension = schema.elements.iter().find_map(|element| match element {
+        Element::FileExtension(FileExtension { ext, .. }) => Some(*ext),
+        _ => None,
+    });
+    Ok(write(&describe(&schema.elements), file_extension))
+}
+
+/// Write the binary schema of `schema`.
+fn write(schema: &json::Schema, file_extension: Option<&str>) -> Vec<u8> {
+    let writer = Writer::new(schema);
+    let mut fbb = FlatBufferBuilder::new();
+
+    let mut objects = vec![];
+    for &object in &writer.objects {
+        objects.push(writer.object(&mut fbb, object));
+    }
+    let mut enums = vec![];
+    for &index in &writer.enums {
+        enums.push(writer.enum_(&mut fbb, index));
+    }
+    let root_table = schema
+        .root_table
+        .map(|index| objects[writer.object_index(ObjectRef::Table(index))]);
+
+    let objects = fbb.create_vector(&objects);
+    let enums = fbb.create_vector(&enums);
+    let file_ident = schema
+        .file_identifier
+        .as_deref()
+        .map(|id| fbb.create_string(id));
+    let file_ext = file_extension.map(|ext| fbb.create_string(ext));
+
+    let start = fbb.start_table();
+    fbb.push_slot_always(Schema::VT_OBJECTS, objects);
+    fbb.push_slot_always(Schema::VT_ENUMS, enums);
+    if let Some(file_ident) = file_ident {
+        fbb.push_slot_always(Schema::VT_FILE_IDENT, file_ident);
+    }
+    if let Some(file_ext) = file_ext {
+        fbb.push_slot_always(Schema::VT_FILE_EXT, file_ext);
+    }
+    if let Some(root_table) = root_table {
+        fbb.push_slot_always(Schema::VT_ROOT_TABLE, root_table);
+    }
+    let root = fbb.end_table(start);
+    fbb.finish(root, Some(reflection::FILE_IDENTIFIER));
+    fbb.finished_data().to_vec()
+}
+
+/// A table or struct of a `json::Schema`, which keeps them in separate lists.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ObjectRef {
+    Table(usize),
+    Struct(usize),
+}
+
+/// Writes the types of a `json::Schema` in the order of a binary schema.
+struct Writer<'a> {
+    schema: &'a json::Schema,
+    /// The tables and structs of the schema, sorted by name.
+    objects: Vec<ObjectRef>,
+    /// The indices of the enums and unions of the schema, sorted by name.
+    enums: Vec<usize>,
+    /// Whether each enum of the schema is the discriminant of a union.
+    is_union: Vec<bool>,
+}
+
+impl<'a> Writer<'a> {
+    fn new(schema: &'a json::Schema) -> Self {
+        let mut objects = (0..schema.tables.len())
+            .map(ObjectRef::Table)
+            .chain((0..schema.structs.len()).map(ObjectRef::Struct))
+            .collect::<Vec<_>>();
+        objects.sort_by_key(|object| match *object {
+            ObjectRef::Table(index) => &schema.tables[index].name,
+            ObjectRef::Struct(index) => &schema.structs[index].name,
+        });
+        let mut enums = (0..schema.enums.len()).collect::<Vec<_>>();
+        enums.sort_by_key(|index| &schema.enums[*index].name);
+
+        let mut is_union = schema
+            .enums
+            .iter()
+            .map(|e| e.values.iter().any(|value| value.union_table.is_some()))
+            .collect::<Vec<_>>();
+        for field in schema.tables.iter().flat_map(|table| &table.fields) {
+            match &field.ty {
+                json::Type::Union(index) => is_union[*index] = true,
+                json::Type::Vector(element) => {
+                    if let json::Type::Union(index) = element.as_ref() {
+                        is_union[*index] = true;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Self {
+            schema,
+            objects,
+            enums,
+            is_union,
+        }
+    }
+
+    /// The index of a table or struct in the objects of the binary schema.
+    fn object_index(&self, object: ObjectRef) -> usize {
+        self.objects
+            .iter()
+            .position(|o| *o == object)
+            .expect("unknown object")
+    }
+
+    /// The index of an enum or union in the enums of the binary schema.
+    fn enum_index(&self, index: usize) -> usize {
+        self.enums
+            .iter()
+            .position(|i| *i == index)
+            .expect("unknown enum")
+    }
+
+    /// The base type of a type, the index of the type it refers to, and its
+    /// size in bytes when stored inline.
+    fn base_type(&self, ty: &json::Type) -> (BaseType, i32, u32) {
+        match ty {
+            json::Type::Bool => (BaseType::BOOL, -1, 1),
+            json::Type::Byte => (BaseType::BYTE, -1, 1),
+            json::Type::UByte => (BaseType::UBYTE, -1, 1),
+            json::Type::Short => (BaseType::SHORT, -1, 2),
+            json::Type::UShort => (BaseType::USHORT, -1, 2),
+            json::Type::Int => (BaseType::INT, -1, 4),
+            json::Type::UInt => (BaseType::UINT, -1, 4),
+            json::Type::Long => (BaseType::LONG, -1, 8),
+            json::Type::ULong => (BaseType::ULONG, -1, 8),
+            json::Type::Float => (BaseType::FLOAT, -1, 4),
+            json::Type::Double => (BaseType::DOUBLE, -1, 8),
+            json::Type::String => (BaseType::STRING, -1, 4),
+            json::Type::Vector(_) => (BaseType::VECTOR, -1, 4),
+            json::Type::Vector64(_) => (BaseType::VECTOR64, -1, 8),
+            json::Type::Array(element, len) => {
+                let (_, _, size) = self.base_type(element);
+                (BaseType::ARRAY, -1, size * *len as u32)
+            }
+            json::Type::Enum(index) => {
+                let enum_index = self.enum_index(*index) as i32;
+                if self.is_union[*index] {
+                    (BaseType::UTYPE, enum_index, 1)
+                } else {
+                    let (base_type, _, size) =
+                        self.base_type(&self.schema.enums[*index].underlying_type);
+                    (base_type, enum_index, size)
+                }
+            }
+            json::Type::Struct(index) => (
+                BaseType::OBJ,
+                self.object_index(ObjectRef::Struct(*index)) as i32,
+                self.schema.structs[*index].size as u32,
+            ),
+            json::Type::Table(index) => (
+                BaseType::OBJ,
+                self.object_index(ObjectRef::Table(*index)) as i32,
+                4,
+            ),
+            json::Type::Union(index) => (BaseType::UNION, self.enum_index(*index) as i32, 4),
+        }
+    }
+
+    fn ty<'b>(&self, fbb: &mut FlatBufferBuilder<'b>, ty: &json::Type) -> WIPOffset<Type<'b>> {
+        let (base_type, mut index, base_size) = self.base_type(ty);
+        let (element, element_size, fixed_length) = match ty {
+            json::Type::Vector(element) | json::Type::Vector64(element) => {
+                let (element, element_index, element_size) = self.base_type(element);
+                index = element_index;
+                (element, element_size, 0)
+            }
+            json::Type::Array(element, len) => {
+                let (element, element_index, element_size) = self.base_type(element);
+                index = element_index;
+                (element, element_size, *len as u16)
+            }
+            _ => (BaseType::NONE, 0, 0),
+        };
+        self.write_type(
+            fbb,
+            (base_type, index, base_size),
+            (element, element_size, fixed_length),
+        )
+    }
+
+    fn write_type<'b>(
+        &self,
+        fbb: &mut FlatBufferBuilder<'b>,
+        (base_type, index, base_size): (BaseType, i32, u32),
+        (element, element_size, fixed_length): (BaseType, u32, u16),
+    ) -> WIPOffset<Type<'b>> {
+        let start = fbb.start_table();
+        fbb.push_slot(Type::VT_BASE_TYPE, base_type.0, BaseType::NONE.0);
+        fbb.push_slot(Type::VT_ELEMENT, element.0, BaseType::NONE.0);
+        fbb.push_slot(Type::VT_INDEX, index, -1);
+        fbb.push_slot(Type::VT_FIXED_LENGTH, fixed_length, 0);
+        fbb.push_slot(Type::VT_BASE_SIZE, base_size, 4);
+        fbb.push_slot(Type::VT_ELEMENT_SIZE, element_size, 0);
+        WIPOffset::new(fbb.end_table(start).value())
+    }
+
+    fn object<'b>(
+        &self,
+        fbb: &mut FlatBufferBuilder<'b>,
+        object: ObjectRef,
+    ) -> WIPOffset<Object<'b>> {
+        let (name, mut fields, is_struct, minalign, bytesize) = match object {
+            ObjectRef::Table(index) => {
+                let table = &self.schema.tables[index];
+                let fields = table
+                    .fields
+                    .iter()
+                    .map(|field| {
+                        let name = fbb.create_string(&field.name);
+                        let ty = self.ty(fbb, &field.ty);
+                        let start = fbb.start_table();
+                        fbb.push_slot_always(Field::VT_NAME, name);
+                        fbb.push_slot_always(Field::VT_TYPE, ty);
+                        fbb.push_slot(Field::VT_ID, field.id as u16, 0);
+                        fbb.push_slot(
+                            Field::VT_OFFSET,
+                            butte::field_index_to_field_offset(field.id) as u16,
+                            0,
+                        );
+                        fbb.push_slot(Field::VT_DEFAULT_INTEGER, field.default_integer, 0);
+                        fbb.push_slot(Field::VT_DEFAULT_REAL, field.default_real, 0.0);
+                        fbb.push_slot(Field::VT_DEPRECATED, field.deprecated, false);
+                        fbb.push_slot(Field::VT_REQUIRED, field.required, false);
+                        fbb.push_slot(Field::VT_OPTIONAL, field.optional, false);
+                        fbb.push_slot(Field::VT_OFFSET64, field.offset64, false);
+                        (
+                            &field.name,
+                            WIPOffset::<Field>::new(fbb.end_table(start).value()),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                (&table.name, fields, false, 1, 0)
+            }
+            ObjectRef::Struct(index) => {
+                let s = &self.schema.structs[index];
+                let fields = s
+                    .fields
+                    .iter()
+                    .enumerate()
+                    .map(|(id, field)| {
+                        let name = fbb.create_string(&field.name);
+                        let ty = self.ty(fbb, &field.ty);
+                        let start = fbb.start_table();
+                        fbb.push_slot_always(Field::VT_NAME, name);
+                        fbb.push_slot_always(Field::VT_TYPE, ty);
+                        fbb.push_slot(Field::VT_ID, id as u16, 0);
+                        fbb.push_slot(Field::VT_OFFSET, field.offset as u16, 0);
+                        (
+                            &field.name,
+                            WIPOffset::<Field>::new(fbb.end_table(start).value()),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                (&s.name, fields, true, s.align as i32, s.size as i32)
+            }
+        };
+        fields.sort_by_key(|(name, _)| *name);
+        let fields = fields
+            .into_iter()
+            .map(|(_, field)| field)
+            .collect::<Vec<_>>();
+
+        let name = fbb.create_string(name);
+        let fields = fbb.create_vector(&fields);
+        let start = fbb.start_table();
+        fbb.push_slot_always(Object::VT_NAME, name);
+        fbb.push_slot_always(Object::VT_FIELDS, fields);
+        fbb.push_slot(Object::VT_IS_STRUCT, is_struct, false);
+        fbb.push_slot(Object::VT_MINALIGN, minalign, 0);
+        fbb.push_slot(Object::VT_BYTESIZE, bytesize, 0);
+        WIPOffset::new(fbb.end_table(start).value())
+    }
+
+    fn enum_<'b>(&self, fbb: &mut FlatBufferBuilder<'b>, index: usize) -> WIPOffset<Enum<'b>> {
+        let e = &self.schema.enums[index];
+        let mut values = e.values.iter().collect::<Vec<_>>();
+        values.sort_by_key(|value| value.value);
+        let values = values
+            .into_iter()
+            .map(|value| {
+                let name = fbb.create_string(&value.name);
+                let union_type = value
+                    .union_table
+                    .map(|table| self.ty(fbb, &json::Type::Table(table)));
+                let start = fbb.start_table();
+                fbb.push_slot_always(EnumVal::VT_NAME, name);
+                fbb.push_slot(EnumVal::VT_VALUE, value.value, 0);
+                if let Some(union_type) = union_type {
+                    fbb.push_slot_always(EnumVal::VT_UNION_TYPE, union_type);
+                }
+                WIPOffset::new(fbb.end_table(start).value())
+            })
+            .collect::<Vec<WIPOffset<EnumVal>>>();
+
+        let name = fbb.create_string(&e.name);
+        let values = fbb.create_vector(&values);
+        // Like flatc, the underlying type of an enum refers to the enum itself.
+        let (base_type, enum_index, base_size) = self.base_type(&json::Type::Enum(index));
+        let underlying_type = self.write_type(
+            fbb,
+            (base_type, enum_index, base_size),
+            (BaseType::NONE, 0, 0),
+        );
+        let start = fbb.start_table();
+        fbb.push_slot_always(Enum::VT_NAME, name);
+        fbb.push_slot_always(Enum::VT_VALUES, values);
+        fbb.push_slot(Enum::VT_IS_UNION, self.is_union[index], false);
+        fbb.push_slot_always(Enum::VT_UNDERLYING_TYPE, underlying_type);
+        WIPOffset::new(fbb.end_table(start).value())
+    }
+}
+
+#[cfg(test)]
+mod reflection_tests {
+    use super::*;
+    use crate::parser::parse_schema;
+    use butte::reflection::get_schema;
+
+    fn bfbs(input: &str) -> Vec<u8> {
+        let schema = parse_schema(input).unwrap();
+        write(&describe(&schema.elements), Some("pet"))
+    }
+
+    #[test]
+    fn test_objects_are_sorted_by_name() -> butte::Result<()> {
+        let buf = bfbs(
+            "namespace zoo;
+             struct Point { x: float; y: float; }
+             table Pet { name: string (required); position: Point; }
+             table Cage { pets: [Pet]; }
+             root_type Cage;
+             file_identifier \"ZOOS\";",
+        );
+        assert!(butte::buffer_has_identifier(
+            &buf,
+            reflection::FILE_IDENTIFIER,
+            false
+        ));
+        let schema = get_schema(&buf)?;
+        let names = schema
+            .objects()?
+            .iter()
+            .map(|object| object?.name())
+            .collect::<butte::Result<Vec<_>>>()?;
+        assert_eq!(names, ["zoo.Cage", "zoo.Pet", "zoo.Point"]);
+        assert_eq!(schema.root_table()?.unwrap().name()?, "zoo.Cage");
+        assert_eq!(schema.file_ident()?, Some("ZOOS"));
+        assert_eq!(schema.file_ext()?, Some("pet"));
+
+        let point = schema.object_by_name("zoo.Point")?.unwrap();
+        assert!(point.is_struct()?);
+        assert_eq!((point.bytesize()?, point.minalign()?), (8, 4));
+        assert_eq!(point.field_by_name("y")?.unwrap().offset()?, 4);
+
+        let pet = schema.object_by_name("zoo.Pet")?.unwrap();
+        let position = pet.field_by_name("position")?.unwrap();
+        assert_eq!((position.id()?, position.offset()?), (1, 6));
+        assert_eq!(position.type_()?.base_type()?, BaseType::OBJ);
+        assert_eq!(position.type_()?.index()?, 2);
+        assert!(pet.field_by_name("name")?.unwrap().required()?);
+
+        let cage = schema.object_by_name("zoo.Cage")?.unwrap();
+        let pets = cage.field_by_name("pets")?.unwrap().type_()?;
+        assert_eq!(pets.base_type()?, BaseType::VECTOR);
+        assert_eq!((pets.element()?, pets.index()?), (BaseType::OBJ, 1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_enums_and_unions() -> butte::Result<()> {
+        let buf = bfbs(
+            "enum Color: short { Red, Green = 3 }
+             table Cat { color: Color = Green; }
+             union Animal { Cat }
+             table Pet { animal: Animal; }",
+        );
+        let schema = get_schema(&buf)?;
+        let color = schema.enum_by_name("Color")?.unwrap();
+        assert!(!color.is_union()?);
+        assert_eq!(color.underlying_type()?.base_type()?, BaseType::SHORT);
+        assert_eq!(color.values()?.get(1)?.value()?, 3);
+
+        let cat = schema.object_by_name("Cat")?.unwrap();
+        let field = cat.field_by_name("color")?.unwrap();
+        assert_eq!(field.default_integer()?, 3);
+        assert_eq!(field.type_()?.index()?, 1);
+
+        let animal = schema.enum_by_name("Animal")?.unwrap();
+        assert!(animal.is_union()?);
+        let cat = animal.values()?.get(1)?;
+        assert_eq!(cat.name()?, "Cat");
+        assert_eq!(cat.union_type()?.unwrap().index()?, 0);
+
+        let pet = schema.object_by_name("Pet")?.unwrap();
+        let animal_type = pet.field_by_name("animal_type")?.unwrap().type_()?;
+        assert_eq!(animal_type.base_type()?, BaseType::UTYPE);
+        let animal = pet.field_by_name("animal")?.unwrap().type_()?;
+        assert_eq!((animal.base_type()?, animal.index()?), (BaseType::UNION, 0));
+        Ok(())
+    }
+}
@@ -1,6 +1,6 @@
 //! Types representing the parts of a flatbuffer schema
 use derive_more::{AsRef, From};
-use std::{collections::HashMap, iter::FromIterator, path::Path};
+use std::{collections::HashMap, convert::TryFrom, iter::FromIterator, path::Path};
 use typed_builder::TypedBuilder;
 
 /// A Flatbuffer schema.
@@ -51,7 +51,7 @@ impl Element<'_> {
     }
 
     /// Return the underlying `Namespace` object from the element if `self` is a `Namespace`.
-    pub fn namespace(&self) -> Option<&Namespace> {
+    pub fn namespace(&self) -> Option<&Namespace<'_>> {
         match self {
             Element::Namespace(ns) => Some(ns),
             _ => None,
@@ -188,7 +188,7 @@ pub struct Field<'a> {
     pub ty: Type<'a>,
 
     #[builder(default)]
-    pub scalar: Option<Scalar>,
+    pub default: Option<DefaultValue<'a>>,
 
     #[builder(default)]
     pub metadata: Option<Metadata<'a>>,
@@ -197,6 +197,174 @@ pub struct Field<'a> {
     pub doc: Comment<'a>,
 }
 
+/// The default value of a table field.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DefaultValue<'a> {
+    /// An integer, float, or boolean constant.
+    Scalar(Scalar),
+
+    /// A value of the field's enum type, e.g., `Green` in `color: Color = Green`.
+    EnumVal(Ident<'a>),
+
+    /// `null`, which makes a scalar field optional: it is absent unless it
+    /// was set explicitly.
+    Null,
+}
+
+impl From<Scalar> for DefaultValue<'_> {
+    fn from(value: Scalar) -> Self {
+        DefaultValue::Scalar(value)
+    }
+}
+
+impl<'a> Field<'a> {
+    /// Check whether the field is annotated with the attribute `name`.
+    pub fn has_attribute(&self, name: &str) -> bool {
+        self.attribute(name).is_some()
+    }
+
+    /// The attribute `name` of the field, which is `Some(None)` if it has no
+    /// value, e.g. `required`, and `None` if the field doesn't have it.
+    pub fn attribute(&self, name: &str) -> Option<Option<&Single<'a>>> {
+        self.metadata.as_ref()?.get(name)
+    }
+
+    /// Check whether the field must be present in every table, i.e., it has
+    /// the `required` attribute.
+    pub fn is_required(&self) -> bool {
+        self.has_attribute("required")
+    }
+
+    /// Check whether the field is no longer written, i.e., it has the
+    /// `deprecated` attribute. It still takes up its slot in the vtable.
+    pub fn is_deprecated(&self) -> bool {
+        self.has_attribute("deprecated")
+    }
+
+    /// Check whether the string or vector the field refers to is stored
+    /// behind a 64-bit offset, i.e., it has the `offset64` or `vector64`
+    /// attribute.
+    pub fn is_offset64(&self) -> bool {
+        self.has_attribute("offset64") || self.is_vector64()
+    }
+
+    /// Check whether the field is a vector with a 64-bit length, i.e., it has
+    /// the `vector64` attribute.
+    pub fn is_vector64(&self) -> bool {
+        self.has_attribute("vector64")
+    }
+
+    /// Check whether the field holds a FlexBuffer, i.e., it has the
+    /// `flexbuffer` attribute.
+    pub fn is_flexbuffer(&self) -> bool {
+        self.has_attribute("flexbuffer")
+    }
+}
+
+impl Struct<'_> {
+    /// The alignment in bytes given by the struct's `force_align` attribute,
+    /// if it is a positive integer.
+    pub fn force_align(&self) -> Option<usize> {
+        match self
+            .metadata
+            .as_ref()?
+            .values
+            .get(&Ident::from("force_align"))
+        {
+            Some(Some(Single::Scalar(Scalar::Integer(align)))) => usize::try_from(*align).ok(),
+            _ => None,
+        }
+    }
+}
+
+impl Table<'_> {
+    /// Check whether the table has the `original_order` attribute, in which
+    /// case its fields are laid out in the order they are declared, rather
+    /// than largest first.
+    pub fn is_original_order(&self) -> bool {
+        self.metadata
+            .as_ref()
+            .is_some_and(|metadata| metadata.values.contains_key(&Ident::from("original_order")))
+    }
+}
+
+impl Enum<'_> {
+    /// Check whether the enum has the `bit_flags` attribute, in which case
+    /// each of its values names a bit and is stored as `1 << value`.
+    pub fn is_bit_flags(&self) -> bool {
+        self.metadata
+            .as_ref()
+            .is_some_and(|metadata| metadata.values.contains_key(&Ident::from("bit_flags")))
+    }
+}
+
+impl<'a> Field<'a> {
+    /// The vtable slot of the field given by its `id` attribute, e.g., `2` in
+    /// `hp: short (id: 2)`, if it is an integer.
+    pub fn id_attribute(&self) -> Option<IntegerConstant> {
+        match self.metadata.as_ref()?.values.get(&Ident::from("id")) {
+            Some(Some(Single::Scalar(Scalar::Integer(id)))) => Some(*id),
+            _ => None,
+        }
+    }
+
+    /// The name of the root table of the buffer stored in the field, given by
+    /// its `nested_flatbuffer` attribute, e.g., `MyGame.Monster`.
+    pub fn nested_flatbuffer(&self) -> Option<&'a str> {
+        match self
+            .metadata
+            .as_ref()?
+            .values
+            .get(&Ident::from("nested_flatbuffer"))
+        {
+            Some(Some(Single::String(name))) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// The algorithm hashing the strings stored in the field, given by its
+    /// `hash` attribute, e.g., `fnv1a_32`, if it is a known one.
+    pub fn hash(&self) -> Option<butte::HashAlgorithm> {
+        match self.metadata.as_ref()?.values.get(&Ident::from("hash")) {
+            Some(Some(Single::String(name))) => butte::HashAlgorithm::from_name(name),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod field_impl_tests {
+    use super::*;
+    use crate::{field, meta};
+
+    #[test]
+    fn test_is_required() {
+        let field = Field::builder()
+            .id(Ident::from("name"))
+            .ty(Type::String)
+            .metadata(Some(Metadata::from(vec![meta!(required)])))
+            .build();
+        assert!(field.is_required());
+        assert!(field.has_attribute("required"));
+        assert!(!field.has_attribute("deprecated"));
+
+        let field = field!(name, String);
+        assert!(!field.is_required());
+    }
+
+    #[test]
+    fn test_is_deprecated() {
+        let field = Field::builder()
+            .id(Ident::from("name"))
+            .ty(Type::String)
+            .metadata(Some(Metadata::from(vec![meta!(deprecated)])))
+            .build();
+        assert!(field.is_deprecated());
+        assert!(!field.is_required());
+        assert!(!field!(name, String).is_deprecated());
+    }
+}
+
 /// An RPC service.
 #[derive(Debug, Clone, PartialEq, TypedBuilder)]
 pub struct Rpc<'a> {
@@ -227,6 +395,128 @@ pub struct RpcMethod<'a> {
     pub doc: Comment<'a>,
 }
 
+/// Which messages of an RPC method are streamed, as given by its `streaming`
+/// attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Streaming {
+    /// A single request and a single response.
+    None,
+    /// A stream of requests and a single response.
+    Client,
+    /// A single request and a stream of responses.
+    Server,
+    /// A stream of requests and a stream of responses.
+    Bidi,
+}
+
+/// Whether an RPC method has side effects, as given by its
+/// `idempotency_level` attribute. The levels are those of gRPC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdempotencyLevel {
+    /// The method may have side effects.
+    Unknown,
+    /// The method has no side effects, so it can be retried or cached.
+    NoSideEffects,
+    /// Calling the method several times has the same effect as calling it
+    /// once, so it can be retried.
+    Idempotent,
+}
+
+impl<'a> RpcMethod<'a> {
+    /// Check whether the method is annotated with the attribute `name`.
+    pub fn has_attribute(&self, name: &str) -> bool {
+        self.attribute(name).is_some()
+    }
+
+    /// The attribute `name` of the method, which is `Some(None)` if it has
+    /// no value, e.g. `deprecated`, and `None` if the method doesn't have it.
+    pub fn attribute(&self, name: &str) -> Option<Option<&Single<'a>>> {
+        self.metadata.as_ref()?.get(name)
+    }
+
+    /// Check whether the method should no longer be called, i.e., it has the
+    /// `deprecated` attribute.
+    pub fn is_deprecated(&self) -> bool {
+        self.has_attribute("deprecated")
+    }
+
+    /// The idempotency level of the method, or `None` if its
+    /// `idempotency_level` attribute isn't one of `"idempotency_unknown"`,
+    /// `"no_side_effects"` or `"idempotent"`.
+    pub fn idempotency_level(&self) -> Option<IdempotencyLevel> {
+        match self.attribute("idempotency_level") {
+            None => Some(IdempotencyLevel::Unknown),
+            Some(Some(Single::String(value))) => match *value {
+                "idempotency_unknown" => Some(IdempotencyLevel::Unknown),
+                "no_side_effects" => Some(IdempotencyLevel::NoSideEffects),
+                "idempotent" => Some(IdempotencyLevel::Idempotent),
+                _ => None,
+            },
+            Some(_) => None,
+        }
+    }
+
+    /// The streaming mode of the method, or `None` if its `streaming`
+    /// attribute isn't one of `"none"`, `"client"`, `"server"` or `"bidi"`.
+    pub fn streaming(&self) -> Option<Streaming> {
+        match self.attribute("streaming") {
+            None => Some(Streaming::None),
+            Some(Some(Single::String(value))) => match *value {
+                "none" => Some(Streaming::None),
+                "client" => Some(Streaming::Client),
+                "server" => Some(Streaming::Server),
+                "bidi" => Some(Streaming::Bidi),
+                _ => None,
+            },
+            Some(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod rpc_method_impl_tests {
+    use super::*;
+    use crate::{meta, method};
+
+    #[test]
+    fn test_streaming() {
+        let method = method!(fn SayHello(Request) -> Response);
+        assert_eq!(method.streaming(), Some(Streaming::None));
+
+        let method = method!(fn SayHello(Request) -> Response, [meta!(streaming, "server")]);
+        assert_eq!(method.streaming(), Some(Streaming::Server));
+
+        let method = method!(fn SayHello(Request) -> Response, [meta!(streaming, "both")]);
+        assert_eq!(method.streaming(), None);
+    }
+
+    #[test]
+    fn test_method_attributes() {
+        let method = method!(fn SayHello(Request) -> Response);
+        assert!(!method.is_deprecated());
+        assert_eq!(method.attribute("deprecated"), None);
+        assert_eq!(method.idempotency_level(), Some(IdempotencyLevel::Unknown));
+
+        let method = method!(
+            fn SayHello(Request) -> Response,
+            [meta!(deprecated), meta!(idempotency_level, "no_side_effects"), meta!(timeout_ms, 500)]
+        );
+        assert!(method.is_deprecated());
+        assert_eq!(method.attribute("deprecated"), Some(None));
+        assert_eq!(
+            method.attribute("timeout_ms"),
+            Some(Some(&crate::single_value!(500)))
+        );
+        assert_eq!(
+            method.idempotency_level(),
+            Some(IdempotencyLevel::NoSideEffects)
+        );
+
+        let method = method!(fn SayHello(Request) -> Response, [meta!(idempotency_level, 1)]);
+        assert_eq!(method.idempotency_level(), None);
+    }
+}
+
 /// Scalar, array, and user-defined types.
 #[derive(Debug, Clone, PartialEq, Hash, Eq)]
 pub enum Type<'a> {
@@ -253,6 +543,9 @@ pub enum Type<'a> {
     Float64,
     String,
     Array(Box<Type<'a>>),
+    /// A fixed-size array like `[float:4]`, holding the given number of
+    /// values inline. Only structs can have fields of this type.
+    FixedArray(Box<Type<'a>>, IntegerConstant),
     Ident(DottedIdent<'a>),
 }
 
@@ -261,9 +554,37 @@ impl Type<'_> {
     pub fn is_scalar(&self) -> bool {
         // If it's a string, array type, or type name (UDT) it's not a scalar.
         // Otherwise it is.
+        !matches!(
+            self,
+            Type::String | Type::Array(_) | Type::FixedArray(..) | Type::Ident(_)
+        )
+    }
+
+    /// Check whether a `Type` is a floating point type.
+    pub fn is_float(&self) -> bool {
+        matches!(
+            self,
+            Type::Float | Type::Double | Type::Float32 | Type::Float64
+        )
+    }
+
+    /// The size in bytes of a scalar `Type`, or `None` if `self` is not a scalar.
+    ///
+    /// Scalars are always aligned to their size, so this is also their alignment.
+    pub fn scalar_size(&self) -> Option<usize> {
         match self {
-            Type::String | Type::Array(_) | Type::Ident(_) => false,
-            _ => true,
+            Type::Bool | Type::Byte | Type::UByte | Type::Int8 | Type::UInt8 => Some(1),
+            Type::Short | Type::UShort | Type::Int16 | Type::UInt16 => Some(2),
+            Type::Int | Type::UInt | Type::Float | Type::Int32 | Type::UInt32 | Type::Float32 => {
+                Some(4)
+            }
+            Type::Long
+            | Type::ULong
+            | Type::Double
+            | Type::Int64
+            | Type::UInt64
+            | Type::Float64 => Some(8),
+            Type::String | Type::Array(_) | Type::FixedArray(..) | Type::Ident(_) => None,
         }
     }
 }
@@ -276,7 +597,7 @@ impl<'a> From<[Type<'a>; 1]> for Type<'a> {
 }
 
 /// Integer constant type.
-pub type IntegerConstant = i64;
+pub type IntegerConstant = i128;
 
 /// Floating point constant type.
 pub type FloatingConstant = f64;
@@ -293,6 +614,24 @@ pub struct EnumVal<'a> {
     /// An optional enum value.
     #[builder(default)]
     pub value: Option<IntegerConstant>,
+
+    /// The table held by a union value whose name is an alias for it, e.g.,
+    /// `my.ns.Monster` in `Enemy: my.ns.Monster`.
+    #[builder(default)]
+    pub ty: Option<DottedIdent<'a>>,
+
+    #[builder(default)]
+    pub doc: Comment<'a>,
+}
+
+impl<'a> EnumVal<'a> {
+    /// The table held by a union value: the one it is an alias for, or else
+    /// the one it is named after.
+    pub fn union_type(&self) -> DottedIdent<'a> {
+        self.ty
+            .clone()
+            .unwrap_or_else(|| DottedIdent::from(vec![self.id]))
+    }
 }
 
 /// Key-value pair metadata.
@@ -302,6 +641,51 @@ pub struct Metadata<'a> {
     pub values: HashMap<Ident<'a>, Option<Single<'a>>>,
 }
 
+/// The attributes that the compiler knows the meaning of. Any other
+/// attribute must be declared by the schema, e.g. `attribute "priority";`,
+/// and is left to custom tooling.
+pub const BUILTIN_ATTRIBUTES: &[&str] = &[
+    "bit_flags",
+    "deprecated",
+    "flexbuffer",
+    "force_align",
+    "hash",
+    "id",
+    "idempotency_level",
+    "key",
+    "nested_flatbuffer",
+    "offset64",
+    "original_order",
+    "preserve_case",
+    "required",
+    "streaming",
+    "vector64",
+];
+
+impl<'a> Metadata<'a> {
+    /// The attribute `name`, which is `Some(None)` if it has no value, e.g.
+    /// `deprecated`, and `None` if there is no such attribute.
+    pub fn get(&self, name: &str) -> Option<Option<&Single<'a>>> {
+        self.values
+            .iter()
+            .find(|(id, _)| id.raw == name)
+            .map(|(_, value)| value.as_ref())
+    }
+
+    /// The attributes that aren't built in, i.e., those declared by the
+    /// schema, with their values, sorted by name.
+    pub fn custom_attributes(&self) -> Vec<(&'a str, Option<&Single<'a>>)> {
+        let mut attributes = self
+            .values
+            .iter()
+            .filter(|(id, _)| !BUILTIN_ATTRIBUTES.contains(&id.raw))
+            .map(|(id, value)| (id.raw, value.as_ref()))
+            .collect::<Vec<_>>();
+        attributes.sort_by_key(|(name, _)| *name);
+        attributes
+    }
+}
+
 impl<'a> From<Vec<(Ident<'a>, Option<Single<'a>>)>> for Metadata<'a> {
     /// Convert a `Vec` of `Ident`/`Value` pairs to a `Value`.
     fn from(values: Vec<(Ident<'a>, Option<Single<'a>>)>) -> Self {
@@ -310,13 +694,26 @@ impl<'a> From<Vec<(Ident<'a>, Option<Single<'a>>)>> for Metadata<'a> {
 }
 
 /// Integer, float, or boolean constants.
-#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, From)]
+#[derive(Debug, Copy, Clone, PartialOrd, From)]
 pub enum Scalar {
     Integer(IntegerConstant),
     Float(FloatingConstant),
     Boolean(BooleanConstant),
 }
 
+impl PartialEq for Scalar {
+    /// Like the derived impl, except that `nan` is equal to `nan`, so a
+    /// schema with a `nan` default is equal to itself.
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Scalar::Integer(a), Scalar::Integer(b)) => a == b,
+            (Scalar::Float(a), Scalar::Float(b)) => a == b || (a.is_nan() && b.is_nan()),
+            (Scalar::Boolean(a), Scalar::Boolean(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
 /// JSON-like values.
 #[derive(Debug, Clone, PartialEq, From, TypedBuilder)]
 pub struct Object<'a> {
@@ -382,7 +779,17 @@ pub struct DottedIdent<'a> {
     pub parts: Vec<Ident<'a>>,
 }
 
+impl<'a> From<&'a str> for DottedIdent<'a> {
+    /// Split a name like `my.ns.Monster` into its parts.
+    fn from(name: &'a str) -> Self {
+        name.split('.').map(Ident::from).collect::<Vec<_>>().into()
+    }
+}
+
 /// A documentation comment.
+///
+/// Its lines are the text after the `///` of each line of the comment, so
+/// they usually start with a space, e.g. `" A monster."`.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default, From, TypedBuilder)]
 pub struct Comment<'a> {
     #[builder(default)]
@@ -431,4 +838,17 @@ mod type_tests {
         assert!(!Type::Ident(vec!["foobar".into()].into()).is_scalar());
         assert!(!Type::Array(Box::new(Type::Byte)).is_scalar());
     }
+
+    #[test]
+    fn test_scalar_size() {
+        assert_eq!(Type::Bool.scalar_size(), Some(1));
+        assert_eq!(Type::UShort.scalar_size(), Some(2));
+        assert_eq!(Type::Float32.scalar_size(), Some(4));
+        assert_eq!(Type::ULong.scalar_size(), Some(8));
+        assert_eq!(Type::String.scalar_size(), None);
+        assert_eq!(
+            Type::Ident(vec!["foobar".into()].into()).scalar_size(),
+            None
+        );
+    }
 }
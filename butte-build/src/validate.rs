@@ -0,0 +1,1237 @@
+//! Semantic checks of a parsed schema, run before generating code for it.
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    codegen::{enum_discriminants, enum_values, union_discriminants, Declaration, SymbolTable},
+    types::*,
+};
+
+/// The largest alignment that the `force_align` attribute of a struct may
+/// request, the same as flatc's.
+const MAX_ALIGNMENT: usize = 32;
+
+/// The largest field id, such that the offset of the field's vtable slot fits
+/// in a `VOffsetT`.
+const MAX_FIELD_ID: i16 = butte::VOffsetT::MAX / 2 - 2;
+
+/// A semantic error in a schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaError<'a> {
+    pub message: String,
+
+    /// The text in the schema that the error refers to. It is a slice of the
+    /// schema text, which allows locating the error in its source.
+    pub at: &'a str,
+}
+
+impl<'a> SchemaError<'a> {
+    fn new(message: impl Into<String>, at: &'a str) -> Self {
+        Self {
+            message: message.into(),
+            at,
+        }
+    }
+}
+
+/// Check that every type referenced in `schema` is declared and used in a
+/// valid way, and that no name is declared twice.
+///
+/// All errors are returned, in the order they occur in the schema. The
+/// alignment of structs is only checked once everything else is valid, as it
+/// depends on the layout of their fields.
+pub fn validate<'a>(schema: &Schema<'a>) -> Vec<SchemaError<'a>> {
+    let symbols = SymbolTable::new(&schema.elements);
+    let mut validator = Validator {
+        symbols: &symbols,
+        namespace: vec![],
+        declared: HashSet::new(),
+        errors: vec![],
+    };
+    for element in &schema.elements {
+        validator.element(element);
+    }
+    if validator.errors.is_empty() {
+        for element in &schema.elements {
+            match element {
+                Element::Namespace(Namespace { ident, .. }) => {
+                    validator.namespace = ident.parts.iter().map(|part| part.raw).collect();
+                }
+                Element::Struct(s) => validator.force_align(s),
+                _ => {}
+            }
+        }
+    }
+    validator.errors
+}
+
+struct Validator<'a, 'b> {
+    symbols: &'b SymbolTable<'a>,
+    namespace: Vec<&'a str>,
+    declared: HashSet<Vec<&'a str>>,
+    errors: Vec<SchemaError<'a>>,
+}
+
+impl<'a> Validator<'a, '_> {
+    fn error(&mut self, message: impl Into<String>, at: &'a str) {
+        self.errors.push(SchemaError::new(message, at));
+    }
+
+    fn element(&mut self, element: &Element<'a>) {
+        match element {
+            Element::Namespace(Namespace { ident, .. }) => {
+                self.namespace = ident.parts.iter().map(|part| part.raw).collect();
+            }
+            Element::Table(t) => {
+                self.declaration(t.id);
+                self.attributes(&t.metadata);
+                self.fields(&t.fields);
+                for field in &t.fields {
+                    self.table_field(field);
+                }
+                self.field_ids(t);
+            }
+            Element::Struct(s) => {
+                self.declaration(s.id);
+                self.attributes(&s.metadata);
+                self.fields(&s.fields);
+                self.struct_(s);
+            }
+            Element::Enum(e) => {
+                self.declaration(e.id);
+                self.attributes(&e.metadata);
+                self.enum_(e);
+            }
+            Element::Union(u) => {
+                self.declaration(u.id);
+                self.attributes(&u.metadata);
+                self.union(u);
+            }
+            Element::Root(Root { typename, .. }) => {
+                let id = DottedIdent::from(vec![*typename]);
+                match self.resolve(&id) {
+                    Some(Declaration::Table) => {}
+                    Some(_) => self.error(
+                        format!("root type `{}` is not a table", typename.raw),
+                        typename.raw,
+                    ),
+                    None => self.error(format!("unknown type `{}`", typename.raw), typename.raw),
+                }
+            }
+            Element::Rpc(rpc) => {
+                for method in &rpc.methods {
+                    self.attributes(&method.metadata);
+                    if method.streaming().is_none() {
+                        self.error(
+                            format!(
+                                "the streaming mode of method `{}` must be one of \"none\", \"client\", \"server\" or \"bidi\"",
+                                method.id.raw
+                            ),
+                            method.id.raw,
+                        );
+                    }
+                    if method.idempotency_level().is_none() {
+                        self.error(
+                            format!(
+                                "the idempotency level of method `{}` must be one of \"idempotency_unknown\", \"no_side_effects\" or \"idempotent\"",
+                                method.id.raw
+                            ),
+                            method.id.raw,
+                        );
+                    }
+                    for ty in &[&method.request_type, &method.response_type] {
+                        let ty = Type::Ident((*ty).clone());
+                        match self.field_type(&ty) {
+                            Some(Declaration::Table) | None => {}
+                            Some(_) => self.error(
+                                format!(
+                                    "the request and response types of method `{}` must be tables",
+                                    method.id.raw
+                                ),
+                                method.id.raw,
+                            ),
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn resolve(&self, id: &DottedIdent<'a>) -> Option<&Declaration<'a>> {
+        self.symbols
+            .resolve(&self.namespace, id)
+            .map(|(_, declaration)| declaration)
+    }
+
+    /// Check that a type named `id` is declared only once in its namespace.
+    fn declaration(&mut self, id: Ident<'a>) {
+        let mut name = self.namespace.clone();
+        name.push(id.raw);
+        if !self.declared.insert(name) {
+            self.error(format!("`{}` is declared more than once", id.raw), id.raw);
+        }
+    }
+
+    /// Check that the attributes in `metadata` are either built in or
+    /// declared by the schema.
+    fn attributes(&mut self, metadata: &Option<Metadata<'a>>) {
+        let metadata = match metadata {
+            Some(metadata) => metadata,
+            None => return,
+        };
+        for (name, _) in metadata.custom_attributes() {
+            if !self.symbols.attributes().contains(&name) {
+                self.error(
+                    format!(
+                        "unknown attribute `{}`, user-defined attributes must be declared with `attribute \"{}\";`",
+                        name, name
+                    ),
+                    name,
+                );
+            }
+        }
+    }
+
+    /// Check that no two fields have the same name.
+    fn fields(&mut self, fields: &[Field<'a>]) {
+        let mut names = HashSet::new();
+        for field in fields {
+            self.attributes(&field.metadata);
+            if !names.insert(field.id.raw) {
+                self.error(
+                    format!("field `{}` is declared more than once", field.id.raw),
+                    field.id.raw,
+                );
+            }
+        }
+    }
+
+    /// Check that either all fields of a table have an `id` attribute or none
+    /// do, and that the ids number the vtable slots from 0 with no gaps, like
+    /// flatc requires. The discriminant of a union field, like the vector of
+    /// discriminants of a vector of unions, takes up the id before the
+    /// field's own.
+    fn field_ids(&mut self, t: &Table<'a>) {
+        let with_id = t
+            .fields
+            .iter()
+            .filter(|field| field.has_attribute("id"))
+            .count();
+        if with_id == 0 {
+            return;
+        }
+        if with_id < t.fields.len() {
+            return self.error(
+                format!(
+                    "either all fields of table `{}` must have an `id` attribute or none",
+                    t.id.raw
+                ),
+                t.id.raw,
+            );
+        }
+
+        let mut slots = HashMap::new();
+        for field in &t.fields {
+            let id = match field.id_attribute() {
+                Some(id) if (0..=IntegerConstant::from(MAX_FIELD_ID)).contains(&id) => id,
+                _ => {
+                    self.error(
+                        format!(
+                            "the `id` attribute of field `{}` must be an integer from 0 to {}",
+                            field.id.raw, MAX_FIELD_ID
+                        ),
+                        field.id.raw,
+                    );
+                    continue;
+                }
+            };
+            let is_union = match &field.ty {
+                Type::Ident(ty) => matches!(self.resolve(ty), Some(Declaration::Union(_))),
+                Type::Array(element) => match element.as_ref() {
+                    Type::Ident(ty) => matches!(self.resolve(ty), Some(Declaration::Union(_))),
+                    _ => false,
+                },
+                _ => false,
+            };
+            let ids = if is_union {
+                if id == 0 {
+                    self.error(
+                        format!(
+                            "the id of union field `{}` must be at least 1, as its type takes up the id before it",
+                            field.id.raw
+                        ),
+                        field.id.raw,
+                    );
+                    continue;
+                }
+                vec![id - 1, id]
+            } else {
+                vec![id]
+            };
+            for id in ids {
+                if let Some(other) = slots.insert(id, field.id.raw) {
+                    self.error(
+                        format!(
+                            "fields `{}` and `{}` both have the id {}",
+                            other, field.id.raw, id
+                        ),
+                        field.id.raw,
+                    );
+                }
+            }
+        }
+        if let Some(missing) =
+            (0..slots.len() as IntegerConstant).find(|id| !slots.contains_key(id))
+        {
+            self.error(
+                format!(
+                    "no field of table `{}` has the id {}, ids must be consecutive from 0",
+                    t.id.raw, missing
+                ),
+                t.id.raw,
+            );
+        }
+    }
+
+    /// Resolve the user-defined type `ty` refers to, reporting an error if it
+    /// is not declared.
+    fn field_type(&mut self, ty: &Type<'a>) -> Option<Declaration<'a>> {
+        match ty {
+            Type::Ident(id) => {
+                let declaration = self.resolve(id).cloned();
+                if declaration.is_none() {
+                    let name = id.parts.iter().map(|part| part.raw).collect::<Vec<_>>();
+                    self.error(format!("unknown type `{}`", name.join(".")), last_part(id));
+                }
+                declaration
+            }
+            _ => None,
+        }
+    }
+
+    fn table_field(&mut self, field: &Field<'a>) {
+        if field.has_attribute("nested_flatbuffer") {
+            self.nested_flatbuffer(field);
+        }
+        if field.is_flexbuffer() {
+            self.byte_vector(field, "flexbuffer");
+        }
+        if field.is_offset64() {
+            self.offset64(field);
+        }
+        if field.has_attribute("hash") {
+            self.hash(field);
+        }
+        let declaration = match &field.ty {
+            Type::Array(element) => match element.as_ref() {
+                Type::Array(_) => {
+                    self.error("vectors of vectors are not supported", field.id.raw);
+                    None
+                }
+                Type::FixedArray(..) => {
+                    self.error(
+                        "fixed-size arrays are only supported in structs",
+                        field.id.raw,
+                    );
+                    None
+                }
+                element => {
+                    self.field_type(element);
+                    None
+                }
+            },
+            Type::FixedArray(..) => {
+                self.error(
+                    "fixed-size arrays are only supported in structs",
+                    field.id.raw,
+                );
+                None
+            }
+            ty => self.field_type(ty),
+        };
+
+        let default = match &field.default {
+            Some(default) => default,
+            None => return,
+        };
+        match (default, &declaration) {
+            (DefaultValue::Null, None) if field.ty.is_scalar() => {}
+            (DefaultValue::Scalar(Scalar::Integer(value)), None) if field.ty.is_scalar() => {
+                if let Some((min, max)) = integer_range(&field.ty) {
+                    if *value < min || *value > max {
+                        self.error(
+                            format!(
+                                "default value {} of field `{}` does not fit in its type",
+                                value, field.id.raw
+                            ),
+                            field.id.raw,
+                        );
+                    }
+                }
+            }
+            (DefaultValue::Scalar(Scalar::Float(value)), None)
+                if field.ty.is_scalar() && !field.ty.is_float() =>
+            {
+                self.error(
+                    format!(
+                        "default value {} of field `{}` is not an integer",
+                        value, field.id.raw
+                    ),
+                    field.id.raw,
+                );
+            }
+            (DefaultValue::Scalar(_), None) if field.ty.is_scalar() => {}
+            (DefaultValue::Null, Some(Declaration::Enum(_))) => {}
+            (DefaultValue::EnumVal(value), Some(Declaration::Enum(e))) => {
+                if !e.values.iter().any(|v| v.id == *value) {
+                    self.error(
+                        format!("`{}` is not a value of enum `{}`", value.raw, e.id.raw),
+                        value.raw,
+                    );
+                }
+            }
+            (DefaultValue::Scalar(Scalar::Integer(value)), Some(Declaration::Enum(e)))
+                if e.is_bit_flags() =>
+            {
+                let all = enum_values(e).iter().fold(0, |all, (_, v)| all | v);
+                if value & !all != 0 {
+                    self.error(
+                        format!(
+                            "{} is not a combination of the flags of `{}`",
+                            value, e.id.raw
+                        ),
+                        field.id.raw,
+                    );
+                }
+            }
+            (DefaultValue::Scalar(Scalar::Integer(value)), Some(Declaration::Enum(e))) => {
+                if !enum_discriminants(&e.values)
+                    .iter()
+                    .any(|(_, v)| v == value)
+                {
+                    self.error(
+                        format!("{} is not a value of enum `{}`", value, e.id.raw),
+                        field.id.raw,
+                    );
+                }
+            }
+            (DefaultValue::EnumVal(value), _) => self.error(
+                format!("field `{}` is not an enum", field.id.raw),
+                value.raw,
+            ),
+            _ => self.error(
+                format!("invalid default value for field `{}`", field.id.raw),
+                field.id.raw,
+            ),
+        }
+    }
+
+    /// Check that a field with the `offset64` or `vector64` attribute is a
+    /// string or a vector of inline values, which is what can be placed past
+    /// the 32-bit part of a buffer.
+    fn offset64(&mut self, field: &Field<'a>) {
+        let valid = match &field.ty {
+            Type::String if !field.is_vector64() => true,
+            Type::Array(element) => match element.as_ref() {
+                Type::Ident(id) => matches!(
+                    self.resolve(id),
+                    Some(Declaration::Struct(_)) | Some(Declaration::Enum(_))
+                ),
+                element => element.is_scalar(),
+            },
+            _ => false,
+        };
+        if !valid {
+            let attribute = if field.is_vector64() {
+                "vector64"
+            } else {
+                "offset64"
+            };
+            self.error(
+                format!(
+                    "the `{}` attribute of field `{}` requires a vector of scalars, enums or structs{}",
+                    attribute,
+                    field.id.raw,
+                    if field.is_vector64() { "" } else { " or a string" }
+                ),
+                field.id.raw,
+            );
+        }
+    }
+
+    /// Check that a field holding a whole buffer, of the kind `what`, is a
+    /// vector of bytes.
+    fn byte_vector(&mut self, field: &Field<'a>, what: &str) {
+        if !matches!(&field.ty, Type::Array(element) if matches!(**element, Type::UByte | Type::UInt8))
+        {
+            self.error(
+                format!("{} field `{}` must be a `[ubyte]`", what, field.id.raw),
+                field.id.raw,
+            );
+        }
+    }
+
+    /// Check that a field with the `hash` attribute names a known algorithm
+    /// and is an integer as wide as its hashes.
+    fn hash(&mut self, field: &Field<'a>) {
+        let algorithm = match field.hash() {
+            Some(algorithm) => algorithm,
+            None => {
+                return self.error(
+                    format!(
+                        "the `hash` attribute of field `{}` must be one of `fnv1_32`, `fnv1a_32`, `fnv1_64` or `fnv1a_64`",
+                        field.id.raw
+                    ),
+                    field.id.raw,
+                )
+            }
+        };
+        let valid = match algorithm.bits() {
+            32 => matches!(
+                field.ty,
+                Type::Int | Type::UInt | Type::Int32 | Type::UInt32
+            ),
+            _ => matches!(
+                field.ty,
+                Type::Long | Type::ULong | Type::Int64 | Type::UInt64
+            ),
+        };
+        if !valid {
+            self.error(
+                format!(
+                    "field `{}` with the `{}` hash must be a {}-bit integer",
+                    field.id.raw,
+                    algorithm.name(),
+                    algorithm.bits()
+                ),
+                field.id.raw,
+            );
+        }
+    }
+
+    /// Check that a field with the `nested_flatbuffer` attribute holds bytes
+    /// and names the table at the root of the nested buffer.
+    fn nested_flatbuffer(&mut self, field: &Field<'a>) {
+        self.byte_vector(field, "nested flatbuffer");
+        let name = match field.nested_flatbuffer() {
+            Some(name) => name,
+            None => {
+                return self.error(
+                    format!(
+                        "the `nested_flatbuffer` attribute of field `{}` must name a table",
+                        field.id.raw
+                    ),
+                    field.id.raw,
+                )
+            }
+        };
+        let id = DottedIdent::from(name.split('.').map(Ident::from).collect::<Vec<_>>());
+        match self.resolve(&id) {
+            Some(Declaration::Table) => {}
+            Some(_) => self.error(format!("`{}` is not a table", name), name),
+            None => self.error(format!("unknown type `{}`", name), name),
+        }
+    }
+
+    /// Check that the `force_align` attribute of a struct is a power of two,
+    /// no smaller than the struct's natural alignment, like flatc does.
+    fn force_align(&mut self, s: &Struct<'a>) {
+        let has_force_align = s
+            .metadata
+            .as_ref()
+            .is_some_and(|metadata| metadata.values.contains_key(&Ident::from("force_align")));
+        if !has_force_align {
+            return;
+        }
+        let natural = Struct {
+            metadata: None,
+            ..s.clone()
+        };
+        let natural_align = self.symbols.struct_layout(&self.namespace, &natural).align;
+        let valid = s.force_align().is_some_and(|align| {
+            align.is_power_of_two() && align >= natural_align && align <= MAX_ALIGNMENT
+        });
+        if !valid {
+            self.error(
+                format!(
+                    "`force_align` of struct `{}` must be a power of two from {} to {}",
+                    s.id.raw, natural_align, MAX_ALIGNMENT
+                ),
+                s.id.raw,
+            );
+        }
+    }
+
+    fn struct_(&mut self, s: &Struct<'a>) {
+        for field in &s.fields {
+            if field.default.is_some() {
+                self.error("struct fields cannot have default values", field.id.raw);
+            }
+            let ty = match &field.ty {
+                Type::FixedArray(element, len) => {
+                    if !(1..=IntegerConstant::from(u16::MAX)).contains(len) {
+                        self.error(
+                            format!(
+                                "the length of array `{}` must be from 1 to {}",
+                                field.id.raw,
+                                u16::MAX
+                            ),
+                            field.id.raw,
+                        );
+                    }
+                    element.as_ref()
+                }
+                ty => ty,
+            };
+            let valid = match ty {
+                Type::String | Type::Array(_) | Type::FixedArray(..) => false,
+                ty => !matches!(
+                    self.field_type(ty),
+                    Some(Declaration::Table) | Some(Declaration::Union(_))
+                ),
+            };
+            if !valid {
+                self.error(
+                    "struct fields must be scalars, enums or structs",
+                    field.id.raw,
+                );
+            }
+        }
+
+        let mut name = self.namespace.clone();
+        name.push(s.id.raw);
+        if struct_contains(self.symbols, &name, s, &name, &mut HashSet::new()) {
+            self.error(format!("struct `{}` contains itself", s.id.raw), s.id.raw);
+        }
+    }
+
+    fn enum_(&mut self, e: &Enum<'a>) {
+        let range = match integer_range(&e.base_type) {
+            Some(range) => Some(range),
+            None => {
+                self.error(
+                    format!(
+                        "the base type of enum `{}` must be an integer type",
+                        e.id.raw
+                    ),
+                    e.id.raw,
+                );
+                None
+            }
+        };
+        let mut names = HashSet::new();
+        let mut values = HashMap::new();
+        for (id, value) in enum_discriminants(&e.values) {
+            if !names.insert(id.raw) {
+                self.error(
+                    format!("enum value `{}` is declared more than once", id.raw),
+                    id.raw,
+                );
+            }
+            if let Some(other) = values.insert(value, id.raw) {
+                self.error(
+                    format!(
+                        "enum values `{}` and `{}` are both {}",
+                        other, id.raw, value
+                    ),
+                    id.raw,
+                );
+            }
+            if e.is_bit_flags() {
+                let fits =
+                    (0..64).contains(&value) && range.is_none_or(|(_, max)| 1i128 << value <= max);
+                if !fits {
+                    self.error(
+                        format!(
+                            "bit flag `{}` does not fit in the base type of `{}`",
+                            id.raw, e.id.raw
+                        ),
+                        id.raw,
+                    );
+                }
+            } else if let Some((min, max)) = range {
+                if value < min || value > max {
+                    self.error(
+                        format!(
+                            "enum value `{}` does not fit in the base type of `{}`",
+                            id.raw, e.id.raw
+                        ),
+                        id.raw,
+                    );
+                }
+            }
+        }
+    }
+
+    fn union(&mut self, u: &Union<'a>) {
+        let mut names = HashSet::new();
+        for value in &u.values {
+            let id = value.id;
+            if !names.insert(id.raw) {
+                self.error(
+                    format!("union variant `{}` is declared more than once", id.raw),
+                    id.raw,
+                );
+            }
+            let ty = value.union_type();
+            let name = ty
+                .parts
+                .iter()
+                .map(|part| part.raw)
+                .collect::<Vec<_>>()
+                .join(".");
+            let span = last_part(&ty);
+            match self.resolve(&ty) {
+                Some(Declaration::Table) => {}
+                Some(_) => self.error(format!("union variant `{}` is not a table", name), span),
+                None => self.error(format!("unknown type `{}`", name), span),
+            }
+        }
+        let mut values = HashMap::new();
+        for (id, value) in union_discriminants(&u.values) {
+            if let Some(other) = values.insert(value, id.raw) {
+                self.error(
+                    format!(
+                        "union variants `{}` and `{}` are both {}",
+                        other, id.raw, value
+                    ),
+                    id.raw,
+                );
+            }
+            if !(1..=255).contains(&value) {
+                self.error(
+                    format!(
+                        "the value of union variant `{}` must be between 1 and 255",
+                        id.raw
+                    ),
+                    id.raw,
+                );
+            }
+        }
+    }
+}
+
+/// Check whether the struct `s` named `name` contains the struct named
+/// `target`, directly or through other structs.
+fn struct_contains<'a>(
+    symbols: &SymbolTable<'a>,
+    name: &[&'a str],
+    s: &Struct<'a>,
+    target: &[&'a str],
+    visited: &mut HashSet<Vec<&'a str>>,
+) -> bool {
+    let namespace = &name[..name.len() - 1];
+    s.fields.iter().any(|field| {
+        let ty = match &field.ty {
+            Type::FixedArray(element, _) => element.as_ref(),
+            ty => ty,
+        };
+        match ty {
+            Type::Ident(id) => match symbols.resolve(namespace, id) {
+                Some((inner_name, Declaration::Struct(inner))) => {
+                    inner_name == target
+                        || (visited.insert(inner_name.to_vec())
+                            && struct_contains(symbols, inner_name, inner, target, visited))
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    })
+}
+
+fn last_part<'a>(id: &DottedIdent<'a>) -> &'a str {
+    id.parts.last().map_or("", |part| part.raw)
+}
+
+/// The range of values of an integer type.
+fn integer_range(ty: &Type) -> Option<(IntegerConstant, IntegerConstant)> {
+    let range = match ty {
+        Type::Byte | Type::Int8 => (i8::MIN.into(), i8::MAX.into()),
+        Type::UByte | Type::UInt8 => (0, u8::MAX.into()),
+        Type::Short | Type::Int16 => (i16::MIN.into(), i16::MAX.into()),
+        Type::UShort | Type::UInt16 => (0, u16::MAX.into()),
+        Type::Int | Type::Int32 => (i32::MIN.into(), i32::MAX.into()),
+        Type::UInt | Type::UInt32 => (0, u32::MAX.into()),
+        Type::Long | Type::Int64 => (i64::MIN.into(), i64::MAX.into()),
+        Type::ULong | Type::UInt64 => (0, u64::MAX.into()),
+        _ => return None,
+    };
+    Some(range)
+}
+
+#[cfg(test)]
+mod validate_tests {
+    use super::*;
+    use crate::parser::schema_decl;
+
+    fn errors(input: &str) -> Vec<(String, &str)> {
+        let (rest, schema) = schema_decl(input).unwrap();
+        assert_eq!(rest.trim(), "");
+        validate(&schema)
+            .into_iter()
+            .map(|error| (error.message, error.at))
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_schema() {
+        let input = "\
+namespace a;
+enum Color : ubyte { Red, Green }
+struct Vec2 { x: float; y: float; }
+table Monster { pos: Vec2; color: Color = Green; friends: [Monster]; }
+union Any { Monster }
+namespace a.b;
+table Holder { any: Any; monster: a.Monster; }
+root_type Holder;
+";
+        assert_eq!(errors(input), vec![]);
+    }
+
+    #[test]
+    fn test_unknown_types() {
+        let input = "\
+table A { x: Colr; y: [Itme]; }
+root_type B;
+";
+        assert_eq!(
+            errors(input),
+            vec![
+                ("unknown type `Colr`".to_string(), "Colr"),
+                ("unknown type `Itme`".to_string(), "Itme"),
+                ("unknown type `B`".to_string(), "B"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let input = "\
+table A { x: int; x: short; }
+table A { y: int; }
+enum E : byte { X, Y, X, Z = 1 }
+";
+        assert_eq!(
+            errors(input),
+            vec![
+                ("field `x` is declared more than once".to_string(), "x"),
+                ("`A` is declared more than once".to_string(), "A"),
+                ("enum value `X` is declared more than once".to_string(), "X"),
+                ("enum values `Y` and `Z` are both 1".to_string(), "Z"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_enums() {
+        let input = "\
+enum E : float { X }
+enum F : ubyte { Y = 256 }
+table T { e: F = Z; f: F = 3; g: int = Y; }
+";
+        assert_eq!(
+            errors(input),
+            vec![
+                (
+                    "the base type of enum `E` must be an integer type".to_string(),
+                    "E"
+                ),
+                (
+                    "enum value `Y` does not fit in the base type of `F`".to_string(),
+                    "Y"
+                ),
+                ("`Z` is not a value of enum `F`".to_string(), "Z"),
+                ("3 is not a value of enum `F`".to_string(), "f"),
+                ("field `g` is not an enum".to_string(), "Y"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_integer_defaults() {
+        let input = "\
+enum E : ulong { Big = 0xFFFFFFFFFFFFFFFF, Bigger = 0x10000000000000000 }
+table T {
+  a: ulong = 18446744073709551615;
+  b: long = -9223372036854775808;
+  c: ubyte = 256;
+  d: ulong = -1;
+  e: long = 0x8000000000000000;
+  f: int = nan;
+  g: float = 0xFF;
+}
+";
+        assert_eq!(
+            errors(input),
+            vec![
+                (
+                    "enum value `Bigger` does not fit in the base type of `E`".to_string(),
+                    "Bigger"
+                ),
+                (
+                    "default value 256 of field `c` does not fit in its type".to_string(),
+                    "c"
+                ),
+                (
+                    "default value -1 of field `d` does not fit in its type".to_string(),
+                    "d"
+                ),
+                (
+                    "default value 9223372036854775808 of field `e` does not fit in its type"
+                        .to_string(),
+                    "e"
+                ),
+                (
+                    "default value NaN of field `f` is not an integer".to_string(),
+                    "f"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bit_flags() {
+        let input = "\
+enum E : byte (bit_flags) { A, B = 7 }
+enum F : ulong (bit_flags) { C = 63, D = 64 }
+table T { e: E = 2; f: E = 1; }
+";
+        assert_eq!(
+            errors(input),
+            vec![
+                (
+                    "bit flag `B` does not fit in the base type of `E`".to_string(),
+                    "B"
+                ),
+                (
+                    "bit flag `D` does not fit in the base type of `F`".to_string(),
+                    "D"
+                ),
+                (
+                    "2 is not a combination of the flags of `E`".to_string(),
+                    "e"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_structs() {
+        let input = "\
+table T { x: int; }
+struct S { name: string; t: T; n: int = 1; }
+struct P { q: Q; }
+struct Q { p: P; }
+";
+        assert_eq!(
+            errors(input),
+            vec![
+                (
+                    "struct fields must be scalars, enums or structs".to_string(),
+                    "name"
+                ),
+                (
+                    "struct fields must be scalars, enums or structs".to_string(),
+                    "t"
+                ),
+                ("struct fields cannot have default values".to_string(), "n"),
+                ("struct `P` contains itself".to_string(), "P"),
+                ("struct `Q` contains itself".to_string(), "Q"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fixed_arrays() {
+        let input = "\
+struct V { x: [float:3]; }
+struct S { v: [V:2]; names: [string:2]; empty: [int:0]; }
+struct R { r: [R:2]; }
+table T { v: [int:2]; w: [[int:2]]; }
+";
+        assert_eq!(
+            errors(input),
+            vec![
+                (
+                    "struct fields must be scalars, enums or structs".to_string(),
+                    "names"
+                ),
+                (
+                    "the length of array `empty` must be from 1 to 65535".to_string(),
+                    "empty"
+                ),
+                ("struct `R` contains itself".to_string(), "R"),
+                (
+                    "fixed-size arrays are only supported in structs".to_string(),
+                    "v"
+                ),
+                (
+                    "fixed-size arrays are only supported in structs".to_string(),
+                    "w"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rpc_types() {
+        let input = "\
+struct S { x: int; }
+table T { x: int; }
+rpc_service Service {
+  Good(T):T;
+  Bad(S):T;
+  Missing(T):U;
+  Streaming(T):T (streaming: \"both\");
+  Idempotent(T):T (idempotency_level: \"safe\", deprecated);
+}
+";
+        assert_eq!(
+            errors(input),
+            vec![
+                (
+                    "the request and response types of method `Bad` must be tables".to_string(),
+                    "Bad"
+                ),
+                ("unknown type `U`".to_string(), "U"),
+                (
+                    "the streaming mode of method `Streaming` must be one of \"none\", \"client\", \"server\" or \"bidi\"".to_string(),
+                    "Streaming"
+                ),
+                (
+                    "the idempotency level of method `Idempotent` must be one of \"idempotency_unknown\", \"no_side_effects\" or \"idempotent\"".to_string(),
+                    "Idempotent"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unions_and_roots() {
+        let input = "\
+struct S { x: int; }
+table T { x: int; }
+union U { T, S, T }
+table V { u: [U]; s: string = 1; }
+root_type S;
+";
+        assert_eq!(
+            errors(input),
+            vec![
+                ("union variant `S` is not a table".to_string(), "S"),
+                (
+                    "union variant `T` is declared more than once".to_string(),
+                    "T"
+                ),
+                ("invalid default value for field `s`".to_string(), "s"),
+                ("root type `S` is not a table".to_string(), "S"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_union_values() {
+        let input = "\
+struct S { x: int; }
+table T { x: int; }
+union U { A: T = 1, B: T = 1, C: S, D: a.W, E: T = 300 }
+";
+        assert_eq!(
+            errors(input),
+            vec![
+                ("union variant `S` is not a table".to_string(), "S"),
+                ("unknown type `a.W`".to_string(), "W"),
+                ("union variants `A` and `B` are both 1".to_string(), "B"),
+                (
+                    "the value of union variant `E` must be between 1 and 255".to_string(),
+                    "E"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nested_flatbuffers() {
+        let input = "\
+struct S { x: int; }
+table T { a: [ubyte] (nested_flatbuffer: \"T\"); b: [int] (nested_flatbuffer: \"T\"); }
+table U { c: [ubyte] (nested_flatbuffer: \"S\"); d: [ubyte] (nested_flatbuffer: \"W\"); }
+table V { e: [ubyte] (nested_flatbuffer); }
+";
+        assert_eq!(
+            errors(input),
+            vec![
+                (
+                    "nested flatbuffer field `b` must be a `[ubyte]`".to_string(),
+                    "b"
+                ),
+                ("`S` is not a table".to_string(), "S"),
+                ("unknown type `W`".to_string(), "W"),
+                (
+                    "the `nested_flatbuffer` attribute of field `e` must name a table".to_string(),
+                    "e"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_custom_attributes() {
+        let input = "\
+attribute \"priority\";
+attribute internal;
+table T (internal) { a: int (priority: 1); b: int (prio, deprecated); }
+struct S (packed) { x: int; }
+enum E : byte (internal) { A }
+";
+        assert_eq!(
+            errors(input),
+            vec![
+                (
+                    "unknown attribute `prio`, user-defined attributes must be declared with `attribute \"prio\";`".to_string(),
+                    "prio"
+                ),
+                (
+                    "unknown attribute `packed`, user-defined attributes must be declared with `attribute \"packed\";`".to_string(),
+                    "packed"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hashes() {
+        let input = "\
+table T {
+  a: uint (hash: \"fnv1a_32\");
+  b: long (hash: \"fnv1_64\");
+  c: int (hash: \"fnv1_64\");
+  d: string (hash: \"fnv1a_32\");
+  e: uint (hash: \"md5\");
+}
+";
+        assert_eq!(
+            errors(input),
+            vec![
+                (
+                    "field `c` with the `fnv1_64` hash must be a 64-bit integer".to_string(),
+                    "c"
+                ),
+                (
+                    "field `d` with the `fnv1a_32` hash must be a 32-bit integer".to_string(),
+                    "d"
+                ),
+                (
+                    "the `hash` attribute of field `e` must be one of `fnv1_32`, `fnv1a_32`, `fnv1_64` or `fnv1a_64`".to_string(),
+                    "e"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_flexbuffers() {
+        let input = "table T { a: [ubyte] (flexbuffer); b: string (flexbuffer); }";
+        assert_eq!(
+            errors(input),
+            vec![("flexbuffer field `b` must be a `[ubyte]`".to_string(), "b")]
+        );
+    }
+
+    #[test]
+    fn test_offset64() {
+        let input = "\
+struct S { x: int; }
+table T { a: [ubyte] (vector64); b: string (offset64); c: [S] (offset64); }
+table U { d: string (vector64); e: T (offset64); f: [string] (offset64); g: int (offset64); }
+";
+        assert_eq!(
+            errors(input),
+            vec![
+                (
+                    "the `vector64` attribute of field `d` requires a vector of scalars, enums or structs".to_string(),
+                    "d"
+                ),
+                (
+                    "the `offset64` attribute of field `e` requires a vector of scalars, enums or structs or a string".to_string(),
+                    "e"
+                ),
+                (
+                    "the `offset64` attribute of field `f` requires a vector of scalars, enums or structs or a string".to_string(),
+                    "f"
+                ),
+                (
+                    "the `offset64` attribute of field `g` requires a vector of scalars, enums or structs or a string".to_string(),
+                    "g"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_field_ids() {
+        let input = "\
+union U { A }
+table A { x: int (id: 1); u: U (id: 3); y: int (id: 0); }
+table B { x: int (id: 0); y: int; }
+table C { x: int (id: 0); y: int (id: 0); z: int (id: 3); }
+table D { u: U (id: 0); x: int (id: -1); y: int (id: \"1\"); }
+table E { v: [U] (id: 2); x: int (id: 1); y: int (id: 0); }
+";
+        assert_eq!(
+            errors(input),
+            vec![
+                (
+                    "either all fields of table `B` must have an `id` attribute or none".to_string(),
+                    "B"
+                ),
+                ("fields `x` and `y` both have the id 0".to_string(), "y"),
+                (
+                    "no field of table `C` has the id 1, ids must be consecutive from 0".to_string(),
+                    "C"
+                ),
+                (
+                    "the id of union field `u` must be at least 1, as its type takes up the id before it"
+                        .to_string(),
+                    "u"
+                ),
+                (
+                    "the `id` attribute of field `x` must be an integer from 0 to 16381".to_string(),
+                    "x"
+                ),
+                (
+                    "the `id` attribute of field `y` must be an integer from 0 to 16381".to_string(),
+                    "y"
+                ),
+                ("fields `v` and `x` both have the id 1".to_string(), "x"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_force_align() {
+        let input = "\
+struct A (force_align: 16) { x: float; y: float; z: float; }
+struct B (force_align: 2) { x: double; }
+struct C (force_align: 12) { x: int; }
+struct D (force_align: 64) { x: int; }
+struct E (force_align: \"8\") { x: int; }
+";
+        let message = |name: &str, natural: usize| {
+            format!(
+                "`force_align` of struct `{}` must be a power of two from {} to 32",
+                name, natural
+            )
+        };
+        assert_eq!(
+            errors(input),
+            vec![
+                (message("B", 8), "B"),
+                (message("C", 4), "C"),
+                (message("D", 4), "D"),
+                (message("E", 4), "E"),
+            ]
+        );
+    }
+}
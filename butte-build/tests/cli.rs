@@ -0,0 +1,176 @@
+use std::{
+    fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+fn butte() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_butte"))
+}
+
+/// An empty directory for the output of a test.
+fn out_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("butte-cli-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn test_compile() {
+    let schemas = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../butte-examples/fbs");
+    let out = out_dir("compile");
+    let status = butte()
+        .arg("compile")
+        .arg("--out")
+        .arg(&out)
+        .arg("--include")
+        .arg(schemas.join("shared"))
+        .args([
+            "--derive",
+            "Hash",
+            "--object-api",
+            "--object-derive",
+            "Hash",
+        ])
+        .arg("--ugly")
+        .arg(schemas.join("scalars/scalars.fbs"))
+        .arg(schemas.join("includes/includes.fbs"))
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let scalars = fs::read_to_string(out.join("scalars.rs")).unwrap();
+    assert!(scalars.contains("pub struct Settings"));
+    assert!(scalars.contains("pub struct SettingsT"));
+    assert!(scalars.contains("Ord , Hash , Debug)]"));
+    assert!(scalars.contains("# [derive (Debug , Clone , PartialEq , Hash)] pub struct SettingsT"));
+    assert!(out.join("includes.rs").exists());
+    fs::remove_dir_all(&out).unwrap();
+}
+
+#[test]
+fn test_compile_visibility() {
+    let schemas = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../butte-examples/fbs");
+    let out = out_dir("visibility");
+    let status = butte()
+        .arg("compile")
+        .arg("--out")
+        .arg(&out)
+        .args(["--visibility", "pub(crate)", "--ugly"])
+        .arg(schemas.join("scalars/scalars.fbs"))
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let scalars = fs::read_to_string(out.join("scalars.rs")).unwrap();
+    assert!(scalars.contains("pub (crate) mod stats"));
+    assert!(scalars.contains("pub (crate) struct Settings < 'a >"));
+    assert!(!scalars.contains("pub struct"));
+    fs::remove_dir_all(&out).unwrap();
+
+    let output = butte()
+        .arg("compile")
+        .arg("--out")
+        .arg(&out)
+        .args(["--visibility", "pub(super)"])
+        .arg(schemas.join("scalars/scalars.fbs"))
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid visibility"));
+    let _ = fs::remove_dir_all(&out);
+}
+
+#[test]
+fn test_compile_errors() {
+    let out = out_dir("errors");
+    let output = butte()
+        .arg("compile")
+        .arg("--out")
+        .arg(&out)
+        .arg("missing.fbs")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("missing.fbs"));
+
+    let output = butte().arg("compile").output().unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_generate_from_stdin() {
+    let mut child = butte()
+        .args(["generate", "--ugly"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"table Cat { lives: int = 9; }")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("pub struct Cat"));
+}
+
+#[test]
+fn test_generate_formats_in_process() {
+    let mut child = butte()
+        .arg("generate")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .env("PATH", "")
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"/// A cat.\ntable Cat { lives: int = 9; }")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let code = String::from_utf8_lossy(&output.stdout);
+    assert!(code.contains("/// A cat.\n"));
+    assert!(code.contains("\npub struct Cat<'a> {\n"));
+
+    let output = butte()
+        .args(["generate", "--ugly", "--rustfmt"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_fmt() {
+    let dir = out_dir("fmt");
+    fs::create_dir_all(&dir).unwrap();
+    let schema = dir.join("cat.fbs");
+    fs::write(&schema, "table Cat{lives:int=9;}").unwrap();
+
+    let output = butte()
+        .args(["fmt", "--check"])
+        .arg(&schema)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("cat.fbs"));
+
+    let status = butte().arg("fmt").arg(&schema).status().unwrap();
+    assert!(status.success());
+    assert_eq!(
+        fs::read_to_string(&schema).unwrap(),
+        "table Cat {\n  lives: int = 9;\n}\n"
+    );
+
+    let status = butte()
+        .args(["fmt", "--check"])
+        .arg(&schema)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
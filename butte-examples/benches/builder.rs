@@ -0,0 +1,77 @@
+//! Compares building many small messages with a fresh builder each time to
+//! reusing one builder. Run with `cargo bench -p butte-examples --bench
+//! builder`.
+
+use butte as fb;
+use criterion::{criterion_group, criterion_main, Criterion};
+
+#[allow(dead_code)]
+mod vectors {
+    butte_build::include_fbs!("vectors");
+}
+
+use vectors::inventory::{Inventory, InventoryArgs, Item, ItemArgs};
+
+fn build_inventory(builder: &mut fb::FlatBufferBuilder, n: u32) {
+    let owner = builder.create_string("Alice");
+    let scores = builder.create_vector(&[3, -1, 4]);
+    let mut items = vec![];
+    for count in 0..n % 8 {
+        let name = builder.create_string("item");
+        items.push(Item::create(builder, &ItemArgs { name, count }));
+    }
+    let items = builder.create_vector(&items);
+    let inventory = Inventory::create(
+        builder,
+        &InventoryArgs {
+            owner: Some(owner),
+            checksum: None,
+            scores: Some(scores),
+            tags: None,
+            kinds: None,
+            items: Some(items),
+            favorite: None,
+        },
+    );
+    builder.finish_minimal(inventory);
+}
+
+fn benches(c: &mut Criterion) {
+    let mut group = c.benchmark_group("builder");
+    // messages of 0 to 7 items, in turn
+    let mut n = 0u32;
+    group.bench_function("new", |b| {
+        b.iter(|| {
+            n = n.wrapping_add(1);
+            let mut builder = fb::FlatBufferBuilder::new();
+            build_inventory(&mut builder, n);
+            builder.finished_data().len()
+        })
+    });
+
+    let mut builder = fb::FlatBufferBuilder::with_capacity(1024);
+    group.bench_function("reset", |b| {
+        b.iter(|| {
+            n = n.wrapping_add(1);
+            builder.reset();
+            build_inventory(&mut builder, n);
+            builder.finished_data().len()
+        })
+    });
+
+    let mut pool = vec![];
+    group.bench_function("from_vec", |b| {
+        b.iter(|| {
+            n = n.wrapping_add(1);
+            let mut builder = fb::FlatBufferBuilder::from_vec(pool.pop().unwrap_or_default());
+            build_inventory(&mut builder, n);
+            let len = builder.finished_data().len();
+            pool.push(builder.collapse().0);
+            len
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(builder, benches);
+criterion_main!(builder);
@@ -0,0 +1,173 @@
+//! The `Inventory` and `Item` tables of `fbs/vectors/vectors.fbs` for the
+//! `flatbuffers` crate, written like the code `flatc --rust` generates, so
+//! that the benchmarks can run the same cases against it. `kinds` is read as
+//! a vector of its `short` base type.
+use flatbuffers::{
+    FlatBufferBuilder, Follow, ForwardsUOffset, InvalidFlatbuffer, Table, VOffsetT, Vector,
+    Verifiable, Verifier, WIPOffset,
+};
+
+#[derive(Copy, Clone)]
+pub struct Item<'a> {
+    table: Table<'a>,
+}
+
+impl<'a> Follow<'a> for Item<'a> {
+    type Inner = Item<'a>;
+
+    unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Item {
+            table: Table::new(buf, loc),
+        }
+    }
+}
+
+impl<'a> Item<'a> {
+    pub const VT_NAME: VOffsetT = 4;
+    pub const VT_COUNT: VOffsetT = 6;
+
+    pub fn create<'fbb>(
+        fbb: &mut FlatBufferBuilder<'fbb>,
+        name: WIPOffset<&'fbb str>,
+        count: u32,
+    ) -> WIPOffset<Item<'fbb>> {
+        let start = fbb.start_table();
+        fbb.push_slot::<u32>(Self::VT_COUNT, count, 0);
+        fbb.push_slot_always::<WIPOffset<_>>(Self::VT_NAME, name);
+        let end = fbb.end_table(start);
+        fbb.required(end, Self::VT_NAME, "name");
+        WIPOffset::new(end.value())
+    }
+
+    pub fn name(&self) -> &'a str {
+        // Safety: the buffer is verified, or built by the benchmark.
+        unsafe {
+            self.table
+                .get::<ForwardsUOffset<&str>>(Self::VT_NAME, None)
+                .unwrap()
+        }
+    }
+
+    pub fn count(&self) -> u32 {
+        // Safety: the buffer is verified, or built by the benchmark.
+        unsafe { self.table.get::<u32>(Self::VT_COUNT, Some(0)).unwrap() }
+    }
+}
+
+impl Verifiable for Item<'_> {
+    fn run_verifier(v: &mut Verifier, pos: usize) -> Result<(), InvalidFlatbuffer> {
+        v.visit_table(pos)?
+            .visit_field::<ForwardsUOffset<&str>>("name", Self::VT_NAME, true)?
+            .visit_field::<u32>("count", Self::VT_COUNT, false)?
+            .finish();
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct Inventory<'a> {
+    table: Table<'a>,
+}
+
+impl<'a> Follow<'a> for Inventory<'a> {
+    type Inner = Inventory<'a>;
+
+    unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Inventory {
+            table: Table::new(buf, loc),
+        }
+    }
+}
+
+pub struct InventoryArgs<'a> {
+    pub owner: Option<WIPOffset<&'a str>>,
+    pub scores: Option<WIPOffset<Vector<'a, i32>>>,
+    pub tags: Option<WIPOffset<Vector<'a, ForwardsUOffset<&'a str>>>>,
+    pub items: Option<WIPOffset<Vector<'a, ForwardsUOffset<Item<'a>>>>>,
+}
+
+impl<'a> Inventory<'a> {
+    pub const VT_OWNER: VOffsetT = 4;
+    pub const VT_CHECKSUM: VOffsetT = 6;
+    pub const VT_SCORES: VOffsetT = 8;
+    pub const VT_TAGS: VOffsetT = 10;
+    pub const VT_KINDS: VOffsetT = 12;
+    pub const VT_ITEMS: VOffsetT = 14;
+    pub const VT_FAVORITE: VOffsetT = 16;
+
+    pub fn create<'fbb>(
+        fbb: &mut FlatBufferBuilder<'fbb>,
+        args: &InventoryArgs<'fbb>,
+    ) -> WIPOffset<Inventory<'fbb>> {
+        let start = fbb.start_table();
+        if let Some(items) = args.items {
+            fbb.push_slot_always::<WIPOffset<_>>(Self::VT_ITEMS, items);
+        }
+        if let Some(tags) = args.tags {
+            fbb.push_slot_always::<WIPOffset<_>>(Self::VT_TAGS, tags);
+        }
+        if let Some(scores) = args.scores {
+            fbb.push_slot_always::<WIPOffset<_>>(Self::VT_SCORES, scores);
+        }
+        if let Some(owner) = args.owner {
+            fbb.push_slot_always::<WIPOffset<_>>(Self::VT_OWNER, owner);
+        }
+        let end = fbb.end_table(start);
+        WIPOffset::new(end.value())
+    }
+
+    pub fn owner(&self) -> Option<&'a str> {
+        // Safety: the buffer is verified, or built by the benchmark.
+        unsafe {
+            self.table
+                .get::<ForwardsUOffset<&str>>(Self::VT_OWNER, None)
+        }
+    }
+
+    pub fn scores(&self) -> Option<Vector<'a, i32>> {
+        // Safety: the buffer is verified, or built by the benchmark.
+        unsafe {
+            self.table
+                .get::<ForwardsUOffset<Vector<i32>>>(Self::VT_SCORES, None)
+        }
+    }
+
+    pub fn tags(&self) -> Option<Vector<'a, ForwardsUOffset<&'a str>>> {
+        // Safety: the buffer is verified, or built by the benchmark.
+        unsafe {
+            self.table
+                .get::<ForwardsUOffset<Vector<ForwardsUOffset<&str>>>>(Self::VT_TAGS, None)
+        }
+    }
+
+    pub fn items(&self) -> Option<Vector<'a, ForwardsUOffset<Item<'a>>>> {
+        // Safety: the buffer is verified, or built by the benchmark.
+        unsafe {
+            self.table
+                .get::<ForwardsUOffset<Vector<ForwardsUOffset<Item>>>>(Self::VT_ITEMS, None)
+        }
+    }
+}
+
+impl Verifiable for Inventory<'_> {
+    fn run_verifier(v: &mut Verifier, pos: usize) -> Result<(), InvalidFlatbuffer> {
+        v.visit_table(pos)?
+            .visit_field::<ForwardsUOffset<&str>>("owner", Self::VT_OWNER, false)?
+            .visit_field::<ForwardsUOffset<Vector<u8>>>("checksum", Self::VT_CHECKSUM, false)?
+            .visit_field::<ForwardsUOffset<Vector<i32>>>("scores", Self::VT_SCORES, false)?
+            .visit_field::<ForwardsUOffset<Vector<ForwardsUOffset<&str>>>>(
+                "tags",
+                Self::VT_TAGS,
+                false,
+            )?
+            .visit_field::<ForwardsUOffset<Vector<i16>>>("kinds", Self::VT_KINDS, false)?
+            .visit_field::<ForwardsUOffset<Vector<ForwardsUOffset<Item>>>>(
+                "items",
+                Self::VT_ITEMS,
+                false,
+            )?
+            .visit_field::<ForwardsUOffset<Item>>("favorite", Self::VT_FAVORITE, false)?
+            .finish();
+        Ok(())
+    }
+}
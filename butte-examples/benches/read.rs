@@ -0,0 +1,187 @@
+//! Measures the throughput of building, verifying and reading a buffer with
+//! butte and with the `flatbuffers` crate, as a baseline for work on the read
+//! path. Run with `cargo bench -p butte-examples --bench read`.
+use butte as fb;
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+mod flatbuffers_inventory;
+
+#[allow(dead_code)]
+mod vectors {
+    butte_build::include_fbs!("vectors");
+}
+
+use vectors::inventory::{Inventory, InventoryArgs, Item, ItemArgs};
+
+const ITEMS: u32 = 64;
+
+fn build_inventory(builder: &mut fb::FlatBufferBuilder) {
+    let owner = builder.create_string("Alice");
+    let scores = builder.create_vector(&(0..ITEMS as i32).collect::<Vec<_>>());
+    let tags = builder.create_vector_of_strings(&["red", "green", "blue"]);
+    let mut items = vec![];
+    for count in 0..ITEMS {
+        let name = builder.create_string("item");
+        items.push(Item::create(builder, &ItemArgs { name, count }));
+    }
+    let items = builder.create_vector(&items);
+    let inventory = Inventory::create(
+        builder,
+        &InventoryArgs {
+            owner: Some(owner),
+            checksum: None,
+            scores: Some(scores),
+            tags: Some(tags),
+            kinds: None,
+            items: Some(items),
+            favorite: None,
+        },
+    );
+    builder.finish_minimal(inventory);
+}
+
+/// Read every field of `inventory`, summing up the numbers and lengths.
+fn read_inventory(inventory: Inventory) -> fb::Result<usize> {
+    let mut sum = inventory.owner()?.map_or(0, str::len);
+    for score in inventory.scores()?.into_iter().flatten() {
+        sum += score? as usize;
+    }
+    for tag in inventory.tags()?.into_iter().flatten() {
+        sum += tag?.len();
+    }
+    for item in inventory.items()?.into_iter().flatten() {
+        let item = item?;
+        sum += item.name()?.len() + item.count()? as usize;
+    }
+    Ok(sum)
+}
+
+/// The same as `build_inventory`, with the `flatbuffers` crate.
+fn build_flatbuffers_inventory(builder: &mut flatbuffers::FlatBufferBuilder) {
+    use flatbuffers_inventory::{Inventory, InventoryArgs, Item};
+
+    let owner = builder.create_string("Alice");
+    let scores = builder.create_vector(&(0..ITEMS as i32).collect::<Vec<_>>());
+    let tags = ["red", "green", "blue"].map(|tag| builder.create_string(tag));
+    let tags = builder.create_vector(&tags);
+    let mut items = vec![];
+    for count in 0..ITEMS {
+        let name = builder.create_string("item");
+        items.push(Item::create(builder, name, count));
+    }
+    let items = builder.create_vector(&items);
+    let inventory = Inventory::create(
+        builder,
+        &InventoryArgs {
+            owner: Some(owner),
+            scores: Some(scores),
+            tags: Some(tags),
+            items: Some(items),
+        },
+    );
+    builder.finish_minimal(inventory);
+}
+
+/// The same as `read_inventory`, with the `flatbuffers` crate.
+fn read_flatbuffers_inventory(inventory: flatbuffers_inventory::Inventory) -> usize {
+    let mut sum = inventory.owner().map_or(0, str::len);
+    for score in inventory.scores().into_iter().flatten() {
+        sum += score as usize;
+    }
+    for tag in inventory.tags().into_iter().flatten() {
+        sum += tag.len();
+    }
+    for item in inventory.items().into_iter().flatten() {
+        sum += item.name().len() + item.count() as usize;
+    }
+    sum
+}
+
+fn benches(c: &mut Criterion) {
+    let mut builder = fb::FlatBufferBuilder::new();
+    build_inventory(&mut builder);
+    let buf = builder.finished_data().to_vec();
+    let mut flatbuffers_builder = flatbuffers::FlatBufferBuilder::new();
+    build_flatbuffers_inventory(&mut flatbuffers_builder);
+    let flatbuffers_buf = flatbuffers_builder.finished_data().to_vec();
+    // both read each other's buffers the same
+    assert_eq!(
+        read_inventory(fb::verified_root::<Inventory>(&flatbuffers_buf).unwrap()).unwrap(),
+        read_flatbuffers_inventory(
+            flatbuffers::root::<flatbuffers_inventory::Inventory>(&buf).unwrap()
+        )
+    );
+
+    let mut group = c.benchmark_group("build");
+    group.throughput(Throughput::Bytes(buf.len() as u64));
+    group.bench_function("butte", |b| {
+        b.iter(|| {
+            builder.reset();
+            build_inventory(&mut builder);
+            builder.finished_data().len()
+        })
+    });
+    group.bench_function("flatbuffers", |b| {
+        b.iter(|| {
+            flatbuffers_builder.reset();
+            build_flatbuffers_inventory(&mut flatbuffers_builder);
+            flatbuffers_builder.finished_data().len()
+        })
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("verify");
+    group.throughput(Throughput::Bytes(buf.len() as u64));
+    group.bench_function("butte", |b| {
+        b.iter(|| fb::verified_root::<Inventory>(black_box(&buf)).unwrap())
+    });
+    group.bench_function("flatbuffers", |b| {
+        b.iter(|| {
+            flatbuffers::root::<flatbuffers_inventory::Inventory>(black_box(&flatbuffers_buf))
+                .unwrap()
+        })
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("read");
+    group.throughput(Throughput::Bytes(buf.len() as u64));
+    group.bench_function("butte", |b| {
+        b.iter(|| {
+            let inventory = fb::get_root::<Inventory>(black_box(&buf)).unwrap();
+            read_inventory(inventory).unwrap()
+        })
+    });
+    group.bench_function("flatbuffers", |b| {
+        b.iter(|| {
+            // Safety: the buffer was built by `build_flatbuffers_inventory`.
+            let inventory = unsafe {
+                flatbuffers::root_unchecked::<flatbuffers_inventory::Inventory>(black_box(
+                    &flatbuffers_buf,
+                ))
+            };
+            read_flatbuffers_inventory(inventory)
+        })
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("verify_and_read");
+    group.throughput(Throughput::Bytes(buf.len() as u64));
+    group.bench_function("butte", |b| {
+        b.iter(|| {
+            let inventory = fb::verified_root::<Inventory>(black_box(&buf)).unwrap();
+            read_inventory(inventory).unwrap()
+        })
+    });
+    group.bench_function("flatbuffers", |b| {
+        b.iter(|| {
+            let inventory =
+                flatbuffers::root::<flatbuffers_inventory::Inventory>(black_box(&flatbuffers_buf))
+                    .unwrap();
+            read_flatbuffers_inventory(inventory)
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(read, benches);
+criterion_main!(read);
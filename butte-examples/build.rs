@@ -1,5 +1,85 @@
 use anyhow::Result;
+use butte_build::codegen::EmptyCollections;
 
 fn main() -> Result<()> {
-    butte_build::compile_fbs("fbs/greeter/greeter.fbs")
+    butte_build::compile_fbs("fbs/greeter/greeter.fbs")?;
+    butte_build::compile_fbs("fbs/structs/structs.fbs")?;
+    butte_build::Config::new()
+        .input("fbs/vectors/vectors.fbs")
+        .round_trip_tests(true)
+        .compile()?;
+    butte_build::Config::new()
+        .input("fbs/scalars/scalars.fbs")
+        .round_trip_tests(true)
+        .compile()?;
+    butte_build::compile_fbs("fbs/deprecated/deprecated.fbs")?;
+    butte_build::Config::new()
+        .input("fbs/keywords/keywords.fbs")
+        .object_api(true)
+        .round_trip_tests(true)
+        .compile()?;
+    butte_build::Config::new()
+        .input("fbs/naming/naming.fbs")
+        .naming(butte_build::codegen::Naming::Idiomatic)
+        .object_api(true)
+        .round_trip_tests(true)
+        .compile()?;
+    butte_build::Config::new()
+        .input("fbs/unions/unions.fbs")
+        .object_api(true)
+        .object_derive("Eq")
+        .object_derive("Hash")
+        .round_trip_tests(true)
+        .compile()?;
+    butte_build::Config::new()
+        .input("fbs/objects/objects.fbs")
+        .object_api(true)
+        .compile()?;
+    butte_build::Config::new()
+        .input_as("fbs/objects/objects.fbs", "objects_shared")
+        .object_api(true)
+        .empty_collections(EmptyCollections::Shared)
+        .compile()?;
+    butte_build::Config::new()
+        .input_as("fbs/objects/objects.fbs", "objects_omitted")
+        .object_api(true)
+        .empty_collections(EmptyCollections::Omitted)
+        .compile()?;
+    butte_build::Config::new()
+        .input_as("fbs/objects/objects.fbs", "objects_crate")
+        .object_api(true)
+        .visibility("pub(crate)")
+        .compile()?;
+    butte_build::Config::new()
+        .input("fbs/big/big.fbs")
+        .object_api(true)
+        .compile()?;
+    butte_build::Config::new()
+        .input("fbs/includes/includes.fbs")
+        .include_dir("fbs/shared")
+        .derive("Eq")
+        .derive("Hash")
+        .compile()?;
+    butte_build::Config::new()
+        .input_as("fbs/includes/includes.fbs", "includes_brush")
+        .include_dir("fbs/shared")
+        .root_type("art.tools.Brush")
+        .compile()?;
+    butte_build::Config::new()
+        .input_as("fbs/includes/includes.fbs", "includes_prelude")
+        .include_dir("fbs/shared")
+        .root_type("art.tools.Brush")
+        .prelude(true)
+        .module("generated")
+        .compile()?;
+    butte_build::Config::new()
+        .input("fbs/store/store_v1.fbs")
+        .input("fbs/store/store_v2.fbs")
+        .adapters("store_v1", "store_v2")
+        .compile()?;
+    butte_build::Config::new()
+        .input_as("fbs/includes/includes.fbs", "includes_split")
+        .include_dir("fbs/shared")
+        .split_namespaces(true)
+        .compile()
 }
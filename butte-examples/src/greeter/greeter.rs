@@ -14,9 +14,9 @@ fn main() -> Result<()> {
     let req = HelloRequest::create(&mut builder, &args);
     builder.finish_minimal(req);
     let raw_bytes = builder.finished_data();
-    let root = fb::get_root::<HelloRequest>(raw_bytes);
-    let dname = root.name();
-    let expected = Some(raw_name);
+    let root = fb::get_root::<HelloRequest>(raw_bytes)?;
+    let dname = root.name()?;
+    let expected = raw_name;
     if dname != expected {
         Err(anyhow!("Expected {:?}, got {:?}", expected, dname))
     } else {
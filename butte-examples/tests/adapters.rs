@@ -0,0 +1,139 @@
+use butte as fb;
+
+#[allow(dead_code)]
+mod store_v1 {
+    butte_build::include_fbs!("store_v1");
+}
+
+#[allow(dead_code)]
+mod store_v2 {
+    butte_build::include_fbs!("store_v2");
+}
+
+mod store_v1_to_store_v2 {
+    butte_build::include_fbs!("store_v1_to_store_v2");
+}
+
+use store_v1::store as v1;
+use store_v2::store as v2;
+
+/// A buffer of the first version of the schema, with every field of `Shop`
+/// set.
+fn v1_shop() -> Vec<u8> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let name = builder.create_string("corner shop");
+    let street = builder.create_string("1 Main Street");
+    let address = v1::Address::create(
+        &mut builder,
+        &v1::AddressArgs {
+            street: Some(street),
+            city: None,
+        },
+    );
+    let apple = builder.create_string("apple");
+    let mut product = v1::ProductBuilder::new(&mut builder);
+    product.add_name(apple);
+    product.add_price(120);
+    product.add_labels(v1::Labels::Fresh | v1::Labels::Organic);
+    let product = product.finish();
+    let products = builder.create_vector(&[product]);
+    let keywords = builder.create_vector_of_strings(&["fruit", "local"]);
+    let ratings = builder.create_vector(&[5_u8, 4, 5]);
+    let history = builder.create_vector(&[v1::Status::Closed, v1::Status::Active]);
+    let message = builder.create_string("happy birthday");
+    let gift = v1::Gift::create(
+        &mut builder,
+        &v1::GiftArgs {
+            message: Some(message),
+        },
+    );
+    let promotion_types = builder.create_vector(&[v1::PromotionType::Gift]);
+    let promotions = builder.create_vector(&[Some(gift.as_union_value())]);
+    let location = v1::Coordinates::new(51.5, -0.1);
+
+    let mut shop = v1::ShopBuilder::new(&mut builder);
+    shop.add_name(name);
+    shop.add_status(v1::Status::Closed);
+    shop.add_location(&location);
+    shop.add_address(address);
+    shop.add_products(products);
+    shop.add_keywords(keywords);
+    shop.add_ratings(ratings);
+    shop.add_history(history);
+    shop.add_featured_type(v1::PromotionType::Product);
+    shop.add_featured(product.as_union_value());
+    shop.add_promotions_type(promotion_types);
+    shop.add_promotions(promotions);
+    shop.add_legacy_code(42);
+    let shop = shop.finish();
+    builder.finish(shop, Some(fb::FileIdent::new("SHOP")));
+    builder.finished_data().to_vec()
+}
+
+#[test]
+fn test_adapt_buffer() -> fb::Result<()> {
+    let buf = store_v1_to_store_v2::adapt_buffer(&v1_shop())?;
+    let shop = fb::verified_root::<v2::Shop<'_>>(&buf)?;
+    assert_eq!(shop.name()?, "corner shop");
+    // enum values keep their names, though their discriminants changed
+    assert_eq!(shop.status()?, v2::Status::Closed);
+    let location = shop.location()?.unwrap();
+    assert_eq!(location.latitude(), 51.5);
+    assert_eq!(location.longitude(), -0.1);
+
+    let address = shop.address()?.unwrap();
+    assert_eq!(address.street()?, Some("1 Main Street"));
+    assert_eq!(address.city()?, None);
+    assert_eq!(address.floor()?, 1);
+
+    let products = shop.products()?.unwrap();
+    assert_eq!(products.len(), 1);
+    let product = products.iter().next().unwrap()?;
+    assert_eq!(product.name()?, "apple");
+    assert_eq!(product.price()?, 120);
+    assert_eq!(product.labels()?, v2::Labels::Fresh | v2::Labels::Organic);
+    assert_eq!(product.stock()?, 10);
+
+    let keywords = shop.keywords()?.unwrap();
+    let keywords = keywords.iter().collect::<fb::Result<Vec<_>>>()?;
+    assert_eq!(keywords, ["fruit", "local"]);
+    assert_eq!(&*shop.ratings()?.unwrap().to_slice()?, [5, 4, 5]);
+    let history = shop.history()?.unwrap();
+    let history = history.iter().collect::<fb::Result<Vec<_>>>()?;
+    assert_eq!(history, [v2::Status::Closed, v2::Status::Active]);
+
+    match shop.featured()? {
+        Some(v2::Promotion::Product(product)) => assert_eq!(product.name()?, "apple"),
+        _ => panic!("unexpected featured promotion"),
+    }
+    let promotions = shop.promotions()?.unwrap();
+    assert_eq!(promotions.len(), 1);
+    match promotions.iter().next().unwrap()? {
+        v2::Promotion::Gift(gift) => assert_eq!(gift.message()?, Some("happy birthday")),
+        _ => panic!("unexpected promotion"),
+    }
+
+    // fields added by the new version read as their defaults
+    assert_eq!(shop.email()?, None);
+    assert_eq!(shop.loyalty()?, 10);
+    Ok(())
+}
+
+#[test]
+fn test_adapt_enums() {
+    assert_eq!(
+        store_v1_to_store_v2::store_status(v1::Status::Active),
+        v2::Status::Active
+    );
+    // flags keep their names, though their bits changed
+    assert_eq!(v1::Labels::Organic.bits(), 2);
+    assert_eq!(
+        store_v1_to_store_v2::store_labels(v1::Labels::Organic).bits(),
+        v2::Labels::Organic.bits()
+    );
+}
+
+#[test]
+fn test_adapt_invalid_buffer() {
+    assert!(store_v1_to_store_v2::adapt_buffer(&[1, 2, 3]).is_err());
+}
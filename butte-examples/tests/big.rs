@@ -0,0 +1,150 @@
+use butte as fb;
+use butte::json::{from_json, to_json};
+use butte_build::{include::SchemaFiles, json_schema};
+
+#[allow(dead_code)]
+mod big {
+    butte_build::include_fbs!("big");
+}
+
+use big::archive::{Blob, BlobArgs, BlobT, Chunk};
+
+fn build_blob(builder: &mut fb::FlatBufferBuilder) {
+    let data = builder.create_vector64(&[1u8, 2, 3, 4, 5]);
+    let chunks = builder.create_vector(&[Chunk::new(0, 2), Chunk::new(2, 3)]);
+    let name = builder.create_string("blob.bin");
+    let blob = Blob::create(
+        builder,
+        &BlobArgs {
+            name: Some(name.into()),
+            data: Some(data),
+            chunks: Some(chunks.into()),
+            checksum: 15,
+        },
+    );
+    builder.finish_minimal(blob);
+}
+
+#[test]
+fn test_64_bit_offsets() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    build_blob(&mut builder);
+    let blob = fb::get_root::<Blob>(builder.finished_data())?;
+
+    assert_eq!(blob.name()?, Some("blob.bin"));
+    assert_eq!(blob.name_bytes()?, Some(&b"blob.bin"[..]));
+    let data = blob.data()?.unwrap();
+    assert_eq!(data.len(), 5);
+    assert_eq!(data.get(4)?, 5);
+    assert!(matches!(
+        data.get(5),
+        Err(fb::Error::OutOfBounds { len: 1, .. })
+    ));
+    assert_eq!(data.safe_slice()?, &[1, 2, 3, 4, 5]);
+    let chunks = blob.chunks()?.unwrap();
+    assert_eq!(chunks.get(1)?, &Chunk::new(2, 3));
+    assert_eq!(blob.checksum()?, 15);
+    Ok(())
+}
+
+#[test]
+fn test_64_bit_offsets_object_round_trip() -> fb::Result<()> {
+    let original = BlobT {
+        name: Some("blob.bin".to_string()),
+        data: Some(vec![1, 2, 3]),
+        chunks: Some(vec![Chunk::new(0, 3)]),
+        checksum: 6,
+    };
+    let mut builder = fb::FlatBufferBuilder::new();
+    let offset = original.pack(&mut builder);
+    builder.finish_minimal(offset);
+
+    let blob = fb::get_root::<Blob>(builder.finished_data())?;
+    assert_eq!(blob.unpack()?, original);
+    Ok(())
+}
+
+#[test]
+fn test_64_bit_offsets_copy() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    build_blob(&mut builder);
+    let blob = fb::get_root::<Blob>(builder.finished_data())?;
+
+    let mut copy = fb::FlatBufferBuilder::new();
+    let offset = blob.copy_to(&mut copy)?;
+    copy.finish_minimal(offset);
+    let copied = fb::verified_root::<Blob>(copy.finished_data())?;
+    assert_eq!(copied.unpack()?, blob.unpack()?);
+    Ok(())
+}
+
+#[test]
+fn test_64_bit_offsets_json() -> anyhow::Result<()> {
+    let files = SchemaFiles::load("fbs/big/big.fbs", &[])?;
+    let schema = json_schema(&files)?;
+    let mut builder = fb::FlatBufferBuilder::new();
+    build_blob(&mut builder);
+
+    let json = to_json(&schema, builder.finished_data())?;
+    assert_eq!(
+        json,
+        r#"{
+  "name": "blob.bin",
+  "data": [
+    1,
+    2,
+    3,
+    4,
+    5
+  ],
+  "chunks": [
+    {
+      "offset": 0,
+      "size": 2
+    },
+    {
+      "offset": 2,
+      "size": 3
+    }
+  ],
+  "checksum": 15
+}
+"#
+    );
+    let buf = from_json(&schema, &json)?;
+    assert_eq!(to_json(&schema, &buf)?, json);
+    Ok(())
+}
+
+#[test]
+#[ignore = "needs 5 gigabytes of memory, run with `cargo test --release -- --ignored`"]
+fn test_64_bit_offsets_past_4_gigabytes() -> fb::Result<()> {
+    let len = (1 << 32) + 8;
+    let mut data = vec![0u8; len];
+    data[0] = 1;
+    data[len - 1] = 2;
+
+    // the string is referred to by a 64-bit offset, but the offset of
+    // `create_string` is 32 bits, so it has to come before the big vector
+    let mut builder = fb::FlatBufferBuilder::with_capacity(len + 1024);
+    let name = builder.create_string("blob.bin");
+    let data = builder.create_vector64(&data);
+    let blob = Blob::create(
+        &mut builder,
+        &BlobArgs {
+            name: Some(name.into()),
+            data: Some(data),
+            chunks: None,
+            checksum: 3,
+        },
+    );
+    builder.finish_minimal(blob);
+
+    let blob = fb::verified_root::<Blob>(builder.finished_data())?;
+    assert_eq!(blob.name()?, Some("blob.bin"));
+    let data = blob.data()?.unwrap();
+    assert_eq!(data.len(), len);
+    assert_eq!((data.get(0)?, data.get(len - 1)?), (1, 2));
+    assert_eq!(blob.checksum()?, 3);
+    Ok(())
+}
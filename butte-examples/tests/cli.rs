@@ -4,7 +4,7 @@ use std::process::Command;
 
 #[test]
 fn test_greeter() -> Result<()> {
-    let mut cmd = Command::cargo_bin("greeter")?;
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_greeter"));
     cmd.assert().success();
     Ok(())
 }
@@ -0,0 +1,123 @@
+use butte as fb;
+
+#[allow(dead_code)]
+mod deprecated {
+    butte_build::include_fbs!("deprecated");
+}
+
+use deprecated::legacy::{
+    PlayerV0, PlayerV1, PlayerV1Args, PlayerV2, PlayerV2Args, PlayerV3, PlayerV3Args,
+};
+
+#[test]
+#[allow(deprecated)]
+fn test_read_deprecated_fields() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let nickname = builder.create_string("old timer");
+    let player = PlayerV1::create(
+        &mut builder,
+        &PlayerV1Args {
+            nickname: Some(nickname),
+            hp: 42,
+            mana: 7,
+        },
+    );
+    builder.finish_minimal(player);
+
+    let player = fb::get_root::<PlayerV2>(builder.finished_data())?;
+    assert_eq!(player.hp()?, 42);
+    assert_eq!(player.nickname()?, Some("old timer"));
+    assert_eq!(player.mana()?, 7);
+    Ok(())
+}
+
+#[test]
+fn test_deprecated_fields_are_not_written() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let player = PlayerV2::create(&mut builder, &PlayerV2Args { hp: 42 });
+    builder.finish_minimal(player);
+
+    let player = fb::get_root::<PlayerV1>(builder.finished_data())?;
+    assert_eq!(player.hp()?, 42);
+    assert_eq!(player.nickname()?, None);
+    assert_eq!(player.mana()?, 0);
+    Ok(())
+}
+
+#[test]
+fn test_field_ids() -> fb::Result<()> {
+    assert_eq!(PlayerV3::VT_NICKNAME, PlayerV1::VT_NICKNAME);
+    assert_eq!(PlayerV3::VT_MANA, PlayerV1::VT_MANA);
+
+    let mut builder = fb::FlatBufferBuilder::new();
+    let nickname = builder.create_string("newcomer");
+    let player = PlayerV3::create(
+        &mut builder,
+        &PlayerV3Args {
+            mana: 3,
+            hp: 80,
+            nickname: Some(nickname),
+        },
+    );
+    builder.finish_minimal(player);
+
+    let player = fb::get_root::<PlayerV1>(builder.finished_data())?;
+    assert_eq!(player.nickname()?, Some("newcomer"));
+    assert_eq!(player.hp()?, 80);
+    assert_eq!(player.mana()?, 3);
+    Ok(())
+}
+
+#[test]
+fn test_unknown_fields() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let nickname = builder.create_string("old timer");
+    let player = PlayerV1::create(
+        &mut builder,
+        &PlayerV1Args {
+            nickname: Some(nickname),
+            hp: 42,
+            mana: 7,
+        },
+    );
+    builder.finish_minimal(player);
+    let data = builder.finished_data();
+
+    let player = fb::get_root::<PlayerV1>(data)?;
+    assert_eq!(player.unknown_fields()?.count(), 0);
+
+    // the fields added after `PlayerV0` are unknown to its reader, which
+    // sees the bytes they are stored in
+    let player = fb::get_root::<PlayerV0>(data)?;
+    assert_eq!(player.nickname()?, Some("old timer"));
+    let unknown = player.unknown_fields()?.collect::<Vec<_>>();
+    assert_eq!(
+        unknown.iter().map(|field| field.id).collect::<Vec<_>>(),
+        [1, 2]
+    );
+    let inline = player.as_bytes()?;
+    for field in &unknown {
+        let offset = field.offset as usize;
+        assert_eq!(&inline[offset..offset + field.bytes.len()], field.bytes);
+    }
+    assert_eq!(unknown[0].bytes[..2], 42_i16.to_le_bytes());
+    assert_eq!(unknown[1].bytes[..4], 7_i32.to_le_bytes());
+
+    // a field left out for its default value isn't present
+    let mut builder = fb::FlatBufferBuilder::new();
+    let player = PlayerV1::create(
+        &mut builder,
+        &PlayerV1Args {
+            nickname: None,
+            hp: 100,
+            mana: 7,
+        },
+    );
+    builder.finish_minimal(player);
+    let player = fb::get_root::<PlayerV0>(builder.finished_data())?;
+    let unknown = player.unknown_fields()?.collect::<Vec<_>>();
+    assert_eq!(unknown.len(), 1);
+    assert_eq!(unknown[0].id, 2);
+    assert_eq!(unknown[0].bytes, 7_i32.to_le_bytes());
+    Ok(())
+}
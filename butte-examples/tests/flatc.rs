@@ -0,0 +1,75 @@
+//! Checks that butte and flatc agree on the buffers they build and read.
+//! They need flatc, so they are ignored unless run with
+//! `cargo test --test flatc -- --ignored`, and then fail if flatc isn't
+//! installed, or named by `FLATC`.
+use butte as fb;
+use butte_build::flatc::Flatc;
+
+#[allow(dead_code)]
+mod objects {
+    butte_build::include_fbs!("objects");
+}
+
+use objects::shop::{Dimensions, Item, ItemArgs, Label, LabelArgs, Size, TagType};
+
+const SCHEMA: &str = "fbs/objects/objects.fbs";
+
+fn flatc() -> Flatc {
+    Flatc::find().expect("flatc not found, install it or name it with `FLATC`")
+}
+
+#[test]
+#[ignore = "needs flatc"]
+fn test_read_flatc_buffer() -> anyhow::Result<()> {
+    let flatc = flatc();
+    let json = r#"{
+      name: "Lamp",
+      size: Small,
+      price: 25,
+      dimensions: { width: 0.5, height: 1.5 },
+      keywords: ["light", "desk"],
+      sizes: [Large, Medium],
+      tag_type: Label,
+      tag: { text: "new" },
+      related: { name: "Bulb" }
+    }"#;
+    flatc.check_reads(SCHEMA, json)?;
+
+    let buf = flatc.binary(SCHEMA, json)?;
+    let item = fb::get_root::<Item>(&buf)?;
+    assert_eq!(item.name()?, "Lamp");
+    assert_eq!(item.dimensions()?, Some(&Dimensions::new(0.5, 1.5)));
+    assert_eq!(item.tag_as_label()?.unwrap().text()?, Some("new"));
+    Ok(())
+}
+
+#[test]
+#[ignore = "needs flatc"]
+fn test_flatc_reads_buffer() -> anyhow::Result<()> {
+    let flatc = flatc();
+    let mut builder = fb::FlatBufferBuilder::new();
+    let text = builder.create_string("new");
+    let label = Label::create(&mut builder, &LabelArgs { text: Some(text) });
+    let labels = builder.create_vector(&[label]);
+    let ratings = builder.create_vector(&[4u8, 5]);
+    let name = builder.create_string("Lamp");
+    let item = Item::create(
+        &mut builder,
+        &ItemArgs {
+            name,
+            size: Size::Large,
+            price: 25,
+            dimensions: None,
+            labels: Some(labels),
+            keywords: None,
+            ratings: Some(ratings),
+            boxes: None,
+            sizes: None,
+            tag_type: TagType::Label,
+            tag: Some(label.as_union_value()),
+            related: None,
+        },
+    );
+    builder.finish_minimal(item);
+    flatc.check_buffer(SCHEMA, builder.finished_data())
+}
@@ -0,0 +1,183 @@
+use butte as fb;
+use butte::flexbuffers::{Builder, FlexBufferType, Reader};
+
+#[test]
+fn test_scalars() -> fb::Result<()> {
+    let mut builder = Builder::new();
+    builder.push_int(1);
+    builder.finish();
+    // the value, its packed type and its width, as flatc writes them
+    assert_eq!(builder.finished_data(), &[1, 4, 1]);
+    assert_eq!(Reader::get_root(builder.finished_data())?.get_i64()?, 1);
+
+    for &value in &[0, -1, 127, -128, 300, -70_000, i64::MAX, i64::MIN] {
+        let mut builder = Builder::new();
+        builder.push_int(value);
+        builder.finish();
+        let root = Reader::get_root(builder.finished_data())?;
+        assert_eq!(root.flexbuffer_type(), FlexBufferType::Int);
+        assert_eq!(root.get_i64()?, value);
+    }
+
+    let mut builder = Builder::new();
+    builder.push_uint(u64::MAX);
+    builder.finish();
+    let root = Reader::get_root(builder.finished_data())?;
+    assert_eq!(root.get_u64()?, u64::MAX);
+    assert_eq!(root.get_i64(), Err(fb::Error::TypeMismatch));
+    assert_eq!(root.get_f64(), Err(fb::Error::TypeMismatch));
+
+    for &value in &[1.5, 0.1, f64::MAX] {
+        let mut builder = Builder::new();
+        builder.push_float(value);
+        builder.finish();
+        assert_eq!(Reader::get_root(builder.finished_data())?.get_f64()?, value);
+    }
+
+    let mut builder = Builder::new();
+    builder.push_bool(true);
+    builder.finish();
+    assert!(Reader::get_root(builder.finished_data())?.get_bool()?);
+
+    let mut builder = Builder::new();
+    builder.push_null();
+    builder.finish();
+    assert!(Reader::get_root(builder.finished_data())?.is_null());
+    Ok(())
+}
+
+#[test]
+fn test_strings_and_blobs() -> fb::Result<()> {
+    let mut builder = Builder::new();
+    builder.push_str("abc");
+    builder.finish();
+    assert_eq!(
+        builder.finished_data(),
+        &[3, b'a', b'b', b'c', 0, 4, 0x14, 1]
+    );
+    assert_eq!(Reader::get_root(builder.finished_data())?.get_str()?, "abc");
+
+    let mut builder = Builder::new();
+    builder.push_blob(&[0xde, 0xad, 0xbe, 0xef]);
+    builder.finish();
+    let root = Reader::get_root(builder.finished_data())?;
+    assert_eq!(root.get_blob()?, &[0xde, 0xad, 0xbe, 0xef]);
+    assert_eq!(root.get_str(), Err(fb::Error::TypeMismatch));
+
+    let long = "x".repeat(1000);
+    let mut builder = Builder::new();
+    builder.push_str(&long);
+    builder.finish();
+    assert_eq!(Reader::get_root(builder.finished_data())?.get_str()?, long);
+    Ok(())
+}
+
+#[test]
+fn test_vectors() -> fb::Result<()> {
+    let mut builder = Builder::new();
+    let start = builder.start_vector();
+    builder.push_int(-100);
+    builder.push_str("Fred");
+    builder.push_float(4.0);
+    let inner = builder.start_vector();
+    builder.push_uint(70_000);
+    builder.push_uint(1);
+    builder.end_typed_vector(inner);
+    builder.push_bool(false);
+    builder.end_vector(start);
+    builder.finish();
+
+    let root = Reader::get_root(builder.finished_data())?;
+    assert_eq!(root.flexbuffer_type(), FlexBufferType::Vector);
+    let vector = root.get_vector()?;
+    assert_eq!(vector.len(), 5);
+    assert_eq!(vector.idx(0)?.get_i64()?, -100);
+    assert_eq!(vector.idx(1)?.get_str()?, "Fred");
+    assert_eq!(vector.idx(2)?.get_f64()?, 4.0);
+    assert!(!vector.idx(4)?.get_bool()?);
+    assert!(matches!(vector.idx(5), Err(fb::Error::OutOfBounds { .. })));
+
+    let inner = vector.idx(3)?;
+    assert_eq!(inner.flexbuffer_type(), FlexBufferType::VectorUInt);
+    let values = inner
+        .get_vector()?
+        .iter()
+        .map(|value| value?.get_u64())
+        .collect::<fb::Result<Vec<_>>>()?;
+    assert_eq!(values, [70_000, 1]);
+    Ok(())
+}
+
+#[test]
+fn test_maps() -> fb::Result<()> {
+    let mut builder = Builder::new();
+    let start = builder.start_map();
+    builder.key("name");
+    builder.push_str("Fred");
+    builder.key("age");
+    builder.push_uint(42);
+    builder.key("tags");
+    let tags = builder.start_vector();
+    builder.push_str("a");
+    builder.push_str("b");
+    builder.end_vector(tags);
+    builder.key("empty");
+    let empty = builder.start_map();
+    builder.end_map(empty);
+    builder.end_map(start);
+    builder.finish();
+
+    let map = Reader::get_root(builder.finished_data())?.get_map()?;
+    assert_eq!(map.len(), 4);
+    assert_eq!(map.get("name")?.unwrap().get_str()?, "Fred");
+    assert_eq!(map.get("age")?.unwrap().get_u64()?, 42);
+    assert_eq!(
+        map.get("tags")?.unwrap().get_vector()?.idx(1)?.get_str()?,
+        "b"
+    );
+    assert!(map.get("empty")?.unwrap().get_map()?.is_empty());
+    assert!(map.get("missing")?.is_none());
+
+    // the keys are sorted
+    let keys = map
+        .iter()
+        .map(|pair| pair.map(|(key, _)| key))
+        .collect::<fb::Result<Vec<_>>>()?;
+    assert_eq!(keys, ["age", "empty", "name", "tags"]);
+    Ok(())
+}
+
+#[test]
+fn test_malformed_buffers() {
+    assert_eq!(
+        Reader::get_root(&[]).map(|_| ()),
+        Err(fb::Error::OutOfBounds {
+            loc: 0,
+            len: 2,
+            buf_len: 0
+        })
+    );
+    assert_eq!(
+        Reader::get_root(&[1, 4, 3]).map(|_| ()),
+        Err(fb::Error::InvalidOffset { loc: 2 })
+    );
+
+    let mut builder = Builder::new();
+    builder.push_str("abc");
+    builder.finish();
+    let mut data = builder.finished_data().to_vec();
+    // the length of the string reaches past the end of the buffer
+    data[0] = 100;
+    let root = Reader::get_root(&data).unwrap();
+    assert!(matches!(
+        root.get_str(),
+        Err(fb::Error::OutOfBounds { len: 100, .. })
+    ));
+    // the offset of the string reaches past the start of the buffer
+    data[5] = 100;
+    let root = Reader::get_root(&data).unwrap();
+    assert!(matches!(
+        root.get_str(),
+        Err(fb::Error::InvalidOffset { .. })
+    ));
+}
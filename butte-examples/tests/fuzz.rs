@@ -0,0 +1,176 @@
+//! Runs the fuzz entry points on corrupted copies of a valid buffer, which
+//! covers the read path of generated code the way a fuzzer would, only
+//! reproducibly.
+use butte as fb;
+use butte::json::{to_json, to_json_with_options, Error, Schema};
+use butte_build::{include::SchemaFiles, json_schema};
+
+#[allow(dead_code)]
+mod vectors {
+    butte_build::include_fbs!("vectors");
+}
+
+use vectors::inventory::{Inventory, InventoryArgs, Item, ItemArgs, Kind, Node, NodeArgs};
+
+fn schema() -> Schema {
+    let files = SchemaFiles::load("fbs/vectors/vectors.fbs", &[]).unwrap();
+    json_schema(&files).unwrap()
+}
+
+fn inventory() -> Vec<u8> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let owner = builder.create_string("Alice");
+    let checksum = builder.create_vector_direct(&[0xde_u8, 0xad]);
+    let scores = builder.create_vector(&[3, -1, 4]);
+    let tags = builder.create_vector_of_strings(&["red", "green"]);
+    let kinds = builder.create_vector(&[Kind::Toy, Kind::Tool]);
+    let name = builder.create_string("hammer");
+    let item = Item::create(&mut builder, &ItemArgs { name, count: 1 });
+    let items = builder.create_vector(&[item]);
+    let inventory = Inventory::create(
+        &mut builder,
+        &InventoryArgs {
+            owner: Some(owner),
+            checksum: Some(checksum),
+            scores: Some(scores),
+            tags: Some(tags),
+            kinds: Some(kinds),
+            items: Some(items),
+            favorite: Some(item),
+        },
+    );
+    builder.finish_minimal(inventory);
+    builder.finished_data().to_vec()
+}
+
+fn read_item(item: Item) -> fb::Result<()> {
+    item.name()?;
+    item.count()?;
+    Ok(())
+}
+
+fn read_inventory(inventory: Inventory) -> fb::Result<()> {
+    inventory.owner()?;
+    for byte in inventory.checksum()?.into_iter().flatten() {
+        byte?;
+    }
+    for score in inventory.scores()?.into_iter().flatten() {
+        score?;
+    }
+    for tag in inventory.tags()?.into_iter().flatten() {
+        tag?;
+    }
+    for kind in inventory.kinds()?.into_iter().flatten() {
+        kind?;
+    }
+    for item in inventory.items()?.into_iter().flatten() {
+        read_item(item?)?;
+    }
+    if let Some(item) = inventory.favorite()? {
+        read_item(item)?;
+    }
+    Ok(())
+}
+
+fn run(schema: &Schema, data: &[u8]) {
+    fb::fuzz::verify::<Inventory>(data);
+    fb::fuzz::verify_and_read::<Inventory, _>(data, read_inventory);
+    let _ = to_json(schema, data);
+}
+
+#[test]
+fn test_corrupted_bytes() {
+    let schema = schema();
+    let buf = inventory();
+    for i in 0..buf.len() {
+        for &value in &[0x00, 0x01, 0x7f, 0x80, 0xff] {
+            let mut data = buf.clone();
+            data[i] = value;
+            run(&schema, &data);
+        }
+        for bit in 0..8 {
+            let mut data = buf.clone();
+            data[i] ^= 1 << bit;
+            run(&schema, &data);
+        }
+    }
+    // the offsets of a vtable are unsigned, and the largest ones are past the
+    // end of any table
+    for i in (0..buf.len() - 1).step_by(2) {
+        for &value in &[0x8000_u16, 0xfffc, 0xfffd, 0xfffe, 0xffff] {
+            let mut data = buf.clone();
+            data[i..i + 2].copy_from_slice(&value.to_le_bytes());
+            run(&schema, &data);
+        }
+    }
+}
+
+#[test]
+fn test_truncated_and_random_buffers() {
+    let schema = schema();
+    let buf = inventory();
+    for len in 0..buf.len() {
+        run(&schema, &buf[..len]);
+    }
+
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    for len in 0..256 {
+        let data = (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect::<Vec<_>>();
+        run(&schema, &data);
+    }
+}
+
+#[test]
+fn test_shared_subtables() {
+    // every node refers to the same child four times, so the tree it stands
+    // for has 4^30 leaves, though the buffer is small
+    let mut builder = fb::FlatBufferBuilder::new();
+    let mut node = Node::create(&mut builder, &NodeArgs { children: None });
+    for _ in 0..30 {
+        let children = builder.create_vector(&[node; 4]);
+        node = Node::create(
+            &mut builder,
+            &NodeArgs {
+                children: Some(children),
+            },
+        );
+    }
+    builder.finish_minimal(node);
+    let data = builder.finished_data();
+    assert!(data.len() < 1000);
+
+    let mut schema = schema();
+    schema.root_table = schema
+        .tables
+        .iter()
+        .position(|table| table.name == "inventory.Node");
+    assert!(matches!(
+        fb::verified_root::<Node>(data).map(|_| ()),
+        Err(fb::Error::TooManyTables)
+    ));
+    // `to_json` stops at a million tables, like the verifier, the limits
+    // here are lower to keep the test fast
+    let options = fb::VerifierOptions {
+        max_tables: 10_000,
+        ..fb::VerifierOptions::default()
+    };
+    assert!(matches!(
+        to_json_with_options(&schema, data, &options),
+        Err(Error::Buffer(fb::Error::TooManyTables))
+    ));
+    let options = fb::VerifierOptions {
+        max_apparent_size: 1 << 16,
+        ..fb::VerifierOptions::default()
+    };
+    assert!(matches!(
+        to_json_with_options(&schema, data, &options),
+        Err(Error::Buffer(fb::Error::ApparentSizeTooLarge))
+    ));
+}
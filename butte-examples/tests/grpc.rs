@@ -0,0 +1,176 @@
+//! Calls the gRPC client generated for the greeter service against its
+//! generated server, over a local TCP connection. Needs the `tonic` feature.
+#![cfg(feature = "tonic")]
+// `tonic::Status`, which the generated code returns, is large
+#![allow(clippy::result_large_err)]
+
+use butte as fb;
+use std::{net::SocketAddr, pin::Pin};
+use tokio_stream::{wrappers::TcpListenerStream, Stream, StreamExt};
+use tonic::{Request, Response, Status, Streaming};
+
+#[allow(dead_code)]
+mod greeter {
+    butte_build::include_fbs!("greeter");
+}
+
+use greeter::{
+    baz::buzz::greeter_grpc::{Greeter, GreeterClient, GreeterServer},
+    foo::bar::{
+        HelloReply, HelloReplyArgs, HelloReplyOffset, HelloRequest, HelloRequestArgs,
+        HelloRequestOffset, ManyHellosRequest, ManyHellosRequestArgs, ManyHellosRequestOffset,
+    },
+};
+
+fn hello_request(name: &str) -> fb::Message<HelloRequestOffset> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let name = builder.create_string(name);
+    let request = HelloRequest::create(&mut builder, &HelloRequestArgs { name });
+    builder.finish_minimal(request);
+    fb::Message::from_builder(&builder)
+}
+
+fn many_hellos_request(name: &str, num_greetings: i32) -> fb::Message<ManyHellosRequestOffset> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let name = builder.create_string(name);
+    let request = ManyHellosRequest::create(
+        &mut builder,
+        &ManyHellosRequestArgs {
+            name: Some(name),
+            num_greetings,
+        },
+    );
+    builder.finish_minimal(request);
+    fb::Message::from_builder(&builder)
+}
+
+fn hello_reply(message: &str) -> fb::Message<HelloReplyOffset> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let message = builder.create_string(message);
+    let reply = HelloReply::create(
+        &mut builder,
+        &HelloReplyArgs {
+            message: Some(message),
+        },
+    );
+    builder.finish_minimal(reply);
+    fb::Message::from_builder(&builder)
+}
+
+/// Reads the root of a message, failing the call if it is malformed.
+fn root<T: fb::TableMarker>(message: &fb::Message<T>) -> Result<T::Table<'_>, Status> {
+    message
+        .root()
+        .map_err(|error| Status::invalid_argument(error.to_string()))
+}
+
+struct Service;
+
+#[tonic::async_trait]
+impl Greeter for Service {
+    type SayManyHellosStream =
+        Pin<Box<dyn Stream<Item = Result<fb::Message<HelloReplyOffset>, Status>> + Send>>;
+
+    async fn say_hello(
+        &self,
+        request: Request<fb::Message<HelloRequestOffset>>,
+    ) -> Result<Response<fb::Message<HelloReplyOffset>>, Status> {
+        let name = root(request.get_ref())?.name().unwrap_or_default();
+        Ok(Response::new(hello_reply(&format!("Hello, {}!", name))))
+    }
+
+    async fn say_many_hellos(
+        &self,
+        request: Request<fb::Message<ManyHellosRequestOffset>>,
+    ) -> Result<Response<Self::SayManyHellosStream>, Status> {
+        let request = root(request.get_ref())?;
+        let name = request.name().unwrap_or_default().unwrap_or_default();
+        let replies = (0..request.num_greetings().unwrap_or_default())
+            .map(|i| Ok(hello_reply(&format!("Hello #{}, {}!", i + 1, name))))
+            .collect::<Vec<_>>();
+        Ok(Response::new(Box::pin(tokio_stream::iter(replies))))
+    }
+
+    async fn say_hello_to_all(
+        &self,
+        request: Request<Streaming<fb::Message<HelloRequestOffset>>>,
+    ) -> Result<Response<fb::Message<HelloReplyOffset>>, Status> {
+        let mut requests = request.into_inner();
+        let mut names = vec![];
+        while let Some(request) = requests.next().await {
+            names.push(root(&request?)?.name().unwrap_or_default().to_string());
+        }
+        Ok(Response::new(hello_reply(&format!(
+            "Hello, {}!",
+            names.join(" and ")
+        ))))
+    }
+
+    async fn say_hi(
+        &self,
+        request: Request<fb::Message<HelloRequestOffset>>,
+    ) -> Result<Response<fb::Message<HelloReplyOffset>>, Status> {
+        let name = root(request.get_ref())?.name().unwrap_or_default();
+        Ok(Response::new(hello_reply(&format!("Hi, {}!", name))))
+    }
+}
+
+/// Serve the greeter on a free local port.
+async fn serve() -> anyhow::Result<SocketAddr> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(
+        tonic::transport::Server::builder()
+            .add_service(GreeterServer::new(Service))
+            .serve_with_incoming(TcpListenerStream::new(listener)),
+    );
+    Ok(addr)
+}
+
+#[tokio::test]
+async fn test_unary_call() -> anyhow::Result<()> {
+    let addr = serve().await?;
+    let mut client = GreeterClient::connect(format!("http://{}", addr)).await?;
+
+    let reply = client.say_hello(hello_request("Ann")).await?.into_inner();
+    assert_eq!(reply.root()?.message()?, Some("Hello, Ann!"));
+
+    // a malformed request is rejected by the server
+    let error = client
+        .say_hello(fb::Message::new(vec![1, 2]))
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), tonic::Code::InvalidArgument);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_server_streaming_call() -> anyhow::Result<()> {
+    let addr = serve().await?;
+    let mut client = GreeterClient::connect(format!("http://{}", addr)).await?;
+
+    let mut replies = client
+        .say_many_hellos(many_hellos_request("Ann", 3))
+        .await?
+        .into_inner();
+    let mut messages = vec![];
+    while let Some(reply) = replies.message().await? {
+        messages.push(reply.root()?.message()?.unwrap_or_default().to_string());
+    }
+    assert_eq!(
+        messages,
+        ["Hello #1, Ann!", "Hello #2, Ann!", "Hello #3, Ann!"]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_client_streaming_call() -> anyhow::Result<()> {
+    let addr = serve().await?;
+    let mut client = GreeterClient::connect(format!("http://{}", addr)).await?;
+
+    let requests = tokio_stream::iter(vec![hello_request("Ann"), hello_request("Bob")]);
+    let reply = client.say_hello_to_all(requests).await?.into_inner();
+    assert_eq!(reply.root()?.message()?, Some("Hello, Ann and Bob!"));
+    Ok(())
+}
@@ -0,0 +1,160 @@
+use butte as fb;
+
+#[allow(dead_code)]
+mod includes {
+    butte_build::include_fbs!("includes");
+}
+
+#[allow(dead_code)]
+mod includes_brush {
+    butte_build::include_fbs!("includes_brush");
+}
+
+#[allow(dead_code)]
+mod includes_prelude {
+    butte_build::include_fbs!("includes_prelude");
+}
+
+#[allow(dead_code)]
+mod includes_split {
+    butte_build::include_generated!("includes_split");
+}
+
+use includes::{
+    art::{
+        get_root_as_canvas,
+        tools::{Brush, BrushArgs},
+        Canvas, CanvasArgs,
+    },
+    palette::{Color, Rgb},
+};
+
+#[test]
+fn test_included_types() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let brush = Brush::create(
+        &mut builder,
+        &BrushArgs {
+            width: 2.5,
+            color: Color::Green,
+        },
+    );
+    let brushes = builder.create_vector(&[brush]);
+    let name = builder.create_string("sunset");
+    let background = Rgb::new(255, 128, 0);
+    let canvas = Canvas::create(
+        &mut builder,
+        &CanvasArgs {
+            name,
+            brushes: Some(brushes),
+            background: Some(&background),
+        },
+    );
+    builder.finish_minimal(canvas);
+
+    let canvas = get_root_as_canvas(builder.finished_data())?;
+    assert_eq!(canvas.name()?, "sunset");
+    assert_eq!(canvas.background()?, Some(&background));
+    let brush = canvas.brushes()?.unwrap().get(0)?;
+    assert_eq!(brush.width()?, 2.5);
+    assert_eq!(brush.color()?, Color::Green);
+    Ok(())
+}
+
+#[test]
+fn test_extra_derives() {
+    let colors = [Rgb::new(1, 2, 3), Rgb::new(1, 2, 3), Rgb::new(3, 2, 1)]
+        .iter()
+        .copied()
+        .collect::<std::collections::HashSet<_>>();
+    assert_eq!(colors.len(), 2);
+}
+
+#[test]
+fn test_split_namespaces() -> fb::Result<()> {
+    use includes_split::{
+        art::{tools::Brush, Canvas},
+        palette::Color,
+    };
+
+    let out_dir = std::path::Path::new(env!("OUT_DIR")).join("includes_split");
+    for file in &["mod.rs", "art.rs", "art/tools.rs", "palette.rs"] {
+        assert!(out_dir.join(file).is_file(), "missing {}", file);
+    }
+
+    // Buffers built with the code of one output are read by the other.
+    let mut builder = fb::FlatBufferBuilder::new();
+    let brush = BrushArgs {
+        width: 1.0,
+        color: includes::palette::Color::Blue,
+    };
+    let brush = includes::art::tools::Brush::create(&mut builder, &brush);
+    builder.finish_minimal(brush);
+    let brush = fb::get_root::<Brush>(builder.finished_data())?;
+    assert_eq!(brush.color()?, Color::Blue);
+
+    builder.reset();
+    let name = builder.create_string("night");
+    let canvas = includes_split::art::Canvas::create(
+        &mut builder,
+        &includes_split::art::CanvasArgs {
+            name,
+            brushes: None,
+            background: None,
+        },
+    );
+    builder.finish_minimal(canvas);
+    assert_eq!(
+        fb::get_root::<Canvas>(builder.finished_data())?.name()?,
+        "night"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_root_type_override() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let brush = includes_brush::art::tools::Brush::create(
+        &mut builder,
+        &includes_brush::art::tools::BrushArgs {
+            width: 3.0,
+            color: includes_brush::palette::Color::Red,
+        },
+    );
+    includes_brush::art::tools::finish_brush_buffer(&mut builder, brush);
+    let brush = includes_brush::art::tools::get_root_as_brush(builder.finished_data())?;
+    assert_eq!(brush.width()?, 3.0);
+
+    // the helpers of the root type declared by the schema aren't generated
+    let code =
+        std::fs::read_to_string(std::path::Path::new(env!("OUT_DIR")).join("includes_brush.rs"))
+            .unwrap();
+    assert!(!code.contains("get_root_as_canvas"));
+    Ok(())
+}
+
+#[test]
+fn test_prelude() -> fb::Result<()> {
+    use includes_prelude::generated::prelude::*;
+
+    let mut builder = fb::FlatBufferBuilder::new();
+    let brush = Brush::create(
+        &mut builder,
+        &includes_prelude::generated::art::tools::BrushArgs {
+            width: 1.5,
+            color: Color::Blue,
+        },
+    );
+    builder.finish_minimal(brush);
+    let brush = fb::get_root::<Brush>(builder.finished_data())?;
+    assert_eq!(brush.color()?, Color::Blue);
+
+    // only the types reachable from the root type are re-exported
+    let code =
+        std::fs::read_to_string(std::path::Path::new(env!("OUT_DIR")).join("includes_prelude.rs"))
+            .unwrap();
+    assert!(code.contains("pub use super::palette::Color;"));
+    assert!(!code.contains("pub use super::art::Canvas;"));
+    assert!(!code.contains("pub use super::palette::Rgb;"));
+    Ok(())
+}
@@ -0,0 +1,327 @@
+use butte as fb;
+use butte::json::{from_json, to_json, Error, Schema};
+use butte_build::{include::SchemaFiles, json_schema};
+
+#[allow(dead_code)]
+mod objects {
+    butte_build::include_fbs!("objects");
+}
+
+use objects::shop::{Dimensions, Item, ItemArgs, Label, LabelArgs, Size, TagType};
+
+fn schema() -> anyhow::Result<Schema> {
+    let files = SchemaFiles::load("fbs/objects/objects.fbs", &[])?;
+    json_schema(&files)
+}
+
+fn lamp() -> Vec<u8> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let text = builder.create_string("new");
+    let label = Label::create(&mut builder, &LabelArgs { text: Some(text) });
+    let labels = builder.create_vector(&[label]);
+    let keywords = builder.create_vector_of_strings(&["light", "desk"]);
+    let ratings = builder.create_vector(&[4u8, 5]);
+    let sizes = builder.create_vector(&[Size::Small, Size::Large]);
+    let name = builder.create_string("Lamp");
+    let dimensions = Dimensions::new(0.5, 1.5);
+    let item = Item::create(
+        &mut builder,
+        &ItemArgs {
+            name,
+            size: Size::Large,
+            price: 25,
+            dimensions: Some(&dimensions),
+            labels: Some(labels),
+            keywords: Some(keywords),
+            ratings: Some(ratings),
+            boxes: None,
+            sizes: Some(sizes),
+            tag_type: TagType::Label,
+            tag: Some(label.as_union_value()),
+            related: None,
+        },
+    );
+    builder.finish_minimal(item);
+    builder.finished_data().to_vec()
+}
+
+#[test]
+fn test_to_json() -> anyhow::Result<()> {
+    let json = to_json(&schema()?, &lamp())?;
+    assert_eq!(
+        json,
+        r#"{
+  "name": "Lamp",
+  "size": "Large",
+  "price": 25,
+  "dimensions": {
+    "width": 0.5,
+    "height": 1.5
+  },
+  "labels": [
+    {
+      "text": "new"
+    }
+  ],
+  "keywords": [
+    "light",
+    "desk"
+  ],
+  "ratings": [
+    4,
+    5
+  ],
+  "sizes": [
+    "Small",
+    "Large"
+  ],
+  "tag_type": "Label",
+  "tag": {
+    "text": "new"
+  }
+}
+"#
+    );
+    Ok(())
+}
+
+#[test]
+fn test_from_json() -> anyhow::Result<()> {
+    let buf = from_json(
+        &schema()?,
+        r#"{
+          // flatc accepts unquoted names, enums by name or number and
+          // trailing commas.
+          name: "Lamp",
+          size: Small,
+          price: 0x20,
+          boxes: [{ width: 1, height: 2.5 }],
+          sizes: [Large, 1],
+          tag_type: "Sticker",
+          tag: { code: "L-1" },
+          related: { name: "Bulb", },
+        }"#,
+    )?;
+    let item = fb::get_root::<Item>(&buf)?;
+    assert_eq!(item.name()?, "Lamp");
+    assert_eq!(item.size()?, Size::Small);
+    assert_eq!(item.price()?, 32);
+    assert_eq!(item.boxes()?.unwrap().get(0)?, &Dimensions::new(1.0, 2.5));
+    let sizes = item.sizes()?.unwrap();
+    assert_eq!((sizes.get(0)?, sizes.get(1)?), (Size::Large, Size::Medium));
+    assert_eq!(item.tag_type()?, TagType::Sticker);
+    assert_eq!(item.tag_as_sticker()?.unwrap().code()?, "L-1");
+    assert_eq!(item.related()?.unwrap().name()?, "Bulb");
+    assert!(item.labels()?.is_none());
+
+    // a scalar equal to its default is left out, and reads as the default
+    let buf = from_json(&schema()?, r#"{ name: "Lamp", price: 0 }"#)?;
+    let item = fb::get_root::<Item>(&buf)?;
+    assert_eq!(item.price()?, 0);
+    Ok(())
+}
+
+#[test]
+fn test_round_trip() -> anyhow::Result<()> {
+    let schema = schema()?;
+    let json = to_json(&schema, &lamp())?;
+    assert_eq!(to_json(&schema, &from_json(&schema, &json)?)?, json);
+    Ok(())
+}
+
+#[test]
+fn test_from_json_errors() -> anyhow::Result<()> {
+    let schema = schema()?;
+    assert!(matches!(
+        from_json(&schema, "{ price: 1 }"),
+        Err(Error::Invalid(message)) if message.contains("name")
+    ));
+    assert!(matches!(
+        from_json(&schema, "{ name: \"Lamp\", size: Huge }"),
+        Err(Error::Invalid(message)) if message.contains("Huge")
+    ));
+    assert!(matches!(
+        from_json(&schema, "{\n  name: \"Lamp\",\n  price: }"),
+        Err(Error::Syntax { line: 3, .. })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_to_json_malformed_vtables() -> anyhow::Result<()> {
+    let schema = schema()?;
+    let data = lamp();
+    let table = fb::read_scalar::<fb::UOffsetT>(&data)? as usize;
+    let vtable =
+        (table as i64 - i64::from(fb::read_scalar_at::<fb::SOffsetT>(&data, table)?)) as usize;
+    // slots past the end of the table, of a scalar and of a union's type
+    for slot in [Item::VT_PRICE, Item::VT_TAG_TYPE] {
+        for offset in [0x8000_u16, 0xFFFD, 0xFFFF] {
+            let mut data = data.clone();
+            let loc = vtable + slot as usize;
+            data[loc..loc + 2].copy_from_slice(&offset.to_le_bytes());
+            assert!(matches!(
+                to_json(&schema, &data),
+                Err(Error::Buffer(fb::Error::InvalidFieldOffset { loc, slot: s }))
+                    if loc == table && s == slot
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_from_json_deeply_nested() -> anyhow::Result<()> {
+    let schema = schema()?;
+    let nested = |depth: usize| "[".repeat(depth) + &"]".repeat(depth);
+    // the parser accepts the nesting, though the schema doesn't
+    assert!(matches!(
+        from_json(&schema, &nested(512)),
+        Err(Error::Invalid(_))
+    ));
+    assert!(matches!(
+        from_json(&schema, &nested(513)),
+        Err(Error::Syntax {
+            line: 1,
+            column: 513,
+            ..
+        })
+    ));
+    assert!(matches!(
+        from_json(&schema, &"[".repeat(1_000_000)),
+        Err(Error::Syntax { .. })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_deeply_nested_tables() -> anyhow::Result<()> {
+    let schema = schema()?;
+    let nested = |depth: usize| {
+        let mut json = "{ name: \"Box\" }".to_string();
+        for _ in 1..depth {
+            json = format!("{{ name: \"Box\", related: {} }}", json);
+        }
+        from_json(&schema, &json)
+    };
+    assert!(to_json(&schema, &nested(100)?).is_ok());
+    assert!(matches!(
+        to_json(&schema, &nested(200)?),
+        Err(Error::Buffer(fb::Error::TooManyLevels))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_fixed_arrays() -> anyhow::Result<()> {
+    let files = SchemaFiles::load("fbs/structs/structs.fbs", &[])?;
+    let mut schema = json_schema(&files)?;
+    schema.root_table = schema
+        .tables
+        .iter()
+        .position(|table| table.name == "geometry.Scene");
+    let json = r#"{
+  "transform": {
+    "scale": [
+      1.0,
+      2.0,
+      3.0,
+      4.0
+    ],
+    "colors": [
+      "Blue",
+      "Red"
+    ],
+    "corners": [
+      {
+        "x": 0.0,
+        "y": 0.0,
+        "z": 0.0
+      },
+      {
+        "x": 1.0,
+        "y": 2.0,
+        "z": 3.0
+      }
+    ],
+    "mask": [
+      7,
+      8,
+      9
+    ]
+  }
+}
+"#;
+    let buf = from_json(&schema, json)?;
+    assert_eq!(to_json(&schema, &buf)?, json);
+    assert!(matches!(
+        from_json(&schema, "{ transform: { scale: [1.0], colors: [Red, Red], corners: [], mask: [] } }"),
+        Err(Error::Invalid(message)) if message.contains("expected 4 values, found 1")
+    ));
+    Ok(())
+}
+
+#[allow(dead_code)]
+mod store_v2 {
+    butte_build::include_fbs!("store_v2");
+}
+
+#[test]
+fn test_flatc_idioms() -> anyhow::Result<()> {
+    use store_v2::store::{Labels, Promotion, PromotionType, Shop, Status};
+
+    let files = SchemaFiles::load("fbs/store/store_v2.fbs", &[])?;
+    let schema = json_schema(&files)?;
+    let buf = from_json(
+        &schema,
+        r#"{
+          name: 'corner shop',
+          status: Status.Closed,
+          location: { latitude: "51.5", longitude: 0x1.8p1 },
+          products: [{ name: "apple", price: "0x78", labels: "Fresh Organic" }],
+          // the discriminant of a union may come after it
+          featured: { name: "pear" },
+          featured_type: "store.Promotion.Product",
+          promotions_type: [Gift, "Coupon"],
+          promotions: [{ message: "happy birthday" }, { code: "SAVE10" }],
+          loyalty: "10",
+        }"#,
+    )?;
+    let shop = fb::get_root::<Shop>(&buf)?;
+    assert_eq!(shop.name()?, "corner shop");
+    assert_eq!(shop.status()?, Status::Closed);
+    let location = shop.location()?.unwrap();
+    assert_eq!((location.latitude(), location.longitude()), (51.5, 3.0));
+    let product = shop.products()?.unwrap().get(0)?;
+    assert_eq!(product.price()?, 120);
+    assert_eq!(product.labels()?, Labels::Fresh | Labels::Organic);
+    assert_eq!(shop.featured_type()?, PromotionType::Product);
+    let promotions = shop
+        .promotions()?
+        .unwrap()
+        .iter()
+        .collect::<fb::Result<Vec<_>>>()?;
+    match promotions.as_slice() {
+        [Promotion::Gift(gift), Promotion::Coupon(coupon)] => {
+            assert_eq!(gift.message()?, Some("happy birthday"));
+            assert_eq!(coupon.code()?, Some("SAVE10"));
+        }
+        _ => panic!("unexpected promotions"),
+    }
+
+    // vectors of unions are printed along with their discriminants, and read
+    // back the same
+    let json = to_json(&schema, &buf)?;
+    assert!(json.contains("\"promotions_type\": [\n    \"Gift\",\n    \"Coupon\"\n  ]"));
+    assert_eq!(to_json(&schema, &from_json(&schema, &json)?)?, json);
+
+    assert!(matches!(
+        from_json(&schema, "{ name: 'shop', promotions_type: [Gift], promotions: [] }"),
+        Err(Error::Invalid(message)) if message.contains("as many as in `promotions_type`")
+    ));
+    assert!(matches!(
+        from_json(&schema, "{ name: 'shop', status: 'Active Closing' }"),
+        Err(Error::Invalid(message)) if message.contains("Active Closing")
+    ));
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use butte as fb;
+
+#[allow(dead_code)]
+mod keywords {
+    butte_build::include_fbs!("keywords");
+}
+
+use keywords::r#ref::r#mod::{
+    enum_name_kind, Kind, Leaf, LeafArgs, LeafT, NodeT, NodeType, Pair, Tree, TreeArgs,
+};
+
+#[test]
+fn test_keyword_names() -> anyhow::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let r#fn = builder.create_string("main");
+    let leaf = Leaf::create(&mut builder, &LeafArgs { r#fn: Some(r#fn) });
+    let r#use = builder.create_vector(&[leaf]);
+    let r#match = builder.create_string("all");
+    let pair = Pair::new(1, 2);
+    let tree = Tree::create(
+        &mut builder,
+        &TreeArgs {
+            r#type: Kind::r#struct,
+            r#match: Some(r#match),
+            r#ref: Some(&pair),
+            r#use: Some(r#use),
+            node_type: NodeType::Leaf,
+            node: Some(leaf.as_union_value()),
+        },
+    );
+    builder.finish_minimal(tree);
+
+    let tree = fb::verified_root::<Tree>(builder.finished_data())?;
+    assert_eq!(tree.r#type()?, Kind::r#struct);
+    assert_eq!(tree.r#match()?, Some("all"));
+    let pair = tree.r#ref()?.unwrap();
+    assert_eq!((pair.r#type(), pair.self_()), (1, 2));
+    assert_eq!(tree.r#use()?.unwrap().get(0)?.r#fn()?, Some("main"));
+    assert_eq!(enum_name_kind(Kind::r#struct), "struct");
+    assert!(format!("{:?}", tree).contains(r#"match: Some("all")"#));
+
+    let leaf = LeafT {
+        r#fn: Some("main".to_string()),
+    };
+    assert_eq!(tree.unpack()?.node, NodeT::Leaf(Box::new(leaf)));
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use butte as fb;
+
+#[allow(dead_code)]
+mod unions {
+    butte_macros::include_fbs!("fbs/unions/unions.fbs");
+}
+
+use unions::zoo::{AnimalType, Cat, CatArgs, Pet, PetArgs};
+
+#[test]
+fn test_include_fbs_macro() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let name = builder.create_string("Tom");
+    let cat = Cat::create(
+        &mut builder,
+        &CatArgs {
+            name: Some(name),
+            lives: 9,
+        },
+    );
+    let owner = builder.create_string("Frank");
+    let pet = Pet::create(
+        &mut builder,
+        &PetArgs {
+            owner,
+            animal_type: AnimalType::Cat,
+            animal: Some(cat.as_union_value()),
+        },
+    );
+    unions::zoo::finish_pet_buffer(&mut builder, pet);
+
+    let pet = unions::zoo::get_root_as_pet(builder.finished_data())?;
+    assert_eq!(pet.owner()?, "Frank");
+    let cat = pet.animal_as_cat()?.unwrap();
+    assert_eq!(cat.name()?, Some("Tom"));
+    assert_eq!(cat.lives()?, 9);
+    Ok(())
+}
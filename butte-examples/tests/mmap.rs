@@ -0,0 +1,64 @@
+use butte as fb;
+use std::{fs, io, path::PathBuf};
+
+#[allow(dead_code)]
+mod objects {
+    butte_build::include_fbs!("objects");
+}
+
+use objects::shop::{Item, ItemOffset, ItemT, Size, TagT};
+
+/// A path in the temporary directory that is unique to the test.
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("butte-{}-{}", std::process::id(), name))
+}
+
+#[test]
+fn test_open_mapped_root() -> anyhow::Result<()> {
+    let original = ItemT {
+        name: "Lamp".to_string(),
+        size: Size::Large,
+        price: 25,
+        dimensions: None,
+        labels: None,
+        keywords: Some(vec!["light".to_string()]),
+        ratings: None,
+        boxes: None,
+        sizes: None,
+        tag: TagT::NONE,
+        related: None,
+    };
+    let mut builder = fb::FlatBufferBuilder::new();
+    let offset = original.pack(&mut builder);
+    builder.finish_minimal(offset);
+    let path = temp_path("item.bin");
+    fs::write(&path, builder.finished_data())?;
+
+    let mapped = unsafe { fb::open_mapped_root::<ItemOffset>(&path)? };
+    fs::remove_file(&path)?;
+    // the mapping outlives the file's name, and can be moved
+    let mapped = std::thread::spawn(move || mapped).join().unwrap();
+    assert_eq!(mapped.data(), builder.finished_data());
+    let item: Item<'_> = mapped.root()?;
+    assert_eq!(item.name()?, "Lamp");
+    assert_eq!(item.unpack()?, original);
+    fb::verified_root::<Item>(mapped.data())?;
+    Ok(())
+}
+
+#[test]
+fn test_open_mapped_root_errors() -> anyhow::Result<()> {
+    let missing = unsafe { fb::open_mapped_root::<ItemOffset>(temp_path("missing.bin")) };
+    assert_eq!(missing.unwrap_err().kind(), io::ErrorKind::NotFound);
+
+    for (name, data) in [("empty.bin", &[][..]), ("short.bin", &[4, 0, 0, 0][..])] {
+        let path = temp_path(name);
+        fs::write(&path, data)?;
+        let result = unsafe { fb::open_mapped_root::<ItemOffset>(&path) };
+        fs::remove_file(&path)?;
+        let error = result.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.get_ref().unwrap().is::<fb::Error>());
+    }
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use butte as fb;
+
+// Items with `preserve_case` keep the casing of the schema.
+#[allow(dead_code, non_camel_case_types, non_snake_case)]
+mod naming {
+    butte_build::include_fbs!("naming");
+}
+
+use naming::my_game::{
+    finish_hero_unit_buffer, get_root_as_hero_unit, raw_record, raw_recordArgs, ColorKind,
+    EquipmentSlot, EquipmentSlotType, HeroStats, HeroStatsArgs, HeroUnit, HeroUnitArgs, Vec2,
+    WeaponInfo, WeaponInfoArgs, WeaponInfoT,
+};
+
+#[test]
+fn test_idiomatic_names() -> anyhow::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let stats = HeroStats::create(&mut builder, &HeroStatsArgs { hit_points: 80 });
+    builder.finish_minimal(stats);
+    let stats_buffer = builder.finished_data().to_vec();
+
+    let mut builder = fb::FlatBufferBuilder::new();
+    let full_name = builder.create_string("Hero");
+    let weapon_name = builder.create_string("Axe");
+    let weapon = WeaponInfo::create(
+        &mut builder,
+        &WeaponInfoArgs {
+            weapon_name: Some(weapon_name),
+            damage_points: 3,
+        },
+    );
+    let weapon_list = builder.create_vector(&[weapon]);
+    let stats_buffer = builder.create_vector(&stats_buffer);
+    let spawn_point = Vec2::new(1.0, 2.0);
+    let hero = HeroUnit::create(
+        &mut builder,
+        &HeroUnitArgs {
+            full_name: Some(full_name),
+            color_kind: ColorKind::green,
+            spawn_point: Some(&spawn_point),
+            weapon_list: Some(weapon_list),
+            equipped_type: EquipmentSlotType::weaponInfo,
+            equipped: Some(weapon.as_union_value()),
+            legacyId: 7,
+            stats_buffer: Some(stats_buffer),
+        },
+    );
+    finish_hero_unit_buffer(&mut builder, hero);
+
+    let hero = get_root_as_hero_unit(builder.finished_data())?;
+    assert_eq!(hero.full_name()?, Some("Hero"));
+    assert_eq!(hero.color_kind()?, ColorKind::green);
+    assert_eq!(hero.spawn_point()?.unwrap().pos_y(), 2.0);
+    assert_eq!(hero.legacy_id()?, 7);
+    let weapon = WeaponInfoT {
+        weapon_name: Some("Axe".to_string()),
+        damage_points: 3,
+    };
+    match hero.equipped()? {
+        Some(EquipmentSlot::weaponInfo(equipped)) => assert_eq!(equipped.unpack()?, weapon),
+        _ => panic!("expected a weapon"),
+    }
+    let stats = hero.stats_buffer_nested_flatbuffer()?.unwrap();
+    assert_eq!(stats.hit_points()?, 80);
+    assert_eq!(hero.unpack()?.legacyId, 7);
+    Ok(())
+}
+
+#[test]
+fn test_preserve_case() -> anyhow::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let record = raw_record::create(&mut builder, &raw_recordArgs { value: 1 });
+    builder.finish_minimal(record);
+    let record = fb::verified_root::<raw_record>(builder.finished_data())?;
+    assert_eq!(record.value()?, 1);
+    Ok(())
+}
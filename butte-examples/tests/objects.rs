@@ -0,0 +1,425 @@
+use butte as fb;
+
+#[allow(dead_code)]
+mod objects {
+    butte_build::include_fbs!("objects");
+}
+
+#[allow(dead_code)]
+mod objects_shared {
+    butte_build::include_fbs!("objects_shared");
+}
+
+#[allow(dead_code)]
+mod objects_omitted {
+    butte_build::include_fbs!("objects_omitted");
+}
+
+#[allow(dead_code)]
+mod objects_crate {
+    butte_build::include_fbs!("objects_crate");
+}
+
+use objects::shop::{
+    Dimensions, Item, ItemArgs, ItemDirectArgs, ItemOffset, ItemT, Label, LabelArgs, LabelT, Size,
+    StickerT, TagT, TagType,
+};
+
+fn item() -> ItemT {
+    ItemT {
+        name: "Lamp".to_string(),
+        size: Size::Large,
+        price: 25,
+        dimensions: Some(Dimensions::new(0.5, 1.5)),
+        labels: Some(vec![
+            LabelT {
+                text: Some("new".to_string()),
+            },
+            LabelT { text: None },
+        ]),
+        keywords: Some(vec!["light".to_string(), "desk".to_string()]),
+        ratings: Some(vec![5, 4, 5]),
+        boxes: Some(vec![Dimensions::new(1.0, 2.0)]),
+        sizes: Some(vec![Size::Small, Size::Large]),
+        tag: TagT::Sticker(Box::new(StickerT {
+            code: "L-1".to_string(),
+        })),
+        related: Some(Box::new(ItemT {
+            name: "Bulb".to_string(),
+            size: Size::Medium,
+            price: 0,
+            dimensions: None,
+            labels: None,
+            keywords: None,
+            ratings: None,
+            boxes: None,
+            sizes: None,
+            tag: TagT::NONE,
+            related: None,
+        })),
+    }
+}
+
+#[test]
+fn test_object_round_trip() -> fb::Result<()> {
+    let original = item();
+    let mut builder = fb::FlatBufferBuilder::new();
+    let offset = original.pack(&mut builder);
+    builder.finish_minimal(offset);
+
+    let item = fb::get_root::<Item>(builder.finished_data())?;
+    assert_eq!(item.name()?, "Lamp");
+    assert_eq!(item.tag_as_sticker()?.unwrap().code()?, "L-1");
+    assert_eq!(item.unpack()?, original);
+    Ok(())
+}
+
+#[test]
+fn test_extract() -> fb::Result<()> {
+    let original = item();
+    let mut builder = fb::FlatBufferBuilder::new();
+    let offset = original.pack(&mut builder);
+    builder.finish_minimal(offset);
+    let batch = builder.finished_data();
+
+    let related = fb::get_root::<Item>(batch)?.related()?.unwrap();
+    let message = related.extract()?;
+    assert!(message.data().len() < batch.len());
+    let extracted = message.root()?;
+    assert_eq!(extracted.name()?, "Bulb");
+    assert_eq!(extracted.unpack()?, *original.related.unwrap());
+    fb::verified_root::<Item>(message.data())?;
+
+    // extracting the root copies all of the buffer
+    let message = fb::Message::<ItemOffset>::extract(&fb::get_root::<Item>(batch)?)?;
+    assert_eq!(message.root()?.unpack()?, item());
+    Ok(())
+}
+
+#[test]
+fn test_merge_messages() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let offset = item().pack(&mut builder);
+    builder.finish_minimal(offset);
+    let incoming = fb::get_root::<Item>(builder.finished_data())?;
+
+    // Splice fields of the incoming item into a new one, along with a field
+    // of its own.
+    let mut merged = fb::FlatBufferBuilder::new();
+    let name = merged.create_string("Lamp, refurbished");
+    let labels = incoming
+        .labels()?
+        .unwrap()
+        .iter()
+        .map(|label| label?.copy_to(&mut merged))
+        .collect::<fb::Result<Vec<_>>>()?;
+    let labels = merged.create_vector(&labels);
+    let related = incoming.related()?.unwrap().copy_to(&mut merged)?;
+    let tag = incoming.tag_as_sticker()?.unwrap().copy_to(&mut merged)?;
+    let offset = Item::create(
+        &mut merged,
+        &ItemArgs {
+            name,
+            size: Size::Medium,
+            price: 0,
+            dimensions: None,
+            labels: Some(labels),
+            keywords: None,
+            ratings: None,
+            boxes: None,
+            sizes: None,
+            tag_type: TagType::Sticker,
+            tag: Some(tag.as_union_value()),
+            related: Some(related),
+        },
+    );
+    merged.finish_minimal(offset);
+
+    let item = fb::verified_root::<Item>(merged.finished_data())?;
+    let original = self::item();
+    assert_eq!(item.name()?, "Lamp, refurbished");
+    let item = item.unpack()?;
+    assert_eq!(item.labels, original.labels);
+    assert_eq!(item.related, original.related);
+    assert_eq!(item.tag, original.tag);
+    assert_eq!(item.keywords, None);
+    Ok(())
+}
+
+#[test]
+fn test_create_direct() -> fb::Result<()> {
+    let original = item();
+    let mut packed = fb::FlatBufferBuilder::new();
+    let offset = original.pack(&mut packed);
+    packed.finish_minimal(offset);
+
+    let mut builder = fb::FlatBufferBuilder::new();
+    let offset = Item::create_direct(
+        &mut builder,
+        &ItemDirectArgs {
+            name: "Lamp",
+            size: Size::Large,
+            price: 25,
+            dimensions: Some(&Dimensions::new(0.5, 1.5)),
+            labels: original.labels.as_deref(),
+            keywords: Some(&["light", "desk"]),
+            ratings: Some(&[5, 4, 5]),
+            boxes: Some(&[Dimensions::new(1.0, 2.0)]),
+            sizes: Some(&[Size::Small, Size::Large]),
+            tag: Some(&original.tag),
+            related: original.related.as_deref(),
+        },
+    );
+    builder.finish_minimal(offset);
+
+    assert_eq!(builder.finished_data(), packed.finished_data());
+    let item = fb::get_root::<Item>(builder.finished_data())?;
+    assert_eq!(item.unpack()?, original);
+    Ok(())
+}
+
+/// An item whose optional strings and vectors are all empty.
+fn empty_item() -> ItemT {
+    ItemT {
+        name: String::new(),
+        size: Size::Medium,
+        price: 0,
+        dimensions: None,
+        labels: Some(vec![LabelT {
+            text: Some(String::new()),
+        }]),
+        keywords: Some(vec![]),
+        ratings: Some(vec![]),
+        boxes: Some(vec![]),
+        sizes: Some(vec![]),
+        tag: TagT::NONE,
+        related: None,
+    }
+}
+
+#[test]
+fn test_crate_visibility() -> fb::Result<()> {
+    use objects_crate::shop::{Item, ItemT, Size, TagT};
+
+    // the items are only visible within the crate, and work like public ones
+    let original = ItemT {
+        name: "Lamp".to_string(),
+        size: Size::Large,
+        price: 25,
+        dimensions: None,
+        labels: None,
+        keywords: None,
+        ratings: None,
+        boxes: None,
+        sizes: None,
+        tag: TagT::NONE,
+        related: None,
+    };
+    let mut builder = fb::FlatBufferBuilder::new();
+    let offset = original.pack(&mut builder);
+    builder.finish_minimal(offset);
+    assert_eq!(
+        fb::verified_root::<Item>(builder.finished_data())?.unpack()?,
+        original
+    );
+    let item = fb::verified_root::<objects::shop::Item>(builder.finished_data())?;
+    assert_eq!(item.name()?, "Lamp");
+    Ok(())
+}
+
+#[test]
+fn test_shared_empty_collections() -> fb::Result<()> {
+    use objects_shared::shop::{Item, ItemDirectArgs, ItemT, LabelT, Size, TagT};
+
+    let original = ItemT {
+        name: String::new(),
+        size: Size::Medium,
+        price: 0,
+        dimensions: None,
+        labels: Some(vec![LabelT {
+            text: Some(String::new()),
+        }]),
+        keywords: Some(vec![]),
+        ratings: Some(vec![]),
+        boxes: Some(vec![]),
+        sizes: Some(vec![]),
+        tag: TagT::NONE,
+        related: None,
+    };
+    let mut builder = fb::FlatBufferBuilder::new();
+    let offset = original.pack(&mut builder);
+    builder.finish_minimal(offset);
+    let shared = builder.finished_data().to_vec();
+
+    // the empty strings and vectors are one string, but read back the same
+    let mut builder = fb::FlatBufferBuilder::new();
+    let offset = empty_item().pack(&mut builder);
+    builder.finish_minimal(offset);
+    assert!(shared.len() + 16 <= builder.finished_data().len());
+    assert_eq!(fb::verified_root::<Item>(&shared)?.unpack()?, original);
+
+    let mut builder = fb::FlatBufferBuilder::new();
+    let offset = Item::create_direct(
+        &mut builder,
+        &ItemDirectArgs {
+            name: "",
+            size: Size::Medium,
+            price: 0,
+            dimensions: None,
+            labels: None,
+            keywords: Some(&[]),
+            ratings: Some(&[]),
+            boxes: None,
+            sizes: None,
+            tag: None,
+            related: None,
+        },
+    );
+    builder.finish_minimal(offset);
+    let item = fb::verified_root::<Item>(builder.finished_data())?;
+    assert_eq!(item.name()?, "");
+    assert_eq!(item.keywords()?.map(|keywords| keywords.len()), Some(0));
+    assert_eq!(item.ratings()?.map(|ratings| ratings.len()), Some(0));
+    Ok(())
+}
+
+#[test]
+fn test_omitted_empty_collections() -> fb::Result<()> {
+    use objects_omitted::shop::{Item, ItemDirectArgs, ItemT, LabelT, Size, TagT};
+
+    let mut builder = fb::FlatBufferBuilder::new();
+    let offset = ItemT {
+        name: String::new(),
+        size: Size::Medium,
+        price: 0,
+        dimensions: None,
+        labels: Some(vec![LabelT {
+            text: Some(String::new()),
+        }]),
+        keywords: Some(vec![]),
+        ratings: Some(vec![]),
+        boxes: Some(vec![]),
+        sizes: None,
+        tag: TagT::NONE,
+        related: None,
+    }
+    .pack(&mut builder);
+    builder.finish_minimal(offset);
+
+    // empty values are left out, and read as missing
+    let item = fb::verified_root::<Item>(builder.finished_data())?;
+    assert_eq!(item.name()?, "");
+    assert_eq!(item.keywords()?, None);
+    assert_eq!(item.labels()?.unwrap().get(0)?.text()?, None);
+    let unpacked = item.unpack()?;
+    assert_eq!(unpacked.keywords, None);
+    assert_eq!(unpacked.ratings, None);
+    assert_eq!(unpacked.labels.unwrap()[0].text, None);
+
+    // a buffer written elsewhere with empty values unpacks them as `None`
+    let mut builder = fb::FlatBufferBuilder::new();
+    let offset = empty_item().pack(&mut builder);
+    builder.finish_minimal(offset);
+    let unpacked = fb::get_root::<Item>(builder.finished_data())?.unpack()?;
+    assert_eq!(unpacked.boxes, None);
+    assert_eq!(unpacked.sizes, None);
+
+    let mut builder = fb::FlatBufferBuilder::new();
+    let offset = Item::create_direct(
+        &mut builder,
+        &ItemDirectArgs {
+            name: "Lamp",
+            size: Size::Medium,
+            price: 0,
+            dimensions: None,
+            labels: None,
+            keywords: Some(&[]),
+            ratings: Some(&[1]),
+            boxes: None,
+            sizes: None,
+            tag: None,
+            related: None,
+        },
+    );
+    builder.finish_minimal(offset);
+    let item = fb::get_root::<Item>(builder.finished_data())?;
+    assert_eq!(item.keywords()?, None);
+    assert_eq!(item.ratings()?.map(|ratings| ratings.len()), Some(1));
+    Ok(())
+}
+
+/// A buffer holding `depth` items, each related to the next, whose first
+/// item has `labels` labels that are all the same table.
+fn nested_items(depth: usize, labels: usize) -> Vec<u8> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let label = Label::create(&mut builder, &LabelArgs { text: None });
+    let mut related = None;
+    for i in 0..depth {
+        let labels = if i + 1 == depth {
+            Some(builder.create_vector(&vec![label; labels]))
+        } else {
+            None
+        };
+        let name = builder.create_string("Box");
+        related = Some(Item::create(
+            &mut builder,
+            &ItemArgs {
+                name,
+                size: Size::Medium,
+                price: 0,
+                dimensions: None,
+                labels,
+                keywords: None,
+                ratings: None,
+                boxes: None,
+                sizes: None,
+                tag_type: TagType::NONE,
+                tag: None,
+                related,
+            },
+        ));
+    }
+    builder.finish_minimal(related.unwrap());
+    builder.finished_data().to_vec()
+}
+
+#[test]
+fn test_verifier_limits() -> fb::Result<()> {
+    let options = fb::VerifierOptions::default();
+    let verify =
+        |buf: &[u8], options| fb::verified_root_with_options::<Item>(buf, options).map(|_| ());
+
+    // tables may be nested as deeply as the options allow, but no deeper
+    let buf = nested_items(options.max_depth, 0);
+    assert_eq!(verify(&buf, options), Ok(()));
+    let buf = nested_items(options.max_depth + 1, 0);
+    assert_eq!(verify(&buf, options), Err(fb::Error::TooManyLevels));
+    assert_eq!(
+        fb::verified_root::<Item>(&buf).map(|_| ()),
+        Err(fb::Error::TooManyLevels)
+    );
+    // reading it without verifying it is still possible
+    assert_eq!(fb::get_root::<Item>(&buf)?.name()?, "Box");
+
+    let buf = nested_items(10, 0);
+    let shallow = fb::VerifierOptions {
+        max_depth: 9,
+        ..options
+    };
+    assert_eq!(verify(&buf, shallow), Err(fb::Error::TooManyLevels));
+
+    // each label is counted, even though they are all the same table
+    let buf = nested_items(1, 100);
+    assert_eq!(verify(&buf, options), Ok(()));
+    let few_tables = fb::VerifierOptions {
+        max_tables: 100,
+        ..options
+    };
+    assert_eq!(verify(&buf, few_tables), Err(fb::Error::TooManyTables));
+    let small = fb::VerifierOptions {
+        max_apparent_size: buf.len(),
+        ..options
+    };
+    assert_eq!(verify(&buf, small), Err(fb::Error::ApparentSizeTooLarge));
+    Ok(())
+}
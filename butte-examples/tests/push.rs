@@ -0,0 +1,98 @@
+use butte as fb;
+use butte::Push;
+
+#[allow(dead_code)]
+mod structs {
+    butte_build::include_fbs!("structs");
+}
+
+use structs::geometry::{Color, Padded, Vec3};
+
+/// The bytes `value` is written as, followed by `rest`.
+fn pushed<P: Push>(value: P, rest: &[u8]) -> Vec<u8> {
+    let mut dst = vec![0xAA; P::size()];
+    value.push(&mut dst, rest);
+    dst
+}
+
+#[test]
+fn test_push_scalars() {
+    assert_eq!(pushed(true, &[]), [1]);
+    assert_eq!(pushed(false, &[]), [0]);
+    assert_eq!(pushed(0x12_u8, &[]), [0x12]);
+    assert_eq!(pushed(-2_i8, &[]), [0xFE]);
+    assert_eq!(pushed(0x1234_u16, &[]), [0x34, 0x12]);
+    assert_eq!(pushed(-2_i16, &[]), [0xFE, 0xFF]);
+    assert_eq!(pushed(0x1234_5678_u32, &[]), [0x78, 0x56, 0x34, 0x12]);
+    assert_eq!(pushed(-2_i32, &[]), [0xFE, 0xFF, 0xFF, 0xFF]);
+    assert_eq!(
+        pushed(0x0102_0304_0506_0708_u64, &[]),
+        [8, 7, 6, 5, 4, 3, 2, 1]
+    );
+    assert_eq!(pushed(i64::MIN, &[]), [0, 0, 0, 0, 0, 0, 0, 0x80]);
+    assert_eq!(pushed(1.5_f32, &[]), 1.5_f32.to_le_bytes());
+    assert_eq!(pushed(-0.25_f64, &[]), (-0.25_f64).to_le_bytes());
+
+    assert_eq!(<u16 as Push>::size(), 2);
+    assert_eq!(<f64 as Push>::size(), 8);
+    assert_eq!(<bool as Push>::alignment().value(), 1);
+    assert_eq!(<i64 as Push>::alignment().value(), 8);
+}
+
+#[test]
+fn test_push_offsets() {
+    // an offset is written as the distance to the data it refers to, which
+    // is `value()` bytes from the end of the buffer
+    let rest = [0; 12];
+    assert_eq!(pushed(fb::WIPOffset::<()>::new(8), &rest), [8, 0, 0, 0]);
+    assert_eq!(
+        pushed(Some(fb::WIPOffset::<()>::new(8)), &rest),
+        [8, 0, 0, 0]
+    );
+    assert_eq!(pushed(None::<fb::WIPOffset<()>>, &rest), [0, 0, 0, 0]);
+    assert_eq!(
+        pushed(fb::WIPOffset64::<()>::new(4), &rest),
+        [16, 0, 0, 0, 0, 0, 0, 0]
+    );
+    assert_eq!(<fb::WIPOffset<()> as Push>::size(), 4);
+    assert_eq!(<fb::WIPOffset64<()> as Push>::alignment().value(), 8);
+
+    // an offset written with the builder refers back to its data
+    let mut builder = fb::FlatBufferBuilder::new();
+    let value = builder.push(7_u32);
+    let offset = builder.push(value);
+    let data = builder.unfinished_data();
+    assert_eq!(offset.value() - value.value(), 4);
+    assert_eq!(data, [4, 0, 0, 0, 7, 0, 0, 0]);
+}
+
+#[test]
+fn test_push_enums_and_structs() {
+    assert_eq!(pushed(Color::Blue, &[]), [2]);
+
+    let point = Vec3::new(1.0, 2.0, 3.0);
+    let bytes = [1.0_f32, 2.0, 3.0]
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect::<Vec<_>>();
+    assert_eq!(pushed(point, &[]), bytes);
+    assert_eq!(pushed::<&Vec3>(&point, &[]), bytes);
+    assert_eq!(<Padded as Push>::alignment().value(), 16);
+
+    // structs are aligned to their alignment in the buffer
+    let mut builder = fb::FlatBufferBuilder::new();
+    builder.push(1_u8);
+    builder.push(Padded::new(1.0, 2.0, 3.0));
+    assert_eq!(builder.unfinished_data().len(), 32);
+}
+
+#[test]
+fn test_padding() {
+    assert_eq!(fb::padding_bytes(0, 8), 0);
+    assert_eq!(fb::padding_bytes(1, 8), 7);
+    assert_eq!(fb::padding_bytes(6, 4), 2);
+    assert_eq!(fb::padding_bytes(9, 1), 0);
+    let alignment = fb::PushAlignment::new(4);
+    assert_eq!(alignment.max_of(2), alignment);
+    assert_eq!(alignment.max_of(16).value(), 16);
+}
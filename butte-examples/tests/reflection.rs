@@ -0,0 +1,115 @@
+use butte as fb;
+use butte::reflection::{
+    get_any_root, get_field_float, get_field_integer, get_field_string, get_field_table,
+    get_schema, BaseType,
+};
+use butte_build::{include::SchemaFiles, json_schema, reflection_schema};
+
+#[allow(dead_code)]
+mod objects {
+    butte_build::include_fbs!("objects");
+}
+
+use objects::shop::{Dimensions, Item, ItemArgs, Label, LabelArgs, Size, TagType};
+
+fn lamp() -> Vec<u8> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let text = builder.create_string("new");
+    let label = Label::create(&mut builder, &LabelArgs { text: Some(text) });
+    let name = builder.create_string("Lamp");
+    let dimensions = Dimensions::new(0.5, 1.5);
+    let item = Item::create(
+        &mut builder,
+        &ItemArgs {
+            name,
+            size: Size::Large,
+            price: 25,
+            dimensions: Some(&dimensions),
+            labels: None,
+            keywords: None,
+            ratings: None,
+            boxes: None,
+            sizes: None,
+            tag_type: TagType::Label,
+            tag: Some(label.as_union_value()),
+            related: None,
+        },
+    );
+    builder.finish_minimal(item);
+    builder.finished_data().to_vec()
+}
+
+fn bfbs() -> anyhow::Result<Vec<u8>> {
+    reflection_schema(&SchemaFiles::load("fbs/objects/objects.fbs", &[])?)
+}
+
+#[test]
+fn test_introspect_schema() -> anyhow::Result<()> {
+    let bfbs = bfbs()?;
+    let schema = get_schema(&bfbs)?;
+    let item = schema.root_table()?.unwrap();
+    assert_eq!(item.name()?, "shop.Item");
+    assert!(!item.is_struct()?);
+
+    let size = item.field_by_name("size")?.unwrap();
+    let ty = size.type_()?;
+    assert_eq!(ty.base_type()?, BaseType::BYTE);
+    let size_enum = schema.enums()?.get(ty.index()? as usize)?;
+    assert_eq!(size_enum.name()?, "shop.Size");
+    assert_eq!(size.default_integer()?, Size::Medium as i64);
+
+    let dimensions = schema.object_by_name("shop.Dimensions")?.unwrap();
+    assert!(dimensions.is_struct()?);
+    assert_eq!(dimensions.bytesize()?, 8);
+    assert!(schema.object_by_name("shop.Missing")?.is_none());
+    Ok(())
+}
+
+#[test]
+fn test_read_fields_at_runtime() -> anyhow::Result<()> {
+    let bfbs = bfbs()?;
+    let schema = get_schema(&bfbs)?;
+    let item = schema.root_table()?.unwrap();
+    let field = |name| item.field_by_name(name).map(Option::unwrap);
+
+    let buf = lamp();
+    let root = get_any_root(&buf)?;
+    assert_eq!(get_field_string(&root, &field("name")?)?, Some("Lamp"));
+    assert_eq!(
+        get_field_integer(&root, &field("size")?)?,
+        Size::Large as i64
+    );
+    assert_eq!(get_field_integer(&root, &field("price")?)?, 25);
+    assert_eq!(
+        get_field_integer(&root, &field("tag_type")?)?,
+        TagType::Label as i64
+    );
+    assert_eq!(
+        get_field_integer(&root, &field("name")?),
+        Err(fb::Error::TypeMismatch)
+    );
+
+    let label = schema.object_by_name("shop.Label")?.unwrap();
+    let tag = get_field_table(&root, &field("tag")?)?.unwrap();
+    let text = label.field_by_name("text")?.unwrap();
+    assert_eq!(get_field_string(&tag, &text)?, Some("new"));
+    assert!(get_field_table(&root, &field("related")?)?.is_none());
+    assert_eq!(
+        get_field_float(&root, &field("price")?),
+        Err(fb::Error::TypeMismatch)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_json_from_binary_schema() -> anyhow::Result<()> {
+    let bfbs = bfbs()?;
+    let from_bfbs = get_schema(&bfbs)?.to_json_schema()?;
+    let from_fbs = json_schema(&SchemaFiles::load("fbs/objects/objects.fbs", &[])?)?;
+    let buf = lamp();
+    let json = fb::json::to_json(&from_bfbs, &buf)?;
+    assert_eq!(json, fb::json::to_json(&from_fbs, &buf)?);
+    let round_trip = fb::json::from_json(&from_bfbs, &json)?;
+    assert_eq!(fb::json::to_json(&from_fbs, &round_trip)?, json);
+    Ok(())
+}
@@ -0,0 +1,736 @@
+use butte as fb;
+
+#[allow(dead_code)]
+mod scalars {
+    butte_build::include_fbs!("scalars");
+}
+
+use scalars::stats::{
+    Access, Counters, CountersArgs, Defaults, DefaultsArgs, Limits, LimitsArgs, Mask, Mode, Offset,
+    Permissions, PermissionsArgs, Record, RecordArgs, Settings, SettingsArgs, Specials,
+    SpecialsArgs, Tagged, TaggedBuilder,
+};
+
+#[test]
+fn test_scalar_round_trip() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let counters = Counters::create(
+        &mut builder,
+        &CountersArgs {
+            flag: true,
+            small: -7,
+            medium: 1234,
+            large: -9_000_000_000,
+            ratio: 0.25,
+            precise: 1e100,
+        },
+    );
+    builder.finish_minimal(counters);
+
+    let counters = fb::get_root::<Counters>(builder.finished_data())?;
+    assert!(counters.flag()?);
+    assert_eq!(counters.small()?, -7);
+    assert_eq!(counters.medium()?, 1234);
+    assert_eq!(counters.large()?, -9_000_000_000);
+    assert_eq!(counters.ratio()?, 0.25);
+    assert_eq!(counters.precise()?, 1e100);
+    Ok(())
+}
+
+#[test]
+fn test_unset_scalars_read_as_zero() -> fb::Result<()> {
+    // scalars without a default value default to zero, and are left out of
+    // the table when they equal it
+    let mut builder = fb::FlatBufferBuilder::new();
+    let counters = Counters::create(&mut builder, &CountersArgs::default());
+    builder.finish_minimal(counters);
+
+    let data = builder.finished_data();
+    let table = fb::Table::new(data, fb::read_scalar::<fb::UOffsetT>(data)? as usize);
+    let vtable = table.vtable()?;
+    assert!((0..6).all(|idx| vtable.get_field(idx) == 0));
+
+    let counters = fb::get_root::<Counters>(data)?;
+    assert!(!counters.flag()?);
+    assert_eq!(counters.small()?, 0);
+    assert_eq!(counters.medium()?, 0);
+    assert_eq!(counters.large()?, 0);
+    assert_eq!(counters.ratio()?, 0.0);
+    assert_eq!(counters.precise()?, 0.0);
+    Ok(())
+}
+
+#[test]
+fn test_read_and_write_scalars() {
+    // scalars are read from their little-endian bytes at any position
+    let data = [0xff, 0x01, 0x02, 0x03, 0x04, 0x00, 0x00, 0x80, 0x3f];
+    assert_eq!(fb::read_scalar_at::<u32>(&data, 1), Ok(0x0403_0201));
+    assert_eq!(fb::read_scalar_at::<i16>(&data, 0), Ok(0x01ff));
+    assert_eq!(fb::read_scalar_at::<f32>(&data, 5), Ok(1.0));
+    assert_eq!(fb::read_scalar::<u8>(&data), Ok(0xff));
+    assert!(matches!(
+        fb::read_scalar_at::<u64>(&data, 2),
+        Err(fb::Error::OutOfBounds { .. })
+    ));
+    assert!(matches!(
+        fb::read_scalar::<u16>(&[]),
+        Err(fb::Error::OutOfBounds { .. })
+    ));
+
+    // any byte but 0 is read as true
+    assert_eq!(fb::read_scalar::<bool>(&[2]), Ok(true));
+    assert_eq!(fb::read_scalar::<bool>(&[0]), Ok(false));
+
+    let mut data = [0; 9];
+    fb::emplace_scalar(&mut data[1..], -2i32);
+    fb::emplace_scalar(&mut data[5..], 1.0f32);
+    assert_eq!(data, [0, 0xfe, 0xff, 0xff, 0xff, 0x00, 0x00, 0x80, 0x3f]);
+}
+
+#[test]
+fn test_size_prefixed_buffer_alignment() -> fb::Result<()> {
+    for with_identifier in &[false, true] {
+        let mut builder = fb::FlatBufferBuilder::new();
+        let counters = Counters::create(
+            &mut builder,
+            &CountersArgs {
+                flag: true,
+                small: 1,
+                medium: 2,
+                large: 3,
+                ratio: 0.5,
+                precise: 0.25,
+            },
+        );
+        let identifier = if *with_identifier {
+            Some(fb::FileIdent::new("CNTR"))
+        } else {
+            None
+        };
+        builder.finish_size_prefixed(counters, identifier);
+
+        // The prefix, root offset and identifier are padded so that the
+        // buffer stays aligned to its largest scalar.
+        let buf = builder.finished_data();
+        assert_eq!(buf.len() % 8, 0);
+        let size = fb::read_scalar::<fb::UOffsetT>(buf)? as usize;
+        assert_eq!(size, buf.len() - fb::SIZE_UOFFSET);
+        assert_eq!(
+            fb::buffer_has_identifier(buf, fb::FileIdent::new("CNTR"), true),
+            *with_identifier
+        );
+
+        let counters = fb::get_size_prefixed_root::<Counters>(buf)?;
+        assert_eq!(counters.large()?, 3);
+        assert_eq!(counters.precise()?, 0.25);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_truncated_size_prefixed_buffers() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let counters = Counters::create(
+        &mut builder,
+        &CountersArgs {
+            flag: false,
+            small: 0,
+            medium: 0,
+            large: 3,
+            ratio: 0.0,
+            precise: 0.0,
+        },
+    );
+    builder.finish_size_prefixed(counters, Some(fb::FileIdent::new("CNTR")));
+    let buf = builder.finished_data();
+
+    // the size prefix itself is cut off
+    assert_eq!(
+        fb::get_size_prefixed_root::<Counters>(&buf[..2]).map(|_| ()),
+        Err(fb::Error::OutOfBounds {
+            loc: 0,
+            len: fb::SIZE_SIZEPREFIX,
+            buf_len: 2
+        })
+    );
+    // the size prefix covers more than the rest of the buffer
+    let truncated = &buf[..buf.len() - 1];
+    let error = fb::Error::OutOfBounds {
+        loc: fb::SIZE_SIZEPREFIX,
+        len: buf.len() - fb::SIZE_SIZEPREFIX,
+        buf_len: truncated.len(),
+    };
+    assert_eq!(
+        fb::get_size_prefixed_root::<Counters>(truncated).map(|_| ()),
+        Err(error)
+    );
+    assert_eq!(
+        fb::verified_size_prefixed_root::<Counters>(truncated).map(|_| ()),
+        Err(error)
+    );
+    for len in 0..buf.len() {
+        assert!(!fb::buffer_has_identifier(
+            &buf[..len],
+            fb::FileIdent::new("CNTR"),
+            true
+        ));
+        assert!(!fb::buffer_has_identifier(
+            &buf[..len],
+            fb::FileIdent::new("CNTR"),
+            false
+        ));
+    }
+
+    // bytes after the data the size prefix covers are ignored
+    let mut padded = buf.to_vec();
+    padded.extend_from_slice(&[0xff; 8]);
+    assert!(fb::buffer_has_identifier(
+        &padded,
+        fb::FileIdent::new("CNTR"),
+        true
+    ));
+    let counters = fb::verified_size_prefixed_root::<Counters>(&padded)?;
+    assert_eq!(counters.large()?, 3);
+    Ok(())
+}
+
+#[test]
+fn test_defaults_are_omitted() -> fb::Result<()> {
+    let mut defaults = fb::FlatBufferBuilder::new();
+    let table = Defaults::create(
+        &mut defaults,
+        &DefaultsArgs {
+            hp: 100,
+            speed: 1.5,
+            enabled: true,
+            level: 0,
+        },
+    );
+    defaults.finish_minimal(table);
+
+    let mut custom = fb::FlatBufferBuilder::new();
+    let table = Defaults::create(
+        &mut custom,
+        &DefaultsArgs {
+            hp: 50,
+            speed: 3.0,
+            enabled: false,
+            level: 7,
+        },
+    );
+    custom.finish_minimal(table);
+
+    assert!(defaults.finished_data().len() < custom.finished_data().len());
+
+    let table = fb::get_root::<Defaults>(defaults.finished_data())?;
+    assert_eq!(table.hp()?, 100);
+    assert_eq!(table.speed()?, 1.5);
+    assert!(table.enabled()?);
+    assert_eq!(table.level()?, 0);
+
+    let table = fb::get_root::<Defaults>(custom.finished_data())?;
+    assert_eq!(table.hp()?, 50);
+    assert_eq!(table.speed()?, 3.0);
+    assert!(!table.enabled()?);
+    assert_eq!(table.level()?, 7);
+    Ok(())
+}
+
+#[test]
+fn test_field_presence() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let table = Defaults::create(
+        &mut builder,
+        &DefaultsArgs {
+            hp: 100,
+            speed: 3.0,
+            enabled: true,
+            level: 0,
+        },
+    );
+    builder.finish_minimal(table);
+
+    // fields holding their default value are left out, so they read the
+    // same as absent fields, but aren't present
+    let table = fb::get_root::<Defaults>(builder.finished_data())?;
+    assert_eq!(table.hp()?, 100);
+    assert!(!table.has_hp()?);
+    assert!(table.has_speed()?);
+    assert!(!table.has_enabled()?);
+    assert!(!table.has_level()?);
+    Ok(())
+}
+
+#[test]
+fn test_manual_builder() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let mut defaults = scalars::stats::DefaultsBuilder::new(&mut builder);
+    defaults.add_level(3);
+    let table = defaults.finish();
+    builder.finish_minimal(table);
+
+    let table = fb::get_root::<Defaults>(builder.finished_data())?;
+    assert_eq!(table.hp()?, 100);
+    assert_eq!(table.level()?, 3);
+    Ok(())
+}
+
+#[test]
+fn test_build_table() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let table = builder.build_table(|table| {
+        table.push_slot(Defaults::VT_HP, 7i16, 100);
+        table.push_slot(Defaults::VT_LEVEL, 0u32, 0);
+    });
+    builder.finish_minimal(table);
+
+    let table = fb::get_root::<Defaults>(builder.finished_data())?;
+    assert_eq!(table.hp()?, 7);
+    assert!(!table.has_level()?);
+    Ok(())
+}
+
+#[test]
+#[should_panic(
+    expected = "create_string can not be called while a table or vector is under construction"
+)]
+fn test_string_in_table() {
+    let mut builder = fb::FlatBufferBuilder::new();
+    builder.start_table();
+    builder.create_string("nested");
+}
+
+#[test]
+#[should_panic(
+    expected = "create_vector can not be called while a table or vector is under construction"
+)]
+fn test_vector_in_vector() {
+    let mut builder = fb::FlatBufferBuilder::new();
+    builder.start_vector::<u8>(1);
+    builder.create_vector(&[1u8]);
+}
+
+#[test]
+#[should_panic(expected = "push_slot must be called while a table or vector is under construction")]
+fn test_field_outside_table() {
+    let mut builder = fb::FlatBufferBuilder::new();
+    builder.push_slot(Defaults::VT_HP, 7i16, 100);
+}
+
+#[test]
+fn test_vtables_are_shared() -> fb::Result<()> {
+    let args = DefaultsArgs {
+        hp: 1,
+        speed: 2.0,
+        enabled: false,
+        level: 3,
+    };
+
+    let mut builder = fb::FlatBufferBuilder::new();
+    let first = Defaults::create(&mut builder, &args);
+    let second = Defaults::create(&mut builder, &args);
+    let both = builder.create_vector(&[first, second]);
+    builder.finish_minimal(both);
+
+    let tables =
+        fb::get_root::<fb::Vector<fb::ForwardsUOffset<fb::Table>>>(builder.finished_data())?;
+    let vtable_locs = tables
+        .iter()
+        .map(|table| {
+            let table = table?;
+            Ok(table.loc as i64
+                - i64::from(fb::read_scalar_at::<fb::SOffsetT>(table.buf, table.loc)?))
+        })
+        .collect::<fb::Result<Vec<_>>>()?;
+    assert_eq!(vtable_locs[0], vtable_locs[1]);
+    Ok(())
+}
+
+#[test]
+fn test_enum_and_null_defaults() -> fb::Result<()> {
+    let mut defaults = fb::FlatBufferBuilder::new();
+    let table = Settings::create(
+        &mut defaults,
+        &SettingsArgs {
+            mode: Mode::Fast,
+            fallback: Mode::Normal,
+            muted: false,
+            volume: None,
+            preferred: None,
+            initial: Mode::Slow,
+        },
+    );
+    defaults.finish_minimal(table);
+
+    let mut custom = fb::FlatBufferBuilder::new();
+    let table = Settings::create(
+        &mut custom,
+        &SettingsArgs {
+            mode: Mode::Slow,
+            fallback: Mode::Fast,
+            muted: true,
+            volume: Some(0),
+            preferred: Some(Mode::Slow),
+            initial: Mode::Normal,
+        },
+    );
+    custom.finish_minimal(table);
+
+    assert!(defaults.finished_data().len() < custom.finished_data().len());
+
+    let table = fb::get_root::<Settings>(defaults.finished_data())?;
+    assert_eq!(table.mode()?, Mode::Fast);
+    assert_eq!(table.fallback()?, Mode::Normal);
+    assert!(!table.muted()?);
+    assert_eq!(table.volume()?, None);
+    assert_eq!(table.preferred()?, None);
+    assert_eq!(table.initial()?, Mode::Slow);
+
+    let table = fb::get_root::<Settings>(custom.finished_data())?;
+    assert_eq!(table.mode()?, Mode::Slow);
+    assert_eq!(table.fallback()?, Mode::Fast);
+    assert!(table.muted()?);
+    assert_eq!(table.volume()?, Some(0));
+    assert_eq!(table.preferred()?, Some(Mode::Slow));
+    assert_eq!(table.initial()?, Mode::Normal);
+    Ok(())
+}
+
+#[test]
+fn test_default_args() -> fb::Result<()> {
+    let args = DefaultsArgs::default();
+    assert_eq!(
+        (args.hp, args.speed, args.enabled, args.level),
+        (100, 1.5, true, 0)
+    );
+    let args = SettingsArgs::default();
+    assert_eq!((args.mode, args.fallback), (Mode::Fast, Mode::Normal));
+    assert_eq!((args.volume, args.preferred), (None, None));
+    assert_eq!(args.initial, Mode::Slow);
+    assert_eq!(
+        PermissionsArgs::default().owner,
+        Access::Read | Access::Write
+    );
+
+    let mut builder = fb::FlatBufferBuilder::new();
+    let table = Defaults::create(
+        &mut builder,
+        &DefaultsArgs {
+            hp: 500,
+            ..Default::default()
+        },
+    );
+    builder.finish_minimal(table);
+
+    let table = fb::get_root::<Defaults>(builder.finished_data())?;
+    assert_eq!(table.hp()?, 500);
+    assert_eq!(table.speed()?, 1.5);
+    assert!(table.enabled()?);
+    assert!(!table.has_speed()?);
+    Ok(())
+}
+
+#[test]
+fn test_integer_limits() -> fb::Result<()> {
+    use scalars::stats::{ENUM_MAX_MASK, ENUM_MIN_OFFSET};
+
+    let args = LimitsArgs::default();
+    assert_eq!(
+        (args.biggest, args.smallest, args.top_bit),
+        (u64::MAX, i64::MIN, 1 << 63)
+    );
+    assert_eq!((args.offset, args.mask), (Offset::Min, Mask::High));
+    assert_eq!(u64::from(Mask::High), u64::MAX);
+    assert_eq!(i64::from(Offset::Max), i64::MAX);
+    assert_eq!((ENUM_MIN_OFFSET, ENUM_MAX_MASK), (i64::MIN, u64::MAX));
+
+    let mut builder = fb::FlatBufferBuilder::new();
+    let table = Limits::create(&mut builder, &args);
+    builder.finish_minimal(table);
+    let table = fb::get_root::<Limits>(builder.finished_data())?;
+    assert!(!table.has_biggest()?);
+    assert_eq!(table.biggest()?, u64::MAX);
+    assert_eq!(table.smallest()?, i64::MIN);
+    assert_eq!(table.top_bit()?, 1 << 63);
+    assert_eq!(table.offset()?, Offset::Min);
+    assert_eq!(table.mask()?, Mask::High);
+    Ok(())
+}
+
+#[test]
+fn test_special_float_defaults() -> fb::Result<()> {
+    let args = SpecialsArgs::default();
+    assert!(args.missing.is_nan());
+    assert_eq!(
+        (args.ceiling, args.floor),
+        (f64::INFINITY, f32::NEG_INFINITY)
+    );
+    assert_eq!((args.tiny, args.large, args.half), (1e-5, 2500.0, 0.5));
+    assert_eq!(args.mask, 255);
+
+    // a `nan` value is the default, and is left out like any other default
+    let mut builder = fb::FlatBufferBuilder::new();
+    let table = Specials::create(&mut builder, &args);
+    builder.finish_minimal(table);
+    let table = fb::get_root::<Specials>(builder.finished_data())?;
+    assert!(!table.has_missing()?);
+    assert!(table.missing()?.is_nan());
+    assert_eq!(table.ceiling()?, f64::INFINITY);
+    assert_eq!(table.floor()?, f32::NEG_INFINITY);
+    assert_eq!(table.tiny()?, 1e-5);
+    assert_eq!(table.mask()?, 0xFF);
+    Ok(())
+}
+
+#[test]
+fn test_strict_bools() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let table = Counters::create(
+        &mut builder,
+        &CountersArgs {
+            flag: true,
+            ..Default::default()
+        },
+    );
+    builder.finish_minimal(table);
+    let mut data = builder.finished_data().to_vec();
+    let flag = fb::get_root::<fb::Table>(&data)?
+        .field_range::<bool>(Counters::VT_FLAG)?
+        .unwrap()
+        .start;
+    data[flag] = 7;
+
+    // a lenient writer's bool reads as true, unless verification is strict
+    assert!(fb::verified_root::<Counters>(&data)?.flag()?);
+    let strict = fb::VerifierOptions {
+        strict_bools: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        fb::verified_root_with_options::<Counters>(&data, strict).unwrap_err(),
+        fb::Error::InvalidBool { loc: flag }
+    );
+    data[flag] = 1;
+    assert!(fb::verified_root_with_options::<Counters>(&data, strict)?.flag()?);
+    Ok(())
+}
+
+#[test]
+fn test_unknown_enum_value() {
+    use fb::Follow;
+
+    assert_eq!(Mode::follow(&[2], 0), Ok(Mode::Fast));
+    assert_eq!(Mode::follow(&[3], 0), Err(fb::Error::UnknownEnumValue));
+    assert_eq!(
+        Mode::follow(&[], 0),
+        Err(fb::Error::OutOfBounds {
+            loc: 0,
+            len: 1,
+            buf_len: 0
+        })
+    );
+}
+
+#[test]
+fn test_strict_enums() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let table = Settings::create(
+        &mut builder,
+        &SettingsArgs {
+            mode: Mode::Slow,
+            ..Default::default()
+        },
+    );
+    builder.finish_minimal(table);
+    let mut data = builder.finished_data().to_vec();
+    let mode = fb::get_root::<fb::Table>(&data)?
+        .field_range::<Mode>(Settings::VT_MODE)?
+        .unwrap()
+        .start;
+    data[mode] = 3;
+
+    // an unknown value fails when it is read, or when it is verified strictly
+    let settings = fb::verified_root::<Settings>(&data)?;
+    assert_eq!(settings.mode(), Err(fb::Error::UnknownEnumValue));
+    let strict = fb::VerifierOptions {
+        strict_enums: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        fb::verified_root_with_options::<Settings>(&data, strict).unwrap_err(),
+        fb::Error::UnknownEnumValue
+    );
+
+    // bits that aren't flags are kept, unless verification is strict
+    let mut builder = fb::FlatBufferBuilder::new();
+    let table = Permissions::create(
+        &mut builder,
+        &PermissionsArgs {
+            owner: Access::Execute,
+            ..Default::default()
+        },
+    );
+    builder.finish_minimal(table);
+    let mut data = builder.finished_data().to_vec();
+    let owner = fb::get_root::<fb::Table>(&data)?
+        .field_range::<Access>(Permissions::VT_OWNER)?
+        .unwrap()
+        .start;
+    data[owner] = 0x90;
+    let permissions = fb::verified_root::<Permissions>(&data)?;
+    assert_eq!(permissions.owner()?.bits(), 0x90);
+    assert_eq!(
+        fb::verified_root_with_options::<Permissions>(&data, strict).unwrap_err(),
+        fb::Error::UnknownEnumValue
+    );
+    data[owner] = 0x11;
+    fb::verified_root_with_options::<Permissions>(&data, fb::VerifierOptions::strict())?;
+    Ok(())
+}
+
+#[test]
+fn test_enum_conversions() {
+    use std::convert::TryFrom;
+
+    assert_eq!(Mode::try_from(1), Ok(Mode::Normal));
+    assert_eq!(Mode::try_from(-1), Err(fb::UnknownEnumValue(-1)));
+    assert_eq!(i8::from(Mode::Fast), 2);
+    let mode: i8 = Mode::Slow.into();
+    assert_eq!(mode, 0);
+    assert_eq!(
+        fb::UnknownEnumValue(7u8).to_string(),
+        "unknown enum value 7"
+    );
+    assert_eq!(
+        fb::Error::from(fb::UnknownEnumValue(7u8)),
+        fb::Error::UnknownEnumValue
+    );
+}
+
+#[test]
+fn test_enum_values() {
+    use scalars::stats::{ENUM_MAX_MODE, ENUM_MIN_MODE, ENUM_VALUES_MODE};
+
+    assert_eq!(ENUM_MIN_MODE, 0);
+    assert_eq!(ENUM_MAX_MODE, 2);
+    assert_eq!(ENUM_VALUES_MODE, [Mode::Slow, Mode::Normal, Mode::Fast]);
+    assert_eq!(Mode::variants().collect::<Vec<_>>(), ENUM_VALUES_MODE);
+    for mode in Mode::variants() {
+        assert!((ENUM_MIN_MODE..=ENUM_MAX_MODE).contains(&i8::from(mode)));
+    }
+}
+#[test]
+fn test_bit_flags() -> fb::Result<()> {
+    let mut flags = Access::Read | Access::Execute;
+    assert_eq!(flags.bits(), 0b1_0001);
+    assert!(flags.contains(Access::Read));
+    assert!(!flags.contains(Access::Read | Access::Write));
+    flags.insert(Access::Write);
+    flags.remove(Access::Read);
+    assert_eq!(flags, Access::Write | Access::Execute);
+    assert_eq!(flags & Access::Write, Access::Write);
+    assert_eq!(!flags, Access::Read);
+    assert!(Access::empty().is_empty());
+    assert_eq!(Access::all().bits(), 0b1_0011);
+    assert_eq!(Access::from_bits(0b10), Some(Access::Write));
+    assert_eq!(Access::from_bits(0b100), None);
+    assert_eq!(Access::from_bits_truncate(0b110), Access::Write);
+    assert_eq!(format!("{:?}", flags), "Access(Write | Execute)");
+    assert_eq!(format!("{:?}", Access::empty()), "Access(0x0)");
+
+    let mut builder = fb::FlatBufferBuilder::new();
+    let granted = builder.create_vector(&[Access::Read, Access::Write | Access::Execute]);
+    let table = Permissions::create(
+        &mut builder,
+        &PermissionsArgs {
+            owner: Access::Read | Access::Write,
+            others: Access::Execute,
+            granted: Some(granted),
+        },
+    );
+    builder.finish_minimal(table);
+
+    let table = fb::get_root::<Permissions>(builder.finished_data())?;
+    assert_eq!(table.owner()?, Access::Read | Access::Write);
+    assert_eq!(table.others()?, Access::Execute);
+    let granted = table.granted()?.expect("granted");
+    assert_eq!(granted.get(1)?, Access::Write | Access::Execute);
+
+    assert_eq!(
+        serde_json::to_string(&(Access::Read | Access::Execute)).unwrap(),
+        "\"Read Execute\""
+    );
+    assert_eq!(
+        serde_json::from_str::<Access>("\"Write Execute\"").unwrap(),
+        Access::Write | Access::Execute
+    );
+    Ok(())
+}
+
+#[test]
+fn test_hashed_fields() -> fb::Result<()> {
+    assert_eq!(fb::hash::fnv1_32(b"a"), 0x050c_5d7e);
+    assert_eq!(fb::hash::fnv1a_32(b"a"), 0xe40c_292c);
+    assert_eq!(fb::hash::fnv1_64(b"a"), 0xaf63_bd4c_8601_b7be);
+    assert_eq!(fb::hash::fnv1a_64(b"a"), 0xaf63_dc4c_8601_ec8c);
+    assert_eq!(fb::hash::fnv1a_32(b""), 0x811c_9dc5);
+
+    let mut builder = fb::FlatBufferBuilder::new();
+    let mut tagged = TaggedBuilder::new(&mut builder);
+    tagged.add_id_hash("sword");
+    tagged.add_key_hash("sword");
+    let tagged = tagged.finish();
+    builder.finish_minimal(tagged);
+
+    let tagged = fb::get_root::<Tagged>(builder.finished_data())?;
+    assert_eq!(Tagged::ID_HASH, fb::HashAlgorithm::Fnv1a_32);
+    assert_eq!(Tagged::KEY_HASH, fb::HashAlgorithm::Fnv1_64);
+    assert_eq!(u64::from(tagged.id()?), Tagged::ID_HASH.hash(b"sword"));
+    assert_eq!(tagged.key()? as u64, Tagged::KEY_HASH.hash(b"sword"));
+    assert_eq!(
+        fb::HashAlgorithm::from_name("fnv1_64"),
+        Some(Tagged::KEY_HASH)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_original_order() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let counters = Counters::create(
+        &mut builder,
+        &CountersArgs {
+            flag: true,
+            large: 2,
+            medium: 3,
+            ..Default::default()
+        },
+    );
+    let record = Record::create(
+        &mut builder,
+        &RecordArgs {
+            flag: true,
+            count: 2,
+            level: 3,
+        },
+    );
+    builder.finish_minimal(record);
+
+    // the fields of `Record` follow each other in the table as declared,
+    // whereas those of `Counters` are sorted by size
+    let record = fb::get_root::<fb::Table>(builder.finished_data())?;
+    let vtable = record.vtable()?;
+    assert!(vtable.get(Record::VT_FLAG) < vtable.get(Record::VT_COUNT));
+    assert!(vtable.get(Record::VT_COUNT) < vtable.get(Record::VT_LEVEL));
+    let data = builder.finished_data();
+    let counters = fb::Table::new(data, data.len() - counters.value() as usize);
+    let vtable = counters.vtable()?;
+    assert!(vtable.get(Counters::VT_FLAG) < vtable.get(Counters::VT_MEDIUM));
+    assert!(vtable.get(Counters::VT_MEDIUM) < vtable.get(Counters::VT_LARGE));
+
+    let record = fb::get_root::<Record>(builder.finished_data())?;
+    assert!(record.flag()?);
+    assert_eq!(record.count()?, 2);
+    assert_eq!(record.level()?, 3);
+    Ok(())
+}
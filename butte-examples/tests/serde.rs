@@ -0,0 +1,120 @@
+use butte as fb;
+
+#[allow(dead_code)]
+mod objects {
+    butte_build::include_fbs!("objects");
+}
+
+use objects::shop::{
+    Dimensions, Item, ItemArgs, ItemT, Label, LabelArgs, Size, Sticker, StickerArgs, TagT, TagType,
+};
+use serde_json::json;
+
+#[test]
+fn test_serialize_to_json() -> anyhow::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let text = builder.create_string("new");
+    let label = Label::create(&mut builder, &LabelArgs { text: Some(text) });
+    let labels = builder.create_vector(&[label]);
+    let keywords = builder.create_vector_of_strings(&["light", "desk"]);
+    let sizes = builder.create_vector(&[Size::Small, Size::Large]);
+    let code = builder.create_string("L-1");
+    let sticker = Sticker::create(&mut builder, &StickerArgs { code });
+    let name = builder.create_string("Lamp");
+    let dimensions = Dimensions::new(0.5, 1.5);
+    let item = Item::create(
+        &mut builder,
+        &ItemArgs {
+            name,
+            size: Size::Large,
+            price: 25,
+            dimensions: Some(&dimensions),
+            labels: Some(labels),
+            keywords: Some(keywords),
+            ratings: None,
+            boxes: None,
+            sizes: Some(sizes),
+            tag_type: TagType::Sticker,
+            tag: Some(sticker.as_union_value()),
+            related: None,
+        },
+    );
+    builder.finish_minimal(item);
+
+    let item = fb::get_root::<Item>(builder.finished_data())?;
+    assert_eq!(
+        serde_json::to_value(item)?,
+        json!({
+            "name": "Lamp",
+            "size": "Large",
+            "price": 25,
+            "dimensions": { "width": 0.5, "height": 1.5 },
+            "labels": [{ "text": "new" }],
+            "keywords": ["light", "desk"],
+            "sizes": ["Small", "Large"],
+            "tag_type": "Sticker",
+            "tag": { "code": "L-1" },
+        })
+    );
+    Ok(())
+}
+
+#[test]
+fn test_deserialize_from_json() -> anyhow::Result<()> {
+    // The discriminant of a union has to come before its value, so the JSON
+    // is parsed from text, which keeps the order of the fields.
+    let text = r#"{
+        "name": "Lamp",
+        "price": 25,
+        "dimensions": { "width": 0.5, "height": 1.5 },
+        "boxes": [{ "width": 1.0, "height": 2.0 }],
+        "sizes": ["Small", "Large"],
+        "tag_type": "Label",
+        "tag": { "text": "new" },
+        "related": { "name": "Bulb" }
+    }"#;
+    let item = serde_json::from_str::<ItemT>(text)?;
+    assert_eq!(item.size, Size::Medium);
+    assert_eq!(item.dimensions, Some(Dimensions::new(0.5, 1.5)));
+    assert!(matches!(&item.tag, TagT::Label(label) if label.text.as_deref() == Some("new")));
+    assert_eq!(item.related.as_ref().unwrap().name, "Bulb");
+
+    let mut builder = fb::FlatBufferBuilder::new();
+    let offset = item.pack(&mut builder);
+    builder.finish_minimal(offset);
+    let reader = fb::get_root::<Item>(builder.finished_data())?;
+    let mut expected = serde_json::from_str::<serde_json::Value>(text)?;
+    expected["size"] = json!("Medium");
+    expected["related"]["price"] = json!(0);
+    expected["related"]["size"] = json!("Medium");
+    expected["related"]["tag_type"] = json!("NONE");
+    assert_eq!(serde_json::to_value(reader)?, expected);
+    Ok(())
+}
+
+#[test]
+fn test_deserialize_errors() {
+    let missing = serde_json::from_value::<ItemT>(json!({ "price": 1 }));
+    assert!(missing
+        .unwrap_err()
+        .to_string()
+        .contains("missing field `name`"));
+
+    let unknown = serde_json::from_value::<ItemT>(json!({ "name": "Lamp", "colour": 1 }));
+    assert!(unknown
+        .unwrap_err()
+        .to_string()
+        .contains("unknown field `colour`"));
+
+    let union = serde_json::from_value::<ItemT>(json!({ "name": "Lamp", "tag": {} }));
+    assert!(union
+        .unwrap_err()
+        .to_string()
+        .contains("`tag` must come after `tag_type`"));
+
+    let size = serde_json::from_value::<Size>(json!("Huge"));
+    assert!(size
+        .unwrap_err()
+        .to_string()
+        .contains("unknown variant `Huge`"));
+}
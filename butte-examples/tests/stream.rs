@@ -0,0 +1,145 @@
+use butte as fb;
+use butte::stream::{Codec, FrameError};
+use std::io::{self, Cursor};
+
+#[allow(dead_code)]
+mod unions {
+    butte_build::include_fbs!("unions");
+}
+
+use unions::zoo::{AnimalType, Pet, PetArgs, PetOffset};
+
+fn pet_frame(owner: &str) -> Vec<u8> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let owner = builder.create_string(owner);
+    let pet = Pet::create(
+        &mut builder,
+        &PetArgs {
+            owner,
+            animal_type: AnimalType::NONE,
+            animal: None,
+        },
+    );
+    builder.finish_size_prefixed(pet, None);
+    builder.finished_data().to_vec()
+}
+
+#[test]
+fn test_decode_frames() -> anyhow::Result<()> {
+    let mut codec = Codec::new().verified::<PetOffset>(fb::VerifierOptions::default());
+    let mut sent = vec![];
+    codec.encode(&pet_frame("Ann"), &mut sent)?;
+    codec.encode(&pet_frame("Bob"), &mut sent)?;
+
+    // frames are decoded once all of their bytes have arrived
+    let mut received = vec![];
+    let mut frames = vec![];
+    for byte in sent {
+        received.push(byte);
+        if let Some(frame) = codec.decode(&mut received)? {
+            frames.push(frame);
+        }
+    }
+    assert!(received.is_empty());
+    assert_eq!(frames.len(), 2);
+    assert_eq!(
+        fb::get_size_prefixed_root::<Pet>(&frames[1])?.owner()?,
+        "Bob"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_frame_errors() {
+    let frame = pet_frame("Ann");
+    let mut codec = Codec::new().max_frame_len(frame.len() - 1);
+    assert_eq!(
+        codec.encode(&frame, &mut vec![]),
+        Err(FrameError::TooLong {
+            len: frame.len(),
+            max: frame.len() - 1
+        })
+    );
+    // a frame that is too long is rejected before it arrives
+    assert!(matches!(
+        codec.decode(&mut frame[..4].to_vec()),
+        Err(FrameError::TooLong { .. })
+    ));
+    assert_eq!(
+        Codec::new().encode(&frame[..frame.len() - 1], &mut vec![]),
+        Err(FrameError::WrongSizePrefix {
+            prefix: frame.len() - 4,
+            len: frame.len() - 5
+        })
+    );
+
+    // a frame holding a table without its required field fails verification,
+    // and leaves the codec at the next frame
+    let mut builder = fb::FlatBufferBuilder::new();
+    let table = builder.build_table(|_| {});
+    builder.finish_size_prefixed(table, None);
+    let mut codec = Codec::new().verified::<PetOffset>(fb::VerifierOptions::default());
+    let mut received = builder.finished_data().to_vec();
+    received.extend_from_slice(&frame);
+    assert!(matches!(
+        codec.decode(&mut received),
+        Err(FrameError::Invalid(fb::Error::MissingRequiredField { .. }))
+    ));
+    assert_eq!(codec.decode(&mut received), Ok(Some(frame)));
+}
+
+#[test]
+fn test_read_and_write_frames() -> io::Result<()> {
+    let mut codec = Codec::new();
+    let mut stream = vec![];
+    codec.write_frame(&pet_frame("Ann"), &mut stream)?;
+    codec.write_frame(&pet_frame("Bob"), &mut stream)?;
+    let error = codec.write_frame(&[1, 0, 0, 0], &mut stream).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+    let mut reader = Cursor::new(&stream);
+    assert_eq!(codec.read_frame(&mut reader)?, Some(pet_frame("Ann")));
+    assert_eq!(codec.read_frame(&mut reader)?, Some(pet_frame("Bob")));
+    assert_eq!(codec.read_frame(&mut reader)?, None);
+
+    let mut reader = Cursor::new(&stream[..stream.len() - 1]);
+    codec.read_frame(&mut reader)?;
+    let error = codec.read_frame(&mut reader).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_framed_round_trip() -> io::Result<()> {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_util::codec::Framed;
+
+    // a small buffer, so that frames arrive in pieces
+    let (client, server) = tokio::io::duplex(16);
+    let mut client = Framed::new(client, Codec::new());
+    let mut server = Framed::new(
+        server,
+        Codec::new().verified::<PetOffset>(fb::VerifierOptions::default()),
+    );
+
+    let frames = [pet_frame("Ann"), pet_frame("Bob")];
+    let send = async {
+        for frame in &frames {
+            client.send(&frame[..]).await?;
+        }
+        let error = client.send(&[1, 0, 0, 0][..]).await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        drop(client);
+        io::Result::Ok(())
+    };
+    let receive = async {
+        let mut received = vec![];
+        while let Some(frame) = server.next().await {
+            received.push(frame?.to_vec());
+        }
+        io::Result::Ok(received)
+    };
+    let ((), received) = tokio::try_join!(send, receive)?;
+    assert_eq!(received, frames);
+    Ok(())
+}
@@ -0,0 +1,319 @@
+use butte as fb;
+use std::mem::{align_of, size_of};
+
+#[allow(dead_code)]
+mod structs {
+    butte_build::include_fbs!("structs");
+}
+
+use structs::geometry::{
+    Cloud, CloudArgs, Color, Padded, Scene, SceneArgs, Shape, ShapeArgs, Tagged, Transform, Vec3,
+};
+
+#[test]
+fn test_struct_layout() {
+    assert_eq!(size_of::<Vec3>(), 12);
+    assert_eq!(align_of::<Vec3>(), 4);
+    assert_eq!(size_of::<Tagged>(), 24);
+    assert_eq!(align_of::<Tagged>(), 8);
+    assert_eq!(size_of::<Padded>(), 16);
+    assert_eq!(align_of::<Padded>(), 16);
+    assert_eq!(size_of::<Transform>(), 48);
+    assert_eq!(align_of::<Transform>(), 4);
+}
+
+#[test]
+fn test_struct_accessors() {
+    let v = Vec3::new(1.0, 2.0, 3.0);
+    assert_eq!(v.x(), 1.0);
+    assert_eq!(v.y(), 2.0);
+    assert_eq!(v.z(), 3.0);
+
+    let t = Tagged::new(true, Color::Blue, &v, 4.5);
+    assert!(t.flag());
+    assert_eq!(t.color(), Ok(Color::Blue));
+    assert_eq!(t.position(), &v);
+    assert_eq!(t.weight(), 4.5);
+}
+
+#[test]
+fn test_struct_fields_round_trip() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let origin = Vec3::new(1.0, 2.0, 3.0);
+    let tag = Tagged::new(true, Color::Green, &Vec3::new(4.0, 5.0, 6.0), 7.5);
+    let path = builder.create_vector_direct(&[Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 1.0)]);
+    let args = ShapeArgs {
+        origin: Some(&origin),
+        tag: Some(&tag),
+        path: Some(path),
+    };
+    let shape = Shape::create(&mut builder, &args);
+    builder.finish_minimal(shape);
+
+    let shape = fb::get_root::<Shape>(builder.finished_data())?;
+    assert_eq!(shape.origin()?, Some(&origin));
+
+    let read_tag = shape.tag()?.unwrap();
+    assert!(read_tag.flag());
+    assert_eq!(read_tag.color()?, Color::Green);
+    assert_eq!(read_tag.position().z(), 6.0);
+    assert_eq!(read_tag.weight(), 7.5);
+
+    let read_path = shape.path()?.unwrap();
+    assert_eq!(read_path.len(), 2);
+    assert_eq!(read_path.get(1)?.y(), 1.0);
+    assert_eq!(
+        read_path.safe_slice()?.iter().map(Vec3::x).sum::<f32>(),
+        1.0
+    );
+    Ok(())
+}
+
+#[test]
+fn test_struct_fields_copied() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let tag = Tagged::new(true, Color::Blue, &Vec3::new(4.0, 5.0, 6.0), 7.5);
+    let args = ShapeArgs {
+        origin: None,
+        tag: Some(&tag),
+        path: None,
+    };
+    let shape = Shape::create(&mut builder, &args);
+    builder.finish_minimal(shape);
+
+    // a copy of the buffer one byte off its alignment
+    let mut data = vec![0; builder.finished_data().len() + 1];
+    data[1..].copy_from_slice(builder.finished_data());
+    let data = &data[1..];
+
+    let shape = fb::get_root::<Shape>(data)?;
+    assert!(matches!(shape.tag(), Err(fb::Error::Unaligned { .. })));
+    let copied = shape.tag_copied()?.unwrap();
+    assert_eq!(copied, tag);
+    assert_eq!(copied.color()?, Color::Blue);
+    assert_eq!(copied.position().y(), 5.0);
+    assert_eq!(copied.weight(), 7.5);
+    assert_eq!(shape.origin_copied()?, None);
+    Ok(())
+}
+
+#[test]
+fn test_unknown_enum_value_in_struct() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let tag = Tagged::new(true, Color::Red, &Vec3::new(0.0, 0.0, 0.0), 1.0);
+    let shape = Shape::create(
+        &mut builder,
+        &ShapeArgs {
+            origin: None,
+            tag: Some(&tag),
+            path: None,
+        },
+    );
+    builder.finish_minimal(shape);
+
+    let mut data = builder.finished_data().to_vec();
+    let tag = fb::get_root::<Shape>(&data)?.tag()?.unwrap();
+    let color = tag as *const Tagged as usize - data.as_ptr() as usize + 1;
+    data[color] = 7;
+
+    let tag = fb::get_root::<Shape>(&data)?.tag()?.unwrap();
+    assert_eq!(tag.color(), Err(fb::Error::UnknownEnumValue));
+    assert!(tag.flag());
+    Ok(())
+}
+
+#[test]
+fn test_bool_bytes_in_struct() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let tag = Tagged::new(false, Color::Red, &Vec3::new(0.0, 0.0, 0.0), 1.0);
+    let shape = Shape::create(
+        &mut builder,
+        &ShapeArgs {
+            origin: None,
+            tag: Some(&tag),
+            path: None,
+        },
+    );
+    builder.finish_minimal(shape);
+
+    let mut data = builder.finished_data().to_vec();
+    let tag = fb::get_root::<Shape>(&data)?.tag()?.unwrap();
+    assert!(!tag.flag());
+    let flag = tag as *const Tagged as usize - data.as_ptr() as usize;
+    // any byte but 0 is read as true
+    data[flag] = 2;
+
+    let tag = fb::get_root::<Shape>(&data)?.tag()?.unwrap();
+    assert!(tag.flag());
+
+    // which only strict verification rejects
+    fb::verified_root::<Shape>(&data)?;
+    let strict = fb::VerifierOptions {
+        strict_bools: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        fb::verified_root_with_options::<Shape>(&data, strict).unwrap_err(),
+        fb::Error::InvalidBool { loc: flag }
+    );
+    Ok(())
+}
+
+#[test]
+fn test_equality_ignores_padding_and_location() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let tag = Tagged::new(true, Color::Blue, &Vec3::new(1.0, 2.0, 3.0), 0.5);
+    let shape = Shape::create(
+        &mut builder,
+        &ShapeArgs {
+            origin: None,
+            tag: Some(&tag),
+            path: None,
+        },
+    );
+    builder.finish_minimal(shape);
+    let data = builder.finished_data().to_vec();
+
+    // the same shape, further into a bigger buffer and with garbage in the
+    // padding after `color`
+    builder.reset();
+    builder.create_string("unrelated");
+    let shape = Shape::create(
+        &mut builder,
+        &ShapeArgs {
+            origin: None,
+            tag: Some(&tag),
+            path: None,
+        },
+    );
+    builder.finish_minimal(shape);
+    let mut other = builder.finished_data().to_vec();
+    let read_tag = fb::get_root::<Shape>(&other)?.tag()?.unwrap();
+    let padding = read_tag as *const Tagged as usize - other.as_ptr() as usize + 2;
+    other[padding] = 0xff;
+
+    let shape = fb::get_root::<Shape>(&data)?;
+    let other_shape = fb::get_root::<Shape>(&other)?;
+    assert_ne!(data.len(), other.len());
+    assert_eq!(shape.tag()?, other_shape.tag()?);
+    assert_eq!(shape, other_shape);
+    Ok(())
+}
+
+/// Storage for a finished buffer that keeps the alignment of the buffer's
+/// start in memory.
+#[repr(C, align(16))]
+struct Aligned([u8; 256]);
+
+#[test]
+fn test_force_align_round_trip() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    // An odd-sized string before the structs would misalign them without
+    // `force_align`.
+    builder.create_string("abc");
+    let center = Padded::new(1.0, 2.0, 3.0);
+    let points = builder.create_vector_direct(&[Padded::new(4.0, 5.0, 6.0)]);
+    let cloud = Cloud::create(
+        &mut builder,
+        &CloudArgs {
+            center: Some(&center),
+            points: Some(points),
+        },
+    );
+    builder.finish_minimal(cloud);
+
+    let data = builder.finished_data();
+    assert_eq!(data.len() % 16, 0);
+    let mut aligned = Box::new(Aligned([0; 256]));
+    aligned.0[..data.len()].copy_from_slice(data);
+    let buf = &aligned.0[..data.len()];
+
+    let cloud = fb::get_root::<Cloud>(buf)?;
+    let read_center = cloud.center()?.unwrap();
+    assert_eq!(read_center, &center);
+    assert_eq!((read_center as *const Padded as usize) % 16, 0);
+    let read_points = cloud.points()?.unwrap().safe_slice()?;
+    assert_eq!(read_points[0].z(), 6.0);
+    assert_eq!((read_points.as_ptr() as usize) % 16, 0);
+    Ok(())
+}
+
+#[test]
+fn test_fixed_array_round_trip() -> fb::Result<()> {
+    let corners = [Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 2.0, 3.0)];
+    let transform = Transform::new(
+        [1.0, 2.0, 3.0, 4.0],
+        [Color::Blue, Color::Green],
+        &corners,
+        [7, 8, 9],
+    );
+    let mut builder = fb::FlatBufferBuilder::new();
+    let scene = Scene::create(
+        &mut builder,
+        &SceneArgs {
+            transform: Some(&transform),
+        },
+    );
+    builder.finish_minimal(scene);
+
+    let read = fb::get_root::<Scene>(builder.finished_data())?
+        .transform()?
+        .unwrap();
+    assert_eq!(read, &transform);
+    assert_eq!(read.scale(), [1.0, 2.0, 3.0, 4.0]);
+    assert_eq!(read.colors()?, [Color::Blue, Color::Green]);
+    assert_eq!(read.corners(), &corners);
+    assert_eq!(read.mask(), &[7, 8, 9]);
+    Ok(())
+}
+
+#[test]
+fn test_fixed_array_unknown_enum_value() -> fb::Result<()> {
+    let transform = Transform::new(
+        [0.0; 4],
+        [Color::Red; 2],
+        &[Vec3::new(0.0, 0.0, 0.0); 2],
+        [0; 3],
+    );
+    let mut builder = fb::FlatBufferBuilder::new();
+    let scene = Scene::create(
+        &mut builder,
+        &SceneArgs {
+            transform: Some(&transform),
+        },
+    );
+    builder.finish_minimal(scene);
+
+    let mut data = builder.finished_data().to_vec();
+    let read = fb::get_root::<Scene>(&data)?.transform()?.unwrap();
+    // The colors start right after the four floats.
+    let color = read as *const Transform as usize - data.as_ptr() as usize + 17;
+    data[color] = 7;
+
+    let read = fb::get_root::<Scene>(&data)?.transform()?.unwrap();
+    assert_eq!(read.colors(), Err(fb::Error::UnknownEnumValue));
+    assert_eq!(read.scale(), [0.0; 4]);
+    Ok(())
+}
+
+#[test]
+fn test_fixed_array_serde() {
+    let transform = Transform::new(
+        [1.0, 2.0, 3.0, 4.0],
+        [Color::Red, Color::Blue],
+        &[Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 2.0, 3.0)],
+        [7, 8, 9],
+    );
+    let text = serde_json::to_string(&transform).unwrap();
+    assert_eq!(
+        text,
+        r#"{"scale":[1.0,2.0,3.0,4.0],"colors":["Red","Blue"],"corners":[{"x":0.0,"y":0.0,"z":0.0},{"x":1.0,"y":2.0,"z":3.0}],"mask":[7,8,9]}"#
+    );
+    assert_eq!(serde_json::from_str::<Transform>(&text).unwrap(), transform);
+
+    let error = serde_json::from_str::<Transform>(
+        r#"{"scale":[1.0],"colors":["Red","Blue"],"corners":[],"mask":[7,8,9]}"#,
+    )
+    .unwrap_err();
+    assert!(error.to_string().contains("an array of 4 values"));
+}
@@ -0,0 +1,424 @@
+use butte as fb;
+use std::convert::TryFrom;
+
+#[allow(dead_code)]
+mod unions {
+    butte_build::include_fbs!("unions");
+}
+
+use serde_json::json;
+use unions::zoo::{
+    Animal, AnimalT, AnimalType, Cat, CatArgs, CatT, Dog, DogArgs, DogT, Pet, PetArgs, PetBuilder,
+    ResidentT, ResidentType, Room, RoomArgs, RoomT, Shelter, ShelterArgs, ShelterT,
+};
+
+#[test]
+fn test_union_round_trip() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let name = builder.create_string("Tom");
+    let cat = Cat::create(
+        &mut builder,
+        &CatArgs {
+            name: Some(name),
+            lives: 9,
+        },
+    );
+    let owner = builder.create_string("Alice");
+    let pet = Pet::create(
+        &mut builder,
+        &PetArgs {
+            owner,
+            animal_type: AnimalType::Cat,
+            animal: Some(cat.as_union_value()),
+        },
+    );
+    builder.finish_minimal(pet);
+
+    let pet = fb::get_root::<Pet>(builder.finished_data())?;
+    assert_eq!(pet.owner()?, "Alice");
+    assert_eq!(pet.animal_type()?, AnimalType::Cat);
+    match pet.animal()? {
+        Some(Animal::Cat(cat)) => assert_eq!(cat.name()?, Some("Tom")),
+        animal => panic!("expected a cat, got {:?}", animal),
+    }
+    assert_eq!(pet.animal_as_cat()?.unwrap().lives()?, 9);
+    assert!(pet.animal_as_dog()?.is_none());
+    assert_eq!(
+        format!("{:?}", pet),
+        r#"Pet { owner: "Alice", animal: Some(Cat { name: Some("Tom"), lives: 9 }) }"#
+    );
+    Ok(())
+}
+
+#[test]
+fn test_union_other_variant() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let name = builder.create_string("Rex");
+    let dog = Dog::create(&mut builder, &DogArgs { name: Some(name) });
+    let owner = builder.create_string("Bob");
+    let pet = Pet::create(
+        &mut builder,
+        &PetArgs {
+            owner,
+            animal_type: AnimalType::Dog,
+            animal: Some(dog.as_union_value()),
+        },
+    );
+    builder.finish_minimal(pet);
+
+    let pet = fb::get_root::<Pet>(builder.finished_data())?;
+    assert_eq!(pet.animal_type()?, AnimalType::Dog);
+    assert!(matches!(pet.animal()?, Some(Animal::Dog(_))));
+    assert!(pet.animal_as_cat()?.is_none());
+    assert_eq!(pet.animal_as_dog()?.unwrap().name()?, Some("Rex"));
+    Ok(())
+}
+
+#[test]
+fn test_union_aliases() -> anyhow::Result<()> {
+    assert_eq!(u8::from(ResidentType::Tenant), 3);
+    assert_eq!(u8::from(ResidentType::Guest), 4);
+    assert_eq!(u8::from(ResidentType::Stray), 5);
+
+    let mut builder = fb::FlatBufferBuilder::new();
+    let name = builder.create_string("Rex");
+    let dog = Dog::create(&mut builder, &DogArgs { name: Some(name) });
+    let room = Room::create(
+        &mut builder,
+        &RoomArgs {
+            resident_type: ResidentType::Guest,
+            resident: Some(dog.as_union_value()),
+        },
+    );
+    builder.finish_minimal(room);
+
+    let room = fb::verified_root::<Room>(builder.finished_data())?;
+    assert_eq!(room.resident_type()?, ResidentType::Guest);
+    assert_eq!(room.resident_as_guest()?.unwrap().name()?, Some("Rex"));
+    assert!(room.resident_as_tenant()?.is_none());
+    assert_eq!(
+        room.unpack()?,
+        RoomT {
+            resident: ResidentT::Guest(Box::new(DogT {
+                name: Some("Rex".to_string()),
+            })),
+        }
+    );
+    assert_eq!(
+        serde_json::to_string(&room)?,
+        r#"{"resident_type":"Guest","resident":{"name":"Rex"}}"#
+    );
+    Ok(())
+}
+
+#[test]
+fn test_object_derives() {
+    let dog = |name: &str| {
+        AnimalT::Dog(Box::new(DogT {
+            name: Some(name.to_string()),
+        }))
+    };
+    let animals = [dog("Rex"), dog("Rex"), dog("Fido"), AnimalT::NONE]
+        .iter()
+        .cloned()
+        .collect::<std::collections::HashSet<_>>();
+    assert_eq!(animals.len(), 3);
+}
+
+#[test]
+fn test_union_none() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let owner = builder.create_string("Carol");
+    let pet = Pet::create(
+        &mut builder,
+        &PetArgs {
+            owner,
+            animal_type: AnimalType::NONE,
+            animal: None,
+        },
+    );
+    builder.finish_minimal(pet);
+
+    let pet = fb::get_root::<Pet>(builder.finished_data())?;
+    assert_eq!(pet.animal_type()?, AnimalType::NONE);
+    assert!(pet.animal()?.is_none());
+    assert!(pet.animal_as_cat()?.is_none());
+    assert!(pet.animal_as_dog()?.is_none());
+    Ok(())
+}
+
+#[test]
+fn test_missing_required_field() -> fb::Result<()> {
+    // A `Dog` without a name shares its layout with a `Pet` that has no owner.
+    let mut builder = fb::FlatBufferBuilder::new();
+    let dog = Dog::create(&mut builder, &DogArgs { name: None });
+    builder.finish_minimal(dog);
+
+    let pet = fb::get_root::<Pet>(builder.finished_data())?;
+    let error = fb::Error::MissingRequiredField {
+        slot: Pet::VT_OWNER,
+    };
+    assert_eq!(pet.owner(), Err(error));
+    assert_eq!(
+        fb::verified_root::<Pet>(builder.finished_data()).map(|_| ()),
+        Err(error)
+    );
+    assert_eq!(
+        format!("{:?}", pet),
+        "Pet { owner: MissingRequiredField { slot: 4 }, animal: None }"
+    );
+    Ok(())
+}
+
+#[test]
+#[should_panic(expected = "owner")]
+fn test_builder_checks_required_fields() {
+    let mut builder = fb::FlatBufferBuilder::new();
+    PetBuilder::new(&mut builder).finish();
+}
+
+#[test]
+fn test_root_type_with_file_identifier() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let owner = builder.create_string("Dave");
+    let pet = Pet::create(
+        &mut builder,
+        &PetArgs {
+            owner,
+            animal_type: AnimalType::NONE,
+            animal: None,
+        },
+    );
+    unions::zoo::finish_pet_buffer(&mut builder, pet);
+
+    let buf = builder.finished_data();
+    assert_eq!(unions::zoo::FILE_IDENTIFIER, fb::FileIdent::new("PETS"));
+    assert_eq!(unions::zoo::FILE_EXTENSION, "pet");
+    assert!(unions::zoo::pet_buffer_has_identifier(buf));
+    assert_eq!(unions::zoo::get_root_as_pet(buf)?.owner()?, "Dave");
+    assert_eq!(unions::zoo::verified_root_as_pet(buf)?.owner()?, "Dave");
+    assert_eq!(unions::zoo::get_root_as_pet_checked(buf)?.owner()?, "Dave");
+
+    let mut builder = fb::FlatBufferBuilder::new();
+    let owner = builder.create_string("Dave");
+    let pet = Pet::create(
+        &mut builder,
+        &PetArgs {
+            owner,
+            animal_type: AnimalType::NONE,
+            animal: None,
+        },
+    );
+    builder.finish(pet, Some(fb::FileIdent::new("CATS")));
+    let buf = builder.finished_data();
+    assert_eq!(
+        unions::zoo::get_root_as_pet_checked(buf).map(|_| ()),
+        Err(fb::Error::WrongFileIdentifier)
+    );
+    assert_eq!(unions::zoo::get_root_as_pet(buf)?.owner()?, "Dave");
+    Ok(())
+}
+
+#[test]
+fn test_file_ident() {
+    const CATS: fb::FileIdent = fb::FileIdent::new("CATS");
+    assert_eq!(CATS.as_bytes(), b"CATS");
+    assert_eq!(fb::FileIdent::from_bytes(*b"CATS"), CATS);
+    assert_eq!(fb::FileIdent::try_from("CATS").ok(), Some(CATS));
+    // identifiers known only at runtime are checked when converted
+    assert!(fb::FileIdent::try_from("CAT").is_err());
+    assert!(fb::FileIdent::try_from("CATS!").is_err());
+    assert!(fb::FileIdent::try_from("CAÉ").is_ok());
+    assert!(fb::FileIdent::try_from("CATÉ").is_err());
+}
+
+#[test]
+fn test_size_prefixed_root_type() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let owner = builder.create_string("Erin");
+    let pet = Pet::create(
+        &mut builder,
+        &PetArgs {
+            owner,
+            animal_type: AnimalType::NONE,
+            animal: None,
+        },
+    );
+    unions::zoo::finish_size_prefixed_pet_buffer(&mut builder, pet);
+
+    let buf = builder.finished_data();
+    assert!(unions::zoo::pet_size_prefixed_buffer_has_identifier(buf));
+    assert!(!unions::zoo::pet_buffer_has_identifier(buf));
+    assert_eq!(
+        unions::zoo::get_root_as_pet_checked(buf).map(|_| ()),
+        Err(fb::Error::WrongFileIdentifier)
+    );
+    assert_eq!(
+        unions::zoo::get_size_prefixed_root_as_pet_checked(buf)?.owner()?,
+        "Erin"
+    );
+    assert_eq!(
+        unions::zoo::get_size_prefixed_root_as_pet(buf)?.owner()?,
+        "Erin"
+    );
+    assert_eq!(
+        unions::zoo::verified_size_prefixed_root_as_pet(buf)?.owner()?,
+        "Erin"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_union_vector_round_trip() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let name = builder.create_string("Tom");
+    let cat = Cat::create(
+        &mut builder,
+        &CatArgs {
+            name: Some(name),
+            lives: 9,
+        },
+    );
+    let dog = Dog::create(&mut builder, &DogArgs { name: None });
+    let types = builder.create_vector(&[AnimalType::Cat, AnimalType::NONE, AnimalType::Dog]);
+    let values =
+        builder.create_vector(&[Some(cat.as_union_value()), None, Some(dog.as_union_value())]);
+    let shelter = Shelter::create(
+        &mut builder,
+        &ShelterArgs {
+            animals_type: Some(types),
+            animals: Some(values),
+        },
+    );
+    builder.finish_minimal(shelter);
+
+    let shelter = fb::verified_root::<Shelter>(builder.finished_data())?;
+    let animals = shelter.animals()?.unwrap();
+    assert_eq!(animals.len(), 3);
+    assert_eq!(animals.types().get(2)?, AnimalType::Dog);
+    match animals.get(0)? {
+        Animal::Cat(cat) => assert_eq!(cat.lives()?, 9),
+        other => panic!("expected a cat, got {:?}", other),
+    }
+    assert_eq!(animals.get(1)?, Animal::NONE);
+    assert!(matches!(animals.get(3), Err(fb::Error::OutOfBounds { .. })));
+    assert_eq!(
+        animals.iter().rev().collect::<fb::Result<Vec<_>>>()?.len(),
+        3
+    );
+    assert_eq!(
+        format!("{:?}", shelter),
+        r#"Shelter { animals: Some([Cat(Cat { name: Some("Tom"), lives: 9 }), NONE, Dog(Dog { name: None })]) }"#
+    );
+    Ok(())
+}
+
+#[test]
+fn test_copy_unions() -> fb::Result<()> {
+    let original = ShelterT {
+        animals: Some(vec![
+            AnimalT::Dog(Box::new(DogT {
+                name: Some("Rex".to_string()),
+            })),
+            AnimalT::NONE,
+            AnimalT::Cat(Box::new(CatT {
+                name: None,
+                lives: 3,
+            })),
+        ]),
+    };
+    let mut builder = fb::FlatBufferBuilder::new();
+    let offset = original.pack(&mut builder);
+    builder.finish_minimal(offset);
+    let shelter = fb::get_root::<Shelter>(builder.finished_data())?;
+
+    let mut copy = fb::FlatBufferBuilder::new();
+    let offset = shelter.copy_to(&mut copy)?;
+    copy.finish_minimal(offset);
+    let shelter = fb::verified_root::<Shelter>(copy.finished_data())?;
+    assert_eq!(shelter.unpack()?, original);
+
+    let mut builder = fb::FlatBufferBuilder::new();
+    let name = builder.create_string("Ann");
+    let dog = Dog::create(&mut builder, &DogArgs { name: None });
+    let pet = Pet::create(
+        &mut builder,
+        &PetArgs {
+            owner: name,
+            animal_type: AnimalType::Dog,
+            animal: Some(dog.as_union_value()),
+        },
+    );
+    builder.finish_minimal(pet);
+    let pet = fb::get_root::<Pet>(builder.finished_data())?;
+    let message = pet.extract()?;
+    assert_eq!(message.root()?.unpack()?, pet.unpack()?);
+    Ok(())
+}
+
+#[test]
+fn test_union_vector_mismatch() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let dog = Dog::create(&mut builder, &DogArgs { name: None });
+    let types = builder.create_vector(&[AnimalType::Dog, AnimalType::Dog]);
+    let values = builder.create_vector(&[Some(dog.as_union_value())]);
+    let shelter = Shelter::create(
+        &mut builder,
+        &ShelterArgs {
+            animals_type: Some(types),
+            animals: Some(values),
+        },
+    );
+    builder.finish_minimal(shelter);
+    let shelter = fb::get_root::<Shelter>(builder.finished_data())?;
+    assert_eq!(shelter.animals(), Err(fb::Error::UnionVectorMismatch));
+    assert_eq!(
+        fb::verified_root::<Shelter>(builder.finished_data()).map(|_| ()),
+        Err(fb::Error::UnionVectorMismatch)
+    );
+
+    let mut builder = fb::FlatBufferBuilder::new();
+    let types = builder.create_vector(&[AnimalType::NONE]);
+    let shelter = Shelter::create(
+        &mut builder,
+        &ShelterArgs {
+            animals_type: Some(types),
+            animals: None,
+        },
+    );
+    builder.finish_minimal(shelter);
+    let shelter = fb::get_root::<Shelter>(builder.finished_data())?;
+    assert_eq!(shelter.animals(), Err(fb::Error::UnionVectorMismatch));
+    Ok(())
+}
+
+#[test]
+fn test_union_vector_objects() -> anyhow::Result<()> {
+    let original = ShelterT {
+        animals: Some(vec![
+            AnimalT::Dog(Box::new(DogT {
+                name: Some("Rex".to_string()),
+            })),
+            AnimalT::NONE,
+        ]),
+    };
+    let mut builder = fb::FlatBufferBuilder::new();
+    let offset = original.pack(&mut builder);
+    builder.finish_minimal(offset);
+
+    let shelter = fb::get_root::<Shelter>(builder.finished_data())?;
+    assert_eq!(shelter.unpack()?, original);
+
+    // The types must come first, which a `serde_json::Value` doesn't keep.
+    let text = r#"{"animals_type":["Dog","NONE"],"animals":[{"name":"Rex"},null]}"#;
+    assert_eq!(serde_json::to_string(&shelter)?, text);
+    assert_eq!(serde_json::from_str::<ShelterT>(text)?, original);
+
+    let short = serde_json::from_str::<ShelterT>(
+        r#"{"animals_type":["Dog","Dog"],"animals":[{"name":"Rex"}]}"#,
+    );
+    assert!(short.is_err());
+    let untyped = serde_json::from_value::<ShelterT>(json!({ "animals": [] }));
+    assert!(untyped.is_err());
+    Ok(())
+}
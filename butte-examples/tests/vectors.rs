@@ -0,0 +1,743 @@
+use butte as fb;
+use std::borrow::Cow;
+
+#[allow(dead_code)]
+mod vectors {
+    butte_build::include_fbs!("vectors");
+}
+
+use vectors::inventory::{
+    Inventory, InventoryArgs, InventoryDirectArgs, Item, ItemArgs, ItemDirectArgs, Kind, Shipment,
+    ShipmentArgs,
+};
+
+fn build_inventory(builder: &mut fb::FlatBufferBuilder) {
+    let owner = builder.create_string("Alice");
+    let checksum = builder.create_vector_direct(&[0xde_u8, 0xad, 0xbe, 0xef]);
+    let scores = builder.create_vector(&[3, -1, 4]);
+    let tags = builder.create_vector_of_strings(&["red", "green"]);
+    let kinds = builder.create_vector(&[Kind::Toy, Kind::Tool]);
+
+    let mut items = vec![];
+    for (name, count) in &[("hammer", 1), ("apple", 12)] {
+        let name = builder.create_string(name);
+        items.push(Item::create(
+            builder,
+            &ItemArgs {
+                name,
+                count: *count,
+            },
+        ));
+    }
+    let favorite = items[0];
+    let items = builder.create_vector(&items);
+
+    let inventory = Inventory::create(
+        builder,
+        &InventoryArgs {
+            owner: Some(owner),
+            checksum: Some(checksum),
+            scores: Some(scores),
+            tags: Some(tags),
+            kinds: Some(kinds),
+            items: Some(items),
+            favorite: Some(favorite),
+        },
+    );
+    builder.finish_minimal(inventory);
+}
+
+#[test]
+fn test_vector_of_scalars() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    build_inventory(&mut builder);
+    let inventory = fb::get_root::<Inventory>(builder.finished_data())?;
+
+    let checksum = inventory.checksum()?.unwrap();
+    assert_eq!(checksum.safe_slice()?, &[0xde, 0xad, 0xbe, 0xef]);
+
+    let scores = inventory.scores()?.unwrap();
+    assert_eq!(scores.len(), 3);
+    assert_eq!(scores.get(2)?, 4);
+    assert!(matches!(
+        scores.get(3),
+        Err(fb::Error::OutOfBounds { len: 4, .. })
+    ));
+    assert_eq!(
+        scores.iter().collect::<fb::Result<Vec<_>>>()?,
+        vec![3, -1, 4]
+    );
+    assert_eq!(&*scores.to_slice()?, &[3, -1, 4]);
+    #[cfg(target_endian = "little")]
+    assert!(matches!(scores.to_slice()?, Cow::Borrowed(_)));
+    Ok(())
+}
+
+#[test]
+fn test_vector_of_strings() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    build_inventory(&mut builder);
+    let inventory = fb::get_root::<Inventory>(builder.finished_data())?;
+
+    let tags = inventory.tags()?.unwrap();
+    assert_eq!(
+        tags.iter().collect::<fb::Result<Vec<_>>>()?,
+        vec!["red", "green"]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_vector_of_enums() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    build_inventory(&mut builder);
+    let inventory = fb::get_root::<Inventory>(builder.finished_data())?;
+
+    let kinds = inventory.kinds()?.unwrap();
+    assert_eq!(
+        kinds.iter().collect::<fb::Result<Vec<_>>>()?,
+        vec![Kind::Toy, Kind::Tool]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_vector_of_tables() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    build_inventory(&mut builder);
+    let inventory = fb::get_root::<Inventory>(builder.finished_data())?;
+
+    let items = inventory.items()?.unwrap();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items.get(1)?.name()?, "apple");
+    let mut total = 0;
+    for item in items {
+        total += item?.count()?;
+    }
+    assert_eq!(total, 13);
+    assert_eq!(inventory.favorite()?.unwrap().name()?, "hammer");
+    Ok(())
+}
+
+#[test]
+fn test_create_direct() -> fb::Result<()> {
+    let mut expected = fb::FlatBufferBuilder::new();
+    build_inventory(&mut expected);
+
+    let mut builder = fb::FlatBufferBuilder::new();
+    let checksum = builder.create_vector_direct(&[0xde_u8, 0xad, 0xbe, 0xef]);
+    let scores = builder.create_vector(&[3, -1, 4]);
+    let tags = builder.create_vector_of_strings(&["red", "green"]);
+    let kinds = builder.create_vector(&[Kind::Toy, Kind::Tool]);
+    let items = [("hammer", 1), ("apple", 12)]
+        .iter()
+        .map(|&(name, count)| Item::create_direct(&mut builder, &ItemDirectArgs { name, count }))
+        .collect::<Vec<_>>();
+    let favorite = items[0];
+    let items = builder.create_vector(&items);
+    let owner = builder.create_string("Alice");
+    let inventory = Inventory::create(
+        &mut builder,
+        &InventoryArgs {
+            owner: Some(owner),
+            checksum: Some(checksum),
+            scores: Some(scores),
+            tags: Some(tags),
+            kinds: Some(kinds),
+            items: Some(items),
+            favorite: Some(favorite),
+        },
+    );
+    builder.finish_minimal(inventory);
+    let inventory = fb::get_root::<Inventory>(builder.finished_data())?;
+    let items = inventory.items()?.unwrap();
+    assert_eq!(items.get(1)?.name()?, "apple");
+    assert_eq!(items.get(1)?.count()?, 12);
+
+    let mut builder = fb::FlatBufferBuilder::new();
+    let hammer = Item::create_direct(
+        &mut builder,
+        &ItemDirectArgs {
+            name: "hammer",
+            count: 1,
+        },
+    );
+    let inventory = Inventory::create_direct(
+        &mut builder,
+        &InventoryDirectArgs {
+            owner: Some("Alice"),
+            checksum: Some(&[0xde, 0xad, 0xbe, 0xef]),
+            scores: Some(&[3, -1, 4]),
+            tags: Some(&["red", "green"]),
+            kinds: Some(&[Kind::Toy, Kind::Tool]),
+            favorite: Some(hammer),
+            ..Default::default()
+        },
+    );
+    builder.finish_minimal(inventory);
+
+    let inventory = fb::get_root::<Inventory>(builder.finished_data())?;
+    let expected = fb::get_root::<Inventory>(expected.finished_data())?;
+    assert_eq!(inventory.owner()?, expected.owner()?);
+    assert_eq!(inventory.checksum()?, expected.checksum()?);
+    assert_eq!(inventory.scores()?, expected.scores()?);
+    assert_eq!(inventory.tags()?, expected.tags()?);
+    assert_eq!(inventory.kinds()?, expected.kinds()?);
+    assert_eq!(inventory.items()?, None);
+    assert_eq!(inventory.favorite()?.unwrap().name()?, "hammer");
+    Ok(())
+}
+
+#[test]
+fn test_iterator_adapters() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    build_inventory(&mut builder);
+    let inventory = fb::get_root::<Inventory>(builder.finished_data())?;
+
+    let items = inventory.items()?.unwrap();
+    let mut iter: fb::VectorIter<_> = items.iter();
+    assert_eq!(iter.len(), 2);
+    assert_eq!(iter.next_back().unwrap()?.name()?, "apple");
+    assert_eq!(iter.len(), 1);
+    assert_eq!(iter.next().unwrap()?.name()?, "hammer");
+    assert!(iter.next().is_none());
+
+    let names = items
+        .iter()
+        .rev()
+        .map(|item| item?.name())
+        .collect::<fb::Result<Vec<_>>>()?;
+    assert_eq!(names, vec!["apple", "hammer"]);
+    let counts = items
+        .iter()
+        .map(|item| item?.count())
+        .collect::<fb::Result<Vec<_>>>()?;
+    assert_eq!(counts, vec![1, 12]);
+    assert_eq!(items.iter().nth(1).unwrap()?.name()?, "apple");
+    assert!(items.iter().nth(2).is_none());
+    Ok(())
+}
+
+#[test]
+fn test_vector_of_tables_shares_vtables() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let mut items = vec![];
+    for count in 0..100 {
+        let name = builder.create_string("item");
+        items.push(Item::create(&mut builder, &ItemArgs { name, count }));
+    }
+    let items = builder.create_vector(&items);
+    let inventory = Inventory::create(
+        &mut builder,
+        &InventoryArgs {
+            owner: None,
+            checksum: None,
+            scores: None,
+            tags: None,
+            kinds: None,
+            items: Some(items),
+            favorite: None,
+        },
+    );
+    // identical vtables are only written once, the first item leaves out its
+    // count, which is the default, so its vtable differs from the others
+    assert_eq!(builder.num_written_vtables(), 3);
+    builder.finish_minimal(inventory);
+
+    let inventory = fb::get_root::<Inventory>(builder.finished_data())?;
+    let items = inventory.items()?.unwrap();
+    assert_eq!(items.len(), 100);
+    assert_eq!(items.get(99)?.count()?, 99);
+    Ok(())
+}
+
+#[test]
+fn test_builder_reuse() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::with_capacity(1024);
+    build_inventory(&mut builder);
+    let data = builder.finished_data().to_vec();
+    assert_eq!(builder.capacity(), 1024);
+
+    // a reset builder keeps its buffer
+    builder.reset();
+    build_inventory(&mut builder);
+    assert_eq!(builder.finished_data(), &data[..]);
+    assert_eq!(builder.capacity(), 1024);
+
+    // and so does one made from the buffer of another
+    let (buf, _) = builder.collapse();
+    let mut builder = fb::FlatBufferBuilder::from_vec(buf);
+    assert_eq!(builder.capacity(), 1024);
+    build_inventory(&mut builder);
+    assert_eq!(builder.finished_data(), &data[..]);
+    assert_eq!(builder.capacity(), 1024);
+    fb::get_root::<Inventory>(builder.finished_data())?;
+    Ok(())
+}
+
+#[test]
+fn test_empty_singletons() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let owner = builder.create_empty_string();
+    let tags = builder.create_empty_vector();
+    let scores = builder.create_empty_vector();
+    let len = builder.unfinished_data().len();
+    assert_eq!(builder.create_empty_string().value(), owner.value());
+    assert_eq!(scores.value(), tags.value());
+    assert_eq!(builder.unfinished_data().len(), len);
+
+    let inventory = Inventory::create(
+        &mut builder,
+        &InventoryArgs {
+            owner: Some(owner),
+            scores: Some(scores),
+            tags: Some(tags),
+            ..Default::default()
+        },
+    );
+    builder.finish_minimal(inventory);
+    let inventory = fb::verified_root::<Inventory>(builder.finished_data())?;
+    assert_eq!(inventory.owner()?, Some(""));
+    assert_eq!(inventory.tags()?.map(|tags| tags.len()), Some(0));
+    assert_eq!(
+        inventory.scores()?.unwrap().to_slice()?,
+        Cow::Borrowed(&[][..])
+    );
+
+    // a reset builder creates them again
+    builder.reset();
+    let owner = builder.create_empty_string();
+    assert_eq!(builder.unfinished_data().len(), len);
+    assert_eq!(owner.value() as usize, len);
+    Ok(())
+}
+
+#[test]
+fn test_debug() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    build_inventory(&mut builder);
+    let inventory = fb::get_root::<Inventory>(builder.finished_data())?;
+    assert_eq!(
+        format!("{:?}", inventory.favorite()?.unwrap()),
+        r#"Item { name: "hammer", count: 1 }"#
+    );
+    assert_eq!(
+        format!("{:?}", inventory),
+        concat!(
+            r#"Inventory { owner: Some("Alice"), checksum: Some([222, 173, 190, 239]), "#,
+            r#"scores: Some([3, -1, 4]), tags: Some(["red", "green"]), kinds: Some([Toy, Tool]), "#,
+            r#"items: Some([Item { name: "hammer", count: 1 }, "#,
+            r#"Item { name: "apple", count: 12 }]), "#,
+            r#"favorite: Some(Item { name: "hammer", count: 1 }) }"#,
+        )
+    );
+    Ok(())
+}
+
+#[test]
+fn test_equality() -> fb::Result<()> {
+    use std::collections::HashSet;
+
+    let mut builder = fb::FlatBufferBuilder::new();
+    build_inventory(&mut builder);
+    let data = builder.finished_data().to_vec();
+    let mut other_builder = fb::FlatBufferBuilder::new();
+    other_builder.create_string("padding");
+    build_inventory(&mut other_builder);
+
+    let inventory = fb::get_root::<Inventory>(&data)?;
+    let other = fb::get_root::<Inventory>(other_builder.finished_data())?;
+    assert_eq!(inventory, other);
+    assert_eq!(inventory.items()?, other.items()?);
+    assert_ne!(
+        inventory.items()?.unwrap().get(0)?,
+        other.items()?.unwrap().get(1)?
+    );
+
+    let tags = [inventory.tags()?.unwrap(), other.tags()?.unwrap()]
+        .iter()
+        .copied()
+        .collect::<HashSet<_>>();
+    assert_eq!(tags.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_nested_flatbuffer() -> fb::Result<()> {
+    let mut inner = fb::FlatBufferBuilder::new();
+    build_inventory(&mut inner);
+
+    let mut builder = fb::FlatBufferBuilder::new();
+    let manifest = builder.create_vector_direct(inner.finished_data());
+    let shipment = Shipment::create(
+        &mut builder,
+        &ShipmentArgs {
+            manifest: Some(manifest),
+            labels: None,
+        },
+    );
+    builder.finish_minimal(shipment);
+
+    let shipment = fb::get_root::<Shipment>(builder.finished_data())?;
+    let inventory = shipment.manifest_nested_flatbuffer()?.unwrap();
+    assert_eq!(inventory.owner()?, Some("Alice"));
+    assert_eq!(inventory.items()?.unwrap().get(1)?.name()?, "apple");
+
+    let mut builder = fb::FlatBufferBuilder::new();
+    let shipment = Shipment::create(
+        &mut builder,
+        &ShipmentArgs {
+            manifest: None,
+            labels: None,
+        },
+    );
+    builder.finish_minimal(shipment);
+    let shipment = fb::get_root::<Shipment>(builder.finished_data())?;
+    assert!(shipment.manifest_nested_flatbuffer()?.is_none());
+    Ok(())
+}
+
+#[test]
+fn test_field_ranges() -> fb::Result<()> {
+    let mut inner = fb::FlatBufferBuilder::new();
+    build_inventory(&mut inner);
+
+    let mut builder = fb::FlatBufferBuilder::new();
+    let manifest = builder.create_vector_direct(inner.finished_data());
+    let shipment = Shipment::create(
+        &mut builder,
+        &ShipmentArgs {
+            manifest: Some(manifest),
+            labels: None,
+        },
+    );
+    builder.finish_minimal(shipment);
+    let data = builder.finished_data();
+
+    // the nested buffer can be forwarded without decoding it
+    let shipment = fb::get_root::<Shipment>(data)?;
+    let manifest = shipment.field_range(Shipment::VT_MANIFEST)?.unwrap();
+    assert_eq!(&data[manifest], inner.finished_data());
+    assert_eq!(shipment.field_range(Shipment::VT_LABELS)?, None);
+    // the offset of the vtable and that of the manifest
+    assert_eq!(shipment.table_size()?, 8);
+
+    let data = inner.finished_data();
+    let inventory = fb::get_root::<Inventory>(data)?;
+    let owner = inventory.field_range(Inventory::VT_OWNER)?.unwrap();
+    assert_eq!(&data[owner], b"Alice");
+    let scores = inventory.field_range(Inventory::VT_SCORES)?.unwrap();
+    assert_eq!(scores.len(), 3 * 4);
+    let favorite = inventory.favorite()?.unwrap();
+    let range = inventory.field_range(Inventory::VT_FAVORITE)?.unwrap();
+    assert_eq!(&data[range.clone()], favorite.as_bytes()?);
+    assert_eq!(range.len(), favorite.table_size()?);
+    let count = favorite.field_range(Item::VT_COUNT)?.unwrap();
+    assert_eq!(&data[count], &1_u32.to_le_bytes());
+    Ok(())
+}
+
+#[test]
+fn test_flexbuffer() -> fb::Result<()> {
+    let mut labels = fb::flexbuffers::Builder::new();
+    let start = labels.start_map();
+    labels.key("fragile");
+    labels.push_bool(true);
+    labels.key("weight");
+    labels.push_float(2.5);
+    labels.end_map(start);
+    labels.finish();
+
+    let mut builder = fb::FlatBufferBuilder::new();
+    let labels = builder.create_vector_direct(labels.finished_data());
+    let shipment = Shipment::create(
+        &mut builder,
+        &ShipmentArgs {
+            manifest: None,
+            labels: Some(labels),
+        },
+    );
+    builder.finish_minimal(shipment);
+
+    let shipment = fb::get_root::<Shipment>(builder.finished_data())?;
+    let labels = shipment.labels_flexbuffer()?.unwrap().get_map()?;
+    assert!(labels.get("fragile")?.unwrap().get_bool()?);
+    assert_eq!(labels.get("weight")?.unwrap().get_f64()?, 2.5);
+    assert!(shipment.manifest_nested_flatbuffer()?.is_none());
+    Ok(())
+}
+
+#[test]
+fn test_truncated_buffer() {
+    let mut builder = fb::FlatBufferBuilder::new();
+    build_inventory(&mut builder);
+    let data = builder.finished_data();
+
+    // every prefix of the buffer is either rejected or read without panicking
+    for len in 0..data.len() {
+        let truncated = &data[..len];
+        if let Ok(inventory) = fb::get_root::<Inventory>(truncated) {
+            let _ = inventory.owner();
+            let _ = inventory.items();
+        }
+    }
+    assert_eq!(
+        fb::get_root::<Inventory>(&data[..2]).map(|_| ()),
+        Err(fb::Error::OutOfBounds {
+            loc: 0,
+            len: fb::SIZE_UOFFSET,
+            buf_len: 2
+        })
+    );
+}
+
+#[test]
+fn test_verified_root() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    build_inventory(&mut builder);
+    let mut data = builder.finished_data().to_vec();
+    let inventory = fb::verified_root::<Inventory>(&data)?;
+    assert_eq!(inventory.items()?.unwrap().get(1)?.name()?, "apple");
+
+    // the length of a string deep inside the buffer reaches past its end,
+    // which only shows when that string is read, unless the buffer is
+    // verified
+    let start = data
+        .windows(5)
+        .position(|window| window == b"apple")
+        .unwrap();
+    data[start - fb::SIZE_UOFFSET..start].copy_from_slice(&1000u32.to_le_bytes());
+    let inventory = fb::get_root::<Inventory>(&data)?;
+    assert_eq!(inventory.owner()?, Some("Alice"));
+    let error = fb::Error::OutOfBounds {
+        loc: start,
+        len: 1000,
+        buf_len: data.len(),
+    };
+    assert_eq!(inventory.items()?.unwrap().get(1)?.name(), Err(error));
+    assert_eq!(
+        fb::verified_root::<Inventory>(&data).map(|_| ()),
+        Err(error)
+    );
+
+    let data = builder.finished_data();
+    assert!(fb::verified_root::<Inventory>(&data[..data.len() / 2]).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_malformed_vtables() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let name = builder.create_string("hammer");
+    let item = Item::create(&mut builder, &ItemArgs { name, count: 1 });
+    builder.finish_minimal(item);
+    let data = builder.finished_data();
+
+    let table = fb::read_scalar::<fb::UOffsetT>(data)? as usize;
+    let vtable =
+        (table as i64 - i64::from(fb::read_scalar_at::<fb::SOffsetT>(data, table)?)) as usize;
+    let corrupt = |loc: usize, bytes: &[u8]| {
+        let mut data = data.to_vec();
+        data[loc..loc + bytes.len()].copy_from_slice(bytes);
+        data
+    };
+    let cases = [
+        // the vtable would be before the start or after the end of the buffer
+        (
+            corrupt(table, &(table as i32 + 1).to_le_bytes()),
+            fb::Error::InvalidVTableOffset { loc: table },
+        ),
+        (
+            corrupt(table, &i32::MIN.to_le_bytes()),
+            fb::Error::InvalidVTableOffset { loc: table },
+        ),
+        // the vtable is too small, odd, or too large
+        (
+            corrupt(vtable, &2u16.to_le_bytes()),
+            fb::Error::InvalidVTableSize { loc: vtable },
+        ),
+        (
+            corrupt(vtable, &7u16.to_le_bytes()),
+            fb::Error::InvalidVTableSize { loc: vtable },
+        ),
+        (
+            corrupt(vtable, &1000u16.to_le_bytes()),
+            fb::Error::InvalidVTableSize { loc: vtable },
+        ),
+        // the table is too small or too large
+        (
+            corrupt(vtable + 2, &2u16.to_le_bytes()),
+            fb::Error::InvalidTableSize { loc: table },
+        ),
+        (
+            corrupt(vtable + 2, &1000u16.to_le_bytes()),
+            fb::Error::InvalidTableSize { loc: table },
+        ),
+        // a field lies outside the table
+        (
+            corrupt(vtable + Item::VT_COUNT as usize, &1000u16.to_le_bytes()),
+            fb::Error::InvalidFieldOffset {
+                loc: table,
+                slot: Item::VT_COUNT,
+            },
+        ),
+    ];
+    for (data, error) in &cases {
+        let item = fb::get_root::<Item>(data)?;
+        assert_eq!(item.count(), Err(*error));
+        assert_eq!(fb::verified_root::<Item>(data).map(|_| ()), Err(*error));
+    }
+    // slots at the top of the range of a `u16`, which read as negative
+    // offsets if taken to be signed
+    for slot in [0xFFFD_u16, 0xFFFE, 0xFFFF] {
+        let data = corrupt(vtable + Item::VT_COUNT as usize, &slot.to_le_bytes());
+        let error = fb::Error::InvalidFieldOffset {
+            loc: table,
+            slot: Item::VT_COUNT,
+        };
+        let item = fb::get_root::<Item>(&data)?;
+        assert_eq!(item.count(), Err(error));
+        assert_eq!(fb::verified_root::<Item>(&data).map(|_| ()), Err(error));
+    }
+    assert_eq!(fb::get_root::<Item>(data)?.count()?, 1);
+    Ok(())
+}
+
+#[test]
+fn test_vtable_slots() -> fb::Result<()> {
+    let mut builder = fb::FlatBufferBuilder::new();
+    let name = builder.create_string("hammer");
+    let item = Item::create(&mut builder, &ItemArgs { name, count: 1 });
+    builder.finish_minimal(item);
+    let data = builder.finished_data();
+
+    let table = fb::Table::new(data, fb::read_scalar::<fb::UOffsetT>(data)? as usize);
+    let vtable = table.vtable()?;
+    assert_eq!(vtable.num_fields(), 2);
+    assert_eq!(vtable.num_bytes(), vtable.as_bytes().len());
+    assert_ne!(vtable.get_field(0), 0);
+    assert_ne!(vtable.get_field(1), 0);
+    assert_eq!(vtable.get(Item::VT_COUNT), vtable.get_field(1));
+    // slots past the end of the vtable read as missing fields, even though
+    // the table's data follows the vtable
+    assert_eq!(vtable.get_field(2), 0);
+    assert_eq!(vtable.get(Item::VT_COUNT + 2), 0);
+    assert_eq!(vtable.get(Item::VT_COUNT + 1), 0);
+    assert_eq!(vtable.get(fb::VOffsetT::MAX), 0);
+    assert_eq!(vtable.get(-2), 0);
+    Ok(())
+}
+
+#[test]
+fn test_large_vtable_entries() -> fb::Result<()> {
+    // A vtable and a table that are both larger than 0x8000 bytes, whose
+    // sizes and offsets would be negative if read as signed.
+    let vtable = 4;
+    let vtable_len = 0x8008_u16;
+    let table = vtable + vtable_len as usize;
+    let inline_len = 0x800c_u16;
+    let count_offset = 0x8008_u16;
+    let name = table + inline_len as usize;
+
+    let mut data = vec![0; name + 8];
+    data[..4].copy_from_slice(&(table as u32).to_le_bytes());
+    data[vtable..vtable + 2].copy_from_slice(&vtable_len.to_le_bytes());
+    data[vtable + 2..vtable + 4].copy_from_slice(&inline_len.to_le_bytes());
+    data[vtable + 4..vtable + 6].copy_from_slice(&4_u16.to_le_bytes());
+    data[vtable + 6..vtable + 8].copy_from_slice(&count_offset.to_le_bytes());
+    data[table..table + 4].copy_from_slice(&(table as i32 - vtable as i32).to_le_bytes());
+    data[table + 4..table + 8].copy_from_slice(&((name - table - 4) as u32).to_le_bytes());
+    let count = table + count_offset as usize;
+    data[count..count + 4].copy_from_slice(&7_u32.to_le_bytes());
+    data[name..name + 4].copy_from_slice(&3_u32.to_le_bytes());
+    data[name + 4..name + 7].copy_from_slice(b"big");
+
+    let root = fb::Table::new(&data, table);
+    let vt = root.vtable()?;
+    assert_eq!(vt.num_bytes(), vtable_len as usize);
+    assert_eq!(vt.object_inline_num_bytes(), inline_len as usize);
+    assert_eq!(vt.get(Item::VT_COUNT) as u16, count_offset);
+
+    let item = fb::verified_root::<Item>(&data)?;
+    assert_eq!(item.name()?, "big");
+    assert_eq!(item.count()?, 7);
+    Ok(())
+}
+
+#[test]
+fn test_invalid_utf8_strings() -> fb::Result<()> {
+    use fb::Follow;
+
+    let mut builder = fb::FlatBufferBuilder::new();
+    let name = builder.create_string("hammer");
+    let item = Item::create(&mut builder, &ItemArgs { name, count: 1 });
+    builder.finish_minimal(item);
+
+    let mut data = builder.finished_data().to_vec();
+    let item = fb::get_root::<Item>(&data)?;
+    assert_eq!(item.name()?, "hammer");
+    assert_eq!(item.name_bytes()?, b"hammer");
+
+    let start = data
+        .windows(6)
+        .position(|window| window == b"hammer")
+        .unwrap();
+    data[start] = 0xff;
+
+    let error = fb::Error::InvalidUtf8 {
+        loc: start - fb::SIZE_UOFFSET,
+    };
+    let item = fb::get_root::<Item>(&data)?;
+    assert_eq!(item.name(), Err(error));
+    assert_eq!(item.name_bytes()?, b"\xffammer");
+
+    let string = fb::FbString::follow(&data, start - fb::SIZE_UOFFSET)?;
+    assert_eq!(string.len(), 6);
+    assert_eq!(string.to_str(), Err(error));
+    assert_eq!(string.to_string_lossy(), "\u{fffd}ammer");
+
+    // strict verification checks the UTF-8 up front
+    fb::verified_root::<Item>(&data)?;
+    assert_eq!(
+        fb::verified_root_with_options::<Item>(&data, fb::VerifierOptions::strict()).unwrap_err(),
+        error
+    );
+    Ok(())
+}
+
+#[test]
+fn test_error_hexdump() {
+    let data = (0..40).collect::<Vec<u8>>();
+    let error = fb::Error::InvalidOffset { loc: 20 };
+    assert_eq!(error.location(), Some(20));
+    assert_eq!(
+        error.hexdump(&data).unwrap().to_string(),
+        "00000000  00 01 02 03 04 05 06 07  08 09 0a 0b 0c 0d 0e 0f\n\
+         00000010  10 11 12 13 14 15 16 17  18 19 1a 1b 1c 1d 1e 1f\n\
+         \x20                     ^^\n\
+         00000020  20 21 22 23 24 25 26 27\n"
+    );
+    // the location may be past the end of the buffer
+    assert_eq!(
+        fb::HexDump::new(&data, 42).rows(0).to_string(),
+        "00000020  20 21 22 23 24 25 26 27\n\
+         \x20                                        ^^\n"
+    );
+    assert!(fb::Error::TypeMismatch.hexdump(&data).is_none());
+
+    // the error of a string whose length is corrupted points at its bytes
+    let mut builder = fb::FlatBufferBuilder::new();
+    let name = builder.create_string("hammer");
+    let item = Item::create(&mut builder, &ItemArgs { name, count: 1 });
+    builder.finish_minimal(item);
+    let mut data = builder.finished_data().to_vec();
+    let start = data
+        .windows(6)
+        .position(|window| window == b"hammer")
+        .unwrap();
+    data[start - fb::SIZE_UOFFSET] = 100;
+    let error = fb::get_root::<Item>(&data).unwrap().name().unwrap_err();
+    assert_eq!(error.location(), Some(start));
+    let hexdump = error.hexdump(&data).unwrap().to_string();
+    assert!(hexdump.contains(" 64 00 00 00 68 61 6d 6d"), "{}", hexdump);
+}
@@ -0,0 +1,48 @@
+//! Generate flatbuffer code at compile time, without a build script.
+use std::path::PathBuf;
+
+use butte_build::{codegen::Options, generate_code, include::SchemaFiles};
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, LitStr};
+
+/// Generate code for a flatbuffer schema file and every file it includes.
+///
+/// The path is relative to the directory containing the `Cargo.toml` of the
+/// crate being compiled, and included files are looked up relative to the
+/// including file. The crate is rebuilt whenever any of the schema files
+/// change.
+///
+/// # Examples
+///
+/// ```ignore
+/// mod greeter {
+///     butte_macros::include_fbs!("fbs/greeter/greeter.fbs");
+/// }
+/// ```
+#[proc_macro]
+pub fn include_fbs(input: TokenStream) -> TokenStream {
+    let path = parse_macro_input!(input as LitStr);
+    match expand(&path) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => syn::Error::new(path.span(), format!("{:#}", error))
+            .to_compile_error()
+            .into(),
+    }
+}
+
+fn expand(path: &LitStr) -> anyhow::Result<proc_macro2::TokenStream> {
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR")?;
+    let files = SchemaFiles::load(PathBuf::from(manifest_dir).join(path.value()), &[])?;
+    let code = generate_code(&files, &Options::default())?;
+
+    // Depend on the contents of the schema files, so that changing any of
+    // them recompiles the generated code.
+    let paths = files
+        .paths()
+        .map(|path| path.to_string_lossy().into_owned());
+    Ok(quote! {
+        #(const _: &[u8] = include_bytes!(#paths);)*
+        #code
+    })
+}
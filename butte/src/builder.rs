@@ -16,14 +16,15 @@
 
 extern crate smallvec;
 
-use std::{cmp::max, marker::PhantomData, ptr::write_bytes, slice::from_raw_parts};
+use alloc::{vec, vec::Vec};
+use core::{cmp::max, marker::PhantomData, ptr::write_bytes, slice::from_raw_parts};
 
 use crate::{
-    endian_scalar::{emplace_scalar, read_scalar_at},
+    endian_scalar::{emplace_scalar, EndianScalar},
     primitives::*,
-    push::{Push, PushAlignment},
+    push::{padding_bytes, Push, PushAlignment},
     table::Table,
-    vector::{SafeSliceAccess, Vector},
+    vector::{SafeSliceAccess, Vector, Vector64},
     vtable::{field_index_to_field_offset, VTable},
     vtable_writer::VTableWriter,
 };
@@ -45,12 +46,16 @@ pub struct FlatBufferBuilder<'fbb> {
     head: usize,
 
     field_locs: Vec<FieldLoc>,
-    written_vtable_revpos: Vec<UOffsetT>,
+    written_vtable_revpos: Vec<usize>,
+    empty_string: Option<UOffsetT>,
 
     nested: bool,
     finished: bool,
 
     min_align: usize,
+    /// How much data the buffer may hold, which is raised once it has data
+    /// referred to by 64-bit offsets.
+    max_size: usize,
 
     _phantom: PhantomData<&'fbb ()>,
 }
@@ -58,32 +63,50 @@ pub struct FlatBufferBuilder<'fbb> {
 impl<'fbb> FlatBufferBuilder<'fbb> {
     /// Create a FlatBufferBuilder that is ready for writing.
     pub fn new() -> Self {
-        Self::new_with_capacity(0)
+        Self::with_capacity(0)
     }
 
     /// Create a FlatBufferBuilder that is ready for writing, with a
     /// ready-to-use capacity of the provided size.
     ///
-    /// The maximum valid value is `FLATBUFFERS_MAX_BUFFER_SIZE`.
+    /// A buffer may hold up to `FLATBUFFERS_MAX_BUFFER_SIZE` bytes, or up to
+    /// `FLATBUFFERS_MAX_64_BUFFER_SIZE` bytes once it has a vector with a
+    /// 64-bit length, which has to be created before the 32-bit offsets are.
+    pub fn with_capacity(size: usize) -> Self {
+        Self::from_vec(vec![0u8; size])
+    }
+
+    /// Create a FlatBufferBuilder that is ready for writing, with a
+    /// ready-to-use capacity of the provided size.
+    ///
+    /// This is the same as `with_capacity`.
     pub fn new_with_capacity(size: usize) -> Self {
-        // we need to check the size here because we create the backing buffer
-        // directly, bypassing the typical way of using grow_owned_buf:
-        assert!(
-            size <= FLATBUFFERS_MAX_BUFFER_SIZE,
-            "cannot initialize buffer bigger than 2 gigabytes"
-        );
+        Self::with_capacity(size)
+    }
+
+    /// Create a FlatBufferBuilder that writes into `buf`, using all of its
+    /// capacity. Its contents are overwritten.
+    ///
+    /// Together with `collapse`, this lets buffers come from, and go back to,
+    /// a pool or any other allocation strategy instead of the builder
+    /// allocating its own.
+    pub fn from_vec(mut buf: Vec<u8>) -> Self {
+        buf.clear();
+        buf.resize(buf.capacity(), 0);
 
         FlatBufferBuilder {
-            owned_buf: vec![0u8; size],
-            head: size,
+            head: buf.len(),
+            owned_buf: buf,
 
             field_locs: Vec::new(),
             written_vtable_revpos: Vec::new(),
+            empty_string: None,
 
             nested: false,
             finished: false,
 
-            min_align: 0,
+            min_align: 1,
+            max_size: FLATBUFFERS_MAX_BUFFER_SIZE,
 
             _phantom: PhantomData,
         }
@@ -104,7 +127,7 @@ impl<'fbb> FlatBufferBuilder<'fbb> {
         // memset only the part of the buffer that could be dirty:
         {
             let to_clear = self.owned_buf.len() - self.head;
-            let ptr = (&mut self.owned_buf[self.head..]).as_mut_ptr();
+            let ptr = self.owned_buf[self.head..].as_mut_ptr();
             unsafe {
                 write_bytes(ptr, 0, to_clear);
             }
@@ -112,11 +135,19 @@ impl<'fbb> FlatBufferBuilder<'fbb> {
 
         self.head = self.owned_buf.len();
         self.written_vtable_revpos.clear();
+        self.empty_string = None;
 
         self.nested = false;
         self.finished = false;
 
-        self.min_align = 0;
+        self.min_align = 1;
+        self.max_size = FLATBUFFERS_MAX_BUFFER_SIZE;
+    }
+
+    /// The number of bytes the builder can hold before it has to grow its
+    /// internal buffer. `reset` keeps the buffer, and so the capacity.
+    pub fn capacity(&self) -> usize {
+        self.owned_buf.len()
     }
 
     /// Destroy the FlatBufferBuilder, returning its internal byte vector
@@ -135,7 +166,7 @@ impl<'fbb> FlatBufferBuilder<'fbb> {
         self.align(sz, P::alignment());
         self.make_space(sz);
         {
-            let (dst, rest) = (&mut self.owned_buf[self.head..]).split_at_mut(sz);
+            let (dst, rest) = self.owned_buf[self.head..].split_at_mut(sz);
             x.push(dst, rest);
         }
         WIPOffset::new(self.used_space() as UOffsetT)
@@ -147,7 +178,11 @@ impl<'fbb> FlatBufferBuilder<'fbb> {
     #[inline]
     pub fn push_slot<X: Push + PartialEq>(&mut self, slotoff: VOffsetT, x: X, default: X) {
         self.assert_nested("push_slot");
-        if x == default {
+        // A `nan` default is the same as a `nan` value, though they don't
+        // compare equal, like flatc does.
+        #[allow(clippy::eq_op)]
+        let both_nan = x != x && default != default;
+        if x == default || both_nan {
             return;
         }
         self.push_slot_always(slotoff, x);
@@ -162,6 +197,17 @@ impl<'fbb> FlatBufferBuilder<'fbb> {
         self.track_field(slotoff, off.value());
     }
 
+    /// Push the little-endian `bytes` of a value onto the front of the
+    /// in-progress data, and store a reference to it in the in-progress
+    /// vtable. This is for values whose type is only known at runtime.
+    #[inline]
+    pub(crate) fn push_slot_bytes(&mut self, slotoff: VOffsetT, bytes: &[u8], alignment: usize) {
+        self.assert_nested("push_slot_bytes");
+        self.align(bytes.len(), PushAlignment::new(alignment));
+        self.push_bytes_unprefixed(bytes);
+        self.track_field(slotoff, self.used_space() as UOffsetT);
+    }
+
     /// Retrieve the number of vtables that have been serialized into the
     /// FlatBuffer. This is primarily used to check vtable deduplication.
     #[inline]
@@ -176,9 +222,7 @@ impl<'fbb> FlatBufferBuilder<'fbb> {
     /// Users probably want to use `push_slot` to add values after calling this.
     #[inline]
     pub fn start_table(&mut self) -> WIPOffset<TableUnfinishedWIPOffset> {
-        self.assert_not_nested(
-            "start_table can not be called when a table or vector is under construction",
-        );
+        self.assert_not_nested("start_table");
         self.nested = true;
 
         WIPOffset::new(self.used_space() as UOffsetT)
@@ -202,6 +246,33 @@ impl<'fbb> FlatBufferBuilder<'fbb> {
         WIPOffset::new(o.value())
     }
 
+    /// Write a table whose fields are pushed by `push_fields`.
+    ///
+    /// Unlike `start_table` and `end_table`, this can't be misused to create
+    /// a string, vector or table while the table is under construction:
+    /// `push_fields` only gets a `TableWriter`, which can push fields but not
+    /// create what they refer to, so that has to be done first.
+    ///
+    /// ```
+    /// # use butte::FlatBufferBuilder;
+    /// let mut fbb = FlatBufferBuilder::new();
+    /// let name = fbb.create_string("Orc");
+    /// let monster = fbb.build_table(|table| {
+    ///     table.push_slot_always(4, name);
+    ///     table.push_slot::<i16>(6, 300, 100);
+    /// });
+    /// fbb.finish_minimal(monster);
+    /// ```
+    #[inline]
+    pub fn build_table(
+        &mut self,
+        push_fields: impl FnOnce(&mut TableWriter<'_, 'fbb>),
+    ) -> WIPOffset<TableFinishedWIPOffset> {
+        let start = self.start_table();
+        push_fields(&mut TableWriter { fbb: self });
+        self.end_table(start)
+    }
+
     /// Start a Vector write.
     ///
     /// Asserts that the builder is not in a nested state.
@@ -211,9 +282,7 @@ impl<'fbb> FlatBufferBuilder<'fbb> {
     /// function will want to use `push` to add values.
     #[inline]
     pub fn start_vector<T: Push>(&mut self, num_items: usize) {
-        self.assert_not_nested(
-            "start_vector can not be called when a table or vector is under construction",
-        );
+        self.assert_not_nested("start_vector");
         self.nested = true;
         self.align(num_items * T::size(), T::alignment().max_of(SIZE_UOFFSET));
     }
@@ -237,18 +306,45 @@ impl<'fbb> FlatBufferBuilder<'fbb> {
     /// The wire format represents this as a zero-terminated byte vector.
     #[inline]
     pub fn create_string<'a: 'b, 'b>(&'a mut self, s: &'b str) -> WIPOffset<&'fbb str> {
-        self.assert_not_nested(
-            "create_string can not be called when a table or vector is under construction",
-        );
+        self.assert_not_nested("create_string");
         WIPOffset::new(self.create_byte_string(s.as_bytes()).value())
     }
 
+    /// Create an empty string, or reuse the one already created in this
+    /// buffer, so that a buffer with many empty strings holds only one.
+    #[inline]
+    pub fn create_empty_string(&mut self) -> WIPOffset<&'fbb str> {
+        self.assert_not_nested("create_empty_string");
+        WIPOffset::new(self.empty_string())
+    }
+
+    /// Create an empty vector, or reuse the one already created in this
+    /// buffer. Empty vectors of any type share their bytes with the empty
+    /// string of `create_empty_string`, since all of them are read from a
+    /// length of 0.
+    #[inline]
+    pub fn create_empty_vector<T>(&mut self) -> WIPOffset<Vector<'fbb, T>> {
+        self.assert_not_nested("create_empty_vector");
+        WIPOffset::new(self.empty_string())
+    }
+
+    /// The offset of the empty string, which is created the first time it is
+    /// needed.
+    fn empty_string(&mut self) -> UOffsetT {
+        match self.empty_string {
+            Some(offset) => offset,
+            None => {
+                let offset = self.create_byte_string(&[]).value();
+                self.empty_string = Some(offset);
+                offset
+            }
+        }
+    }
+
     /// Create a zero-terminated byte vector.
     #[inline]
     pub fn create_byte_string(&mut self, data: &[u8]) -> WIPOffset<&'fbb [u8]> {
-        self.assert_not_nested(
-            "create_byte_string can not be called when a table or vector is under construction",
-        );
+        self.assert_not_nested("create_byte_string");
         self.align(data.len() + 1, PushAlignment::new(SIZE_UOFFSET));
         self.push(0u8);
         self.push_bytes_unprefixed(data);
@@ -260,21 +356,19 @@ impl<'fbb> FlatBufferBuilder<'fbb> {
     /// `create_vector`, but the underlying type must be represented as
     /// little-endian on the host machine. This property is encoded in the
     /// type system through the SafeSliceAccess trait. The following types are
-    /// always safe, on any platform: bool, u8, i8, and any
-    /// FlatBuffers-generated struct.
+    /// always safe, on any platform: u8, i8, and any FlatBuffers-generated
+    /// struct.
     #[inline]
     pub fn create_vector_direct<'a: 'b, 'b, T: SafeSliceAccess + Push + Sized + 'b>(
         &'a mut self,
         items: &'b [T],
     ) -> WIPOffset<Vector<'fbb, T>> {
-        self.assert_not_nested(
-            "create_vector_direct can not be called when a table or vector is under construction",
-        );
+        self.assert_not_nested("create_vector_direct");
         let elem_size = T::size();
         self.align(items.len() * elem_size, T::alignment().max_of(SIZE_UOFFSET));
 
         let bytes = {
-            let ptr = items.as_ptr() as *const T as *const u8;
+            let ptr = items.as_ptr() as *const u8;
             unsafe { from_raw_parts(ptr, items.len() * elem_size) }
         };
         self.push_bytes_unprefixed(bytes);
@@ -283,6 +377,26 @@ impl<'fbb> FlatBufferBuilder<'fbb> {
         WIPOffset::new(self.used_space() as UOffsetT)
     }
 
+    /// Create a vector whose elements are stored inline, from the little-endian
+    /// `bytes` of its `len` elements. This is for elements whose type is only
+    /// known at runtime.
+    #[inline]
+    pub(crate) fn create_vector_bytes(
+        &mut self,
+        bytes: &[u8],
+        len: usize,
+        alignment: usize,
+    ) -> UOffsetT {
+        self.assert_not_nested("create_vector_bytes");
+        self.align(
+            bytes.len(),
+            PushAlignment::new(alignment).max_of(SIZE_UOFFSET),
+        );
+        self.push_bytes_unprefixed(bytes);
+        self.push(len as UOffsetT);
+        self.used_space() as UOffsetT
+    }
+
     /// Create a vector of strings.
     ///
     /// Speed-sensitive users may wish to reduce memory usage by creating the
@@ -292,20 +406,17 @@ impl<'fbb> FlatBufferBuilder<'fbb> {
         &'a mut self,
         xs: &'b [&'b str],
     ) -> WIPOffset<Vector<'fbb, ForwardsUOffset<&'fbb str>>> {
-        self.assert_not_nested("create_vector_of_strings can not be called when a table or vector is under construction");
+        self.assert_not_nested("create_vector_of_strings");
         // internally, smallvec can be a stack-allocated or heap-allocated vector:
         // if xs.len() > N_SMALLVEC_STRING_VECTOR_CAPACITY then it will overflow to the heap.
         let mut offsets: smallvec::SmallVec<[WIPOffset<&str>; N_SMALLVEC_STRING_VECTOR_CAPACITY]> =
             smallvec::SmallVec::with_capacity(xs.len());
-        unsafe {
-            offsets.set_len(xs.len());
-        }
 
         // note that this happens in reverse, because the buffer is built back-to-front:
-        for (i, &s) in xs.iter().enumerate().rev() {
-            let o = self.create_string(s);
-            offsets[i] = o;
+        for &s in xs.iter().rev() {
+            offsets.push(self.create_string(s));
         }
+        offsets.reverse();
         self.create_vector(&offsets[..])
     }
 
@@ -318,6 +429,7 @@ impl<'fbb> FlatBufferBuilder<'fbb> {
         &'a mut self,
         items: &'b [T],
     ) -> WIPOffset<Vector<'fbb, T::Output>> {
+        self.assert_not_nested("create_vector");
         let elem_size = T::size();
         self.align(items.len() * elem_size, T::alignment().max_of(SIZE_UOFFSET));
         for i in (0..items.len()).rev() {
@@ -326,6 +438,47 @@ impl<'fbb> FlatBufferBuilder<'fbb> {
         WIPOffset::new(self.push::<UOffsetT>(items.len() as UOffsetT).value())
     }
 
+    /// Create a vector with a 64-bit length, for a field with the `vector64`
+    /// attribute.
+    #[inline]
+    pub fn create_vector64<'a: 'b, 'b, T: Push + Copy + 'b>(
+        &'a mut self,
+        items: &'b [T],
+    ) -> WIPOffset64<Vector64<'fbb, T::Output>> {
+        self.assert_not_nested("create_vector64");
+        self.max_size = FLATBUFFERS_MAX_64_BUFFER_SIZE;
+        let elem_size = T::size();
+        self.align(
+            items.len() * elem_size,
+            T::alignment().max_of(SIZE_UOFFSET64),
+        );
+        for i in (0..items.len()).rev() {
+            self.push(items[i]);
+        }
+        self.push::<UOffset64T>(items.len() as UOffset64T);
+        WIPOffset64::new(self.used_space() as UOffset64T)
+    }
+
+    /// Create a vector with a 64-bit length from the little-endian `bytes` of
+    /// its `len` elements, like `create_vector_bytes`.
+    #[inline]
+    pub(crate) fn create_vector64_bytes(
+        &mut self,
+        bytes: &[u8],
+        len: usize,
+        alignment: usize,
+    ) -> UOffset64T {
+        self.assert_not_nested("create_vector64_bytes");
+        self.max_size = FLATBUFFERS_MAX_64_BUFFER_SIZE;
+        self.align(
+            bytes.len(),
+            PushAlignment::new(alignment).max_of(SIZE_UOFFSET64),
+        );
+        self.push_bytes_unprefixed(bytes);
+        self.push(len as UOffset64T);
+        self.used_space() as UOffset64T
+    }
+
     /// Get the byte slice for the data that has been written, regardless of
     /// whether it has been finished.
     #[inline]
@@ -351,7 +504,10 @@ impl<'fbb> FlatBufferBuilder<'fbb> {
     ) {
         let idx = self.used_space() - tab_revloc.value() as usize;
         let tab = Table::new(&self.owned_buf[self.head..], idx);
-        let o = tab.vtable().get(slot_byte_loc) as usize;
+        let vtable = tab
+            .vtable()
+            .expect("the builder always writes a valid vtable");
+        let o = vtable.get(slot_byte_loc);
         assert!(o != 0, "missing required field {}", assert_msg_name);
     }
 
@@ -359,8 +515,17 @@ impl<'fbb> FlatBufferBuilder<'fbb> {
     /// identifier on to it, pushing a size prefix on to it, and marking the
     /// internal state of the FlatBufferBuilder as `finished`. Afterwards,
     /// users can call `finished_data` to get the resulting data.
+    ///
+    /// The size prefix is a `UOffsetT` holding the length of the rest of the
+    /// buffer. It is counted when aligning the buffer, so the data after it
+    /// keeps the alignment of the largest value in the buffer. Read the buffer
+    /// back with `get_size_prefixed_root`.
     #[inline]
-    pub fn finish_size_prefixed<T>(&mut self, root: WIPOffset<T>, file_identifier: Option<&str>) {
+    pub fn finish_size_prefixed<T>(
+        &mut self,
+        root: WIPOffset<T>,
+        file_identifier: Option<FileIdent>,
+    ) {
         self.finish_with_opts(root, file_identifier, true);
     }
 
@@ -369,7 +534,7 @@ impl<'fbb> FlatBufferBuilder<'fbb> {
     /// FlatBufferBuilder as `finished`. Afterwards, users can call
     /// `finished_data` to get the resulting data.
     #[inline]
-    pub fn finish<T>(&mut self, root: WIPOffset<T>, file_identifier: Option<&str>) {
+    pub fn finish<T>(&mut self, root: WIPOffset<T>, file_identifier: Option<FileIdent>) {
         self.finish_with_opts(root, file_identifier, false);
     }
 
@@ -383,7 +548,7 @@ impl<'fbb> FlatBufferBuilder<'fbb> {
 
     #[inline]
     fn used_space(&self) -> usize {
-        self.owned_buf.len() - self.head as usize
+        self.owned_buf.len() - self.head
     }
 
     #[inline]
@@ -403,6 +568,10 @@ impl<'fbb> FlatBufferBuilder<'fbb> {
         // We fill its value later.
         let object_revloc_to_vtable: WIPOffset<VTableWIPOffset> =
             WIPOffset::new(self.push::<UOffsetT>(0xF0F0_F0F0 as UOffsetT).value());
+        // The offsets of the table and its fields are truncated to 32 bits
+        // past 64-bit data bigger than 4 gigabytes, but the differences
+        // between them are still right, as the table can't be that big.
+        let object_revloc = self.used_space();
 
         // Layout of the data this function will create when a new vtable is
         // needed.
@@ -448,7 +617,9 @@ impl<'fbb> FlatBufferBuilder<'fbb> {
         self.make_space(vtable_byte_len);
 
         // compute the length of the table (not vtable!) in bytes:
-        let table_object_size = object_revloc_to_vtable.value() - table_tail_revloc.value();
+        let table_object_size = object_revloc_to_vtable
+            .value()
+            .wrapping_sub(table_tail_revloc.value());
         debug_assert!(table_object_size < 0x10000); // vTable use 16bit offsets.
 
         // Write the VTable (we may delete it afterwards, if it is a duplicate):
@@ -462,7 +633,7 @@ impl<'fbb> FlatBufferBuilder<'fbb> {
 
             // serialize every FieldLoc to the vtable:
             for &fl in self.field_locs.iter() {
-                let pos: VOffsetT = (object_revloc_to_vtable.value() - fl.off) as VOffsetT;
+                let pos = object_revloc_to_vtable.value().wrapping_sub(fl.off) as VOffsetT;
                 debug_assert_eq!(
                     vtfw.get_field_offset(fl.id),
                     0,
@@ -472,7 +643,7 @@ impl<'fbb> FlatBufferBuilder<'fbb> {
             }
         }
         let dup_vt_use = {
-            let this_vt = VTable::init(&self.owned_buf[..], self.head);
+            let this_vt = VTable::new_unchecked(&self.owned_buf[..], self.head);
             self.find_duplicate_stored_vtable_revloc(this_vt)
         };
 
@@ -483,19 +654,19 @@ impl<'fbb> FlatBufferBuilder<'fbb> {
                 n
             }
             None => {
-                let new_vt_use = self.used_space() as UOffsetT;
+                let new_vt_use = self.used_space();
                 self.written_vtable_revpos.push(new_vt_use);
                 new_vt_use
             }
         };
 
         {
-            let n = self.head + self.used_space() - object_revloc_to_vtable.value() as usize;
-            let saw = read_scalar_at::<UOffsetT>(&self.owned_buf, n);
+            let n = self.head + self.used_space() - object_revloc;
+            let saw = UOffsetT::read_le(&self.owned_buf[n..]);
             debug_assert_eq!(saw, 0xF0F0_F0F0);
             emplace_scalar::<SOffsetT>(
                 &mut self.owned_buf[n..n + SIZE_SOFFSET],
-                vt_use as SOffsetT - object_revloc_to_vtable.value() as SOffsetT,
+                (vt_use as isize - object_revloc as isize) as SOffsetT,
             );
         }
 
@@ -505,12 +676,14 @@ impl<'fbb> FlatBufferBuilder<'fbb> {
     }
 
     #[inline]
-    fn find_duplicate_stored_vtable_revloc(&self, needle: VTable) -> Option<UOffsetT> {
+    fn find_duplicate_stored_vtable_revloc(&self, needle: VTable) -> Option<usize> {
         for &revloc in self.written_vtable_revpos.iter().rev() {
-            let o = VTable::init(
-                &self.owned_buf[..],
-                self.head + self.used_space() - revloc as usize,
-            );
+            // a table can only refer to a vtable less than 2 gigabytes away
+            if self.used_space() - revloc > SOffsetT::MAX as usize {
+                break;
+            }
+            let o =
+                VTable::new_unchecked(&self.owned_buf[..], self.head + self.used_space() - revloc);
             if needle == o {
                 return Some(revloc);
             }
@@ -546,7 +719,7 @@ impl<'fbb> FlatBufferBuilder<'fbb> {
         }
         // finally, zero out the old end data.
         {
-            let ptr = (&mut self.owned_buf[..middle]).as_mut_ptr();
+            let ptr = self.owned_buf[..middle].as_mut_ptr();
             unsafe {
                 write_bytes(ptr, 0, middle);
             }
@@ -558,13 +731,11 @@ impl<'fbb> FlatBufferBuilder<'fbb> {
     fn finish_with_opts<T>(
         &mut self,
         root: WIPOffset<T>,
-        file_identifier: Option<&str>,
+        file_identifier: Option<FileIdent>,
         size_prefixed: bool,
     ) {
         self.assert_not_finished("buffer cannot be finished when it is already finished");
-        self.assert_not_nested(
-            "buffer cannot be finished when a table or vector is under construction",
-        );
+        self.assert_not_nested("finish");
         self.written_vtable_revpos.clear();
 
         let to_align = {
@@ -587,7 +758,6 @@ impl<'fbb> FlatBufferBuilder<'fbb> {
         }
 
         if let Some(ident) = file_identifier {
-            debug_assert_eq!(ident.len(), FILE_IDENTIFIER_LENGTH);
             self.push_bytes_unprefixed(ident.as_bytes());
         }
 
@@ -603,7 +773,7 @@ impl<'fbb> FlatBufferBuilder<'fbb> {
     #[inline]
     fn align(&mut self, len: usize, alignment: PushAlignment) {
         self.track_min_align(alignment.value());
-        let s = self.used_space() as usize;
+        let s = self.used_space();
         self.make_space(padding_bytes(s + len, alignment.value()));
     }
 
@@ -633,8 +803,8 @@ impl<'fbb> FlatBufferBuilder<'fbb> {
             return want;
         }
         assert!(
-            want <= FLATBUFFERS_MAX_BUFFER_SIZE,
-            "cannot grow buffer beyond 2 gigabytes"
+            want <= self.max_size - self.used_space(),
+            "cannot grow buffer beyond 2 gigabytes without 64-bit offsets"
         );
 
         while self.unused_ready_space() < want {
@@ -646,29 +816,65 @@ impl<'fbb> FlatBufferBuilder<'fbb> {
     fn unused_ready_space(&self) -> usize {
         self.head
     }
+    /// Misusing the nesting of tables and vectors silently corrupts the
+    /// buffer, so unlike the other checks of the builder, the nesting checks
+    /// are made in release builds too.
     #[inline]
     fn assert_nested(&self, fn_name: &'static str) {
         // we don't assert that self.field_locs.len() >0 because the vtable
         // could be empty (e.g. for empty tables, or for all-default values).
-        debug_assert!(
+        assert!(
             self.nested,
-            format!(
-                "incorrect FlatBufferBuilder usage: {} must be called while in a nested state",
-                fn_name
-            )
+            "incorrect FlatBufferBuilder usage: {} must be called while a table or vector is \
+             under construction",
+            fn_name
         );
     }
     #[inline]
-    fn assert_not_nested(&self, msg: &'static str) {
-        debug_assert!(!self.nested, msg);
+    fn assert_not_nested(&self, fn_name: &'static str) {
+        assert!(
+            !self.nested,
+            "incorrect FlatBufferBuilder usage: {} can not be called while a table or vector is \
+             under construction, create the strings, vectors and tables it refers to first",
+            fn_name
+        );
     }
     #[inline]
     fn assert_finished(&self, msg: &'static str) {
-        debug_assert!(self.finished, msg);
+        debug_assert!(self.finished, "{}", msg);
     }
     #[inline]
     fn assert_not_finished(&self, msg: &'static str) {
-        debug_assert!(!self.finished, msg);
+        debug_assert!(!self.finished, "{}", msg);
+    }
+}
+
+/// Pushes the fields of a table under construction, see
+/// `FlatBufferBuilder::build_table`.
+#[derive(Debug)]
+pub struct TableWriter<'a, 'fbb> {
+    fbb: &'a mut FlatBufferBuilder<'fbb>,
+}
+
+impl TableWriter<'_, '_> {
+    /// Push a field, unless it is equal to its default value, like
+    /// `FlatBufferBuilder::push_slot`.
+    #[inline]
+    pub fn push_slot<X: Push + PartialEq>(&mut self, slotoff: VOffsetT, x: X, default: X) {
+        self.fbb.push_slot(slotoff, x, default)
+    }
+
+    /// Push a field, like `FlatBufferBuilder::push_slot_always`.
+    #[inline]
+    pub fn push_slot_always<X: Push>(&mut self, slotoff: VOffsetT, x: X) {
+        self.fbb.push_slot_always(slotoff, x)
+    }
+
+    /// Push a field from its little-endian `bytes`, like
+    /// `FlatBufferBuilder::push_slot_bytes`.
+    #[inline]
+    pub(crate) fn push_slot_bytes(&mut self, slotoff: VOffsetT, bytes: &[u8], alignment: usize) {
+        self.fbb.push_slot_bytes(slotoff, bytes, alignment)
     }
 }
 
@@ -684,14 +890,8 @@ fn get_vtable_byte_len(field_locs: &[FieldLoc]) -> usize {
     }
 }
 
-#[inline]
-fn padding_bytes(buf_size: usize, scalar_size: usize) -> usize {
-    // ((!buf_size) + 1) & (scalar_size - 1)
-    (!buf_size).wrapping_add(1) & (scalar_size.wrapping_sub(1))
-}
-
 impl<'fbb> Default for FlatBufferBuilder<'fbb> {
     fn default() -> Self {
-        Self::new_with_capacity(0)
+        Self::with_capacity(0)
     }
 }
@@ -0,0 +1,306 @@
+//! Copying tables from one buffer into a buffer being built, without reading
+//! them into objects first.
+//!
+//! [`copy_table`] is driven by the layout generated for every table, such as
+//! `Monster::layout()`, which tells it how the table's fields are stored. It
+//! copies the bytes of their values as they are, so a table can be spliced
+//! into a new buffer along with fields that are written as usual:
+//!
+//! ```ignore
+//! let weapon = incoming.equipped_as_weapon()?.unwrap().copy_to(&mut fbb)?;
+//! let monster = Monster::create(&mut fbb, &MonsterArgs {
+//!     equipped: Some(weapon.as_union_value()),
+//!     ..Default::default()
+//! });
+//! ```
+use alloc::vec::Vec;
+use core::{cmp::Reverse, convert::TryFrom};
+
+use crate::{
+    builder::FlatBufferBuilder,
+    endian_scalar::read_scalar_at,
+    error::{Error, Result},
+    primitives::*,
+    table::Table,
+    vector::{length64_prefixed_bytes, length_prefixed_bytes},
+};
+
+/// How deeply tables are nested in the copied data, which keeps the tables
+/// of a malicious buffer from overflowing the stack. This is the default
+/// `VerifierOptions::max_depth`.
+const MAX_DEPTH: usize = 64;
+
+/// How the fields of a table are stored in a buffer.
+#[derive(Debug)]
+pub struct TableLayout {
+    /// The fields that aren't deprecated, which are the ones copied.
+    pub fields: &'static [FieldLayout],
+}
+
+/// How a field of a table is stored in a buffer.
+#[derive(Debug, Clone, Copy)]
+pub struct FieldLayout {
+    /// The offset of the field's slot in the vtable.
+    pub slot: VOffsetT,
+    /// Whether the field refers to its value by a 64-bit offset.
+    pub offset64: bool,
+    pub value: ValueLayout,
+}
+
+/// How the value of a field is stored in a buffer. The tables that fields
+/// refer to are given by functions returning their layout, which lets a
+/// table refer to itself.
+#[derive(Debug, Clone, Copy)]
+pub enum ValueLayout {
+    /// A scalar, enum or struct of `size` bytes, stored inline.
+    Inline {
+        size: usize,
+        align: usize,
+    },
+    String,
+    /// A vector of scalars, enums or structs of `size` bytes.
+    Vector {
+        size: usize,
+        align: usize,
+    },
+    /// A vector of scalars, enums or structs of `size` bytes, with a 64-bit
+    /// length.
+    Vector64 {
+        size: usize,
+        align: usize,
+    },
+    /// A vector of strings.
+    Strings,
+    Table(fn() -> &'static TableLayout),
+    /// A vector of tables.
+    Tables(fn() -> &'static TableLayout),
+    /// A union, whose type is stored in the slot before it. The function
+    /// returns the layout of the table of each of the union's types.
+    Union(fn(u8) -> Option<&'static TableLayout>),
+    /// A vector of unions, whose types are stored in a vector in the slot
+    /// before it.
+    Unions(fn(u8) -> Option<&'static TableLayout>),
+}
+
+impl FieldLayout {
+    /// The size of the field's slot.
+    fn slot_size(&self) -> usize {
+        match self.value {
+            ValueLayout::Inline { size, .. } => size,
+            ValueLayout::Vector64 { .. } => SIZE_UOFFSET64,
+            _ if self.offset64 => SIZE_UOFFSET64,
+            _ => SIZE_UOFFSET,
+        }
+    }
+}
+
+/// Copy `table`, whose fields are stored as `layout` describes, to `fbb`,
+/// along with all the data it refers to, and return the offset of the copy.
+///
+/// Data that is referred to more than once, like a string shared by two
+/// tables, is copied for each reference. A union whose type isn't one of
+/// those in the layout fails with `Error::UnknownEnumValue`, since its table
+/// can't be copied.
+pub fn copy_table<'b>(
+    fbb: &mut FlatBufferBuilder<'b>,
+    table: &Table<'_>,
+    layout: &TableLayout,
+) -> Result<WIPOffset<TableFinishedWIPOffset>> {
+    Copier { fbb }.table(table, layout, 0).map(WIPOffset::new)
+}
+
+/// The slot a field of a copied table is stored in.
+enum Slot<'a> {
+    /// The bytes of a scalar, enum or struct, and their alignment.
+    Inline(&'a [u8], usize),
+    Offset(UOffsetT),
+    Offset64(UOffset64T),
+}
+
+impl Slot<'_> {
+    fn size(&self) -> usize {
+        match self {
+            Slot::Inline(bytes, _) => bytes.len(),
+            Slot::Offset(_) => SIZE_UOFFSET,
+            Slot::Offset64(_) => SIZE_UOFFSET64,
+        }
+    }
+}
+
+struct Copier<'a, 'b> {
+    fbb: &'a mut FlatBufferBuilder<'b>,
+}
+
+impl Copier<'_, '_> {
+    /// Copy the table and return its offset. The data its fields refer to is
+    /// copied first, since a table can't be built while it is.
+    fn table(&mut self, table: &Table<'_>, layout: &TableLayout, depth: usize) -> Result<UOffsetT> {
+        if depth >= MAX_DEPTH {
+            return Err(Error::TooManyLevels);
+        }
+        let buf = table.buf;
+        let mut slots = Vec::with_capacity(layout.fields.len());
+        for field in layout.fields {
+            let loc = match table.field_loc_sized(field.slot, field.slot_size())? {
+                Some(loc) => loc,
+                None => continue,
+            };
+            // The types of a union or vector of unions are stored in the slot
+            // before its values.
+            let type_slot = field.slot - SIZE_VOFFSET as VOffsetT;
+            let slot = match field.value {
+                ValueLayout::Inline { size, align } => Slot::Inline(&buf[loc..loc + size], align),
+                ValueLayout::Vector64 { size, align } => {
+                    let loc = follow64(buf, loc)?;
+                    let bytes = length64_prefixed_bytes(buf, loc, size)?;
+                    Slot::Offset64(
+                        self.fbb
+                            .create_vector64_bytes(bytes, bytes.len() / size, align),
+                    )
+                }
+                ValueLayout::Union(layout) => {
+                    let type_loc = match table.field_loc_sized(type_slot, 1)? {
+                        Some(type_loc) => type_loc,
+                        // A value without a type reads as `NONE`.
+                        None => continue,
+                    };
+                    let layout = match buf[type_loc] {
+                        0 => continue,
+                        ty => layout(ty).ok_or(Error::UnknownEnumValue)?,
+                    };
+                    let union_table = Table::new(buf, follow(buf, loc)?);
+                    slots.push((type_slot, Slot::Inline(&buf[type_loc..type_loc + 1], 1)));
+                    Slot::Offset(self.table(&union_table, layout, depth + 1)?)
+                }
+                ValueLayout::Unions(layout) => {
+                    let types_loc = table
+                        .field_loc_sized(type_slot, SIZE_UOFFSET)?
+                        .ok_or(Error::UnionVectorMismatch)?;
+                    let types = length_prefixed_bytes(buf, follow(buf, types_loc)?, 1)?;
+                    let values = self.unions(buf, follow(buf, loc)?, types, layout, depth)?;
+                    let types = self.fbb.create_vector_bytes(types, types.len(), 1);
+                    slots.push((type_slot, Slot::Offset(types)));
+                    Slot::Offset(values)
+                }
+                value if field.offset64 => {
+                    let loc = follow64(buf, loc)?;
+                    Slot::Offset64(UOffset64T::from(self.offset(buf, loc, value, depth)?))
+                }
+                value => Slot::Offset(self.offset(buf, follow(buf, loc)?, value, depth)?),
+            };
+            slots.push((field.slot, slot));
+        }
+
+        // Like generated code, write the largest fields first to minimize
+        // padding.
+        slots.sort_by_key(|(_, slot)| Reverse(slot.size()));
+        let table = self.fbb.build_table(|table| {
+            for (slot_offset, slot) in slots {
+                match slot {
+                    Slot::Inline(bytes, align) => table.push_slot_bytes(slot_offset, bytes, align),
+                    Slot::Offset(offset) => {
+                        table.push_slot_always(slot_offset, WIPOffset::<()>::new(offset))
+                    }
+                    Slot::Offset64(offset) => {
+                        table.push_slot_always(slot_offset, WIPOffset64::<()>::new(offset))
+                    }
+                }
+            }
+        });
+        Ok(table.value())
+    }
+
+    /// Copy the string, vector or table `value` at `loc` and return its
+    /// offset.
+    fn offset(
+        &mut self,
+        buf: &[u8],
+        loc: usize,
+        value: ValueLayout,
+        depth: usize,
+    ) -> Result<UOffsetT> {
+        match value {
+            ValueLayout::String => {
+                let bytes = length_prefixed_bytes(buf, loc, 1)?;
+                Ok(self.fbb.create_byte_string(bytes).value())
+            }
+            ValueLayout::Vector { size, align } => {
+                let bytes = length_prefixed_bytes(buf, loc, size)?;
+                Ok(self
+                    .fbb
+                    .create_vector_bytes(bytes, bytes.len() / size, align))
+            }
+            ValueLayout::Table(layout) => self.table(&Table::new(buf, loc), layout(), depth + 1),
+            ValueLayout::Strings | ValueLayout::Tables(_) => {
+                let elements = length_prefixed_bytes(buf, loc, SIZE_UOFFSET)?;
+                let element = match value {
+                    ValueLayout::Tables(layout) => ValueLayout::Table(layout),
+                    _ => ValueLayout::String,
+                };
+                let offsets = (0..elements.len() / SIZE_UOFFSET)
+                    .map(|i| {
+                        let element_loc = loc + SIZE_UOFFSET * (i + 1);
+                        self.offset(buf, follow(buf, element_loc)?, element, depth)
+                            .map(Some)
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(self.offsets(&offsets))
+            }
+            _ => unreachable!("not referred to by an offset: {:?}", value),
+        }
+    }
+
+    /// Copy the values of a vector of unions, at `loc`, whose types are
+    /// `types`.
+    fn unions(
+        &mut self,
+        buf: &[u8],
+        loc: usize,
+        types: &[u8],
+        layout: fn(u8) -> Option<&'static TableLayout>,
+        depth: usize,
+    ) -> Result<UOffsetT> {
+        let values = length_prefixed_bytes(buf, loc, SIZE_UOFFSET)?;
+        if values.len() / SIZE_UOFFSET != types.len() {
+            return Err(Error::UnionVectorMismatch);
+        }
+        let offsets = types
+            .iter()
+            .enumerate()
+            .map(|(i, &ty)| {
+                if ty == 0 {
+                    return Ok(None);
+                }
+                let layout = layout(ty).ok_or(Error::UnknownEnumValue)?;
+                let table = Table::new(buf, follow(buf, loc + SIZE_UOFFSET * (i + 1))?);
+                self.table(&table, layout, depth + 1).map(Some)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(self.offsets(&offsets))
+    }
+
+    /// Write a vector of offsets, where `None` is written as 0.
+    fn offsets(&mut self, offsets: &[Option<UOffsetT>]) -> UOffsetT {
+        self.fbb.start_vector::<WIPOffset<()>>(offsets.len());
+        for offset in offsets.iter().rev() {
+            self.fbb.push(offset.map(WIPOffset::<()>::new));
+        }
+        self.fbb.end_vector::<WIPOffset<()>>(offsets.len()).value()
+    }
+}
+
+/// The location an offset stored at `loc` points to.
+fn follow(buf: &[u8], loc: usize) -> Result<usize> {
+    let offset = read_scalar_at::<UOffsetT>(buf, loc)?;
+    loc.checked_add(offset as usize)
+        .ok_or(Error::InvalidOffset { loc })
+}
+
+/// The location a 64-bit offset stored at `loc` points to.
+fn follow64(buf: &[u8], loc: usize) -> Result<usize> {
+    let offset = read_scalar_at::<UOffset64T>(buf, loc)?;
+    usize::try_from(offset)
+        .ok()
+        .and_then(|offset| loc.checked_add(offset))
+        .ok_or(Error::InvalidOffset { loc })
+}
@@ -14,7 +14,9 @@
  * limitations under the License.
  */
 
-use std::mem::size_of;
+use core::{convert::TryInto, mem::size_of};
+
+use crate::{error::Result, follow::checked_slice};
 
 /// Trait for values that must be stored in little-endian byte order, but
 /// might be represented in memory as big-endian. Every type that implements
@@ -26,121 +28,119 @@ use std::mem::size_of;
 /// Note that we do not use the num-traits crate for this, because it provides
 /// "too much". For example, num-traits provides i128 support, but that is an
 /// invalid FlatBuffers type.
+///
+/// Scalars are read and written through their little-endian bytes, never by
+/// casting pointers into the buffer, so they may be at any position in it,
+/// on hosts of either byte order, and a type doesn't need every bit pattern
+/// to be valid to implement this trait.
+#[allow(clippy::wrong_self_convention)]
 pub trait EndianScalar: Sized + PartialEq + Copy + Clone {
     fn to_little_endian(self) -> Self;
     fn from_little_endian(self) -> Self;
+
+    /// Read the scalar from the little-endian bytes at the start of `bytes`.
+    ///
+    /// Panics if `bytes` is shorter than the scalar.
+    fn read_le(bytes: &[u8]) -> Self;
+
+    /// Write the little-endian bytes of the scalar to the start of `dst`.
+    ///
+    /// Panics if `dst` is shorter than the scalar.
+    fn write_le(self, dst: &mut [u8]);
 }
 
-/// Macro for implementing a no-op endian conversion. This is used for types
-/// that are one byte wide.
-macro_rules! impl_endian_scalar_noop {
+/// Macro for implementing an endian conversion using the stdlib `to_le` and
+/// `from_le` functions, and reads and writes using `from_le_bytes` and
+/// `to_le_bytes`. This is used for integer types.
+macro_rules! impl_endian_scalar_int {
     ($ty:ident) => {
         impl EndianScalar for $ty {
             #[inline]
             fn to_little_endian(self) -> Self {
-                self
+                Self::to_le(self)
             }
             #[inline]
             fn from_little_endian(self) -> Self {
-                self
+                Self::from_le(self)
+            }
+            #[inline]
+            fn read_le(bytes: &[u8]) -> Self {
+                Self::from_le_bytes(bytes[..size_of::<Self>()].try_into().unwrap())
+            }
+            #[inline]
+            fn write_le(self, dst: &mut [u8]) {
+                dst[..size_of::<Self>()].copy_from_slice(&self.to_le_bytes());
             }
         }
     };
 }
 
-/// Macro for implementing an endian conversion using the stdlib `to_le` and
-/// `from_le` functions. This is used for integer types. It is not used for
-/// floats, because the `to_le` and `from_le` are not implemented for them in
-/// the stdlib.
-macro_rules! impl_endian_scalar_stdlib_le_conversion {
-    ($ty:ident) => {
+/// Macro for implementing an endian conversion for floats through their
+/// bits, since `to_le` and `from_le` are not implemented for them in the
+/// stdlib.
+macro_rules! impl_endian_scalar_float {
+    ($ty:ident, $bits:ident) => {
         impl EndianScalar for $ty {
             #[inline]
             fn to_little_endian(self) -> Self {
-                Self::to_le(self)
+                Self::from_bits(self.to_bits().to_le())
             }
             #[inline]
             fn from_little_endian(self) -> Self {
-                Self::from_le(self)
+                Self::from_bits($bits::from_le(self.to_bits()))
+            }
+            #[inline]
+            fn read_le(bytes: &[u8]) -> Self {
+                Self::from_le_bytes(bytes[..size_of::<Self>()].try_into().unwrap())
+            }
+            #[inline]
+            fn write_le(self, dst: &mut [u8]) {
+                dst[..size_of::<Self>()].copy_from_slice(&self.to_le_bytes());
             }
         }
     };
 }
 
-impl_endian_scalar_noop!(bool);
-impl_endian_scalar_noop!(u8);
-impl_endian_scalar_noop!(i8);
-
-impl_endian_scalar_stdlib_le_conversion!(u16);
-impl_endian_scalar_stdlib_le_conversion!(u32);
-impl_endian_scalar_stdlib_le_conversion!(u64);
-impl_endian_scalar_stdlib_le_conversion!(i16);
-impl_endian_scalar_stdlib_le_conversion!(i32);
-impl_endian_scalar_stdlib_le_conversion!(i64);
-
-impl EndianScalar for f32 {
-    /// Convert f32 from host endian-ness to little-endian.
+/// A `bool` is stored as a byte, which is `true` unless it is 0, like
+/// FlatBuffers readers in other languages read it.
+impl EndianScalar for bool {
     #[inline]
     fn to_little_endian(self) -> Self {
-        #[cfg(target_endian = "little")]
-        {
-            self
-        }
-        #[cfg(not(target_endian = "little"))]
-        {
-            byte_swap_f32(self)
-        }
+        self
     }
-    /// Convert f32 from little-endian to host endian-ness.
     #[inline]
     fn from_little_endian(self) -> Self {
-        #[cfg(target_endian = "little")]
-        {
-            self
-        }
-        #[cfg(not(target_endian = "little"))]
-        {
-            byte_swap_f32(self)
-        }
+        self
     }
-}
-
-impl EndianScalar for f64 {
-    /// Convert f64 from host endian-ness to little-endian.
     #[inline]
-    fn to_little_endian(self) -> Self {
-        #[cfg(target_endian = "little")]
-        {
-            self
-        }
-        #[cfg(not(target_endian = "little"))]
-        {
-            byte_swap_f64(self)
-        }
+    fn read_le(bytes: &[u8]) -> Self {
+        bytes[0] != 0
     }
-    /// Convert f64 from little-endian to host endian-ness.
     #[inline]
-    fn from_little_endian(self) -> Self {
-        #[cfg(target_endian = "little")]
-        {
-            self
-        }
-        #[cfg(not(target_endian = "little"))]
-        {
-            byte_swap_f64(self)
-        }
+    fn write_le(self, dst: &mut [u8]) {
+        dst[0] = u8::from(self);
     }
 }
 
+impl_endian_scalar_int!(u8);
+impl_endian_scalar_int!(i8);
+impl_endian_scalar_int!(u16);
+impl_endian_scalar_int!(u32);
+impl_endian_scalar_int!(u64);
+impl_endian_scalar_int!(i16);
+impl_endian_scalar_int!(i32);
+impl_endian_scalar_int!(i64);
+
+impl_endian_scalar_float!(f32, u32);
+impl_endian_scalar_float!(f64, u64);
+
 /// Swaps the bytes of an f32.
-#[allow(dead_code)]
 #[inline]
 pub fn byte_swap_f32(x: f32) -> f32 {
     f32::from_bits(x.to_bits().swap_bytes())
 }
 
 /// Swaps the bytes of an f64.
-#[allow(dead_code)]
 #[inline]
 pub fn byte_swap_f64(x: f64) -> f64 {
     f64::from_bits(x.to_bits().swap_bytes())
@@ -148,32 +148,27 @@ pub fn byte_swap_f64(x: f64) -> f64 {
 
 /// Place an EndianScalar into the provided mutable byte slice. Performs
 /// endian conversion, if necessary.
+///
+/// Panics if `s` is shorter than the scalar.
 #[inline]
 pub fn emplace_scalar<T: EndianScalar>(s: &mut [u8], x: T) {
-    let sz = size_of::<T>();
-    let mut_ptr = (&mut s[..sz]).as_mut_ptr() as *mut T;
-    let val = x.to_little_endian();
-    unsafe {
-        *mut_ptr = val;
-    }
+    x.write_le(s);
 }
 
 /// Read an EndianScalar from the provided byte slice at the specified location.
 /// Performs endian conversion, if necessary.
+///
+/// Fails with `Error::OutOfBounds` if the scalar extends past the end of `s`.
 #[inline]
-pub fn read_scalar_at<T: EndianScalar>(s: &[u8], loc: usize) -> T {
-    let buf = &s[loc..loc + size_of::<T>()];
-    read_scalar(buf)
+pub fn read_scalar_at<T: EndianScalar>(s: &[u8], loc: usize) -> Result<T> {
+    checked_slice(s, loc, size_of::<T>()).map(T::read_le)
 }
 
-/// Read an EndianScalar from the provided byte slice. Performs endian
-/// conversion, if necessary.
+/// Read an EndianScalar from the start of the provided byte slice. Performs
+/// endian conversion, if necessary.
+///
+/// Fails with `Error::OutOfBounds` if `s` is shorter than the scalar.
 #[inline]
-pub fn read_scalar<T: EndianScalar>(s: &[u8]) -> T {
-    let sz = size_of::<T>();
-
-    let p = (&s[..sz]).as_ptr() as *const T;
-    let x = unsafe { *p };
-
-    x.from_little_endian()
+pub fn read_scalar<T: EndianScalar>(s: &[u8]) -> Result<T> {
+    read_scalar_at(s, 0)
 }
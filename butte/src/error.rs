@@ -0,0 +1,223 @@
+use core::fmt;
+
+use crate::primitives::VOffsetT;
+
+/// Errors that can occur while reading a FlatBuffer.
+///
+/// Errors about the layout of a buffer carry the location in the buffer they
+/// occurred at, which [`Error::hexdump`] shows the bytes around.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Error {
+    /// A read of `len` bytes at `loc` extends past the end of the buffer,
+    /// which is `buf_len` bytes long.
+    OutOfBounds {
+        loc: usize,
+        len: usize,
+        buf_len: usize,
+    },
+    /// A struct or slice at `loc` is not aligned to `align` bytes.
+    Unaligned { loc: usize, align: usize },
+    /// The offset at `loc` points outside the addressable range of the
+    /// buffer.
+    InvalidOffset { loc: usize },
+    /// A field marked as `required` in the schema is missing from a table.
+    /// `slot` is the offset of the field's slot in the vtable.
+    MissingRequiredField { slot: VOffsetT },
+    /// A field was read as a type that doesn't match its type in the schema.
+    TypeMismatch,
+    /// An enum holds a value that isn't declared in the schema.
+    UnknownEnumValue,
+    /// The string at `loc` is not valid UTF-8.
+    InvalidUtf8 { loc: usize },
+    /// The `bool` at `loc` is stored as a byte other than 0 or 1, which only
+    /// fails verification with `VerifierOptions::strict_bools`.
+    InvalidBool { loc: usize },
+    /// The vectors holding the types and the values of a vector of unions
+    /// don't have the same length, or only one of them is present.
+    UnionVectorMismatch,
+    /// The buffer doesn't hold the file identifier of the schema.
+    WrongFileIdentifier,
+    /// The offset from the table at `loc` to its vtable points outside the
+    /// buffer.
+    InvalidVTableOffset { loc: usize },
+    /// The vtable at `loc` is too small to hold its size fields, isn't made
+    /// of whole slots, or extends past the end of the buffer.
+    InvalidVTableSize { loc: usize },
+    /// The inline size of the table at `loc`, as given by its vtable, is too
+    /// small to hold the offset to the vtable, or extends past the end of the
+    /// buffer.
+    InvalidTableSize { loc: usize },
+    /// The field in the vtable slot `slot` of the table at `loc` doesn't lie
+    /// within the table's inline data.
+    InvalidFieldOffset { loc: usize, slot: VOffsetT },
+    /// Tables are nested more deeply than `VerifierOptions::max_depth` allows,
+    /// or than a reader is prepared to follow.
+    TooManyLevels,
+    /// A buffer holds more tables than `VerifierOptions::max_tables` allows.
+    TooManyTables,
+    /// The data verified in a buffer adds up to more bytes than
+    /// `VerifierOptions::max_apparent_size` allows.
+    ApparentSizeTooLarge,
+}
+
+impl Error {
+    /// A read of `len` bytes at `loc` past the end of `buf`.
+    #[inline]
+    pub(crate) fn out_of_bounds(buf: &[u8], loc: usize, len: usize) -> Self {
+        Error::OutOfBounds {
+            loc,
+            len,
+            buf_len: buf.len(),
+        }
+    }
+
+    /// The location in the buffer the error occurred at, if it is known.
+    pub fn location(&self) -> Option<usize> {
+        match *self {
+            Error::OutOfBounds { loc, .. }
+            | Error::Unaligned { loc, .. }
+            | Error::InvalidOffset { loc }
+            | Error::InvalidUtf8 { loc }
+            | Error::InvalidBool { loc }
+            | Error::InvalidVTableOffset { loc }
+            | Error::InvalidVTableSize { loc }
+            | Error::InvalidTableSize { loc }
+            | Error::InvalidFieldOffset { loc, .. } => Some(loc),
+            _ => None,
+        }
+    }
+
+    /// A hexdump of the bytes of `buf`, the buffer the error occurred in,
+    /// around the location of the error, if it is known.
+    pub fn hexdump<'a>(&self, buf: &'a [u8]) -> Option<HexDump<'a>> {
+        self.location().map(|loc| HexDump::new(buf, loc))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::OutOfBounds { loc, len, buf_len } => write!(
+                f,
+                "read of {} bytes at {} past the end of the buffer of {} bytes",
+                len, loc, buf_len
+            ),
+            Error::Unaligned { loc, align } => {
+                write!(f, "data at {} is not aligned to {} bytes", loc, align)
+            }
+            Error::InvalidOffset { loc } => write!(f, "invalid offset at {}", loc),
+            Error::MissingRequiredField { slot } => {
+                write!(f, "missing required field in vtable slot {}", slot)
+            }
+            Error::TypeMismatch => write!(f, "field type mismatch"),
+            Error::UnknownEnumValue => write!(f, "unknown enum value"),
+            Error::InvalidUtf8 { loc } => write!(f, "invalid UTF-8 in string at {}", loc),
+            Error::InvalidBool { loc } => write!(f, "invalid bool at {}", loc),
+            Error::UnionVectorMismatch => write!(f, "mismatched union vector types and values"),
+            Error::WrongFileIdentifier => write!(f, "wrong file identifier"),
+            Error::InvalidVTableOffset { loc } => {
+                write!(
+                    f,
+                    "vtable offset of table at {} points outside the buffer",
+                    loc
+                )
+            }
+            Error::InvalidVTableSize { loc } => write!(f, "invalid size of vtable at {}", loc),
+            Error::InvalidTableSize { loc } => write!(f, "invalid size of table at {}", loc),
+            Error::InvalidFieldOffset { loc, slot } => write!(
+                f,
+                "field in vtable slot {} lies outside the table at {}",
+                slot, loc
+            ),
+            Error::TooManyLevels => write!(f, "tables nested too deeply"),
+            Error::TooManyTables => write!(f, "too many tables"),
+            Error::ApparentSizeTooLarge => write!(f, "apparent size of the buffer too large"),
+        }
+    }
+}
+
+/// The bytes of a buffer around a location, formatted as a hexdump of the
+/// rows of 16 bytes before, at and after it, with the byte at the location
+/// marked.
+///
+/// ```text
+/// 00000010  0c 00 00 00 08 00 0c 00  04 00 08 00 08 00 00 00
+/// 00000020  01 00 00 00 04 00 00 00  06 00 00 00 68 61 6d 6d
+///                                    ^^
+/// 00000030  65 72 00 00
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct HexDump<'a> {
+    buf: &'a [u8],
+    loc: usize,
+    rows: usize,
+}
+
+impl<'a> HexDump<'a> {
+    const ROW: usize = 16;
+
+    /// A hexdump of `buf` around `loc`, with one row before and one row after
+    /// the row of `loc`.
+    pub fn new(buf: &'a [u8], loc: usize) -> Self {
+        Self { buf, loc, rows: 1 }
+    }
+
+    /// Show `rows` rows before and after the row of the location.
+    pub fn rows(self, rows: usize) -> Self {
+        Self { rows, ..self }
+    }
+}
+
+impl fmt::Display for HexDump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let row_of_loc = self.loc / Self::ROW;
+        let first = row_of_loc.saturating_sub(self.rows);
+        let last = row_of_loc.saturating_add(self.rows);
+        for row in first..=last {
+            let start = row * Self::ROW;
+            let bytes = match self.buf.get(start..) {
+                Some(bytes) if !bytes.is_empty() || row == row_of_loc => bytes,
+                _ => break,
+            };
+            let bytes = &bytes[..bytes.len().min(Self::ROW)];
+            write!(f, "{:08x} ", start)?;
+            for (i, byte) in bytes.iter().enumerate() {
+                let sep = if i == Self::ROW / 2 { "  " } else { " " };
+                write!(f, "{}{:02x}", sep, byte)?;
+            }
+            writeln!(f)?;
+            if row == row_of_loc {
+                // mark the location, even if it is past the end of the buffer
+                let col = self.loc - start;
+                let indent = 10 + 3 * col + usize::from(col >= Self::ROW / 2);
+                writeln!(f, "{:indent$}^^", "", indent = indent)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// The error returned when converting an integer to an enum that has no value
+/// with that discriminant. It holds the integer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UnknownEnumValue<T>(pub T);
+
+impl<T: fmt::Display> fmt::Display for UnknownEnumValue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown enum value {}", self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: fmt::Debug + fmt::Display> std::error::Error for UnknownEnumValue<T> {}
+
+impl<T> From<UnknownEnumValue<T>> for Error {
+    fn from(_: UnknownEnumValue<T>) -> Self {
+        Error::UnknownEnumValue
+    }
+}
+
+pub type Result<T> = core::result::Result<T, Error>;
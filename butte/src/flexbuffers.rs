@@ -0,0 +1,886 @@
+//! The FlexBuffers format, for schemaless data.
+//!
+//! FlexBuffers store self-describing values: every value carries its type, so
+//! a buffer can be read without a schema, e.g., to embed dynamic data in a
+//! `[ubyte]` field with the `flexbuffer` attribute. The format is the one of
+//! flatc's `flexbuffers.h`, so buffers written by its C++ builder can be read
+//! with [`Reader`], and the other way around with [`Builder`].
+//!
+//! Like the rest of the crate, reading checks every access against the bounds
+//! of the buffer and returns an error instead of panicking.
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::{cmp::Ordering, convert::TryFrom, str};
+
+use crate::{
+    error::{Error, Result},
+    follow::checked_slice,
+};
+
+/// The type of a value in a FlexBuffer.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FlexBufferType {
+    Null = 0,
+    Int = 1,
+    UInt = 2,
+    Float = 3,
+    /// A NUL-terminated string, used for the keys of maps.
+    Key = 4,
+    String = 5,
+    IndirectInt = 6,
+    IndirectUInt = 7,
+    IndirectFloat = 8,
+    Map = 9,
+    /// A vector whose elements each have their own type.
+    Vector = 10,
+    VectorInt = 11,
+    VectorUInt = 12,
+    VectorFloat = 13,
+    VectorKey = 14,
+    /// A vector of strings, which flatc no longer writes.
+    VectorString = 15,
+    VectorInt2 = 16,
+    VectorUInt2 = 17,
+    VectorFloat2 = 18,
+    VectorInt3 = 19,
+    VectorUInt3 = 20,
+    VectorFloat3 = 21,
+    VectorInt4 = 22,
+    VectorUInt4 = 23,
+    VectorFloat4 = 24,
+    Blob = 25,
+    Bool = 26,
+    VectorBool = 36,
+}
+
+impl TryFrom<u8> for FlexBufferType {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        use FlexBufferType::*;
+        Ok(match value {
+            0 => Null,
+            1 => Int,
+            2 => UInt,
+            3 => Float,
+            4 => Key,
+            5 => String,
+            6 => IndirectInt,
+            7 => IndirectUInt,
+            8 => IndirectFloat,
+            9 => Map,
+            10 => Vector,
+            11 => VectorInt,
+            12 => VectorUInt,
+            13 => VectorFloat,
+            14 => VectorKey,
+            15 => VectorString,
+            16 => VectorInt2,
+            17 => VectorUInt2,
+            18 => VectorFloat2,
+            19 => VectorInt3,
+            20 => VectorUInt3,
+            21 => VectorFloat3,
+            22 => VectorInt4,
+            23 => VectorUInt4,
+            24 => VectorFloat4,
+            25 => Blob,
+            26 => Bool,
+            36 => VectorBool,
+            _ => return Err(Error::UnknownEnumValue),
+        })
+    }
+}
+
+impl FlexBufferType {
+    /// Check whether values of the type are stored in their parent, rather
+    /// than referred to by an offset.
+    pub fn is_inline(self) -> bool {
+        use FlexBufferType::*;
+        matches!(self, Null | Int | UInt | Float | Bool)
+    }
+
+    /// The type of the elements and the length of a typed vector, where the
+    /// length is only known for vectors of a fixed length.
+    fn typed_vector(self) -> Option<(Self, Option<usize>)> {
+        use FlexBufferType::*;
+        Some(match self {
+            VectorInt => (Int, None),
+            VectorUInt => (UInt, None),
+            VectorFloat => (Float, None),
+            VectorKey => (Key, None),
+            VectorString => (String, None),
+            VectorBool => (Bool, None),
+            VectorInt2 => (Int, Some(2)),
+            VectorUInt2 => (UInt, Some(2)),
+            VectorFloat2 => (Float, Some(2)),
+            VectorInt3 => (Int, Some(3)),
+            VectorUInt3 => (UInt, Some(3)),
+            VectorFloat3 => (Float, Some(3)),
+            VectorInt4 => (Int, Some(4)),
+            VectorUInt4 => (UInt, Some(4)),
+            VectorFloat4 => (Float, Some(4)),
+            _ => return None,
+        })
+    }
+
+    /// The type of a vector of values of this type, if they can be stored in a
+    /// typed vector.
+    #[cfg(feature = "alloc")]
+    fn to_typed_vector(self) -> Option<Self> {
+        use FlexBufferType::*;
+        Some(match self {
+            Int => VectorInt,
+            UInt => VectorUInt,
+            Float => VectorFloat,
+            Key => VectorKey,
+            Bool => VectorBool,
+            _ => return None,
+        })
+    }
+}
+
+/// The width of a value in a FlexBuffer, which is the log2 of its size in
+/// bytes.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BitWidth {
+    W8 = 0,
+    W16 = 1,
+    W32 = 2,
+    W64 = 3,
+}
+
+impl BitWidth {
+    fn from_bits(bits: u8) -> Self {
+        match bits & 3 {
+            0 => BitWidth::W8,
+            1 => BitWidth::W16,
+            2 => BitWidth::W32,
+            _ => BitWidth::W64,
+        }
+    }
+
+    /// The size in bytes of values of this width.
+    pub fn byte_width(self) -> usize {
+        1 << self as u8
+    }
+
+    #[cfg(feature = "alloc")]
+    fn of_u64(value: u64) -> Self {
+        if value <= u64::from(u8::MAX) {
+            BitWidth::W8
+        } else if value <= u64::from(u16::MAX) {
+            BitWidth::W16
+        } else if value <= u64::from(u32::MAX) {
+            BitWidth::W32
+        } else {
+            BitWidth::W64
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    fn of_i64(value: i64) -> Self {
+        // zigzag encoding maps values close to zero to small unsigned values
+        Self::of_u64(((value << 1) ^ (value >> 63)) as u64)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn of_f64(value: f64) -> Self {
+        if f64::from(value as f32) == value || value.is_nan() {
+            BitWidth::W32
+        } else {
+            BitWidth::W64
+        }
+    }
+}
+
+fn read_u64(buf: &[u8], loc: usize, byte_width: usize) -> Result<u64> {
+    let bytes = checked_slice(buf, loc, byte_width)?;
+    Ok(match *bytes {
+        [a] => u64::from(a),
+        [a, b] => u64::from(u16::from_le_bytes([a, b])),
+        [a, b, c, d] => u64::from(u32::from_le_bytes([a, b, c, d])),
+        [a, b, c, d, e, f, g, h] => u64::from_le_bytes([a, b, c, d, e, f, g, h]),
+        _ => return Err(Error::InvalidOffset { loc }),
+    })
+}
+
+fn read_i64(buf: &[u8], loc: usize, byte_width: usize) -> Result<i64> {
+    let value = read_u64(buf, loc, byte_width)?;
+    // sign-extend narrower values
+    let shift = 64 - 8 * byte_width as u32;
+    Ok(((value << shift) as i64) >> shift)
+}
+
+fn read_f64(buf: &[u8], loc: usize, byte_width: usize) -> Result<f64> {
+    let value = read_u64(buf, loc, byte_width)?;
+    match byte_width {
+        4 => Ok(f64::from(f32::from_bits(value as u32))),
+        8 => Ok(f64::from_bits(value)),
+        _ => Err(Error::TypeMismatch),
+    }
+}
+
+fn read_usize(buf: &[u8], loc: usize, byte_width: usize) -> Result<usize> {
+    usize::try_from(read_u64(buf, loc, byte_width)?).map_err(|_| Error::InvalidOffset { loc })
+}
+
+/// Follow the offset at `loc`, which points backwards.
+fn follow_offset(buf: &[u8], loc: usize, byte_width: usize) -> Result<usize> {
+    let off = read_usize(buf, loc, byte_width)?;
+    loc.checked_sub(off).ok_or(Error::InvalidOffset { loc })
+}
+
+/// Read the NUL-terminated string at `loc`.
+fn read_key(buf: &[u8], loc: usize) -> Result<&[u8]> {
+    let bytes = buf
+        .get(loc..)
+        .ok_or_else(|| Error::out_of_bounds(buf, loc, 1))?;
+    let len = bytes
+        .iter()
+        .position(|&b| b == 0)
+        .ok_or_else(|| Error::out_of_bounds(buf, loc, bytes.len() + 1))?;
+    Ok(&bytes[..len])
+}
+
+/// A value in a FlexBuffer.
+#[derive(Debug, Clone, Copy)]
+pub struct Reader<'a> {
+    buf: &'a [u8],
+    loc: usize,
+    /// The byte width the value is stored with in its parent.
+    parent_width: usize,
+    /// The byte width of the data an offset refers to.
+    byte_width: usize,
+    ty: FlexBufferType,
+}
+
+impl<'a> Reader<'a> {
+    /// Read the root value of the FlexBuffer `buf`.
+    pub fn get_root(buf: &'a [u8]) -> Result<Self> {
+        let end = buf
+            .len()
+            .checked_sub(2)
+            .ok_or_else(|| Error::out_of_bounds(buf, 0, 2))?;
+        let parent_width = usize::from(buf[end + 1]);
+        if !matches!(parent_width, 1 | 2 | 4 | 8) {
+            return Err(Error::InvalidOffset { loc: end + 1 });
+        }
+        let loc = end
+            .checked_sub(parent_width)
+            .ok_or_else(|| Error::out_of_bounds(buf, 0, parent_width + 2))?;
+        Self::new(buf, loc, parent_width, buf[end])
+    }
+
+    fn new(buf: &'a [u8], loc: usize, parent_width: usize, packed_type: u8) -> Result<Self> {
+        Ok(Self {
+            buf,
+            loc,
+            parent_width,
+            byte_width: BitWidth::from_bits(packed_type).byte_width(),
+            ty: FlexBufferType::try_from(packed_type >> 2)?,
+        })
+    }
+
+    pub fn flexbuffer_type(&self) -> FlexBufferType {
+        self.ty
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.ty == FlexBufferType::Null
+    }
+
+    /// The location of the data the value refers to.
+    fn target(&self) -> Result<usize> {
+        follow_offset(self.buf, self.loc, self.parent_width)
+    }
+
+    pub fn get_bool(&self) -> Result<bool> {
+        match self.ty {
+            FlexBufferType::Bool => Ok(read_u64(self.buf, self.loc, self.parent_width)? != 0),
+            _ => Err(Error::TypeMismatch),
+        }
+    }
+
+    /// Read an integer, which fails if it doesn't fit into an `i64`.
+    pub fn get_i64(&self) -> Result<i64> {
+        match self.ty {
+            FlexBufferType::Int => read_i64(self.buf, self.loc, self.parent_width),
+            FlexBufferType::IndirectInt => read_i64(self.buf, self.target()?, self.byte_width),
+            FlexBufferType::UInt | FlexBufferType::IndirectUInt => {
+                i64::try_from(self.get_u64()?).map_err(|_| Error::TypeMismatch)
+            }
+            _ => Err(Error::TypeMismatch),
+        }
+    }
+
+    /// Read an integer, which fails if it doesn't fit into a `u64`.
+    pub fn get_u64(&self) -> Result<u64> {
+        match self.ty {
+            FlexBufferType::UInt => read_u64(self.buf, self.loc, self.parent_width),
+            FlexBufferType::IndirectUInt => read_u64(self.buf, self.target()?, self.byte_width),
+            FlexBufferType::Int | FlexBufferType::IndirectInt => {
+                u64::try_from(self.get_i64()?).map_err(|_| Error::TypeMismatch)
+            }
+            _ => Err(Error::TypeMismatch),
+        }
+    }
+
+    pub fn get_f64(&self) -> Result<f64> {
+        match self.ty {
+            FlexBufferType::Float => read_f64(self.buf, self.loc, self.parent_width),
+            FlexBufferType::IndirectFloat => read_f64(self.buf, self.target()?, self.byte_width),
+            _ => Err(Error::TypeMismatch),
+        }
+    }
+
+    /// Read a string or a key.
+    pub fn get_str(&self) -> Result<&'a str> {
+        let (loc, bytes) = match self.ty {
+            FlexBufferType::String => (self.target()?, self.length_prefixed_bytes()?),
+            FlexBufferType::Key => {
+                let loc = self.target()?;
+                (loc, read_key(self.buf, loc)?)
+            }
+            _ => return Err(Error::TypeMismatch),
+        };
+        str::from_utf8(bytes).map_err(|_| Error::InvalidUtf8 { loc })
+    }
+
+    pub fn get_blob(&self) -> Result<&'a [u8]> {
+        match self.ty {
+            FlexBufferType::Blob => self.length_prefixed_bytes(),
+            _ => Err(Error::TypeMismatch),
+        }
+    }
+
+    fn length_prefixed_bytes(&self) -> Result<&'a [u8]> {
+        let loc = self.target()?;
+        let len_loc = loc
+            .checked_sub(self.byte_width)
+            .ok_or(Error::InvalidOffset { loc })?;
+        let len = read_usize(self.buf, len_loc, self.byte_width)?;
+        checked_slice(self.buf, loc, len)
+    }
+
+    /// Read a vector of any kind, or the values of a map.
+    pub fn get_vector(&self) -> Result<VectorReader<'a>> {
+        let loc = self.target()?;
+        let (element_type, len) = match self.ty {
+            FlexBufferType::Vector | FlexBufferType::Map => (None, None),
+            ty => match ty.typed_vector() {
+                Some((element_type, len)) => (Some(element_type), len),
+                None => return Err(Error::TypeMismatch),
+            },
+        };
+        VectorReader::new(self.buf, loc, self.byte_width, element_type, len)
+    }
+
+    pub fn get_map(&self) -> Result<MapReader<'a>> {
+        if self.ty != FlexBufferType::Map {
+            return Err(Error::TypeMismatch);
+        }
+        let values = self.get_vector()?;
+        let bw = self.byte_width;
+        let keys_loc = values
+            .loc
+            .checked_sub(3 * bw)
+            .ok_or(Error::InvalidOffset { loc: values.loc })?;
+        let keys_width = read_usize(self.buf, keys_loc + bw, bw)?;
+        if !matches!(keys_width, 1 | 2 | 4 | 8) {
+            return Err(Error::InvalidOffset { loc: keys_loc + bw });
+        }
+        let keys_loc = follow_offset(self.buf, keys_loc, bw)?;
+        let keys = VectorReader::new(
+            self.buf,
+            keys_loc,
+            keys_width,
+            Some(FlexBufferType::Key),
+            None,
+        )?;
+        if keys.len() != values.len() {
+            return Err(Error::InvalidOffset { loc: keys_loc });
+        }
+        Ok(MapReader { keys, values })
+    }
+}
+
+/// A vector in a FlexBuffer.
+#[derive(Debug, Clone, Copy)]
+pub struct VectorReader<'a> {
+    buf: &'a [u8],
+    /// The location of the first element.
+    loc: usize,
+    byte_width: usize,
+    len: usize,
+    /// The type of the elements of a typed vector. The elements of other
+    /// vectors are followed by their types.
+    element_type: Option<FlexBufferType>,
+}
+
+impl<'a> VectorReader<'a> {
+    fn new(
+        buf: &'a [u8],
+        loc: usize,
+        byte_width: usize,
+        element_type: Option<FlexBufferType>,
+        len: Option<usize>,
+    ) -> Result<Self> {
+        let len = match len {
+            Some(len) => len,
+            None => {
+                let len_loc = loc
+                    .checked_sub(byte_width)
+                    .ok_or(Error::InvalidOffset { loc })?;
+                read_usize(buf, len_loc, byte_width)?
+            }
+        };
+        // the elements, and the types of the elements of untyped vectors
+        let size = match element_type {
+            Some(_) => len.checked_mul(byte_width),
+            None => len.checked_mul(byte_width + 1),
+        };
+        let size = size.ok_or_else(|| Error::out_of_bounds(buf, loc, usize::MAX))?;
+        checked_slice(buf, loc, size)?;
+        Ok(Self {
+            buf,
+            loc,
+            byte_width,
+            len,
+            element_type,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Read the element at index `idx`.
+    pub fn idx(&self, idx: usize) -> Result<Reader<'a>> {
+        if idx >= self.len {
+            let loc = self.loc.saturating_add(idx.saturating_mul(self.byte_width));
+            return Err(Error::out_of_bounds(self.buf, loc, self.byte_width));
+        }
+        let loc = self.loc + idx * self.byte_width;
+        match self.element_type {
+            Some(ty) => Ok(Reader {
+                buf: self.buf,
+                loc,
+                parent_width: self.byte_width,
+                byte_width: self.byte_width,
+                ty,
+            }),
+            None => {
+                let packed_type = self.buf[self.loc + self.len * self.byte_width + idx];
+                Reader::new(self.buf, loc, self.byte_width, packed_type)
+            }
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Result<Reader<'a>>> + 'a {
+        let vector = *self;
+        (0..self.len).map(move |idx| vector.idx(idx))
+    }
+}
+
+/// A map in a FlexBuffer, whose keys are sorted.
+#[derive(Debug, Clone, Copy)]
+pub struct MapReader<'a> {
+    keys: VectorReader<'a>,
+    values: VectorReader<'a>,
+}
+
+impl<'a> MapReader<'a> {
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    fn key_loc(&self, idx: usize) -> Result<usize> {
+        follow_offset(self.keys.buf, self.keys.idx(idx)?.loc, self.keys.byte_width)
+    }
+
+    fn key_bytes(&self, idx: usize) -> Result<&'a [u8]> {
+        read_key(self.keys.buf, self.key_loc(idx)?)
+    }
+
+    /// Read the key at index `idx`.
+    pub fn key(&self, idx: usize) -> Result<&'a str> {
+        let loc = self.key_loc(idx)?;
+        str::from_utf8(read_key(self.keys.buf, loc)?).map_err(|_| Error::InvalidUtf8 { loc })
+    }
+
+    /// Read the value at index `idx`.
+    pub fn idx(&self, idx: usize) -> Result<Reader<'a>> {
+        self.values.idx(idx)
+    }
+
+    /// Look up the value of `key`, using a binary search.
+    pub fn get(&self, key: &str) -> Result<Option<Reader<'a>>> {
+        let (mut lo, mut hi) = (0, self.len());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            match self.key_bytes(mid)?.cmp(key.as_bytes()) {
+                Ordering::Less => lo = mid + 1,
+                Ordering::Greater => hi = mid,
+                Ordering::Equal => return self.values.idx(mid).map(Some),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Iterate over the keys and values of the map, in the order of the keys.
+    pub fn iter(&self) -> impl Iterator<Item = Result<(&'a str, Reader<'a>)>> + 'a {
+        let map = *self;
+        (0..self.len()).map(move |idx| Ok((map.key(idx)?, map.idx(idx)?)))
+    }
+}
+
+/// A value on the stack of a [`Builder`].
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Copy)]
+struct Value {
+    /// The bits of an inline value, or the location of the data of others.
+    data: u64,
+    ty: FlexBufferType,
+    /// The width an inline value needs, or the width of the data of others.
+    width: BitWidth,
+}
+
+#[cfg(feature = "alloc")]
+impl Value {
+    /// The width needed to store the value as the element at index `idx` of
+    /// a vector written at the end of `buf`.
+    fn element_width(&self, buf: &[u8], idx: usize) -> BitWidth {
+        if self.ty.is_inline() {
+            return self.width;
+        }
+        for &width in &[BitWidth::W8, BitWidth::W16, BitWidth::W32] {
+            let byte_width = width.byte_width();
+            let padded = buf.len().next_multiple_of(byte_width);
+            let off = (padded + idx * byte_width) as u64 - self.data;
+            if BitWidth::of_u64(off) <= width {
+                return width;
+            }
+        }
+        BitWidth::W64
+    }
+
+    /// The type of the value, packed with the width it is stored with.
+    fn packed_type(&self, parent_width: BitWidth) -> u8 {
+        let width = if self.ty.is_inline() {
+            self.width.max(parent_width)
+        } else {
+            self.width
+        };
+        (self.ty as u8) << 2 | width as u8
+    }
+}
+
+/// A builder of FlexBuffers.
+///
+/// Values are pushed onto a stack. Vectors and maps take the values pushed
+/// since they were started, where the values of maps are each preceded by
+/// their key. Finishing the buffer writes the single value left on the stack
+/// as its root.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Default)]
+pub struct Builder {
+    buf: Vec<u8>,
+    stack: Vec<Value>,
+    finished: bool,
+}
+
+#[cfg(feature = "alloc")]
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reset the builder, keeping its allocations, so that it can build
+    /// another buffer.
+    pub fn reset(&mut self) {
+        self.buf.clear();
+        self.stack.clear();
+        self.finished = false;
+    }
+
+    pub fn push_null(&mut self) {
+        self.push_value(0, FlexBufferType::Null, BitWidth::W8);
+    }
+
+    pub fn push_bool(&mut self, value: bool) {
+        self.push_value(value as u64, FlexBufferType::Bool, BitWidth::W8);
+    }
+
+    pub fn push_int(&mut self, value: i64) {
+        self.push_value(value as u64, FlexBufferType::Int, BitWidth::of_i64(value));
+    }
+
+    pub fn push_uint(&mut self, value: u64) {
+        self.push_value(value, FlexBufferType::UInt, BitWidth::of_u64(value));
+    }
+
+    /// Push a float, which is stored as an `f32` if that doesn't lose
+    /// precision.
+    pub fn push_float(&mut self, value: f64) {
+        self.push_value(
+            value.to_bits(),
+            FlexBufferType::Float,
+            BitWidth::of_f64(value),
+        );
+    }
+
+    pub fn push_str(&mut self, value: &str) {
+        self.push_blob_of_type(value.as_bytes(), FlexBufferType::String, 1);
+    }
+
+    pub fn push_blob(&mut self, value: &[u8]) {
+        self.push_blob_of_type(value, FlexBufferType::Blob, 0);
+    }
+
+    /// Push the key of the next value of a map.
+    pub fn key(&mut self, key: &str) {
+        assert!(
+            !key.as_bytes().contains(&0),
+            "keys of FlexBuffer maps cannot contain NUL"
+        );
+        self.assert_not_finished();
+        let loc = self.buf.len() as u64;
+        self.buf.extend_from_slice(key.as_bytes());
+        self.buf.push(0);
+        self.stack.push(Value {
+            data: loc,
+            ty: FlexBufferType::Key,
+            width: BitWidth::W8,
+        });
+    }
+
+    /// Start a vector, returning the start to pass to [`end_vector`].
+    ///
+    /// [`end_vector`]: Builder::end_vector
+    pub fn start_vector(&mut self) -> usize {
+        self.assert_not_finished();
+        self.stack.len()
+    }
+
+    /// End the vector of the values pushed since `start`.
+    pub fn end_vector(&mut self, start: usize) {
+        let len = self.stack.len() - start;
+        let vector = self.create_vector(start, len, 1, false, None);
+        self.stack.truncate(start);
+        self.stack.push(vector);
+    }
+
+    /// End the vector of the values pushed since `start`, as a typed vector,
+    /// which doesn't store the type of each element.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the values aren't all integers, all unsigned integers, all
+    /// floats or all booleans.
+    pub fn end_typed_vector(&mut self, start: usize) {
+        let len = self.stack.len() - start;
+        let vector = self.create_vector(start, len, 1, true, None);
+        self.stack.truncate(start);
+        self.stack.push(vector);
+    }
+
+    /// Start a map, returning the start to pass to [`end_map`].
+    ///
+    /// [`end_map`]: Builder::end_map
+    pub fn start_map(&mut self) -> usize {
+        self.assert_not_finished();
+        self.stack.len()
+    }
+
+    /// End the map of the keys and values pushed since `start`.
+    pub fn end_map(&mut self, start: usize) {
+        let len = self.stack.len() - start;
+        assert!(
+            len.is_multiple_of(2),
+            "the values of a map must each have a key"
+        );
+        let mut pairs: Vec<(Value, Value)> = self.stack[start..]
+            .chunks(2)
+            .map(|pair| {
+                assert!(
+                    pair[0].ty == FlexBufferType::Key,
+                    "map keys must be pushed with key"
+                );
+                (pair[0], pair[1])
+            })
+            .collect();
+        let buf = &self.buf;
+        let key = |value: &Value| read_key(buf, value.data as usize).unwrap();
+        pairs.sort_by(|a, b| key(&a.0).cmp(key(&b.0)));
+        for (idx, (key, value)) in pairs.into_iter().enumerate() {
+            self.stack[start + 2 * idx] = key;
+            self.stack[start + 2 * idx + 1] = value;
+        }
+
+        let keys = self.create_vector(start, len / 2, 2, true, None);
+        let map = self.create_vector(start + 1, len / 2, 2, false, Some(keys));
+        self.stack.truncate(start);
+        self.stack.push(map);
+    }
+
+    /// Finish the buffer, whose root is the single value on the stack.
+    pub fn finish(&mut self) {
+        self.assert_not_finished();
+        assert!(
+            self.stack.len() == 1,
+            "a FlexBuffer must have exactly one root value"
+        );
+        let root = self.stack[0];
+        let width = root.element_width(&self.buf, 0);
+        let byte_width = self.align(width);
+        self.write_value(root, byte_width);
+        self.buf.push(root.packed_type(width));
+        self.buf.push(byte_width as u8);
+        self.finished = true;
+    }
+
+    pub fn finished_data(&self) -> &[u8] {
+        assert!(self.finished, "the FlexBuffer is not finished");
+        &self.buf
+    }
+
+    /// Take the finished buffer.
+    pub fn into_vec(self) -> Vec<u8> {
+        assert!(self.finished, "the FlexBuffer is not finished");
+        self.buf
+    }
+
+    fn push_value(&mut self, data: u64, ty: FlexBufferType, width: BitWidth) {
+        self.assert_not_finished();
+        self.stack.push(Value { data, ty, width });
+    }
+
+    fn push_blob_of_type(&mut self, bytes: &[u8], ty: FlexBufferType, trailing_zeros: usize) {
+        self.assert_not_finished();
+        let width = BitWidth::of_u64(bytes.len() as u64);
+        let byte_width = self.align(width);
+        self.write_u64(bytes.len() as u64, byte_width);
+        let loc = self.buf.len() as u64;
+        self.buf.extend_from_slice(bytes);
+        self.buf.resize(self.buf.len() + trailing_zeros, 0);
+        self.stack.push(Value {
+            data: loc,
+            ty,
+            width,
+        });
+    }
+
+    /// Write the vector of the `len` values on the stack from `start` on,
+    /// every `step`th. The keys of maps are written before their length.
+    fn create_vector(
+        &mut self,
+        start: usize,
+        len: usize,
+        step: usize,
+        typed: bool,
+        keys: Option<Value>,
+    ) -> Value {
+        let mut width = BitWidth::of_u64(len as u64);
+        let mut prefix_len = 1;
+        if let Some(keys) = keys {
+            width = width.max(keys.element_width(&self.buf, 0));
+            prefix_len += 2;
+        }
+        let mut element_type = None;
+        for idx in 0..len {
+            let value = self.stack[start + idx * step];
+            width = width.max(value.element_width(&self.buf, idx + prefix_len));
+            if typed {
+                match element_type {
+                    None => element_type = Some(value.ty),
+                    Some(ty) => assert!(
+                        ty == value.ty,
+                        "the elements of a typed vector must have the same type"
+                    ),
+                }
+            }
+        }
+
+        let byte_width = self.align(width);
+        if let Some(keys) = keys {
+            self.write_offset(keys.data, byte_width);
+            self.write_u64(keys.width.byte_width() as u64, byte_width);
+        }
+        self.write_u64(len as u64, byte_width);
+        let loc = self.buf.len() as u64;
+        for idx in 0..len {
+            let value = self.stack[start + idx * step];
+            self.write_value(value, byte_width);
+        }
+        if !typed {
+            for idx in 0..len {
+                let value = self.stack[start + idx * step];
+                self.buf.push(value.packed_type(width));
+            }
+        }
+        let ty = if keys.is_some() {
+            FlexBufferType::Map
+        } else if typed {
+            // an empty typed vector has no elements to take a type from
+            let element_type = element_type.unwrap_or(FlexBufferType::Int);
+            element_type
+                .to_typed_vector()
+                .expect("typed vectors can only hold integers, floats, booleans or keys")
+        } else {
+            FlexBufferType::Vector
+        };
+        Value {
+            data: loc,
+            ty,
+            width,
+        }
+    }
+
+    fn write_value(&mut self, value: Value, byte_width: usize) {
+        match value.ty {
+            FlexBufferType::Float if byte_width == 4 => {
+                let value = f64::from_bits(value.data) as f32;
+                self.write_u64(u64::from(value.to_bits()), byte_width);
+            }
+            ty if ty.is_inline() => self.write_u64(value.data, byte_width),
+            _ => self.write_offset(value.data, byte_width),
+        }
+    }
+
+    fn write_offset(&mut self, loc: u64, byte_width: usize) {
+        let off = self.buf.len() as u64 - loc;
+        self.write_u64(off, byte_width);
+    }
+
+    /// Write the low `byte_width` bytes of `value`. Negative integers are
+    /// truncated, which keeps their sign when they are read back.
+    fn write_u64(&mut self, value: u64, byte_width: usize) {
+        self.buf
+            .extend_from_slice(&value.to_le_bytes()[..byte_width]);
+    }
+
+    /// Pad the buffer to a multiple of the size of `width`, returning that
+    /// size.
+    fn align(&mut self, width: BitWidth) -> usize {
+        let byte_width = width.byte_width();
+        let padded = self.buf.len().next_multiple_of(byte_width);
+        self.buf.resize(padded, 0);
+        byte_width
+    }
+
+    fn assert_not_finished(&self) {
+        assert!(!self.finished, "the FlexBuffer is already finished");
+    }
+}
@@ -14,7 +14,9 @@
  * limitations under the License.
  */
 
-use std::marker::PhantomData;
+use core::marker::PhantomData;
+
+use crate::error::{Error, Result};
 
 /// Follow is a trait that allows us to access FlatBuffers in a declarative,
 /// type safe, and fast way. They compile down to almost no code (after
@@ -27,18 +29,30 @@ use std::marker::PhantomData;
 /// Writing a new Follow implementation primarily involves deciding whether
 /// you want to return data (of the type Self::Inner) or do you want to
 /// continue traversing the FlatBuffer.
+///
+/// Following is fallible: the buffer may be truncated or contain offsets that
+/// point outside of it, in which case an `Error` is returned instead.
 pub trait Follow<'a> {
     type Inner;
-    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner;
+    fn follow(buf: &'a [u8], loc: usize) -> Result<Self::Inner>;
 }
 
 /// Execute a follow as a top-level function.
 #[allow(dead_code)]
 #[inline]
-pub fn lifted_follow<'a, T: Follow<'a>>(buf: &'a [u8], loc: usize) -> T::Inner {
+pub fn lifted_follow<'a, T: Follow<'a>>(buf: &'a [u8], loc: usize) -> Result<T::Inner> {
     T::follow(buf, loc)
 }
 
+/// Return the `len` bytes of `buf` starting at `loc`, or an error if they do
+/// not lie within `buf`.
+#[inline(always)]
+pub(crate) fn checked_slice(buf: &[u8], loc: usize, len: usize) -> Result<&[u8]> {
+    loc.checked_add(len)
+        .and_then(|end| buf.get(loc..end))
+        .ok_or_else(|| Error::out_of_bounds(buf, loc, len))
+}
+
 /// FollowStart wraps a Follow impl in a struct type. This can make certain
 /// programming patterns more ergonomic.
 #[derive(Debug, Default)]
@@ -46,17 +60,17 @@ pub struct FollowStart<T>(PhantomData<T>);
 impl<'a, T: Follow<'a> + 'a> FollowStart<T> {
     #[inline]
     pub fn new() -> Self {
-        Self { 0: PhantomData }
+        Self(PhantomData)
     }
     #[inline]
-    pub fn self_follow(&'a self, buf: &'a [u8], loc: usize) -> T::Inner {
+    pub fn self_follow(&'a self, buf: &'a [u8], loc: usize) -> Result<T::Inner> {
         T::follow(buf, loc)
     }
 }
 impl<'a, T: Follow<'a>> Follow<'a> for FollowStart<T> {
     type Inner = T::Inner;
     #[inline]
-    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    fn follow(buf: &'a [u8], loc: usize) -> Result<Self::Inner> {
         T::follow(buf, loc)
     }
 }
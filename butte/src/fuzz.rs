@@ -0,0 +1,75 @@
+//! Entry points for fuzzing the read path.
+//!
+//! Each function takes arbitrary bytes, as a fuzzer produces them, and only
+//! panics when it finds a bug: reading a buffer must never panic, however
+//! malformed it is, and a buffer that passes verification must not fail to be
+//! read because of its layout. They are generic over the root type, so a
+//! fuzz target only names the schema's root table and, for
+//! [`verify_and_read`], how to read it. With cargo-fuzz, e.g.:
+//!
+//! ```ignore
+//! fuzz_target!(|data: &[u8]| {
+//!     butte::fuzz::verify_and_read::<Monster, _>(data, |monster| {
+//!         monster.name()?;
+//!         monster.inventory()?;
+//!         Ok(())
+//!     })
+//! });
+//! ```
+
+use crate::{
+    error::{Error, Result},
+    follow::Follow,
+    table::{get_root, get_size_prefixed_root},
+    verifier::{verified_root, verified_size_prefixed_root, Verify},
+};
+
+/// Verify `data` as a buffer whose root is a `T`, with and without a size
+/// prefix.
+pub fn verify<'a, T: Follow<'a> + Verify + 'a>(data: &'a [u8]) {
+    let _ = verified_root::<T>(data);
+    let _ = verified_size_prefixed_root::<T>(data);
+}
+
+/// Read `data` as a buffer whose root is a `T` with `read`, both before and
+/// after verifying it.
+///
+/// Reading must only fail because of the values in the buffer, e.g. unknown
+/// enum values or invalid UTF-8, once the buffer has been verified. `read`
+/// should therefore only read what verification covers, i.e. not the
+/// contents of nested FlatBuffers or FlexBuffers.
+pub fn verify_and_read<'a, T, F>(data: &'a [u8], read: F)
+where
+    T: Follow<'a> + Verify + 'a,
+    F: Fn(T::Inner) -> Result<()>,
+{
+    if let Ok(root) = get_root::<T>(data) {
+        let _ = read(root);
+    }
+    if let Ok(root) = get_size_prefixed_root::<T>(data) {
+        let _ = read(root);
+    }
+    if let Ok(root) = verified_root::<T>(data) {
+        check_verified(read(root));
+    }
+    if let Ok(root) = verified_size_prefixed_root::<T>(data) {
+        check_verified(read(root));
+    }
+}
+
+/// Panic if reading a verified buffer failed because of its layout.
+fn check_verified(result: Result<()>) {
+    if let Err(
+        error @ (Error::OutOfBounds { .. }
+        | Error::InvalidOffset { .. }
+        | Error::InvalidVTableOffset { .. }
+        | Error::InvalidVTableSize { .. }
+        | Error::InvalidTableSize { .. }
+        | Error::InvalidFieldOffset { .. }
+        | Error::MissingRequiredField { .. }
+        | Error::UnionVectorMismatch),
+    ) = result
+    {
+        panic!("failed to read a verified buffer: {}", error);
+    }
+}
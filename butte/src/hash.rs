@@ -0,0 +1,112 @@
+//! The hash functions of the `hash` attribute, with which an integer field
+//! stores the hash of a string, e.g., `id: uint (hash: "fnv1a_32")`.
+//!
+//! Generated builders hash the strings given to their `add_<field>_hash`
+//! methods, and readers name the algorithm of a hashed field with a
+//! `<FIELD>_HASH` constant, so that the strings looked up can be hashed the
+//! same way.
+
+const FNV1_32_OFFSET_BASIS: u32 = 0x811c_9dc5;
+const FNV1_32_PRIME: u32 = 0x0100_0193;
+const FNV1_64_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV1_64_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// A hash function which can be named by the `hash` attribute.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HashAlgorithm {
+    /// 32-bit FNV-1, `fnv1_32`.
+    Fnv1_32,
+    /// 32-bit FNV-1a, `fnv1a_32`.
+    Fnv1a_32,
+    /// 64-bit FNV-1, `fnv1_64`.
+    Fnv1_64,
+    /// 64-bit FNV-1a, `fnv1a_64`.
+    Fnv1a_64,
+}
+
+impl HashAlgorithm {
+    /// The algorithm named `name` in a schema, e.g., `fnv1a_32`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "fnv1_32" => Some(Self::Fnv1_32),
+            "fnv1a_32" => Some(Self::Fnv1a_32),
+            "fnv1_64" => Some(Self::Fnv1_64),
+            "fnv1a_64" => Some(Self::Fnv1a_64),
+            _ => None,
+        }
+    }
+
+    /// The name of the algorithm in a schema.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Fnv1_32 => "fnv1_32",
+            Self::Fnv1a_32 => "fnv1a_32",
+            Self::Fnv1_64 => "fnv1_64",
+            Self::Fnv1a_64 => "fnv1a_64",
+        }
+    }
+
+    /// The size of the hashes in bits, which is that of the fields they are
+    /// stored in.
+    pub const fn bits(self) -> u32 {
+        match self {
+            Self::Fnv1_32 | Self::Fnv1a_32 => 32,
+            Self::Fnv1_64 | Self::Fnv1a_64 => 64,
+        }
+    }
+
+    /// Hash `bytes`, zero-extending 32-bit hashes.
+    pub const fn hash(self, bytes: &[u8]) -> u64 {
+        match self {
+            Self::Fnv1_32 => fnv1_32(bytes) as u64,
+            Self::Fnv1a_32 => fnv1a_32(bytes) as u64,
+            Self::Fnv1_64 => fnv1_64(bytes),
+            Self::Fnv1a_64 => fnv1a_64(bytes),
+        }
+    }
+}
+
+/// The 32-bit FNV-1 hash of `bytes`.
+pub const fn fnv1_32(bytes: &[u8]) -> u32 {
+    let mut hash = FNV1_32_OFFSET_BASIS;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash = hash.wrapping_mul(FNV1_32_PRIME) ^ bytes[i] as u32;
+        i += 1;
+    }
+    hash
+}
+
+/// The 32-bit FNV-1a hash of `bytes`.
+pub const fn fnv1a_32(bytes: &[u8]) -> u32 {
+    let mut hash = FNV1_32_OFFSET_BASIS;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash = (hash ^ bytes[i] as u32).wrapping_mul(FNV1_32_PRIME);
+        i += 1;
+    }
+    hash
+}
+
+/// The 64-bit FNV-1 hash of `bytes`.
+pub const fn fnv1_64(bytes: &[u8]) -> u64 {
+    let mut hash = FNV1_64_OFFSET_BASIS;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash = hash.wrapping_mul(FNV1_64_PRIME) ^ bytes[i] as u64;
+        i += 1;
+    }
+    hash
+}
+
+/// The 64-bit FNV-1a hash of `bytes`.
+pub const fn fnv1a_64(bytes: &[u8]) -> u64 {
+    let mut hash = FNV1_64_OFFSET_BASIS;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash = (hash ^ bytes[i] as u64).wrapping_mul(FNV1_64_PRIME);
+        i += 1;
+    }
+    hash
+}
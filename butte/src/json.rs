@@ -0,0 +1,1501 @@
+//! Conversion between binary buffers and the FlatBuffers JSON text format.
+//!
+//! The conversion is driven by a [`Schema`] describing the types in the
+//! buffer at runtime. `butte_build::json_schema` builds one from a schema
+//! file, and [`reflection::Schema::to_json_schema`] from a binary schema.
+//! Buffers are printed like `flatc --json --strict-json` prints them, and
+//! JSON is parsed like flatc parses it, so that JSON written for flatc, such
+//! as its test vectors, can be read unchanged:
+//!
+//! - field names may be unquoted, and strings may be in single quotes;
+//! - comments and trailing commas are allowed;
+//! - integers may be hexadecimal, like `0x1F`, and so may floating point
+//!   numbers, like `0x1.8p1`;
+//! - enum values may be given by number or by name, which may be qualified by
+//!   the enum, like `Color.Red`, and the names of several values separated by
+//!   spaces, like `"Red Blue"`, stand for the values ORed together, as for the
+//!   fields of `bit_flags` enums;
+//! - scalars may be given as strings, like `"42"`;
+//! - the discriminant of a union, or the discriminants of a vector of unions,
+//!   are given by a companion `<name>_type` member, before or after the
+//!   union.
+//!
+//! Fields that are left out or `null` read as their defaults.
+//!
+//! [`reflection::Schema::to_json_schema`]: crate::reflection::Schema::to_json_schema
+
+use alloc::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{cmp::Reverse, convert::TryFrom, fmt};
+
+use crate::{
+    builder::FlatBufferBuilder,
+    endian_scalar::{read_scalar_at, EndianScalar},
+    follow::checked_slice,
+    primitives::*,
+    table::Table,
+    vector::{length64_prefixed_bytes, length_prefixed_bytes},
+    verifier::VerifierOptions,
+    vtable::field_index_to_field_offset,
+};
+
+/// The types of a schema, as needed to convert buffers to and from JSON.
+///
+/// Types refer to each other by their index in the lists of the schema.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Schema {
+    pub tables: Vec<TableDef>,
+    pub structs: Vec<StructDef>,
+    /// Enums, and the discriminants of unions.
+    pub enums: Vec<EnumDef>,
+    /// The index of the root table, if the schema declares one.
+    pub root_table: Option<usize>,
+    pub file_identifier: Option<String>,
+}
+
+/// A table type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableDef {
+    /// The fully qualified name of the table, e.g. `MyGame.Monster`.
+    pub name: String,
+    pub fields: Vec<FieldDef>,
+}
+
+/// A field of a table.
+///
+/// A union field is made up of two fields: one named `<name>_type` holding
+/// its discriminant, followed by the field holding its value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDef {
+    pub name: String,
+    pub ty: Type,
+    /// The index of the field's slot in the vtable.
+    pub id: VOffsetT,
+    /// The default value of boolean, integer and enum fields.
+    pub default_integer: i64,
+    /// The default value of floating point fields.
+    pub default_real: f64,
+    /// Whether the scalar field has no default value and is written whenever
+    /// it is set.
+    pub optional: bool,
+    pub required: bool,
+    pub deprecated: bool,
+    /// Whether the string or vector field is referred to by a 64-bit offset,
+    /// i.e., it has the `offset64` or `vector64` attribute.
+    pub offset64: bool,
+}
+
+/// A struct type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructDef {
+    /// The fully qualified name of the struct.
+    pub name: String,
+    pub fields: Vec<StructFieldDef>,
+    pub size: usize,
+    pub align: usize,
+}
+
+/// A field of a struct.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructFieldDef {
+    pub name: String,
+    pub ty: Type,
+    /// The offset of the field from the start of the struct in bytes.
+    pub offset: usize,
+}
+
+/// An enum type, or the discriminant of a union.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumDef {
+    /// The fully qualified name of the enum or union.
+    pub name: String,
+    /// The integer type the enum is stored as.
+    pub underlying_type: Type,
+    pub values: Vec<EnumValDef>,
+}
+
+/// A value of an enum type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumValDef {
+    pub name: String,
+    pub value: i64,
+    /// For the discriminant of a union, the index of the table stored in the
+    /// union when it has this value.
+    pub union_table: Option<usize>,
+}
+
+/// The type of a field.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Bool,
+    Byte,
+    UByte,
+    Short,
+    UShort,
+    Int,
+    UInt,
+    Long,
+    ULong,
+    Float,
+    Double,
+    String,
+    Vector(Box<Type>),
+    /// A vector with a 64-bit length.
+    Vector64(Box<Type>),
+    /// A fixed-size array of the given length, stored inline in a struct.
+    Array(Box<Type>, usize),
+    /// An index into [`Schema::enums`].
+    Enum(usize),
+    /// An index into [`Schema::structs`].
+    Struct(usize),
+    /// An index into [`Schema::tables`].
+    Table(usize),
+    /// The value of a union, whose discriminant is an index into
+    /// [`Schema::enums`].
+    Union(usize),
+}
+
+impl Type {
+    /// Check whether values of the type are stored behind an offset.
+    fn is_offset(&self) -> bool {
+        matches!(
+            self,
+            Type::String | Type::Vector(_) | Type::Vector64(_) | Type::Table(_) | Type::Union(_)
+        )
+    }
+}
+
+impl TableDef {
+    /// The field holding the discriminant of the union, or the discriminants
+    /// of the vector of unions, `field`.
+    fn type_field(&self, field: &FieldDef) -> Result<&FieldDef, Error> {
+        self.fields
+            .iter()
+            .find(|type_field| type_field.id + 1 == field.id)
+            .ok_or_else(|| invalid(format!("the union `{}` has no type field", field.name)))
+    }
+}
+
+impl EnumDef {
+    /// The value called `name`, which may be qualified by the name of the
+    /// enum, like `Color.Red` or `MyGame.Color.Red`.
+    fn value_of(&self, name: &str) -> Option<i64> {
+        let short_name = self.name.rsplit('.').next().unwrap_or(&self.name);
+        let name = [self.name.as_str(), short_name]
+            .iter()
+            .find_map(|prefix| name.strip_prefix(prefix)?.strip_prefix('.'))
+            .unwrap_or(name);
+        self.values
+            .iter()
+            .find(|enum_val| enum_val.name == name)
+            .map(|enum_val| enum_val.value)
+    }
+
+    /// The table stored in a union with the discriminant `value`, if this is
+    /// the discriminant of a union.
+    fn union_table(&self, value: i128) -> Option<usize> {
+        self.values
+            .iter()
+            .find(|enum_val| i128::from(enum_val.value) == value)
+            .and_then(|enum_val| enum_val.union_table)
+    }
+}
+
+impl Schema {
+    /// The value called `name`, qualified by the name of its enum, like
+    /// `Color.Red`, which flatc accepts for integer fields.
+    fn qualified_enum_value(&self, name: &str) -> Option<i64> {
+        let (enum_name, _) = name.rsplit_once('.')?;
+        self.enums
+            .iter()
+            .filter(|def| def.name == enum_name || def.name.rsplit('.').next() == Some(enum_name))
+            .find_map(|def| def.value_of(name))
+    }
+
+    /// The size and alignment of a value of type `ty` stored inline in a
+    /// table, struct or vector.
+    fn inline_size(&self, ty: &Type) -> (usize, usize) {
+        match ty {
+            Type::Bool | Type::Byte | Type::UByte => (1, 1),
+            Type::Short | Type::UShort => (2, 2),
+            Type::Int | Type::UInt | Type::Float => (4, 4),
+            Type::Long | Type::ULong | Type::Double => (8, 8),
+            Type::Enum(index) => self.inline_size(&self.enums[*index].underlying_type),
+            Type::Struct(index) => (self.structs[*index].size, self.structs[*index].align),
+            Type::Array(element, len) => {
+                let (size, align) = self.inline_size(element);
+                (size * len, align)
+            }
+            Type::String | Type::Vector(_) | Type::Table(_) | Type::Union(_) => {
+                (SIZE_UOFFSET, SIZE_UOFFSET)
+            }
+            Type::Vector64(_) => (SIZE_UOFFSET64, SIZE_UOFFSET64),
+        }
+    }
+
+    fn root_table(&self) -> Result<usize, Error> {
+        self.root_table
+            .ok_or_else(|| Error::Invalid("the schema has no root table".to_string()))
+    }
+}
+
+/// Errors that can occur while converting between buffers and JSON.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// The buffer is malformed.
+    Buffer(crate::Error),
+    /// The JSON text is malformed.
+    Syntax {
+        /// The line of the error, starting at 1.
+        line: usize,
+        /// The column of the error in characters, starting at 1.
+        column: usize,
+        message: String,
+    },
+    /// The JSON or the buffer doesn't match the schema.
+    Invalid(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Buffer(error) => error.fmt(f),
+            Error::Syntax {
+                line,
+                column,
+                message,
+            } => write!(f, "{} at line {} column {}", message, line, column),
+            Error::Invalid(message) => f.write_str(message),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+impl From<crate::Error> for Error {
+    fn from(error: crate::Error) -> Self {
+        Error::Buffer(error)
+    }
+}
+
+impl Error {
+    /// Prefix the message of an `Invalid` error with the field it occurred in.
+    fn in_field(self, parent: &str, field: &str) -> Self {
+        match self {
+            Error::Invalid(message) => Error::Invalid(format!("{}.{}: {}", parent, field, message)),
+            error => error,
+        }
+    }
+}
+
+fn invalid(message: impl Into<String>) -> Error {
+    Error::Invalid(message.into())
+}
+
+/// Convert `buf`, a buffer whose root is the root table of `schema`, to
+/// JSON.
+///
+/// This is limited like verification with the default `VerifierOptions`, see
+/// [`to_json_with_options`], except that tables may be nested as deeply as
+/// the printer nests values, 128 levels.
+pub fn to_json(schema: &Schema, buf: &[u8]) -> Result<String, Error> {
+    let options = VerifierOptions {
+        max_depth: MAX_DEPTH,
+        ..VerifierOptions::default()
+    };
+    to_json_with_options(schema, buf, &options)
+}
+
+/// Convert `buf` to JSON like [`to_json`], within the limits of `options`.
+///
+/// A table or vector that several offsets refer to is printed once for each
+/// of them, so a small buffer can stand for a huge JSON text. Printing fails
+/// with `Error::TooManyTables` once it has printed more than `max_tables`
+/// tables, with `Error::ApparentSizeTooLarge` once the tables, strings and
+/// vectors it has printed add up to more than `max_apparent_size` bytes, and
+/// with `Error::TooManyLevels` if tables are nested more than `max_depth`
+/// levels deep. The `strict_*` options don't apply.
+pub fn to_json_with_options(
+    schema: &Schema,
+    buf: &[u8],
+    options: &VerifierOptions,
+) -> Result<String, Error> {
+    let root = schema.root_table()?;
+    let mut printer = Printer {
+        schema,
+        buf,
+        options,
+        depth: 0,
+        num_tables: 0,
+        apparent_size: 0,
+        max_apparent_size: options.max_apparent_size_of(buf),
+        out: String::new(),
+    };
+    let loc = printer.follow(0)?;
+    printer.table(root, loc, 0)?;
+    printer.out.push('\n');
+    Ok(printer.out)
+}
+
+/// Convert `json`, a value of the root table of `schema`, to a finished
+/// buffer.
+///
+/// Like flatc, scalar fields equal to their default value are left out of the
+/// buffer, and the buffer gets the file identifier of the schema.
+pub fn from_json(schema: &Schema, json: &str) -> Result<Vec<u8>, Error> {
+    let root = schema.root_table()?;
+    let file_identifier = schema
+        .file_identifier
+        .as_deref()
+        .map(|id| {
+            FileIdent::try_from(id).map_err(|_| {
+                Error::Invalid(format!("the file identifier {:?} isn't 4 bytes long", id))
+            })
+        })
+        .transpose()?;
+    let value = Parser {
+        text: json,
+        pos: 0,
+        depth: 0,
+    }
+    .parse()?;
+    let mut writer = Writer {
+        schema,
+        fbb: FlatBufferBuilder::new(),
+    };
+    let root = writer.table(root, &value)?;
+    writer.fbb.finish(
+        WIPOffset::<TableFinishedWIPOffset>::new(root),
+        file_identifier,
+    );
+    Ok(writer.fbb.finished_data().to_vec())
+}
+
+/// Where the value of a member of a table or struct is stored.
+enum Member {
+    /// The value is stored at the location, or referred to by the offset
+    /// stored there.
+    Inline(usize),
+    /// The string or vector is stored at the location, as the target of a
+    /// 64-bit offset.
+    Referenced(usize),
+    /// The vector of unions is referred to by the offset stored at the
+    /// location, and its elements hold the given tables, or none if their
+    /// discriminants are `NONE` or unknown.
+    Unions(usize, Vec<Option<usize>>),
+}
+
+/// How deeply the printer nests values, which keeps the tables of a
+/// malicious buffer from overflowing the stack. The fields of a table and the
+/// elements of a vector are a level deeper than it.
+const MAX_DEPTH: usize = 128;
+
+/// Writes the JSON text of a buffer.
+struct Printer<'a> {
+    schema: &'a Schema,
+    buf: &'a [u8],
+    options: &'a VerifierOptions,
+    /// The number of tables the printer is inside.
+    depth: usize,
+    num_tables: usize,
+    apparent_size: usize,
+    max_apparent_size: usize,
+    out: String,
+}
+
+impl<'a> Printer<'a> {
+    /// The location an offset stored at `loc` points to.
+    fn follow(&self, loc: usize) -> Result<usize, Error> {
+        let offset = read_scalar_at::<UOffsetT>(self.buf, loc)?;
+        Ok(loc
+            .checked_add(offset as usize)
+            .ok_or(crate::Error::InvalidOffset { loc })?)
+    }
+
+    /// The location a 64-bit offset stored at `loc` points to.
+    fn follow64(&self, loc: usize) -> Result<usize, Error> {
+        let offset = read_scalar_at::<UOffset64T>(self.buf, loc)?;
+        Ok(usize::try_from(offset)
+            .ok()
+            .and_then(|offset| loc.checked_add(offset))
+            .ok_or(crate::Error::InvalidOffset { loc })?)
+    }
+
+    /// Count `len` bytes that are printed towards the apparent size.
+    fn count(&mut self, len: usize) -> Result<(), Error> {
+        self.apparent_size = self.apparent_size.saturating_add(len);
+        if self.apparent_size > self.max_apparent_size {
+            return Err(crate::Error::ApparentSizeTooLarge.into());
+        }
+        Ok(())
+    }
+
+    fn newline(&mut self, depth: usize) {
+        self.out.push('\n');
+        for _ in 0..depth {
+            self.out.push_str("  ");
+        }
+    }
+
+    fn table(&mut self, index: usize, loc: usize, depth: usize) -> Result<(), Error> {
+        if depth >= MAX_DEPTH || self.depth >= self.options.max_depth {
+            return Err(crate::Error::TooManyLevels.into());
+        }
+        self.num_tables += 1;
+        if self.num_tables > self.options.max_tables {
+            return Err(crate::Error::TooManyTables.into());
+        }
+        let table = Table::new(self.buf, loc);
+        self.count(table.vtable()?.object_inline_num_bytes())?;
+        self.depth += 1;
+        let result = self.fields(index, &table, depth);
+        self.depth -= 1;
+        result
+    }
+
+    /// Write the fields of `table`, the table at `index` in the schema.
+    fn fields(&mut self, index: usize, table: &Table<'a>, depth: usize) -> Result<(), Error> {
+        let schema = self.schema;
+        let def = &schema.tables[index];
+        let mut members = vec![];
+        for field in &def.fields {
+            let size = if field.offset64 {
+                SIZE_UOFFSET64
+            } else {
+                schema.inline_size(&field.ty).0
+            };
+            let slot = field_index_to_field_offset(field.id);
+            let field_loc = match table.field_loc_sized(slot, size)? {
+                Some(field_loc) => field_loc,
+                None => continue,
+            };
+            let ty = match &field.ty {
+                Type::Union(union) => match self.union_table(*union, def, field, table)? {
+                    Some(table) => Type::Table(table),
+                    None => continue,
+                },
+                Type::Vector(element) => {
+                    if let Type::Union(union) = element.as_ref() {
+                        let tables = self.union_tables(*union, def, field, table)?;
+                        members.push((
+                            field.name.as_str(),
+                            field.ty.clone(),
+                            Member::Unions(field_loc, tables),
+                        ));
+                        continue;
+                    }
+                    field.ty.clone()
+                }
+                ty => ty.clone(),
+            };
+            let loc = if field.offset64 {
+                Member::Referenced(self.follow64(field_loc)?)
+            } else {
+                Member::Inline(field_loc)
+            };
+            members.push((field.name.as_str(), ty, loc));
+        }
+        self.members(members, depth)
+    }
+
+    /// The table stored in the union `field` of `table`, a `def`, or `None`
+    /// if the union is empty.
+    fn union_table(
+        &self,
+        union: usize,
+        def: &TableDef,
+        field: &FieldDef,
+        table: &Table,
+    ) -> Result<Option<usize>, Error> {
+        let type_field = def.type_field(field)?;
+        let slot = field_index_to_field_offset(type_field.id);
+        let type_loc = match table.field_loc_sized(slot, 1)? {
+            Some(type_loc) => type_loc,
+            None => return Ok(None),
+        };
+        // The field lies within the table's inline data, which lies within
+        // the buffer.
+        let discriminant = self.buf[type_loc];
+        Ok(self.schema.enums[union].union_table(i128::from(discriminant)))
+    }
+
+    /// The tables stored in the elements of the vector of unions `field` of
+    /// `table`, a `def`.
+    fn union_tables(
+        &self,
+        union: usize,
+        def: &TableDef,
+        field: &FieldDef,
+        table: &Table,
+    ) -> Result<Vec<Option<usize>>, Error> {
+        let type_field = def.type_field(field)?;
+        let slot = field_index_to_field_offset(type_field.id);
+        let types_loc = table
+            .field_loc_sized(slot, SIZE_UOFFSET)?
+            .ok_or(crate::Error::UnionVectorMismatch)?;
+        let types_loc = self.follow(types_loc)?;
+        let types = length_prefixed_bytes(self.buf, types_loc, 1)?;
+        Ok(types
+            .iter()
+            .map(|discriminant| self.schema.enums[union].union_table(i128::from(*discriminant)))
+            .collect())
+    }
+
+    fn struct_(&mut self, index: usize, loc: usize, depth: usize) -> Result<(), Error> {
+        let schema = self.schema;
+        let def = &schema.structs[index];
+        checked_slice(self.buf, loc, def.size)?;
+        let members = def
+            .fields
+            .iter()
+            .map(|field| {
+                let loc = Member::Inline(loc + field.offset);
+                (field.name.as_str(), field.ty.clone(), loc)
+            })
+            .collect();
+        self.members(members, depth)
+    }
+
+    /// Write an object with the values of type `ty` at `loc` as members.
+    fn members(&mut self, members: Vec<(&str, Type, Member)>, depth: usize) -> Result<(), Error> {
+        if members.is_empty() {
+            self.out.push_str("{}");
+            return Ok(());
+        }
+        self.out.push('{');
+        for (i, (name, ty, loc)) in members.into_iter().enumerate() {
+            if i > 0 {
+                self.out.push(',');
+            }
+            self.newline(depth + 1);
+            write_string(&mut self.out, name);
+            self.out.push_str(": ");
+            match loc {
+                Member::Inline(loc) => self.value(&ty, loc, depth + 1)?,
+                Member::Referenced(loc) => self.referenced(&ty, loc, depth + 1)?,
+                Member::Unions(loc, tables) => self.unions(loc, &tables, depth + 1)?,
+            }
+        }
+        self.newline(depth);
+        self.out.push('}');
+        Ok(())
+    }
+
+    /// Write the value of type `ty` stored inline at `loc`.
+    fn value(&mut self, ty: &Type, loc: usize, depth: usize) -> Result<(), Error> {
+        let schema = self.schema;
+        match ty {
+            Type::String | Type::Vector(_) | Type::Vector64(_) => {
+                let loc = self.follow(loc)?;
+                self.referenced(ty, loc, depth)?;
+            }
+            Type::Array(element, len) => {
+                let (size, _) = schema.inline_size(element);
+                checked_slice(self.buf, loc, size * len)?;
+                self.elements(element, loc, *len, depth)?;
+            }
+            Type::Table(index) => {
+                let loc = self.follow(loc)?;
+                self.table(*index, loc, depth)?;
+            }
+            Type::Struct(index) => self.struct_(*index, loc, depth)?,
+            Type::Union(_) => return Err(invalid("vectors of unions are not supported")),
+            Type::Enum(index) => {
+                let def = &schema.enums[*index];
+                let value = self.integer(&def.underlying_type, loc)?;
+                match def
+                    .values
+                    .iter()
+                    .find(|enum_val| i128::from(enum_val.value) == value)
+                {
+                    Some(enum_val) => write_string(&mut self.out, &enum_val.name),
+                    None => self.out.push_str(&value.to_string()),
+                }
+            }
+            Type::Bool => {
+                let value = self.integer(ty, loc)? != 0;
+                self.out.push_str(&value.to_string());
+            }
+            Type::Float => {
+                let bytes = checked_slice(self.buf, loc, 4)?;
+                self.out.push_str(&float_text(f32::read_le(bytes)));
+            }
+            Type::Double => {
+                let bytes = checked_slice(self.buf, loc, 8)?;
+                self.out.push_str(&float_text(f64::read_le(bytes)));
+            }
+            _ => {
+                let value = self.integer(ty, loc)?;
+                self.out.push_str(&value.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the string or vector of type `ty` that an offset points to at
+    /// `loc`.
+    fn referenced(&mut self, ty: &Type, loc: usize, depth: usize) -> Result<(), Error> {
+        match ty {
+            Type::String => {
+                let bytes = length_prefixed_bytes(self.buf, loc, 1)?;
+                self.count(SIZE_UOFFSET + bytes.len())?;
+                write_string(&mut self.out, &String::from_utf8_lossy(bytes));
+            }
+            Type::Vector(element) => {
+                let (size, _) = self.schema.inline_size(element);
+                let bytes = length_prefixed_bytes(self.buf, loc, size)?.len();
+                self.count(SIZE_UOFFSET + bytes)?;
+                self.elements(element, loc + SIZE_UOFFSET, bytes / size, depth)?;
+            }
+            Type::Vector64(element) => {
+                let (size, _) = self.schema.inline_size(element);
+                let bytes = length64_prefixed_bytes(self.buf, loc, size)?.len();
+                self.count(SIZE_UOFFSET64 + bytes)?;
+                self.elements(element, loc + SIZE_UOFFSET64, bytes / size, depth)?;
+            }
+            Type::Table(index) => self.table(*index, loc, depth)?,
+            _ => {
+                return Err(invalid(
+                    "only strings, vectors and tables are stored behind offsets",
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the vector of unions that an offset points to at `loc`, whose
+    /// elements hold `tables`. Elements without a table are written as
+    /// `null`.
+    fn unions(&mut self, loc: usize, tables: &[Option<usize>], depth: usize) -> Result<(), Error> {
+        let loc = self.follow(loc)?;
+        let bytes = length_prefixed_bytes(self.buf, loc, SIZE_UOFFSET)?.len();
+        self.count(SIZE_UOFFSET + bytes)?;
+        let len = bytes / SIZE_UOFFSET;
+        if len != tables.len() {
+            return Err(crate::Error::UnionVectorMismatch.into());
+        }
+        if len == 0 {
+            self.out.push_str("[]");
+            return Ok(());
+        }
+        self.out.push('[');
+        for (i, table) in tables.iter().enumerate() {
+            if i > 0 {
+                self.out.push(',');
+            }
+            self.newline(depth + 1);
+            match table {
+                Some(table) => {
+                    let loc = self.follow(loc + SIZE_UOFFSET * (i + 1))?;
+                    self.table(*table, loc, depth + 1)?;
+                }
+                None => self.out.push_str("null"),
+            }
+        }
+        self.newline(depth);
+        self.out.push(']');
+        Ok(())
+    }
+
+    /// Write a list of the `len` values of type `element` stored one after
+    /// another from `loc`.
+    fn elements(
+        &mut self,
+        element: &Type,
+        loc: usize,
+        len: usize,
+        depth: usize,
+    ) -> Result<(), Error> {
+        if len == 0 {
+            self.out.push_str("[]");
+            return Ok(());
+        }
+        let (size, _) = self.schema.inline_size(element);
+        self.out.push('[');
+        for i in 0..len {
+            if i > 0 {
+                self.out.push(',');
+            }
+            self.newline(depth + 1);
+            self.value(element, loc + i * size, depth + 1)?;
+        }
+        self.newline(depth);
+        self.out.push(']');
+        Ok(())
+    }
+
+    /// Read the boolean or integer of type `ty` at `loc`.
+    fn integer(&self, ty: &Type, loc: usize) -> Result<i128, Error> {
+        let (size, _) = self.schema.inline_size(ty);
+        let bytes = checked_slice(self.buf, loc, size)?;
+        Ok(match ty {
+            Type::Bool | Type::UByte => i128::from(u8::read_le(bytes)),
+            Type::Byte => i128::from(i8::read_le(bytes)),
+            Type::Short => i128::from(i16::read_le(bytes)),
+            Type::UShort => i128::from(u16::read_le(bytes)),
+            Type::Int => i128::from(i32::read_le(bytes)),
+            Type::UInt => i128::from(u32::read_le(bytes)),
+            Type::Long => i128::from(i64::read_le(bytes)),
+            Type::ULong => i128::from(u64::read_le(bytes)),
+            _ => return Err(invalid("enums must have an integer type")),
+        })
+    }
+}
+
+/// Format a floating point number like flatc does.
+fn float_text<T: fmt::Debug + Into<f64> + Copy>(value: T) -> String {
+    let wide = value.into();
+    if wide.is_nan() {
+        "nan".to_string()
+    } else if wide.is_infinite() {
+        if wide > 0.0 { "inf" } else { "-inf" }.to_string()
+    } else {
+        format!("{:?}", value)
+    }
+}
+
+/// Write `value` as a JSON string literal.
+fn write_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// A parsed JSON value.
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Null,
+    Bool(bool),
+    /// The text of a number, which is only converted once its type is known.
+    Number(String),
+    String(String),
+    /// An unquoted identifier, such as the name of an enum value.
+    Ident(String),
+    Array(Vec<Value>),
+    /// The members of an object, in the order they were given.
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    fn kind(&self) -> &'static str {
+        match self {
+            Value::Null => "null",
+            Value::Bool(_) => "a boolean",
+            Value::Number(_) => "a number",
+            Value::String(_) => "a string",
+            Value::Ident(_) => "an identifier",
+            Value::Array(_) => "an array",
+            Value::Object(_) => "an object",
+        }
+    }
+
+    /// An error saying that `expected` was expected instead of this value.
+    fn expected(&self, expected: &str) -> Error {
+        invalid(format!("expected {}, found {}", expected, self.kind()))
+    }
+
+    fn members(&self, expected: &str) -> Result<&[(String, Value)], Error> {
+        match self {
+            Value::Object(members) => Ok(members),
+            value => Err(value.expected(expected)),
+        }
+    }
+}
+
+/// Find the member called `name` of an object.
+fn member<'v>(members: &'v [(String, Value)], name: &str) -> Option<&'v Value> {
+    members
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value)
+}
+
+/// How deeply the parser nests objects and arrays, which keeps malicious
+/// text from overflowing the stack.
+const MAX_PARSE_DEPTH: usize = 512;
+
+/// A recursive descent parser of JSON text, with flatc's extensions.
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+    /// The number of objects and arrays the parser is inside.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn parse(mut self) -> Result<Value, Error> {
+        let value = self.value()?;
+        self.skip_whitespace()?;
+        if self.pos < self.text.len() {
+            return Err(self.error("expected the end of the input"));
+        }
+        Ok(value)
+    }
+
+    fn error(&self, message: &str) -> Error {
+        let before = &self.text[..self.pos];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Error::Syntax {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            message: message.to_string(),
+        }
+    }
+
+    fn rest(&self) -> &str {
+        &self.text[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), Error> {
+        self.skip_whitespace()?;
+        if self.peek() == Some(expected) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{}`", expected)))
+        }
+    }
+
+    /// Skip whitespace and comments.
+    fn skip_whitespace(&mut self) -> Result<(), Error> {
+        loop {
+            let rest = self.rest();
+            self.pos += rest.len() - rest.trim_start().len();
+            let rest = self.rest();
+            if rest.starts_with("//") {
+                self.pos += rest.find('\n').unwrap_or(rest.len());
+            } else if rest.starts_with("/*") {
+                match rest.find("*/") {
+                    Some(end) => self.pos += end + 2,
+                    None => return Err(self.error("unterminated comment")),
+                }
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, Error> {
+        self.skip_whitespace()?;
+        match self.peek() {
+            Some('{' | '[') => {
+                if self.depth >= MAX_PARSE_DEPTH {
+                    return Err(self.error("too deeply nested"));
+                }
+                self.depth += 1;
+                let value = if self.peek() == Some('{') {
+                    self.object()
+                } else {
+                    self.array()
+                };
+                self.depth -= 1;
+                value
+            }
+            Some('"' | '\'') => Ok(Value::String(self.string()?)),
+            Some(c) if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' => {
+                Ok(Value::Number(self.number()))
+            }
+            Some(c) if c.is_alphabetic() || c == '_' => Ok(match self.ident() {
+                "null" => Value::Null,
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                ident => Value::Ident(ident.to_string()),
+            }),
+            Some(_) => Err(self.error("expected a value")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, Error> {
+        self.expect('{')?;
+        let mut members = vec![];
+        loop {
+            self.skip_whitespace()?;
+            if self.peek() == Some('}') {
+                break;
+            }
+            let key = match self.peek() {
+                Some('"' | '\'') => self.string()?,
+                Some(c) if c.is_alphabetic() || c == '_' => self.ident().to_string(),
+                _ => return Err(self.error("expected a field name")),
+            };
+            self.expect(':')?;
+            members.push((key, self.value()?));
+            self.skip_whitespace()?;
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some('}') => break,
+                _ => return Err(self.error("expected `,` or `}`")),
+            }
+        }
+        self.pos += 1;
+        Ok(Value::Object(members))
+    }
+
+    fn array(&mut self) -> Result<Value, Error> {
+        self.expect('[')?;
+        let mut values = vec![];
+        loop {
+            self.skip_whitespace()?;
+            if self.peek() == Some(']') {
+                break;
+            }
+            values.push(self.value()?);
+            self.skip_whitespace()?;
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(']') => break,
+                _ => return Err(self.error("expected `,` or `]`")),
+            }
+        }
+        self.pos += 1;
+        Ok(Value::Array(values))
+    }
+
+    fn ident(&mut self) -> &str {
+        let start = self.pos;
+        while let Some(c) = self
+            .peek()
+            .filter(|&c| c.is_alphanumeric() || c == '_' || c == '.')
+        {
+            self.pos += c.len_utf8();
+        }
+        &self.text[start..self.pos]
+    }
+
+    fn number(&mut self) -> String {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || "+-.".contains(c)) {
+            self.pos += 1;
+        }
+        self.text[start..self.pos].to_string()
+    }
+
+    /// Parse a string in double quotes or, like flatc allows, in single
+    /// quotes.
+    fn string(&mut self) -> Result<String, Error> {
+        let quote = self.next();
+        let mut value = String::new();
+        loop {
+            match self.next() {
+                c if c == quote => return Ok(value),
+                Some('\\') => {
+                    let c = match self.next() {
+                        Some('"') => '"',
+                        Some('\'') => '\'',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('x') => char::from(self.hex(2)? as u8),
+                        Some('u') => self.unicode_escape()?,
+                        _ => return Err(self.error("invalid escape sequence")),
+                    };
+                    value.push(c);
+                }
+                Some(c) => value.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    /// Parse the code point of a `\u` escape, which may be the first half of
+    /// a surrogate pair.
+    fn unicode_escape(&mut self) -> Result<char, Error> {
+        let high = self.hex(4)?;
+        let code_point = if (0xd800..0xdc00).contains(&high) {
+            if !self.rest().starts_with("\\u") {
+                return Err(self.error("unpaired surrogate"));
+            }
+            self.pos += 2;
+            let low = self.hex(4)?;
+            if !(0xdc00..0xe000).contains(&low) {
+                return Err(self.error("unpaired surrogate"));
+            }
+            0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+        } else {
+            high
+        };
+        char::from_u32(code_point).ok_or_else(|| self.error("invalid code point"))
+    }
+
+    fn hex(&mut self, digits: usize) -> Result<u32, Error> {
+        let text = self
+            .rest()
+            .get(..digits)
+            .filter(|text| text.chars().all(|c| c.is_ascii_hexdigit()))
+            .ok_or_else(|| self.error("invalid escape sequence"))?;
+        let value = u32::from_str_radix(text, 16).expect("hex digits");
+        self.pos += digits;
+        Ok(value)
+    }
+}
+
+/// Parse the text of an integer, which may be hexadecimal.
+fn parse_integer(text: &str) -> Option<i128> {
+    let (negative, digits) = match text.as_bytes().first()? {
+        b'-' => (true, &text[1..]),
+        b'+' => (false, &text[1..]),
+        _ => (false, text),
+    };
+    let value = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => i128::from_str_radix(hex, 16).ok()?,
+        None => digits.parse::<i128>().ok()?,
+    };
+    Some(if negative { -value } else { value })
+}
+
+/// Parse the text of a floating point number, which may be hexadecimal with
+/// a binary exponent, like `0x1.8p1`, as flatc allows.
+fn parse_float(text: &str) -> Option<f64> {
+    let (negative, digits) = match text.as_bytes().first()? {
+        b'-' => (true, &text[1..]),
+        b'+' => (false, &text[1..]),
+        _ => (false, text),
+    };
+    let hex = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => hex,
+        None => return text.parse().ok(),
+    };
+    let (mantissa, exponent) = match hex.find(['p', 'P']) {
+        Some(i) => (&hex[..i], hex[i + 1..].parse::<i32>().ok()?),
+        None => (hex, 0),
+    };
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return None;
+    }
+    let mut value = 0.0;
+    for c in whole.chars().chain(fraction.chars()) {
+        value = value * 16.0 + f64::from(c.to_digit(16)?);
+    }
+    // Scale by a power of two a step at a time, since `powi` needs `std`.
+    let exponent = exponent.saturating_sub(4 * fraction.len() as i32);
+    for _ in 0..exponent.unsigned_abs().min(2200) {
+        value = if exponent > 0 {
+            value * 2.0
+        } else {
+            value / 2.0
+        };
+    }
+    Some(if negative { -value } else { value })
+}
+
+/// The value flatc reads a string as where a scalar is expected, which is
+/// the value of its text without the quotes, like `"42"` for an integer or
+/// `"Red Blue"` for an enum.
+fn unquoted(text: &str) -> Value {
+    match text.trim() {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        text if text.starts_with(|c: char| c.is_ascii_digit() || "+-.".contains(c)) => {
+            Value::Number(text.to_string())
+        }
+        text => Value::Ident(text.to_string()),
+    }
+}
+
+/// The value of an integer or enum of type `ty` given by `names`, the names
+/// of one or more values separated by spaces, which are looked up with
+/// `value_of`. Like flatc, the values of several names are ORed together.
+fn enum_names(
+    ty: &Type,
+    names: &str,
+    value_of: impl Fn(&str) -> Option<i64>,
+) -> Result<i128, Error> {
+    let mut bits = 0_u64;
+    for name in names.split_whitespace() {
+        let value =
+            value_of(name).ok_or_else(|| invalid(format!("unknown enum value `{}`", name)))?;
+        bits |= value as u64;
+    }
+    Ok(match ty {
+        Type::Byte | Type::Short | Type::Int | Type::Long => i128::from(bits as i64),
+        _ => i128::from(bits),
+    })
+}
+
+/// The little-endian bytes of `value` as a boolean or integer of type `ty`,
+/// or `None` if it is out of range.
+fn integer_bytes(ty: &Type, value: i128) -> Option<Vec<u8>> {
+    Some(match ty {
+        Type::Bool => vec![u8::try_from(value).ok().filter(|value| *value <= 1)?],
+        Type::Byte => i8::try_from(value).ok()?.to_le_bytes().to_vec(),
+        Type::UByte => u8::try_from(value).ok()?.to_le_bytes().to_vec(),
+        Type::Short => i16::try_from(value).ok()?.to_le_bytes().to_vec(),
+        Type::UShort => u16::try_from(value).ok()?.to_le_bytes().to_vec(),
+        Type::Int => i32::try_from(value).ok()?.to_le_bytes().to_vec(),
+        Type::UInt => u32::try_from(value).ok()?.to_le_bytes().to_vec(),
+        Type::Long => i64::try_from(value).ok()?.to_le_bytes().to_vec(),
+        Type::ULong => u64::try_from(value).ok()?.to_le_bytes().to_vec(),
+        _ => return None,
+    })
+}
+
+/// The value of the enum `def` given by `value`, a number or the names of
+/// its values.
+fn enum_value(def: &EnumDef, value: &Value) -> Result<i128, Error> {
+    match value {
+        Value::String(text) => enum_value(def, &unquoted(text)),
+        Value::Ident(names) => enum_names(&def.underlying_type, names, |name| def.value_of(name))
+            .map_err(|_| invalid(format!("`{}` is not a value of `{}`", names, def.name))),
+        Value::Number(text) => {
+            parse_integer(text).ok_or_else(|| invalid(format!("invalid integer `{}`", text)))
+        }
+        value => Err(value.expected(&format!("a value of `{}`", def.name))),
+    }
+}
+
+/// A value to store in a slot of a table.
+enum Slot {
+    /// The bytes of a scalar or struct, and their alignment.
+    Inline(Vec<u8>, usize),
+    /// An offset to a string, vector or table.
+    Offset(UOffsetT),
+    /// A 64-bit offset to a string or vector.
+    Offset64(UOffset64T),
+}
+
+impl Slot {
+    fn size(&self) -> usize {
+        match self {
+            Slot::Inline(bytes, _) => bytes.len(),
+            Slot::Offset(_) => SIZE_UOFFSET,
+            Slot::Offset64(_) => SIZE_UOFFSET64,
+        }
+    }
+}
+
+/// Builds a buffer from parsed JSON.
+struct Writer<'a, 'fbb> {
+    schema: &'a Schema,
+    fbb: FlatBufferBuilder<'fbb>,
+}
+
+impl Writer<'_, '_> {
+    /// Write the table `index` and return its offset.
+    fn table(&mut self, index: usize, value: &Value) -> Result<UOffsetT, Error> {
+        let schema = self.schema;
+        let def = &schema.tables[index];
+        let members = value.members(&format!("a `{}` table", def.name))?;
+        let mut slots = vec![];
+        for (name, value) in members {
+            let field = def
+                .fields
+                .iter()
+                .find(|field| field.name == *name)
+                .ok_or_else(|| invalid(format!("`{}` has no field `{}`", def.name, name)))?;
+            if *value == Value::Null || field.deprecated {
+                continue;
+            }
+            let slot = self
+                .slot(def, field, members, value)
+                .map_err(|error| error.in_field(&def.name, &field.name))?;
+            if let Some(slot) = slot {
+                slots.push((field.id, slot));
+            }
+        }
+        if let Some(field) = def
+            .fields
+            .iter()
+            .find(|field| field.required && !slots.iter().any(|(id, _)| *id == field.id))
+        {
+            return Err(invalid(format!(
+                "missing required field `{}` of `{}`",
+                field.name, def.name
+            )));
+        }
+
+        // Like generated code, write the largest fields first to minimize
+        // padding.
+        slots.sort_by_key(|(_, slot)| Reverse(slot.size()));
+        let table = self.fbb.build_table(|table| {
+            for (id, slot) in slots {
+                let slot_offset = field_index_to_field_offset(id);
+                match slot {
+                    Slot::Inline(bytes, align) => table.push_slot_bytes(slot_offset, &bytes, align),
+                    Slot::Offset(offset) => {
+                        table.push_slot_always(slot_offset, WIPOffset::<()>::new(offset))
+                    }
+                    Slot::Offset64(offset) => {
+                        table.push_slot_always(slot_offset, WIPOffset64::<()>::new(offset))
+                    }
+                }
+            }
+        });
+        Ok(table.value())
+    }
+
+    /// The slot to store `value` in as the field `field` of the table `table`,
+    /// whose members are `members`. Scalars equal to their default value
+    /// aren't stored.
+    fn slot(
+        &mut self,
+        table: &TableDef,
+        field: &FieldDef,
+        members: &[(String, Value)],
+        value: &Value,
+    ) -> Result<Option<Slot>, Error> {
+        let schema = self.schema;
+        match &field.ty {
+            Type::Union(union) => {
+                let type_field = table.type_field(field)?;
+                let discriminant = member(members, &type_field.name)
+                    .ok_or_else(|| invalid(format!("missing `{}`", type_field.name)))?;
+                let offset = self.union_value(*union, discriminant, value)?;
+                Ok(Some(Slot::Offset(offset)))
+            }
+            Type::Vector(element) if matches!(element.as_ref(), Type::Union(_)) => {
+                let union = match element.as_ref() {
+                    Type::Union(union) => *union,
+                    _ => unreachable!(),
+                };
+                let type_field = table.type_field(field)?;
+                let discriminants = match member(members, &type_field.name) {
+                    Some(Value::Array(discriminants)) => discriminants,
+                    Some(value) => return Err(value.expected("an array")),
+                    None => return Err(invalid(format!("missing `{}`", type_field.name))),
+                };
+                let values = match value {
+                    Value::Array(values) => values,
+                    value => return Err(value.expected("an array")),
+                };
+                if discriminants.len() != values.len() {
+                    return Err(invalid(format!(
+                        "expected {} values, as many as in `{}`, found {}",
+                        discriminants.len(),
+                        type_field.name,
+                        values.len()
+                    )));
+                }
+                let offsets = discriminants
+                    .iter()
+                    .zip(values)
+                    .map(|(discriminant, value)| match value {
+                        Value::Null => Ok(None),
+                        value => self.union_value(union, discriminant, value).map(Some),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.fbb.start_vector::<WIPOffset<()>>(offsets.len());
+                for offset in offsets.iter().rev() {
+                    self.fbb.push(offset.map(WIPOffset::<()>::new));
+                }
+                let offset = self.fbb.end_vector::<WIPOffset<()>>(offsets.len());
+                Ok(Some(Slot::Offset(offset.value())))
+            }
+            Type::Vector64(element) => {
+                let values = match value {
+                    Value::Array(values) => values,
+                    value => return Err(value.expected("an array")),
+                };
+                let mut bytes = vec![];
+                for value in values {
+                    self.inline(element, value, &mut bytes)?;
+                }
+                let (_, align) = schema.inline_size(element);
+                Ok(Some(Slot::Offset64(self.fbb.create_vector64_bytes(
+                    &bytes,
+                    values.len(),
+                    align,
+                ))))
+            }
+            ty if ty.is_offset() && field.offset64 => Ok(Some(Slot::Offset64(UOffset64T::from(
+                self.offset(ty, value)?,
+            )))),
+            ty if ty.is_offset() => Ok(Some(Slot::Offset(self.offset(ty, value)?))),
+            ty => {
+                let mut bytes = vec![];
+                self.inline(ty, value, &mut bytes)?;
+                if !matches!(ty, Type::Struct(_))
+                    && !field.optional
+                    && Some(&bytes) == self.default_bytes(field).as_ref()
+                {
+                    return Ok(None);
+                }
+                let (_, align) = schema.inline_size(ty);
+                Ok(Some(Slot::Inline(bytes, align)))
+            }
+        }
+    }
+
+    /// Write the table `value` of the union `union` with the discriminant
+    /// `discriminant`, and return its offset.
+    fn union_value(
+        &mut self,
+        union: usize,
+        discriminant: &Value,
+        value: &Value,
+    ) -> Result<UOffsetT, Error> {
+        let def = &self.schema.enums[union];
+        let discriminant = enum_value(def, discriminant)?;
+        let table = def
+            .union_table(discriminant)
+            .ok_or_else(|| invalid(format!("`{}` {} has no table", def.name, discriminant)))?;
+        self.table(table, value)
+    }
+
+    /// The bytes of the default value of a scalar field.
+    fn default_bytes(&self, field: &FieldDef) -> Option<Vec<u8>> {
+        match &field.ty {
+            Type::Float => Some((field.default_real as f32).to_le_bytes().to_vec()),
+            Type::Double => Some(field.default_real.to_le_bytes().to_vec()),
+            Type::Enum(index) => {
+                let ty = &self.schema.enums[*index].underlying_type;
+                integer_bytes(ty, i128::from(field.default_integer))
+            }
+            // The default of a `ulong` may not fit in the `i64` it is stored in.
+            Type::ULong => Some((field.default_integer as u64).to_le_bytes().to_vec()),
+            ty => integer_bytes(ty, i128::from(field.default_integer)),
+        }
+    }
+
+    /// Write the string, vector or table `value` and return its offset.
+    fn offset(&mut self, ty: &Type, value: &Value) -> Result<UOffsetT, Error> {
+        let schema = self.schema;
+        match (ty, value) {
+            (Type::String, Value::String(value)) => Ok(self.fbb.create_string(value).value()),
+            (Type::String, value) => Err(value.expected("a string")),
+            (Type::Table(index), value) => self.table(*index, value),
+            (Type::Vector(element), Value::Array(values)) if element.is_offset() => {
+                let offsets = values
+                    .iter()
+                    .map(|value| self.offset(element, value))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.fbb.start_vector::<WIPOffset<()>>(offsets.len());
+                for offset in offsets.iter().rev() {
+                    self.fbb.push(WIPOffset::<()>::new(*offset));
+                }
+                Ok(self.fbb.end_vector::<WIPOffset<()>>(offsets.len()).value())
+            }
+            (Type::Vector(element), Value::Array(values)) => {
+                let mut bytes = vec![];
+                for value in values {
+                    self.inline(element, value, &mut bytes)?;
+                }
+                let (_, align) = schema.inline_size(element);
+                Ok(self.fbb.create_vector_bytes(&bytes, values.len(), align))
+            }
+            (Type::Vector(_), value) => Err(value.expected("an array")),
+            _ => Err(invalid("vectors of unions are not supported")),
+        }
+    }
+
+    /// Append the bytes of the scalar or struct `value` of type `ty` to
+    /// `dst`.
+    fn inline(&self, ty: &Type, value: &Value, dst: &mut Vec<u8>) -> Result<(), Error> {
+        let schema = self.schema;
+        match ty {
+            Type::Struct(index) => {
+                let def = &schema.structs[*index];
+                let members = value.members(&format!("a `{}` struct", def.name))?;
+                if let Some((name, _)) = members
+                    .iter()
+                    .find(|(name, _)| !def.fields.iter().any(|field| field.name == *name))
+                {
+                    return Err(invalid(format!("`{}` has no field `{}`", def.name, name)));
+                }
+                let start = dst.len();
+                dst.resize(start + def.size, 0);
+                for field in &def.fields {
+                    let value = member(members, &field.name).ok_or_else(|| {
+                        invalid(format!("missing field `{}` of `{}`", field.name, def.name))
+                    })?;
+                    let mut bytes = vec![];
+                    self.inline(&field.ty, value, &mut bytes)
+                        .map_err(|error| error.in_field(&def.name, &field.name))?;
+                    let offset = start + field.offset;
+                    dst[offset..offset + bytes.len()].copy_from_slice(&bytes);
+                }
+            }
+            Type::Array(element, len) => {
+                let values = match value {
+                    Value::Array(values) => values,
+                    value => return Err(value.expected("an array")),
+                };
+                if values.len() != *len {
+                    return Err(invalid(format!(
+                        "expected {} values, found {}",
+                        len,
+                        values.len()
+                    )));
+                }
+                for value in values {
+                    self.inline(element, value, dst)?;
+                }
+            }
+            Type::Enum(index) => {
+                let def = &schema.enums[*index];
+                let value = enum_value(def, value)?;
+                let bytes = integer_bytes(&def.underlying_type, value)
+                    .ok_or_else(|| invalid(format!("{} is out of range", value)))?;
+                dst.extend(bytes);
+            }
+            Type::Float | Type::Double => {
+                let value = match value {
+                    Value::String(text) => return self.inline(ty, &unquoted(text), dst),
+                    Value::Number(text) | Value::Ident(text) => parse_float(text)
+                        .ok_or_else(|| invalid(format!("invalid number `{}`", text)))?,
+                    value => return Err(value.expected("a number")),
+                };
+                if *ty == Type::Float {
+                    dst.extend((value as f32).to_le_bytes());
+                } else {
+                    dst.extend(value.to_le_bytes());
+                }
+            }
+            ty => {
+                let value = match value {
+                    Value::Bool(value) => i128::from(*value),
+                    Value::Number(text) => parse_integer(text)
+                        .ok_or_else(|| invalid(format!("invalid integer `{}`", text)))?,
+                    Value::String(text) => return self.inline(ty, &unquoted(text), dst),
+                    Value::Ident(names) => {
+                        enum_names(ty, names, |name| schema.qualified_enum_value(name))?
+                    }
+                    value => return Err(value.expected("an integer")),
+                };
+                let bytes = integer_bytes(ty, value)
+                    .ok_or_else(|| invalid(format!("{} is out of range", value)))?;
+                dst.extend(bytes);
+            }
+        }
+        Ok(())
+    }
+}
@@ -27,27 +27,79 @@
 //!
 //! At this time, to generate Rust code, you will need the latest `master` version of `flatc`, available from here: https://github.com/google/flatbuffers
 //! (On OSX, you can install FlatBuffers from `HEAD` with the Homebrew package manager.)
+//!
+//! The crate is `no_std` without its default `std` feature. Buffers can then
+//! be read without allocating; the `alloc` feature adds the builders, `Message`,
+//! copying tables between buffers, framing streams of buffers and JSON
+//! conversion. On Unix, the `mmap` feature adds `open_mapped_root`, which
+//! reads buffers from memory-mapped files.
+
+#![cfg_attr(not(feature = "std"), no_std)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
 mod builder;
+#[cfg(feature = "alloc")]
+mod copy;
 mod endian_scalar;
+mod error;
+pub mod flexbuffers;
 mod follow;
+pub mod fuzz;
+pub mod hash;
+#[cfg(feature = "alloc")]
+pub mod json;
+#[cfg(feature = "alloc")]
+mod message;
+#[cfg(all(feature = "mmap", unix))]
+mod mmap;
 mod primitives;
 mod push;
+pub mod reflection;
+#[cfg(feature = "alloc")]
+pub mod stream;
+mod string;
 mod table;
+mod union;
 mod vector;
+mod verifier;
 mod vtable;
+#[cfg(feature = "alloc")]
 mod vtable_writer;
 
+#[cfg(all(feature = "mmap", unix))]
+pub use crate::mmap::{open_mapped_root, MappedRoot};
+#[cfg(feature = "alloc")]
+pub use crate::{
+    builder::{FlatBufferBuilder, TableWriter},
+    copy::{copy_table, FieldLayout, TableLayout, ValueLayout},
+    message::{DeepCopy, Message},
+};
 pub use crate::{
-    builder::FlatBufferBuilder,
     endian_scalar::{
         byte_swap_f32, byte_swap_f64, emplace_scalar, read_scalar, read_scalar_at, EndianScalar,
     },
+    error::{Error, HexDump, Result, UnknownEnumValue},
     follow::{Follow, FollowStart},
+    hash::HashAlgorithm,
     primitives::*,
-    push::Push,
-    table::{buffer_has_identifier, get_root, get_size_prefixed_root, Table},
-    vector::{follow_cast_ref, SafeSliceAccess, Vector},
+    push::{padding_bytes, Push, PushAlignment},
+    string::FbString,
+    table::{
+        buffer_has_identifier, get_root, get_size_prefixed_root, Table, TableMarker, UnknownField,
+        UnknownFields,
+    },
+    union::{UnionReader, UnionVector, UnionVectorIter},
+    vector::{
+        follow_cast_copy, follow_cast_ref, SafeSliceAccess, ScalarSliceAccess, Vector, Vector64,
+        VectorIter,
+    },
+    verifier::{
+        verified_root, verified_root_with_options, verified_size_prefixed_root,
+        verified_size_prefixed_root_with_options, Verifier, VerifierOptions, Verify,
+    },
     vtable::field_index_to_field_offset,
 };
 
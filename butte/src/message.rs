@@ -0,0 +1,83 @@
+use alloc::vec::Vec;
+use core::{fmt, marker::PhantomData};
+
+use crate::{
+    builder::FlatBufferBuilder,
+    error::Result,
+    primitives::WIPOffset,
+    table::{get_root, TableMarker},
+};
+
+/// An owned, finished flatbuffer whose root is the table marked by `T`.
+///
+/// This is useful where a buffer has to outlive the builder that created it,
+/// for example when it is sent to another thread or over the network.
+pub struct Message<T> {
+    data: Vec<u8>,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Message<T> {
+    /// Wrap the bytes of a finished buffer.
+    pub fn new(data: Vec<u8>) -> Self {
+        Self {
+            data,
+            marker: PhantomData,
+        }
+    }
+
+    /// Copy the finished data of `builder`.
+    pub fn from_builder(builder: &FlatBufferBuilder) -> Self {
+        Self::new(builder.finished_data().to_vec())
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+impl<T: TableMarker> Message<T> {
+    /// Read the root table of the buffer.
+    pub fn root(&self) -> Result<T::Table<'_>> {
+        get_root::<T::Table<'_>>(&self.data)
+    }
+}
+
+/// Tables that can be copied to another buffer along with all the data they
+/// refer to. Generated tables implement it for their marker type, such as
+/// `MonsterOffset`, with `copy_table`.
+pub trait DeepCopy: TableMarker {
+    /// Write a copy of `table`, and of the data it refers to, to `fbb`.
+    fn copy_to<'b>(
+        table: &Self::Table<'_>,
+        fbb: &mut FlatBufferBuilder<'b>,
+    ) -> Result<WIPOffset<Self::Table<'b>>>;
+}
+
+impl<T: DeepCopy> Message<T> {
+    /// Copy `table` out of the buffer it is in, into a buffer of its own of
+    /// which it is the root. The copy holds the data that `table` refers to,
+    /// directly or through other tables, and nothing else of the buffer.
+    pub fn extract(table: &T::Table<'_>) -> Result<Self> {
+        let mut builder = FlatBufferBuilder::new();
+        let root = T::copy_to(table, &mut builder)?;
+        builder.finish_minimal(root);
+        Ok(Self::from_builder(&builder))
+    }
+}
+
+impl<T> Clone for Message<T> {
+    fn clone(&self) -> Self {
+        Self::new(self.data.clone())
+    }
+}
+
+impl<T> fmt::Debug for Message<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Message").field("data", &self.data).finish()
+    }
+}
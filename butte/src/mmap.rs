@@ -0,0 +1,147 @@
+//! Reading buffers from memory-mapped files, see [`open_mapped_root`].
+//!
+//! Mapping a file lets a buffer much larger than memory be read in place:
+//! only the pages that are read are loaded, and the operating system can drop
+//! them again when memory runs low.
+use std::{
+    convert::TryFrom,
+    fs::File,
+    io,
+    marker::PhantomData,
+    ops::Deref,
+    os::unix::io::AsRawFd,
+    path::Path,
+    ptr::{self, NonNull},
+    slice,
+};
+
+use crate::{
+    error::Result,
+    table::{get_root, Table, TableMarker},
+};
+
+/// A file mapped read-only into memory, which is unmapped when dropped.
+struct Mmap {
+    ptr: NonNull<u8>,
+    len: usize,
+}
+
+// The mapping is read-only, so it can be shared between threads like a
+// `&[u8]`.
+unsafe impl Send for Mmap {}
+unsafe impl Sync for Mmap {}
+
+impl Mmap {
+    /// Map all of `file`. An empty file isn't mapped, since a mapping can't
+    /// be empty.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified while it is mapped.
+    unsafe fn map(file: &File) -> io::Result<Self> {
+        let len = usize::try_from(file.metadata()?.len()).map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidData, "the file is too large to map")
+        })?;
+        if len == 0 {
+            return Ok(Mmap {
+                ptr: NonNull::dangling(),
+                len,
+            });
+        }
+        let ptr = libc::mmap(
+            ptr::null_mut(),
+            len,
+            libc::PROT_READ,
+            libc::MAP_PRIVATE,
+            file.as_raw_fd(),
+            0,
+        );
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Mmap {
+            ptr: NonNull::new_unchecked(ptr as *mut u8),
+            len,
+        })
+    }
+}
+
+impl Deref for Mmap {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        if self.len != 0 {
+            unsafe {
+                libc::munmap(self.ptr.as_ptr() as *mut libc::c_void, self.len);
+            }
+        }
+    }
+}
+
+/// A memory-mapped file holding a flatbuffer whose root is the table marked
+/// by `T`, which owns the mapping that the readers it returns borrow.
+///
+/// The mapping doesn't move when a `MappedRoot` does, so it can be moved
+/// around, or sent to another thread, like a `Message`.
+pub struct MappedRoot<T> {
+    map: Mmap,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T: TableMarker> MappedRoot<T> {
+    /// Read the root table of the buffer.
+    #[inline]
+    pub fn root(&self) -> Result<T::Table<'_>> {
+        get_root::<T::Table<'_>>(&self.map)
+    }
+}
+
+impl<T> MappedRoot<T> {
+    /// The mapped bytes of the file.
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        &self.map
+    }
+}
+
+impl<T> core::fmt::Debug for MappedRoot<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MappedRoot")
+            .field("len", &self.map.len)
+            .finish()
+    }
+}
+
+/// Memory-map the file at `path`, which holds a flatbuffer whose root is the
+/// table marked by `T`, such as `MonsterOffset`.
+///
+/// This checks that the root table and its vtable lie within the file, but
+/// doesn't verify the buffer, which would read all of it. Buffers that can't
+/// be trusted can be verified with `verified_root` on `MappedRoot::data`.
+/// Errors reading the root are returned as `io::ErrorKind::InvalidData`.
+///
+/// # Safety
+///
+/// The file must not be modified, or truncated, while it is mapped, which
+/// would change the bytes that readers borrowing the mapping have already
+/// checked, or make them unreadable.
+pub unsafe fn open_mapped_root<T: TableMarker>(
+    path: impl AsRef<Path>,
+) -> io::Result<MappedRoot<T>> {
+    let file = File::open(path)?;
+    let mapped = MappedRoot {
+        map: Mmap::map(&file)?,
+        marker: PhantomData,
+    };
+    get_root::<Table<'_>>(mapped.data())
+        .and_then(|table| table.vtable())
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+    Ok(mapped)
+}
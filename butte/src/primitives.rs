@@ -14,18 +14,75 @@
  * limitations under the License.
  */
 
-use std::{marker::PhantomData, mem::size_of, ops::Deref};
+use core::{convert::TryFrom, marker::PhantomData, mem::size_of, ops::Deref};
 
 use crate::{
-    endian_scalar::{emplace_scalar, read_scalar, read_scalar_at},
-    follow::Follow,
+    endian_scalar::{emplace_scalar, read_scalar_at, EndianScalar},
+    error::{Error, Result},
+    follow::{checked_slice, Follow},
     push::Push,
+    vector::{follow_cast_copy, SafeSliceAccess},
 };
 
 pub const FLATBUFFERS_MAX_BUFFER_SIZE: usize = (1u64 << 31) as usize;
 
+/// The largest buffer with 64-bit offsets, which can grow past
+/// `FLATBUFFERS_MAX_BUFFER_SIZE` as far as the address space allows.
+pub const FLATBUFFERS_MAX_64_BUFFER_SIZE: usize = isize::MAX as usize;
+
 pub const FILE_IDENTIFIER_LENGTH: usize = 4;
 
+/// The file identifier of a schema: the 4 bytes that a buffer finished with
+/// it holds after its root offset.
+///
+/// `FileIdent::new` is a `const fn`, so an identifier of the wrong length
+/// given to it in a constant, like the `FILE_IDENTIFIER` generated for a
+/// schema, fails to compile rather than to run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileIdent([u8; FILE_IDENTIFIER_LENGTH]);
+
+impl FileIdent {
+    /// The identifier `ident`, which must be 4 bytes long.
+    ///
+    /// Panics if it isn't, which is a compile error in a constant.
+    pub const fn new(ident: &str) -> Self {
+        let bytes = ident.as_bytes();
+        assert!(
+            bytes.len() == FILE_IDENTIFIER_LENGTH,
+            "a file identifier must be 4 bytes long"
+        );
+        FileIdent([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+
+    #[inline]
+    pub const fn from_bytes(bytes: [u8; FILE_IDENTIFIER_LENGTH]) -> Self {
+        FileIdent(bytes)
+    }
+
+    #[inline]
+    pub const fn as_bytes(&self) -> &[u8; FILE_IDENTIFIER_LENGTH] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for FileIdent {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Convert an identifier known only at runtime, which fails unless it is 4
+/// bytes long.
+impl TryFrom<&str> for FileIdent {
+    type Error = core::array::TryFromSliceError;
+
+    #[inline]
+    fn try_from(ident: &str) -> core::result::Result<Self, Self::Error> {
+        <[u8; FILE_IDENTIFIER_LENGTH]>::try_from(ident.as_bytes()).map(FileIdent)
+    }
+}
+
 pub const VTABLE_METADATA_FIELDS: usize = 2;
 
 pub const SIZE_U8: usize = size_of::<u8>();
@@ -45,6 +102,7 @@ pub const SIZE_F64: usize = size_of::<f64>();
 
 pub const SIZE_SOFFSET: usize = SIZE_I32;
 pub const SIZE_UOFFSET: usize = SIZE_U32;
+pub const SIZE_UOFFSET64: usize = SIZE_U64;
 pub const SIZE_VOFFSET: usize = SIZE_I16;
 
 pub const SIZE_SIZEPREFIX: usize = SIZE_UOFFSET;
@@ -56,6 +114,11 @@ pub type SOffsetT = i32;
 /// and lengths of vectors.
 pub type UOffsetT = u32;
 
+/// UOffset64T is a u64 that is used by fields with the `offset64` or
+/// `vector64` attribute to refer to data beyond the reach of a `UOffsetT`, and
+/// as the length of vectors with the `vector64` attribute.
+pub type UOffset64T = u64;
+
 /// VOffsetT is a i32 that is used by vtables to store field data.
 pub type VOffsetT = i16;
 
@@ -107,14 +170,11 @@ impl<T> Deref for WIPOffset<T> {
         &self.0
     }
 }
-impl<'a, T: 'a> WIPOffset<T> {
+impl<T> WIPOffset<T> {
     /// Create a new WIPOffset.
     #[inline]
     pub fn new(o: UOffsetT) -> WIPOffset<T> {
-        WIPOffset {
-            0: o,
-            1: PhantomData,
-        }
+        WIPOffset(o, PhantomData)
     }
 
     /// Return a wrapped value that brings its meaning as a union WIPOffset
@@ -140,6 +200,20 @@ impl<T> Push for WIPOffset<T> {
     }
 }
 
+/// An empty offset is written as 0, which is how the elements of a vector of
+/// unions whose type is `NONE` are stored.
+impl<T> Push for Option<WIPOffset<T>> {
+    type Output = ForwardsUOffset<T>;
+
+    #[inline(always)]
+    fn push(&self, dst: &mut [u8], rest: &[u8]) {
+        match self {
+            Some(offset) => offset.push(dst, rest),
+            None => emplace_scalar::<UOffsetT>(dst, 0),
+        }
+    }
+}
+
 impl<T> Push for ForwardsUOffset<T> {
     type Output = Self;
 
@@ -176,10 +250,111 @@ impl<T> ForwardsUOffset<T> {
 impl<'a, T: Follow<'a>> Follow<'a> for ForwardsUOffset<T> {
     type Inner = T::Inner;
     #[inline(always)]
-    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
-        let slice = &buf[loc..loc + SIZE_UOFFSET];
-        let off = read_scalar::<u32>(slice) as usize;
-        T::follow(buf, loc + off)
+    fn follow(buf: &'a [u8], loc: usize) -> Result<Self::Inner> {
+        let slice = checked_slice(buf, loc, SIZE_UOFFSET)?;
+        let off = u32::read_le(slice) as usize;
+        T::follow(
+            buf,
+            loc.checked_add(off).ok_or(Error::InvalidOffset { loc })?,
+        )
+    }
+}
+
+/// WIPOffset64 is the location of data relative to the end of an in-progress
+/// FlatBuffer, like a `WIPOffset`, that is referred to by a 64-bit offset.
+/// The impl of Push converts it into a ForwardsUOffset64.
+#[derive(Debug)]
+pub struct WIPOffset64<T>(UOffset64T, PhantomData<T>);
+
+impl<T> Copy for WIPOffset64<T> {}
+impl<T> Clone for WIPOffset64<T> {
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> PartialEq for WIPOffset64<T> {
+    fn eq(&self, o: &WIPOffset64<T>) -> bool {
+        self.value() == o.value()
+    }
+}
+
+impl<T> WIPOffset64<T> {
+    /// Create a new WIPOffset64.
+    #[inline]
+    pub fn new(o: UOffset64T) -> WIPOffset64<T> {
+        WIPOffset64(o, PhantomData)
+    }
+
+    /// Get the underlying value.
+    #[inline(always)]
+    pub fn value(self) -> UOffset64T {
+        self.0
+    }
+}
+
+/// Any data written to the builder can be referred to by a 64-bit offset,
+/// e.g., a string created with `create_string` for a field with the
+/// `offset64` attribute, as long as it is within the first 4 gigabytes, which
+/// a `WIPOffset` can point into.
+impl<T> From<WIPOffset<T>> for WIPOffset64<T> {
+    #[inline]
+    fn from(offset: WIPOffset<T>) -> Self {
+        WIPOffset64::new(UOffset64T::from(offset.value()))
+    }
+}
+
+impl<T> Push for WIPOffset64<T> {
+    type Output = ForwardsUOffset64<T>;
+
+    #[inline(always)]
+    fn push(&self, dst: &mut [u8], rest: &[u8]) {
+        let n = (SIZE_UOFFSET64 + rest.len()) as UOffset64T - self.value();
+        emplace_scalar::<UOffset64T>(dst, n);
+    }
+}
+
+/// ForwardsUOffset64 is used by Follow to traverse a FlatBuffer: the pointer
+/// is incremented by the 64-bit value contained in this type.
+#[derive(Debug)]
+pub struct ForwardsUOffset64<T>(UOffset64T, PhantomData<T>);
+
+impl<T> Copy for ForwardsUOffset64<T> {}
+impl<T> Clone for ForwardsUOffset64<T> {
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> ForwardsUOffset64<T> {
+    #[inline(always)]
+    pub fn value(self) -> UOffset64T {
+        self.0
+    }
+}
+
+impl<'a, T: Follow<'a>> Follow<'a> for ForwardsUOffset64<T> {
+    type Inner = T::Inner;
+    #[inline(always)]
+    fn follow(buf: &'a [u8], loc: usize) -> Result<Self::Inner> {
+        let slice = checked_slice(buf, loc, SIZE_UOFFSET64)?;
+        let off = usize::try_from(UOffset64T::read_le(slice))
+            .map_err(|_| Error::InvalidOffset { loc })?;
+        T::follow(
+            buf,
+            loc.checked_add(off).ok_or(Error::InvalidOffset { loc })?,
+        )
+    }
+}
+
+impl<T> Push for ForwardsUOffset64<T> {
+    type Output = Self;
+
+    #[inline(always)]
+    fn push(&self, dst: &mut [u8], rest: &[u8]) {
+        self.value().push(dst, rest);
     }
 }
 
@@ -197,10 +372,13 @@ impl<T> ForwardsVOffset<T> {
 impl<'a, T: Follow<'a>> Follow<'a> for ForwardsVOffset<T> {
     type Inner = T::Inner;
     #[inline(always)]
-    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
-        let slice = &buf[loc..loc + SIZE_VOFFSET];
-        let off = read_scalar::<VOffsetT>(slice) as usize;
-        T::follow(buf, loc + off)
+    fn follow(buf: &'a [u8], loc: usize) -> Result<Self::Inner> {
+        let slice = checked_slice(buf, loc, SIZE_VOFFSET)?;
+        let off = u16::read_le(slice) as usize;
+        T::follow(
+            buf,
+            loc.checked_add(off).ok_or(Error::InvalidOffset { loc })?,
+        )
     }
 }
 
@@ -227,10 +405,14 @@ impl<T> BackwardsSOffset<T> {
 impl<'a, T: Follow<'a>> Follow<'a> for BackwardsSOffset<T> {
     type Inner = T::Inner;
     #[inline(always)]
-    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
-        let slice = &buf[loc..loc + SIZE_SOFFSET];
-        let off = read_scalar::<SOffsetT>(slice);
-        T::follow(buf, (loc as SOffsetT - off) as usize)
+    fn follow(buf: &'a [u8], loc: usize) -> Result<Self::Inner> {
+        let slice = checked_slice(buf, loc, SIZE_SOFFSET)?;
+        let off = SOffsetT::read_le(slice);
+        let loc = (loc as i64)
+            .checked_sub(i64::from(off))
+            .and_then(|loc| usize::try_from(loc).ok())
+            .ok_or(Error::InvalidOffset { loc })?;
+        T::follow(buf, loc)
     }
 }
 
@@ -245,22 +427,38 @@ impl<T> Push for BackwardsSOffset<T> {
 
 /// SkipSizePrefix is used by Follow to traverse a FlatBuffer: the pointer is
 /// incremented by a fixed constant in order to skip over the size prefix value.
+///
+/// The size prefix must lie within the buffer, and so must the data whose size
+/// it gives. What follows it only sees that data, not anything after it.
 pub struct SkipSizePrefix<T>(PhantomData<T>);
 impl<'a, T: Follow<'a> + 'a> Follow<'a> for SkipSizePrefix<T> {
     type Inner = T::Inner;
     #[inline(always)]
-    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
-        T::follow(buf, loc + SIZE_SIZEPREFIX)
+    fn follow(buf: &'a [u8], loc: usize) -> Result<Self::Inner> {
+        let (buf, loc) = skip_size_prefix(buf, loc)?;
+        T::follow(buf, loc)
     }
 }
 
+/// Skip the size prefix at `loc`, returning the part of `buf` up to the end of
+/// the data whose size it gives, and the location of that data.
+#[inline]
+pub(crate) fn skip_size_prefix(buf: &[u8], loc: usize) -> Result<(&[u8], usize)> {
+    let size = read_scalar_at::<UOffsetT>(buf, loc)? as usize;
+    let start = loc + SIZE_SIZEPREFIX;
+    let data = checked_slice(buf, start, size)?;
+    Ok((&buf[..start + data.len()], start))
+}
+
 /// SkipRootOffset is used by Follow to traverse a FlatBuffer: the pointer is
-/// incremented by a fixed constant in order to skip over the root offset value.
+/// incremented by a fixed constant in order to skip over the root offset value,
+/// which must lie within the buffer.
 pub struct SkipRootOffset<T>(PhantomData<T>);
 impl<'a, T: Follow<'a> + 'a> Follow<'a> for SkipRootOffset<T> {
     type Inner = T::Inner;
     #[inline(always)]
-    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    fn follow(buf: &'a [u8], loc: usize) -> Result<Self::Inner> {
+        checked_slice(buf, loc, SIZE_UOFFSET)?;
         T::follow(buf, loc + SIZE_UOFFSET)
     }
 }
@@ -271,28 +469,45 @@ pub struct FileIdentifier;
 impl<'a> Follow<'a> for FileIdentifier {
     type Inner = &'a [u8];
     #[inline(always)]
-    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
-        &buf[loc..loc + FILE_IDENTIFIER_LENGTH]
+    fn follow(buf: &'a [u8], loc: usize) -> Result<Self::Inner> {
+        checked_slice(buf, loc, FILE_IDENTIFIER_LENGTH)
     }
 }
 
 /// SkipFileIdentifier is used by Follow to traverse a FlatBuffer: the pointer
 /// is incremented by a fixed constant in order to skip over the file
-/// identifier value.
+/// identifier value, which must lie within the buffer.
 pub struct SkipFileIdentifier<T>(PhantomData<T>);
 impl<'a, T: Follow<'a> + 'a> Follow<'a> for SkipFileIdentifier<T> {
     type Inner = T::Inner;
     #[inline(always)]
-    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    fn follow(buf: &'a [u8], loc: usize) -> Result<Self::Inner> {
+        checked_slice(buf, loc, FILE_IDENTIFIER_LENGTH)?;
         T::follow(buf, loc + FILE_IDENTIFIER_LENGTH)
     }
 }
 
+/// Copied is used by Follow to read a struct stored inline by value, rather
+/// than by reference, which doesn't require the struct to be aligned in the
+/// buffer.
+#[repr(transparent)]
+pub struct Copied<T>(T);
+impl<'a, T: SafeSliceAccess + Copy + 'a> Follow<'a> for Copied<T> {
+    type Inner = T;
+    #[inline(always)]
+    fn follow(buf: &'a [u8], loc: usize) -> Result<Self::Inner> {
+        follow_cast_copy::<T>(buf, loc)
+    }
+}
+
+/// A `bool` is read from its byte as `true` unless the byte is 0, so a
+/// buffer from a writer that stores other bytes reads like it would in other
+/// languages. `VerifierOptions::strict_bools` rejects such buffers instead.
 impl<'a> Follow<'a> for bool {
     type Inner = bool;
     #[inline(always)]
-    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
-        read_scalar_at::<u8>(buf, loc) != 0
+    fn follow(buf: &'a [u8], loc: usize) -> Result<Self::Inner> {
+        Ok(read_scalar_at::<u8>(buf, loc)? != 0)
     }
 }
 
@@ -306,7 +521,7 @@ macro_rules! impl_follow_for_endian_scalar {
         impl<'a> Follow<'a> for $ty {
             type Inner = $ty;
             #[inline(always)]
-            fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+            fn follow(buf: &'a [u8], loc: usize) -> Result<Self::Inner> {
                 read_scalar_at::<$ty>(buf, loc)
             }
         }
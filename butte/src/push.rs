@@ -14,47 +14,96 @@
  * limitations under the License.
  */
 
-use std::{
+use core::{
     cmp::max,
     mem::{align_of, size_of},
 };
 
 use crate::endian_scalar::emplace_scalar;
 
-/// Trait to abstract over functionality needed to write values (either owned
-/// or referenced). Used in FlatBufferBuilder and implemented for generated
-/// types.
+/// A value that can be written to a buffer by `FlatBufferBuilder::push`, and
+/// the vectors and table fields built on it.
+///
+/// The builder writes buffers back to front. To push a value it aligns the
+/// front of the data written so far to `alignment()`, makes room for
+/// `size()` bytes in front of it, and calls `push` with those bytes as `dst`
+/// and the data already written after them as `rest`. `push` fills all of
+/// `dst`, with scalars in little-endian byte order. `rest` lets a value that
+/// refers to data written earlier, like a `WIPOffset`, work out how far away
+/// that data is.
+///
+/// `Push` is implemented for:
+///
+/// - the scalars `bool`, `u8`, `i8`, `u16`, `i16`, `u32`, `i32`, `u64`,
+///   `i64`, `f32` and `f64`, which are written as their little-endian bytes,
+///   with `bool` written as 0 or 1;
+/// - `WIPOffset<T>` and `WIPOffset64<T>`, which are written as the offsets of
+///   `ForwardsUOffset<T>` and `ForwardsUOffset64<T>` from where they are
+///   written to the data they refer to, and `Option<WIPOffset<T>>`, whose
+///   `None` is written as 0;
+/// - the offsets read by `Follow`, which are written as they are;
+/// - generated enums, which are written as their base type, and generated
+///   structs and references to them, which are written as their bytes.
 pub trait Push: Sized {
+    /// The type the written value is read as.
     type Output;
+
+    /// Write the value to `dst`, which is `size()` bytes long and is followed
+    /// in the buffer by `rest`.
     fn push(&self, dst: &mut [u8], _rest: &[u8]);
+
+    /// The number of bytes the value is written as.
     #[inline]
     fn size() -> usize {
         size_of::<Self::Output>()
     }
+
+    /// The alignment the value is written with.
     #[inline]
     fn alignment() -> PushAlignment {
         PushAlignment::new(align_of::<Self::Output>())
     }
 }
 
-/// Ensure Push alignment calculations are typesafe (because this helps reduce
-/// implementation issues when using FlatBufferBuilder::align).
+/// The alignment of a value written by `Push`, in bytes, which is a power of
+/// two. Keeping it apart from other sizes makes it harder to confuse the two
+/// in the builder's alignment calculations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct PushAlignment(usize);
+
 impl PushAlignment {
     #[inline]
     pub fn new(x: usize) -> Self {
-        PushAlignment { 0: x }
+        PushAlignment(x)
     }
+
+    /// The alignment in bytes.
     #[inline]
     pub fn value(&self) -> usize {
         self.0
     }
+
+    /// The larger of this alignment and `o`.
     #[inline]
     pub fn max_of(&self, o: usize) -> Self {
         PushAlignment::new(max(self.0, o))
     }
 }
 
+/// The number of bytes of padding to put in front of `buf_size` bytes,
+/// written back to front from the end of a buffer, so that they start at a
+/// multiple of `alignment`, a power of two.
+///
+/// ```
+/// assert_eq!(butte::padding_bytes(6, 4), 2);
+/// assert_eq!(butte::padding_bytes(8, 4), 0);
+/// ```
+#[inline]
+pub fn padding_bytes(buf_size: usize, alignment: usize) -> usize {
+    // ((!buf_size) + 1) & (alignment - 1)
+    (!buf_size).wrapping_add(1) & (alignment.wrapping_sub(1))
+}
+
 /// Macro to implement Push for EndianScalar types.
 macro_rules! impl_push_for_endian_scalar {
     ($ty:ident) => {
@@ -0,0 +1,766 @@
+//! Runtime access to binary schemas.
+//!
+//! A binary schema (a `.bfbs` file) is a FlatBuffer describing the types of a
+//! schema, as written by `flatc --schema --binary` or
+//! `butte_build::reflection_schema`. Its layout is defined by flatc's
+//! `reflection.fbs`, and the readers in this module follow that layout.
+//!
+//! Besides introspecting the types themselves, the schema can be used to read
+//! the fields of buffers whose types are only known at runtime, or to convert
+//! them to JSON with [`Schema::to_json_schema`].
+
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, format, string::ToString, vec, vec::Vec};
+use core::cmp::Ordering;
+#[cfg(feature = "alloc")]
+use core::convert::TryFrom;
+
+#[cfg(feature = "alloc")]
+use crate::json;
+use crate::{
+    error::{Error, Result},
+    follow::Follow,
+    primitives::*,
+    table::{get_root, Table},
+    vector::Vector,
+};
+
+/// The file identifier of binary schemas.
+pub const FILE_IDENTIFIER: FileIdent = FileIdent::new("BFBS");
+
+/// The file extension of binary schemas.
+pub const FILE_EXTENSION: &str = "bfbs";
+
+/// The type of a value, as stored in a [`Type`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BaseType(pub i8);
+
+impl BaseType {
+    pub const NONE: Self = Self(0);
+    /// The discriminant of a union.
+    pub const UTYPE: Self = Self(1);
+    pub const BOOL: Self = Self(2);
+    pub const BYTE: Self = Self(3);
+    pub const UBYTE: Self = Self(4);
+    pub const SHORT: Self = Self(5);
+    pub const USHORT: Self = Self(6);
+    pub const INT: Self = Self(7);
+    pub const UINT: Self = Self(8);
+    pub const LONG: Self = Self(9);
+    pub const ULONG: Self = Self(10);
+    pub const FLOAT: Self = Self(11);
+    pub const DOUBLE: Self = Self(12);
+    pub const STRING: Self = Self(13);
+    pub const VECTOR: Self = Self(14);
+    /// A table or a struct.
+    pub const OBJ: Self = Self(15);
+    /// The value of a union.
+    pub const UNION: Self = Self(16);
+    /// A fixed-size array stored inline in a struct.
+    pub const ARRAY: Self = Self(17);
+    /// A vector with a 64-bit length.
+    pub const VECTOR64: Self = Self(18);
+
+    /// Check whether values of the type are stored as integers.
+    pub fn is_integer(self) -> bool {
+        (Self::UTYPE.0..=Self::ULONG.0).contains(&self.0)
+    }
+
+    /// Check whether values of the type are stored as floating point numbers.
+    pub fn is_float(self) -> bool {
+        self == Self::FLOAT || self == Self::DOUBLE
+    }
+}
+
+impl<'a> Follow<'a> for BaseType {
+    type Inner = Self;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Result<Self::Inner> {
+        i8::follow(buf, loc).map(Self)
+    }
+}
+
+/// Declare a reader for a table of `reflection.fbs`.
+macro_rules! reflection_table {
+    ($(#[$attr:meta])* $name:ident) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        pub struct $name<'a> {
+            table: Table<'a>,
+        }
+
+        impl<'a> From<Table<'a>> for $name<'a> {
+            fn from(table: Table<'a>) -> Self {
+                Self { table }
+            }
+        }
+
+        impl<'a> Follow<'a> for $name<'a> {
+            type Inner = Self;
+            #[inline]
+            fn follow(buf: &'a [u8], loc: usize) -> Result<Self::Inner> {
+                Ok(Self {
+                    table: Table { buf, loc },
+                })
+            }
+        }
+    };
+}
+
+reflection_table! {
+    /// A schema: the root of a binary schema.
+    Schema
+}
+
+reflection_table! {
+    /// A table or struct type.
+    Object
+}
+
+reflection_table! {
+    /// A field of a table or struct.
+    Field
+}
+
+reflection_table! {
+    /// The type of a field, enum or union value.
+    Type
+}
+
+reflection_table! {
+    /// An enum or union type.
+    Enum
+}
+
+reflection_table! {
+    /// A value of an enum or union type.
+    EnumVal
+}
+
+type Objects<'a> = Vector<'a, ForwardsUOffset<Object<'a>>>;
+type Fields<'a> = Vector<'a, ForwardsUOffset<Field<'a>>>;
+type Enums<'a> = Vector<'a, ForwardsUOffset<Enum<'a>>>;
+type EnumVals<'a> = Vector<'a, ForwardsUOffset<EnumVal<'a>>>;
+
+/// Read a string field that the schema marks as required.
+fn required_str<'a>(table: &Table<'a>, slot_byte_loc: VOffsetT) -> Result<&'a str> {
+    required::<&str>(table, slot_byte_loc)
+}
+
+/// Read an offset field that the schema marks as required.
+fn required<'a, T: Follow<'a> + 'a>(
+    table: &Table<'a>,
+    slot_byte_loc: VOffsetT,
+) -> Result<T::Inner> {
+    table
+        .get::<ForwardsUOffset<T>>(slot_byte_loc, None)?
+        .ok_or(Error::MissingRequiredField {
+            slot: slot_byte_loc,
+        })
+}
+
+/// Find the element of a vector sorted by name with the given name.
+fn lookup_by_name<'a, T>(
+    vector: Vector<'a, ForwardsUOffset<T>>,
+    name: &str,
+    name_of: impl Fn(&T::Inner) -> Result<&'a str>,
+) -> Result<Option<T::Inner>>
+where
+    T: Follow<'a> + 'a,
+{
+    let (mut low, mut high) = (0, vector.len());
+    while low < high {
+        let mid = low + (high - low) / 2;
+        let element = vector.get(mid)?;
+        match name_of(&element)?.cmp(name) {
+            Ordering::Less => low = mid + 1,
+            Ordering::Greater => high = mid,
+            Ordering::Equal => return Ok(Some(element)),
+        }
+    }
+    Ok(None)
+}
+
+impl<'a> Schema<'a> {
+    pub const VT_OBJECTS: VOffsetT = 4;
+    pub const VT_ENUMS: VOffsetT = 6;
+    pub const VT_FILE_IDENT: VOffsetT = 8;
+    pub const VT_FILE_EXT: VOffsetT = 10;
+    pub const VT_ROOT_TABLE: VOffsetT = 12;
+
+    /// All tables and structs, sorted by their fully qualified name.
+    #[inline]
+    pub fn objects(&self) -> Result<Objects<'a>> {
+        required::<Vector<_>>(&self.table, Self::VT_OBJECTS)
+    }
+
+    /// All enums and unions, sorted by their fully qualified name.
+    #[inline]
+    pub fn enums(&self) -> Result<Enums<'a>> {
+        required::<Vector<_>>(&self.table, Self::VT_ENUMS)
+    }
+
+    #[inline]
+    pub fn file_ident(&self) -> Result<Option<&'a str>> {
+        self.table
+            .get::<ForwardsUOffset<&str>>(Self::VT_FILE_IDENT, None)
+    }
+
+    #[inline]
+    pub fn file_ext(&self) -> Result<Option<&'a str>> {
+        self.table
+            .get::<ForwardsUOffset<&str>>(Self::VT_FILE_EXT, None)
+    }
+
+    #[inline]
+    pub fn root_table(&self) -> Result<Option<Object<'a>>> {
+        self.table
+            .get::<ForwardsUOffset<Object>>(Self::VT_ROOT_TABLE, None)
+    }
+
+    /// Look up a table or struct by its fully qualified name, e.g.
+    /// `MyGame.Monster`.
+    pub fn object_by_name(&self, name: &str) -> Result<Option<Object<'a>>> {
+        lookup_by_name(self.objects()?, name, Object::name)
+    }
+
+    /// Look up an enum or union by its fully qualified name.
+    pub fn enum_by_name(&self, name: &str) -> Result<Option<Enum<'a>>> {
+        lookup_by_name(self.enums()?, name, Enum::name)
+    }
+
+    /// Describe the schema for converting buffers to and from JSON with
+    /// [`json::to_json`] and [`json::from_json`].
+    #[cfg(feature = "alloc")]
+    pub fn to_json_schema(&self) -> core::result::Result<json::Schema, json::Error> {
+        let objects = self.objects()?;
+        // Tables and structs are kept in separate lists by `json::Schema`, so
+        // the index of every object in its list is needed to resolve types.
+        let mut indices = Vec::with_capacity(objects.len());
+        let (mut tables, mut structs) = (0, 0);
+        for object in objects.iter() {
+            let counter = if object?.is_struct()? {
+                &mut structs
+            } else {
+                &mut tables
+            };
+            indices.push(*counter);
+            *counter += 1;
+        }
+        let converter = JsonConverter {
+            schema: *self,
+            indices,
+        };
+
+        let mut schema = json::Schema::default();
+        for object in objects.iter() {
+            let object = object?;
+            if object.is_struct()? {
+                schema.structs.push(converter.struct_(object)?);
+            } else {
+                schema.tables.push(converter.table(object)?);
+            }
+        }
+        for e in self.enums()?.iter() {
+            schema.enums.push(converter.enum_(e?)?);
+        }
+        if let Some(root) = self.root_table()? {
+            match converter.ty(root_type(&objects, root)?)? {
+                json::Type::Table(index) => schema.root_table = Some(index),
+                _ => {
+                    return Err(json::Error::Invalid(
+                        "the root type is a struct".to_string(),
+                    ))
+                }
+            }
+        }
+        schema.file_identifier = self.file_ident()?.map(str::to_string);
+        Ok(schema)
+    }
+}
+
+/// The index of `root` in `objects`, as an object type.
+#[cfg(feature = "alloc")]
+fn root_type<'a>(objects: &Objects<'a>, root: Object<'a>) -> Result<(BaseType, i32)> {
+    let name = root.name()?;
+    for (i, object) in objects.iter().enumerate() {
+        if object?.name()? == name {
+            return Ok((BaseType::OBJ, i as i32));
+        }
+    }
+    Err(Error::InvalidOffset {
+        loc: root.table.loc,
+    })
+}
+
+impl<'a> Object<'a> {
+    pub const VT_NAME: VOffsetT = 4;
+    pub const VT_FIELDS: VOffsetT = 6;
+    pub const VT_IS_STRUCT: VOffsetT = 8;
+    pub const VT_MINALIGN: VOffsetT = 10;
+    pub const VT_BYTESIZE: VOffsetT = 12;
+
+    /// The fully qualified name of the type.
+    #[inline]
+    pub fn name(&self) -> Result<&'a str> {
+        required_str(&self.table, Self::VT_NAME)
+    }
+
+    /// The fields of the type, sorted by name.
+    #[inline]
+    pub fn fields(&self) -> Result<Fields<'a>> {
+        required::<Vector<_>>(&self.table, Self::VT_FIELDS)
+    }
+
+    #[inline]
+    pub fn is_struct(&self) -> Result<bool> {
+        Ok(self
+            .table
+            .get::<bool>(Self::VT_IS_STRUCT, Some(false))?
+            .unwrap())
+    }
+
+    /// The alignment of a struct in bytes.
+    #[inline]
+    pub fn minalign(&self) -> Result<i32> {
+        Ok(self.table.get::<i32>(Self::VT_MINALIGN, Some(0))?.unwrap())
+    }
+
+    /// The size of a struct in bytes.
+    #[inline]
+    pub fn bytesize(&self) -> Result<i32> {
+        Ok(self.table.get::<i32>(Self::VT_BYTESIZE, Some(0))?.unwrap())
+    }
+
+    /// Look up a field by name.
+    pub fn field_by_name(&self, name: &str) -> Result<Option<Field<'a>>> {
+        lookup_by_name(self.fields()?, name, Field::name)
+    }
+}
+
+impl<'a> Field<'a> {
+    pub const VT_NAME: VOffsetT = 4;
+    pub const VT_TYPE: VOffsetT = 6;
+    pub const VT_ID: VOffsetT = 8;
+    pub const VT_OFFSET: VOffsetT = 10;
+    pub const VT_DEFAULT_INTEGER: VOffsetT = 12;
+    pub const VT_DEFAULT_REAL: VOffsetT = 14;
+    pub const VT_DEPRECATED: VOffsetT = 16;
+    pub const VT_REQUIRED: VOffsetT = 18;
+    pub const VT_KEY: VOffsetT = 20;
+    pub const VT_OPTIONAL: VOffsetT = 26;
+    pub const VT_OFFSET64: VOffsetT = 30;
+
+    #[inline]
+    pub fn name(&self) -> Result<&'a str> {
+        required_str(&self.table, Self::VT_NAME)
+    }
+
+    #[inline]
+    pub fn type_(&self) -> Result<Type<'a>> {
+        required::<Type>(&self.table, Self::VT_TYPE)
+    }
+
+    /// The index of the field in the vtable of a table, or in the declaration
+    /// of a struct.
+    #[inline]
+    pub fn id(&self) -> Result<u16> {
+        Ok(self.table.get::<u16>(Self::VT_ID, Some(0))?.unwrap())
+    }
+
+    /// The offset of the field's slot in the vtable of a table, or of the
+    /// field from the start of a struct.
+    #[inline]
+    pub fn offset(&self) -> Result<u16> {
+        Ok(self.table.get::<u16>(Self::VT_OFFSET, Some(0))?.unwrap())
+    }
+
+    #[inline]
+    pub fn default_integer(&self) -> Result<i64> {
+        Ok(self
+            .table
+            .get::<i64>(Self::VT_DEFAULT_INTEGER, Some(0))?
+            .unwrap())
+    }
+
+    #[inline]
+    pub fn default_real(&self) -> Result<f64> {
+        Ok(self
+            .table
+            .get::<f64>(Self::VT_DEFAULT_REAL, Some(0.0))?
+            .unwrap())
+    }
+
+    #[inline]
+    pub fn deprecated(&self) -> Result<bool> {
+        Ok(self
+            .table
+            .get::<bool>(Self::VT_DEPRECATED, Some(false))?
+            .unwrap())
+    }
+
+    #[inline]
+    pub fn required(&self) -> Result<bool> {
+        Ok(self
+            .table
+            .get::<bool>(Self::VT_REQUIRED, Some(false))?
+            .unwrap())
+    }
+
+    #[inline]
+    pub fn key(&self) -> Result<bool> {
+        Ok(self.table.get::<bool>(Self::VT_KEY, Some(false))?.unwrap())
+    }
+
+    #[inline]
+    pub fn optional(&self) -> Result<bool> {
+        Ok(self
+            .table
+            .get::<bool>(Self::VT_OPTIONAL, Some(false))?
+            .unwrap())
+    }
+
+    /// Whether the string or vector field is referred to by a 64-bit offset.
+    #[inline]
+    pub fn offset64(&self) -> Result<bool> {
+        Ok(self
+            .table
+            .get::<bool>(Self::VT_OFFSET64, Some(false))?
+            .unwrap())
+    }
+}
+
+impl<'a> Type<'a> {
+    pub const VT_BASE_TYPE: VOffsetT = 4;
+    pub const VT_ELEMENT: VOffsetT = 6;
+    pub const VT_INDEX: VOffsetT = 8;
+    pub const VT_FIXED_LENGTH: VOffsetT = 10;
+    pub const VT_BASE_SIZE: VOffsetT = 12;
+    pub const VT_ELEMENT_SIZE: VOffsetT = 14;
+
+    #[inline]
+    pub fn base_type(&self) -> Result<BaseType> {
+        Ok(self
+            .table
+            .get::<BaseType>(Self::VT_BASE_TYPE, Some(BaseType::NONE))?
+            .unwrap())
+    }
+
+    /// The type of the elements of a vector.
+    #[inline]
+    pub fn element(&self) -> Result<BaseType> {
+        Ok(self
+            .table
+            .get::<BaseType>(Self::VT_ELEMENT, Some(BaseType::NONE))?
+            .unwrap())
+    }
+
+    /// The index in [`Schema::objects`] of a table or struct type, or in
+    /// [`Schema::enums`] of an enum or union type, or -1.
+    #[inline]
+    pub fn index(&self) -> Result<i32> {
+        Ok(self.table.get::<i32>(Self::VT_INDEX, Some(-1))?.unwrap())
+    }
+
+    /// The number of elements of a fixed-size array.
+    #[inline]
+    pub fn fixed_length(&self) -> Result<u16> {
+        Ok(self
+            .table
+            .get::<u16>(Self::VT_FIXED_LENGTH, Some(0))?
+            .unwrap())
+    }
+
+    /// The size in bytes of a value of the type stored inline.
+    #[inline]
+    pub fn base_size(&self) -> Result<u32> {
+        Ok(self.table.get::<u32>(Self::VT_BASE_SIZE, Some(4))?.unwrap())
+    }
+
+    /// The size in bytes of an element of a vector stored inline.
+    #[inline]
+    pub fn element_size(&self) -> Result<u32> {
+        Ok(self
+            .table
+            .get::<u32>(Self::VT_ELEMENT_SIZE, Some(0))?
+            .unwrap())
+    }
+}
+
+impl<'a> Enum<'a> {
+    pub const VT_NAME: VOffsetT = 4;
+    pub const VT_VALUES: VOffsetT = 6;
+    pub const VT_IS_UNION: VOffsetT = 8;
+    pub const VT_UNDERLYING_TYPE: VOffsetT = 10;
+
+    /// The fully qualified name of the type.
+    #[inline]
+    pub fn name(&self) -> Result<&'a str> {
+        required_str(&self.table, Self::VT_NAME)
+    }
+
+    /// The values of the type, sorted by value.
+    #[inline]
+    pub fn values(&self) -> Result<EnumVals<'a>> {
+        required::<Vector<_>>(&self.table, Self::VT_VALUES)
+    }
+
+    #[inline]
+    pub fn is_union(&self) -> Result<bool> {
+        Ok(self
+            .table
+            .get::<bool>(Self::VT_IS_UNION, Some(false))?
+            .unwrap())
+    }
+
+    #[inline]
+    pub fn underlying_type(&self) -> Result<Type<'a>> {
+        required::<Type>(&self.table, Self::VT_UNDERLYING_TYPE)
+    }
+}
+
+impl<'a> EnumVal<'a> {
+    pub const VT_NAME: VOffsetT = 4;
+    pub const VT_VALUE: VOffsetT = 6;
+    pub const VT_UNION_TYPE: VOffsetT = 10;
+
+    #[inline]
+    pub fn name(&self) -> Result<&'a str> {
+        required_str(&self.table, Self::VT_NAME)
+    }
+
+    #[inline]
+    pub fn value(&self) -> Result<i64> {
+        Ok(self.table.get::<i64>(Self::VT_VALUE, Some(0))?.unwrap())
+    }
+
+    /// The type of the table stored in a union with this discriminant.
+    #[inline]
+    pub fn union_type(&self) -> Result<Option<Type<'a>>> {
+        self.table
+            .get::<ForwardsUOffset<Type>>(Self::VT_UNION_TYPE, None)
+    }
+}
+
+/// Read the binary schema in `buf`.
+#[inline]
+pub fn get_schema(buf: &[u8]) -> Result<Schema<'_>> {
+    get_root::<Schema>(buf)
+}
+
+/// Read the root table of a buffer whose type is only known at runtime.
+#[inline]
+pub fn get_any_root(buf: &[u8]) -> Result<Table<'_>> {
+    get_root::<Table>(buf)
+}
+
+fn slot(field: &Field) -> Result<VOffsetT> {
+    Ok(field.offset()? as VOffsetT)
+}
+
+/// Read an integer, boolean or enum field of `table`, or the field's default
+/// value if it is absent.
+pub fn get_field_integer(table: &Table, field: &Field) -> Result<i64> {
+    let slot = slot(field)?;
+    let default = field.default_integer()?;
+    let value = match field.type_()?.base_type()? {
+        BaseType::BOOL => table.get::<bool>(slot, None)?.map(i64::from),
+        BaseType::BYTE => table.get::<i8>(slot, None)?.map(i64::from),
+        BaseType::UTYPE | BaseType::UBYTE => table.get::<u8>(slot, None)?.map(i64::from),
+        BaseType::SHORT => table.get::<i16>(slot, None)?.map(i64::from),
+        BaseType::USHORT => table.get::<u16>(slot, None)?.map(i64::from),
+        BaseType::INT => table.get::<i32>(slot, None)?.map(i64::from),
+        BaseType::UINT => table.get::<u32>(slot, None)?.map(i64::from),
+        BaseType::LONG => table.get::<i64>(slot, None)?,
+        BaseType::ULONG => table.get::<u64>(slot, None)?.map(|value| value as i64),
+        _ => return Err(Error::TypeMismatch),
+    };
+    Ok(value.unwrap_or(default))
+}
+
+/// Read a floating point field of `table`, or the field's default value if it
+/// is absent.
+pub fn get_field_float(table: &Table, field: &Field) -> Result<f64> {
+    let slot = slot(field)?;
+    let default = field.default_real()?;
+    let value = match field.type_()?.base_type()? {
+        BaseType::FLOAT => table.get::<f32>(slot, None)?.map(f64::from),
+        BaseType::DOUBLE => table.get::<f64>(slot, None)?,
+        _ => return Err(Error::TypeMismatch),
+    };
+    Ok(value.unwrap_or(default))
+}
+
+/// Read a string field of `table`.
+pub fn get_field_string<'a>(table: &Table<'a>, field: &Field) -> Result<Option<&'a str>> {
+    if field.type_()?.base_type()? != BaseType::STRING {
+        return Err(Error::TypeMismatch);
+    }
+    table.get::<ForwardsUOffset<&str>>(slot(field)?, None)
+}
+
+/// Read a table field of `table`, or the value of a union field.
+///
+/// Struct fields are stored inline rather than behind an offset and can't be
+/// read as tables.
+pub fn get_field_table<'a>(table: &Table<'a>, field: &Field) -> Result<Option<Table<'a>>> {
+    match field.type_()?.base_type()? {
+        BaseType::OBJ | BaseType::UNION => table.get::<ForwardsUOffset<Table>>(slot(field)?, None),
+        _ => Err(Error::TypeMismatch),
+    }
+}
+
+/// Converts the types of a binary schema to the types of a `json::Schema`.
+#[cfg(feature = "alloc")]
+struct JsonConverter<'a> {
+    schema: Schema<'a>,
+    /// The index of every object of the binary schema in the tables or the
+    /// structs of the `json::Schema`.
+    indices: Vec<usize>,
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> JsonConverter<'a> {
+    fn object(&self, index: i32) -> core::result::Result<(Object<'a>, usize), json::Error> {
+        let i = usize::try_from(index)
+            .ok()
+            .filter(|i| *i < self.indices.len())
+            .ok_or_else(|| invalid_index(index))?;
+        Ok((self.schema.objects()?.get(i)?, self.indices[i]))
+    }
+
+    fn enum_index(&self, index: i32) -> core::result::Result<usize, json::Error> {
+        usize::try_from(index)
+            .ok()
+            .filter(|i| *i < self.schema.enums().map_or(0, |enums| enums.len()))
+            .ok_or_else(|| invalid_index(index))
+    }
+
+    /// Convert a base type and the index of the type it refers to.
+    fn ty(
+        &self,
+        (base_type, index): (BaseType, i32),
+    ) -> core::result::Result<json::Type, json::Error> {
+        Ok(match base_type {
+            ty if ty.is_integer() && index >= 0 => json::Type::Enum(self.enum_index(index)?),
+            BaseType::BOOL => json::Type::Bool,
+            BaseType::BYTE => json::Type::Byte,
+            BaseType::UTYPE | BaseType::UBYTE => json::Type::UByte,
+            BaseType::SHORT => json::Type::Short,
+            BaseType::USHORT => json::Type::UShort,
+            BaseType::INT => json::Type::Int,
+            BaseType::UINT => json::Type::UInt,
+            BaseType::LONG => json::Type::Long,
+            BaseType::ULONG => json::Type::ULong,
+            BaseType::FLOAT => json::Type::Float,
+            BaseType::DOUBLE => json::Type::Double,
+            BaseType::STRING => json::Type::String,
+            BaseType::OBJ => match self.object(index)? {
+                (object, index) if object.is_struct()? => json::Type::Struct(index),
+                (_, index) => json::Type::Table(index),
+            },
+            BaseType::UNION => json::Type::Union(self.enum_index(index)?),
+            ty => {
+                return Err(json::Error::Invalid(format!(
+                    "unsupported base type {}",
+                    ty.0
+                )))
+            }
+        })
+    }
+
+    fn field_type(&self, ty: Type) -> core::result::Result<json::Type, json::Error> {
+        let index = ty.index()?;
+        match ty.base_type()? {
+            BaseType::VECTOR => Ok(json::Type::Vector(Box::new(
+                self.ty((ty.element()?, index))?,
+            ))),
+            BaseType::VECTOR64 => Ok(json::Type::Vector64(Box::new(
+                self.ty((ty.element()?, index))?,
+            ))),
+            BaseType::ARRAY => Ok(json::Type::Array(
+                Box::new(self.ty((ty.element()?, index))?),
+                usize::from(ty.fixed_length()?),
+            )),
+            base_type => self.ty((base_type, index)),
+        }
+    }
+
+    fn table(&self, object: Object) -> core::result::Result<json::TableDef, json::Error> {
+        let mut fields = vec![];
+        for field in object.fields()?.iter() {
+            let field = field?;
+            fields.push(json::FieldDef {
+                name: field.name()?.to_string(),
+                ty: self.field_type(field.type_()?)?,
+                id: field.id()? as VOffsetT,
+                default_integer: field.default_integer()?,
+                default_real: field.default_real()?,
+                optional: field.optional()?,
+                required: field.required()?,
+                deprecated: field.deprecated()?,
+                offset64: field.offset64()?,
+            });
+        }
+        // Fields are sorted by name in the binary schema, but JSON lists them
+        // in the order they were declared in.
+        fields.sort_by_key(|field| field.id);
+        Ok(json::TableDef {
+            name: object.name()?.to_string(),
+            fields,
+        })
+    }
+
+    fn struct_(&self, object: Object) -> core::result::Result<json::StructDef, json::Error> {
+        let mut fields = vec![];
+        for field in object.fields()?.iter() {
+            let field = field?;
+            fields.push((
+                field.id()?,
+                json::StructFieldDef {
+                    name: field.name()?.to_string(),
+                    ty: self.field_type(field.type_()?)?,
+                    offset: field.offset()? as usize,
+                },
+            ));
+        }
+        fields.sort_by_key(|(id, _)| *id);
+        Ok(json::StructDef {
+            name: object.name()?.to_string(),
+            fields: fields.into_iter().map(|(_, field)| field).collect(),
+            size: object.bytesize()? as usize,
+            align: object.minalign()? as usize,
+        })
+    }
+
+    fn enum_(&self, e: Enum) -> core::result::Result<json::EnumDef, json::Error> {
+        let mut values = vec![];
+        for value in e.values()?.iter() {
+            let value = value?;
+            let union_table = match value.union_type()? {
+                Some(ty) if ty.base_type()? == BaseType::OBJ => match self.field_type(ty)? {
+                    json::Type::Table(index) => Some(index),
+                    _ => None,
+                },
+                _ => None,
+            };
+            values.push(json::EnumValDef {
+                name: value.name()?.to_string(),
+                value: value.value()?,
+                union_table,
+            });
+        }
+        let underlying_type = e.underlying_type()?;
+        Ok(json::EnumDef {
+            name: e.name()?.to_string(),
+            // The underlying type of an enum refers back to the enum itself.
+            underlying_type: self.ty((underlying_type.base_type()?, -1))?,
+            values,
+        })
+    }
+}
+
+#[cfg(feature = "alloc")]
+fn invalid_index(index: i32) -> json::Error {
+    json::Error::Invalid(format!("invalid type index {}", index))
+}
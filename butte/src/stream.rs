@@ -0,0 +1,291 @@
+//! Framing for streams of size-prefixed buffers, like those sent over a
+//! network connection.
+//!
+//! Each frame is a buffer finished with
+//! `FlatBufferBuilder::finish_size_prefixed`: a little-endian `u32` holding
+//! the length of the rest of the frame, followed by the buffer. [`Codec`]
+//! splits frames off the bytes read from a stream and appends frames to the
+//! bytes to be written to it, without doing any I/O itself, so it can be
+//! driven by blocking or asynchronous I/O alike. With the `std` feature,
+//! [`Codec::read_frame`] and [`Codec::write_frame`] do the I/O on a
+//! `std::io::Read` or `std::io::Write`. With the `tokio` feature, `Codec`
+//! implements `tokio_util::codec::{Decoder, Encoder}`, so that
+//! `tokio_util::codec::Framed` sends and receives frames over an
+//! asynchronous stream.
+//!
+//! ```
+//! use butte::stream::Codec;
+//!
+//! let mut builder = butte::FlatBufferBuilder::new();
+//! let greeting = builder.create_string("hello");
+//! builder.finish_size_prefixed(greeting, None);
+//!
+//! let mut codec = Codec::new();
+//! let mut bytes = vec![];
+//! codec.encode(builder.finished_data(), &mut bytes).unwrap();
+//! codec.encode(builder.finished_data(), &mut bytes).unwrap();
+//! let frame = codec.decode(&mut bytes).unwrap().unwrap();
+//! assert_eq!(frame, builder.finished_data());
+//! assert_eq!(bytes.len(), frame.len());
+//! ```
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{
+    endian_scalar::EndianScalar,
+    error,
+    primitives::{UOffsetT, SIZE_UOFFSET},
+    table::TableMarker,
+    verifier::{verified_size_prefixed_root_with_options, VerifierOptions, Verify},
+};
+
+/// The largest frame a `Codec` accepts by default, including its size
+/// prefix.
+pub const DEFAULT_MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+/// Errors that can occur while encoding or decoding frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameError {
+    /// A frame of `len` bytes is longer than the codec's maximum, `max`.
+    TooLong { len: usize, max: usize },
+    /// The size prefix of a frame to encode, `prefix`, doesn't hold the
+    /// length of the rest of the frame, `len`.
+    WrongSizePrefix { prefix: usize, len: usize },
+    /// A decoded frame failed verification.
+    Invalid(error::Error),
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::TooLong { len, max } => write!(
+                f,
+                "a frame of {} bytes is longer than the maximum of {} bytes",
+                len, max
+            ),
+            FrameError::WrongSizePrefix { prefix, len } => write!(
+                f,
+                "the size prefix {} doesn't match the frame's length {}",
+                prefix, len
+            ),
+            FrameError::Invalid(error) => write!(f, "invalid frame: {}", error),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FrameError {}
+
+/// Checks a decoded frame.
+type VerifyFrame = fn(&[u8], VerifierOptions) -> error::Result<()>;
+
+/// Splits a stream of bytes into size-prefixed frames, and joins frames into
+/// one, see the [module documentation](self).
+#[derive(Debug, Clone, Copy)]
+pub struct Codec {
+    max_frame_len: usize,
+    verify: Option<(VerifyFrame, VerifierOptions)>,
+}
+
+impl Default for Codec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Codec {
+    /// A codec accepting frames of up to `DEFAULT_MAX_FRAME_LEN` bytes,
+    /// which doesn't verify them.
+    pub fn new() -> Self {
+        Codec {
+            max_frame_len: DEFAULT_MAX_FRAME_LEN,
+            verify: None,
+        }
+    }
+
+    /// Accept frames of up to `max_frame_len` bytes, including their size
+    /// prefix. A longer frame is rejected as soon as its size prefix is read,
+    /// before buffering any of it.
+    pub fn max_frame_len(mut self, max_frame_len: usize) -> Self {
+        self.max_frame_len = max_frame_len;
+        self
+    }
+
+    /// Verify every decoded frame as a buffer whose root is the table marked
+    /// by `T`, such as `MonsterOffset`, with `options`. A frame that fails is
+    /// returned as `FrameError::Invalid`, which leaves the codec positioned
+    /// at the next frame.
+    pub fn verified<T>(mut self, options: VerifierOptions) -> Self
+    where
+        T: TableMarker,
+        for<'a> T::Table<'a>: Verify,
+    {
+        self.verify = Some((verify_frame::<T>, options));
+        self
+    }
+
+    /// Split the first frame off `src`, the bytes read from a stream so far,
+    /// or return `None` if `src` doesn't hold all of it yet.
+    pub fn decode(&mut self, src: &mut Vec<u8>) -> Result<Option<Vec<u8>>, FrameError> {
+        if src.len() < SIZE_UOFFSET {
+            return Ok(None);
+        }
+        let len = self.frame_len(UOffsetT::read_le(&src[..SIZE_UOFFSET]))?;
+        if src.len() < len {
+            src.reserve(len - src.len());
+            return Ok(None);
+        }
+        // Leave the rest in `src`, with its capacity, rather than moving it
+        // to a new allocation for every frame.
+        let frame = src.drain(..len).collect::<Vec<_>>();
+        self.check(&frame)?;
+        Ok(Some(frame))
+    }
+
+    /// Append `frame`, a buffer finished with `finish_size_prefixed`, to
+    /// `dst`, the bytes to be written to a stream.
+    pub fn encode(&mut self, frame: &[u8], dst: &mut Vec<u8>) -> Result<(), FrameError> {
+        self.check_prefix(frame)?;
+        dst.extend_from_slice(frame);
+        Ok(())
+    }
+
+    /// The length of a frame, including its size prefix `prefix`, checked
+    /// against the maximum.
+    fn frame_len(&self, prefix: UOffsetT) -> Result<usize, FrameError> {
+        let len = (prefix as usize).saturating_add(SIZE_UOFFSET);
+        if len > self.max_frame_len {
+            return Err(FrameError::TooLong {
+                len,
+                max: self.max_frame_len,
+            });
+        }
+        Ok(len)
+    }
+
+    /// Check that `frame` is a size-prefixed buffer that isn't too long.
+    fn check_prefix(&self, frame: &[u8]) -> Result<(), FrameError> {
+        let len = frame.len().saturating_sub(SIZE_UOFFSET);
+        let prefix = match frame.get(..SIZE_UOFFSET) {
+            Some(prefix) => UOffsetT::read_le(prefix) as usize,
+            None => return Err(FrameError::WrongSizePrefix { prefix: 0, len }),
+        };
+        if prefix != len {
+            return Err(FrameError::WrongSizePrefix { prefix, len });
+        }
+        if frame.len() > self.max_frame_len {
+            return Err(FrameError::TooLong {
+                len: frame.len(),
+                max: self.max_frame_len,
+            });
+        }
+        Ok(())
+    }
+
+    /// Verify a decoded frame, if the codec does.
+    fn check(&self, frame: &[u8]) -> Result<(), FrameError> {
+        match self.verify {
+            Some((verify, options)) => verify(frame, options).map_err(FrameError::Invalid),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Codec {
+    /// Read the next frame from `reader`, or `None` if the stream ends before
+    /// it starts. A stream that ends within a frame fails with
+    /// `io::ErrorKind::UnexpectedEof`, and errors of the codec are returned as
+    /// `io::ErrorKind::InvalidData`.
+    pub fn read_frame(
+        &mut self,
+        reader: &mut impl std::io::Read,
+    ) -> std::io::Result<Option<Vec<u8>>> {
+        use std::io::ErrorKind;
+
+        let mut prefix = [0; SIZE_UOFFSET];
+        let mut read = 0;
+        while read < SIZE_UOFFSET {
+            match reader.read(&mut prefix[read..]) {
+                Ok(0) if read == 0 => return Ok(None),
+                Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(n) => read += n,
+                Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+        let len = self
+            .frame_len(UOffsetT::read_le(&prefix))
+            .map_err(invalid_data)?;
+        let mut frame = vec![0; len];
+        frame[..SIZE_UOFFSET].copy_from_slice(&prefix);
+        reader.read_exact(&mut frame[SIZE_UOFFSET..])?;
+        self.check(&frame).map_err(invalid_data)?;
+        Ok(Some(frame))
+    }
+
+    /// Write `frame`, a buffer finished with `finish_size_prefixed`, to
+    /// `writer`. Errors of the codec are returned as
+    /// `io::ErrorKind::InvalidData`.
+    pub fn write_frame(
+        &mut self,
+        frame: &[u8],
+        writer: &mut impl std::io::Write,
+    ) -> std::io::Result<()> {
+        self.check_prefix(frame).map_err(invalid_data)?;
+        writer.write_all(frame)
+    }
+}
+
+/// An error of the codec, as an I/O error.
+#[cfg(feature = "std")]
+fn invalid_data(error: FrameError) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, error)
+}
+
+/// Splits frames off the bytes read from an asynchronous stream, like
+/// `Codec::decode`. Errors of the codec are returned as
+/// `io::ErrorKind::InvalidData`.
+#[cfg(feature = "tokio")]
+impl tokio_util::codec::Decoder for Codec {
+    type Item = bytes::BytesMut;
+    type Error = std::io::Error;
+
+    fn decode(&mut self, src: &mut bytes::BytesMut) -> std::io::Result<Option<Self::Item>> {
+        if src.len() < SIZE_UOFFSET {
+            return Ok(None);
+        }
+        let len = self
+            .frame_len(UOffsetT::read_le(&src[..SIZE_UOFFSET]))
+            .map_err(invalid_data)?;
+        if src.len() < len {
+            src.reserve(len - src.len());
+            return Ok(None);
+        }
+        let frame = src.split_to(len);
+        self.check(&frame).map_err(invalid_data)?;
+        Ok(Some(frame))
+    }
+}
+
+/// Appends frames to the bytes to be written to an asynchronous stream, like
+/// `Codec::encode`. Errors of the codec are returned as
+/// `io::ErrorKind::InvalidData`.
+#[cfg(feature = "tokio")]
+impl tokio_util::codec::Encoder<&[u8]> for Codec {
+    type Error = std::io::Error;
+
+    fn encode(&mut self, frame: &[u8], dst: &mut bytes::BytesMut) -> std::io::Result<()> {
+        self.check_prefix(frame).map_err(invalid_data)?;
+        dst.extend_from_slice(frame);
+        Ok(())
+    }
+}
+
+fn verify_frame<T>(frame: &[u8], options: VerifierOptions) -> error::Result<()>
+where
+    T: TableMarker,
+    for<'a> T::Table<'a>: Verify,
+{
+    verified_size_prefixed_root_with_options::<T::Table<'_>>(frame, options).map(|_| ())
+}
@@ -0,0 +1,107 @@
+//! Strings stored in a buffer.
+//!
+//! A string is a length-prefixed vector of bytes followed by a zero byte.
+//! Reading it as `&str` checks that the bytes are valid UTF-8, which a buffer
+//! written by another implementation doesn't guarantee. [`FbString`] reads
+//! the bytes without that check, so that strings that aren't valid UTF-8 can
+//! still be read.
+
+#[cfg(feature = "alloc")]
+use alloc::{borrow::Cow, string::String};
+use core::{
+    hash::{Hash, Hasher},
+    ops::Range,
+    str::from_utf8,
+};
+
+use crate::{
+    error::{Error, Result},
+    follow::Follow,
+    primitives::SIZE_UOFFSET,
+    vector::length_prefixed_bytes,
+};
+
+/// A string in a buffer, whose bytes have not been checked to be valid UTF-8.
+#[derive(Debug, Clone, Copy)]
+pub struct FbString<'a> {
+    bytes: &'a [u8],
+    /// The location of the string's length prefix.
+    loc: usize,
+}
+
+impl<'a> FbString<'a> {
+    /// The bytes of the string, without its terminating zero byte.
+    #[inline]
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// The string, or `Error::InvalidUtf8` if it isn't valid UTF-8.
+    #[inline]
+    pub fn to_str(&self) -> Result<&'a str> {
+        from_utf8(self.bytes).map_err(|_| Error::InvalidUtf8 { loc: self.loc })
+    }
+
+    /// The string, with invalid UTF-8 replaced by `U+FFFD REPLACEMENT
+    /// CHARACTER`.
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub fn to_string_lossy(&self) -> Cow<'a, str> {
+        String::from_utf8_lossy(self.bytes)
+    }
+
+    /// The length of the string in bytes.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// The range of the buffer holding the bytes of the string, after its
+    /// length prefix and without its terminating zero byte.
+    #[inline]
+    pub fn byte_range(&self) -> Range<usize> {
+        let start = self.loc + SIZE_UOFFSET;
+        start..start + self.bytes.len()
+    }
+}
+
+/// Strings are equal if their bytes are, no matter where they are.
+impl PartialEq for FbString<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.bytes == other.bytes
+    }
+}
+
+impl Eq for FbString<'_> {}
+
+impl Hash for FbString<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.bytes.hash(state);
+    }
+}
+
+/// Following a string checks that its length prefix and bytes lie within the
+/// buffer, but not that the bytes are valid UTF-8.
+impl<'a> Follow<'a> for FbString<'a> {
+    type Inner = FbString<'a>;
+
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Result<Self::Inner> {
+        length_prefixed_bytes(buf, loc, 1).map(|bytes| FbString { bytes, loc })
+    }
+}
+
+/// Following a `&str` checks that the string is valid UTF-8.
+impl<'a> Follow<'a> for &'a str {
+    type Inner = &'a str;
+
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Result<Self::Inner> {
+        FbString::follow(buf, loc)?.to_str()
+    }
+}
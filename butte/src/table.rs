@@ -14,7 +14,14 @@
  * limitations under the License.
  */
 
-use crate::{follow::Follow, primitives::*, vtable::VTable};
+use core::{mem::size_of, ops::Range};
+
+use crate::{
+    error::{Error, Result},
+    follow::Follow,
+    primitives::*,
+    vtable::{field_index_to_field_offset, VTable},
+};
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Table<'a> {
@@ -28,48 +35,199 @@ impl<'a> Table<'a> {
         Table { buf, loc }
     }
     #[inline]
-    pub fn vtable(&self) -> VTable<'a> {
-        <BackwardsSOffset<VTable<'a>>>::follow(self.buf, self.loc)
+    pub fn vtable(&self) -> Result<VTable<'a>> {
+        VTable::init(self.buf, self.loc)
     }
     #[inline]
     pub fn get<T: Follow<'a> + 'a>(
         &self,
         slot_byte_loc: VOffsetT,
         default: Option<T::Inner>,
-    ) -> Option<T::Inner> {
-        let o = self.vtable().get(slot_byte_loc) as usize;
+    ) -> Result<Option<T::Inner>> {
+        match self.field_loc::<T>(slot_byte_loc)? {
+            Some(loc) => <T>::follow(self.buf, loc).map(Some),
+            None => Ok(default),
+        }
+    }
+
+    /// The range of the buffer holding the table's inline data: the offset of
+    /// its vtable, followed by the fields stored inline. The data that its
+    /// fields refer to, like strings and vectors, lies outside of it.
+    #[inline]
+    pub fn inline_range(&self) -> Result<Range<usize>> {
+        let size = self.vtable()?.object_inline_num_bytes();
+        Ok(self.loc..self.loc + size)
+    }
+
+    /// The table's inline data, see `inline_range`.
+    #[inline]
+    pub fn inline_bytes(&self) -> Result<&'a [u8]> {
+        Ok(&self.buf[self.inline_range()?])
+    }
+
+    /// The range of the buffer holding the inline value of the field in the
+    /// vtable slot `slot_byte_loc`, a `T`, or `None` if the field isn't
+    /// present.
+    #[inline]
+    pub fn field_range<T>(&self, slot_byte_loc: VOffsetT) -> Result<Option<Range<usize>>> {
+        Ok(self
+            .field_loc::<T>(slot_byte_loc)?
+            .map(|loc| loc..loc + size_of::<T>()))
+    }
+
+    /// Whether the field in the vtable slot `slot_byte_loc` is present, which
+    /// it isn't if it was left out for its default value.
+    #[inline]
+    pub fn is_present(&self, slot_byte_loc: VOffsetT) -> Result<bool> {
+        Ok(self.vtable()?.get(slot_byte_loc) != 0)
+    }
+
+    /// The location of the field in the vtable slot `slot_byte_loc`, whose
+    /// inline value is a `T`, or `None` if the field isn't present.
+    ///
+    /// The field must lie within the table's inline data, as given by its
+    /// vtable, so that reading it can't reach into the data of other objects.
+    #[inline]
+    pub(crate) fn field_loc<T>(&self, slot_byte_loc: VOffsetT) -> Result<Option<usize>> {
+        self.field_loc_sized(slot_byte_loc, size_of::<T>())
+    }
+
+    /// The location of the field in the vtable slot `slot_byte_loc`, whose
+    /// inline value is `size` bytes long, like `field_loc`.
+    #[inline]
+    pub(crate) fn field_loc_sized(
+        &self,
+        slot_byte_loc: VOffsetT,
+        size: usize,
+    ) -> Result<Option<usize>> {
+        let vtable = self.vtable()?;
+        // The offsets in a vtable are unsigned, though `VOffsetT` isn't.
+        let o = vtable.get(slot_byte_loc) as u16 as usize;
         if o == 0 {
-            return default;
+            return Ok(None);
+        }
+        match o.checked_add(size) {
+            Some(end) if end <= vtable.object_inline_num_bytes() => Ok(Some(self.loc + o)),
+            _ => Err(Error::InvalidFieldOffset {
+                loc: self.loc,
+                slot: slot_byte_loc,
+            }),
+        }
+    }
+
+    /// The fields of the table that are present but whose ids are
+    /// `known_fields` or greater, which its reader doesn't know. These are
+    /// the fields written by a newer version of the table's schema, which a
+    /// proxy may want to log or pass on. Generated readers call this with
+    /// the number of fields they know.
+    ///
+    /// Fails with `Error::InvalidFieldOffset` if the value of one of them lies
+    /// outside of the table's inline data.
+    pub fn unknown_fields(&self, known_fields: usize) -> Result<UnknownFields<'a>> {
+        let vtable = self.vtable()?;
+        let inline_size = vtable.object_inline_num_bytes();
+        for id in known_fields..vtable.num_fields() {
+            if vtable.get_field(id) as u16 as usize >= inline_size {
+                return Err(Error::InvalidFieldOffset {
+                    loc: self.loc,
+                    slot: field_index_to_field_offset(id as VOffsetT),
+                });
+            }
         }
-        Some(<T>::follow(self.buf, self.loc + o))
+        Ok(UnknownFields {
+            table: *self,
+            vtable,
+            next: known_fields,
+        })
     }
 }
 
+/// A field of a table that its reader doesn't know, see
+/// `Table::unknown_fields`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownField<'a> {
+    /// The field's id, which is the index of its slot in the vtable.
+    pub id: VOffsetT,
+    /// The offset of the field's value from the start of the table.
+    pub offset: VOffsetT,
+    /// The table's inline data from the field's value up to the next field
+    /// or the end of the inline data. The size of the value isn't known
+    /// without its type, so this is the value followed by any padding.
+    ///
+    /// A value that refers to other data, like a string, is an offset
+    /// relative to where it is stored, so its bytes only make sense in the
+    /// buffer they were read from.
+    pub bytes: &'a [u8],
+}
+
+/// An iterator over the unknown fields of a table, in the order of their
+/// ids, see `Table::unknown_fields`.
+#[derive(Debug, Clone)]
+pub struct UnknownFields<'a> {
+    table: Table<'a>,
+    vtable: VTable<'a>,
+    next: usize,
+}
+
+impl<'a> Iterator for UnknownFields<'a> {
+    type Item = UnknownField<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let num_fields = self.vtable.num_fields();
+        while self.next < num_fields {
+            let id = self.next;
+            self.next += 1;
+            let offset = self.vtable.get_field(id);
+            if offset == 0 {
+                continue;
+            }
+            let inline_size = self.vtable.object_inline_num_bytes();
+            let end = (0..num_fields)
+                .map(|id| self.vtable.get_field(id) as u16 as usize)
+                .filter(|&other| other > offset as u16 as usize && other <= inline_size)
+                .min()
+                .unwrap_or(inline_size);
+            let start = self.table.loc + offset as u16 as usize;
+            return Some(UnknownField {
+                id: id as VOffsetT,
+                offset,
+                bytes: &self.table.buf[start..self.table.loc + end],
+            });
+        }
+        None
+    }
+}
+
+/// Associates the marker type generated for a table, such as `MonsterOffset`,
+/// with the table's reader type.
+pub trait TableMarker {
+    type Table<'a>: Follow<'a, Inner = Self::Table<'a>> + 'a;
+}
+
 impl<'a> Follow<'a> for Table<'a> {
     type Inner = Table<'a>;
     #[inline]
-    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
-        Table { buf, loc }
+    fn follow(buf: &'a [u8], loc: usize) -> Result<Self::Inner> {
+        Ok(Table { buf, loc })
     }
 }
 
 #[inline]
-pub fn get_root<'a, T: Follow<'a> + 'a>(data: &'a [u8]) -> T::Inner {
+pub fn get_root<'a, T: Follow<'a> + 'a>(data: &'a [u8]) -> Result<T::Inner> {
     <ForwardsUOffset<T>>::follow(data, 0)
 }
 #[inline]
-pub fn get_size_prefixed_root<'a, T: Follow<'a> + 'a>(data: &'a [u8]) -> T::Inner {
+pub fn get_size_prefixed_root<'a, T: Follow<'a> + 'a>(data: &'a [u8]) -> Result<T::Inner> {
     <SkipSizePrefix<ForwardsUOffset<T>>>::follow(data, 0)
 }
 #[inline]
-pub fn buffer_has_identifier(data: &[u8], ident: &str, size_prefixed: bool) -> bool {
-    assert_eq!(ident.len(), FILE_IDENTIFIER_LENGTH);
-
+pub fn buffer_has_identifier(data: &[u8], ident: FileIdent, size_prefixed: bool) -> bool {
     let got = if size_prefixed {
         <SkipSizePrefix<SkipRootOffset<FileIdentifier>>>::follow(data, 0)
     } else {
         <SkipRootOffset<FileIdentifier>>::follow(data, 0)
     };
 
-    ident.as_bytes() == got
+    // a buffer too short to hold an identifier does not have one
+    got == Ok(&ident.as_bytes()[..])
 }
@@ -0,0 +1,188 @@
+use core::{
+    fmt,
+    iter::{ExactSizeIterator, FusedIterator},
+};
+
+use crate::{
+    error::{Error, Result},
+    follow::Follow,
+    primitives::ForwardsUOffset,
+    table::Table,
+    vector::{Element, Vector, VectorIter},
+    verifier::{Verifier, Verify},
+};
+
+/// The reader of a union: an enum with a variant holding the table of each of
+/// the union's types. Generated code implements it for every union.
+pub trait UnionReader<'a>: Sized {
+    /// The enum of the union's types, whose values are stored next to the
+    /// union's values.
+    type Type: Follow<'a, Inner = Self::Type> + Verify + 'a;
+
+    /// Read the value of type `ty` stored in `table`.
+    fn from_table(ty: Self::Type, table: Table<'a>) -> Self;
+
+    /// Verify the table of type `ty` at `loc` in the buffer of `verifier`.
+    fn verify(ty: Self::Type, verifier: &mut Verifier<'_>, loc: usize) -> Result<()>;
+}
+
+/// A vector of unions, stored as a vector of their types along with a vector
+/// of their values of the same length.
+pub struct UnionVector<'a, U: UnionReader<'a>> {
+    types: Vector<'a, U::Type>,
+    values: Vector<'a, ForwardsUOffset<Table<'a>>>,
+}
+
+impl<'a, U: UnionReader<'a>> Copy for UnionVector<'a, U> {}
+impl<'a, U: UnionReader<'a>> Clone for UnionVector<'a, U> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, U: UnionReader<'a>> UnionVector<'a, U> {
+    /// Pair the vectors holding the types and the values of a vector of
+    /// unions, which fails if they don't have the same length.
+    pub fn new(
+        types: Vector<'a, U::Type>,
+        values: Vector<'a, ForwardsUOffset<Table<'a>>>,
+    ) -> Result<Self> {
+        if types.len() != values.len() {
+            return Err(Error::UnionVectorMismatch);
+        }
+        Ok(Self { types, values })
+    }
+
+    /// Pair the vectors of a vector of unions read from a table, where either
+    /// both or none of them must be present.
+    pub fn from_parts(
+        types: Option<Vector<'a, U::Type>>,
+        values: Option<Vector<'a, ForwardsUOffset<Table<'a>>>>,
+    ) -> Result<Option<Self>> {
+        match (types, values) {
+            (Some(types), Some(values)) => Self::new(types, values).map(Some),
+            (None, None) => Ok(None),
+            _ => Err(Error::UnionVectorMismatch),
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The types of the unions.
+    #[inline]
+    pub fn types(&self) -> Vector<'a, U::Type> {
+        self.types
+    }
+
+    pub fn get(&self, idx: usize) -> Result<U> {
+        let ty = self.types.get(idx)?;
+        Ok(U::from_table(ty, self.values.get(idx)?))
+    }
+
+    #[inline]
+    pub fn iter(&self) -> UnionVectorIter<'a, U> {
+        UnionVectorIter {
+            types: self.types.iter(),
+            values: self.values.iter(),
+        }
+    }
+}
+
+/// Vectors of unions are printed as a list of their values. Values that can't
+/// be read are printed as the error.
+impl<'a, U: UnionReader<'a> + fmt::Debug> fmt::Debug for UnionVector<'a, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter().map(Element)).finish()
+    }
+}
+
+/// Vectors of unions are equal if their values are, no matter which buffers
+/// they are in.
+impl<'a, U: UnionReader<'a> + PartialEq> PartialEq for UnionVector<'a, U> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<'a, U: UnionReader<'a>> IntoIterator for UnionVector<'a, U> {
+    type Item = Result<U>;
+    type IntoIter = UnionVectorIter<'a, U>;
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Vectors of unions are serialized as sequences of their values.
+#[cfg(feature = "serde")]
+impl<'a, U: UnionReader<'a> + serde::Serialize> serde::Serialize for UnionVector<'a, U> {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::{Error as _, SerializeSeq as _};
+
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for item in self.iter() {
+            seq.serialize_element(&item.map_err(S::Error::custom)?)?;
+        }
+        seq.end()
+    }
+}
+
+/// An iterator over the values of a vector of unions.
+pub struct UnionVectorIter<'a, U: UnionReader<'a>> {
+    types: VectorIter<'a, U::Type>,
+    values: VectorIter<'a, ForwardsUOffset<Table<'a>>>,
+}
+
+impl<'a, U: UnionReader<'a>> Clone for UnionVectorIter<'a, U> {
+    fn clone(&self) -> Self {
+        Self {
+            types: self.types.clone(),
+            values: self.values.clone(),
+        }
+    }
+}
+
+impl<'a, U: UnionReader<'a>> Iterator for UnionVectorIter<'a, U> {
+    type Item = Result<U>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let ty = self.types.next()?;
+        let value = self.values.next()?;
+        Some(ty.and_then(|ty| Ok(U::from_table(ty, value?))))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.types.size_hint()
+    }
+}
+
+impl<'a, U: UnionReader<'a>> DoubleEndedIterator for UnionVectorIter<'a, U> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let ty = self.types.next_back()?;
+        let value = self.values.next_back()?;
+        Some(ty.and_then(|ty| Ok(U::from_table(ty, value?))))
+    }
+}
+
+impl<'a, U: UnionReader<'a>> ExactSizeIterator for UnionVectorIter<'a, U> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.types.len()
+    }
+}
+
+impl<'a, U: UnionReader<'a>> FusedIterator for UnionVectorIter<'a, U> {}
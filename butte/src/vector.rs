@@ -14,19 +14,27 @@
  * limitations under the License.
  */
 
-use std::{
+use core::{
+    convert::TryFrom,
+    fmt,
+    hash::{Hash, Hasher},
     iter::{DoubleEndedIterator, ExactSizeIterator, FusedIterator},
     marker::PhantomData,
-    mem::size_of,
+    mem::{align_of, size_of},
+    ops::Range,
     slice::from_raw_parts,
-    str::from_utf8_unchecked,
 };
 
-#[cfg(target_endian = "little")]
-use crate::endian_scalar::EndianScalar;
-use crate::{endian_scalar::read_scalar_at, follow::Follow, primitives::*};
+#[cfg(feature = "alloc")]
+use alloc::{borrow::Cow, vec::Vec};
+
+use crate::{
+    endian_scalar::{read_scalar_at, EndianScalar},
+    error::{Error, Result},
+    follow::{checked_slice, Follow},
+    primitives::*,
+};
 
-#[derive(Debug)]
 pub struct Vector<'a, T: 'a>(&'a [u8], usize, PhantomData<T>);
 
 // We cannot use derive for these two impls, as it would only implement Copy
@@ -42,30 +50,37 @@ impl<'a, T> Clone for Vector<'a, T> {
 impl<'a, T: 'a> Vector<'a, T> {
     #[inline(always)]
     pub fn new(buf: &'a [u8], loc: usize) -> Self {
-        Vector {
-            0: buf,
-            1: loc,
-            2: PhantomData,
-        }
+        Vector(buf, loc, PhantomData)
     }
 
     #[inline(always)]
     pub fn len(&self) -> usize {
-        read_scalar_at::<UOffsetT>(&self.0, self.1) as usize
+        UOffsetT::read_le(&self.0[self.1..]) as usize
     }
     #[inline(always)]
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    /// The range of the buffer holding the elements, after the length
+    /// prefix.
+    #[inline]
+    pub fn byte_range(&self) -> Range<usize> {
+        let start = self.1 + SIZE_UOFFSET;
+        start..start + self.len() * size_of::<T>()
+    }
 }
 
 impl<'a, T: Follow<'a> + 'a> Vector<'a, T> {
     #[inline(always)]
-    pub fn get(&self, idx: usize) -> T::Inner {
-        debug_assert!(idx < read_scalar_at::<u32>(&self.0, self.1) as usize);
+    pub fn get(&self, idx: usize) -> Result<T::Inner> {
         let sz = size_of::<T>();
         debug_assert!(sz > 0);
-        T::follow(self.0, self.1 as usize + SIZE_UOFFSET + sz * idx)
+        if idx >= self.len() {
+            let loc = (self.1 + SIZE_UOFFSET).saturating_add(sz.saturating_mul(idx));
+            return Err(Error::out_of_bounds(self.0, loc, sz));
+        }
+        T::follow(self.0, self.1 + SIZE_UOFFSET + sz * idx)
     }
 
     #[inline(always)]
@@ -74,24 +89,69 @@ impl<'a, T: Follow<'a> + 'a> Vector<'a, T> {
     }
 }
 
+/// Vectors are printed as a list of their decoded elements. Elements that
+/// can't be read are printed as the error.
+impl<'a, T: Follow<'a> + 'a> fmt::Debug for Vector<'a, T>
+where
+    T::Inner: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter().map(Element)).finish()
+    }
+}
+
+/// An element of a vector that is printed as its value if it could be read
+/// and as the error otherwise.
+pub(crate) struct Element<T>(pub(crate) Result<T>);
+
+impl<T: fmt::Debug> fmt::Debug for Element<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Ok(value) => value.fmt(f),
+            Err(error) => error.fmt(f),
+        }
+    }
+}
+
+/// Vectors are equal if their decoded elements are, no matter which buffers
+/// they are in.
+impl<'a, T: Follow<'a> + 'a> PartialEq for Vector<'a, T>
+where
+    T::Inner: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<'a, T: Follow<'a> + 'a> Eq for Vector<'a, T> where T::Inner: Eq {}
+
+impl<'a, T: Follow<'a> + 'a> Hash for Vector<'a, T>
+where
+    T::Inner: Hash,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len());
+        for element in self.iter() {
+            element.hash(state);
+        }
+    }
+}
+
+/// Types whose values can be read from the bytes of a buffer in place, as
+/// they are stored in memory as they are in a buffer, and any bytes are a
+/// valid value. That's `u8` and `i8`, the other scalars but `bool` on
+/// little-endian hosts, and generated structs, which store their fields as
+/// little-endian bytes.
 pub trait SafeSliceAccess {}
 impl<'a, T: SafeSliceAccess + 'a> Vector<'a, T> {
-    pub fn safe_slice(self) -> &'a [T] {
-        let buf = self.0;
-        let loc = self.1;
-        let sz = size_of::<T>();
-        debug_assert!(sz > 0);
-        let len = read_scalar_at::<UOffsetT>(&buf, loc) as usize;
-        let data_buf = &buf[loc + SIZE_UOFFSET..loc + SIZE_UOFFSET + len * sz];
-        let ptr = data_buf.as_ptr() as *const T;
-        let s: &'a [T] = unsafe { from_raw_parts(ptr, len) };
-        s
+    pub fn safe_slice(self) -> Result<&'a [T]> {
+        slice_helper::<T>(self.0, self.1)
     }
 }
 
 impl SafeSliceAccess for u8 {}
 impl SafeSliceAccess for i8 {}
-impl SafeSliceAccess for bool {}
 
 #[cfg(target_endian = "little")]
 mod le_safe_slice_impls {
@@ -107,50 +167,129 @@ mod le_safe_slice_impls {
     impl super::SafeSliceAccess for f64 {}
 }
 
-#[cfg(target_endian = "little")]
-pub use self::le_safe_slice_impls::*;
+/// Scalars whose vectors can be read with `to_slice`. That's all of them but
+/// `bool`, since a byte other than 0 or 1 isn't a valid `bool`.
+pub trait ScalarSliceAccess: EndianScalar {}
+
+impl ScalarSliceAccess for u8 {}
+impl ScalarSliceAccess for u16 {}
+impl ScalarSliceAccess for u32 {}
+impl ScalarSliceAccess for u64 {}
+impl ScalarSliceAccess for i8 {}
+impl ScalarSliceAccess for i16 {}
+impl ScalarSliceAccess for i32 {}
+impl ScalarSliceAccess for i64 {}
+impl ScalarSliceAccess for f32 {}
+impl ScalarSliceAccess for f64 {}
+
+#[cfg(feature = "alloc")]
+impl<'a, T: ScalarSliceAccess + Follow<'a, Inner = T> + 'a> Vector<'a, T> {
+    /// The elements of a vector of scalars as a slice.
+    ///
+    /// On little-endian hosts the slice is borrowed from the buffer, unless
+    /// the elements aren't aligned. Otherwise they are read one by one.
+    pub fn to_slice(self) -> Result<Cow<'a, [T]>> {
+        #[cfg(target_endian = "little")]
+        match slice_helper::<T>(self.0, self.1) {
+            Err(Error::Unaligned { .. }) => {}
+            result => return result.map(Cow::Borrowed),
+        }
+        self.iter().collect::<Result<Vec<_>>>().map(Cow::Owned)
+    }
+}
 
-pub fn follow_cast_ref<'a, T: Sized + 'a>(buf: &'a [u8], loc: usize) -> &'a T {
+pub fn follow_cast_ref<'a, T: Sized + 'a>(buf: &'a [u8], loc: usize) -> Result<&'a T> {
     let sz = size_of::<T>();
-    let buf = &buf[loc..loc + sz];
+    let buf = checked_slice(buf, loc, sz)?;
     let ptr = buf.as_ptr() as *const T;
-    unsafe { &*ptr }
+    if !(ptr as usize).is_multiple_of(align_of::<T>()) {
+        return Err(Error::Unaligned {
+            loc,
+            align: align_of::<T>(),
+        });
+    }
+    Ok(unsafe { &*ptr })
 }
 
-impl<'a> Follow<'a> for &'a str {
-    type Inner = &'a str;
-    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
-        let len = read_scalar_at::<UOffsetT>(&buf, loc) as usize;
-        let slice = &buf[loc + SIZE_UOFFSET..loc + SIZE_UOFFSET + len];
-        unsafe { from_utf8_unchecked(slice) }
-    }
+/// Copy the `T` at `loc` out of `buf`. Unlike [`follow_cast_ref`], this
+/// doesn't require `T` to be aligned in `buf`.
+///
+/// `T` is a scalar or a struct that can be read from any bytes, as marked by
+/// [`SafeSliceAccess`]. The fields of a struct are kept in little endian and
+/// converted by its accessors, so its copy reads the same on any host.
+pub fn follow_cast_copy<T: SafeSliceAccess + Copy>(buf: &[u8], loc: usize) -> Result<T> {
+    let buf = checked_slice(buf, loc, size_of::<T>())?;
+    Ok(unsafe { core::ptr::read_unaligned(buf.as_ptr() as *const T) })
 }
 
-#[cfg(target_endian = "little")]
-fn follow_slice_helper<T>(buf: &[u8], loc: usize) -> &[T] {
+/// Return the data of the length-prefixed vector at `loc`, whose elements are
+/// `elem_size` bytes wide.
+#[inline]
+pub(crate) fn length_prefixed_bytes(buf: &[u8], loc: usize, elem_size: usize) -> Result<&[u8]> {
+    let len = read_scalar_at::<UOffsetT>(buf, loc)? as usize;
+    let num_bytes = len
+        .checked_mul(elem_size)
+        .ok_or_else(|| Error::out_of_bounds(buf, loc + SIZE_UOFFSET, usize::MAX))?;
+    checked_slice(buf, loc + SIZE_UOFFSET, num_bytes)
+}
+
+/// Return the data of the vector at `loc` with a 64-bit length prefix, whose
+/// elements are `elem_size` bytes wide.
+#[inline]
+pub(crate) fn length64_prefixed_bytes(buf: &[u8], loc: usize, elem_size: usize) -> Result<&[u8]> {
+    let len = read_scalar_at::<UOffset64T>(buf, loc)?;
+    let num_bytes = usize::try_from(len)
+        .ok()
+        .and_then(|len| len.checked_mul(elem_size))
+        .ok_or_else(|| Error::out_of_bounds(buf, loc + SIZE_UOFFSET64, usize::MAX))?;
+    checked_slice(buf, loc + SIZE_UOFFSET64, num_bytes)
+}
+
+fn slice_helper<T>(buf: &[u8], loc: usize) -> Result<&[T]> {
     let sz = size_of::<T>();
     debug_assert!(sz > 0);
-    let len = read_scalar_at::<UOffsetT>(&buf, loc) as usize;
-    let data_buf = &buf[loc + SIZE_UOFFSET..loc + SIZE_UOFFSET + len * sz];
+    cast_slice(length_prefixed_bytes(buf, loc, sz)?, loc + SIZE_UOFFSET)
+}
+
+/// Reinterpret the bytes of a vector's elements, which start at `loc`, as a
+/// slice of them.
+fn cast_slice<T>(data_buf: &[u8], loc: usize) -> Result<&[T]> {
+    // an empty vector may be shared by vectors of any type, so its data
+    // needn't be aligned
+    if data_buf.is_empty() {
+        return Ok(&[]);
+    }
+    let sz = size_of::<T>();
     let ptr = data_buf.as_ptr() as *const T;
-    let s: &[T] = unsafe { from_raw_parts(ptr, len) };
-    s
+    if !(ptr as usize).is_multiple_of(align_of::<T>()) {
+        return Err(Error::Unaligned {
+            loc,
+            align: align_of::<T>(),
+        });
+    }
+    let s: &[T] = unsafe { from_raw_parts(ptr, data_buf.len() / sz) };
+    Ok(s)
 }
 
-/// Implement direct slice access if the host is little-endian.
+/// Implement direct slice access if the host is little-endian. Not for
+/// `bool`, whose slice could hold bytes that aren't a valid `bool`.
 #[cfg(target_endian = "little")]
-impl<'a, T: EndianScalar> Follow<'a> for &'a [T] {
+impl<'a, T: ScalarSliceAccess> Follow<'a> for &'a [T] {
     type Inner = &'a [T];
-    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
-        follow_slice_helper::<T>(buf, loc)
+    fn follow(buf: &'a [u8], loc: usize) -> Result<Self::Inner> {
+        slice_helper::<T>(buf, loc)
     }
 }
 
 /// Implement Follow for all possible Vectors that have Follow-able elements.
+///
+/// Following a vector checks that all of its elements lie within the buffer,
+/// which the vector's accessors rely on.
 impl<'a, T: Follow<'a> + 'a> Follow<'a> for Vector<'a, T> {
     type Inner = Vector<'a, T>;
-    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
-        Vector::new(buf, loc)
+    fn follow(buf: &'a [u8], loc: usize) -> Result<Self::Inner> {
+        length_prefixed_bytes(buf, loc, size_of::<T>())?;
+        Ok(Vector::new(buf, loc))
     }
 }
 
@@ -190,10 +329,10 @@ impl<'a, T: Follow<'a> + 'a> Clone for VectorIter<'a, T> {
 }
 
 impl<'a, T: Follow<'a> + 'a> Iterator for VectorIter<'a, T> {
-    type Item = T::Inner;
+    type Item = Result<T::Inner>;
 
     #[inline]
-    fn next(&mut self) -> Option<T::Inner> {
+    fn next(&mut self) -> Option<Self::Item> {
         let sz = size_of::<T>();
         debug_assert!(sz > 0);
 
@@ -208,7 +347,7 @@ impl<'a, T: Follow<'a> + 'a> Iterator for VectorIter<'a, T> {
     }
 
     #[inline]
-    fn nth(&mut self, n: usize) -> Option<T::Inner> {
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
         let sz = size_of::<T>();
         debug_assert!(sz > 0);
 
@@ -229,7 +368,7 @@ impl<'a, T: Follow<'a> + 'a> Iterator for VectorIter<'a, T> {
 
 impl<'a, T: Follow<'a> + 'a> DoubleEndedIterator for VectorIter<'a, T> {
     #[inline]
-    fn next_back(&mut self) -> Option<T::Inner> {
+    fn next_back(&mut self) -> Option<Self::Item> {
         let sz = size_of::<T>();
         debug_assert!(sz > 0);
 
@@ -242,7 +381,7 @@ impl<'a, T: Follow<'a> + 'a> DoubleEndedIterator for VectorIter<'a, T> {
     }
 
     #[inline]
-    fn nth_back(&mut self, n: usize) -> Option<T::Inner> {
+    fn nth_back(&mut self, n: usize) -> Option<Self::Item> {
         self.remaining = self.remaining.saturating_sub(n);
         self.next_back()
     }
@@ -258,7 +397,7 @@ impl<'a, T: 'a + Follow<'a>> ExactSizeIterator for VectorIter<'a, T> {
 impl<'a, T: 'a + Follow<'a>> FusedIterator for VectorIter<'a, T> {}
 
 impl<'a, T: Follow<'a> + 'a> IntoIterator for Vector<'a, T> {
-    type Item = T::Inner;
+    type Item = Result<T::Inner>;
     type IntoIter = VectorIter<'a, T>;
     #[inline]
     fn into_iter(self) -> Self::IntoIter {
@@ -266,10 +405,197 @@ impl<'a, T: Follow<'a> + 'a> IntoIterator for Vector<'a, T> {
     }
 }
 
-impl<'a, 'b, T: Follow<'a> + 'a> IntoIterator for &'b Vector<'a, T> {
-    type Item = T::Inner;
+impl<'a, T: Follow<'a> + 'a> IntoIterator for &Vector<'a, T> {
+    type Item = Result<T::Inner>;
     type IntoIter = VectorIter<'a, T>;
     fn into_iter(self) -> Self::IntoIter {
         self.iter()
     }
 }
+
+/// Vectors are serialized as sequences, reading each element as it is
+/// serialized.
+#[cfg(feature = "serde")]
+impl<'a, T: Follow<'a> + 'a> serde::Serialize for Vector<'a, T>
+where
+    T::Inner: serde::Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::{Error as _, SerializeSeq as _};
+
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for item in self.iter() {
+            seq.serialize_element(&item.map_err(S::Error::custom)?)?;
+        }
+        seq.end()
+    }
+}
+
+/// A vector with a 64-bit length, as stored for fields with the `vector64`
+/// attribute. Its elements are read like those of a `Vector`.
+pub struct Vector64<'a, T: 'a>(&'a [u8], usize, PhantomData<T>);
+
+impl<'a, T> Copy for Vector64<'a, T> {}
+impl<'a, T> Clone for Vector64<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T: 'a> Vector64<'a, T> {
+    #[inline(always)]
+    pub fn new(buf: &'a [u8], loc: usize) -> Self {
+        Vector64(buf, loc, PhantomData)
+    }
+
+    /// The number of elements. Following the vector checked that they lie
+    /// within the buffer, so it fits in a `usize`.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        UOffset64T::read_le(&self.0[self.1..]) as usize
+    }
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The range of the buffer holding the elements, after the length
+    /// prefix.
+    #[inline]
+    pub fn byte_range(&self) -> Range<usize> {
+        let start = self.1 + SIZE_UOFFSET64;
+        start..start + self.len() * size_of::<T>()
+    }
+}
+
+impl<'a, T: Follow<'a> + 'a> Vector64<'a, T> {
+    #[inline(always)]
+    pub fn get(&self, idx: usize) -> Result<T::Inner> {
+        let sz = size_of::<T>();
+        debug_assert!(sz > 0);
+        if idx >= self.len() {
+            let loc = (self.1 + SIZE_UOFFSET64).saturating_add(sz.saturating_mul(idx));
+            return Err(Error::out_of_bounds(self.0, loc, sz));
+        }
+        T::follow(self.0, self.1 + SIZE_UOFFSET64 + sz * idx)
+    }
+
+    #[inline(always)]
+    pub fn iter(&self) -> VectorIter<'a, T> {
+        VectorIter {
+            buf: self.0,
+            loc: self.1 + SIZE_UOFFSET64,
+            remaining: self.len(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, T: SafeSliceAccess + 'a> Vector64<'a, T> {
+    pub fn safe_slice(self) -> Result<&'a [T]> {
+        let sz = size_of::<T>();
+        debug_assert!(sz > 0);
+        cast_slice(
+            length64_prefixed_bytes(self.0, self.1, sz)?,
+            self.1 + SIZE_UOFFSET64,
+        )
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, T: ScalarSliceAccess + Follow<'a, Inner = T> + 'a> Vector64<'a, T> {
+    /// The elements of a vector of scalars as a slice, like
+    /// `Vector::to_slice`.
+    pub fn to_slice(self) -> Result<Cow<'a, [T]>> {
+        #[cfg(target_endian = "little")]
+        match cast_slice::<T>(
+            length64_prefixed_bytes(self.0, self.1, size_of::<T>())?,
+            self.1 + SIZE_UOFFSET64,
+        ) {
+            Err(Error::Unaligned { .. }) => {}
+            result => return result.map(Cow::Borrowed),
+        }
+        self.iter().collect::<Result<Vec<_>>>().map(Cow::Owned)
+    }
+}
+
+impl<'a, T: Follow<'a> + 'a> fmt::Debug for Vector64<'a, T>
+where
+    T::Inner: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter().map(Element)).finish()
+    }
+}
+
+impl<'a, T: Follow<'a> + 'a> PartialEq for Vector64<'a, T>
+where
+    T::Inner: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<'a, T: Follow<'a> + 'a> Eq for Vector64<'a, T> where T::Inner: Eq {}
+
+impl<'a, T: Follow<'a> + 'a> Hash for Vector64<'a, T>
+where
+    T::Inner: Hash,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len());
+        for element in self.iter() {
+            element.hash(state);
+        }
+    }
+}
+
+/// Following a vector checks that all of its elements lie within the buffer,
+/// which the vector's accessors rely on.
+impl<'a, T: Follow<'a> + 'a> Follow<'a> for Vector64<'a, T> {
+    type Inner = Vector64<'a, T>;
+    fn follow(buf: &'a [u8], loc: usize) -> Result<Self::Inner> {
+        length64_prefixed_bytes(buf, loc, size_of::<T>())?;
+        Ok(Vector64::new(buf, loc))
+    }
+}
+
+impl<'a, T: Follow<'a> + 'a> IntoIterator for Vector64<'a, T> {
+    type Item = Result<T::Inner>;
+    type IntoIter = VectorIter<'a, T>;
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T: Follow<'a> + 'a> IntoIterator for &Vector64<'a, T> {
+    type Item = Result<T::Inner>;
+    type IntoIter = VectorIter<'a, T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'a, T: Follow<'a> + 'a> serde::Serialize for Vector64<'a, T>
+where
+    T::Inner: serde::Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::{Error as _, SerializeSeq as _};
+
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for item in self.iter() {
+            seq.serialize_element(&item.map_err(S::Error::custom)?)?;
+        }
+        seq.end()
+    }
+}
@@ -0,0 +1,408 @@
+use core::{convert::TryFrom, mem::size_of};
+
+use crate::{
+    endian_scalar::EndianScalar,
+    error::{Error, Result},
+    follow::{checked_slice, Follow},
+    primitives::*,
+    string::FbString,
+    table::{get_root, Table},
+    union::{UnionReader, UnionVector},
+    vector::{length64_prefixed_bytes, length_prefixed_bytes, Vector, Vector64},
+};
+
+/// Types whose data in a buffer can be checked up front, before it is read.
+///
+/// Verifying a value checks that it, and everything it refers to through
+/// offsets, lies within the buffer, so that reading it afterwards doesn't
+/// fail because the buffer is malformed. The values themselves, e.g., enum
+/// values and the UTF-8 of strings, are still checked when they are read.
+/// Generated code implements it for every table, struct and enum.
+pub trait Verify {
+    /// Verify the value at `loc` in the buffer of `verifier`.
+    fn verify(verifier: &mut Verifier<'_>, loc: usize) -> Result<()>;
+}
+
+/// Limits on the work verifying a buffer may do, so that a malicious buffer
+/// can neither overflow the stack with deeply nested tables nor make
+/// verification take long by referring to the same data over and over.
+///
+/// By default, verification is lenient about the values in a buffer: strings
+/// and enums are checked as they are read, and bools are read like other
+/// languages read them. [`VerifierOptions::strict`] checks all of them up
+/// front instead, so that reading a verified buffer can't fail.
+///
+/// The options only apply to verification, not to reading: accessors check
+/// the UTF-8 of strings and the values of enums as they read them whichever
+/// options the buffer was verified with, and read any byte but 0 as a `true`
+/// bool, so a lenient reader of a buffer a strict verifier would reject gets
+/// an error from the accessor of the bad value rather than from verification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VerifierOptions {
+    /// How deeply tables may be nested, counting the root table as the first
+    /// level.
+    pub max_depth: usize,
+    /// How many tables may be verified.
+    pub max_tables: usize,
+    /// How many bytes may be verified, counting data that several offsets
+    /// refer to once for each of them. A buffer bigger than
+    /// `FLATBUFFERS_MAX_BUFFER_SIZE`, which only 64-bit offsets can make, may
+    /// have its own size on top of this.
+    pub max_apparent_size: usize,
+    /// Whether a `bool` must be stored as 0 or 1. Readers take any byte but
+    /// 0 as `true`, like those of other languages, so a buffer from a lenient
+    /// writer is only rejected if this is set, with `Error::InvalidBool`.
+    pub strict_bools: bool,
+    /// Whether strings must be valid UTF-8, which fails with
+    /// `Error::InvalidUtf8` rather than when a string is read.
+    pub strict_utf8: bool,
+    /// Whether enums must hold values declared in the schema, and bit flags
+    /// only flags declared in it, which fails with `Error::UnknownEnumValue`
+    /// rather than when an enum is read.
+    pub strict_enums: bool,
+}
+
+impl Default for VerifierOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: 64,
+            max_tables: 1_000_000,
+            max_apparent_size: 1 << 31,
+            strict_bools: false,
+            strict_utf8: false,
+            strict_enums: false,
+        }
+    }
+}
+
+impl VerifierOptions {
+    /// The apparent size verifying `buf` may reach.
+    pub(crate) fn max_apparent_size_of(&self, buf: &[u8]) -> usize {
+        if buf.len() > FLATBUFFERS_MAX_BUFFER_SIZE {
+            self.max_apparent_size.saturating_add(buf.len())
+        } else {
+            self.max_apparent_size
+        }
+    }
+
+    /// The default limits, checking the values of bools, strings and enums.
+    pub fn strict() -> Self {
+        Self {
+            strict_bools: true,
+            strict_utf8: true,
+            strict_enums: true,
+            ..Self::default()
+        }
+    }
+}
+
+/// The state of verifying a buffer: the buffer, the limits on verifying it,
+/// and how close verification has come to them.
+#[derive(Debug, Clone)]
+pub struct Verifier<'a> {
+    buf: &'a [u8],
+    options: VerifierOptions,
+    depth: usize,
+    num_tables: usize,
+    apparent_size: usize,
+    max_apparent_size: usize,
+}
+
+impl<'a> Verifier<'a> {
+    pub fn new(buf: &'a [u8], options: VerifierOptions) -> Self {
+        Self {
+            buf,
+            options,
+            depth: 0,
+            num_tables: 0,
+            apparent_size: 0,
+            max_apparent_size: options.max_apparent_size_of(buf),
+        }
+    }
+
+    /// The buffer being verified.
+    #[inline]
+    pub fn buf(&self) -> &'a [u8] {
+        self.buf
+    }
+
+    /// The options the buffer is verified with.
+    #[inline]
+    pub fn options(&self) -> &VerifierOptions {
+        &self.options
+    }
+
+    /// Check that the `len` bytes at `loc` lie within the buffer, and count
+    /// them towards its apparent size.
+    #[inline]
+    pub fn range(&mut self, loc: usize, len: usize) -> Result<&'a [u8]> {
+        let bytes = checked_slice(self.buf, loc, len)?;
+        self.apparent_size = self.apparent_size.saturating_add(len);
+        if self.apparent_size > self.max_apparent_size {
+            return Err(Error::ApparentSizeTooLarge);
+        }
+        Ok(bytes)
+    }
+
+    /// Check the bools at `offsets` from `loc`, whose bytes have been checked
+    /// to lie within the buffer, if the options ask for `strict_bools`.
+    pub fn bools(&self, loc: usize, offsets: &[usize]) -> Result<()> {
+        if !self.options.strict_bools {
+            return Ok(());
+        }
+        match offsets
+            .iter()
+            .map(|offset| loc + offset)
+            .find(|&loc| self.buf[loc] > 1)
+        {
+            Some(loc) => Err(Error::InvalidBool { loc }),
+            None => Ok(()),
+        }
+    }
+
+    /// Check the vtable and the inline data of the table at `loc`, see
+    /// `VTable::init`.
+    pub fn table(&mut self, loc: usize) -> Result<Table<'a>> {
+        let table = Table::new(self.buf, loc);
+        let inline_size = table.vtable()?.object_inline_num_bytes();
+        self.range(loc, inline_size)?;
+        Ok(table)
+    }
+
+    /// Start verifying the fields of the table at `loc`, one level deeper
+    /// than the table whose field it is. Once they are verified,
+    /// [`Verifier::exit_table`] goes back up a level.
+    pub fn enter_table(&mut self, loc: usize) -> Result<Table<'a>> {
+        self.depth += 1;
+        if self.depth > self.options.max_depth {
+            return Err(Error::TooManyLevels);
+        }
+        self.num_tables += 1;
+        if self.num_tables > self.options.max_tables {
+            return Err(Error::TooManyTables);
+        }
+        self.table(loc)
+    }
+
+    /// Finish verifying the fields of a table.
+    #[inline]
+    pub fn exit_table(&mut self) {
+        self.depth -= 1;
+    }
+}
+
+macro_rules! impl_verify_for_scalar {
+    ($ty:ident) => {
+        impl Verify for $ty {
+            #[inline]
+            fn verify(verifier: &mut Verifier<'_>, loc: usize) -> Result<()> {
+                verifier.range(loc, size_of::<$ty>()).map(|_| ())
+            }
+        }
+    };
+}
+
+impl_verify_for_scalar!(u8);
+impl_verify_for_scalar!(u16);
+impl_verify_for_scalar!(u32);
+impl_verify_for_scalar!(u64);
+impl_verify_for_scalar!(i8);
+impl_verify_for_scalar!(i16);
+impl_verify_for_scalar!(i32);
+impl_verify_for_scalar!(i64);
+impl_verify_for_scalar!(f32);
+impl_verify_for_scalar!(f64);
+
+impl Verify for bool {
+    #[inline]
+    fn verify(verifier: &mut Verifier<'_>, loc: usize) -> Result<()> {
+        verifier.range(loc, 1)?;
+        verifier.bools(loc, &[0])
+    }
+}
+
+impl Verify for FbString<'_> {
+    #[inline]
+    fn verify(verifier: &mut Verifier<'_>, loc: usize) -> Result<()> {
+        let string = FbString::follow(verifier.buf(), loc)?;
+        // the length prefix, the bytes and the terminating zero byte
+        verifier.range(loc, SIZE_UOFFSET + string.len() + 1)?;
+        if verifier.options.strict_utf8 {
+            string.to_str()?;
+        }
+        Ok(())
+    }
+}
+
+impl Verify for &str {
+    #[inline]
+    fn verify(verifier: &mut Verifier<'_>, loc: usize) -> Result<()> {
+        FbString::verify(verifier, loc)
+    }
+}
+
+/// Offsets must point past themselves, which also keeps a malformed buffer
+/// from making verification go around in circles.
+impl<T: Verify> Verify for ForwardsUOffset<T> {
+    #[inline]
+    fn verify(verifier: &mut Verifier<'_>, loc: usize) -> Result<()> {
+        let off = UOffsetT::read_le(verifier.range(loc, SIZE_UOFFSET)?) as usize;
+        if off == 0 {
+            return Err(Error::InvalidOffset { loc });
+        }
+        T::verify(
+            verifier,
+            loc.checked_add(off).ok_or(Error::InvalidOffset { loc })?,
+        )
+    }
+}
+
+impl<T: Verify> Verify for ForwardsUOffset64<T> {
+    #[inline]
+    fn verify(verifier: &mut Verifier<'_>, loc: usize) -> Result<()> {
+        let off = UOffset64T::read_le(verifier.range(loc, SIZE_UOFFSET64)?);
+        let off = usize::try_from(off).map_err(|_| Error::InvalidOffset { loc })?;
+        if off == 0 {
+            return Err(Error::InvalidOffset { loc });
+        }
+        T::verify(
+            verifier,
+            loc.checked_add(off).ok_or(Error::InvalidOffset { loc })?,
+        )
+    }
+}
+
+impl<T: Verify> Verify for Vector<'_, T> {
+    fn verify(verifier: &mut Verifier<'_>, loc: usize) -> Result<()> {
+        let sz = size_of::<T>();
+        let data = length_prefixed_bytes(verifier.buf(), loc, sz)?;
+        verifier.range(loc, SIZE_UOFFSET)?;
+        verify_elements::<T>(verifier, loc + SIZE_UOFFSET, data.len() / sz)
+    }
+}
+
+impl<T: Verify> Verify for Vector64<'_, T> {
+    fn verify(verifier: &mut Verifier<'_>, loc: usize) -> Result<()> {
+        let sz = size_of::<T>();
+        let data = length64_prefixed_bytes(verifier.buf(), loc, sz)?;
+        verifier.range(loc, SIZE_UOFFSET64)?;
+        verify_elements::<T>(verifier, loc + SIZE_UOFFSET64, data.len() / sz)
+    }
+}
+
+/// Verify the `len` elements of a vector, starting at `loc`.
+fn verify_elements<T: Verify>(verifier: &mut Verifier<'_>, loc: usize, len: usize) -> Result<()> {
+    let sz = size_of::<T>();
+    (0..len).try_for_each(|idx| T::verify(verifier, loc + sz * idx))
+}
+
+/// Verifying a table checks its vtable and its inline data, see
+/// `VTable::init`, but not its fields, which only the table's generated type
+/// knows.
+impl Verify for Table<'_> {
+    fn verify(verifier: &mut Verifier<'_>, loc: usize) -> Result<()> {
+        verifier.table(loc).map(|_| ())
+    }
+}
+
+impl<'a> Table<'a> {
+    /// Verify the field in the vtable slot `slot_byte_loc`, whose type is
+    /// `T`, which fails if it is `required` but missing.
+    pub fn verify_field<T: Verify>(
+        &self,
+        verifier: &mut Verifier<'_>,
+        slot_byte_loc: VOffsetT,
+        required: bool,
+    ) -> Result<()> {
+        match self.field_loc::<T>(slot_byte_loc)? {
+            Some(loc) => T::verify(verifier, loc),
+            None if required => Err(Error::MissingRequiredField {
+                slot: slot_byte_loc,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Verify the union `U` whose type and value are in the vtable slots
+    /// `type_slot` and `value_slot`.
+    pub fn verify_union<U: UnionReader<'a>>(
+        &self,
+        verifier: &mut Verifier<'_>,
+        type_slot: VOffsetT,
+        value_slot: VOffsetT,
+        required: bool,
+    ) -> Result<()> {
+        self.verify_field::<U::Type>(verifier, type_slot, false)?;
+        self.verify_field::<ForwardsUOffset<Table<'a>>>(verifier, value_slot, required)?;
+        let ty = self.get::<U::Type>(type_slot, None)?;
+        let value = self.get::<ForwardsUOffset<Table<'a>>>(value_slot, None)?;
+        match (ty, value) {
+            (Some(ty), Some(value)) => U::verify(ty, verifier, value.loc),
+            _ => Ok(()),
+        }
+    }
+
+    /// Verify the vector of unions `U` whose types and values are in the
+    /// vtable slots `type_slot` and `value_slot`.
+    pub fn verify_union_vector<U: UnionReader<'a>>(
+        &self,
+        verifier: &mut Verifier<'_>,
+        type_slot: VOffsetT,
+        value_slot: VOffsetT,
+        required: bool,
+    ) -> Result<()> {
+        self.verify_field::<ForwardsUOffset<Vector<'a, U::Type>>>(verifier, type_slot, required)?;
+        // the values of `NONE` are zero offsets, so each value is verified
+        // along with its type
+        self.verify_field::<ForwardsUOffset<Vector<'a, UOffsetT>>>(verifier, value_slot, required)?;
+        let types = self.get::<ForwardsUOffset<Vector<'a, U::Type>>>(type_slot, None)?;
+        let values =
+            self.get::<ForwardsUOffset<Vector<'a, ForwardsUOffset<Table<'a>>>>>(value_slot, None)?;
+        UnionVector::<U>::from_parts(types, values)?;
+        if let (Some(types), Some(values)) = (types, values) {
+            for (ty, value) in types.iter().zip(values.iter()) {
+                let value = value?;
+                U::verify(ty?, verifier, value.loc)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Verify the buffer `data`, whose root is a `T`, and then read the root.
+#[inline]
+pub fn verified_root<'a, T: Follow<'a> + Verify + 'a>(data: &'a [u8]) -> Result<T::Inner> {
+    verified_root_with_options::<T>(data, VerifierOptions::default())
+}
+
+/// Verify the buffer `data`, whose root is a `T`, within the limits of
+/// `options`, and then read the root.
+#[inline]
+pub fn verified_root_with_options<'a, T: Follow<'a> + Verify + 'a>(
+    data: &'a [u8],
+    options: VerifierOptions,
+) -> Result<T::Inner> {
+    <ForwardsUOffset<T>>::verify(&mut Verifier::new(data, options), 0)?;
+    get_root::<T>(data)
+}
+
+/// Verify the size-prefixed buffer `data`, whose root is a `T`, and then read
+/// the root.
+#[inline]
+pub fn verified_size_prefixed_root<'a, T: Follow<'a> + Verify + 'a>(
+    data: &'a [u8],
+) -> Result<T::Inner> {
+    verified_size_prefixed_root_with_options::<T>(data, VerifierOptions::default())
+}
+
+/// Verify the size-prefixed buffer `data`, whose root is a `T`, within the
+/// limits of `options`, and then read the root.
+#[inline]
+pub fn verified_size_prefixed_root_with_options<'a, T: Follow<'a> + Verify + 'a>(
+    data: &'a [u8],
+    options: VerifierOptions,
+) -> Result<T::Inner> {
+    let (data, loc) = skip_size_prefix(data, 0)?;
+    <ForwardsUOffset<T>>::verify(&mut Verifier::new(data, options), loc)?;
+    <ForwardsUOffset<T>>::follow(data, loc)
+}
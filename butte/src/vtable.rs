@@ -14,56 +14,106 @@
  * limitations under the License.
  */
 
-use crate::{endian_scalar::read_scalar_at, follow::Follow, primitives::*};
+use core::convert::TryFrom;
+
+use crate::{
+    endian_scalar::{read_scalar_at, EndianScalar},
+    error::{Error, Result},
+    follow::{checked_slice, Follow},
+    primitives::*,
+};
 
 /// VTable encapsulates read-only usage of a vtable. It is only to be used
 /// by generated code.
-#[derive(Debug)]
+///
+/// The sizes and offsets in a vtable are unsigned, though `VOffsetT` isn't,
+/// so they are read as `u16`.
+///
+/// It only holds the bytes of the vtable, as many as its size field gives, so
+/// reading a slot can't reach past the end of the vtable. A slot outside of
+/// it reads as 0, like a slot of a field that isn't present.
+#[derive(Debug, Clone, PartialEq)]
 pub struct VTable<'a> {
-    buf: &'a [u8],
-    loc: usize,
+    bytes: &'a [u8],
 }
 
-impl<'a> PartialEq for VTable<'a> {
-    fn eq(&self, other: &VTable) -> bool {
-        self.as_bytes().eq(other.as_bytes())
+impl<'a> VTable<'a> {
+    /// The vtable of the table at `table_loc`.
+    ///
+    /// This checks that the table's offset to its vtable points into the
+    /// buffer, that the vtable's size covers its two size fields and lies
+    /// within the buffer, and that the table's inline size covers the offset
+    /// to its vtable and lies within the buffer.
+    pub fn init(buf: &'a [u8], table_loc: usize) -> Result<Self> {
+        let offset = read_scalar_at::<SOffsetT>(buf, table_loc)?;
+        let loc = (table_loc as i64)
+            .checked_sub(i64::from(offset))
+            .and_then(|loc| usize::try_from(loc).ok())
+            .filter(|&loc| checked_slice(buf, loc, VTABLE_METADATA_FIELDS * SIZE_VOFFSET).is_ok())
+            .ok_or(Error::InvalidVTableOffset { loc: table_loc })?;
+        let vtable = Self::at(buf, loc)?;
+        let inline_size = vtable.object_inline_num_bytes();
+        if inline_size < SIZE_SOFFSET || checked_slice(buf, table_loc, inline_size).is_err() {
+            return Err(Error::InvalidTableSize { loc: table_loc });
+        }
+        Ok(vtable)
     }
-}
 
-impl<'a> VTable<'a> {
-    pub fn init(buf: &'a [u8], loc: usize) -> Self {
-        VTable { buf, loc }
+    /// The vtable at `loc`, checking its size.
+    fn at(buf: &'a [u8], loc: usize) -> Result<Self> {
+        let num_bytes = read_scalar_at::<u16>(buf, loc)? as usize;
+        if num_bytes < VTABLE_METADATA_FIELDS * SIZE_VOFFSET
+            || !num_bytes.is_multiple_of(SIZE_VOFFSET)
+        {
+            return Err(Error::InvalidVTableSize { loc });
+        }
+        let bytes =
+            checked_slice(buf, loc, num_bytes).map_err(|_| Error::InvalidVTableSize { loc })?;
+        Ok(VTable { bytes })
+    }
+
+    /// The vtable at `loc` of a buffer under construction, which is known to
+    /// be well-formed.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn new_unchecked(buf: &'a [u8], loc: usize) -> Self {
+        let num_bytes = u16::read_le(&buf[loc..]) as usize;
+        VTable {
+            bytes: &buf[loc..loc + num_bytes],
+        }
+    }
+
+    /// The offset in the slot at `byte_loc`, or 0 if the slot is outside the
+    /// vtable.
+    #[inline]
+    fn slot(&self, byte_loc: usize) -> VOffsetT {
+        byte_loc
+            .checked_add(SIZE_VOFFSET)
+            .and_then(|end| self.bytes.get(byte_loc..end))
+            .map_or(0, VOffsetT::read_le)
     }
+
     pub fn num_fields(&self) -> usize {
-        (self.num_bytes() / SIZE_VOFFSET) - 2
+        (self.num_bytes() / SIZE_VOFFSET).saturating_sub(VTABLE_METADATA_FIELDS)
     }
     pub fn num_bytes(&self) -> usize {
-        read_scalar_at::<VOffsetT>(self.buf, self.loc) as usize
+        self.bytes.len()
     }
     pub fn object_inline_num_bytes(&self) -> usize {
-        let n = read_scalar_at::<VOffsetT>(self.buf, self.loc + SIZE_VOFFSET);
-        n as usize
+        self.slot(SIZE_VOFFSET) as u16 as usize
     }
+    /// The offset of the field with index `idx`, or 0 if it isn't present.
     pub fn get_field(&self, idx: usize) -> VOffsetT {
-        // TODO(rw): distinguish between None and 0?
-        if idx > self.num_fields() {
+        if idx >= self.num_fields() {
             return 0;
         }
-        read_scalar_at::<VOffsetT>(
-            self.buf,
-            self.loc + SIZE_VOFFSET + SIZE_VOFFSET + SIZE_VOFFSET * idx,
-        )
+        self.slot((VTABLE_METADATA_FIELDS + idx) * SIZE_VOFFSET)
     }
+    /// The offset in the slot at `byte_loc`, or 0 if the field isn't present.
     pub fn get(&self, byte_loc: VOffsetT) -> VOffsetT {
-        // TODO(rw): distinguish between None and 0?
-        if byte_loc as usize >= self.num_bytes() {
-            return 0;
-        }
-        read_scalar_at::<VOffsetT>(self.buf, self.loc + byte_loc as usize)
+        self.slot(byte_loc as u16 as usize)
     }
-    pub fn as_bytes(&self) -> &[u8] {
-        let len = self.num_bytes();
-        &self.buf[self.loc..self.loc + len]
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
     }
 }
 
@@ -81,9 +131,11 @@ pub fn field_offset_to_field_index(field_o: VOffsetT) -> VOffsetT {
     (field_o / (SIZE_VOFFSET as VOffsetT)) - fixed_fields
 }
 
+/// Following a vtable only checks its size, see `VTable::init` for the
+/// checks that need the table it belongs to.
 impl<'a> Follow<'a> for VTable<'a> {
     type Inner = VTable<'a>;
-    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
-        VTable::init(buf, loc)
+    fn follow(buf: &'a [u8], loc: usize) -> Result<Self::Inner> {
+        VTable::at(buf, loc)
     }
 }
@@ -14,10 +14,10 @@
  * limitations under the License.
  */
 
-use std::ptr::write_bytes;
+use core::ptr::write_bytes;
 
 use crate::{
-    endian_scalar::{emplace_scalar, read_scalar_at},
+    endian_scalar::{emplace_scalar, EndianScalar},
     primitives::*,
 };
 
@@ -59,7 +59,7 @@ impl<'a> VTableWriter<'a> {
     #[inline(always)]
     pub fn get_field_offset(&self, vtable_offset: VOffsetT) -> VOffsetT {
         let idx = vtable_offset as usize;
-        read_scalar_at::<VOffsetT>(&self.buf, idx)
+        VOffsetT::read_le(&self.buf[idx..])
     }
 
     /// Writes an object field offset into the vtable.
@@ -78,7 +78,7 @@ impl<'a> VTableWriter<'a> {
     pub fn clear(&mut self) {
         // This is the closest thing to memset in Rust right now.
         let len = self.buf.len();
-        let p = self.buf.as_mut_ptr() as *mut u8;
+        let p = self.buf.as_mut_ptr();
         unsafe {
             write_bytes(p, 0, len);
         }